@@ -51,6 +51,7 @@ class ErrorEvent extends Event {
   static const int statusUnknown = 0;
   static const int statusForbidden = 1;
   static const int statusEventLoss = 2;
+  static const int statusResync = 3;
 
   ErrorEvent(super.seq, this._status, this._message);
 
@@ -204,6 +204,8 @@ public class ErrorEvent : Event {
     Forbidden = 1,
     /// Server-side event loss. Independently events can get lost between TrailBase and the client.
     Loss = 2,
+    /// Missed events could not be replayed when resuming. Clients need to re-fetch.
+    Resync = 3,
   }
 
   /// <summary>Get associated record value as JSON object.</summary>
@@ -7,10 +7,15 @@ use std::sync::atomic::{AtomicI64, Ordering};
 use trailbase_wasm::db::{Transaction, Value, execute, query};
 use trailbase_wasm::fetch::{Uri, get};
 use trailbase_wasm::fs::read_file;
+use trailbase_wasm::hook::{Event, RecordHook, RecordHookResult};
 use trailbase_wasm::http::{HttpError, HttpRoute, Json, StatusCode, routing};
 use trailbase_wasm::job::Job;
+use trailbase_wasm::sqlite::{Error as SqliteError, Value as SqliteValue};
 use trailbase_wasm::time::{Duration, SystemTime, Timer};
-use trailbase_wasm::{Guest, SqliteFunction, export, sqlite::SqliteFunctionFlags};
+use trailbase_wasm::{
+  Guest, SqliteAggregate, SqliteAggregateFunction, SqliteFunction, export,
+  sqlite::SqliteFunctionFlags,
+};
 
 // Implement the function exported in this world (see above).
 struct Endpoints;
@@ -233,10 +238,68 @@ impl Guest for Endpoints {
       ),
     ];
   }
+
+  fn sqlite_aggregate_functions() -> Vec<SqliteAggregateFunction> {
+    return vec![SqliteAggregateFunction::new_window::<Sum>(
+      "custom_sum",
+      1,
+      &[SqliteFunctionFlags::Deterministic],
+    )];
+  }
+
+  fn record_hooks() -> Vec<RecordHook> {
+    return vec![RecordHook::new(
+      "wasm_hook_api",
+      &[Event::BeforeCreate],
+      |ctx| {
+        let Some(record) = ctx.record else {
+          return RecordHookResult::Continue;
+        };
+        if record.get("text").and_then(|v| v.as_str()) == Some("forbidden") {
+          return RecordHookResult::abort(StatusCode::FORBIDDEN, "forbidden text");
+        }
+
+        let mut patch = serde_json::Map::new();
+        patch.insert("source".to_string(), serde_json::Value::from("wasm"));
+        return RecordHookResult::Modify(patch);
+      },
+    )];
+  }
 }
 
 export!(Endpoints);
 
+/// Integer sum, also usable as a window function.
+#[derive(Default)]
+struct Sum(i64);
+
+impl SqliteAggregate for Sum {
+  fn step(&mut self, args: Vec<SqliteValue>) -> Result<(), SqliteError> {
+    self.0 += integer_arg(&args)?;
+    return Ok(());
+  }
+
+  fn finalize(self: Box<Self>) -> Result<SqliteValue, SqliteError> {
+    return Ok(SqliteValue::Integer(self.0));
+  }
+
+  fn value(&self) -> Result<SqliteValue, SqliteError> {
+    return Ok(SqliteValue::Integer(self.0));
+  }
+
+  fn inverse(&mut self, args: Vec<SqliteValue>) -> Result<(), SqliteError> {
+    self.0 -= integer_arg(&args)?;
+    return Ok(());
+  }
+}
+
+fn integer_arg(args: &[SqliteValue]) -> Result<i64, SqliteError> {
+  return match args.first() {
+    Some(SqliteValue::Integer(i)) => Ok(*i),
+    _ => Err(SqliteError::Other("expected integer".to_string())),
+  };
+}
+
 #[inline]
 fn fibonacci(n: usize) -> usize {
   return match n {
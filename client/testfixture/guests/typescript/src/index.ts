@@ -7,6 +7,8 @@ import {
   StatusCode,
 } from "trailbase-wasm/http";
 import { execute, query, Transaction } from "trailbase-wasm/db";
+import { SqliteAggregateFunction } from "trailbase-wasm/sqlite";
+import type { SqliteAggregate, Value } from "trailbase-wasm/sqlite";
 
 export default defineConfig({
   httpHandlers: [
@@ -91,9 +93,42 @@ export default defineConfig({
     HttpHandler.get("/js/random", async (): Promise<string> => {
       return `${Math.random().toString()}\n`;
     }),
+    HttpHandler.get("/js/sqlite_sum", async (): Promise<string> => {
+      const rows = await query(
+        "SELECT js_sum(value) FROM (VALUES (1), (2), (3))",
+        [],
+      );
+      return `${rows[0][0]}\n`;
+    }),
+  ],
+  sqliteAggregateFunctions: [
+    SqliteAggregateFunction.window("js_sum", 1, () => new Sum(), [
+      "deterministic",
+    ]),
   ],
 });
 
+// Integer sum, also usable as a window function.
+class Sum implements SqliteAggregate {
+  private sum: bigint = 0n;
+
+  step(args: Value[]) {
+    this.sum += BigInt(args[0] as bigint);
+  }
+
+  finalize(): Value {
+    return this.sum;
+  }
+
+  value(): Value {
+    return this.sum;
+  }
+
+  inverse(args: Value[]) {
+    this.sum -= BigInt(args[0] as bigint);
+  }
+}
+
 function jsonHandler(req: HttpRequest): HttpResponse {
   const json = req.json();
   return HttpResponse.json(
@@ -155,6 +155,7 @@ export enum SystemJobId {
   AUTH_CLEANER = 4,
   QUERY_OPTIMIZER = 5,
   FILE_DELETIONS = 6,
+  EXPIRED_UPLOADS = 7,
   UNRECOGNIZED = -1,
 }
 
@@ -181,6 +182,9 @@ export function systemJobIdFromJSON(object: any): SystemJobId {
     case 6:
     case "FILE_DELETIONS":
       return SystemJobId.FILE_DELETIONS;
+    case 7:
+    case "EXPIRED_UPLOADS":
+      return SystemJobId.EXPIRED_UPLOADS;
     case -1:
     case "UNRECOGNIZED":
     default:
@@ -204,6 +208,8 @@ export function systemJobIdToJSON(object: SystemJobId): string {
       return "QUERY_OPTIMIZER";
     case SystemJobId.FILE_DELETIONS:
       return "FILE_DELETIONS";
+    case SystemJobId.EXPIRED_UPLOADS:
+      return "EXPIRED_UPLOADS";
     case SystemJobId.UNRECOGNIZED:
     default:
       return "UNRECOGNIZED";
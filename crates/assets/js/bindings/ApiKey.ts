@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ApiKey = { id: string, user: string, name: string, record_apis: Array<string> | null, permissions: Array<string>, expires: bigint | null, created: bigint, };
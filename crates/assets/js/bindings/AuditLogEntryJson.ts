@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type AuditLogEntryJson = { id: bigint, created: number, actor_id: string | null, actor: string, action: string, target: string | null, 
+/**
+ * JSON-encoded change details.
+ */
+diff: string | null, client_ip: string, };
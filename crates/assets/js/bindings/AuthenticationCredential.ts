@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AssertionResponse } from "./AssertionResponse";
+
+/**
+ * JSON representation of an asserted `PublicKeyCredential`, e.g. as returned by its `toJSON()`.
+ * Binary fields are url-safe Base64 encoded.
+ */
+export type AuthenticationCredential = { id: string, response: AssertionResponse, };
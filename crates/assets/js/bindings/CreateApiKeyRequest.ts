@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CreateApiKeyRequest = { 
+/**
+ * Id of the (service account) user the key acts on behalf of.
+ */
+user: string, 
+/**
+ * Human-readable name, e.g. describing the client.
+ */
+name: string, 
+/**
+ * Record APIs the key is restricted to. All if absent.
+ */
+record_apis: Array<string> | null, 
+/**
+ * Permitted operations, e.g. ["READ", "CREATE"].
+ */
+permissions: Array<string>, 
+/**
+ * Optional expiration as UNIX timestamp in seconds.
+ */
+expires: bigint | null, };
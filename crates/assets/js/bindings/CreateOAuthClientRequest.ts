@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CreateOAuthClientRequest = { 
+/**
+ * Human-readable name shown to users on the consent screen.
+ */
+name: string, 
+/**
+ * Exact redirect URIs the client may request.
+ */
+redirect_uris: Array<string>, 
+/**
+ * Public clients, e.g. SPAs or mobile apps, cannot keep a secret and solely rely on PKCE.
+ */
+public: boolean, };
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CreateOAuthClientResponse = { 
+/**
+ * The public `client_id`.
+ */
+id: string, 
+/**
+ * The client secret for confidential clients. It is only returned once and cannot be
+ * recovered later.
+ */
+secret: string | null, };
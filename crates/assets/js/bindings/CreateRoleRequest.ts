@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CreateRoleRequest = { 
+/**
+ * Unique name consisting of alphanumeric characters, "_" and "-".
+ */
+name: string, description: string | null, };
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AuthenticatorSelection } from "./AuthenticatorSelection";
+import type { CredentialDescriptor } from "./CredentialDescriptor";
+import type { CredentialParameters } from "./CredentialParameters";
+import type { RelyingPartyEntity } from "./RelyingPartyEntity";
+import type { UserEntity } from "./UserEntity";
+
+/**
+ * JSON representation of `PublicKeyCredentialCreationOptions`, which can be passed to
+ * `PublicKeyCredential.parseCreationOptionsFromJSON()` in the browser.
+ */
+export type CreationOptions = { rp: RelyingPartyEntity, user: UserEntity, challenge: string, pubKeyCredParams: Array<CredentialParameters>, timeout: number, excludeCredentials: Array<CredentialDescriptor>, authenticatorSelection: AuthenticatorSelection, attestation: string, };
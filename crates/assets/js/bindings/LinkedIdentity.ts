@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * External OAuth identity linked to a user.
+ */
+export type LinkedIdentity = { 
+/**
+ * Name of the OAuth provider, e.g. "github".
+ */
+provider: string, 
+/**
+ * The provider's id for the user.
+ */
+provider_user_id: string, 
+/**
+ * E-mail address reported by the provider when the identity was linked.
+ */
+email: string | null, created: bigint, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AuditLogEntryJson } from "./AuditLogEntryJson";
+
+export type ListAuditLogResponse = { total_row_count: bigint, cursor: string | null, entries: Array<AuditLogEntryJson>, };
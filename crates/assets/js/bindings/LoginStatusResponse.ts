@@ -1,3 +1,8 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LinkedIdentity } from "./LinkedIdentity";
 
-export type LoginStatusResponse = { auth_token: string | null, refresh_token: string | null, csrf_token: string | null, };
+export type LoginStatusResponse = { auth_token: string | null, refresh_token: string | null, csrf_token: string | null, 
+/**
+ * External OAuth identities linked to the signed-in user.
+ */
+identities: Array<LinkedIdentity>, };
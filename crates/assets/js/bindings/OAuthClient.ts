@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Client application registered to sign in users via TrailBase.
+ */
+export type OAuthClient = { 
+/**
+ * The public `client_id`.
+ */
+id: string, name: string, 
+/**
+ * Whether the client authenticates with a secret. Public clients, e.g. SPAs or mobile apps,
+ * solely rely on PKCE.
+ */
+confidential: boolean, redirect_uris: Array<string>, created: bigint, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RegistrationCredential } from "./RegistrationCredential";
+
+export type RegisterWebAuthnRequest = { challenge_token: string, 
+/**
+ * User-provided label for the new passkey.
+ */
+name: string | null, credential: RegistrationCredential, };
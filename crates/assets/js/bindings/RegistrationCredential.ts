@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AttestationResponse } from "./AttestationResponse";
+
+/**
+ * JSON representation of a newly created `PublicKeyCredential`, e.g. as returned by its
+ * `toJSON()`. Binary fields are url-safe Base64 encoded.
+ */
+export type RegistrationCredential = { id: string, response: AttestationResponse, };
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CredentialDescriptor } from "./CredentialDescriptor";
+
+/**
+ * JSON representation of `PublicKeyCredentialRequestOptions`, which can be passed to
+ * `PublicKeyCredential.parseRequestOptionsFromJSON()` in the browser.
+ */
+export type RequestOptions = { challenge: string, rpId: string, timeout: number, 
+/**
+ * Empty for discoverable credentials, i.e. when the user isn't known upfront.
+ */
+allowCredentials: Array<CredentialDescriptor>, userVerification: string, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Session = { id: bigint, user_agent: string | null, client_ip: string | null, created: bigint, 
+/**
+ * Last time the session was used to refresh an auth token.
+ */
+last_used: bigint, expires: bigint, 
+/**
+ * Whether this is the session of the listing request.
+ */
+current: boolean, };
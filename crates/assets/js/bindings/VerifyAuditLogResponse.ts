@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type VerifyAuditLogResponse = { 
+/**
+ * Number of entries, whose hashes checked out.
+ */
+entries: bigint, valid: boolean, 
+/**
+ * Id of the first entry that has been tampered with, if any.
+ */
+first_invalid_id: bigint | null, };
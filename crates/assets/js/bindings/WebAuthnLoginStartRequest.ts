@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type WebAuthnLoginStartRequest = { 
+/**
+ * Optional e-mail address to restrict the ceremony to the user's passkeys. Otherwise, the
+ * authenticator will offer discoverable credentials.
+ */
+email: string | null, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AuthenticationCredential } from "./AuthenticationCredential";
+import type { ResponseType } from "./ResponseType";
+
+export type WebAuthnMfaRequest = { mfa_token: string, challenge_token: string, credential: AuthenticationCredential, redirect_uri: string | null, mfa_redirect_uri: string | null, response_type: ResponseType | null, pkce_code_challenge: string | null, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CreationOptions } from "./CreationOptions";
+
+export type WebAuthnRegistrationOptions = { 
+/**
+ * Opaque token, which has to be passed back when finishing the ceremony.
+ */
+challenge_token: string, options: CreationOptions, };
@@ -13,6 +13,7 @@ import type {
 } from "./record_api";
 import { RecordApiImpl } from "./record_api";
 import { DefaultTransport, Transport } from "./transport";
+import { createCredential, getCredential } from "./webauthn";
 
 export type { Transport } from "./transport";
 
@@ -27,15 +28,25 @@ import type { LoginRequest } from "@bindings/LoginRequest";
 import type { LoginMfaRequest } from "@bindings/LoginMfaRequest";
 import type { LoginResponse } from "@bindings/LoginResponse";
 import type { LoginStatusResponse } from "@bindings/LoginStatusResponse";
+import type { LinkedIdentity } from "@bindings/LinkedIdentity";
+import type { ListSessionsResponse } from "@bindings/ListSessionsResponse";
+import type { Session } from "@bindings/Session";
 import type { LogoutRequest } from "@bindings/LogoutRequest";
 import type { RefreshRequest } from "@bindings/RefreshRequest";
 import type { RefreshResponse } from "@bindings/RefreshResponse";
+import type { WebAuthnRegistrationOptions } from "@bindings/WebAuthnRegistrationOptions";
+import type { WebAuthnAuthenticationOptions } from "@bindings/WebAuthnAuthenticationOptions";
+import type { WebAuthnCredential } from "@bindings/WebAuthnCredential";
+import type { ListWebAuthnCredentialsResponse } from "@bindings/ListWebAuthnCredentialsResponse";
 
 export type User = {
   id: string;
   email: string;
   admin?: boolean;
   mfa?: boolean;
+  roles?: string[];
+  /// Custom claims, e.g. a tenant id, if configured.
+  claims?: { [key: string]: unknown };
 };
 
 export interface MultiFactorAuthToken {
@@ -44,6 +55,9 @@ export interface MultiFactorAuthToken {
 
 export type RegisterTotp = { url: string; png: string | null };
 
+export type Passkey = WebAuthnCredential;
+export type { LinkedIdentity, Session };
+
 export type Tokens = {
   auth_token: string;
   refresh_token: string | null;
@@ -58,6 +72,9 @@ type TokenClaims = {
   csrf_token: string;
   admin?: boolean;
   mfa?: boolean;
+  roles?: string[];
+  // Custom claims.
+  [key: string]: unknown;
 };
 
 type TokenState = {
@@ -81,11 +98,28 @@ function buildTokenState(tokens?: Tokens): TokenState {
 function buildUser(state: TokenState): User | undefined {
   const claims = state.state?.claims;
   if (claims) {
+    const {
+      sub,
+      email,
+      admin,
+      mfa,
+      roles,
+      /* eslint-disable @typescript-eslint/no-unused-vars */
+      iat,
+      exp,
+      type,
+      csrf_token,
+      /* eslint-enable @typescript-eslint/no-unused-vars */
+      ...custom
+    } = claims;
+
     return {
-      id: claims.sub,
-      email: claims.email,
-      admin: claims.admin,
-      mfa: claims.mfa,
+      id: sub,
+      email,
+      admin,
+      mfa,
+      roles,
+      claims: Object.keys(custom).length > 0 ? custom : undefined,
     };
   }
 }
@@ -188,6 +222,25 @@ export interface Client {
   confirmTOTP(totpUrl: string, totp: string): Promise<void>;
   unregisterTOTP(totp: string): Promise<void>;
 
+  registerPasskey(opts?: { name?: string }): Promise<void>;
+  loginPasskey(opts?: {
+    email?: string;
+  }): Promise<MultiFactorAuthToken | undefined>;
+  loginSecondPasskey(opts: { mfaToken: MultiFactorAuthToken }): Promise<void>;
+  listPasskeys(): Promise<Passkey[]>;
+  deletePasskey(id: string): Promise<void>;
+
+  /// Lists external OAuth identities linked to the current user.
+  linkedIdentities(): Promise<LinkedIdentity[]>;
+  /// URL to navigate the browser to for linking an external OAuth provider's
+  /// identity to the current, cookie-authenticated user.
+  linkOAuthProviderUrl(provider: string, opts?: { redirectUri?: string }): string;
+  unlinkOAuthProvider(provider: string): Promise<void>;
+
+  /// Lists the current user's sessions, i.e. signed-in devices.
+  listSessions(): Promise<Session[]>;
+  revokeSession(id: bigint | number): Promise<void>;
+
   deleteUser(): Promise<void>;
   checkCookies(): Promise<Tokens | undefined>;
   refreshAuthToken(opts?: { force?: boolean }): Promise<void>;
@@ -426,6 +479,142 @@ class ClientImpl implements Client {
     await this.refreshAuthToken({ force: true });
   }
 
+  public async registerPasskey(opts?: { name?: string }): Promise<void> {
+    const response = await this.fetch(
+      `${authApiBasePath}/webauthn/register/start`,
+      {
+        method: "POST",
+      },
+    );
+    const { challenge_token, options }: WebAuthnRegistrationOptions =
+      await response.json();
+
+    const credential = await createCredential(options);
+
+    await this.fetch(`${authApiBasePath}/webauthn/register/finish`, {
+      method: "POST",
+      body: JSON.stringify({
+        challenge_token,
+        name: opts?.name ?? null,
+        credential,
+      }),
+    });
+  }
+
+  public async loginPasskey(opts?: {
+    email?: string;
+  }): Promise<MultiFactorAuthToken | undefined> {
+    const response = await this.fetch(
+      `${authApiBasePath}/webauthn/login/start`,
+      {
+        method: "POST",
+        body: JSON.stringify({ email: opts?.email ?? null }),
+      },
+    );
+    const { challenge_token, options }: WebAuthnAuthenticationOptions =
+      await response.json();
+
+    const credential = await getCredential(options);
+
+    try {
+      const response = await this.fetch(
+        `${authApiBasePath}/webauthn/login/finish`,
+        {
+          method: "POST",
+          body: JSON.stringify({ challenge_token, credential }),
+        },
+      );
+
+      this.setTokenState(
+        buildTokenState((await response.json()) as LoginResponse),
+      );
+    } catch (err) {
+      if (err instanceof FetchError && err.status === 403) {
+        const mfaTokenResponse = JSON.parse(err.message) as MfaTokenResponse;
+        return {
+          token: mfaTokenResponse.mfa_token,
+        };
+      }
+
+      throw err;
+    }
+  }
+
+  public async loginSecondPasskey(opts: {
+    mfaToken: MultiFactorAuthToken;
+  }): Promise<void> {
+    const mfa_token = opts.mfaToken.token;
+    const response = await this.fetch(`${authApiBasePath}/webauthn/mfa/start`, {
+      method: "POST",
+      body: JSON.stringify({ mfa_token }),
+    });
+    const { challenge_token, options }: WebAuthnAuthenticationOptions =
+      await response.json();
+
+    const credential = await getCredential(options);
+
+    const loginResponse = await this.fetch(
+      `${authApiBasePath}/webauthn/mfa/finish`,
+      {
+        method: "POST",
+        body: JSON.stringify({ mfa_token, challenge_token, credential }),
+      },
+    );
+
+    this.setTokenState(
+      buildTokenState((await loginResponse.json()) as LoginResponse),
+    );
+  }
+
+  public async listPasskeys(): Promise<Passkey[]> {
+    const response = await this.fetch(`${authApiBasePath}/webauthn/credentials`);
+    const parsed: ListWebAuthnCredentialsResponse = parseJSON(
+      await response.text(),
+    );
+    return parsed.credentials;
+  }
+
+  public async deletePasskey(id: string): Promise<void> {
+    await this.fetch(`${authApiBasePath}/webauthn/credentials/${id}`, {
+      method: "DELETE",
+    });
+  }
+
+  public async linkedIdentities(): Promise<LinkedIdentity[]> {
+    const response = await this.fetch(`${authApiBasePath}/status`);
+    const status: LoginStatusResponse = parseJSON(await response.text());
+    return status.identities;
+  }
+
+  public linkOAuthProviderUrl(
+    provider: string,
+    opts?: { redirectUri?: string },
+  ): string {
+    const path = `${authApiBasePath}/oauth/${provider}/link`;
+    const redirectUri = opts?.redirectUri;
+    return redirectUri
+      ? `${path}?redirect_uri=${encodeURIComponent(redirectUri)}`
+      : path;
+  }
+
+  public async unlinkOAuthProvider(provider: string): Promise<void> {
+    await this.fetch(`${authApiBasePath}/oauth/${provider}/unlink`, {
+      method: "POST",
+    });
+  }
+
+  public async listSessions(): Promise<Session[]> {
+    const response = await this.fetch(`${authApiBasePath}/sessions`);
+    const parsed: ListSessionsResponse = parseJSON(await response.text());
+    return parsed.sessions;
+  }
+
+  public async revokeSession(id: bigint | number): Promise<void> {
+    await this.fetch(`${authApiBasePath}/sessions/${id}`, {
+      method: "DELETE",
+    });
+  }
+
   /// This will call the status endpoint, which validates any provided tokens
   /// but also hoists any tokens provided as cookies into a JSON response.
   private async checkAuthStatus(): Promise<Tokens | undefined> {
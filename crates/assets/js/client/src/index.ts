@@ -2,6 +2,7 @@ import { isDev } from "./constants";
 
 export * from "./client";
 export * from "./record_api";
+export * from "./webauthn";
 
 /// Decode a base64 string to bytes.
 function base64Decode(base64: string): Uint8Array {
@@ -74,11 +74,13 @@ export type RecordId = string | number;
 export const ChangeEventStatusUnknown = 0 as const;
 export const ChangeEventStatusForbidden = 1 as const;
 export const ChangeEventStatusLoss = 2 as const;
+export const ChangeEventStatusResync = 3 as const;
 
 export type ChangeEventStatus =
   | typeof ChangeEventStatusUnknown
   | typeof ChangeEventStatusForbidden
-  | typeof ChangeEventStatusLoss;
+  | typeof ChangeEventStatusLoss
+  | typeof ChangeEventStatusResync;
 
 export type ChangeErrorEvent = {
   seq?: number;
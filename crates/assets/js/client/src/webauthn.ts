@@ -0,0 +1,97 @@
+import type { CreationOptions } from "@bindings/CreationOptions";
+import type { RequestOptions } from "@bindings/RequestOptions";
+import type { RegistrationCredential } from "@bindings/RegistrationCredential";
+import type { AuthenticationCredential } from "@bindings/AuthenticationCredential";
+
+function decode(base64: string): ArrayBuffer {
+  const bytes = Uint8Array.from(
+    atob(base64.replace(/_/g, "/").replace(/-/g, "+")),
+    (c) => c.charCodeAt(0),
+  );
+  return bytes.buffer;
+}
+
+function encode(buffer: ArrayBuffer): string {
+  return btoa(String.fromCharCode(...new Uint8Array(buffer)))
+    .replace(/\//g, "_")
+    .replace(/\+/g, "-")
+    .replace(/=+$/, "");
+}
+
+/// Create a new passkey using the platform authenticator, e.g. in the browser.
+export async function createCredential(
+  options: CreationOptions,
+): Promise<RegistrationCredential> {
+  const credential = (await navigator.credentials.create({
+    publicKey: {
+      ...options,
+      challenge: decode(options.challenge),
+      user: {
+        ...options.user,
+        id: decode(options.user.id),
+      },
+      pubKeyCredParams: options.pubKeyCredParams.map((p) => ({
+        type: p.type as PublicKeyCredentialType,
+        alg: p.alg,
+      })),
+      excludeCredentials: options.excludeCredentials.map((c) => ({
+        type: c.type as PublicKeyCredentialType,
+        id: decode(c.id),
+      })),
+      authenticatorSelection: {
+        residentKey: options.authenticatorSelection
+          .residentKey as ResidentKeyRequirement,
+        userVerification: options.authenticatorSelection
+          .userVerification as UserVerificationRequirement,
+      },
+      attestation: options.attestation as AttestationConveyancePreference,
+    },
+  })) as PublicKeyCredential | null;
+
+  if (credential === null) {
+    throw new Error("Passkey creation aborted");
+  }
+
+  const response = credential.response as AuthenticatorAttestationResponse;
+  return {
+    id: credential.id,
+    response: {
+      clientDataJSON: encode(response.clientDataJSON),
+      attestationObject: encode(response.attestationObject),
+    },
+  };
+}
+
+/// Assert an existing passkey using the platform authenticator, e.g. in the browser.
+export async function getCredential(
+  options: RequestOptions,
+): Promise<AuthenticationCredential> {
+  const credential = (await navigator.credentials.get({
+    publicKey: {
+      challenge: decode(options.challenge),
+      rpId: options.rpId,
+      timeout: options.timeout,
+      allowCredentials: options.allowCredentials.map((c) => ({
+        type: c.type as PublicKeyCredentialType,
+        id: decode(c.id),
+      })),
+      userVerification:
+        options.userVerification as UserVerificationRequirement,
+    },
+  })) as PublicKeyCredential | null;
+
+  if (credential === null) {
+    throw new Error("Passkey assertion aborted");
+  }
+
+  const response = credential.response as AuthenticatorAssertionResponse;
+  return {
+    id: credential.id,
+    response: {
+      clientDataJSON: encode(response.clientDataJSON),
+      authenticatorData: encode(response.authenticatorData),
+      signature: encode(response.signature),
+      userHandle: response.userHandle ? encode(response.userHandle) : null,
+    },
+  };
+}
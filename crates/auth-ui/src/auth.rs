@@ -20,6 +20,8 @@ pub struct AuthConfig {
   pub disable_password_auth: bool,
   pub enable_otp_signin: bool,
   pub oauth_providers: Vec<OAuthProvider>,
+  #[serde(default)]
+  pub saml_providers: Vec<OAuthProvider>,
 }
 
 /// Render a slice of tuples into an unescpaed query string.
@@ -44,6 +46,7 @@ pub struct LoginTemplate<'a> {
   pub enable_registration: bool,
   pub enable_otp: bool,
   pub oauth_providers: &'a [OAuthProvider],
+  pub saml_providers: &'a [OAuthProvider],
   pub oauth_query_params: &'a [(&'a str, &'a str)],
 }
 
@@ -104,6 +107,15 @@ pub struct ChangeEmailTemplate<'a> {
   pub alert: &'a str,
 }
 
+#[derive(Template)]
+#[template(path = "oidc/consent/index.html")]
+pub struct ConsentTemplate<'a> {
+  pub state: String,
+  pub alert: &'a str,
+  pub client_name: &'a str,
+  pub scope: &'a str,
+}
+
 pub fn hidden_input<T>(name: &str, value: Option<T>) -> String
 where
   T: AsRef<str>,
@@ -140,6 +152,7 @@ mod tests {
       enable_registration: true,
       enable_otp: true,
       oauth_providers: &[],
+      saml_providers: &[],
       oauth_query_params: &[("redirect_uri", redirect_uri)],
     }
     .render()
@@ -161,6 +174,7 @@ mod tests {
         display_name: "Fancy Name".to_string(),
         img_name: "oidc".to_string(),
       }],
+      saml_providers: &[],
       oauth_query_params: &[("redirect_uri", redirect_uri), ("foo", "bar")],
     }
     .render()
@@ -169,6 +183,28 @@ mod tests {
     assert!(oauth_template.contains(&state), "{template}"); // Not escaped.
     assert!(oauth_template.contains(&redirect_uri), "{template}"); // Not escaped.
     assert!(oauth_template.contains("foo=bar"), "{template}"); // Not escaped.
+
+    let saml_template = LoginTemplate {
+      state: state.clone(),
+      alert: "",
+      enable_registration: false,
+      enable_otp: false,
+      oauth_providers: &[],
+      saml_providers: &[OAuthProvider {
+        name: "acme".to_string(),
+        display_name: "ACME <SSO>".to_string(),
+        img_name: "oidc.svg".to_string(),
+      }],
+      oauth_query_params: &[("redirect_uri", redirect_uri)],
+    }
+    .render()
+    .unwrap();
+
+    assert!(
+      saml_template.contains("/saml/acme/login"),
+      "{saml_template}"
+    );
+    assert!(!saml_template.contains("ACME <SSO>"), "{saml_template}"); // Is escaped.
   }
 
   #[test]
@@ -270,4 +306,24 @@ mod tests {
     assert!(template.contains(&state), "{template}"); // Not escaped.
     assert!(!template.contains(alert), "{template}"); // Is escaped.
   }
+
+  #[test]
+  fn test_consent_template_escaping() {
+    let state = hidden_input("TEST", Some("FOO"));
+    let alert = "<><>";
+    let client_name = "<script>evil</script>";
+
+    let template = ConsentTemplate {
+      state: state.clone(),
+      alert,
+      client_name,
+      scope: "openid email",
+    }
+    .render()
+    .unwrap();
+
+    assert!(template.contains(&state), "{template}"); // Not escaped.
+    assert!(!template.contains(alert), "{template}"); // Is escaped.
+    assert!(!template.contains(client_name), "{template}"); // Is escaped.
+  }
 }
@@ -100,6 +100,16 @@ impl Guest for Endpoints {
           return ui_change_email_handler(req.query_parse()?, user).await;
         },
       ),
+      routing::get(
+        OIDC_CONSENT_UI,
+        async |req: Request| -> Result<Response, HttpError> {
+          let Some(user) = req.user() else {
+            // Sign in first and come back here afterwards.
+            return Ok(Redirect::to(LOGIN_UI).into_response());
+          };
+          return ui_oidc_consent_handler(req.query_parse()?, user).await;
+        },
+      ),
       routing::get("/_/auth/{*wildcard}", async |req: Request| {
         return static_assets_handler(
           req
@@ -169,6 +179,7 @@ async fn ui_login_handler(
     enable_registration: !config.disable_password_auth,
     enable_otp: config.enable_otp_signin,
     oauth_providers: &config.oauth_providers,
+    saml_providers: &config.saml_providers,
     oauth_query_params: &oauth_query_params,
   }
   .render();
@@ -396,6 +407,41 @@ async fn ui_change_email_handler(
   return Ok(Html(html.map_err(internal)?).into_response());
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct OidcConsentQuery {
+  consent_token: String,
+  client_name: Option<String>,
+  scope: Option<String>,
+  alert: Option<String>,
+}
+
+async fn ui_oidc_consent_handler(
+  query: OidcConsentQuery,
+  _user: &User,
+) -> Result<Response, HttpError> {
+  // The token is a JWT. Reject anything else, since it's embedded into the page verbatim.
+  if !query
+    .consent_token
+    .chars()
+    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+  {
+    return Err(HttpError::message(
+      StatusCode::BAD_REQUEST,
+      "invalid consent token",
+    ));
+  }
+
+  let html = auth::ConsentTemplate {
+    state: auth::hidden_input("consent_token", Some(&query.consent_token)),
+    alert: query.alert.as_deref().unwrap_or_default(),
+    client_name: query.client_name.as_deref().unwrap_or("An application"),
+    scope: query.scope.as_deref().unwrap_or_default(),
+  }
+  .render();
+
+  return Ok(Html(html.map_err(internal)?).into_response());
+}
+
 async fn static_assets_handler(path: &str) -> Result<Response, HttpError> {
   // We want as little magic as possible. The only /_/auth/subpath that isn't SSR, is
   // profile, so we when hitting /profile or /profile, we want actually want to serve
@@ -432,3 +478,4 @@ const PROFILE_UI: &str = "/_/auth/profile";
 const REGISTER_USER_UI: &str = "/_/auth/register";
 const CHANGE_PASSWORD_UI: &str = "/_/auth/change_password";
 const CHANGE_EMAIL_UI: &str = "/_/auth/change_email";
+const OIDC_CONSENT_UI: &str = "/_/auth/oidc/consent";
@@ -0,0 +1,92 @@
+import { createResource, For, Show } from "solid-js";
+import { TbOutlineUnlink } from "solid-icons/tb";
+import type { Client } from "trailbase";
+
+import { AUTH_API } from "@/lib/constants";
+import { Button, buttonVariants } from "@/components/ui/button";
+import { showToast } from "@/components/ui/toast";
+
+type Provider = {
+  name: string;
+  displayName: string;
+};
+
+async function listProviders(client: Client): Promise<Provider[]> {
+  const response = await client.fetch(`${AUTH_API}/oauth/providers`);
+  const { providers }: { providers: [string, string][] } =
+    await response.json();
+  return providers.map(([name, displayName]) => ({ name, displayName }));
+}
+
+export function LinkedAccounts(props: { client: Client }) {
+  const [providers] = createResource(
+    async () => await listProviders(props.client),
+  );
+  const [identities, { refetch }] = createResource(
+    async () => await props.client.linkedIdentities(),
+  );
+
+  const displayName = (name: string) =>
+    (providers() ?? []).find((p) => p.name === name)?.displayName ?? name;
+  const unlinked = () =>
+    (providers() ?? []).filter(
+      (p) => !(identities() ?? []).some((i) => i.provider === p.name),
+    );
+
+  async function unlink(provider: string) {
+    try {
+      await props.client.unlinkOAuthProvider(provider);
+      refetch();
+    } catch (err) {
+      showToast({
+        title: "Error unlinking account",
+        description: `${err}`,
+        variant: "error",
+      });
+    }
+  }
+
+  return (
+    <div class="flex w-full flex-col items-end gap-2">
+      <Show when={(identities() ?? []).length > 0}>
+        <ul class="w-full">
+          <For each={identities()}>
+            {(identity) => (
+              <li class="flex items-center justify-between gap-2">
+                <span>
+                  {displayName(identity.provider)}
+                  <Show when={identity.email}>
+                    <span class="text-muted-foreground">
+                      {" "}
+                      ({identity.email})
+                    </span>
+                  </Show>
+                </span>
+
+                <Button
+                  variant="ghost"
+                  size="icon"
+                  title="Unlink"
+                  onClick={() => unlink(identity.provider)}
+                >
+                  <TbOutlineUnlink />
+                </Button>
+              </li>
+            )}
+          </For>
+        </ul>
+      </Show>
+
+      <For each={unlinked()}>
+        {(provider) => (
+          <a
+            class={buttonVariants({ variant: "outline" })}
+            href={`${AUTH_API}/oauth/${provider.name}/link?redirect_uri=${encodeURIComponent(window.location.pathname)}`}
+          >
+            Link {provider.displayName}
+          </a>
+        )}
+      </For>
+    </div>
+  );
+}
@@ -0,0 +1,74 @@
+import { createResource, For, Show } from "solid-js";
+import { TbOutlineTrash } from "solid-icons/tb";
+import type { Client } from "trailbase";
+
+import { Button } from "@/components/ui/button";
+import { showToast } from "@/components/ui/toast";
+
+export function Passkeys(props: { client: Client }) {
+  const [passkeys, { refetch }] = createResource(
+    async () => await props.client.listPasskeys(),
+  );
+
+  async function registerPasskey() {
+    try {
+      // TODO: Better UI than browser prompt.
+      const name = prompt("Name for the new passkey, e.g. 'Laptop'");
+
+      await props.client.registerPasskey({ name: name ?? undefined });
+      showToast({
+        title: "Passkey added",
+        description: "You can now sign in using your passkey.",
+        variant: "success",
+      });
+      refetch();
+    } catch (err) {
+      showToast({
+        title: "Error adding passkey",
+        description: `${err}`,
+        variant: "error",
+      });
+    }
+  }
+
+  async function deletePasskey(id: string) {
+    try {
+      await props.client.deletePasskey(id);
+      refetch();
+    } catch (err) {
+      showToast({
+        title: "Error deleting passkey",
+        description: `${err}`,
+        variant: "error",
+      });
+    }
+  }
+
+  return (
+    <div class="flex w-full flex-col items-end gap-2">
+      <Show when={(passkeys() ?? []).length > 0}>
+        <ul class="w-full">
+          <For each={passkeys()}>
+            {(passkey) => (
+              <li class="flex items-center justify-between gap-2">
+                <span>{passkey.name ?? "Passkey"}</span>
+
+                <Button
+                  variant="ghost"
+                  size="icon"
+                  onClick={() => deletePasskey(passkey.id)}
+                >
+                  <TbOutlineTrash />
+                </Button>
+              </li>
+            )}
+          </For>
+        </ul>
+      </Show>
+
+      <Button type="button" variant="outline" onClick={registerPasskey}>
+        Add Passkey
+      </Button>
+    </div>
+  );
+}
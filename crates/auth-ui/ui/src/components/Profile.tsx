@@ -20,6 +20,8 @@ import { Card } from "@/components/ui/card";
 import { showToast } from "@/components/ui/toast";
 import { ErrorBoundary } from "@/components/ErrorBoundary";
 import { TotpToggleButton } from "@/components/Totp";
+import { Passkeys } from "@/components/Passkeys";
+import { LinkedAccounts } from "@/components/LinkedAccounts";
 import {
   Dialog,
   DialogContent,
@@ -241,6 +243,8 @@ function ProfileTable(props: { client: Client; user: User }) {
 
         <div class="my-4 flex w-full flex-col items-end gap-2">
           <TotpToggleButton {...props} />
+          <Passkeys client={props.client} />
+          <LinkedAccounts client={props.client} />
         </div>
       </Card>
 
@@ -0,0 +1,44 @@
+import { getCredential } from "trailbase";
+
+import { AUTH_API } from "@/lib/constants";
+
+function setHiddenInput(form: HTMLFormElement, name: string, value: string) {
+  let input = form.querySelector<HTMLInputElement>(`input[name="${name}"]`);
+  if (input === null) {
+    input = document.createElement("input");
+    input.type = "hidden";
+    input.name = name;
+    form.appendChild(input);
+  }
+  input.value = value;
+}
+
+/// Runs a passkey assertion ceremony and submits the resulting credential
+/// alongside the form's hidden state, e.g. redirect URIs.
+export async function submitPasskeyForm(
+  form: HTMLFormElement,
+  startPath: string,
+  body: Record<string, string | null>,
+) {
+  try {
+    const response = await fetch(`${AUTH_API}/${startPath}`, {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify(body),
+    });
+    if (!response.ok) {
+      throw new Error(await response.text());
+    }
+
+    const { challenge_token, options } = await response.json();
+    const credential = await getCredential(options);
+
+    setHiddenInput(form, "challenge_token", challenge_token);
+    setHiddenInput(form, "credential", JSON.stringify(credential));
+    form.submit();
+  } catch (err) {
+    const url = new URL(window.location.href);
+    url.searchParams.set("alert", `Passkey: ${err}`);
+    window.location.replace(url);
+  }
+}
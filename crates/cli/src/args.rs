@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use clap::{Args, Parser, Subcommand, ValueEnum};
 
 use trailbase::DataDir;
@@ -77,6 +78,11 @@ pub enum SubCommands {
     #[command(subcommand)]
     cmd: Option<UserSubCommands>,
   },
+  /// Manage roles (list, create, delete, assign, unassign).
+  Role {
+    #[command(subcommand)]
+    cmd: Option<RoleSubCommands>,
+  },
   /// Programmatically send emails.
   Email(EmailArgs),
   /// Manage WASM components
@@ -84,6 +90,23 @@ pub enum SubCommands {
     #[command(subcommand)]
     cmd: Option<ComponentSubCommands>,
   },
+  /// Manage backups (list, restore).
+  Backup {
+    #[command(subcommand)]
+    cmd: Option<BackupSubCommands>,
+  },
+  /// Restore databases from continuous replicas. The server must not be running.
+  Restore(RestoreArgs),
+  /// Manage the keys used for signing auth tokens (list, rotate).
+  Keys {
+    #[command(subcommand)]
+    cmd: Option<KeysSubCommands>,
+  },
+  /// Manage OAuth clients signing in users via TrailBase (list, create, delete).
+  OauthClient {
+    #[command(subcommand)]
+    cmd: Option<OAuthClientSubCommands>,
+  },
 }
 
 #[derive(Args, Clone, Debug)]
@@ -130,6 +153,10 @@ pub struct ServerArgs {
   #[arg(long, default_value = "*")]
   pub cors_allowed_origins: Vec<String>,
 
+  /// Expose Prometheus metrics at `/metrics`. Served on the admin address, if separate.
+  #[arg(long, env, default_value_t = false)]
+  pub enable_metrics: bool,
+
   /// Number of JavaScript isolates/workers to start (Default: #cpus).
   #[arg(long, env)]
   pub runtime_threads: Option<usize>,
@@ -189,6 +216,42 @@ pub enum AdminSubCommands {
 }
 
 // TODO: Add "create user" (low priority since users can be created via the UI).
+#[derive(Subcommand, Debug, Clone)]
+pub enum RoleSubCommands {
+  /// Lists roles, optionally only the ones assigned to the given user.
+  List {
+    /// User in question, either email or UUID.
+    user: Option<String>,
+  },
+  /// Creates a new role.
+  Create {
+    /// Unique name consisting of alphanumeric characters, "_" and "-".
+    name: String,
+    /// Optional human-readable description.
+    #[arg(long)]
+    description: Option<String>,
+  },
+  /// Deletes a role, which also removes it from all users.
+  Delete {
+    /// Name of the role.
+    name: String,
+  },
+  /// Assigns a role to a user.
+  Assign {
+    /// User in question, either email or UUID.
+    user: String,
+    /// Name of the role.
+    role: String,
+  },
+  /// Removes a role from a user.
+  Unassign {
+    /// User in question, either email or UUID.
+    user: String,
+    /// Name of the role.
+    role: String,
+  },
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum UserSubCommands {
   /// Change a user's password.
@@ -235,6 +298,33 @@ pub enum UserSubCommands {
     /// User in question, either email or UUID.
     user: String,
   },
+  /// Mint a long-lived API key acting on behalf of the given (service account) user.
+  MintApiKey {
+    /// User in question, either email or UUID.
+    user: String,
+    /// Human-readable name of the key, e.g. describing the client.
+    #[arg(long)]
+    name: String,
+    /// Record API the key is restricted to. Can be repeated, defaults to all APIs.
+    #[arg(long = "api")]
+    apis: Vec<String>,
+    /// Permitted operation: create, read, update, delete or schema. Can be repeated.
+    #[arg(long = "permission", required = true)]
+    permissions: Vec<String>,
+    /// Expire the key after the given number of days. Never expires by default.
+    #[arg(long)]
+    expires_in_days: Option<u32>,
+  },
+  /// List API keys, optionally only the ones of the given user.
+  ListApiKeys {
+    /// User in question, either email or UUID.
+    user: Option<String>,
+  },
+  /// Revoke the API key with the given id.
+  RevokeApiKey {
+    /// UUID of the API key.
+    id: uuid::Uuid,
+  },
   // Import users from a file.
   Import {
     /// In dry-run mode users will only be validated and not imported.
@@ -302,3 +392,70 @@ pub enum ComponentSubCommands {
   /// Update all installed first-party components.
   Update,
 }
+
+#[derive(Args, Clone, Debug)]
+pub struct RestoreArgs {
+  /// Restore from the replicas in the configured S3 bucket, see `server.replication_config`.
+  #[arg(long, default_value_t = false)]
+  pub from_s3: bool,
+
+  /// Point in time to restore as RFC 3339 timestamp, e.g. "2025-01-31T12:00:00Z". Defaults to the
+  /// latest replicated state.
+  #[arg(long)]
+  pub at: Option<DateTime<Utc>>,
+
+  /// Restore even if databases appear to be in use.
+  #[arg(long, default_value_t = false)]
+  pub force: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum KeysSubCommands {
+  /// Lists the current and retired keys.
+  List,
+  /// Replaces the current key with a new one. Requires a server restart to take effect.
+  Rotate {
+    /// Number of hours the retired key will still be accepted for validating tokens. Should
+    /// exceed the auth token TTL to not sign out users.
+    #[arg(long, default_value_t = 24)]
+    grace_period_hours: i64,
+  },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum OAuthClientSubCommands {
+  /// Lists registered clients.
+  List,
+  /// Registers a new client and prints its id and secret.
+  Create {
+    /// Human-readable name shown to users on the consent screen.
+    #[arg(long)]
+    name: String,
+    /// Redirect URI the client may request. Can be repeated.
+    #[arg(long = "redirect-uri", required = true)]
+    redirect_uris: Vec<String>,
+    /// Registers a public client, e.g. a SPA or mobile app, which solely relies on PKCE and has
+    /// no secret.
+    #[arg(long, default_value_t = false)]
+    public: bool,
+  },
+  /// Deletes a client.
+  Delete {
+    /// Id of the client.
+    id: String,
+  },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum BackupSubCommands {
+  /// List available backups.
+  List,
+  /// Restore databases from the given backup. The server must not be running.
+  Restore {
+    /// Id of the backup to restore, see `list`.
+    id: String,
+    /// Restore even if databases appear to be in use.
+    #[arg(long, default_value_t = false)]
+    force: bool,
+  },
+}
@@ -17,8 +17,9 @@ use trailbase_cli::wasm::{
 use utoipa::OpenApi;
 
 use trailbase_cli::{
-  AdminSubCommands, CommandLineArgs, ComponentReference, ComponentSubCommands, OpenApiSubCommands,
-  SubCommands, UserSubCommands,
+  AdminSubCommands, BackupSubCommands, CommandLineArgs, ComponentReference, ComponentSubCommands,
+  KeysSubCommands, OAuthClientSubCommands, OpenApiSubCommands, RoleSubCommands, SubCommands,
+  UserSubCommands,
 };
 
 type BoxError = Box<dyn std::error::Error + Send + Sync>;
@@ -77,6 +78,7 @@ async fn async_main(
         dev: cmd.dev,
         demo: cmd.demo,
         cors_allowed_origins: cmd.cors_allowed_origins,
+        enable_metrics: cmd.enable_metrics,
         wasm_tokio_runtime,
         tls_key: None,
         tls_cert: None,
@@ -140,6 +142,8 @@ async fn async_main(
     }
     SubCommands::Admin { cmd } => {
       let (conn, _metadata, _new) = api::init_main_db(Some(&data_dir), None, vec![], vec![])?;
+      let audit_log =
+        api::AuditLog::init_from_path(&data_dir, api::init_logs_db(Some(&data_dir))?).await?;
 
       match cmd {
         Some(AdminSubCommands::List) => {
@@ -160,11 +164,13 @@ async fn async_main(
           }
         }
         Some(AdminSubCommands::Demote { user }) => {
-          let id = api::cli::demote_admin_to_user(&conn, to_user_reference(user)).await?;
+          let id =
+            api::cli::demote_admin_to_user(&conn, &audit_log, to_user_reference(user)).await?;
           println!("Demoted admin to user for '{id}'");
         }
         Some(AdminSubCommands::Promote { user }) => {
-          let id = api::cli::promote_user_to_admin(&conn, to_user_reference(user)).await?;
+          let id =
+            api::cli::promote_user_to_admin(&conn, &audit_log, to_user_reference(user)).await?;
           println!("Promoted user to admin for '{id}'");
         }
         None => {
@@ -174,35 +180,90 @@ async fn async_main(
         }
       };
     }
+    SubCommands::Role { cmd } => {
+      let (conn, _metadata, _new) = api::init_main_db(Some(&data_dir), None, vec![], vec![])?;
+      let audit_log =
+        api::AuditLog::init_from_path(&data_dir, api::init_logs_db(Some(&data_dir))?).await?;
+
+      match cmd {
+        Some(RoleSubCommands::List { user }) => {
+          let roles = api::cli::list_roles(&conn, user.map(to_user_reference)).await?;
+
+          println!("name\tdescription\tcreated");
+          for role in roles {
+            println!(
+              "{}\t{}\t{created:?}",
+              role.name,
+              role.description.unwrap_or_default(),
+              created = chrono::Utc.timestamp_opt(role.created, 0),
+            );
+          }
+        }
+        Some(RoleSubCommands::Create { name, description }) => {
+          api::cli::create_role(&conn, &audit_log, &name, description).await?;
+          println!("Created role '{name}'");
+        }
+        Some(RoleSubCommands::Delete { name }) => {
+          api::cli::delete_role(&conn, &audit_log, &name).await?;
+          println!("Deleted role '{name}'");
+        }
+        Some(RoleSubCommands::Assign { user, role }) => {
+          let id = api::cli::assign_role(&conn, &audit_log, to_user_reference(user), &role).await?;
+          println!("Assigned role '{role}' to '{id}'");
+        }
+        Some(RoleSubCommands::Unassign { user, role }) => {
+          let id =
+            api::cli::unassign_role(&conn, &audit_log, to_user_reference(user), &role).await?;
+          println!("Removed role '{role}' from '{id}'");
+        }
+        None => {
+          CommandLineArgs::command()
+            .find_subcommand_mut("role")
+            .map(|cmd| cmd.print_help());
+        }
+      };
+    }
     SubCommands::User { cmd } => {
       let (user_conn, _metadata, _new) = api::init_main_db(Some(&data_dir), None, vec![], vec![])?;
       let session_conn = api::init_session_db(Some(&data_dir))?;
+      let audit_log =
+        api::AuditLog::init_from_path(&data_dir, api::init_logs_db(Some(&data_dir))?).await?;
 
       match cmd {
         Some(UserSubCommands::ChangePassword { user, password }) => {
           let id =
-            api::cli::change_password(&user_conn, to_user_reference(user), &password).await?;
+            api::cli::change_password(&user_conn, &audit_log, to_user_reference(user), &password)
+              .await?;
           println!("Updated password for '{id}'");
         }
         Some(UserSubCommands::ChangeEmail { user, new_email }) => {
-          let id = api::cli::change_email(&user_conn, to_user_reference(user), &new_email).await?;
+          let id =
+            api::cli::change_email(&user_conn, &audit_log, to_user_reference(user), &new_email)
+              .await?;
           println!("Updated email for '{id}'");
         }
         Some(UserSubCommands::Add { email, password }) => {
-          api::cli::add_user(&user_conn, &email, &password).await?;
+          api::cli::add_user(&user_conn, &audit_log, &email, &password).await?;
           println!("Added user '{email}'");
         }
         Some(UserSubCommands::Delete { user }) => {
-          api::cli::delete_user(&user_conn, to_user_reference(user.clone())).await?;
+          api::cli::delete_user(&user_conn, &audit_log, to_user_reference(user.clone())).await?;
           println!("Deleted user '{user}'");
         }
         Some(UserSubCommands::Verify { user, verified }) => {
-          let id = api::cli::set_verified(&user_conn, to_user_reference(user), verified).await?;
+          let id =
+            api::cli::set_verified(&user_conn, &audit_log, to_user_reference(user), verified)
+              .await?;
           println!("Set verified={verified} for '{id}'");
         }
         Some(UserSubCommands::InvalidateSession { user }) => {
-          api::cli::invalidate_sessions(&user_conn, &session_conn, to_user_reference(user.clone()))
-            .await?;
+          api::cli::invalidate_sessions(
+            &user_conn,
+            &session_conn,
+            &audit_log,
+            to_user_reference(user.clone()),
+          )
+          .await?;
           println!("Sessions invalidated for '{user}'");
         }
         Some(UserSubCommands::MintToken { user }) => {
@@ -210,11 +271,50 @@ async fn async_main(
             &data_dir,
             &user_conn,
             &session_conn,
+            &audit_log,
             to_user_reference(user.clone()),
           )
           .await?;
           println!("Bearer {auth_token}");
         }
+        Some(UserSubCommands::MintApiKey {
+          user,
+          name,
+          apis,
+          permissions,
+          expires_in_days,
+        }) => {
+          let (id, key) = api::cli::mint_api_key(
+            &user_conn,
+            &audit_log,
+            to_user_reference(user),
+            name,
+            (!apis.is_empty()).then_some(apis),
+            &permissions,
+            expires_in_days.map(|days| chrono::Duration::days(days as i64)),
+          )
+          .await?;
+          println!("Minted API key '{id}': {key}");
+        }
+        Some(UserSubCommands::ListApiKeys { user }) => {
+          let keys = api::cli::list_api_keys(&user_conn, user.map(to_user_reference)).await?;
+          for key in keys {
+            println!(
+              "{id}\t{name}\tuser={user}\tapis={apis}\tpermissions={permissions}",
+              id = key.id,
+              name = key.name,
+              user = key.user,
+              apis = key
+                .record_apis
+                .map_or("*".to_string(), |apis| apis.join(",")),
+              permissions = key.permissions.join(","),
+            );
+          }
+        }
+        Some(UserSubCommands::RevokeApiKey { id }) => {
+          api::cli::revoke_api_key(&user_conn, &audit_log, id).await?;
+          println!("Revoked API key '{id}'");
+        }
         Some(UserSubCommands::Import {
           dry_run,
           auth0_json,
@@ -226,7 +326,7 @@ async fn async_main(
             println!("Importing {} users.", users.len());
 
             if !dry_run {
-              api::cli::import_users(&user_conn, users).await?;
+              api::cli::import_users(&user_conn, &audit_log, users).await?;
             }
           } else {
             return Err("Missing '--auth0_json' path".into());
@@ -366,6 +466,122 @@ async fn async_main(
         }
       };
     }
+    SubCommands::Backup { cmd } => {
+      match cmd {
+        Some(BackupSubCommands::List) => {
+          let backups = api::list_backups(&data_dir)?;
+
+          println!("{: <24}\tcreated\tsize\tfiles", "id");
+          for backup in backups {
+            println!(
+              "{: <24}\t{}\t{}\t{}",
+              backup.id,
+              backup.created.to_rfc3339(),
+              backup.size_bytes,
+              backup.files.join(","),
+            );
+          }
+        }
+        Some(BackupSubCommands::Restore { id, force }) => {
+          let restored = api::restore_backup(&data_dir, &id, force)?;
+          println!("Restored backup '{id}': {restored:?}");
+        }
+        None => {
+          CommandLineArgs::command()
+            .find_subcommand_mut("backup")
+            .map(|cmd| cmd.print_help());
+        }
+      };
+    }
+    SubCommands::Keys { cmd } => {
+      match cmd {
+        Some(KeysSubCommands::List) => {
+          let jwt = api::JwtHelper::init_from_path(&data_dir).await?;
+
+          println!("kid\tcurrent\texpires");
+          for key in jwt.keys() {
+            println!(
+              "{}\t{}\t{}",
+              key.kid,
+              key.current,
+              key
+                .expires
+                .and_then(|expires| chrono::Utc.timestamp_opt(expires, 0).single())
+                .map(|expires| expires.to_rfc3339())
+                .unwrap_or_default(),
+            );
+          }
+        }
+        Some(KeysSubCommands::Rotate { grace_period_hours }) => {
+          let kid =
+            api::rotate_keys(&data_dir, chrono::Duration::hours(grace_period_hours)).await?;
+          println!("Rotated keys, new key: '{kid}'. Restart the server to start using it.");
+        }
+        None => {
+          CommandLineArgs::command()
+            .find_subcommand_mut("keys")
+            .map(|cmd| cmd.print_help());
+        }
+      };
+    }
+    SubCommands::OauthClient { cmd } => {
+      let (conn, _metadata, _new) = api::init_main_db(Some(&data_dir), None, vec![], vec![])?;
+      let audit_log =
+        api::AuditLog::init_from_path(&data_dir, api::init_logs_db(Some(&data_dir))?).await?;
+
+      match cmd {
+        Some(OAuthClientSubCommands::List) => {
+          let clients = api::cli::list_oauth_clients(&conn).await?;
+
+          println!("id\tname\tconfidential\tredirect_uris");
+          for client in clients {
+            println!(
+              "{}\t{}\t{}\t{}",
+              client.id,
+              client.name,
+              client.confidential,
+              client.redirect_uris.join(","),
+            );
+          }
+        }
+        Some(OAuthClientSubCommands::Create {
+          name,
+          redirect_uris,
+          public,
+        }) => {
+          let (id, secret) =
+            api::cli::register_oauth_client(&conn, &audit_log, name, redirect_uris, !public)
+              .await?;
+          println!("Registered OAuth client '{id}'");
+          if let Some(secret) = secret {
+            println!("Client secret (only shown once): {secret}");
+          }
+        }
+        Some(OAuthClientSubCommands::Delete { id }) => {
+          api::cli::delete_oauth_client(&conn, &audit_log, &id).await?;
+          println!("Deleted OAuth client '{id}'");
+        }
+        None => {
+          CommandLineArgs::command()
+            .find_subcommand_mut("oauth-client")
+            .map(|cmd| cmd.print_help());
+        }
+      };
+    }
+    SubCommands::Restore(args) => {
+      if !args.from_s3 {
+        return Err(
+          "Missing restore source, e.g. '--from-s3'. For local backups see 'trail backup restore'."
+            .into(),
+        );
+      }
+
+      let restored = api::restore_from_replica(&data_dir, args.at, args.force).await?;
+      match args.at {
+        Some(at) => println!("Restored state at {}: {restored:?}", at.to_rfc3339()),
+        None => println!("Restored latest state: {restored:?}"),
+      };
+    }
   }
 
   return Ok(());
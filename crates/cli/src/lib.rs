@@ -5,8 +5,9 @@ pub mod import;
 pub mod wasm;
 
 pub use args::{
-  AdminSubCommands, CommandLineArgs, ComponentReference, ComponentSubCommands, EmailArgs,
-  JsonSchemaModeArg, SubCommands, UserSubCommands,
+  AdminSubCommands, BackupSubCommands, CommandLineArgs, ComponentReference, ComponentSubCommands,
+  EmailArgs, JsonSchemaModeArg, KeysSubCommands, OAuthClientSubCommands, RestoreArgs,
+  RoleSubCommands, SubCommands, UserSubCommands,
 };
 
 pub use args::OpenApiSubCommands;
@@ -60,6 +60,8 @@ impl From<reqwest::Error> for Error {
 pub struct User {
   pub sub: String,
   pub email: String,
+  /// Names of the roles assigned to the user.
+  pub roles: Vec<String>,
 }
 
 /// Holds the tokens minted by the server on login.
@@ -77,6 +79,38 @@ pub struct MultiFactorAuthToken {
   mfa_token: String,
 }
 
+/// Options for a WebAuthn ceremony, i.e. registering or asserting a passkey.
+///
+/// `options` are the JSON-encoded `PublicKeyCredentialCreationOptions` or
+/// `PublicKeyCredentialRequestOptions` to be handed to the platform authenticator. The opaque
+/// `challenge_token` has to be passed back alongside the resulting credential.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct WebAuthnChallenge {
+  pub challenge_token: String,
+  pub options: serde_json::Value,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct WebAuthnCredential {
+  pub id: String,
+  pub name: Option<String>,
+  pub created: i64,
+  pub last_used: Option<i64>,
+}
+
+/// A session of the logged-in user, i.e. a signed-in device.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Session {
+  pub id: i64,
+  pub user_agent: Option<String>,
+  pub client_ip: Option<String>,
+  pub created: i64,
+  pub last_used: i64,
+  pub expires: i64,
+  /// Whether this is the client's own session.
+  pub current: bool,
+}
+
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Pagination {
   cursor: Option<String>,
@@ -118,6 +152,9 @@ pub enum EventErrorStatus {
   /// additional losses that may happen between the TrailBase server and the client. This
   /// needs to be determined client-side based on event `seq` numbers.
   Loss = 2,
+  /// Events missed while disconnected could not be replayed, e.g. because the resume token is too
+  /// old or unknown. Clients need to refetch to get back in sync.
+  Resync = 3,
 }
 
 #[derive(Debug, Clone, Deserialize, PartialEq)]
@@ -136,6 +173,10 @@ pub struct ChangeEvent {
   #[serde(flatten)]
   pub event: Arc<EventPayload>,
   pub seq: Option<i64>,
+  /// Opaque token, which can be passed to `RecordApi::resume` to continue a subscription after
+  /// this event without missing any changes in-between.
+  #[serde(default)]
+  pub resume: Option<String>,
 }
 
 impl ChangeEvent {
@@ -182,6 +223,7 @@ impl RecordId<'_> for i64 {
 pub trait ReadArgumentsTrait<'a> {
   fn serialized_id(self) -> Cow<'a, str>;
   fn expand(&self) -> Option<&Vec<&'a str>>;
+  fn fields(&self) -> Option<&Vec<&'a str>>;
 }
 
 impl<'a, T: RecordId<'a>> ReadArgumentsTrait<'a> for T {
@@ -192,23 +234,39 @@ impl<'a, T: RecordId<'a>> ReadArgumentsTrait<'a> for T {
   fn expand(&self) -> Option<&Vec<&'a str>> {
     return None;
   }
+
+  fn fields(&self) -> Option<&Vec<&'a str>> {
+    return None;
+  }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct ReadArguments<'a, T: RecordId<'a>> {
   id: T,
   expand: Option<Vec<&'a str>>,
+  fields: Option<Vec<&'a str>>,
 }
 
 impl<'a, T: RecordId<'a>> ReadArguments<'a, T> {
   pub fn new(id: T) -> Self {
-    return Self { id, expand: None };
+    return Self {
+      id,
+      expand: None,
+      fields: None,
+    };
   }
 
   pub fn with_expand(mut self, expand: impl AsRef<[&'a str]>) -> Self {
     self.expand = Some(expand.as_ref().to_vec());
     return self;
   }
+
+  /// Only return the given columns. Columns of expanded foreign records can be selected using
+  /// `<fk>.<column>`.
+  pub fn with_fields(mut self, fields: impl AsRef<[&'a str]>) -> Self {
+    self.fields = Some(fields.as_ref().to_vec());
+    return self;
+  }
 }
 
 impl<'a, T: RecordId<'a>> ReadArgumentsTrait<'a> for ReadArguments<'a, T> {
@@ -219,6 +277,10 @@ impl<'a, T: RecordId<'a>> ReadArgumentsTrait<'a> for ReadArguments<'a, T> {
   fn expand(&self) -> Option<&Vec<&'a str>> {
     return self.expand.as_ref();
   }
+
+  fn fields(&self) -> Option<&Vec<&'a str>> {
+    return self.fields.as_ref();
+  }
 }
 
 #[async_trait::async_trait]
@@ -332,6 +394,8 @@ struct JwtTokenClaims {
   exp: i64,
   email: String,
   csrf_token: String,
+  #[serde(default)]
+  roles: Vec<String>,
 }
 
 fn decode_auth_token<T: DeserializeOwned + Clone>(token: &str) -> Result<T, Error> {
@@ -352,7 +416,10 @@ pub struct ListArguments<'a> {
   order: Option<Vec<&'a str>>,
   filters: Option<ValueOrFilterGroup>,
   expand: Option<Vec<&'a str>>,
+  fields: Option<Vec<&'a str>>,
   count: bool,
+  snippet: Option<&'a str>,
+  highlight: Option<&'a str>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -368,6 +435,14 @@ pub enum CompareOp {
   StWithin,
   StIntersects,
   StContains,
+  /// Full-text search against the table's FTS5 index.
+  Match,
+  /// Value is one of a comma-separated list.
+  In,
+  /// Value is none of a comma-separated list.
+  NotIn,
+  /// Value is within the inclusive range given as "<lower>,<upper>".
+  Between,
 }
 
 impl CompareOp {
@@ -384,6 +459,10 @@ impl CompareOp {
       Self::StWithin => "@within",
       Self::StIntersects => "@intersects",
       Self::StContains => "@contains",
+      Self::Match => "$match",
+      Self::In => "$in",
+      Self::NotIn => "$nin",
+      Self::Between => "$between",
     };
   }
 }
@@ -403,6 +482,47 @@ impl Filter {
       value: value.into(),
     };
   }
+
+  /// Matches records where `column` equals any of the given values.
+  ///
+  /// NOTE: Values must not contain commas.
+  pub fn is_in(
+    column: impl Into<String>,
+    values: impl IntoIterator<Item = impl Into<String>>,
+  ) -> Self {
+    return Self::new(column, CompareOp::In, join_values(values));
+  }
+
+  /// Matches records where `column` equals none of the given values.
+  ///
+  /// NOTE: Values must not contain commas.
+  pub fn not_in(
+    column: impl Into<String>,
+    values: impl IntoIterator<Item = impl Into<String>>,
+  ) -> Self {
+    return Self::new(column, CompareOp::NotIn, join_values(values));
+  }
+
+  /// Matches records where `column` is within the inclusive range `[lower, upper]`.
+  pub fn between(
+    column: impl Into<String>,
+    lower: impl Into<String>,
+    upper: impl Into<String>,
+  ) -> Self {
+    return Self::new(
+      column,
+      CompareOp::Between,
+      join_values([lower.into(), upper.into()]),
+    );
+  }
+}
+
+fn join_values(values: impl IntoIterator<Item = impl Into<String>>) -> String {
+  return values
+    .into_iter()
+    .map(|v| v.into())
+    .collect::<Vec<String>>()
+    .join(",");
 }
 
 impl From<Filter> for ValueOrFilterGroup {
@@ -458,10 +578,29 @@ impl<'a> ListArguments<'a> {
     return self;
   }
 
+  /// Only return the given columns. Columns of expanded foreign records can be selected using
+  /// `<fk>.<column>`.
+  pub fn with_fields(mut self, fields: impl AsRef<[&'a str]>) -> Self {
+    self.fields = Some(fields.as_ref().to_vec());
+    return self;
+  }
+
   pub fn with_count(mut self, count: bool) -> Self {
     self.count = count;
     return self;
   }
+
+  /// Returns a `_snippet` of the given column for `$match` full-text searches.
+  pub fn with_snippet(mut self, column: &'a str) -> Self {
+    self.snippet = Some(column);
+    return self;
+  }
+
+  /// Returns the given column with `_highlight`ed matches for `$match` full-text searches.
+  pub fn with_highlight(mut self, column: &'a str) -> Self {
+    self.highlight = Some(column);
+    return self;
+  }
 }
 
 impl RecordApi {
@@ -496,10 +635,27 @@ impl RecordApi {
       params.push((Cow::Borrowed("expand"), Cow::Owned(to_list(&expand))));
     }
 
+    if let Some(fields) = args.fields
+      && !fields.is_empty()
+    {
+      params.push((Cow::Borrowed("fields"), Cow::Owned(to_list(&fields))));
+    }
+
     if args.count {
       params.push((Cow::Borrowed("count"), Cow::Borrowed("true")));
     }
 
+    if let Some(snippet) = args.snippet {
+      params.push((Cow::Borrowed("snippet"), Cow::Owned(snippet.to_string())));
+    }
+
+    if let Some(highlight) = args.highlight {
+      params.push((
+        Cow::Borrowed("highlight"),
+        Cow::Owned(highlight.to_string()),
+      ));
+    }
+
     fn traverse_filters(params: &mut Vec<Param>, path: String, filter: ValueOrFilterGroup) {
       match filter {
         ValueOrFilterGroup::Filter(filter) => {
@@ -554,9 +710,13 @@ impl RecordApi {
     &self,
     args: impl ReadArgumentsTrait<'a>,
   ) -> Result<T, Error> {
-    let expand = args
-      .expand()
-      .map(|e| vec![(Cow::Borrowed("expand"), Cow::Owned(e.join(",")))]);
+    let mut params: Vec<(Cow<'static, str>, Cow<'static, str>)> = vec![];
+    if let Some(expand) = args.expand() {
+      params.push((Cow::Borrowed("expand"), Cow::Owned(expand.join(","))));
+    }
+    if let Some(fields) = args.fields() {
+      params.push((Cow::Borrowed("fields"), Cow::Owned(fields.join(","))));
+    }
 
     let response = self
       .client
@@ -568,7 +728,7 @@ impl RecordApi {
         ),
         Method::GET,
         None,
-        expand.as_deref(),
+        Some(&params),
         /* error_for_status= */ true,
       )
       .await?;
@@ -650,6 +810,27 @@ impl RecordApi {
     &self,
     id: T,
   ) -> Result<impl Stream<Item = ChangeEvent> + use<T>, Error> {
+    return self.subscribe_impl(id, None).await;
+  }
+
+  /// Re-subscribe after a disconnect, replaying all changes following the event the `resume`
+  /// token was taken from. If changes cannot be replayed, the stream will yield an
+  /// `EventErrorStatus::Resync` error.
+  pub async fn resume<'a, T: RecordId<'a>>(
+    &self,
+    id: T,
+    resume: &str,
+  ) -> Result<impl Stream<Item = ChangeEvent> + use<T>, Error> {
+    return self.subscribe_impl(id, Some(resume)).await;
+  }
+
+  async fn subscribe_impl<'a, T: RecordId<'a>>(
+    &self,
+    id: T,
+    resume: Option<&str>,
+  ) -> Result<impl Stream<Item = ChangeEvent> + use<T>, Error> {
+    let params = resume.map(|resume| [(Cow::Borrowed("resume"), Cow::Owned(resume.to_string()))]);
+
     // TODO: Might have to add HeaderValue::from_static("text/event-stream").
     let response = self
       .client
@@ -661,7 +842,7 @@ impl RecordApi {
         ),
         Method::GET,
         None,
-        None,
+        params.as_ref().map(|p| p.as_slice()),
         /* error_for_status= */ true,
       )
       .await?;
@@ -674,6 +855,15 @@ impl RecordApi {
           // for better error handling here.
           if let Ok(event) = event_or {
             return ChangeEvent::from_str(&event.data)
+              .map(|ev| {
+                if ev.resume.is_none() && !event.id.is_empty() {
+                  return ChangeEvent {
+                    resume: Some(event.id),
+                    ..ev
+                  };
+                }
+                return ev;
+              })
               .map_err(|err| {
                 warn!("Failed to parse change event: {}", event.data);
                 return err;
@@ -873,6 +1063,7 @@ impl Client {
       return Some(User {
         sub: state.1.sub.clone(),
         email: state.1.email.clone(),
+        roles: state.1.roles.clone(),
       });
     }
     return None;
@@ -1018,6 +1209,254 @@ impl Client {
     return Ok(());
   }
 
+  /// Starts registering a new passkey for the currently logged-in user.
+  pub async fn webauthn_register_start(&self) -> Result<WebAuthnChallenge, Error> {
+    let response = self
+      .state
+      .fetch(
+        &format!("/{AUTH_API}/webauthn/register/start"),
+        Method::POST,
+        None,
+        None,
+        /* error_for_status= */ true,
+      )
+      .await?;
+
+    return json(response).await;
+  }
+
+  /// Completes a passkey registration with the JSON-encoded `PublicKeyCredential` created by the
+  /// authenticator.
+  pub async fn webauthn_register_finish(
+    &self,
+    challenge_token: &str,
+    credential: &serde_json::Value,
+    name: Option<&str>,
+  ) -> Result<(), Error> {
+    #[derive(Serialize)]
+    struct Request<'a> {
+      challenge_token: &'a str,
+      credential: &'a serde_json::Value,
+      name: Option<&'a str>,
+    }
+
+    self
+      .state
+      .fetch(
+        &format!("/{AUTH_API}/webauthn/register/finish"),
+        Method::POST,
+        Some(
+          serde_json::to_vec(&Request {
+            challenge_token,
+            credential,
+            name,
+          })
+          .map_err(Error::RecordSerialization)?,
+        ),
+        None,
+        /* error_for_status= */ true,
+      )
+      .await?;
+
+    return Ok(());
+  }
+
+  /// Starts logging in with a passkey. Without `email` the authenticator will offer discoverable
+  /// credentials.
+  pub async fn webauthn_login_start(
+    &self,
+    email: Option<&str>,
+  ) -> Result<WebAuthnChallenge, Error> {
+    #[derive(Serialize)]
+    struct Request<'a> {
+      email: Option<&'a str>,
+    }
+
+    let response = self
+      .state
+      .fetch(
+        &format!("/{AUTH_API}/webauthn/login/start"),
+        Method::POST,
+        Some(serde_json::to_vec(&Request { email }).map_err(Error::RecordSerialization)?),
+        None,
+        /* error_for_status= */ true,
+      )
+      .await?;
+
+    return json(response).await;
+  }
+
+  /// Completes a passkey login with the JSON-encoded `PublicKeyCredential` asserted by the
+  /// authenticator. Like [Self::login], returns a token if a second factor is required.
+  pub async fn webauthn_login_finish(
+    &self,
+    challenge_token: &str,
+    credential: &serde_json::Value,
+  ) -> Result<Option<MultiFactorAuthToken>, Error> {
+    #[derive(Serialize)]
+    struct Request<'a> {
+      challenge_token: &'a str,
+      credential: &'a serde_json::Value,
+    }
+
+    let response = self
+      .state
+      .fetch(
+        &format!("/{AUTH_API}/webauthn/login/finish"),
+        Method::POST,
+        Some(
+          serde_json::to_vec(&Request {
+            challenge_token,
+            credential,
+          })
+          .map_err(Error::RecordSerialization)?,
+        ),
+        None,
+        /* error_for_status= */ false,
+      )
+      .await?;
+
+    if response.status() == StatusCode::FORBIDDEN {
+      let mfa_token: MultiFactorAuthToken = json(response).await?;
+      return Ok(Some(mfa_token));
+    }
+
+    let tokens: Tokens = json(error_for_status_unpack(response)?).await?;
+    self.update_tokens(Some(&tokens));
+
+    return Ok(None);
+  }
+
+  /// Starts a passkey ceremony to provide the second factor for a pending login.
+  pub async fn webauthn_second_start(
+    &self,
+    mfa_token: &MultiFactorAuthToken,
+  ) -> Result<WebAuthnChallenge, Error> {
+    let response = self
+      .state
+      .fetch(
+        &format!("/{AUTH_API}/webauthn/mfa/start"),
+        Method::POST,
+        Some(serde_json::to_vec(mfa_token).map_err(Error::RecordSerialization)?),
+        None,
+        /* error_for_status= */ true,
+      )
+      .await?;
+
+    return json(response).await;
+  }
+
+  /// Completes a pending login using a passkey as second factor.
+  pub async fn login_second_webauthn(
+    &self,
+    mfa_token: &MultiFactorAuthToken,
+    challenge_token: &str,
+    credential: &serde_json::Value,
+  ) -> Result<(), Error> {
+    #[derive(Serialize)]
+    struct Request<'a> {
+      mfa_token: &'a str,
+      challenge_token: &'a str,
+      credential: &'a serde_json::Value,
+    }
+
+    let response = self
+      .state
+      .fetch(
+        &format!("/{AUTH_API}/webauthn/mfa/finish"),
+        Method::POST,
+        Some(
+          serde_json::to_vec(&Request {
+            mfa_token: &mfa_token.mfa_token,
+            challenge_token,
+            credential,
+          })
+          .map_err(Error::RecordSerialization)?,
+        ),
+        None,
+        /* error_for_status= */ true,
+      )
+      .await?;
+
+    let tokens: Tokens = json(error_for_status_unpack(response)?).await?;
+    self.update_tokens(Some(&tokens));
+
+    return Ok(());
+  }
+
+  /// Lists the passkeys of the currently logged-in user.
+  pub async fn webauthn_credentials(&self) -> Result<Vec<WebAuthnCredential>, Error> {
+    #[derive(Deserialize)]
+    struct Response {
+      credentials: Vec<WebAuthnCredential>,
+    }
+
+    let response = self
+      .state
+      .fetch(
+        &format!("/{AUTH_API}/webauthn/credentials"),
+        Method::GET,
+        None,
+        None,
+        /* error_for_status= */ true,
+      )
+      .await?;
+
+    return Ok(json::<Response>(response).await?.credentials);
+  }
+
+  pub async fn delete_webauthn_credential(&self, id: &str) -> Result<(), Error> {
+    self
+      .state
+      .fetch(
+        &format!("/{AUTH_API}/webauthn/credentials/{id}"),
+        Method::DELETE,
+        None,
+        None,
+        /* error_for_status= */ true,
+      )
+      .await?;
+
+    return Ok(());
+  }
+
+  /// Lists the sessions, i.e. signed-in devices, of the currently logged-in user.
+  pub async fn sessions(&self) -> Result<Vec<Session>, Error> {
+    #[derive(Deserialize)]
+    struct Response {
+      sessions: Vec<Session>,
+    }
+
+    let response = self
+      .state
+      .fetch(
+        &format!("/{AUTH_API}/sessions"),
+        Method::GET,
+        None,
+        None,
+        /* error_for_status= */ true,
+      )
+      .await?;
+
+    return Ok(json::<Response>(response).await?.sessions);
+  }
+
+  /// Revokes one of the currently logged-in user's sessions, e.g. to sign out a lost device.
+  pub async fn revoke_session(&self, id: i64) -> Result<(), Error> {
+    self
+      .state
+      .fetch(
+        &format!("/{AUTH_API}/sessions/{id}"),
+        Method::DELETE,
+        None,
+        None,
+        /* error_for_status= */ true,
+      )
+      .await?;
+
+    return Ok(());
+  }
+
   pub async fn logout(&self) -> Result<(), Error> {
     #[derive(Serialize)]
     struct LogoutRequest {
@@ -192,6 +192,9 @@ async fn login_test() {
   let user = client.user().unwrap();
   assert_eq!(user.email, "admin@localhost");
 
+  let sessions = client.sessions().await.unwrap();
+  assert_eq!(sessions.iter().filter(|s| s.current).count(), 1);
+
   client.refresh().await.unwrap();
 
   client.logout().await.unwrap();
@@ -337,6 +340,27 @@ async fn records_test() {
     );
   }
 
+  {
+    // List messages by membership.
+    let records: ListResponse<SimpleStrict> = api
+      .list(
+        ListArguments::new()
+          .with_order(["+text_not_null"])
+          .with_filters(Filter::is_in("text_not_null", messages.clone())),
+      )
+      .await
+      .unwrap();
+
+    assert_eq!(
+      messages,
+      records
+        .records
+        .into_iter()
+        .map(|s| s.text_not_null)
+        .collect::<Vec<_>>()
+    );
+  }
+
   {
     // Read
     let record: SimpleStrict = api.read(&ids[0]).await.unwrap();
@@ -390,6 +414,30 @@ async fn expand_foreign_records_test() {
     assert_eq!("first post", comment.post.data.as_ref().unwrap().title)
   }
 
+  {
+    let comment: serde_json::Value = api
+      .read(
+        ReadArguments::new(1)
+          .with_expand(["post"])
+          .with_fields(["body", "post.title"]),
+      )
+      .await
+      .unwrap();
+    assert_eq!(comment["body"], "first comment");
+    assert_eq!(comment["post"]["data"]["title"], "first post");
+    assert!(comment.get("id").is_none());
+    assert!(comment.get("author").is_none());
+
+    let comments: ListResponse<serde_json::Value> = api
+      .list(ListArguments::new().with_order(["-id"]).with_fields(["id"]))
+      .await
+      .unwrap();
+    assert!(!comments.records.is_empty());
+    for comment in &comments.records {
+      assert_eq!(1, comment.as_object().unwrap().len());
+    }
+  }
+
   {
     let comments: ListResponse<Comment> = api
       .list(
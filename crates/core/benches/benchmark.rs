@@ -3,7 +3,6 @@
 use criterion::{Bencher, Criterion, Throughput, criterion_group, criterion_main};
 
 use axum::body::Body;
-use axum::extract::{Json, State};
 use axum::http::{self, Request};
 use base64::prelude::*;
 use hyper::StatusCode;
@@ -11,7 +10,7 @@ use std::time::{Duration, Instant};
 use tower::{Service, ServiceExt};
 
 use trailbase::AppState;
-use trailbase::api::{CreateUserRequest, create_user_handler, login_with_password_for_test};
+use trailbase::api::{CreateUserRequest, create_user, login_with_password_for_test};
 use trailbase::config::proto::{PermissionFlag, RecordApiConfig};
 use trailbase::constants::RECORD_API_PATH;
 use trailbase::{DataDir, Server, ServerOptions};
@@ -137,14 +136,14 @@ async fn setup_app() -> Result<Setup, anyhow::Error> {
   .await?;
 
   let email = "user_x@bar.com";
-  let user_x = create_user_handler(
-    State(app.state.clone()),
-    Json(CreateUserRequest {
+  let user_x = create_user(
+    &app.state,
+    CreateUserRequest {
       email: email.to_string(),
       password: password.to_string(),
       verified: true,
       admin: false,
-    }),
+    },
   )
   .await?
   .id
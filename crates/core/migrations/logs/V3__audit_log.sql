@@ -0,0 +1,40 @@
+-- Hash-chained audit trail of administrative and security-relevant actions.
+CREATE TABLE IF NOT EXISTS _audit_log (
+  id                           INTEGER PRIMARY KEY,
+
+  -- Timestamp in seconds with fractional millisecond resolution.
+  created                      REAL NOT NULL,
+
+  -- Id of the acting user if any. NULL for the command line or failed logins.
+  -- Like for `_logs.user_id`, there's no foreign key since users live in a
+  -- separate database.
+  actor_id                     BLOB,
+  -- Human-readable actor, e.g. the user's email address or "cli".
+  actor                        TEXT NOT NULL,
+  -- Action, e.g. "config.update" or "auth.login_failed".
+  action                       TEXT NOT NULL,
+  -- What was acted upon, e.g. a table name or a user's email address.
+  target                       TEXT,
+  -- JSON-encoded change details.
+  diff                         TEXT,
+  client_ip                    TEXT DEFAULT '' NOT NULL,
+
+  -- HMAC-SHA256 of the previous entry's hash and of this entry's content keyed
+  -- with a server-side secret.
+  prev_hash                    BLOB NOT NULL,
+  hash                         BLOB NOT NULL
+) STRICT;
+
+CREATE INDEX IF NOT EXISTS __audit_log__created_index ON _audit_log (created);
+CREATE INDEX IF NOT EXISTS __audit_log__action_index ON _audit_log (action);
+CREATE INDEX IF NOT EXISTS __audit_log__actor_index ON _audit_log (actor);
+
+-- Last entry dropped due to retention, which anchors the remaining chain. There's
+-- at most one checkpoint.
+CREATE TABLE IF NOT EXISTS _audit_log_checkpoint (
+  -- Id and hash of the dropped entry.
+  id                           INTEGER PRIMARY KEY NOT NULL,
+  hash                         BLOB NOT NULL,
+  -- HMAC-SHA256 of the above keyed with the same secret as the chain.
+  mac                          BLOB NOT NULL
+) STRICT;
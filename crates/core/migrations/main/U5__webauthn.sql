@@ -0,0 +1,17 @@
+-- WebAuthn credentials, i.e. passkeys, registered by users.
+CREATE TABLE IF NOT EXISTS _webauthn_credential (
+  -- Credential id as chosen by the authenticator.
+  id                           BLOB PRIMARY KEY NOT NULL,
+  user                         BLOB NOT NULL REFERENCES _user(id) ON DELETE CASCADE,
+  -- User-provided label, e.g. "YubiKey" or "Laptop".
+  name                         TEXT,
+  -- COSE-encoded public key.
+  public_key                   BLOB NOT NULL,
+  -- Signature counter reported by the authenticator to detect cloned authenticators.
+  sign_count                   INTEGER DEFAULT 0 NOT NULL,
+
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL,
+  last_used                    INTEGER
+) STRICT;
+
+CREATE INDEX IF NOT EXISTS __webauthn_credential__user_index ON _webauthn_credential (user);
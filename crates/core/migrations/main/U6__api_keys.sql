@@ -0,0 +1,19 @@
+-- Long-lived API keys for machine clients, e.g. backend workers or cron jobs,
+-- acting on behalf of the owning (service account) user.
+CREATE TABLE IF NOT EXISTS _api_key (
+  id                           BLOB PRIMARY KEY NOT NULL CHECK(is_uuid(id)) DEFAULT (uuid_v4()),
+  user                         BLOB NOT NULL REFERENCES _user(id) ON DELETE CASCADE,
+  name                         TEXT NOT NULL,
+  -- SHA256 hash of the key. The key itself is only revealed once when minted.
+  key_hash                     BLOB NOT NULL,
+  -- JSON array of Record API names the key is restricted to. NULL means all APIs.
+  record_apis                  TEXT CHECK(record_apis IS NULL OR json_valid(record_apis)),
+  -- Bitmask of permitted `PermissionFlag`s, e.g. 2 for read-only access.
+  permissions                  INTEGER NOT NULL,
+  expires                      INTEGER,
+
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL
+) STRICT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS __api_key__key_hash_index ON _api_key (key_hash);
+CREATE INDEX IF NOT EXISTS __api_key__user_index ON _api_key (user);
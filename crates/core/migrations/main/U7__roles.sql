@@ -0,0 +1,19 @@
+-- Roles, i.e. named groups of users, which are carried in auth tokens and can
+-- be referenced by record API ACLs and access rules.
+CREATE TABLE IF NOT EXISTS _role (
+  name                         TEXT PRIMARY KEY NOT NULL CHECK(name <> ''),
+  description                  TEXT,
+
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL
+) STRICT;
+
+CREATE TABLE IF NOT EXISTS _user_role (
+  user                         BLOB NOT NULL REFERENCES _user(id) ON DELETE CASCADE,
+  role                         TEXT NOT NULL REFERENCES _role(name) ON DELETE CASCADE ON UPDATE CASCADE,
+
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL,
+
+  PRIMARY KEY (user, role)
+) STRICT;
+
+CREATE INDEX IF NOT EXISTS __user_role__role_index ON _user_role (role);
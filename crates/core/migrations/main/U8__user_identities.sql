@@ -0,0 +1,24 @@
+-- External OAuth identities linked to users. A user may have multiple linked
+-- identities, e.g. GitHub and Google, in addition to a password or passkeys.
+CREATE TABLE IF NOT EXISTS _user_identity (
+  user                         BLOB NOT NULL REFERENCES _user(id) ON DELETE CASCADE,
+  -- provider_id maps to proto.config.OAuthProviderId enum.
+  provider_id                  INTEGER NOT NULL,
+  -- The external provider's id for the user.
+  provider_user_id             TEXT NOT NULL,
+  -- E-mail address and avatar as reported by the provider when last linked.
+  email                        TEXT,
+  avatar_url                   TEXT,
+
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL,
+
+  PRIMARY KEY (provider_id, provider_user_id)
+) STRICT;
+
+CREATE INDEX IF NOT EXISTS __user_identity__user_index ON _user_identity (user);
+
+-- Migrate existing identities. The legacy `_user.provider_*` columns are kept
+-- for backwards compatibility but `_user_identity` is authoritative.
+INSERT INTO _user_identity (user, provider_id, provider_user_id, email, avatar_url)
+  SELECT id, provider_id, provider_user_id, email, provider_avatar_url FROM _user
+  WHERE provider_id > 0 AND provider_user_id IS NOT NULL;
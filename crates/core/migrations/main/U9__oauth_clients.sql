@@ -0,0 +1,15 @@
+-- Client applications signing in users via TrailBase acting as OAuth2/OpenID
+-- Connect provider.
+CREATE TABLE IF NOT EXISTS _oauth_client (
+  -- The public `client_id`.
+  id                           TEXT PRIMARY KEY NOT NULL,
+  name                         TEXT NOT NULL,
+  -- SHA256 hash of the client secret, which is only revealed once when the
+  -- client is registered. NULL for public clients, e.g. SPAs or mobile apps,
+  -- which solely rely on PKCE.
+  secret_hash                  BLOB,
+  -- JSON array of permitted redirect URIs, which have to match exactly.
+  redirect_uris                TEXT NOT NULL CHECK(json_valid(redirect_uris)),
+
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL
+) STRICT;
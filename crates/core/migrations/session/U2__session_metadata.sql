@@ -0,0 +1,6 @@
+-- Metadata to let users and admins tell sessions apart, e.g. when listing
+-- signed-in devices.
+ALTER TABLE _session ADD COLUMN user_agent TEXT;
+ALTER TABLE _session ADD COLUMN client_ip TEXT;
+-- Updated whenever the session's refresh token is used to mint a new auth token.
+ALTER TABLE _session ADD COLUMN last_used INTEGER;
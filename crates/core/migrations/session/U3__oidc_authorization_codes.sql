@@ -0,0 +1,6 @@
+-- Authorization codes issued to registered OAuth clients, when TrailBase acts
+-- as OpenID Connect provider. NULL for TrailBase's own auth-code flow.
+ALTER TABLE _authorization_code ADD COLUMN client_id TEXT;
+ALTER TABLE _authorization_code ADD COLUMN redirect_uri TEXT;
+ALTER TABLE _authorization_code ADD COLUMN scope TEXT;
+ALTER TABLE _authorization_code ADD COLUMN nonce TEXT;
@@ -0,0 +1,28 @@
+--
+-- Files uploaded directly to the object store using presigned URLs. Entries
+-- are kept after completion, since the presigned URL remains valid, and are
+-- cleaned up periodically together with their staged objects once both the
+-- URL and the entry itself have expired or been consumed.
+--
+CREATE TABLE _pending_upload (
+  id                           INTEGER PRIMARY KEY NOT NULL,
+  -- The file's UUID and thus object store path.
+  file_id                      TEXT NOT NULL,
+  record_api                   TEXT NOT NULL,
+  record_id                    TEXT NOT NULL,
+  column_name                  TEXT NOT NULL,
+  user                         BLOB,
+  original_filename            TEXT,
+  content_type                 TEXT,
+  size                         INTEGER NOT NULL,
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL,
+  expires                      INTEGER NOT NULL,
+  -- Expiration of the presigned URL, until which the staged object may change.
+  url_expires                  INTEGER NOT NULL,
+  -- Whether the upload has been completed and assigned under a new id.
+  consumed                     INTEGER DEFAULT FALSE NOT NULL
+) STRICT;
+
+CREATE UNIQUE INDEX __pending_upload__file_id ON _pending_upload (file_id);
+CREATE INDEX __pending_upload__expires ON _pending_upload (expires);
+CREATE INDEX __pending_upload__url_expires ON _pending_upload (url_expires);
@@ -0,0 +1,23 @@
+--
+-- Resumable uploads using the tus protocol. Received chunks are kept on the
+-- local file system until the upload is complete. Abandoned uploads are
+-- cleaned up periodically.
+--
+CREATE TABLE _tus_upload (
+  id                           INTEGER PRIMARY KEY NOT NULL,
+  -- The file's UUID and thus object store path once completed.
+  upload_id                    TEXT NOT NULL,
+  record_api                   TEXT NOT NULL,
+  record_id                    TEXT NOT NULL,
+  column_name                  TEXT NOT NULL,
+  user                         BLOB,
+  original_filename            TEXT,
+  content_type                 TEXT,
+  upload_length                INTEGER NOT NULL,
+  upload_offset                INTEGER DEFAULT 0 NOT NULL,
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL,
+  expires                      INTEGER NOT NULL
+) STRICT;
+
+CREATE UNIQUE INDEX __tus_upload__upload_id ON _tus_upload (upload_id);
+CREATE INDEX __tus_upload__expires ON _tus_upload (expires);
@@ -0,0 +1,15 @@
+--
+-- Outstanding WebAuthn challenges. Challenges are consumed when a ceremony is
+-- finished to prevent replaying signed assertions. Expired challenges are
+-- cleaned up periodically.
+--
+CREATE TABLE _webauthn_challenge (
+  id                           INTEGER PRIMARY KEY NOT NULL,
+  -- Url-safe Base64 encoded random challenge.
+  challenge                    TEXT NOT NULL,
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL,
+  expires                      INTEGER NOT NULL
+) STRICT;
+
+CREATE UNIQUE INDEX __webauthn_challenge__challenge ON _webauthn_challenge (challenge);
+CREATE INDEX __webauthn_challenge__expires ON _webauthn_challenge (expires);
@@ -0,0 +1,136 @@
+use axum::{
+  Json,
+  extract::{Query, State},
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
+use crate::auth::api_key::{ApiKey, NewApiKey, list_api_keys, mint_api_key, revoke_api_key};
+use crate::auth::util::get_user_by_id;
+use crate::config::proto::PermissionFlag;
+
+#[derive(Debug, Default, Deserialize, TS)]
+#[ts(export)]
+pub struct ListApiKeysQuery {
+  /// Only list keys of the given user.
+  user: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct ListApiKeysResponse {
+  keys: Vec<ApiKey>,
+}
+
+pub async fn list_api_keys_handler(
+  State(state): State<AppState>,
+  Query(query): Query<ListApiKeysQuery>,
+) -> Result<Json<ListApiKeysResponse>, Error> {
+  let keys = list_api_keys(state.user_conn(), query.user.as_ref()).await?;
+  return Ok(Json(ListApiKeysResponse { keys }));
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct CreateApiKeyRequest {
+  /// Id of the (service account) user the key acts on behalf of.
+  user: Uuid,
+  /// Human-readable name, e.g. describing the client.
+  name: String,
+  /// Record APIs the key is restricted to. All if absent.
+  record_apis: Option<Vec<String>>,
+  /// Permitted operations, e.g. ["READ", "CREATE"].
+  permissions: Vec<String>,
+  /// Optional expiration as UNIX timestamp in seconds.
+  expires: Option<i64>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct CreateApiKeyResponse {
+  id: Uuid,
+  /// The secret key. It is only returned once and cannot be recovered later.
+  key: String,
+}
+
+pub async fn create_api_key_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<CreateApiKeyRequest>,
+) -> Result<Json<CreateApiKeyResponse>, Error> {
+  let db_user = get_user_by_id(state.user_conn(), &request.user).await?;
+
+  let permissions = request
+    .permissions
+    .iter()
+    .map(|p| {
+      PermissionFlag::from_str_name(&p.to_uppercase())
+        .ok_or_else(|| Error::BadRequest(format!("invalid permission: {p}").into()))
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let expires = request
+    .expires
+    .map(|ts| {
+      chrono::DateTime::from_timestamp(ts, 0)
+        .ok_or_else(|| Error::BadRequest("invalid expiration".into()))
+    })
+    .transpose()?;
+
+  let diff = serde_json::json!({
+    "name": &request.name,
+    "user": &db_user.email,
+    "record_apis": &request.record_apis,
+    "permissions": &request.permissions,
+    "expires": request.expires,
+  });
+
+  let (id, key) = mint_api_key(
+    state.user_conn(),
+    &db_user.uuid(),
+    NewApiKey {
+      name: request.name,
+      record_apis: request.record_apis,
+      permissions,
+      expires,
+    },
+  )
+  .await?;
+
+  audit(
+    &state,
+    AuditEvent::new(Actor::user(&user), AuditAction::ApiKeyCreate)
+      .target(id.to_string())
+      .diff(diff),
+  )
+  .await;
+
+  return Ok(Json(CreateApiKeyResponse { id, key }));
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct DeleteApiKeyRequest {
+  id: Uuid,
+}
+
+pub async fn delete_api_key_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<DeleteApiKeyRequest>,
+) -> Result<(), Error> {
+  revoke_api_key(state.user_conn(), &request.id).await?;
+
+  audit(
+    &state,
+    AuditEvent::new(Actor::user(&user), AuditAction::ApiKeyRevoke).target(request.id.to_string()),
+  )
+  .await;
+
+  return Ok(());
+}
@@ -0,0 +1,258 @@
+use axum::{
+  Json,
+  extract::{RawQuery, State},
+};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use trailbase_qs::{Order, OrderPrecedent, Query};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::audit::{AuditLogVerification, verify_audit_log};
+use crate::constants::{AUDIT_LOG_TABLE, LOGS_TABLE_ID_COLUMN};
+use crate::listing::{WhereClause, build_filter_where_clause, limit_or_default};
+use crate::schema_metadata::{TableMetadata, lookup_and_parse_table_schema};
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct ListAuditLogResponse {
+  total_row_count: i64,
+  cursor: Option<String>,
+  entries: Vec<AuditLogEntryJson>,
+}
+
+#[derive(Debug, Serialize, TS)]
+pub struct AuditLogEntryJson {
+  pub id: i64,
+  pub created: f64,
+
+  pub actor_id: Option<String>,
+  pub actor: String,
+  pub action: String,
+  pub target: Option<String>,
+  /// JSON-encoded change details.
+  pub diff: Option<String>,
+  pub client_ip: String,
+}
+
+/// Lists audit log entries, most recent first. Supports the same filters as the logs listing,
+/// e.g. `?filter[action]=config.update`.
+pub async fn list_audit_log_handler(
+  State(state): State<AppState>,
+  RawQuery(raw_url_query): RawQuery,
+) -> Result<Json<ListAuditLogResponse>, Error> {
+  let conn = state.logs_conn();
+
+  let Query {
+    limit,
+    cursor,
+    order,
+    filter: filter_params,
+    offset,
+    ..
+  } = raw_url_query
+    .as_ref()
+    .map_or_else(|| Ok(Query::default()), |query| Query::parse(query))
+    .map_err(|err| {
+      return Error::BadRequest(format!("Invalid query '{err}': {raw_url_query:?}").into());
+    })?;
+
+  let filter_where_clause = {
+    let table = lookup_and_parse_table_schema(conn, AUDIT_LOG_TABLE, None).await?;
+    let table_metadata = TableMetadata::new(
+      &trailbase_extension::jsonschema::JsonSchemaRegistry::from_schemas(vec![]),
+      table.clone(),
+      &[table],
+    )?;
+
+    build_filter_where_clause(TABLE_ALIAS, &table_metadata.column_metadata, filter_params)?
+  };
+
+  let total_row_count: i64 = conn
+    .read_query_row_get(
+      format!(
+        "SELECT COUNT(*) FROM {AUDIT_LOG_TABLE} AS {TABLE_ALIAS} WHERE {where_clause}",
+        where_clause = filter_where_clause.clause
+      ),
+      filter_where_clause.params.clone(),
+      0,
+    )
+    .await?
+    .unwrap_or(-1);
+
+  lazy_static! {
+    static ref DEFAULT_ORDERING: Order = Order {
+      columns: vec![(LOGS_TABLE_ID_COLUMN.to_string(), OrderPrecedent::Descending)],
+    };
+  }
+
+  let supports_cursor = order.is_none();
+  let cursor = if supports_cursor && let Some(cursor) = cursor {
+    Some(
+      cursor
+        .parse::<i64>()
+        .map_err(|err| Error::BadRequest(err.into()))?,
+    )
+  } else {
+    None
+  };
+
+  let mut entries = fetch_entries(
+    conn,
+    filter_where_clause,
+    cursor,
+    offset,
+    order.as_ref().unwrap_or_else(|| &DEFAULT_ORDERING),
+    limit_or_default(limit, None).map_err(|err| Error::BadRequest(err.into()))?,
+  )
+  .await?;
+
+  if state.demo_mode() {
+    for entry in &mut entries {
+      if !entry.client_ip.is_empty() {
+        entry.client_ip = "<demo>".to_string();
+      }
+    }
+  }
+
+  let next_cursor = if supports_cursor {
+    entries.last().map(|entry| entry.id.to_string())
+  } else {
+    None
+  };
+
+  return Ok(Json(ListAuditLogResponse {
+    total_row_count,
+    cursor: next_cursor,
+    entries: entries
+      .into_iter()
+      .map(|entry| entry.into())
+      .collect::<Vec<AuditLogEntryJson>>(),
+  }));
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct VerifyAuditLogResponse {
+  /// Number of entries, whose hashes checked out.
+  entries: i64,
+  valid: bool,
+  /// Id of the first entry that has been tampered with, if any.
+  first_invalid_id: Option<i64>,
+}
+
+/// Checks the audit log's hash chain for signs of tampering.
+pub async fn verify_audit_log_handler(
+  State(state): State<AppState>,
+) -> Result<Json<VerifyAuditLogResponse>, Error> {
+  let AuditLogVerification {
+    entries,
+    first_invalid_id,
+  } = verify_audit_log(state.audit_log()).await?;
+
+  return Ok(Json(VerifyAuditLogResponse {
+    entries,
+    valid: first_invalid_id.is_none(),
+    first_invalid_id,
+  }));
+}
+
+async fn fetch_entries(
+  conn: &trailbase_sqlite::Connection,
+  filter_where_clause: WhereClause,
+  cursor: Option<i64>,
+  offset: Option<usize>,
+  order: &Order,
+  limit: usize,
+) -> Result<Vec<AuditLogEntry>, Error> {
+  let mut params = filter_where_clause.params;
+  let mut where_clause = filter_where_clause.clause;
+  params.push((
+    Cow::Borrowed(":limit"),
+    trailbase_sqlite::Value::Integer(limit as i64),
+  ));
+  params.push((
+    Cow::Borrowed(":offset"),
+    trailbase_sqlite::Value::Integer(offset.map_or(0, |o| o.try_into().unwrap_or(0))),
+  ));
+
+  if let Some(cursor) = cursor {
+    params.push((
+      Cow::Borrowed(":cursor"),
+      trailbase_sqlite::Value::Integer(cursor),
+    ));
+    where_clause = format!("{where_clause} AND {TABLE_ALIAS}.id < :cursor",);
+  }
+
+  let order_clause = order
+    .columns
+    .iter()
+    .map(|(col, ord)| {
+      format!(
+        "{TABLE_ALIAS}.{col} {}",
+        match ord {
+          OrderPrecedent::Descending => "DESC",
+          OrderPrecedent::Ascending => "ASC",
+        }
+      )
+    })
+    .collect::<Vec<_>>()
+    .join(", ");
+
+  let sql_query = format!(
+    r#"
+      SELECT
+        {TABLE_ALIAS}.id, {TABLE_ALIAS}.created, {TABLE_ALIAS}.actor_id, {TABLE_ALIAS}.actor,
+        {TABLE_ALIAS}.action, {TABLE_ALIAS}.target, {TABLE_ALIAS}.diff, {TABLE_ALIAS}.client_ip
+      FROM
+        {AUDIT_LOG_TABLE} AS {TABLE_ALIAS}
+      WHERE
+        {where_clause}
+      ORDER BY
+        {order_clause}
+      LIMIT :limit
+      OFFSET :offset
+    "#,
+  );
+
+  return Ok(
+    conn
+      .read_query_values::<AuditLogEntry>(sql_query, params)
+      .await?,
+  );
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AuditLogEntry {
+  id: i64,
+  created: f64,
+
+  actor_id: Option<[u8; 16]>,
+  actor: String,
+  action: String,
+  target: Option<String>,
+  diff: Option<String>,
+  client_ip: String,
+}
+
+impl From<AuditLogEntry> for AuditLogEntryJson {
+  fn from(value: AuditLogEntry) -> Self {
+    return AuditLogEntryJson {
+      id: value.id,
+      created: value.created,
+      actor_id: value
+        .actor_id
+        .map(|blob| Uuid::from_bytes(blob).to_string()),
+      actor: value.actor,
+      action: value.action,
+      target: value.target,
+      diff: value.diff,
+      client_ip: value.client_ip,
+    };
+  }
+}
+
+const TABLE_ALIAS: &str = "audit";
@@ -4,12 +4,15 @@ use axum::response::IntoResponse;
 
 use crate::admin::AdminError as Error;
 use crate::app_state::AppState;
-use crate::config::proto::{UpdateConfigRequest, Vault};
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
+use crate::config::proto::{Config, UpdateConfigRequest, Vault};
 use crate::config::{merge_vault_and_env, redact_secrets};
 use crate::extract::protobuf::Protobuf;
 
 pub async fn update_config_handler(
   State(state): State<AppState>,
+  user: User,
   Protobuf(request): Protobuf<UpdateConfigRequest>,
 ) -> Result<impl IntoResponse, Error> {
   if state.demo_mode() {
@@ -26,8 +29,36 @@ pub async fn update_config_handler(
   let (_, secrets) = redact_secrets(&current)?;
 
   let merged = merge_vault_and_env(config, Vault { secrets })?;
+  let diff = config_diff(&current, &merged)?;
 
   state.validate_and_update_config(merged, Some(hash)).await?;
 
+  audit(
+    &state,
+    AuditEvent::new(Actor::user(&user), AuditAction::ConfigUpdate).diff(diff),
+  )
+  .await;
+
   return Ok((StatusCode::OK, "Config updated"));
 }
+
+/// Line diff between the textproto representations of both configs with secrets redacted.
+fn config_diff(old: &Config, new: &Config) -> Result<serde_json::Value, Error> {
+  let old = redact_secrets(old)?.0.to_text()?;
+  let new = redact_secrets(new)?.0.to_text()?;
+
+  let mut removed: Vec<&str> = vec![];
+  let mut added: Vec<&str> = vec![];
+  for line in diff::lines(&old, &new) {
+    match line {
+      diff::Result::Left(line) => removed.push(line),
+      diff::Result::Right(line) => added.push(line),
+      diff::Result::Both(..) => {}
+    }
+  }
+
+  return Ok(serde_json::json!({
+    "removed": removed,
+    "added": added,
+  }));
+}
@@ -58,6 +58,8 @@ pub enum AdminError {
   File(#[from] crate::records::files::FileError),
   #[error("SqlValueDecode: {0}")]
   SqlValueDecode(#[from] trailbase_sqlvalue::DecodeError),
+  #[error("Audit: {0}")]
+  Audit(#[from] crate::audit::AuditError),
 }
 
 impl IntoResponse for AdminError {
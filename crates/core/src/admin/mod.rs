@@ -1,3 +1,5 @@
+mod api_key;
+mod audit;
 mod config;
 mod email;
 mod error;
@@ -6,9 +8,11 @@ mod jobs;
 mod json_schema;
 mod jwt;
 mod logs;
+mod oauth_client;
 mod oauth_providers;
 mod parse;
 mod query;
+mod role;
 pub(crate) mod rows;
 mod table;
 pub(crate) mod user;
@@ -52,6 +56,31 @@ pub fn router() -> Router<AppState> {
     .route("/user", post(user::create_user_handler))
     .route("/user", patch(user::update_user_handler))
     .route("/user", delete(user::delete_user_handler))
+    .route("/user/sessions", get(user::list_user_sessions_handler))
+    .route("/user/sessions", delete(user::revoke_user_session_handler))
+    // Role actions
+    .route("/role", get(role::list_roles_handler))
+    .route("/role", post(role::create_role_handler))
+    .route("/role", delete(role::delete_role_handler))
+    .route("/user/role", post(role::assign_role_handler))
+    .route("/user/role", delete(role::unassign_role_handler))
+    // API key actions
+    .route("/api_key", get(api_key::list_api_keys_handler))
+    .route("/api_key", post(api_key::create_api_key_handler))
+    .route("/api_key", delete(api_key::delete_api_key_handler))
+    // OAuth client actions, i.e. apps signing in users via TrailBase.
+    .route(
+      "/oauth_client",
+      get(oauth_client::list_oauth_clients_handler),
+    )
+    .route(
+      "/oauth_client",
+      post(oauth_client::create_oauth_client_handler),
+    )
+    .route(
+      "/oauth_client",
+      delete(oauth_client::delete_oauth_client_handler),
+    )
     // Schema actions
     .route("/schema", get(json_schema::list_schemas_handler))
     .route(
@@ -62,6 +91,9 @@ pub fn router() -> Router<AppState> {
     .route("/logs/list", get(logs::list_logs::list_logs_handler))
     // Stats
     .route("/logs/stats", get(logs::stats::fetch_stats_handler))
+    // Audit log
+    .route("/audit/list", get(audit::list_audit_log_handler))
+    .route("/audit/verify", get(audit::verify_audit_log_handler))
     // Query execution handler for the UI editor
     .route("/query", post(query::query_handler))
     // Parse handler for UI validation.
@@ -0,0 +1,97 @@
+use axum::{Json, extract::State};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
+use crate::auth::oidc::client::{
+  OAuthClient, delete_oauth_client, list_oauth_clients, register_oauth_client,
+};
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct ListOAuthClientsResponse {
+  clients: Vec<OAuthClient>,
+}
+
+pub async fn list_oauth_clients_handler(
+  State(state): State<AppState>,
+) -> Result<Json<ListOAuthClientsResponse>, Error> {
+  let clients = list_oauth_clients(state.user_conn()).await?;
+  return Ok(Json(ListOAuthClientsResponse { clients }));
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct CreateOAuthClientRequest {
+  /// Human-readable name shown to users on the consent screen.
+  name: String,
+  /// Exact redirect URIs the client may request.
+  redirect_uris: Vec<String>,
+  /// Public clients, e.g. SPAs or mobile apps, cannot keep a secret and solely rely on PKCE.
+  public: bool,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct CreateOAuthClientResponse {
+  /// The public `client_id`.
+  id: String,
+  /// The client secret for confidential clients. It is only returned once and cannot be
+  /// recovered later.
+  secret: Option<String>,
+}
+
+pub async fn create_oauth_client_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<CreateOAuthClientRequest>,
+) -> Result<Json<CreateOAuthClientResponse>, Error> {
+  let diff = serde_json::json!({
+    "name": &request.name,
+    "redirect_uris": &request.redirect_uris,
+    "public": request.public,
+  });
+
+  let (id, secret) = register_oauth_client(
+    state.user_conn(),
+    request.name,
+    request.redirect_uris,
+    !request.public,
+  )
+  .await?;
+
+  audit(
+    &state,
+    AuditEvent::new(Actor::user(&user), AuditAction::OAuthClientCreate)
+      .target(id.clone())
+      .diff(diff),
+  )
+  .await;
+
+  return Ok(Json(CreateOAuthClientResponse { id, secret }));
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct DeleteOAuthClientRequest {
+  id: String,
+}
+
+pub async fn delete_oauth_client_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<DeleteOAuthClientRequest>,
+) -> Result<(), Error> {
+  delete_oauth_client(state.user_conn(), &request.id).await?;
+
+  audit(
+    &state,
+    AuditEvent::new(Actor::user(&user), AuditAction::OAuthClientDelete).target(request.id),
+  )
+  .await;
+
+  return Ok(());
+}
@@ -8,6 +8,8 @@ use ts_rs::TS;
 use crate::AppState;
 use crate::admin::AdminError as Error;
 use crate::admin::util::{rows_to_columns, rows_to_sql_value_rows};
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
 use crate::connection::ConnectionEntry;
 
 #[derive(Debug, Default, Serialize, TS)]
@@ -27,6 +29,7 @@ pub struct QueryRequest {
 
 pub async fn query_handler(
   State(state): State<AppState>,
+  user: User,
   Json(request): Json<QueryRequest>,
 ) -> Result<Json<QueryResponse>, Error> {
   // Check the statements are correct before executing anything, just to be sure.
@@ -80,6 +83,12 @@ pub async fn query_handler(
       .as_ref(),
   )?;
 
+  // Read-only queries are not audited.
+  let audit_event = mutation.then(|| {
+    AuditEvent::new(Actor::user(&user), AuditAction::QueryExecute)
+      .diff(serde_json::json!({ "sql": &request.query }))
+  });
+
   let batched_rows_result = trailbase_sqlite::sqlite::execute_batch(&conn, request.query).await;
 
   // In the fallback case we always need to invalidate the cache.
@@ -88,6 +97,10 @@ pub async fn query_handler(
   }
 
   let batched_rows = batched_rows_result.map_err(|err| Error::BadRequest(err.into()))?;
+  if let Some(event) = audit_event {
+    audit(&state, event).await;
+  }
+
   if let Some(rows) = batched_rows {
     return Ok(Json(QueryResponse {
       columns: Some(rows_to_columns(&rows)),
@@ -0,0 +1,123 @@
+use axum::{
+  Json,
+  extract::{Query, State},
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
+use crate::auth::roles::{Role, assign_role, create_role, delete_role, list_roles, unassign_role};
+
+#[derive(Debug, Default, Deserialize, TS)]
+#[ts(export)]
+pub struct ListRolesQuery {
+  /// Only list roles assigned to the given user.
+  user: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct ListRolesResponse {
+  roles: Vec<Role>,
+}
+
+pub async fn list_roles_handler(
+  State(state): State<AppState>,
+  Query(query): Query<ListRolesQuery>,
+) -> Result<Json<ListRolesResponse>, Error> {
+  let roles = list_roles(state.user_conn(), query.user.as_ref()).await?;
+  return Ok(Json(ListRolesResponse { roles }));
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct CreateRoleRequest {
+  /// Unique name consisting of alphanumeric characters, "_" and "-".
+  name: String,
+  description: Option<String>,
+}
+
+pub async fn create_role_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<CreateRoleRequest>,
+) -> Result<(), Error> {
+  create_role(state.user_conn(), &request.name, request.description).await?;
+
+  audit(
+    &state,
+    AuditEvent::new(Actor::user(&user), AuditAction::RoleCreate).target(request.name),
+  )
+  .await;
+
+  return Ok(());
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct DeleteRoleRequest {
+  name: String,
+}
+
+pub async fn delete_role_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<DeleteRoleRequest>,
+) -> Result<(), Error> {
+  delete_role(state.user_conn(), &request.name).await?;
+
+  audit(
+    &state,
+    AuditEvent::new(Actor::user(&user), AuditAction::RoleDelete).target(request.name),
+  )
+  .await;
+
+  return Ok(());
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct UserRoleRequest {
+  user: Uuid,
+  role: String,
+}
+
+pub async fn assign_role_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<UserRoleRequest>,
+) -> Result<(), Error> {
+  assign_role(state.user_conn(), &request.user, &request.role).await?;
+
+  audit(
+    &state,
+    AuditEvent::new(Actor::user(&user), AuditAction::RoleAssign)
+      .target(request.user.to_string())
+      .diff(serde_json::json!({ "role": request.role })),
+  )
+  .await;
+
+  return Ok(());
+}
+
+pub async fn unassign_role_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<UserRoleRequest>,
+) -> Result<(), Error> {
+  unassign_role(state.user_conn(), &request.user, &request.role).await?;
+
+  audit(
+    &state,
+    AuditEvent::new(Actor::user(&user), AuditAction::RoleUnassign)
+      .target(request.user.to_string())
+      .diff(serde_json::json!({ "role": request.role })),
+  )
+  .await;
+
+  return Ok(());
+}
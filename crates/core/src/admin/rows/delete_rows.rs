@@ -126,6 +126,7 @@ mod tests {
   use crate::admin::rows::update_row::{UpdateRowRequest, update_row_handler};
   use crate::admin::table::{CreateTableRequest, create_table_handler};
   use crate::app_state::*;
+  use crate::auth::User;
   use crate::util::uuid_to_b64;
 
   // TODO: This full-lifecycle test should probably live outside the scope of delete_row.
@@ -145,6 +146,7 @@ mod tests {
 
     let _ = create_table_handler(
       State(state.clone()),
+      User::from_unverified(Uuid::now_v7(), "admin@localhost"),
       Json(CreateTableRequest {
         schema: Table {
           name: QualifiedName::parse(&table_name).unwrap(),
@@ -1,5 +1,6 @@
 use axum::{
   extract::{Path, Query, State},
+  http::HeaderMap,
   response::Response,
 };
 use serde::Deserialize;
@@ -26,6 +27,7 @@ pub async fn read_files_handler(
   State(state): State<AppState>,
   Path(table_name): Path<String>,
   Query(query): Query<ReadFilesQuery>,
+  headers: HeaderMap,
 ) -> Result<Response, Error> {
   let table_name = QualifiedName::parse(&table_name)?;
   let ConnectionEntry {
@@ -77,8 +79,8 @@ pub async fn read_files_handler(
       return Err(Error::Precondition(format!("File '{filename}' not found")));
     };
 
-    Ok(read_file_into_response(&state, file).await?)
+    Ok(read_file_into_response(&state, file, &headers, false).await?)
   } else {
-    Ok(read_file_into_response(&state, file_uploads.remove(0)).await?)
+    Ok(read_file_into_response(&state, file_uploads.remove(0), &headers, false).await?)
   };
 }
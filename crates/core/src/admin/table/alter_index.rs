@@ -6,6 +6,8 @@ use ts_rs::TS;
 
 use crate::admin::AdminError as Error;
 use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
 use crate::transaction_recorder::TransactionRecorder;
 
 #[derive(Clone, Debug, Deserialize, TS)]
@@ -24,6 +26,7 @@ pub struct AlterIndexResponse {
 
 pub async fn alter_index_handler(
   State(state): State<AppState>,
+  user: User,
   Json(request): Json<AlterIndexRequest>,
 ) -> Result<Json<AlterIndexResponse>, Error> {
   if state.demo_mode() {
@@ -80,6 +83,14 @@ pub async fn alter_index_handler(
       .apply_as_migration(&conn, migration_path, &filename)
       .await?;
     debug!("Migration report: {report:?}");
+
+    audit(
+      &state,
+      AuditEvent::new(Actor::user(&user), AuditAction::IndexAlter)
+        .target(request.source_schema.name.to_string())
+        .diff(serde_json::json!({ "sql": log.build_sql() })),
+    )
+    .await;
   }
 
   return Ok(Json(AlterIndexResponse {
@@ -7,6 +7,8 @@ use ts_rs::TS;
 
 use crate::admin::AdminError as Error;
 use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
 use crate::config::proto::hash_config;
 use crate::transaction_recorder::{TransactionLog, TransactionRecorder};
 
@@ -41,6 +43,7 @@ pub struct AlterTableResponse {
 /// the data over, see https://sqlite.org/lang_altertable.html.
 pub async fn alter_table_handler(
   State(state): State<AppState>,
+  user: User,
   Json(request): Json<AlterTableRequest>,
 ) -> Result<Json<AlterTableResponse>, Error> {
   if state.demo_mode() {
@@ -188,6 +191,14 @@ pub async fn alter_table_handler(
     }
 
     state.rebuild_connection_metadata().await?;
+
+    audit(
+      &state,
+      AuditEvent::new(Actor::user(&user), AuditAction::TableAlter)
+        .target(source_table_schema.name.to_string())
+        .diff(serde_json::json!({ "sql": log.build_sql() })),
+    )
+    .await;
   }
 
   return Ok(Json(AlterTableResponse {
@@ -610,7 +621,13 @@ mod tests {
       "Create Table: {}",
       create_table_request.schema.create_table_statement()
     );
-    let _ = create_table_handler(State(state.clone()), Json(create_table_request.clone())).await?;
+    let admin = User::from_unverified(uuid::Uuid::now_v7(), "admin@localhost");
+    let _ = create_table_handler(
+      State(state.clone()),
+      admin.clone(),
+      Json(create_table_request.clone()),
+    )
+    .await?;
 
     conn
       .read_query_rows(format!("SELECT {pk_col} FROM foo"), ())
@@ -624,10 +641,13 @@ mod tests {
         dry_run: None,
       };
 
-      let Json(response) =
-        alter_table_handler(State(state.clone()), Json(alter_table_request.clone()))
-          .await
-          .unwrap();
+      let Json(response) = alter_table_handler(
+        State(state.clone()),
+        admin.clone(),
+        Json(alter_table_request.clone()),
+      )
+      .await
+      .unwrap();
       assert_eq!(response.sql, "");
 
       conn
@@ -654,10 +674,13 @@ mod tests {
         dry_run: None,
       };
 
-      let Json(response) =
-        alter_table_handler(State(state.clone()), Json(alter_table_request.clone()))
-          .await
-          .unwrap();
+      let Json(response) = alter_table_handler(
+        State(state.clone()),
+        admin.clone(),
+        Json(alter_table_request.clone()),
+      )
+      .await
+      .unwrap();
       assert!(response.sql.contains("new"));
 
       conn
@@ -675,10 +698,13 @@ mod tests {
         dry_run: None,
       };
 
-      let Json(response) =
-        alter_table_handler(State(state.clone()), Json(alter_table_request.clone()))
-          .await
-          .unwrap();
+      let Json(response) = alter_table_handler(
+        State(state.clone()),
+        admin.clone(),
+        Json(alter_table_request.clone()),
+      )
+      .await
+      .unwrap();
       assert!(response.sql.contains("bar"));
 
       assert!(conn.read_query_rows("SELECT * FROM foo", ()).await.is_err());
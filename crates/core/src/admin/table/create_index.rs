@@ -5,6 +5,8 @@ use ts_rs::TS;
 
 use crate::admin::AdminError as Error;
 use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
 use crate::transaction_recorder::TransactionRecorder;
 
 #[derive(Clone, Debug, Deserialize, TS)]
@@ -22,6 +24,7 @@ pub struct CreateIndexResponse {
 
 pub async fn create_index_handler(
   State(state): State<AppState>,
+  user: User,
   Json(request): Json<CreateIndexRequest>,
 ) -> Result<Json<CreateIndexResponse>, Error> {
   let dry_run = request.dry_run.unwrap_or(false);
@@ -54,6 +57,14 @@ pub async fn create_index_handler(
     log
       .apply_as_migration(&conn, migration_path, &filename)
       .await?;
+
+    audit(
+      &state,
+      AuditEvent::new(Actor::user(&user), AuditAction::IndexCreate)
+        .target(request.schema.name.to_string())
+        .diff(serde_json::json!({ "sql": log.build_sql() })),
+    )
+    .await;
   }
 
   return Ok(Json(CreateIndexResponse {
@@ -5,6 +5,8 @@ use ts_rs::TS;
 
 use crate::admin::AdminError as Error;
 use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
 use crate::transaction_recorder::TransactionRecorder;
 
 #[derive(Clone, Debug, Deserialize, TS)]
@@ -22,6 +24,7 @@ pub struct CreateTableResponse {
 
 pub async fn create_table_handler(
   State(state): State<AppState>,
+  user: User,
   Json(request): Json<CreateTableRequest>,
 ) -> Result<Json<CreateTableResponse>, Error> {
   if request.schema.columns.is_empty() {
@@ -60,6 +63,14 @@ pub async fn create_table_handler(
       .await?;
 
     state.rebuild_connection_metadata().await?;
+
+    audit(
+      &state,
+      AuditEvent::new(Actor::user(&user), AuditAction::TableCreate)
+        .target(request.schema.name.to_string())
+        .diff(serde_json::json!({ "sql": log.build_sql() })),
+    )
+    .await;
   }
 
   return Ok(Json(CreateTableResponse {
@@ -6,6 +6,8 @@ use ts_rs::TS;
 
 use crate::admin::AdminError as Error;
 use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
 use crate::transaction_recorder::TransactionRecorder;
 
 #[derive(Clone, Debug, Deserialize, TS)]
@@ -23,6 +25,7 @@ pub struct DropIndexResponse {
 
 pub async fn drop_index_handler(
   State(state): State<AppState>,
+  user: User,
   Json(request): Json<DropIndexRequest>,
 ) -> Result<Json<DropIndexResponse>, Error> {
   if state.demo_mode() {
@@ -65,6 +68,14 @@ pub async fn drop_index_handler(
     let _report = log
       .apply_as_migration(&conn, migration_path, &filename)
       .await?;
+
+    audit(
+      &state,
+      AuditEvent::new(Actor::user(&user), AuditAction::IndexDrop)
+        .target(request.name.clone())
+        .diff(serde_json::json!({ "sql": log.build_sql() })),
+    )
+    .await;
   }
 
   return Ok(Json(DropIndexResponse {
@@ -6,6 +6,8 @@ use ts_rs::TS;
 
 use crate::admin::AdminError as Error;
 use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
 use crate::config::proto::hash_config;
 use crate::constants::SQLITE_SCHEMA_TABLE;
 use crate::transaction_recorder::TransactionRecorder;
@@ -26,6 +28,7 @@ pub struct DropTableResponse {
 
 pub async fn drop_table_handler(
   State(state): State<AppState>,
+  user: User,
   Json(request): Json<DropTableRequest>,
 ) -> Result<Json<DropTableResponse>, Error> {
   if state.demo_mode() {
@@ -114,6 +117,14 @@ pub async fn drop_table_handler(
     }
 
     state.rebuild_connection_metadata().await?;
+
+    audit(
+      &state,
+      AuditEvent::new(Actor::user(&user), AuditAction::TableDrop)
+        .target(request.name.clone())
+        .diff(serde_json::json!({ "sql": log.build_sql() })),
+    )
+    .await;
   }
 
   return Ok(Json(DropTableResponse {
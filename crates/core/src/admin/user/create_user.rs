@@ -7,6 +7,8 @@ use uuid::Uuid;
 
 use crate::admin::AdminError as Error;
 use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
 use crate::auth::jwt::EmailVerificationTokenClaims;
 use crate::auth::password::{hash_password, validate_password_policy};
 use crate::auth::user::DbUser;
@@ -31,8 +33,33 @@ pub struct CreateUserResponse {
 
 pub async fn create_user_handler(
   State(state): State<AppState>,
+  user: User,
   Json(request): Json<CreateUserRequest>,
 ) -> Result<Json<CreateUserResponse>, Error> {
+  let diff = serde_json::json!({
+    "email": &request.email,
+    "verified": request.verified,
+    "admin": request.admin,
+  });
+
+  let response = create_user(&state, request).await?;
+
+  audit(
+    &state,
+    AuditEvent::new(Actor::user(&user), AuditAction::UserCreate)
+      .target(response.id.to_string())
+      .diff(diff),
+  )
+  .await;
+
+  return Ok(Json(response));
+}
+
+/// Creates a new user without recording an audit event, e.g. for tests or benchmarks.
+pub async fn create_user(
+  state: &AppState,
+  request: CreateUserRequest,
+) -> Result<CreateUserResponse, Error> {
   let normalized_email = validate_and_normalize_email_address(&request.email)?;
 
   let auth_options = state.auth_options();
@@ -42,7 +69,7 @@ pub async fn create_user_handler(
     auth_options.password_options(),
   )?;
 
-  if user_exists(&state, &normalized_email).await {
+  if user_exists(state, &normalized_email).await {
     return Err(Error::AlreadyExists("user"));
   }
 
@@ -89,14 +116,14 @@ pub async fn create_user_handler(
 
     // NOTE: We cannot pass a valid redirect_uri, since we cannot be sure if auth UI is
     // installed.
-    Email::verification_email(&state, &user.email, &token, None)?
+    Email::verification_email(state, &user.email, &token, None)?
       .send()
       .await?;
   }
 
-  return Ok(Json(CreateUserResponse {
+  return Ok(CreateUserResponse {
     id: Uuid::from_bytes(user.id),
-  }));
+  });
 }
 
 #[cfg(test)]
@@ -105,14 +132,14 @@ pub(crate) async fn create_user_for_test(
   email: &str,
   password: &str,
 ) -> Result<Uuid, Error> {
-  let response = create_user_handler(
-    State(state.clone()),
-    Json(CreateUserRequest {
+  let response = create_user(
+    state,
+    CreateUserRequest {
       email: email.to_string(),
       password: password.to_string(),
       verified: true,
       admin: false,
-    }),
+    },
   )
   .await?;
 
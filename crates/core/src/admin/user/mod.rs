@@ -1,11 +1,13 @@
 mod create_user;
 mod delete_user;
 mod list_users;
+mod sessions;
 mod update_user;
 
-pub use create_user::{CreateUserRequest, create_user_handler};
+pub use create_user::{CreateUserRequest, create_user, create_user_handler};
 pub(super) use delete_user::delete_user_handler;
 pub(super) use list_users::list_users_handler;
+pub(super) use sessions::{list_user_sessions_handler, revoke_user_session_handler};
 pub(super) use update_user::update_user_handler;
 
 #[cfg(test)]
@@ -19,6 +21,7 @@ mod tests {
   use uuid::Uuid;
 
   use crate::app_state::{TestStateOptions, test_state};
+  use crate::auth::User;
   use crate::auth::util::user_by_email;
   use crate::constants::USER_TABLE;
   use crate::email::{Mailer, testing::TestAsyncSmtpTransport};
@@ -42,6 +45,7 @@ mod tests {
     let email = "foo@bar.org";
     let user_id = create_user_handler(
       State(state.clone()),
+      User::from_unverified(Uuid::now_v7(), "admin@localhost"),
       Json(CreateUserRequest {
         email: email.to_string(),
         password: "Secret!1!!".to_string(),
@@ -0,0 +1,58 @@
+use axum::{
+  Json,
+  extract::{Query, State},
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
+use crate::auth::session::{Session, list_sessions, revoke_session};
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct ListUserSessionsQuery {
+  user: Uuid,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct ListUserSessionsResponse {
+  sessions: Vec<Session>,
+}
+
+pub async fn list_user_sessions_handler(
+  State(state): State<AppState>,
+  Query(query): Query<ListUserSessionsQuery>,
+) -> Result<Json<ListUserSessionsResponse>, Error> {
+  let sessions = list_sessions(state.session_conn(), &query.user, None).await?;
+  return Ok(Json(ListUserSessionsResponse { sessions }));
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct RevokeUserSessionRequest {
+  user: Uuid,
+  id: i64,
+}
+
+pub async fn revoke_user_session_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<RevokeUserSessionRequest>,
+) -> Result<(), Error> {
+  revoke_session(state.session_conn(), &request.user, request.id).await?;
+
+  audit(
+    &state,
+    AuditEvent::new(Actor::user(&user), AuditAction::SessionRevoke)
+      .target(request.user.to_string())
+      .diff(serde_json::json!({ "session_id": request.id })),
+  )
+  .await;
+
+  return Ok(());
+}
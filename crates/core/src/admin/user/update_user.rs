@@ -12,6 +12,8 @@ use ts_rs::TS;
 
 use crate::admin::AdminError as Error;
 use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::User;
 use crate::auth::password::hash_password;
 use crate::auth::util::is_admin;
 use crate::constants::USER_TABLE;
@@ -33,6 +35,7 @@ pub struct UpdateUserRequest {
 
 pub async fn update_user_handler(
   State(state): State<AppState>,
+  user: User,
   Json(request): Json<UpdateUserRequest>,
 ) -> Result<Response, Error> {
   let UpdateUserRequest {
@@ -66,7 +69,14 @@ pub async fn update_user_handler(
     "
   );
 
-  return match state
+  // NOTE: Never record the password itself.
+  let diff = serde_json::json!({
+    "email": &email,
+    "password_changed": password.is_some(),
+    "verified": verified,
+  });
+
+  let rows_affected = state
     .user_conn()
     .execute(
       UPDATE_QUERY,
@@ -77,10 +87,21 @@ pub async fn update_user_handler(
           ":verified": verified.map_or(Value::Null, |v| Value::Integer(if v {1} else {0})),
       },
     )
-    .await?
-  {
+    .await?;
+
+  return match rows_affected {
     0 => Ok((StatusCode::NOT_FOUND, "race?").into_response()),
-    1 => Ok((StatusCode::OK, "updated").into_response()),
+    1 => {
+      audit(
+        &state,
+        AuditEvent::new(Actor::user(&user), AuditAction::UserUpdate)
+          .target(user_id.to_string())
+          .diff(diff),
+      )
+      .await;
+
+      Ok((StatusCode::OK, "updated").into_response())
+    }
     _ => {
       unreachable!("user id must be unique");
     }
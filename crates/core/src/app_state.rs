@@ -1,5 +1,6 @@
 use log::*;
 use object_store::ObjectStore;
+use object_store::signer::Signer;
 use serde::Serialize;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -9,6 +10,8 @@ use trailbase_extension::jsonschema::JsonSchemaRegistry;
 use trailbase_reactive::Reactive;
 use trailbase_schema::QualifiedName;
 
+use crate::audit::AuditLog;
+use crate::auth::claims::{CustomClaims, CustomClaimsHooks};
 use crate::auth::jwt::JwtHelper;
 use crate::auth::options::AuthOptions;
 use crate::config::proto::{
@@ -19,7 +22,9 @@ use crate::connection::{ConnectionEntry, ConnectionError, ConnectionManager};
 use crate::data_dir::DataDir;
 use crate::email::Mailer;
 use crate::records::RecordApi;
+use crate::records::hooks::RecordHooks;
 use crate::records::subscribe::manager::SubscriptionManager;
+use crate::replication::{Replication, start_replication};
 use crate::scheduler::{JobRegistry, build_job_registry_from_config};
 use crate::wasm::Runtime;
 
@@ -46,19 +51,28 @@ struct InternalState {
   conn: trailbase_sqlite::Connection,
   session_conn: trailbase_sqlite::Connection,
   logs_conn: trailbase_sqlite::Connection,
+  audit_log: AuditLog,
   connection_manager: ConnectionManager,
 
   jwt: JwtHelper,
 
   record_apis: Reactive<HashMap<String, RecordApi>>,
+  record_hooks: RecordHooks,
+  custom_claims_hooks: CustomClaimsHooks,
   subscription_manager: SubscriptionManager,
   object_store: Arc<dyn ObjectStore>,
+  /// Only available for S3 storage, e.g. to presign direct uploads.
+  object_store_signer: Option<Arc<dyn Signer>>,
 
   /// Actual WASM runtimes.
   wasm_runtimes: Vec<Arc<RwLock<Runtime>>>,
   /// WASM runtime builders needed to rebuild above runtimes, e.g. when hot-reloading.
   wasm_runtimes_builder: crate::wasm::WasmRuntimeBuilder,
 
+  /// Keeps continuous replication running, if enabled.
+  #[allow(unused)]
+  replication: Reactive<Arc<Option<Replication>>>,
+
   #[cfg(test)]
   #[allow(unused)]
   test_cleanup: Vec<Box<dyn std::any::Any + Send + Sync>>,
@@ -75,9 +89,13 @@ pub(crate) struct AppStateArgs {
   pub json_schema_registry: Arc<parking_lot::RwLock<JsonSchemaRegistry>>,
   pub session_conn: trailbase_sqlite::Connection,
   pub logs_conn: trailbase_sqlite::Connection,
+  pub audit_log: AuditLog,
   pub connection_manager: ConnectionManager,
   pub jwt: JwtHelper,
   pub object_store: Box<dyn ObjectStore>,
+  pub object_store_signer: Option<Arc<dyn Signer>>,
+  pub record_hooks: RecordHooks,
+  pub custom_claims_hooks: CustomClaimsHooks,
   pub wasm_tokio_runtime: Option<tokio::runtime::Handle>,
 }
 
@@ -119,6 +137,7 @@ impl AppState {
       args.data_dir.clone(),
       args.connection_manager.clone(),
       args.logs_conn.clone(),
+      args.audit_log.clone(),
       args.session_conn.clone(),
       object_store.clone(),
     );
@@ -152,6 +171,32 @@ impl AppState {
     )
     .expect("startup");
 
+    let replication = {
+      let data_dir = args.data_dir.clone();
+      config
+        .derive(|c| {
+          return (
+            c.server.replication_config.clone(),
+            c.server.s3_storage_config.clone(),
+            c.databases.clone(),
+          );
+        })
+        .derive_unchecked(move |(replication_config, s3_config, databases)| {
+          return Arc::new(
+            start_replication(
+              &data_dir,
+              replication_config.as_ref(),
+              s3_config.as_ref(),
+              databases,
+            )
+            .unwrap_or_else(|err| {
+              error!("Failed to start replication: {err}");
+              return None;
+            }),
+          );
+        })
+    };
+
     AppState {
       state: Arc::new(InternalState {
         data_dir: args.data_dir,
@@ -165,7 +210,7 @@ impl AppState {
         jobs: config.derive_unchecked(move |c| {
           debug!("(re-)building jobs from config");
 
-          let (data_dir, conn_mgr, logs_conn, session_conn, object_store) = &jobs_input;
+          let (data_dir, conn_mgr, logs_conn, audit_log, session_conn, object_store) = &jobs_input;
 
           return Arc::new(
             build_job_registry_from_config(
@@ -173,6 +218,7 @@ impl AppState {
               data_dir,
               conn_mgr,
               logs_conn,
+              audit_log,
               session_conn,
               object_store.clone(),
             )
@@ -188,17 +234,22 @@ impl AppState {
         conn: (*main_conn).clone(),
         session_conn: args.session_conn,
         logs_conn: args.logs_conn,
+        audit_log: args.audit_log,
         connection_manager: args.connection_manager,
         jwt: args.jwt,
         record_apis: record_apis.clone(),
+        record_hooks: args.record_hooks,
+        custom_claims_hooks: args.custom_claims_hooks,
         subscription_manager: SubscriptionManager::new(record_apis),
         object_store,
+        object_store_signer: args.object_store_signer,
         wasm_runtimes: wasm_runtimes_builder()
           .expect("startup")
           .into_iter()
           .map(|rt| Arc::new(RwLock::new(rt)))
           .collect(),
         wasm_runtimes_builder,
+        replication,
         #[cfg(test)]
         test_cleanup: vec![],
       }),
@@ -253,6 +304,10 @@ impl AppState {
     return &self.state.logs_conn;
   }
 
+  pub(crate) fn audit_log(&self) -> &AuditLog {
+    return &self.state.audit_log;
+  }
+
   pub fn connection_manager(&self) -> ConnectionManager {
     return self.state.connection_manager.clone();
   }
@@ -295,6 +350,25 @@ impl AppState {
     return &self.state.object_store;
   }
 
+  pub(crate) fn objectstore_signer(&self) -> Option<&Arc<dyn Signer>> {
+    return self.state.object_store_signer.as_ref();
+  }
+
+  pub(crate) fn record_hooks(&self) -> &RecordHooks {
+    return &self.state.record_hooks;
+  }
+
+  /// Custom auth token claims from the configured query and registered hooks.
+  pub(crate) fn custom_claims(&self) -> CustomClaims {
+    return CustomClaims::new(
+      self
+        .auth_options()
+        .custom_claims_query()
+        .map(|q| q.to_string()),
+      self.state.custom_claims_hooks.clone(),
+    );
+  }
+
   pub(crate) fn jobs(&self) -> Arc<JobRegistry> {
     return self.state.jobs.value();
   }
@@ -502,6 +576,8 @@ pub struct TestStateOptions {
   pub config: Option<Config>,
   pub json_schema_registry: Option<JsonSchemaRegistry>,
   pub(crate) mailer: Option<Mailer>,
+  pub(crate) record_hooks: Option<RecordHooks>,
+  pub(crate) custom_claims_hooks: Option<CustomClaimsHooks>,
 }
 
 #[cfg(test)]
@@ -518,6 +594,8 @@ pub async fn test_state(options: Option<TestStateOptions>) -> anyhow::Result<App
     config,
     mailer,
     json_schema_registry,
+    record_hooks,
+    custom_claims_hooks,
   } = options.unwrap_or_default();
 
   let json_schema_registry = Arc::new(parking_lot::RwLock::new(
@@ -534,24 +612,27 @@ pub async fn test_state(options: Option<TestStateOptions>) -> anyhow::Result<App
   let connection_manager =
     ConnectionManager::new_for_test(data_dir.clone(), json_schema_registry.clone(), vec![]);
 
-  let object_store = if std::env::var("TEST_S3_OBJECT_STORE").map_or(false, |v| v == "TRUE") {
-    info!("Use S3 Storage for tests");
+  let (object_store, object_store_signer) =
+    if std::env::var("TEST_S3_OBJECT_STORE").map_or(false, |v| v == "TRUE") {
+      info!("Use S3 Storage for tests");
 
-    build_objectstore(
-      &data_dir,
-      Some(&S3StorageConfig {
+      let s3_config = S3StorageConfig {
         endpoint: Some("http://127.0.0.1:9000".to_string()),
         region: None,
         bucket_name: Some("test".to_string()),
         access_key: Some("minioadmin".to_string()),
         secret_access_key: Some("minioadmin".to_string()),
-      }),
-    )
-    .unwrap()
-    .into()
-  } else {
-    build_objectstore(&data_dir, None).unwrap().into()
-  };
+      };
+
+      (
+        build_objectstore(&data_dir, Some(&s3_config))
+          .unwrap()
+          .into(),
+        build_objectstore_signer(Some(&s3_config)).unwrap(),
+      )
+    } else {
+      (build_objectstore(&data_dir, None).unwrap().into(), None)
+    };
 
   let config = Reactive::new(config);
 
@@ -579,14 +660,19 @@ pub async fn test_state(options: Option<TestStateOptions>) -> anyhow::Result<App
       json_schema_registry,
       conn: (*connection_manager.main_entry().connection).clone(),
       session_conn,
+      audit_log: AuditLog::new_for_test(logs_conn.clone()),
       logs_conn,
       connection_manager,
       jwt: crate::auth::jwt::test_jwt_helper(),
       record_apis: record_apis.clone(),
+      record_hooks: record_hooks.unwrap_or_default(),
+      custom_claims_hooks: custom_claims_hooks.unwrap_or_default(),
       subscription_manager: SubscriptionManager::new(record_apis),
       object_store,
+      object_store_signer,
       wasm_runtimes: vec![],
       wasm_runtimes_builder: Box::new(|| Ok(vec![])),
+      replication: Reactive::new(Arc::new(None)),
       test_cleanup: vec![Box::new(temp_dir)],
     }),
   });
@@ -706,40 +792,58 @@ pub(crate) fn build_objectstore(
   config: Option<&S3StorageConfig>,
 ) -> Result<Box<dyn ObjectStore>, object_store::Error> {
   if let Some(config) = config {
-    let mut builder = object_store::aws::AmazonS3Builder::from_env();
+    return Ok(Box::new(build_s3_objectstore(config)?));
+  }
 
-    if let Some(ref endpoint) = config.endpoint {
-      builder = builder.with_endpoint(endpoint);
+  return Ok(Box::new(
+    object_store::local::LocalFileSystem::new_with_prefix(data_dir.uploads_path())?,
+  ));
+}
 
-      if endpoint.starts_with("http://") {
-        builder =
-          builder.with_client_options(object_store::ClientOptions::default().with_allow_http(true))
-      }
-    }
+/// Builds a signer for presigned URLs. Only S3 storage supports signing.
+pub(crate) fn build_objectstore_signer(
+  config: Option<&S3StorageConfig>,
+) -> Result<Option<Arc<dyn Signer>>, object_store::Error> {
+  let Some(config) = config else {
+    return Ok(None);
+  };
 
-    if let Some(ref region) = config.region {
-      builder = builder.with_region(region);
-    }
+  let signer: Arc<dyn Signer> = Arc::new(build_s3_objectstore(config)?);
+  return Ok(Some(signer));
+}
 
-    let Some(ref bucket_name) = config.bucket_name else {
-      panic!("S3StorageConfig missing 'bucket_name'.");
-    };
-    builder = builder.with_bucket_name(bucket_name);
+fn build_s3_objectstore(
+  config: &S3StorageConfig,
+) -> Result<object_store::aws::AmazonS3, object_store::Error> {
+  let mut builder = object_store::aws::AmazonS3Builder::from_env();
 
-    if let Some(ref access_key) = config.access_key {
-      builder = builder.with_access_key_id(access_key);
-    }
+  if let Some(ref endpoint) = config.endpoint {
+    builder = builder.with_endpoint(endpoint);
 
-    if let Some(ref secret_access_key) = config.secret_access_key {
-      builder = builder.with_secret_access_key(secret_access_key);
+    if endpoint.starts_with("http://") {
+      builder =
+        builder.with_client_options(object_store::ClientOptions::default().with_allow_http(true))
     }
+  }
 
-    return Ok(Box::new(builder.build()?));
+  if let Some(ref region) = config.region {
+    builder = builder.with_region(region);
   }
 
-  return Ok(Box::new(
-    object_store::local::LocalFileSystem::new_with_prefix(data_dir.uploads_path())?,
-  ));
+  let Some(ref bucket_name) = config.bucket_name else {
+    panic!("S3StorageConfig missing 'bucket_name'.");
+  };
+  builder = builder.with_bucket_name(bucket_name);
+
+  if let Some(ref access_key) = config.access_key {
+    builder = builder.with_access_key_id(access_key);
+  }
+
+  if let Some(ref secret_access_key) = config.secret_access_key {
+    builder = builder.with_secret_access_key(secret_access_key);
+  }
+
+  return builder.build();
 }
 
 fn build_site_url(c: &Config) -> Result<Option<url::Url>, url::ParseError> {
@@ -762,6 +866,7 @@ struct AuthConfig {
   disable_password_auth: bool,
   enable_otp_signin: bool,
   oauth_providers: Vec<OAuthProvider>,
+  saml_providers: Vec<OAuthProvider>,
 }
 
 fn build_auth_config(config: &Config) -> AuthConfig {
@@ -800,10 +905,26 @@ fn build_auth_config(config: &Config) -> AuthConfig {
     })
     .collect();
 
+  let mut saml_providers: Vec<_> = config
+    .auth
+    .saml_providers
+    .iter()
+    .filter_map(|(key, config)| {
+      let provider = crate::auth::saml::SamlProvider::from_config(key, config).ok()?;
+      return Some(OAuthProvider {
+        name: provider.name().to_string(),
+        display_name: provider.display_name().to_string(),
+        img_name: "oidc.svg".to_string(),
+      });
+    })
+    .collect();
+  saml_providers.sort_by(|a, b| Ord::cmp(&a.name, &b.name));
+
   return AuthConfig {
     disable_password_auth: config.auth.disable_password_auth(),
     enable_otp_signin: config.auth.enable_otp_signin(),
     oauth_providers,
+    saml_providers,
   };
 }
 
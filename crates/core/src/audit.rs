@@ -0,0 +1,673 @@
+//! Tamper-evident audit trail of administrative and security-relevant actions, e.g. config
+//! changes, schema alterations, user management or logins.
+//!
+//! Entries are kept in the logs database alongside the HTTP request logs. Each entry is
+//! hash-chained to its predecessor, i.e. its HMAC covers its own content and the previous entry's
+//! hash. The HMAC is keyed with a server-side secret, which is kept outside the logs database.
+//! Altering, inserting or removing entries after the fact therefore breaks the chain, which can be
+//! checked using [verify_audit_log]. Retention only ever drops the oldest entries and records a
+//! keyed checkpoint of the last dropped entry, which anchors the remaining chain.
+use chrono::Utc;
+use const_format::formatcp;
+use hmac::{Hmac, KeyInit, Mac};
+use log::*;
+use rand::Rng;
+use serde::Serialize;
+use sha2::Sha256;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use trailbase_sqlite::{Connection, SyncConnectionTrait, params};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::DataDir;
+use crate::auth::User;
+use crate::auth::user::DbUser;
+use crate::constants::{AUDIT_LOG_CHECKPOINT_TABLE, AUDIT_LOG_TABLE};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const AUDIT_KEY_FILE: &str = "audit.key";
+const AUDIT_KEY_LEN: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum AuditError {
+  #[error("Sqlite: {0}")]
+  Sqlite(#[from] trailbase_sqlite::Error),
+  #[error("FromSql: {0}")]
+  FromSql(#[from] trailbase_sqlite::from_sql::FromSqlError),
+  #[error("Json: {0}")]
+  Json(#[from] serde_json::Error),
+  #[error("IO: {0}")]
+  IO(#[from] std::io::Error),
+  #[error("Invalid key")]
+  InvalidKey,
+}
+
+/// Handle to the audit log, i.e. the logs database and the secret keying its hash chain.
+#[derive(Clone)]
+pub struct AuditLog {
+  conn: Connection,
+  key: Arc<[u8; AUDIT_KEY_LEN]>,
+}
+
+impl AuditLog {
+  fn new(conn: Connection, key: [u8; AUDIT_KEY_LEN]) -> Self {
+    return Self {
+      conn,
+      key: Arc::new(key),
+    };
+  }
+
+  /// Loads the key from the data directory's secrets. Creates a new key if none exists.
+  pub async fn init_from_path(data_dir: &DataDir, conn: Connection) -> Result<Self, AuditError> {
+    let key_path = data_dir.key_path();
+    let path = key_path.join(AUDIT_KEY_FILE);
+
+    let key: [u8; AUDIT_KEY_LEN] = match tokio::fs::read(&path).await {
+      Ok(key) => key.try_into().map_err(|_| AuditError::InvalidKey)?,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+        let mut key = [0u8; AUDIT_KEY_LEN];
+        rand::rng().fill_bytes(&mut key);
+
+        tokio::fs::create_dir_all(&key_path).await?;
+        // NOTE: Never overwrite an existing key, which would invalidate the entire chain.
+        tokio::fs::OpenOptions::new()
+          .write(true)
+          .create_new(true)
+          .open(&path)
+          .await?
+          .write_all(&key)
+          .await?;
+
+        key
+      }
+      Err(err) => {
+        return Err(err.into());
+      }
+    };
+
+    return Ok(Self::new(conn, key));
+  }
+
+  #[cfg(test)]
+  pub(crate) fn new_for_test(conn: Connection) -> Self {
+    let mut key = [0u8; AUDIT_KEY_LEN];
+    rand::rng().fill_bytes(&mut key);
+    return Self::new(conn, key);
+  }
+
+  fn mac(&self) -> HmacSha256 {
+    return HmacSha256::new_from_slice(self.key.as_slice()).expect("any key length");
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditAction {
+  ConfigUpdate,
+  TableCreate,
+  TableAlter,
+  TableDrop,
+  IndexCreate,
+  IndexAlter,
+  IndexDrop,
+  QueryExecute,
+  UserCreate,
+  UserUpdate,
+  UserDelete,
+  UserPromote,
+  UserDemote,
+  SessionRevoke,
+  RoleCreate,
+  RoleDelete,
+  RoleAssign,
+  RoleUnassign,
+  ApiKeyCreate,
+  ApiKeyRevoke,
+  OAuthClientCreate,
+  OAuthClientDelete,
+  TokenMint,
+  Login,
+  LoginFailed,
+  PasswordChange,
+  PasswordReset,
+  EmailChange,
+}
+
+impl AuditAction {
+  pub fn as_str(&self) -> &'static str {
+    return match self {
+      Self::ConfigUpdate => "config.update",
+      Self::TableCreate => "table.create",
+      Self::TableAlter => "table.alter",
+      Self::TableDrop => "table.drop",
+      Self::IndexCreate => "index.create",
+      Self::IndexAlter => "index.alter",
+      Self::IndexDrop => "index.drop",
+      Self::QueryExecute => "query.execute",
+      Self::UserCreate => "user.create",
+      Self::UserUpdate => "user.update",
+      Self::UserDelete => "user.delete",
+      Self::UserPromote => "user.promote",
+      Self::UserDemote => "user.demote",
+      Self::SessionRevoke => "session.revoke",
+      Self::RoleCreate => "role.create",
+      Self::RoleDelete => "role.delete",
+      Self::RoleAssign => "role.assign",
+      Self::RoleUnassign => "role.unassign",
+      Self::ApiKeyCreate => "api_key.create",
+      Self::ApiKeyRevoke => "api_key.revoke",
+      Self::OAuthClientCreate => "oauth_client.create",
+      Self::OAuthClientDelete => "oauth_client.delete",
+      Self::TokenMint => "token.mint",
+      Self::Login => "auth.login",
+      Self::LoginFailed => "auth.login_failed",
+      Self::PasswordChange => "auth.change_password",
+      Self::PasswordReset => "auth.reset_password",
+      Self::EmailChange => "auth.change_email",
+    };
+  }
+}
+
+/// Who performed an audited action.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Actor {
+  /// Id of the acting user. Absent for the command line or unauthenticated parties.
+  pub id: Option<Uuid>,
+  /// Human-readable identity, e.g. the user's email address or "cli".
+  pub name: String,
+}
+
+impl Actor {
+  pub fn user(user: &User) -> Self {
+    return Self {
+      id: Some(user.uuid),
+      name: user.email.clone(),
+    };
+  }
+
+  pub(crate) fn db_user(user: &DbUser) -> Self {
+    return Self {
+      id: Some(user.uuid()),
+      name: user.email.clone(),
+    };
+  }
+
+  /// Actions taken via the `trail` command line, i.e. by someone with access to the data
+  /// directory.
+  pub fn cli() -> Self {
+    return Self {
+      id: None,
+      name: "cli".to_string(),
+    };
+  }
+
+  /// Unauthenticated party, e.g. someone failing to log in as `email`.
+  pub fn anonymous(email: &str) -> Self {
+    return Self {
+      id: None,
+      name: email.to_string(),
+    };
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct AuditEvent {
+  pub actor: Actor,
+  pub action: AuditAction,
+  /// What was acted upon, e.g. a table name or a user's email address.
+  pub target: Option<String>,
+  /// Change details, e.g. the altered config lines or executed SQL.
+  pub diff: Option<serde_json::Value>,
+  pub client_ip: Option<String>,
+}
+
+impl AuditEvent {
+  pub fn new(actor: Actor, action: AuditAction) -> Self {
+    return Self {
+      actor,
+      action,
+      target: None,
+      diff: None,
+      client_ip: None,
+    };
+  }
+
+  pub fn target(mut self, target: impl Into<String>) -> Self {
+    self.target = Some(target.into());
+    return self;
+  }
+
+  pub fn diff(mut self, diff: serde_json::Value) -> Self {
+    self.diff = Some(diff);
+    return self;
+  }
+
+  pub fn client_ip(mut self, client_ip: Option<String>) -> Self {
+    self.client_ip = client_ip;
+    return self;
+  }
+}
+
+/// Content covered by an entry's HMAC in addition to the previous entry's hash.
+#[derive(Serialize)]
+struct HashedContent<'a> {
+  id: i64,
+  created: f64,
+  actor_id: Option<String>,
+  actor: &'a str,
+  action: &'a str,
+  target: Option<&'a str>,
+  diff: Option<&'a str>,
+  client_ip: &'a str,
+}
+
+impl HashedContent<'_> {
+  fn hash(&self, mut mac: HmacSha256, prev_hash: &[u8]) -> Result<Vec<u8>, serde_json::Error> {
+    mac.update(prev_hash);
+    mac.update(&serde_json::to_vec(self)?);
+    return Ok(mac.finalize().into_bytes().to_vec());
+  }
+}
+
+/// MAC of a checkpoint, i.e. the id and hash of the last entry dropped due to retention.
+fn checkpoint_mac(mut mac: HmacSha256, id: i64, hash: &[u8]) -> Vec<u8> {
+  mac.update(b"checkpoint");
+  mac.update(&id.to_be_bytes());
+  mac.update(hash);
+  return mac.finalize().into_bytes().to_vec();
+}
+
+const CHECKPOINT_QUERY: &str =
+  formatcp!("SELECT id, hash, mac FROM {AUDIT_LOG_CHECKPOINT_TABLE} ORDER BY id DESC LIMIT 1");
+
+/// Appends the event to the audit log returning the new entry's id.
+pub(crate) async fn record_audit_event(
+  audit_log: &AuditLog,
+  event: AuditEvent,
+) -> Result<i64, AuditError> {
+  let AuditEvent {
+    actor,
+    action,
+    target,
+    diff,
+    client_ip,
+  } = event;
+  let diff = diff.map(|diff| serde_json::to_string(&diff)).transpose()?;
+  let created = Utc::now().timestamp_millis() as f64 / 1000.0;
+  let mac = audit_log.mac();
+
+  return Ok(
+    audit_log
+      .conn
+      .transaction(move |tx| -> Result<i64, trailbase_sqlite::Error> {
+        const LAST_QUERY: &str =
+          formatcp!("SELECT id, hash FROM {AUDIT_LOG_TABLE} ORDER BY id DESC LIMIT 1");
+        let (prev_id, prev_hash) = match tx.query_row(LAST_QUERY, ())? {
+          Some(row) => (row.get::<i64>(0)?, row.get::<Vec<u8>>(1)?),
+          // All entries may have been dropped due to retention, thus continue from the checkpoint.
+          None => match tx.query_row(CHECKPOINT_QUERY, ())? {
+            Some(row) => (row.get::<i64>(0)?, row.get::<Vec<u8>>(1)?),
+            None => (0, vec![]),
+          },
+        };
+
+        let id = prev_id + 1;
+        let client_ip = client_ip.unwrap_or_default();
+        let hash = HashedContent {
+          id,
+          created,
+          actor_id: actor.id.map(|id| id.to_string()),
+          actor: &actor.name,
+          action: action.as_str(),
+          target: target.as_deref(),
+          diff: diff.as_deref(),
+          client_ip: &client_ip,
+        }
+        .hash(mac, &prev_hash)
+        .map_err(|err| trailbase_sqlite::Error::Other(err.into()))?;
+
+        const INSERT_QUERY: &str = formatcp!(
+          "\
+            INSERT INTO {AUDIT_LOG_TABLE} \
+              (id, created, actor_id, actor, action, target, diff, client_ip, prev_hash, hash) \
+            VALUES \
+              ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) \
+          "
+        );
+        tx.execute(
+          INSERT_QUERY,
+          params!(
+            id,
+            created,
+            actor.id.map(|id| id.into_bytes().to_vec()),
+            actor.name,
+            action.as_str(),
+            target,
+            diff,
+            client_ip,
+            prev_hash,
+            hash,
+          ),
+        )?;
+        tx.commit()?;
+
+        return Ok(id);
+      })
+      .await?,
+  );
+}
+
+/// Records the event, merely logging failures. Audited actions have already taken place by the
+/// time they're recorded, thus failing the request would be misleading.
+pub(crate) async fn audit(state: &AppState, event: AuditEvent) {
+  audit_with_log(state.audit_log(), event).await;
+}
+
+/// Like [audit] but for use w/o an [AppState], e.g. from the command line.
+pub(crate) async fn audit_with_log(audit_log: &AuditLog, event: AuditEvent) {
+  let action = event.action;
+  if let Err(err) = record_audit_event(audit_log, event).await {
+    error!("Failed to record audit event {}: {err}", action.as_str());
+  }
+}
+
+/// Result of checking the audit log's hash chain.
+#[derive(Debug, PartialEq)]
+pub(crate) struct AuditLogVerification {
+  /// Number of checked entries.
+  pub entries: i64,
+  /// Id of the first entry, whose hash or link to its predecessor doesn't check out.
+  pub first_invalid_id: Option<i64>,
+}
+
+/// Drops entries created before `cutoff`, i.e. a UNIX timestamp, and records a checkpoint of the
+/// last dropped entry to anchor the remaining chain.
+pub(crate) async fn apply_audit_log_retention(
+  audit_log: &AuditLog,
+  cutoff: i64,
+) -> Result<(), AuditError> {
+  let mac = audit_log.mac();
+
+  return Ok(
+    audit_log
+      .conn
+      .transaction(move |tx| -> Result<(), trailbase_sqlite::Error> {
+        // NOTE: Only ever drop a contiguous range of the oldest entries, which keeps the remaining
+        // chain intact.
+        const LAST_QUERY: &str = formatcp!(
+          "SELECT id, hash FROM {AUDIT_LOG_TABLE} WHERE created < $1 ORDER BY id DESC LIMIT 1"
+        );
+        let Some(row) = tx.query_row(LAST_QUERY, params!(cutoff))? else {
+          return Ok(());
+        };
+        let id: i64 = row.get(0)?;
+        let hash: Vec<u8> = row.get(1)?;
+
+        tx.execute(
+          formatcp!("DELETE FROM {AUDIT_LOG_TABLE} WHERE id <= $1"),
+          params!(id),
+        )?;
+        tx.execute(formatcp!("DELETE FROM {AUDIT_LOG_CHECKPOINT_TABLE}"), ())?;
+        tx.execute(
+          formatcp!("INSERT INTO {AUDIT_LOG_CHECKPOINT_TABLE} (id, hash, mac) VALUES ($1, $2, $3)"),
+          params!(id, hash.clone(), checkpoint_mac(mac, id, &hash)),
+        )?;
+        tx.commit()?;
+
+        return Ok(());
+      })
+      .await?,
+  );
+}
+
+/// Recomputes the HMACs of all retained entries and checks that they're linked to their
+/// predecessors, starting from the retention checkpoint if any.
+///
+/// NOTE: The chain cannot protect against someone, who can write to the logs database,
+/// truncating the most recent entries. Rewriting the chain requires the key.
+pub(crate) async fn verify_audit_log(
+  audit_log: &AuditLog,
+) -> Result<AuditLogVerification, AuditError> {
+  const BATCH_SIZE: i64 = 1000;
+  const QUERY: &str = formatcp!(
+    "\
+      SELECT id, created, actor_id, actor, action, target, diff, client_ip, prev_hash, hash \
+      FROM {AUDIT_LOG_TABLE} WHERE id > $1 ORDER BY id ASC LIMIT $2 \
+    "
+  );
+
+  // Without a valid checkpoint, the chain has to start with the very first entry.
+  let checkpoint = match audit_log.conn.read_query_row(CHECKPOINT_QUERY, ()).await? {
+    Some(row) => {
+      let id: i64 = row.get(0)?;
+      let hash: Vec<u8> = row.get(1)?;
+      let mac: Vec<u8> = row.get(2)?;
+      (checkpoint_mac(audit_log.mac(), id, &hash) == mac).then_some((id, hash))
+    }
+    None => None,
+  };
+
+  let mut entries: i64 = 0;
+  let mut prev: (i64, Vec<u8>) = checkpoint.unwrap_or((0, vec![]));
+  loop {
+    let rows = audit_log
+      .conn
+      .read_query_rows(QUERY, params!(prev.0, BATCH_SIZE))
+      .await?;
+    if rows.is_empty() {
+      break;
+    }
+
+    for row in rows.iter() {
+      let id: i64 = row.get(0)?;
+      let actor: String = row.get(3)?;
+      let action: String = row.get(4)?;
+      let target: Option<String> = row.get(5)?;
+      let diff: Option<String> = row.get(6)?;
+      let client_ip: String = row.get(7)?;
+      let prev_hash: Vec<u8> = row.get(8)?;
+      let hash: Vec<u8> = row.get(9)?;
+
+      let content = HashedContent {
+        id,
+        created: row.get(1)?,
+        actor_id: row
+          .get::<Option<[u8; 16]>>(2)?
+          .map(|id| Uuid::from_bytes(id).to_string()),
+        actor: &actor,
+        action: &action,
+        target: target.as_deref(),
+        diff: diff.as_deref(),
+        client_ip: &client_ip,
+      };
+
+      let linked = id == prev.0 + 1 && prev_hash == prev.1;
+      if !linked || content.hash(audit_log.mac(), &prev_hash)? != hash {
+        return Ok(AuditLogVerification {
+          entries,
+          first_invalid_id: Some(id),
+        });
+      }
+
+      entries += 1;
+      prev = (id, hash);
+    }
+  }
+
+  return Ok(AuditLogVerification {
+    entries,
+    first_invalid_id: None,
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::app_state::test_state;
+
+  async fn record_test_events(audit_log: &AuditLog) {
+    let admin = Actor {
+      id: Some(Uuid::now_v7()),
+      name: "admin@test.org".to_string(),
+    };
+    for table in ["a", "b", "c"] {
+      record_audit_event(
+        audit_log,
+        AuditEvent::new(admin.clone(), AuditAction::TableCreate)
+          .target(table)
+          .diff(serde_json::json!({ "sql": format!("CREATE TABLE {table} (id INTEGER)") })),
+      )
+      .await
+      .unwrap();
+    }
+    record_audit_event(
+      audit_log,
+      AuditEvent::new(
+        Actor::anonymous("mallory@test.org"),
+        AuditAction::LoginFailed,
+      )
+      .client_ip(Some("127.0.0.1".to_string())),
+    )
+    .await
+    .unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_audit_log_hash_chain() {
+    let state = test_state(None).await.unwrap();
+    let audit_log = state.audit_log();
+    let conn = state.logs_conn();
+
+    record_test_events(audit_log).await;
+
+    assert_eq!(
+      verify_audit_log(audit_log).await.unwrap(),
+      AuditLogVerification {
+        entries: 4,
+        first_invalid_id: None,
+      }
+    );
+
+    // The chain cannot be verified, and thus not be rewritten, w/o the key.
+    assert_eq!(
+      verify_audit_log(&AuditLog::new_for_test(conn.clone()))
+        .await
+        .unwrap()
+        .first_invalid_id,
+      Some(1)
+    );
+
+    // Dropping the oldest entry w/o a checkpoint breaks the chain.
+    conn
+      .execute(format!("DELETE FROM {AUDIT_LOG_TABLE} WHERE id = 1"), ())
+      .await
+      .unwrap();
+    assert_eq!(
+      verify_audit_log(audit_log).await.unwrap().first_invalid_id,
+      Some(2)
+    );
+  }
+
+  #[tokio::test]
+  async fn test_audit_log_tampering() {
+    let state = test_state(None).await.unwrap();
+    let audit_log = state.audit_log();
+    let conn = state.logs_conn();
+
+    record_test_events(audit_log).await;
+
+    // Covering one's tracks breaks the chain.
+    conn
+      .execute(
+        format!("UPDATE {AUDIT_LOG_TABLE} SET actor = 'someone@test.org' WHERE id = 3"),
+        (),
+      )
+      .await
+      .unwrap();
+    assert_eq!(
+      verify_audit_log(audit_log).await.unwrap().first_invalid_id,
+      Some(3)
+    );
+
+    conn
+      .execute(format!("DELETE FROM {AUDIT_LOG_TABLE} WHERE id = 3"), ())
+      .await
+      .unwrap();
+    assert_eq!(
+      verify_audit_log(audit_log).await.unwrap().first_invalid_id,
+      Some(4)
+    );
+  }
+
+  #[tokio::test]
+  async fn test_audit_log_retention() {
+    let state = test_state(None).await.unwrap();
+    let audit_log = state.audit_log();
+    let conn = state.logs_conn();
+
+    record_test_events(audit_log).await;
+
+    // Age the first two entries past retention.
+    conn
+      .execute(
+        format!("UPDATE {AUDIT_LOG_TABLE} SET created = 0 WHERE id <= 2"),
+        (),
+      )
+      .await
+      .unwrap();
+    apply_audit_log_retention(audit_log, 1).await.unwrap();
+
+    // The checkpoint anchors the remaining chain.
+    assert_eq!(
+      verify_audit_log(audit_log).await.unwrap(),
+      AuditLogVerification {
+        entries: 2,
+        first_invalid_id: None,
+      }
+    );
+
+    // Dropping all entries continues the chain from the checkpoint.
+    apply_audit_log_retention(audit_log, i64::MAX)
+      .await
+      .unwrap();
+    assert_eq!(
+      record_audit_event(audit_log, AuditEvent::new(Actor::cli(), AuditAction::Login))
+        .await
+        .unwrap(),
+      5
+    );
+    assert_eq!(
+      verify_audit_log(audit_log).await.unwrap(),
+      AuditLogVerification {
+        entries: 1,
+        first_invalid_id: None,
+      }
+    );
+
+    // Forging a checkpoint to hide the oldest entry requires the key.
+    conn
+      .execute(
+        format!(
+          "UPDATE {AUDIT_LOG_CHECKPOINT_TABLE} SET id = 5, \
+             hash = (SELECT hash FROM {AUDIT_LOG_TABLE} WHERE id = 5)"
+        ),
+        (),
+      )
+      .await
+      .unwrap();
+    conn
+      .execute(format!("DELETE FROM {AUDIT_LOG_TABLE} WHERE id = 5"), ())
+      .await
+      .unwrap();
+    record_audit_event(audit_log, AuditEvent::new(Actor::cli(), AuditAction::Login))
+      .await
+      .unwrap();
+    assert_eq!(
+      verify_audit_log(audit_log).await.unwrap().first_invalid_id,
+      Some(6)
+    );
+  }
+}
@@ -1,4 +1,5 @@
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
+use axum::http::HeaderMap;
 use axum::response::Response;
 use const_format::formatcp;
 use std::sync::LazyLock;
@@ -11,8 +12,10 @@ use crate::connection::ConnectionEntry;
 use crate::constants::AVATAR_TABLE;
 use crate::extract::Either;
 use crate::records::RecordError;
+use crate::records::image_transform::{ImageTransform, read_image_variant_into_response};
 use crate::records::params::{JsonRow, LazyParams};
 use crate::records::read_queries::run_get_file_query;
+use crate::records::read_record::ReadFileQuery;
 use crate::records::write_queries::run_insert_query;
 use crate::util::uuid_to_b64;
 
@@ -20,11 +23,14 @@ use crate::util::uuid_to_b64;
   get,
   path = "/avatar/:b64_user_id",
   tag = "auth",
+  params(ReadFileQuery),
   responses((status = 200, description = "Optional Avatar file"))
 )]
 pub async fn get_avatar_handler(
   State(state): State<AppState>,
   Path(b64_user_id): Path<String>,
+  Query(query): Query<ReadFileQuery>,
+  headers: HeaderMap,
 ) -> Result<Response, AuthError> {
   let Ok(user_id) = crate::util::b64_to_uuid(&b64_user_id) else {
     return Err(AuthError::BadRequest("Invalid user id"));
@@ -56,7 +62,25 @@ pub async fn get_avatar_handler(
     _ => AuthError::Internal(err.into()),
   })?;
 
-  return crate::records::files::read_file_into_response(&state, file_upload)
+  let inline = query.inline.unwrap_or(false);
+  if let Some(transform) = ImageTransform::from_query(&query) {
+    let allowed = state.access_config(|c| c.auth.avatar_image_transforms.clone());
+    return read_image_variant_into_response(
+      &state,
+      &allowed,
+      file_upload,
+      transform,
+      &headers,
+      inline,
+    )
+    .await
+    .map_err(|err| match err {
+      RecordError::BadRequest(msg) => AuthError::BadRequest(msg),
+      _ => AuthError::Internal(err.into()),
+    });
+  }
+
+  return crate::records::files::read_file_into_response(&state, file_upload, &headers, inline)
     .await
     .map_err(|err| AuthError::Internal(err.into()));
 }
@@ -148,7 +172,7 @@ static AVATAR_TABLE_NAME: LazyLock<QualifiedName> = LazyLock::new(|| QualifiedNa
 
 #[cfg(test)]
 mod tests {
-  use axum::extract::{FromRequest, Path, State};
+  use axum::extract::{FromRequest, Path, Query, State};
   use axum::http;
   use axum::response::Response;
   use axum_test::multipart::{MultipartForm, Part};
@@ -158,8 +182,10 @@ mod tests {
   use crate::app_state::*;
   use crate::auth::user::{DbUser, User};
   use crate::auth::util::login_with_password;
+  use crate::config::proto;
   use crate::constants::USER_TABLE;
   use crate::extract::Either;
+  use crate::records::image_transform::{ImageFit, ImageFormat};
   use crate::util::id_to_b64;
 
   type Request = http::Request<axum::body::Body>;
@@ -201,9 +227,14 @@ mod tests {
   }
 
   async fn download_avatar(state: &AppState, record_id: &[u8; 16]) -> Response {
-    return get_avatar_handler(State(state.clone()), Path(id_to_b64(record_id)))
-      .await
-      .unwrap();
+    return get_avatar_handler(
+      State(state.clone()),
+      Path(id_to_b64(record_id)),
+      Query(ReadFileQuery::default()),
+      HeaderMap::new(),
+    )
+    .await
+    .unwrap();
   }
 
   #[tokio::test]
@@ -227,10 +258,14 @@ mod tests {
       .unwrap()
       .unwrap();
 
-    let missing_profile_response =
-      get_avatar_handler(State(state.clone()), Path(id_to_b64(&db_user.id)))
-        .await
-        .err();
+    let missing_profile_response = get_avatar_handler(
+      State(state.clone()),
+      Path(id_to_b64(&db_user.id)),
+      Query(ReadFileQuery::default()),
+      HeaderMap::new(),
+    )
+    .await
+    .err();
     assert!(matches!(
       missing_profile_response,
       Some(AuthError::NotFound)
@@ -271,9 +306,14 @@ mod tests {
       .is_err()
     );
 
-    let response = get_avatar_handler(State(state.clone()), Path(id_to_b64(&db_user.id)))
-      .await
-      .unwrap();
+    let response = get_avatar_handler(
+      State(state.clone()),
+      Path(id_to_b64(&db_user.id)),
+      Query(ReadFileQuery::default()),
+      HeaderMap::new(),
+    )
+    .await
+    .unwrap();
     assert_eq!(
       axum::body::to_bytes(response.into_body(), usize::MAX)
         .await
@@ -281,4 +321,70 @@ mod tests {
       PNG1
     );
   }
+
+  #[tokio::test]
+  async fn test_avatar_image_transforms() {
+    let mut config = test_config();
+    config.auth.avatar_image_transforms = vec![proto::ImageTransform {
+      width: Some(16),
+      height: Some(16),
+      fit: Some(proto::ImageFit::Cover as i32),
+      format: Some(proto::ImageFormat::Jpeg as i32),
+    }];
+
+    let state = test_state(Some(TestStateOptions {
+      config: Some(config),
+      ..Default::default()
+    }))
+    .await
+    .unwrap();
+
+    let email = "user_x@test.com";
+    let password = "SuperSecret5";
+    create_user_for_test(&state, email, &password)
+      .await
+      .unwrap();
+    let user_x_token = login_with_password(&state, email, password).await.unwrap();
+    let user = User::from_auth_token(&state, &user_x_token.auth_token).unwrap();
+
+    let png = {
+      let image = image::ImageBuffer::from_pixel(40, 20, image::Rgba([255u8, 0, 0, 255]));
+      let mut buffer = std::io::Cursor::new(Vec::new());
+      image
+        .write_to(&mut buffer, image::ImageFormat::Png)
+        .unwrap();
+      buffer.into_inner()
+    };
+    let user_id = upload_avatar(&state, user, &png).await.unwrap();
+
+    let get_variant = async |w: u32| {
+      return get_avatar_handler(
+        State(state.clone()),
+        Path(id_to_b64(&user_id.into_bytes())),
+        Query(ReadFileQuery {
+          w: Some(w),
+          h: Some(w),
+          fit: Some(ImageFit::Cover),
+          format: Some(ImageFormat::Jpeg),
+          ..Default::default()
+        }),
+        HeaderMap::new(),
+      )
+      .await;
+    };
+
+    let response = get_variant(16).await.unwrap();
+    assert_eq!(response.headers()["content-type"], "image/jpeg");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+      .await
+      .unwrap();
+    let variant = image::load_from_memory_with_format(&body, image::ImageFormat::Jpeg).unwrap();
+    assert_eq!((variant.width(), variant.height()), (16, 16));
+
+    // Only configured presets are allowed.
+    assert!(matches!(
+      get_variant(17).await.err(),
+      Some(AuthError::BadRequest(_))
+    ));
+  }
 }
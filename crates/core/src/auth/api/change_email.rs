@@ -10,7 +10,9 @@ use ts_rs::TS;
 use utoipa::{IntoParams, ToSchema};
 
 use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
 use crate::auth::jwt::EmailChangeTokenClaims;
+use crate::auth::session::SessionMetadata;
 use crate::auth::util::{user_by_id, validate_and_normalize_email_address, validate_redirect};
 use crate::auth::{AuthError, User};
 use crate::constants::USER_TABLE;
@@ -162,6 +164,7 @@ pub async fn change_email_confirm_handler(
   State(state): State<AppState>,
   Path(email_verification_token): Path<String>,
   Query(query): Query<ChangeEmailConfigQuery>,
+  session: SessionMetadata,
   // user: Option<User>,
 ) -> Result<Response, AuthError> {
   if state.demo_mode() {
@@ -185,12 +188,28 @@ pub async fn change_email_confirm_handler(
 
   let rows_affected = state
     .user_conn()
-    .execute(QUERY, params!(claims.new_email, claims.old_email))
+    .execute(
+      QUERY,
+      params!(claims.new_email.clone(), claims.old_email.clone()),
+    )
     .await?;
 
   return match rows_affected {
     0 => Err(AuthError::Conflict),
     1 => {
+      // NOTE: The confirmation link is unauthenticated, the token proves ownership.
+      audit(
+        &state,
+        AuditEvent::new(
+          Actor::anonymous(&claims.old_email),
+          AuditAction::EmailChange,
+        )
+        .target(claims.old_email)
+        .diff(serde_json::json!({ "new_email": claims.new_email }))
+        .client_ip(session.client_ip),
+      )
+      .await;
+
       if let Some(redirect) = redirect_uri {
         Ok(Redirect::to(&redirect).into_response())
       } else if state.public_dir().is_some() {
@@ -7,7 +7,9 @@ use trailbase_sqlite::named_params;
 use ts_rs::TS;
 use utoipa::{IntoParams, ToSchema};
 
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
 use crate::auth::password::{check_user_password, hash_password, validate_password_policy};
+use crate::auth::session::SessionMetadata;
 use crate::auth::util::validate_redirect;
 use crate::auth::{AuthError, User};
 use crate::constants::USER_TABLE;
@@ -52,6 +54,7 @@ pub async fn change_password_handler(
   State(state): State<AppState>,
   Query(query): Query<ChangePasswordQuery>,
   user: User,
+  session: SessionMetadata,
   either_request: Either<ChangePasswordRequest>,
 ) -> Result<Response, AuthError> {
   if state.demo_mode() {
@@ -87,10 +90,13 @@ pub async fn change_password_handler(
 
   let db_user = user_by_id(&state, &user.uuid).await?;
 
-  // Validate old password.
+  // Validate old password. Users who signed up via an external OAuth provider may not have a
+  // password yet, in which case they can set one.
   //
   // TODO: It would probably be good practice to check TOTP as well for users of multi-factor auth.
-  if let Err(_err) = check_user_password(&db_user, &request.old_password, state.demo_mode()) {
+  if !db_user.password_hash.is_empty()
+    && let Err(_err) = check_user_password(&db_user, &request.old_password, state.demo_mode())
+  {
     const MSG: &str = "invalid `old_password`";
     if !json && let Some(redirect_uri) = err_redirect_uri.or(redirect_uri) {
       return Ok(
@@ -128,6 +134,14 @@ pub async fn change_password_handler(
   return match rows_affected {
     0 => Err(AuthError::BadRequest("Invalid old password")),
     1 => {
+      audit(
+        &state,
+        AuditEvent::new(Actor::user(&user), AuditAction::PasswordChange)
+          .target(user.uuid.to_string())
+          .client_ip(session.client_ip),
+      )
+      .await;
+
       if let Some(ref redirect) = redirect_uri {
         Ok(
           Redirect::to(&format!(
@@ -5,7 +5,9 @@ use const_format::formatcp;
 use tower_cookies::Cookies;
 
 use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
 use crate::auth::AuthError;
+use crate::auth::session::SessionMetadata;
 use crate::auth::user::User;
 use crate::auth::util::{delete_all_sessions_for_user, remove_all_cookies};
 use crate::constants::USER_TABLE;
@@ -22,6 +24,7 @@ use crate::constants::USER_TABLE;
 pub(crate) async fn delete_handler(
   State(state): State<AppState>,
   user: User,
+  session: SessionMetadata,
   cookies: Cookies,
 ) -> Result<Response, AuthError> {
   let _ = delete_all_sessions_for_user(state.session_conn(), user.uuid).await;
@@ -33,6 +36,14 @@ pub(crate) async fn delete_handler(
     .execute(QUERY, [trailbase_sqlite::Value::Blob(user.uuid.into())])
     .await?;
 
+  audit(
+    &state,
+    AuditEvent::new(Actor::user(&user), AuditAction::UserDelete)
+      .target(user.uuid.to_string())
+      .client_ip(session.client_ip),
+  )
+  .await;
+
   remove_all_cookies(&cookies);
 
   return Ok((StatusCode::OK, "deleted").into_response());
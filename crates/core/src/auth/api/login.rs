@@ -9,6 +9,9 @@ use trailbase_sqlite::params;
 use ts_rs::TS;
 use utoipa::ToSchema;
 
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
+use crate::auth::api::webauthn::has_webauthn_credentials;
+use crate::auth::session::SessionMetadata;
 use crate::auth::user::DbUser;
 use crate::auth::util::{
   new_cookie, remove_cookie, user_by_email, validate_and_normalize_email_address,
@@ -74,6 +77,7 @@ pub struct MfaTokenResponse {
 pub(crate) async fn login_handler(
   State(state): State<AppState>,
   Query(query_login_input): Query<LoginInputParams>,
+  session: SessionMetadata,
   cookies: Cookies,
   either_request: Either<LoginRequest>,
 ) -> Result<Response, AuthError> {
@@ -124,6 +128,14 @@ pub(crate) async fn login_handler(
 
   let db_user = match check_credentials().await {
     Err(err) => {
+      audit(
+        &state,
+        AuditEvent::new(Actor::anonymous(&email), AuditAction::LoginFailed)
+          .diff(serde_json::json!({ "method": "password" }))
+          .client_ip(session.client_ip.clone()),
+      )
+      .await;
+
       if !json && let Some(redirect_uri) = redirect_uri.as_deref() {
         return Ok(auth_error_to_response(err, &cookies, Some(redirect_uri)));
       }
@@ -134,31 +146,56 @@ pub(crate) async fn login_handler(
   };
 
   // Does the user require multi-factor auth (MFA)?
-  if db_user.totp_secret.is_some() {
-    let mfa_token = state
-      .jwt()
-      .encode(&PendingAuthTokenClaims::new(
-        db_user.uuid(),
-        DEFAULT_MFA_TOKEN_TTL,
-      ))
-      .map_err(|err| AuthError::Internal(err.into()))?;
-
-    if json {
-      return Ok((StatusCode::FORBIDDEN, Json(MfaTokenResponse { mfa_token })).into_response());
-    } else {
-      let Some(mfa_redirect) = mfa_redirect_uri else {
-        return Err(AuthError::BadRequest("?mfa_redirect required"));
-      };
-
-      return Ok(Redirect::to(&format!("{mfa_redirect}?mfa_token={mfa_token}")).into_response());
-    }
+  if db_user.totp_secret.is_some() || has_webauthn_credentials(&state, &db_user.id).await? {
+    return build_mfa_required_response(
+      &state,
+      PendingAuthTokenClaims::new(db_user.uuid(), DEFAULT_MFA_TOKEN_TTL),
+      mfa_redirect_uri,
+      json,
+    );
   }
 
   // Otherwise build auth token or authorization code responses.
+  return build_login_response(&state, &db_user, &session, &cookies, params, json).await;
+}
+
+/// Respond with a pending auth token, which the client has to exchange for auth tokens by
+/// providing a second factor, e.g. a TOTP or a passkey.
+pub(crate) fn build_mfa_required_response(
+  state: &AppState,
+  claims: PendingAuthTokenClaims,
+  mfa_redirect_uri: Option<String>,
+  json: bool,
+) -> Result<Response, AuthError> {
+  let mfa_token = state
+    .jwt()
+    .encode(&claims)
+    .map_err(|err| AuthError::Internal(err.into()))?;
+
+  if json {
+    return Ok((StatusCode::FORBIDDEN, Json(MfaTokenResponse { mfa_token })).into_response());
+  }
+
+  let Some(mfa_redirect) = mfa_redirect_uri else {
+    return Err(AuthError::BadRequest("?mfa_redirect required"));
+  };
+
+  return Ok(Redirect::to(&format!("{mfa_redirect}?mfa_token={mfa_token}")).into_response());
+}
+
+/// Build auth token or authorization code responses for a successfully authenticated user.
+pub(crate) async fn build_login_response(
+  state: &AppState,
+  db_user: &DbUser,
+  session: &SessionMetadata,
+  cookies: &Cookies,
+  params: LoginParams,
+  json: bool,
+) -> Result<Response, AuthError> {
   return match params {
     // Auth-token flow.
     LoginParams::Password { redirect_uri } => {
-      build_auth_token_flow_response(&state, &db_user, &cookies, redirect_uri, json).await
+      build_auth_token_flow_response(state, db_user, session, cookies, redirect_uri, json).await
     }
     // Authorization-code flow.
     LoginParams::AuthorizationCodeFlowWithPkce {
@@ -166,8 +203,9 @@ pub(crate) async fn login_handler(
       pkce_code_challenge,
     } => {
       build_authorization_code_flow_and_pkce_response(
-        &state,
-        &db_user,
+        state,
+        db_user,
+        session,
         redirect_uri,
         pkce_code_challenge,
       )
@@ -189,6 +227,7 @@ pub(crate) async fn login_handler(
 pub(crate) async fn build_auth_token_flow_response(
   state: &AppState,
   db_user: &DbUser,
+  session: &SessionMetadata,
   cookies: &Cookies,
   redirect: Option<String>,
   is_json: bool,
@@ -196,8 +235,11 @@ pub(crate) async fn build_auth_token_flow_response(
   let (auth_token_ttl, refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
   let build_new_tokens = async || {
     let tokens = crate::auth::tokens::mint_new_tokens(
+      state.user_conn(),
       state.session_conn(),
       db_user,
+      session,
+      &state.custom_claims(),
       &auth_token_ttl,
       &refresh_token_ttl,
     )
@@ -213,7 +255,17 @@ pub(crate) async fn build_auth_token_flow_response(
     });
   };
 
-  return match build_new_tokens().await {
+  let result: Result<LoginResponse, AuthError> = build_new_tokens().await;
+  if result.is_ok() {
+    audit(
+      state,
+      AuditEvent::new(Actor::db_user(db_user), AuditAction::Login)
+        .client_ip(session.client_ip.clone()),
+    )
+    .await;
+  }
+
+  return match result {
     Ok(response) if is_json => Ok(Json(response).into_response()),
     Ok(response) => {
       cookies.add(new_cookie(
@@ -268,6 +320,7 @@ pub(crate) async fn build_auth_token_flow_response(
 async fn build_authorization_code_flow_and_pkce_response(
   state: &AppState,
   db_user: &DbUser,
+  session: &SessionMetadata,
   redirect: String,
   pkce_code_challenge: String,
 ) -> Result<Response, AuthError> {
@@ -296,6 +349,15 @@ async fn build_authorization_code_flow_and_pkce_response(
     )
     .await?;
 
+  if rows_affected == 1 {
+    audit(
+      state,
+      AuditEvent::new(Actor::db_user(db_user), AuditAction::Login)
+        .client_ip(session.client_ip.clone()),
+    )
+    .await;
+  }
+
   return match rows_affected {
     0 => Err(AuthError::BadRequest("invalid user")),
     1 => Ok(Redirect::to(&format!("{redirect}?code={authorization_code}")).into_response()),
@@ -332,6 +394,7 @@ pub struct LoginMfaRequest {
 pub(crate) async fn login_mfa_handler(
   State(state): State<AppState>,
   Query(query_login_input): Query<LoginInputParams>,
+  session: SessionMetadata,
   cookies: Cookies,
   either_request: Either<LoginMfaRequest>,
 ) -> Result<Response, AuthError> {
@@ -389,32 +452,25 @@ pub(crate) async fn login_mfa_handler(
 
   let totp = new_totp(&totp_rs::Secret::Encoded(totp_secret.clone()), None, None)?;
   if !totp.check_current(&user_totp).unwrap_or(false) {
+    audit(
+      &state,
+      AuditEvent::new(Actor::db_user(&db_user), AuditAction::LoginFailed)
+        .diff(serde_json::json!({ "method": "totp" }))
+        .client_ip(session.client_ip.clone()),
+    )
+    .await;
+
     return Err(AuthError::Unauthorized);
   }
 
-  // Otherwise build auth token or authorization code responses.
-  return match params {
-    // Auth-token flow.
-    LoginParams::Password { redirect_uri } => {
-      build_auth_token_flow_response(&state, &db_user, &cookies, redirect_uri, json).await
-    }
-    // Authorization-code flow.
-    LoginParams::AuthorizationCodeFlowWithPkce {
-      redirect_uri,
-      pkce_code_challenge,
-    } => {
-      build_authorization_code_flow_and_pkce_response(
-        &state,
-        &db_user,
-        redirect_uri,
-        pkce_code_challenge,
-      )
-      .await
-    }
-  };
+  return build_login_response(&state, &db_user, &session, &cookies, params, json).await;
 }
 
-fn auth_error_to_response(err: AuthError, cookies: &Cookies, redirect: Option<&str>) -> Response {
+pub(crate) fn auth_error_to_response(
+  err: AuthError,
+  cookies: &Cookies,
+  redirect: Option<&str>,
+) -> Response {
   let err_response: Response = err.into_response();
   let status = err_response.status();
 
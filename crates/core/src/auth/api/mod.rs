@@ -9,7 +9,9 @@ pub(super) mod logout;
 pub(super) mod otp;
 pub(super) mod refresh;
 pub(super) mod reset_password;
+pub(super) mod sessions;
 pub(super) mod status;
 pub(super) mod token;
 pub(super) mod totp;
 pub(super) mod verify_email;
+pub(super) mod webauthn;
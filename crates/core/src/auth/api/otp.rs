@@ -13,8 +13,10 @@ use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
 use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
 use crate::auth::AuthError;
 use crate::auth::api::login::{LoginResponse, build_auth_token_flow_response};
+use crate::auth::session::SessionMetadata;
 use crate::auth::util::{
   get_user_by_id, user_by_email, validate_and_normalize_email_address, validate_redirect,
 };
@@ -166,6 +168,7 @@ pub struct LoginOtpRequest {
 )]
 pub async fn login_otp_handler(
   State(state): State<AppState>,
+  session: SessionMetadata,
   cookies: Cookies,
   Query(query): Query<LoginOtpQuery>,
   either_request: Either<LoginOtpRequest>,
@@ -224,11 +227,22 @@ pub async fn login_otp_handler(
     .session_conn()
     .read_query_row_get(
       LOOKUP_OTP_QUERY,
-      params!(normalized_email, otp_code.to_string()),
+      params!(normalized_email.clone(), otp_code.to_string()),
       0,
     )
     .await?
   else {
+    audit(
+      &state,
+      AuditEvent::new(
+        Actor::anonymous(&normalized_email),
+        AuditAction::LoginFailed,
+      )
+      .diff(serde_json::json!({ "method": "otp" }))
+      .client_ip(session.client_ip.clone()),
+    )
+    .await;
+
     return Err(AuthError::Unauthorized);
   };
 
@@ -237,6 +251,7 @@ pub async fn login_otp_handler(
   return build_auth_token_flow_response(
     &state,
     &db_user,
+    &session,
     &cookies,
     redirect_uri.map(|uri| uri.to_string()),
     json,
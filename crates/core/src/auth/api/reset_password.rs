@@ -11,9 +11,11 @@ use ts_rs::TS;
 use utoipa::{IntoParams, ToSchema};
 
 use crate::app_state::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
 use crate::auth::AuthError;
 use crate::auth::jwt::PasswordResetTokenClaims;
 use crate::auth::password::{hash_password, validate_password_policy};
+use crate::auth::session::SessionMetadata;
 use crate::auth::util::{user_by_email, validate_and_normalize_email_address, validate_redirect};
 use crate::constants::USER_TABLE;
 use crate::email::Email;
@@ -132,6 +134,7 @@ pub struct ResetPasswordUpdateRequest {
 pub async fn reset_password_update_handler(
   State(state): State<AppState>,
   Query(query): Query<ResetPasswordUpdateQuery>,
+  session: SessionMetadata,
   either_request: Either<ResetPasswordUpdateRequest>,
 ) -> Result<Response, AuthError> {
   let request = match either_request {
@@ -166,13 +169,24 @@ pub async fn reset_password_update_handler(
     .user_conn()
     .execute(
       UPDATE_PASSWORD_QUERY,
-      params!(hashed_password, password_reset_claims.sub),
+      params!(hashed_password, password_reset_claims.sub.clone()),
     )
     .await?;
 
   return match rows_affected {
     0 => Err(AuthError::Unauthorized),
     1 => {
+      audit(
+        &state,
+        AuditEvent::new(
+          Actor::anonymous(&password_reset_claims.sub),
+          AuditAction::PasswordReset,
+        )
+        .target(password_reset_claims.sub)
+        .client_ip(session.client_ip),
+      )
+      .await;
+
       if let Some(redirect) = redirect_uri {
         Ok(
           Redirect::to(&format!(
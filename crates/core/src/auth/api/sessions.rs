@@ -0,0 +1,63 @@
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::ToSchema;
+
+use crate::app_state::AppState;
+use crate::auth::session::{Session, list_sessions, revoke_session};
+use crate::auth::tokens::Tokens;
+use crate::auth::{AuthError, User};
+use crate::util::b64_to_uuid;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct ListSessionsResponse {
+  pub sessions: Vec<Session>,
+}
+
+/// List the current user's sessions, i.e. signed-in devices.
+#[utoipa::path(
+  get,
+  path = "/sessions",
+  tag = "auth",
+  responses(
+    (status = 200, description = "Active sessions.", body = ListSessionsResponse)
+  )
+)]
+pub async fn list_sessions_handler(
+  State(state): State<AppState>,
+  tokens: Tokens,
+) -> Result<Json<ListSessionsResponse>, AuthError> {
+  let user_id =
+    b64_to_uuid(&tokens.auth_token_claims.sub).map_err(|_err| AuthError::Unauthorized)?;
+
+  let sessions = list_sessions(
+    state.session_conn(),
+    &user_id,
+    tokens.refresh_token.as_deref(),
+  )
+  .await?;
+
+  return Ok(Json(ListSessionsResponse { sessions }));
+}
+
+/// Revoke one of the current user's sessions, e.g. to sign out a lost device.
+#[utoipa::path(
+  delete,
+  path = "/sessions/{session_id}",
+  tag = "auth",
+  responses(
+    (status = 200, description = "Session revoked.")
+  )
+)]
+pub async fn revoke_session_handler(
+  State(state): State<AppState>,
+  Path(session_id): Path<i64>,
+  user: User,
+) -> Result<Response, AuthError> {
+  revoke_session(state.session_conn(), &user.uuid, session_id).await?;
+
+  return Ok((StatusCode::OK, "Session revoked").into_response());
+}
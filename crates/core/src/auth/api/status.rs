@@ -2,10 +2,13 @@ use axum::extract::{Json, State};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use utoipa::ToSchema;
+use uuid::Uuid;
 
 use crate::app_state::AppState;
 use crate::auth::AuthError;
+use crate::auth::oauth::identity::{LinkedIdentity, list_identities};
 use crate::auth::tokens::{Tokens, reauth_with_refresh_token};
+use crate::util::b64_to_id;
 
 #[derive(Debug, Serialize, Deserialize, TS, ToSchema)]
 #[ts(export)]
@@ -13,6 +16,8 @@ pub struct LoginStatusResponse {
   pub auth_token: Option<String>,
   pub refresh_token: Option<String>,
   pub csrf_token: Option<String>,
+  /// External OAuth identities linked to the signed-in user.
+  pub identities: Vec<LinkedIdentity>,
 }
 
 /// Check login status.
@@ -31,6 +36,7 @@ pub(crate) async fn login_status_handler(
   let Some(Tokens {
     auth_token_claims,
     refresh_token,
+    api_key,
   }) = tokens
   else {
     // Return Ok but all Nones.
@@ -38,9 +44,18 @@ pub(crate) async fn login_status_handler(
       auth_token: None,
       refresh_token: None,
       csrf_token: None,
+      identities: vec![],
     }));
   };
 
+  // API keys must not be exchanged for unscoped auth tokens.
+  if api_key.is_some() {
+    return Err(AuthError::Forbidden);
+  }
+
+  let user_id = b64_to_id(&auth_token_claims.sub).map_err(|_err| AuthError::Unauthorized)?;
+  let identities = list_identities(&state, &Uuid::from_bytes(user_id)).await?;
+
   // Decoding the auth token into its claims, already validated the therein contained expiration
   // time (exp). But rather than just re-encoding it, we refresh it. This ensures that the
   // session is still alive.
@@ -56,6 +71,7 @@ pub(crate) async fn login_status_handler(
       auth_token: Some(auth_token),
       refresh_token: Some(refresh_token),
       csrf_token: Some(claims.csrf_token),
+      identities,
     }));
   } else {
     // Fall back case: we don't have a refresh token so we cannot validate if a session is still
@@ -72,6 +88,7 @@ pub(crate) async fn login_status_handler(
       auth_token: Some(auth_token),
       refresh_token: None,
       csrf_token: Some(auth_token_claims.csrf_token),
+      identities,
     }));
   }
 }
@@ -8,6 +8,7 @@ use uuid::Uuid;
 
 use crate::app_state::AppState;
 use crate::auth::AuthError;
+use crate::auth::session::SessionMetadata;
 use crate::auth::tokens::mint_new_tokens;
 use crate::auth::util::{derive_pkce_code_challenge, get_user_by_id};
 use crate::constants::{AUTHORIZATION_CODE_TABLE, VERIFICATION_CODE_LENGTH};
@@ -41,6 +42,7 @@ pub struct TokenResponse {
 )]
 pub(crate) async fn auth_code_to_token_handler(
   State(state): State<AppState>,
+  session: SessionMetadata,
   Json(request): Json<AuthCodeToTokenRequest>,
 ) -> Result<Json<TokenResponse>, AuthError> {
   let authorization_code = match request.authorization_code {
@@ -62,6 +64,7 @@ pub(crate) async fn auth_code_to_token_handler(
         authorization_code = $1 \
           AND pkce_code_challenge = $2 \
           AND expires > UNIXEPOCH() \
+          AND client_id IS NULL \
     "
   );
 
@@ -82,8 +85,11 @@ pub(crate) async fn auth_code_to_token_handler(
   let (auth_token_ttl, refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
 
   let tokens = mint_new_tokens(
+    state.user_conn(),
     state.session_conn(),
     &db_user,
+    &session,
+    &state.custom_claims(),
     &auth_token_ttl,
     &refresh_token_ttl,
   )
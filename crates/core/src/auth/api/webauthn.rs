@@ -0,0 +1,717 @@
+use axum::{
+  extract::{Json, Path, Query, State},
+  http::StatusCode,
+  response::{IntoResponse, Response},
+};
+use base64::prelude::*;
+use const_format::formatcp;
+use serde::{Deserialize, Serialize};
+use tower_cookies::Cookies;
+use trailbase_sqlite::params;
+use ts_rs::TS;
+use utoipa::ToSchema;
+
+use crate::app_state::AppState;
+use crate::auth::api::login::{
+  auth_error_to_response, build_login_response, build_mfa_required_response,
+};
+use crate::auth::jwt::{
+  AuthMethod, PendingAuthTokenClaims, WebAuthnCeremony, WebAuthnChallengeClaims,
+};
+use crate::auth::login_params::{LoginInputParams, ResponseType, build_and_validate_input_params};
+use crate::auth::session::SessionMetadata;
+use crate::auth::user::DbUser;
+use crate::auth::util::{user_by_email, user_by_id, validate_and_normalize_email_address};
+use crate::auth::webauthn::{
+  AuthenticationCredential, CreationOptions, CredentialDescriptor, RegistrationCredential,
+  RelyingParty, RequestOptions, UserEntity, decode_b64, deserialize_credential, new_challenge,
+  verify_assertion, verify_registration,
+};
+use crate::auth::{AuthError, User};
+use crate::constants::{
+  DEFAULT_MFA_TOKEN_TTL, DEFAULT_WEBAUTHN_CHALLENGE_TTL, WEBAUTHN_CHALLENGE_TABLE,
+  WEBAUTHN_CREDENTIAL_TABLE,
+};
+use crate::extract::Either;
+use crate::util::{b64_to_uuid, uuid_to_b64};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct WebAuthnRegistrationOptions {
+  /// Opaque token, which has to be passed back when finishing the ceremony.
+  pub challenge_token: String,
+  pub options: CreationOptions,
+}
+
+/// Start registering a new passkey for the current user.
+#[utoipa::path(
+  post,
+  path = "/webauthn/register/start",
+  tag = "auth",
+  responses(
+    (status = 200, description = "Credential creation options.", body = WebAuthnRegistrationOptions)
+  )
+)]
+pub async fn register_webauthn_start_handler(
+  State(state): State<AppState>,
+  user: User,
+) -> Result<Json<WebAuthnRegistrationOptions>, AuthError> {
+  if state.demo_mode() {
+    return Err(AuthError::BadRequest("Disallowed in demo"));
+  }
+
+  let rp = RelyingParty::from_state(&state)?;
+  let (challenge, challenge_token) =
+    new_challenge_token(&state, Some(&user.uuid), WebAuthnCeremony::Register).await?;
+
+  // Prevent registering the same authenticator twice.
+  let exclude_credentials = credentials_for_user(&state, &user.uuid.into_bytes())
+    .await?
+    .iter()
+    .map(|c| CredentialDescriptor::new(&c.id))
+    .collect();
+
+  return Ok(Json(WebAuthnRegistrationOptions {
+    challenge_token,
+    options: CreationOptions::new(
+      &rp,
+      UserEntity {
+        id: BASE64_URL_SAFE_NO_PAD.encode(user.uuid.as_bytes()),
+        name: user.email.clone(),
+        display_name: user.email,
+      },
+      challenge,
+      exclude_credentials,
+    ),
+  }));
+}
+
+#[derive(Debug, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct RegisterWebAuthnRequest {
+  pub challenge_token: String,
+  /// User-provided label for the new passkey.
+  pub name: Option<String>,
+  #[serde(deserialize_with = "deserialize_credential")]
+  pub credential: RegistrationCredential,
+}
+
+/// Finish registering a new passkey for the current user.
+#[utoipa::path(
+  post,
+  path = "/webauthn/register/finish",
+  tag = "auth",
+  request_body = RegisterWebAuthnRequest,
+  responses(
+    (status = 200, description = "Passkey registered.")
+  )
+)]
+pub async fn register_webauthn_finish_handler(
+  State(state): State<AppState>,
+  user: User,
+  either_request: Either<RegisterWebAuthnRequest>,
+) -> Result<Response, AuthError> {
+  let (request, _json) = match either_request {
+    Either::Json(req) => (req, true),
+    Either::Multipart(req, _) => (req, false),
+    Either::Form(req) => (req, false),
+  };
+
+  let rp = RelyingParty::from_state(&state)?;
+  let claims = WebAuthnChallengeClaims::decode(
+    state.jwt(),
+    &request.challenge_token,
+    WebAuthnCeremony::Register,
+  )
+  .map_err(|_err| AuthError::BadRequest("invalid challenge"))?;
+  if claims.sub.as_deref() != Some(user.id.as_str())
+    || !consume_challenge(&state, &claims.challenge).await?
+  {
+    return Err(AuthError::BadRequest("invalid challenge"));
+  }
+
+  let registration =
+    verify_registration(&rp, &claims.challenge, &request.credential).map_err(|err| {
+      log::debug!("Passkey registration failed: {err}");
+      return AuthError::BadRequest("invalid credential");
+    })?;
+
+  const INSERT_QUERY: &str = formatcp!(
+    "INSERT INTO '{WEBAUTHN_CREDENTIAL_TABLE}' (id, user, name, public_key, sign_count) \
+     VALUES ($1, $2, $3, $4, $5)"
+  );
+
+  state
+    .user_conn()
+    .execute(
+      INSERT_QUERY,
+      params!(
+        registration.credential_id,
+        user.uuid.into_bytes(),
+        request.name,
+        registration.public_key,
+        registration.sign_count as i64,
+      ),
+    )
+    .await?;
+
+  return Ok((StatusCode::OK, "Passkey registered").into_response());
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct WebAuthnLoginStartRequest {
+  /// Optional e-mail address to restrict the ceremony to the user's passkeys. Otherwise, the
+  /// authenticator will offer discoverable credentials.
+  pub email: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct WebAuthnAuthenticationOptions {
+  /// Opaque token, which has to be passed back when finishing the ceremony.
+  pub challenge_token: String,
+  pub options: RequestOptions,
+}
+
+/// Start logging in with a passkey.
+#[utoipa::path(
+  post,
+  path = "/webauthn/login/start",
+  tag = "auth",
+  request_body = WebAuthnLoginStartRequest,
+  responses(
+    (status = 200, description = "Credential request options.", body = WebAuthnAuthenticationOptions)
+  )
+)]
+pub async fn login_webauthn_start_handler(
+  State(state): State<AppState>,
+  Json(request): Json<WebAuthnLoginStartRequest>,
+) -> Result<Json<WebAuthnAuthenticationOptions>, AuthError> {
+  let rp = RelyingParty::from_state(&state)?;
+  let (challenge, challenge_token) =
+    new_challenge_token(&state, None, WebAuthnCeremony::Login).await?;
+
+  let allow_credentials = match request.email {
+    Some(email) => {
+      let normalized_email = validate_and_normalize_email_address(&email)?;
+      // NOTE: Don't leak whether the user exists, just fall back to discoverable credentials.
+      match user_by_email(&state, &normalized_email).await {
+        Ok(db_user) => credentials_for_user(&state, &db_user.id)
+          .await?
+          .iter()
+          .map(|c| CredentialDescriptor::new(&c.id))
+          .collect(),
+        Err(_) => vec![],
+      }
+    }
+    None => vec![],
+  };
+
+  return Ok(Json(WebAuthnAuthenticationOptions {
+    challenge_token,
+    options: RequestOptions::new(&rp, challenge, allow_credentials),
+  }));
+}
+
+#[derive(Debug, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct WebAuthnLoginRequest {
+  pub challenge_token: String,
+  #[serde(deserialize_with = "deserialize_credential")]
+  pub credential: AuthenticationCredential,
+
+  // Mirror of LoginInputParams.
+  pub redirect_uri: Option<String>,
+  pub mfa_redirect_uri: Option<String>,
+  pub response_type: Option<ResponseType>,
+  pub pkce_code_challenge: Option<String>,
+}
+
+/// Finish logging in with a passkey.
+///
+/// Passkeys, which verified the user, e.g. using biometrics or a PIN, are multi-factor on their
+/// own. Otherwise, users with TOTP enabled will still be asked for their TOTP.
+#[utoipa::path(
+  post,
+  path = "/webauthn/login/finish",
+  tag = "auth",
+  params(LoginInputParams),
+  request_body = WebAuthnLoginRequest,
+  responses(
+    (status = 200, description = "Auth, refresh & CSRF tokens.", body = crate::auth::api::login::LoginResponse),
+    (status = 303, description = "Form Fail OR Auth, refresh & CSRF tokens via cookies."),
+    (status = 401, description = "Unauthorized"),
+    (status = 403, description = "Forbidden, when login succeeded but MFA is needed.", body = crate::auth::api::login::MfaTokenResponse),
+  )
+)]
+pub async fn login_webauthn_finish_handler(
+  State(state): State<AppState>,
+  Query(query_login_input): Query<LoginInputParams>,
+  session: SessionMetadata,
+  cookies: Cookies,
+  either_request: Either<WebAuthnLoginRequest>,
+) -> Result<Response, AuthError> {
+  let (
+    WebAuthnLoginRequest {
+      challenge_token,
+      credential,
+      redirect_uri,
+      mfa_redirect_uri,
+      response_type,
+      pkce_code_challenge,
+    },
+    json,
+  ) = match either_request {
+    Either::Json(req) => (req, true),
+    Either::Form(req) => (req, false),
+    Either::Multipart(req, _) => (req, false),
+  };
+
+  let params = build_and_validate_input_params(
+    &state,
+    query_login_input.merge(LoginInputParams {
+      redirect_uri: redirect_uri.clone(),
+      mfa_redirect_uri: mfa_redirect_uri.clone(),
+      response_type,
+      pkce_code_challenge,
+    }),
+  )?;
+
+  let check_credentials = async || -> Result<(DbUser, bool), AuthError> {
+    let claims =
+      WebAuthnChallengeClaims::decode(state.jwt(), &challenge_token, WebAuthnCeremony::Login)
+        .map_err(|_err| AuthError::Unauthorized)?;
+    if !consume_challenge(&state, &claims.challenge).await? {
+      return Err(AuthError::Unauthorized);
+    }
+
+    return check_assertion(&state, &claims, &credential, None).await;
+  };
+
+  let (db_user, user_verified) = match check_credentials().await {
+    Err(err) => {
+      if !json && let Some(redirect_uri) = redirect_uri.as_deref() {
+        return Ok(auth_error_to_response(err, &cookies, Some(redirect_uri)));
+      }
+
+      return Err(err);
+    }
+    Ok(result) => result,
+  };
+
+  if !user_verified && db_user.totp_secret.is_some() {
+    return build_mfa_required_response(
+      &state,
+      PendingAuthTokenClaims::new(db_user.uuid(), DEFAULT_MFA_TOKEN_TTL)
+        .with_method(AuthMethod::WebAuthn),
+      mfa_redirect_uri,
+      json,
+    );
+  }
+
+  return build_login_response(&state, &db_user, &session, &cookies, params, json).await;
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct WebAuthnMfaStartRequest {
+  pub mfa_token: String,
+}
+
+/// Start a passkey ceremony to provide a second factor for a pending login.
+#[utoipa::path(
+  post,
+  path = "/webauthn/mfa/start",
+  tag = "auth",
+  request_body = WebAuthnMfaStartRequest,
+  responses(
+    (status = 200, description = "Credential request options.", body = WebAuthnAuthenticationOptions)
+  )
+)]
+pub async fn mfa_webauthn_start_handler(
+  State(state): State<AppState>,
+  Json(request): Json<WebAuthnMfaStartRequest>,
+) -> Result<Json<WebAuthnAuthenticationOptions>, AuthError> {
+  let rp = RelyingParty::from_state(&state)?;
+  let user_id = pending_passkey_mfa_user(&state, &request.mfa_token)?;
+
+  let allow_credentials: Vec<_> = credentials_for_user(&state, &user_id.into_bytes())
+    .await?
+    .iter()
+    .map(|c| CredentialDescriptor::new(&c.id))
+    .collect();
+  if allow_credentials.is_empty() {
+    return Err(AuthError::FailedDependency("No passkeys registered".into()));
+  }
+
+  let (challenge, challenge_token) =
+    new_challenge_token(&state, Some(&user_id), WebAuthnCeremony::SecondFactor).await?;
+
+  return Ok(Json(WebAuthnAuthenticationOptions {
+    challenge_token,
+    options: RequestOptions::new(&rp, challenge, allow_credentials),
+  }));
+}
+
+#[derive(Debug, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct WebAuthnMfaRequest {
+  pub mfa_token: String,
+  pub challenge_token: String,
+  #[serde(deserialize_with = "deserialize_credential")]
+  pub credential: AuthenticationCredential,
+
+  // Mirror of LoginInputParams.
+  pub redirect_uri: Option<String>,
+  pub mfa_redirect_uri: Option<String>,
+  pub response_type: Option<ResponseType>,
+  pub pkce_code_challenge: Option<String>,
+}
+
+/// Finish a pending login using a passkey as second factor.
+#[utoipa::path(
+  post,
+  path = "/webauthn/mfa/finish",
+  tag = "auth",
+  params(LoginInputParams),
+  request_body = WebAuthnMfaRequest,
+  responses(
+    (status = 200, description = "Auth & refresh tokens.", body = crate::auth::api::login::LoginResponse)
+  )
+)]
+pub async fn mfa_webauthn_finish_handler(
+  State(state): State<AppState>,
+  Query(query_login_input): Query<LoginInputParams>,
+  session: SessionMetadata,
+  cookies: Cookies,
+  either_request: Either<WebAuthnMfaRequest>,
+) -> Result<Response, AuthError> {
+  let (
+    WebAuthnMfaRequest {
+      mfa_token,
+      challenge_token,
+      credential,
+      redirect_uri,
+      mfa_redirect_uri,
+      response_type,
+      pkce_code_challenge,
+    },
+    json,
+  ) = match either_request {
+    Either::Json(req) => (req, true),
+    Either::Form(req) => (req, false),
+    Either::Multipart(req, _) => (req, false),
+  };
+
+  let params = build_and_validate_input_params(
+    &state,
+    query_login_input.merge(LoginInputParams {
+      redirect_uri,
+      mfa_redirect_uri,
+      response_type,
+      pkce_code_challenge,
+    }),
+  )?;
+
+  let user_id = pending_passkey_mfa_user(&state, &mfa_token)?;
+  let claims = WebAuthnChallengeClaims::decode(
+    state.jwt(),
+    &challenge_token,
+    WebAuthnCeremony::SecondFactor,
+  )
+  .map_err(|_err| AuthError::Unauthorized)?;
+  if !consume_challenge(&state, &claims.challenge).await? {
+    return Err(AuthError::Unauthorized);
+  }
+
+  let (db_user, _user_verified) =
+    check_assertion(&state, &claims, &credential, Some(&user_id)).await?;
+
+  return build_login_response(&state, &db_user, &session, &cookies, params, json).await;
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct WebAuthnCredential {
+  /// Url-safe Base64 encoded credential id.
+  pub id: String,
+  pub name: Option<String>,
+  pub created: i64,
+  pub last_used: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct ListWebAuthnCredentialsResponse {
+  pub credentials: Vec<WebAuthnCredential>,
+}
+
+/// List the current user's passkeys.
+#[utoipa::path(
+  get,
+  path = "/webauthn/credentials",
+  tag = "auth",
+  responses(
+    (status = 200, description = "Registered passkeys.", body = ListWebAuthnCredentialsResponse)
+  )
+)]
+pub async fn list_webauthn_credentials_handler(
+  State(state): State<AppState>,
+  user: User,
+) -> Result<Json<ListWebAuthnCredentialsResponse>, AuthError> {
+  let credentials = credentials_for_user(&state, &user.uuid.into_bytes()).await?;
+
+  return Ok(Json(ListWebAuthnCredentialsResponse {
+    credentials: credentials
+      .into_iter()
+      .map(|c| WebAuthnCredential {
+        id: BASE64_URL_SAFE_NO_PAD.encode(&c.id),
+        name: c.name,
+        created: c.created,
+        last_used: c.last_used,
+      })
+      .collect(),
+  }));
+}
+
+/// Delete one of the current user's passkeys.
+#[utoipa::path(
+  delete,
+  path = "/webauthn/credentials/{credential_id}",
+  tag = "auth",
+  responses(
+    (status = 200, description = "Passkey deleted.")
+  )
+)]
+pub async fn delete_webauthn_credential_handler(
+  State(state): State<AppState>,
+  Path(credential_id): Path<String>,
+  user: User,
+) -> Result<Response, AuthError> {
+  let credential_id =
+    decode_b64(&credential_id).map_err(|_err| AuthError::BadRequest("invalid id"))?;
+
+  const DELETE_QUERY: &str =
+    formatcp!("DELETE FROM '{WEBAUTHN_CREDENTIAL_TABLE}' WHERE id = $1 AND user = $2");
+
+  let rows_affected = state
+    .user_conn()
+    .execute(DELETE_QUERY, params!(credential_id, user.uuid.into_bytes()))
+    .await?;
+  if rows_affected == 0 {
+    return Err(AuthError::NotFound);
+  }
+
+  return Ok((StatusCode::OK, "Passkey deleted").into_response());
+}
+
+/// Whether the given user has any passkeys, in which case they're required as a second factor
+/// for password logins.
+pub(crate) async fn has_webauthn_credentials(
+  state: &AppState,
+  user_id: &[u8; 16],
+) -> Result<bool, AuthError> {
+  const QUERY: &str =
+    formatcp!("SELECT EXISTS(SELECT 1 FROM '{WEBAUTHN_CREDENTIAL_TABLE}' WHERE user = $1)");
+
+  return Ok(
+    state
+      .user_conn()
+      .read_query_row_get::<bool>(QUERY, params!(*user_id), 0)
+      .await?
+      .unwrap_or(false),
+  );
+}
+
+struct DbWebAuthnCredential {
+  id: Vec<u8>,
+  user: [u8; 16],
+  name: Option<String>,
+  public_key: Vec<u8>,
+  sign_count: i64,
+  created: i64,
+  last_used: Option<i64>,
+}
+
+impl TryFrom<trailbase_sqlite::Row> for DbWebAuthnCredential {
+  type Error = trailbase_sqlite::from_sql::FromSqlError;
+
+  fn try_from(row: trailbase_sqlite::Row) -> Result<Self, Self::Error> {
+    return Ok(Self {
+      id: row.get(0)?,
+      user: row.get(1)?,
+      name: row.get(2)?,
+      public_key: row.get(3)?,
+      sign_count: row.get(4)?,
+      created: row.get(5)?,
+      last_used: row.get(6)?,
+    });
+  }
+}
+
+const CREDENTIAL_COLUMNS: &str = "id, user, name, public_key, sign_count, created, last_used";
+
+async fn credentials_for_user(
+  state: &AppState,
+  user_id: &[u8; 16],
+) -> Result<Vec<DbWebAuthnCredential>, AuthError> {
+  const QUERY: &str = formatcp!(
+    "SELECT {CREDENTIAL_COLUMNS} FROM '{WEBAUTHN_CREDENTIAL_TABLE}' WHERE user = $1 ORDER BY created"
+  );
+
+  let rows = state
+    .user_conn()
+    .read_query_rows(QUERY, params!(*user_id))
+    .await?;
+
+  return rows
+    .into_iter()
+    .map(|row| DbWebAuthnCredential::try_from(row).map_err(|err| AuthError::Internal(err.into())))
+    .collect();
+}
+
+/// Issues a new challenge. Besides being handed to the client as signed token, the challenge is
+/// recorded to make sure it can only be used once.
+async fn new_challenge_token(
+  state: &AppState,
+  user_id: Option<&uuid::Uuid>,
+  ceremony: WebAuthnCeremony,
+) -> Result<(String, String), AuthError> {
+  let claims = WebAuthnChallengeClaims::new(
+    user_id,
+    ceremony,
+    new_challenge(),
+    DEFAULT_WEBAUTHN_CHALLENGE_TTL,
+  );
+  let token = state
+    .jwt()
+    .encode(&claims)
+    .map_err(|err| AuthError::Internal(err.into()))?;
+
+  const QUERY: &str =
+    formatcp!("INSERT INTO '{WEBAUTHN_CHALLENGE_TABLE}' (challenge, expires) VALUES ($1, $2)");
+  state
+    .session_conn()
+    .execute(QUERY, params!(claims.challenge.clone(), claims.exp))
+    .await?;
+
+  return Ok((claims.challenge, token));
+}
+
+/// Consumes an outstanding challenge. Returns false if the challenge has already been used or
+/// expired.
+///
+/// NOTE: Challenges are consumed regardless of whether the ceremony succeeds.
+async fn consume_challenge(state: &AppState, challenge: &str) -> Result<bool, AuthError> {
+  const QUERY: &str = formatcp!(
+    "DELETE FROM '{WEBAUTHN_CHALLENGE_TABLE}' WHERE challenge = $1 AND expires > UNIXEPOCH()"
+  );
+
+  let rows_affected = state
+    .session_conn()
+    .execute(QUERY, params!(challenge.to_string()))
+    .await?;
+  return Ok(rows_affected > 0);
+}
+
+/// Validates the pending auth token of a password login awaiting a passkey as second factor.
+fn pending_passkey_mfa_user(state: &AppState, mfa_token: &str) -> Result<uuid::Uuid, AuthError> {
+  let PendingAuthTokenClaims { sub, method, .. } =
+    PendingAuthTokenClaims::from_pending_auth_token(state.jwt(), mfa_token)
+      .map_err(|_err| AuthError::Unauthorized)?;
+
+  // A passkey cannot be both, first and second factor.
+  if method == AuthMethod::WebAuthn {
+    return Err(AuthError::Unauthorized);
+  }
+
+  return b64_to_uuid(&sub).map_err(|_err| AuthError::Unauthorized);
+}
+
+/// Verifies the assertion against the stored credential and bumps its sign count. Returns the
+/// credential's owner and whether the authenticator verified the user.
+async fn check_assertion(
+  state: &AppState,
+  claims: &WebAuthnChallengeClaims,
+  credential: &AuthenticationCredential,
+  expected_user: Option<&uuid::Uuid>,
+) -> Result<(DbUser, bool), AuthError> {
+  let rp = RelyingParty::from_state(state)?;
+  let credential_id = decode_b64(&credential.id).map_err(|_err| AuthError::Unauthorized)?;
+
+  const QUERY: &str =
+    formatcp!("SELECT {CREDENTIAL_COLUMNS} FROM '{WEBAUTHN_CREDENTIAL_TABLE}' WHERE id = $1");
+  let Some(row) = state
+    .user_conn()
+    .read_query_row(QUERY, params!(credential_id.clone()))
+    .await?
+  else {
+    return Err(AuthError::Unauthorized);
+  };
+  let stored =
+    DbWebAuthnCredential::try_from(row).map_err(|err| AuthError::Internal(err.into()))?;
+
+  if let Some(expected_user) = expected_user
+    && stored.user != expected_user.into_bytes()
+  {
+    return Err(AuthError::Unauthorized);
+  }
+  if let Some(ref sub) = claims.sub
+    && *sub != uuid_to_b64(&uuid::Uuid::from_bytes(stored.user))
+  {
+    return Err(AuthError::Unauthorized);
+  }
+
+  let verified = verify_assertion(
+    &rp,
+    &claims.challenge,
+    credential,
+    &stored.public_key,
+    stored.sign_count as u32,
+  )
+  .map_err(|err| {
+    log::debug!("Passkey assertion failed: {err}");
+    return AuthError::Unauthorized;
+  })?;
+
+  const UPDATE_QUERY: &str = formatcp!(
+    "UPDATE '{WEBAUTHN_CREDENTIAL_TABLE}' SET sign_count = $1, last_used = UNIXEPOCH() WHERE id = $2"
+  );
+  state
+    .user_conn()
+    .execute(
+      UPDATE_QUERY,
+      params!(verified.sign_count as i64, credential_id),
+    )
+    .await?;
+
+  let db_user = user_by_id(state, &uuid::Uuid::from_bytes(stored.user))
+    .await
+    .map_err(|_err| AuthError::Unauthorized)?;
+
+  return Ok((db_user, verified.user_verified));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app_state::test_state;
+
+  #[tokio::test]
+  async fn test_challenges_are_single_use() {
+    let state = test_state(None).await.unwrap();
+
+    let (challenge, token) = new_challenge_token(&state, None, WebAuthnCeremony::Login)
+      .await
+      .unwrap();
+    let claims =
+      WebAuthnChallengeClaims::decode(state.jwt(), &token, WebAuthnCeremony::Login).unwrap();
+    assert_eq!(challenge, claims.challenge);
+
+    assert!(consume_challenge(&state, &challenge).await.unwrap());
+    // Replays are rejected.
+    assert!(!consume_challenge(&state, &challenge).await.unwrap());
+    assert!(!consume_challenge(&state, "unknown").await.unwrap());
+  }
+}
@@ -0,0 +1,333 @@
+//! Long-lived API keys for machine clients.
+//!
+//! API keys are minted by admins for a given (service account) user. Requests presenting an API
+//! key act as that user, i.e. `_USER_` in access rules refers to the owner, but are additionally
+//! restricted to the key's scope: a set of Record APIs and permitted operations.
+use axum::extract::Request;
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::Response;
+use const_format::formatcp;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use trailbase_sqlite::{Connection, params};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::auth::AuthError;
+use crate::auth::jwt::AuthTokenClaims;
+use crate::auth::roles::get_user_roles;
+use crate::auth::util::get_user_by_id;
+use crate::config::proto::PermissionFlag;
+use crate::constants::{API_KEY_AUTH_TOKEN_TTL, API_KEY_LENGTH, API_KEY_PREFIX, API_KEY_TABLE};
+use crate::rand::random_alphanumeric;
+use crate::records::Permission;
+use crate::util::get_header;
+
+/// Restrictions of an API key, enforced in addition to the owner's regular access checks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApiKeyScope {
+  /// Record APIs the key may access. `None` means all.
+  pub record_apis: Option<Vec<String>>,
+  /// Bitmask of permitted [Permission]s.
+  pub permissions: u8,
+}
+
+impl ApiKeyScope {
+  pub(crate) fn allows(&self, api_name: &str, p: Permission) -> bool {
+    if self.permissions & (p as u8) == 0 {
+      return false;
+    }
+
+    return match self.record_apis {
+      Some(ref apis) => apis.iter().any(|name| name == api_name),
+      None => true,
+    };
+  }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct ApiKey {
+  pub id: Uuid,
+  pub user: Uuid,
+  pub name: String,
+  pub record_apis: Option<Vec<String>>,
+  pub permissions: Vec<String>,
+  pub expires: Option<i64>,
+  pub created: i64,
+}
+
+/// Parameters for minting a new API key.
+#[derive(Clone, Debug, Default)]
+pub struct NewApiKey {
+  pub name: String,
+  pub record_apis: Option<Vec<String>>,
+  pub permissions: Vec<PermissionFlag>,
+  pub expires: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Mints a new API key for the given user, returning its id and the secret key. The key itself
+/// is not stored and cannot be recovered later.
+pub async fn mint_api_key(
+  user_conn: &Connection,
+  user_id: &Uuid,
+  new_key: NewApiKey,
+) -> Result<(Uuid, String), AuthError> {
+  if new_key.name.is_empty() {
+    return Err(AuthError::BadRequest("missing name"));
+  }
+
+  let permissions = permissions_to_mask(&new_key.permissions);
+  if permissions == 0 {
+    return Err(AuthError::BadRequest("missing permissions"));
+  }
+
+  let record_apis = new_key
+    .record_apis
+    .map(|apis| serde_json::to_string(&apis))
+    .transpose()
+    .map_err(|err| AuthError::Internal(err.into()))?;
+
+  let key = format!("{API_KEY_PREFIX}{}", random_alphanumeric(API_KEY_LENGTH));
+
+  const QUERY: &str = formatcp!(
+    "\
+      INSERT INTO '{API_KEY_TABLE}' \
+        (user, name, key_hash, record_apis, permissions, expires) \
+      VALUES \
+        ($1, $2, $3, $4, $5, $6) \
+      RETURNING id \
+    "
+  );
+
+  let id: Uuid = user_conn
+    .write_query_value(
+      QUERY,
+      params!(
+        user_id.into_bytes(),
+        new_key.name,
+        hash_api_key(&key),
+        record_apis,
+        permissions as i64,
+        new_key.expires.map(|e| e.timestamp()),
+      ),
+    )
+    .await?
+    .ok_or_else(|| AuthError::Internal("insert failed".into()))?;
+
+  return Ok((id, key));
+}
+
+/// Revokes, i.e. deletes, the API key with the given id.
+pub async fn revoke_api_key(user_conn: &Connection, id: &Uuid) -> Result<(), AuthError> {
+  const QUERY: &str = formatcp!("DELETE FROM '{API_KEY_TABLE}' WHERE id = $1");
+
+  let rows_affected = user_conn.execute(QUERY, params!(id.into_bytes())).await?;
+  if rows_affected == 0 {
+    return Err(AuthError::NotFound);
+  }
+  return Ok(());
+}
+
+/// Lists API keys, optionally only the ones owned by the given user.
+pub async fn list_api_keys(
+  user_conn: &Connection,
+  user_id: Option<&Uuid>,
+) -> Result<Vec<ApiKey>, AuthError> {
+  const QUERY: &str = formatcp!(
+    "\
+      SELECT id, user, name, record_apis, permissions, expires, created \
+      FROM '{API_KEY_TABLE}' \
+      WHERE $1 IS NULL OR user = $1 \
+      ORDER BY created \
+    "
+  );
+
+  let rows = user_conn
+    .read_query_rows(QUERY, params!(user_id.map(|id| id.into_bytes().to_vec())))
+    .await?;
+
+  return rows
+    .into_iter()
+    .map(|row| -> Result<ApiKey, AuthError> {
+      let internal =
+        |err: trailbase_sqlite::from_sql::FromSqlError| AuthError::Internal(err.into());
+
+      let record_apis: Option<String> = row.get(3).map_err(internal)?;
+      let permissions: i64 = row.get(4).map_err(internal)?;
+
+      return Ok(ApiKey {
+        id: Uuid::from_bytes(row.get(0).map_err(internal)?),
+        user: Uuid::from_bytes(row.get(1).map_err(internal)?),
+        name: row.get(2).map_err(internal)?,
+        record_apis: record_apis
+          .map(|apis| serde_json::from_str(&apis))
+          .transpose()
+          .map_err(|err| AuthError::Internal(err.into()))?,
+        permissions: mask_to_permissions(permissions as u8)
+          .into_iter()
+          .map(|p| p.as_str_name().to_string())
+          .collect(),
+        expires: row.get(5).map_err(internal)?,
+        created: row.get(6).map_err(internal)?,
+      });
+    })
+    .collect();
+}
+
+/// Exchanges a presented API key for ephemeral auth token claims of the owning user and the
+/// key's scope.
+pub(crate) async fn authenticate_with_api_key(
+  user_conn: &Connection,
+  key: &str,
+) -> Result<(AuthTokenClaims, ApiKeyScope), AuthError> {
+  const QUERY: &str = formatcp!(
+    "\
+      SELECT user, record_apis, permissions \
+      FROM '{API_KEY_TABLE}' \
+      WHERE key_hash = $1 AND (expires IS NULL OR expires > UNIXEPOCH()) \
+    "
+  );
+
+  let Some(row) = user_conn
+    .read_query_row(QUERY, params!(hash_api_key(key)))
+    .await?
+  else {
+    // Unknown, revoked or expired key.
+    return Err(AuthError::Unauthorized);
+  };
+
+  let internal = |err: trailbase_sqlite::from_sql::FromSqlError| AuthError::Internal(err.into());
+  let user_id: [u8; 16] = row.get(0).map_err(internal)?;
+  let record_apis: Option<String> = row.get(1).map_err(internal)?;
+  let permissions: i64 = row.get(2).map_err(internal)?;
+
+  let db_user = get_user_by_id(user_conn, &Uuid::from_bytes(user_id))
+    .await
+    .map_err(|_err| AuthError::Unauthorized)?;
+  if !db_user.verified {
+    return Err(AuthError::Unauthorized);
+  }
+
+  let mut claims = AuthTokenClaims::new(&db_user, &API_KEY_AUTH_TOKEN_TTL)
+    .with_roles(get_user_roles(user_conn, &db_user.id).await?);
+  // API keys must never grant admin access, even if owned by an admin.
+  claims.admin = false;
+
+  return Ok((
+    claims,
+    ApiKeyScope {
+      record_apis: record_apis
+        .map(|apis| serde_json::from_str(&apis))
+        .transpose()
+        .map_err(|err| AuthError::Internal(err.into()))?,
+      permissions: permissions as u8,
+    },
+  ));
+}
+
+#[inline]
+pub(crate) fn is_api_key(token: &str) -> bool {
+  return token.starts_with(API_KEY_PREFIX);
+}
+
+/// Middleware rejecting requests authenticated with an API key.
+pub(crate) async fn reject_api_key_auth(req: Request, next: Next) -> Result<Response, AuthError> {
+  let bearer =
+    get_header(req.headers(), header::AUTHORIZATION).and_then(|v| v.strip_prefix("Bearer "));
+  if bearer.is_some_and(is_api_key) {
+    return Err(AuthError::Forbidden);
+  }
+  return Ok(next.run(req).await);
+}
+
+fn hash_api_key(key: &str) -> Vec<u8> {
+  return Sha256::digest(key.as_bytes()).to_vec();
+}
+
+fn permissions_to_mask(permissions: &[PermissionFlag]) -> u8 {
+  return permissions
+    .iter()
+    .fold(0, |mask, p| mask | (*p as i32 as u8));
+}
+
+fn mask_to_permissions(mask: u8) -> Vec<PermissionFlag> {
+  return [
+    PermissionFlag::Create,
+    PermissionFlag::Read,
+    PermissionFlag::Update,
+    PermissionFlag::Delete,
+    PermissionFlag::Schema,
+  ]
+  .into_iter()
+  .filter(|p| mask & (*p as i32 as u8) != 0)
+  .collect();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::admin::user::create_user_for_test;
+  use crate::app_state::test_state;
+
+  #[tokio::test]
+  async fn test_api_key_lifecycle() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.user_conn();
+
+    let user_id = create_user_for_test(&state, "worker@test.org", "secret123")
+      .await
+      .unwrap();
+
+    let (id, key) = mint_api_key(
+      conn,
+      &user_id,
+      NewApiKey {
+        name: "worker".to_string(),
+        record_apis: Some(vec!["messages".to_string()]),
+        permissions: vec![PermissionFlag::Read],
+        expires: None,
+      },
+    )
+    .await
+    .unwrap();
+    assert!(is_api_key(&key));
+
+    let (claims, scope) = authenticate_with_api_key(conn, &key).await.unwrap();
+    assert_eq!(claims.email, "worker@test.org");
+    assert!(!claims.admin);
+    assert!(scope.allows("messages", Permission::Read));
+    assert!(!scope.allows("messages", Permission::Create));
+    assert!(!scope.allows("other", Permission::Read));
+
+    let keys = list_api_keys(conn, Some(&user_id)).await.unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].id, id);
+    assert_eq!(keys[0].permissions, vec!["READ".to_string()]);
+
+    assert!(matches!(
+      authenticate_with_api_key(conn, &format!("{key}x")).await,
+      Err(AuthError::Unauthorized)
+    ));
+
+    // Expired keys are rejected.
+    let (_id, expired_key) = mint_api_key(
+      conn,
+      &user_id,
+      NewApiKey {
+        name: "expired".to_string(),
+        permissions: vec![PermissionFlag::Read],
+        expires: Some(chrono::Utc::now() - chrono::Duration::seconds(1)),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+    assert!(authenticate_with_api_key(conn, &expired_key).await.is_err());
+
+    revoke_api_key(conn, &id).await.unwrap();
+    assert!(authenticate_with_api_key(conn, &key).await.is_err());
+  }
+}
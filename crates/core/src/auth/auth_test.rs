@@ -30,6 +30,7 @@ use crate::auth::api::token::{AuthCodeToTokenRequest, TokenResponse, auth_code_t
 use crate::auth::api::verify_email::{VerifyEmailQuery, verify_email_handler};
 use crate::auth::jwt::PasswordResetTokenClaims;
 use crate::auth::login_params::{LoginInputParams, ResponseType};
+use crate::auth::session::SessionMetadata;
 use crate::auth::user::{DbUser, User};
 use crate::auth::util::login_with_password;
 use crate::config::proto::EmailTemplate;
@@ -128,6 +129,7 @@ async fn register_test_user(
     login_handler(
       State(state.clone()),
       Query(LoginInputParams::default()),
+      SessionMetadata::default(),
       Cookies::default(),
       Either::Json(LoginRequest {
         email: email.to_string(),
@@ -181,6 +183,7 @@ async fn test_auth_password_login_flow_with_pkce() {
     return login_handler(
       State(state.clone()),
       Query(LoginInputParams::default()),
+      SessionMetadata::default(),
       Cookies::default(),
       request,
     )
@@ -259,6 +262,7 @@ async fn test_auth_password_login_flow_with_pkce() {
   // And now upgrade to tokens, i.e. complete log-in.
   let Json(token_response): Json<TokenResponse> = auth_code_to_token_handler(
     State(state.clone()),
+    SessionMetadata::default(),
     Json(AuthCodeToTokenRequest {
       authorization_code: Some(auth_code.as_str().to_string()),
       pkce_code_verifier: Some(pkce_code_verifier.secret().to_string()),
@@ -303,6 +307,7 @@ async fn test_auth_password_login_flow_without_pkce() {
     return login_handler(
       State(state.clone()),
       Query(LoginInputParams::default()),
+      SessionMetadata::default(),
       Cookies::default(),
       request,
     )
@@ -494,6 +499,7 @@ async fn test_auth_reset_password_flow() {
   let _ = reset_password_update_handler(
     State(state.clone()),
     Query(Default::default()),
+    SessionMetadata::default(),
     Either::Form(ResetPasswordUpdateRequest {
       password: new_password.clone(),
       password_repeat: new_password.clone(),
@@ -607,6 +613,7 @@ async fn test_auth_change_email_flow() {
     State(state.clone()),
     Path(change_email_token.clone()),
     Query(ChangeEmailConfigQuery { redirect_uri: None }),
+    SessionMetadata::default(),
   )
   .await
   .expect(&format!("CODE: '{change_email_token}'"));
@@ -646,6 +653,7 @@ async fn test_auth_change_password_flow() {
     State(state.clone()),
     Query(ChangePasswordQuery::default()),
     user.clone(),
+    SessionMetadata::default(),
     Either::Json(ChangePasswordRequest {
       old_password: password.clone(),
       new_password: new_password.clone(),
@@ -656,6 +664,17 @@ async fn test_auth_change_password_flow() {
   .await
   .unwrap();
 
+  let action: Option<String> = state
+    .logs_conn()
+    .read_query_row_get(
+      format!("SELECT action FROM {AUDIT_LOG_TABLE} ORDER BY id DESC LIMIT 1"),
+      (),
+      0,
+    )
+    .await
+    .unwrap();
+  assert_eq!(action.as_deref(), Some("auth.change_password"));
+
   assert!(
     login_with_password(&state, &email, &password)
       .await
@@ -686,9 +705,14 @@ async fn test_auth_delete_user_flow() {
   assert!(session_exists(&state, user.uuid).await);
 
   // Delete user flow.
-  delete_handler(State(state.clone()), user.clone(), Cookies::default())
-    .await
-    .unwrap();
+  delete_handler(
+    State(state.clone()),
+    user.clone(),
+    SessionMetadata::default(),
+    Cookies::default(),
+  )
+  .await
+  .unwrap();
 
   let user_exists: bool = state
     .user_conn()
@@ -755,6 +779,7 @@ async fn test_auth_otp_flow() {
   assert!(
     otp::login_otp_handler(
       State(state.clone()),
+      SessionMetadata::default(),
       Cookies::default(),
       Query(Default::default()),
       Either::Form(otp::LoginOtpRequest {
@@ -780,6 +805,7 @@ async fn test_auth_otp_flow() {
 
   let response = otp::login_otp_handler(
     State(state.clone()),
+    SessionMetadata::default(),
     Cookies::default(),
     Query(Default::default()),
     Either::Json(otp::LoginOtpRequest {
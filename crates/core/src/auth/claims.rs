@@ -0,0 +1,203 @@
+//! Custom auth token claims, e.g. a tenant id, provided by a SQL query or WASM components.
+use log::*;
+use std::sync::Arc;
+use trailbase_schema::json::value_to_flat_json;
+use trailbase_sqlite::{Connection, params};
+
+use crate::auth::AuthError;
+use crate::auth::jwt::AuthTokenClaims;
+use crate::util::b64_to_id;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type JsonObject = serde_json::Map<String, serde_json::Value>;
+
+/// Registered JWT claims as well as TrailBase's own claims, which cannot be overridden.
+const RESERVED_CLAIMS: &[&str] = &[
+  "iss",
+  "sub",
+  "aud",
+  "exp",
+  "nbf",
+  "iat",
+  "jti",
+  "type",
+  "admin",
+  "mfa",
+  "email",
+  "roles",
+  "csrf_token",
+];
+
+/// Abstraction over custom claims providers, e.g. WASM components.
+#[async_trait::async_trait]
+pub trait CustomClaimsHook: Send + Sync {
+  /// Returns custom claims for the user of the given, not yet minted, auth token claims.
+  async fn custom_claims(&self, claims: &AuthTokenClaims) -> Result<JsonObject, BoxError>;
+}
+
+/// Registry of custom claims hooks.
+#[derive(Clone, Default)]
+pub struct CustomClaimsHooks {
+  hooks: Arc<Vec<Arc<dyn CustomClaimsHook>>>,
+}
+
+impl CustomClaimsHooks {
+  pub fn new(hooks: impl IntoIterator<Item = Arc<dyn CustomClaimsHook>>) -> Self {
+    return Self {
+      hooks: Arc::new(hooks.into_iter().collect()),
+    };
+  }
+
+  pub fn is_empty(&self) -> bool {
+    return self.hooks.is_empty();
+  }
+}
+
+/// Source of custom claims, i.e. the configured SQL query and registered hooks.
+#[derive(Clone, Default)]
+pub(crate) struct CustomClaims {
+  query: Option<String>,
+  hooks: CustomClaimsHooks,
+}
+
+impl CustomClaims {
+  pub(crate) fn new(query: Option<String>, hooks: CustomClaimsHooks) -> Self {
+    return Self {
+      query: query.filter(|q| !q.trim().is_empty()),
+      hooks,
+    };
+  }
+
+  /// Adds custom claims to the given claims.
+  ///
+  /// The query's result columns are added first and can be overridden by hooks in registration
+  /// order. Reserved claims, e.g. `sub` or `exp`, are skipped.
+  pub(crate) async fn apply(
+    &self,
+    conn: &Connection,
+    mut claims: AuthTokenClaims,
+  ) -> Result<AuthTokenClaims, AuthError> {
+    if self.query.is_none() && self.hooks.is_empty() {
+      return Ok(claims);
+    }
+
+    let mut custom = JsonObject::new();
+
+    if let Some(ref query) = self.query {
+      let user_id = b64_to_id(&claims.sub).map_err(|_err| AuthError::BadRequest("invalid user"))?;
+
+      if let Some(row) = conn.read_query_row(query.clone(), params!(user_id)).await? {
+        for i in 0..row.column_count() {
+          let (Some(name), Some(value)) = (row.column_name(i), row.get_value(i)) else {
+            continue;
+          };
+          if matches!(value, trailbase_sqlite::Value::Null) {
+            continue;
+          }
+
+          custom.insert(
+            name.to_string(),
+            value_to_flat_json(value).map_err(|err| AuthError::Internal(err.into()))?,
+          );
+        }
+      }
+    }
+
+    for hook in self.hooks.hooks.iter() {
+      custom.extend(
+        hook
+          .custom_claims(&claims)
+          .await
+          .map_err(AuthError::FailedDependency)?,
+      );
+    }
+
+    custom.retain(|name, _| {
+      if RESERVED_CLAIMS.contains(&name.as_str()) {
+        warn!("Skipping reserved custom claim: {name}");
+        return false;
+      }
+      return true;
+    });
+
+    claims.custom = custom;
+    return Ok(claims);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::admin::user::create_user_for_test;
+  use crate::app_state::{TestStateOptions, test_config, test_state};
+  use crate::auth::User;
+  use crate::auth::util::login_with_password;
+
+  struct TestHook;
+
+  #[async_trait::async_trait]
+  impl CustomClaimsHook for TestHook {
+    async fn custom_claims(&self, claims: &AuthTokenClaims) -> Result<JsonObject, BoxError> {
+      return Ok(JsonObject::from_iter([
+        ("plan".to_string(), serde_json::json!("pro")),
+        ("hook_email".to_string(), serde_json::json!(claims.email)),
+        // Reserved, must not override the actual subject.
+        ("sub".to_string(), serde_json::json!("evil")),
+      ]));
+    }
+  }
+
+  #[tokio::test]
+  async fn test_custom_claims() {
+    let mut config = test_config();
+    config.auth.custom_claims_query =
+      Some("SELECT tenant, plan, NULL AS missing FROM tenant_member WHERE user = $1".to_string());
+
+    let state = test_state(Some(TestStateOptions {
+      config: Some(config),
+      custom_claims_hooks: Some(CustomClaimsHooks::new([
+        Arc::new(TestHook) as Arc<dyn CustomClaimsHook>
+      ])),
+      ..Default::default()
+    }))
+    .await
+    .unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        "CREATE TABLE tenant_member (user BLOB NOT NULL, tenant TEXT NOT NULL, plan TEXT) STRICT;",
+      )
+      .await
+      .unwrap();
+
+    let email = "user@test.org";
+    let password = "secret123";
+    let user_id = create_user_for_test(&state, email, password).await.unwrap();
+
+    state
+      .conn()
+      .execute(
+        "INSERT INTO tenant_member (user, tenant, plan) VALUES ($1, 'acme', 'free')",
+        params!(user_id.into_bytes()),
+      )
+      .await
+      .unwrap();
+
+    let tokens = login_with_password(&state, email, password).await.unwrap();
+    let claims = AuthTokenClaims::from_auth_token(state.jwt(), &tokens.auth_token).unwrap();
+
+    assert_eq!(claims.sub, crate::util::uuid_to_b64(&user_id));
+    assert_eq!(claims.custom["tenant"], "acme");
+    // Hooks take precedence over the query.
+    assert_eq!(claims.custom["plan"], "pro");
+    assert_eq!(claims.custom["hook_email"], email);
+    assert!(!claims.custom.contains_key("missing"));
+    assert!(!claims.custom.contains_key("sub"));
+
+    let user = User::from_auth_token(&state, &tokens.auth_token).unwrap();
+    assert_eq!(user.claims["tenant"], "acme");
+  }
+}
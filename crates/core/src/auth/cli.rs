@@ -4,11 +4,17 @@ use trailbase_sqlite::{Connection, SyncConnectionTrait, params};
 use uuid::Uuid;
 
 use crate::DataDir;
+use crate::audit::{Actor, AuditAction, AuditEvent, AuditLog, audit_with_log};
 use crate::auth::AuthError;
+use crate::auth::claims::CustomClaims;
+use crate::auth::oidc::client as oauth_client;
 use crate::auth::password::hash_password;
+use crate::auth::session::SessionMetadata;
 use crate::auth::tokens::mint_new_tokens;
 use crate::auth::user::DbUser;
 use crate::auth::util::{get_user_by_email, get_user_by_id, validate_and_normalize_email_address};
+use crate::auth::{api_key, roles};
+use crate::config::proto::PermissionFlag;
 use crate::constants::USER_TABLE;
 
 pub enum UserReference {
@@ -37,6 +43,7 @@ impl UserReference {
 
 pub async fn change_password(
   user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
   user: UserReference,
   password: &str,
 ) -> Result<Uuid, AuthError> {
@@ -47,14 +54,23 @@ pub async fn change_password(
   const UPDATE_PASSWORD_QUERY: &str =
     formatcp!("UPDATE '{USER_TABLE}' SET password_hash = $1 WHERE id = $2 RETURNING id");
 
-  return user_conn
+  let id: Uuid = user_conn
     .write_query_value(UPDATE_PASSWORD_QUERY, params!(hashed_password, db_user.id))
     .await?
-    .ok_or(AuthError::NotFound);
+    .ok_or(AuthError::NotFound)?;
+
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::PasswordChange).target(id.to_string()),
+  )
+  .await;
+
+  return Ok(id);
 }
 
 pub async fn change_email(
   user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
   user: UserReference,
   new_email: &str,
 ) -> Result<Uuid, AuthError> {
@@ -64,14 +80,28 @@ pub async fn change_email(
   const UPDATE_EMAIL_QUERY: &str =
     formatcp!("UPDATE '{USER_TABLE}' SET email = $1 WHERE id = $2 RETURNING id");
 
-  return user_conn
-    .write_query_value(UPDATE_EMAIL_QUERY, params!(normalized_email, db_user.id))
+  let id: Uuid = user_conn
+    .write_query_value(
+      UPDATE_EMAIL_QUERY,
+      params!(normalized_email.clone(), db_user.id),
+    )
     .await?
-    .ok_or(AuthError::NotFound);
+    .ok_or(AuthError::NotFound)?;
+
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::EmailChange)
+      .target(id.to_string())
+      .diff(serde_json::json!({ "old_email": &db_user.email, "new_email": normalized_email })),
+  )
+  .await;
+
+  return Ok(id);
 }
 
 pub async fn add_user(
   user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
   email: &str,
   password: &str,
 ) -> Result<Uuid, AuthError> {
@@ -93,11 +123,20 @@ pub async fn add_user(
     .await?
     .ok_or(AuthError::NotFound)?;
 
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::UserCreate)
+      .target(user.uuid().to_string())
+      .diff(serde_json::json!({ "email": &user.email, "verified": true })),
+  )
+  .await;
+
   return Ok(user.uuid());
 }
 
 pub async fn delete_user(
   user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
   user: UserReference,
 ) -> Result<(), AuthError> {
   let db_user = user.lookup_user(user_conn).await?;
@@ -106,6 +145,14 @@ pub async fn delete_user(
 
   let rows_affected = user_conn.execute(DELETE_QUERY, params!(db_user.id)).await?;
   if rows_affected > 0 {
+    audit_with_log(
+      audit_log,
+      AuditEvent::new(Actor::cli(), AuditAction::UserDelete)
+        .target(db_user.uuid().to_string())
+        .diff(serde_json::json!({ "email": &db_user.email })),
+    )
+    .await;
+
     return Ok(());
   }
 
@@ -114,6 +161,7 @@ pub async fn delete_user(
 
 pub async fn set_verified(
   user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
   user: UserReference,
   verified: bool,
 ) -> Result<Uuid, AuthError> {
@@ -122,15 +170,26 @@ pub async fn set_verified(
   const SET_VERIFIED_QUERY: &str =
     formatcp!("UPDATE '{USER_TABLE}' SET verified = $1 WHERE id = $2 RETURNING id");
 
-  return user_conn
+  let id: Uuid = user_conn
     .write_query_value(SET_VERIFIED_QUERY, params!(verified, db_user.id))
     .await?
-    .ok_or(AuthError::NotFound);
+    .ok_or(AuthError::NotFound)?;
+
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::UserUpdate)
+      .target(id.to_string())
+      .diff(serde_json::json!({ "verified": verified })),
+  )
+  .await;
+
+  return Ok(id);
 }
 
 pub async fn invalidate_sessions(
   user_conn: &trailbase_sqlite::Connection,
   session_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
   user: UserReference,
 ) -> Result<(), AuthError> {
   let db_user = user.lookup_user(user_conn).await?;
@@ -138,6 +197,12 @@ pub async fn invalidate_sessions(
   crate::auth::util::delete_all_sessions_for_user(session_conn, Uuid::from_bytes(db_user.id))
     .await?;
 
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::SessionRevoke).target(db_user.uuid().to_string()),
+  )
+  .await;
+
   return Ok(());
 }
 
@@ -145,6 +210,7 @@ pub async fn mint_auth_token(
   data_dir: &DataDir,
   user_conn: &trailbase_sqlite::Connection,
   session_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
   user: UserReference,
 ) -> Result<String, AuthError> {
   let jwt = crate::api::JwtHelper::init_from_path(data_dir)
@@ -155,17 +221,254 @@ pub async fn mint_auth_token(
   // NOTE: we just discard the refresh token.
   let auth_token_ttl = chrono::Duration::hours(12);
   let refresh_token_ttl = chrono::Duration::hours(12);
-  let tokens = mint_new_tokens(session_conn, &db_user, &auth_token_ttl, &refresh_token_ttl).await?;
+  let tokens = mint_new_tokens(
+    user_conn,
+    session_conn,
+    &db_user,
+    &SessionMetadata {
+      user_agent: Some("trail CLI".to_string()),
+      client_ip: None,
+    },
+    // NOTE: Custom claims depend on the config and WASM components, which aren't loaded here.
+    &CustomClaims::default(),
+    &auth_token_ttl,
+    &refresh_token_ttl,
+  )
+  .await?;
 
   let auth_token = jwt
     .encode(&tokens.auth_token_claims)
     .map_err(|err| AuthError::Internal(err.into()))?;
 
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::TokenMint)
+      .target(db_user.uuid().to_string())
+      .diff(
+        serde_json::json!({ "email": &db_user.email, "ttl_sec": auth_token_ttl.num_seconds() }),
+      ),
+  )
+  .await;
+
   return Ok(auth_token);
 }
 
+/// Mints a new API key for the given user. Permissions are names, e.g. "read" or "CREATE".
+pub async fn mint_api_key(
+  user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
+  user: UserReference,
+  name: String,
+  record_apis: Option<Vec<String>>,
+  permissions: &[String],
+  expires_in: Option<chrono::Duration>,
+) -> Result<(Uuid, String), AuthError> {
+  let db_user = user.lookup_user(user_conn).await?;
+
+  let permissions = permissions
+    .iter()
+    .map(|p| {
+      PermissionFlag::from_str_name(&p.to_uppercase())
+        .ok_or(AuthError::BadRequest("invalid permission"))
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let diff = serde_json::json!({
+    "name": &name,
+    "user": &db_user.email,
+    "record_apis": &record_apis,
+    "permissions": permissions.iter().map(|p| p.as_str_name()).collect::<Vec<_>>(),
+  });
+
+  let (id, key) = api_key::mint_api_key(
+    user_conn,
+    &db_user.uuid(),
+    api_key::NewApiKey {
+      name,
+      record_apis,
+      permissions,
+      expires: expires_in.map(|d| chrono::Utc::now() + d),
+    },
+  )
+  .await?;
+
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::ApiKeyCreate)
+      .target(id.to_string())
+      .diff(diff),
+  )
+  .await;
+
+  return Ok((id, key));
+}
+
+pub async fn list_api_keys(
+  user_conn: &trailbase_sqlite::Connection,
+  user: Option<UserReference>,
+) -> Result<Vec<api_key::ApiKey>, AuthError> {
+  let user_id = match user {
+    Some(user) => Some(user.lookup_user(user_conn).await?.uuid()),
+    None => None,
+  };
+
+  return api_key::list_api_keys(user_conn, user_id.as_ref()).await;
+}
+
+pub async fn revoke_api_key(
+  user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
+  id: Uuid,
+) -> Result<(), AuthError> {
+  api_key::revoke_api_key(user_conn, &id).await?;
+
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::ApiKeyRevoke).target(id.to_string()),
+  )
+  .await;
+
+  return Ok(());
+}
+
+/// Registers a new OAuth client, returning its id and, for confidential clients, the secret.
+pub async fn register_oauth_client(
+  user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
+  name: String,
+  redirect_uris: Vec<String>,
+  confidential: bool,
+) -> Result<(String, Option<String>), AuthError> {
+  let diff = serde_json::json!({
+    "name": &name,
+    "redirect_uris": &redirect_uris,
+    "public": !confidential,
+  });
+
+  let (id, secret) =
+    oauth_client::register_oauth_client(user_conn, name, redirect_uris, confidential).await?;
+
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::OAuthClientCreate)
+      .target(id.clone())
+      .diff(diff),
+  )
+  .await;
+
+  return Ok((id, secret));
+}
+
+pub async fn list_oauth_clients(
+  user_conn: &trailbase_sqlite::Connection,
+) -> Result<Vec<oauth_client::OAuthClient>, AuthError> {
+  return oauth_client::list_oauth_clients(user_conn).await;
+}
+
+pub async fn delete_oauth_client(
+  user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
+  id: &str,
+) -> Result<(), AuthError> {
+  oauth_client::delete_oauth_client(user_conn, id).await?;
+
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::OAuthClientDelete).target(id),
+  )
+  .await;
+
+  return Ok(());
+}
+
+pub async fn create_role(
+  user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
+  name: &str,
+  description: Option<String>,
+) -> Result<(), AuthError> {
+  roles::create_role(user_conn, name, description).await?;
+
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::RoleCreate).target(name),
+  )
+  .await;
+
+  return Ok(());
+}
+
+pub async fn delete_role(
+  user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
+  name: &str,
+) -> Result<(), AuthError> {
+  roles::delete_role(user_conn, name).await?;
+
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::RoleDelete).target(name),
+  )
+  .await;
+
+  return Ok(());
+}
+
+pub async fn list_roles(
+  user_conn: &trailbase_sqlite::Connection,
+  user: Option<UserReference>,
+) -> Result<Vec<roles::Role>, AuthError> {
+  let user_id = match user {
+    Some(user) => Some(user.lookup_user(user_conn).await?.uuid()),
+    None => None,
+  };
+
+  return roles::list_roles(user_conn, user_id.as_ref()).await;
+}
+
+pub async fn assign_role(
+  user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
+  user: UserReference,
+  role: &str,
+) -> Result<Uuid, AuthError> {
+  let db_user = user.lookup_user(user_conn).await?;
+  roles::assign_role(user_conn, &db_user.uuid(), role).await?;
+
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::RoleAssign)
+      .target(db_user.uuid().to_string())
+      .diff(serde_json::json!({ "role": role })),
+  )
+  .await;
+
+  return Ok(db_user.uuid());
+}
+
+pub async fn unassign_role(
+  user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
+  user: UserReference,
+  role: &str,
+) -> Result<Uuid, AuthError> {
+  let db_user = user.lookup_user(user_conn).await?;
+  roles::unassign_role(user_conn, &db_user.uuid(), role).await?;
+
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::RoleUnassign)
+      .target(db_user.uuid().to_string())
+      .diff(serde_json::json!({ "role": role })),
+  )
+  .await;
+
+  return Ok(db_user.uuid());
+}
+
 pub async fn promote_user_to_admin(
   user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
   user: UserReference,
 ) -> Result<Uuid, AuthError> {
   let db_user = user.lookup_user(user_conn).await?;
@@ -173,14 +476,25 @@ pub async fn promote_user_to_admin(
   const PROMOTE_ADMIN_QUERY: &str =
     formatcp!("UPDATE '{USER_TABLE}' SET admin = TRUE WHERE id = $1 RETURNING id");
 
-  return user_conn
+  let id: Uuid = user_conn
     .write_query_value(PROMOTE_ADMIN_QUERY, params!(db_user.id))
     .await?
-    .ok_or(AuthError::NotFound);
+    .ok_or(AuthError::NotFound)?;
+
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::UserPromote)
+      .target(id.to_string())
+      .diff(serde_json::json!({ "email": &db_user.email })),
+  )
+  .await;
+
+  return Ok(id);
 }
 
 pub async fn demote_admin_to_user(
   user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
   user: UserReference,
 ) -> Result<Uuid, AuthError> {
   let db_user = user.lookup_user(user_conn).await?;
@@ -188,10 +502,20 @@ pub async fn demote_admin_to_user(
   const DEMOTE_ADMIN_QUERY: &str =
     formatcp!("UPDATE '{USER_TABLE}' SET admin = FALSE WHERE id = $1 RETURNING id");
 
-  return user_conn
+  let id: Uuid = user_conn
     .write_query_value(DEMOTE_ADMIN_QUERY, params!(db_user.id))
     .await?
-    .ok_or(AuthError::NotFound);
+    .ok_or(AuthError::NotFound)?;
+
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::UserDemote)
+      .target(id.to_string())
+      .diff(serde_json::json!({ "email": &db_user.email })),
+  )
+  .await;
+
+  return Ok(id);
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -203,6 +527,7 @@ pub struct ImportUser {
 
 pub async fn import_users(
   user_conn: &trailbase_sqlite::Connection,
+  audit_log: &AuditLog,
   users: Vec<ImportUser>,
 ) -> Result<(), AuthError> {
   // First validate the users.
@@ -213,6 +538,7 @@ pub async fn import_users(
 
     let _ = validate_and_normalize_email_address(&user.email)?;
   }
+  let emails: Vec<String> = users.iter().map(|user| user.email.clone()).collect();
 
   user_conn
     .transaction(|tx| -> Result<(), trailbase_sqlite::Error> {
@@ -239,5 +565,12 @@ pub async fn import_users(
     .await
     .map_err(|err| AuthError::FailedDependency(err.into()))?;
 
+  audit_with_log(
+    audit_log,
+    AuditEvent::new(Actor::cli(), AuditAction::UserCreate)
+      .diff(serde_json::json!({ "imported": emails })),
+  )
+  .await;
+
   return Ok(());
 }
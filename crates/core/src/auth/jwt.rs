@@ -1,11 +1,14 @@
 use crate::auth::user::DbUser;
 use crate::rand::random_alphanumeric;
 use crate::util::{id_to_b64, uuid_to_b64};
+use base64::prelude::*;
 use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
-use ed25519_dalek::pkcs8::{EncodePrivateKey, EncodePublicKey};
+use ed25519_dalek::pkcs8::{DecodePublicKey, EncodePrivateKey, EncodePublicKey};
 use ed25519_dalek::{SigningKey, VerifyingKey};
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, errors::Error as JwtError};
+use log::*;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 use tokio::fs;
@@ -34,6 +37,8 @@ enum TokenType {
   ResetPassword,
   ChangeEmail,
   VerifyEmail,
+  WebAuthnChallenge,
+  OidcConsent,
 }
 
 /// The actual "AuthToken" used for signed-in users.
@@ -61,9 +66,18 @@ pub struct AuthTokenClaims {
   /// E-mail address of the [sub].
   pub email: String,
 
+  /// Names of the roles assigned to [sub].
+  #[serde(default)]
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub roles: Vec<String>,
+
   /// CSRF random token. Requiring that the client echos this random token back on a non-cookie,
   /// non-auto-attach channel can be used to protect from CSRF.
   pub csrf_token: String,
+
+  /// Custom claims, see [crate::auth::claims::CustomClaims].
+  #[serde(flatten)]
+  pub custom: serde_json::Map<String, serde_json::Value>,
 }
 
 impl AuthTokenClaims {
@@ -79,10 +93,18 @@ impl AuthTokenClaims {
       admin: db_user.admin,
       mfa: db_user.totp_secret.is_some(),
       email: db_user.email.clone(),
+      roles: vec![],
       csrf_token: random_alphanumeric(20),
+      custom: Default::default(),
     };
   }
 
+  /// Attach the user's roles, see [crate::auth::roles::get_user_roles].
+  pub(crate) fn with_roles(mut self, roles: Vec<String>) -> Self {
+    self.roles = roles;
+    return self;
+  }
+
   pub fn from_auth_token(jwt: &JwtHelper, auth_token: &str) -> Result<Self, JwtError> {
     let claims = jwt.decode::<Self>(auth_token)?;
     assert_eq!(claims.r#type, TokenType::Auth as u8);
@@ -95,6 +117,9 @@ impl AuthTokenClaims {
 pub enum AuthMethod {
   #[serde(rename = "pw")]
   Password,
+  /// Passkey, i.e. a WebAuthn assertion.
+  #[serde(rename = "webauthn")]
+  WebAuthn,
   // #[serde(rename = "totp")]
   // Totp,
   // #[serde(rename = "otp")]
@@ -128,6 +153,12 @@ impl PendingAuthTokenClaims {
     };
   }
 
+  /// Override the first factor, which defaults to [AuthMethod::Password].
+  pub fn with_method(mut self, method: AuthMethod) -> Self {
+    self.method = method;
+    return self;
+  }
+
   pub fn from_pending_auth_token(jwt: &JwtHelper, token: &str) -> Result<Self, JwtError> {
     let claims = jwt.decode::<Self>(token)?;
     assert_eq!(claims.r#type, TokenType::PendingAuth as u8);
@@ -135,6 +166,125 @@ impl PendingAuthTokenClaims {
   }
 }
 
+/// Purpose of a WebAuthn ceremony the challenge was issued for.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum WebAuthnCeremony {
+  #[serde(rename = "reg")]
+  Register,
+  #[serde(rename = "login")]
+  Login,
+  #[serde(rename = "mfa")]
+  SecondFactor,
+}
+
+/// WebAuthn challenge, which is handed to the client alongside the credential options and has to
+/// be echoed back to complete the ceremony. The challenge itself is also recorded server-side to
+/// make it single-use.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebAuthnChallengeClaims {
+  /// Url-safe Base64 encoded id of the user, if known ahead of the ceremony.
+  pub sub: Option<String>,
+  /// Expiration timestamp
+  pub exp: i64,
+
+  // Token type.
+  pub r#type: u8,
+
+  /// Url-safe Base64 encoded random challenge to be signed by the authenticator.
+  pub challenge: String,
+  pub ceremony: WebAuthnCeremony,
+}
+
+impl WebAuthnChallengeClaims {
+  pub fn new(
+    user_id: Option<&uuid::Uuid>,
+    ceremony: WebAuthnCeremony,
+    challenge: String,
+    expires_in: chrono::Duration,
+  ) -> Self {
+    let now = chrono::Utc::now();
+
+    return Self {
+      sub: user_id.map(uuid_to_b64),
+      exp: (now + expires_in).timestamp(),
+      r#type: TokenType::WebAuthnChallenge as u8,
+      challenge,
+      ceremony,
+    };
+  }
+
+  pub fn decode(
+    jwt: &JwtHelper,
+    token: &str,
+    ceremony: WebAuthnCeremony,
+  ) -> Result<Self, JwtError> {
+    let claims = jwt.decode::<Self>(token)?;
+    if claims.r#type != TokenType::WebAuthnChallenge as u8 || claims.ceremony != ceremony {
+      return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+    }
+    return Ok(claims);
+  }
+}
+
+/// Sign-in request of a registered OAuth client pending the user's consent. It's handed to the
+/// consent screen and has to be echoed back with the user's decision.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OidcConsentClaims {
+  /// Url-safe Base64 encoded id of the user asked for consent.
+  pub sub: String,
+  /// Expiration timestamp
+  pub exp: i64,
+
+  // Token type.
+  pub r#type: u8,
+
+  pub client_id: String,
+  pub redirect_uri: String,
+  /// Space-separated list of granted scopes, e.g. "openid email".
+  pub scope: String,
+  /// Opaque client state, which is passed back to the client as is.
+  pub state: Option<String>,
+  pub nonce: Option<String>,
+  /// S256 PKCE code challenge provided by the client.
+  pub code_challenge: String,
+}
+
+impl OidcConsentClaims {
+  #[allow(clippy::too_many_arguments)]
+  pub fn new(
+    user_id: &uuid::Uuid,
+    client_id: String,
+    redirect_uri: String,
+    scope: String,
+    state: Option<String>,
+    nonce: Option<String>,
+    code_challenge: String,
+    expires_in: chrono::Duration,
+  ) -> Self {
+    let now = chrono::Utc::now();
+
+    return Self {
+      sub: uuid_to_b64(user_id),
+      exp: (now + expires_in).timestamp(),
+      r#type: TokenType::OidcConsent as u8,
+      client_id,
+      redirect_uri,
+      scope,
+      state,
+      nonce,
+      code_challenge,
+    };
+  }
+
+  pub fn decode(jwt: &JwtHelper, token: &str) -> Result<Self, JwtError> {
+    let claims = jwt.decode::<Self>(token)?;
+    if claims.r#type != TokenType::OidcConsent as u8 {
+      return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+    }
+    return Ok(claims);
+  }
+}
+
 // Password reset token.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct PasswordResetTokenClaims {
@@ -238,6 +388,70 @@ impl EmailChangeTokenClaims {
   }
 }
 
+/// JSON Web Key, i.e. a public key used to validate JWTs, see RFC 7517 and RFC 8037.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Jwk {
+  /// Key type, always "OKP" (Octet Key Pair) for Ed25519.
+  pub kty: String,
+  pub crv: String,
+  /// Url-safe Base64 encoded public key.
+  pub x: String,
+  pub kid: String,
+  pub alg: String,
+  pub r#use: String,
+}
+
+/// JSON Web Key Set as served at `/.well-known/jwks.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JwkSet {
+  pub keys: Vec<Jwk>,
+}
+
+/// Metadata of a signing key, see [JwtHelper::keys].
+#[derive(Debug, Clone)]
+pub struct KeyInfo {
+  pub kid: String,
+  /// Whether the key is used for minting new tokens. Otherwise, it's a retired key that is still
+  /// accepted until it expires.
+  pub current: bool,
+  /// Unix timestamp in seconds after which a retired key is no longer accepted.
+  pub expires: Option<i64>,
+}
+
+struct VerificationKey {
+  kid: String,
+  jwk: Jwk,
+  decoding_key: DecodingKey,
+  /// Unix timestamp in seconds for retired keys.
+  expires: Option<i64>,
+}
+
+impl VerificationKey {
+  fn from_pem(public_key: &[u8], expires: Option<i64>) -> Result<Self, JwtHelperError> {
+    let verifying_key = VerifyingKey::from_public_key_pem(&String::from_utf8_lossy(public_key))?;
+    let x = BASE64_URL_SAFE_NO_PAD.encode(verifying_key.as_bytes());
+    let kid = key_thumbprint(&x);
+
+    return Ok(Self {
+      jwk: Jwk {
+        kty: "OKP".to_string(),
+        crv: "Ed25519".to_string(),
+        x,
+        kid: kid.clone(),
+        alg: "EdDSA".to_string(),
+        r#use: "sig".to_string(),
+      },
+      kid,
+      decoding_key: DecodingKey::from_ed_pem(public_key)?,
+      expires,
+    });
+  }
+
+  fn is_expired(&self, now: i64) -> bool {
+    return self.expires.is_some_and(|expires| expires <= now);
+  }
+}
+
 pub struct JwtHelper {
   header: Header,
   validation: Validation,
@@ -245,22 +459,30 @@ pub struct JwtHelper {
   // The private key used for minting new JWTs.
   encoding_key: EncodingKey,
 
-  // The public key used for validating provided JWTs.
-  decoding_key: DecodingKey,
+  // The public keys used for validating provided JWTs: the current key first followed by retired
+  // keys, which are still accepted during their grace period.
+  keys: Vec<VerificationKey>,
   public_key: String,
 }
 
 impl JwtHelper {
   pub fn new(private_key: Vec<u8>, public_key: Vec<u8>) -> Result<Self, JwtHelperError> {
+    let key = VerificationKey::from_pem(&public_key, None)?;
+
+    let mut header = Header::new(jsonwebtoken::Algorithm::EdDSA);
+    header.kid = Some(key.kid.clone());
+
     return Ok(JwtHelper {
-      header: Header::new(jsonwebtoken::Algorithm::EdDSA),
+      header,
       validation: Validation::new(jsonwebtoken::Algorithm::EdDSA),
       encoding_key: EncodingKey::from_ed_pem(&private_key)?,
-      decoding_key: DecodingKey::from_ed_pem(&public_key)?,
+      keys: vec![key],
       public_key: String::from_utf8_lossy(&public_key).to_string(),
     });
   }
 
+  /// Loads the current key pair as well as retired keys still within their grace period from the
+  /// data directory. Creates a new key pair if none exists.
   pub async fn init_from_path(data_dir: &DataDir) -> Result<Self, JwtHelperError> {
     let key_path = data_dir.key_path();
 
@@ -284,17 +506,81 @@ impl JwtHelper {
       },
     };
 
-    return Self::new(private_key, public_key);
+    let mut helper = Self::new(private_key, public_key)?;
+    for (public_key, expires) in read_retired_keys(&key_path).await? {
+      helper
+        .keys
+        .push(VerificationKey::from_pem(&public_key, Some(expires))?);
+    }
+
+    return Ok(helper);
   }
 
+  /// PEM encoded public key of the current key pair.
   pub fn public_key(&self) -> String {
     return self.public_key.clone();
   }
 
+  /// Key id of the current key pair, i.e. the `kid` header of newly minted tokens.
+  pub fn kid(&self) -> &str {
+    return &self.keys[0].kid;
+  }
+
+  pub fn keys(&self) -> Vec<KeyInfo> {
+    return self
+      .keys
+      .iter()
+      .enumerate()
+      .map(|(i, key)| KeyInfo {
+        kid: key.kid.clone(),
+        current: i == 0,
+        expires: key.expires,
+      })
+      .collect();
+  }
+
+  /// Public keys accepted for validating tokens as JSON Web Key Set.
+  pub fn jwks(&self) -> JwkSet {
+    let now = chrono::Utc::now().timestamp();
+    return JwkSet {
+      keys: self
+        .keys
+        .iter()
+        .filter(|key| !key.is_expired(now))
+        .map(|key| key.jwk.clone())
+        .collect(),
+    };
+  }
+
   pub fn decode<T: DeserializeOwned + Clone>(&self, token: &str) -> Result<T, JwtError> {
-    // Note: we don't need to expose the token headers.
-    return jsonwebtoken::decode::<T>(token, &self.decoding_key, &self.validation)
-      .map(|data| data.claims);
+    let kid = jsonwebtoken::decode_header(token)?.kid;
+    let now = chrono::Utc::now().timestamp();
+
+    // NOTE: Tokens minted prior to the introduction of key ids don't have a `kid` header, thus
+    // we fall back to trying all keys.
+    let mut result: Result<T, JwtError> =
+      Err(jsonwebtoken::errors::ErrorKind::InvalidSignature.into());
+    for key in &self.keys {
+      if key.is_expired(now) || kid.as_ref().is_some_and(|kid| *kid != key.kid) {
+        continue;
+      }
+
+      // Note: we don't need to expose the token headers.
+      result = jsonwebtoken::decode::<T>(token, &key.decoding_key, &self.validation)
+        .map(|data| data.claims);
+      match result {
+        Err(ref err)
+          if matches!(
+            err.kind(),
+            jsonwebtoken::errors::ErrorKind::InvalidSignature
+          ) =>
+        {
+          continue;
+        }
+        _ => break,
+      }
+    }
+    return result;
   }
 
   pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String, JwtError> {
@@ -302,6 +588,99 @@ impl JwtHelper {
   }
 }
 
+/// Replaces the current key pair with a newly generated one and returns the new key's id.
+///
+/// The previous public key is retired, i.e. continues to be accepted and listed in the JWKS until
+/// the grace period expires. Retired keys with an expired grace period are removed. A running
+/// server only picks up the new key after a restart.
+pub async fn rotate_keys(
+  data_dir: &DataDir,
+  grace_period: chrono::Duration,
+) -> Result<String, JwtHelperError> {
+  let key_path = data_dir.key_path();
+  let retired_path = key_path.join(RETIRED_KEYS_DIR);
+  fs::create_dir_all(&retired_path).await?;
+
+  match fs::read(key_path.join(PUBLIC_KEY_FILE)).await {
+    Ok(public_key) => {
+      let retired = VerificationKey::from_pem(&public_key, None)?;
+      let expires = (chrono::Utc::now() + grace_period).timestamp();
+      write_new_file(
+        retired_path.join(format!("{expires}_{kid}.pem", kid = retired.kid)),
+        &public_key,
+      )
+      .await?;
+    }
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+    Err(err) => {
+      return Err(err.into());
+    }
+  };
+
+  // Write the new key pair to temporary files first to not leave a partially written key pair
+  // behind.
+  let (private_key, public_key) = new_pem_keys()?;
+  let private_tmp = key_path.join(format!("{PRIVATE_KEY_FILE}.new"));
+  let public_tmp = key_path.join(format!("{PUBLIC_KEY_FILE}.new"));
+  write_new_file(private_tmp.clone(), &private_key).await?;
+  write_new_file(public_tmp.clone(), &public_key).await?;
+  fs::rename(private_tmp, key_path.join(PRIVATE_KEY_FILE)).await?;
+  fs::rename(public_tmp, key_path.join(PUBLIC_KEY_FILE)).await?;
+
+  // Prunes expired keys as a side-effect.
+  read_retired_keys(&key_path).await?;
+
+  return Ok(VerificationKey::from_pem(&public_key, None)?.kid);
+}
+
+/// Computes the key id as JWK thumbprint, see RFC 7638.
+fn key_thumbprint(x: &str) -> String {
+  let canonical = format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{x}"}}"#);
+  return BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()));
+}
+
+/// Reads retired public keys and their expiration, removing the ones that have expired.
+async fn read_retired_keys(key_path: &Path) -> Result<Vec<(Vec<u8>, i64)>, JwtHelperError> {
+  let mut entries = match fs::read_dir(key_path.join(RETIRED_KEYS_DIR)).await {
+    Ok(entries) => entries,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+      return Ok(vec![]);
+    }
+    Err(err) => {
+      return Err(err.into());
+    }
+  };
+
+  let now = chrono::Utc::now().timestamp();
+  let mut keys = vec![];
+  while let Some(entry) = entries.next_entry().await? {
+    let file_name = entry.file_name();
+    let Some(expires) = file_name
+      .to_str()
+      .and_then(|name| name.split_once('_'))
+      .and_then(|(expires, _)| expires.parse::<i64>().ok())
+    else {
+      warn!("Skipping unexpected file in retired keys: {file_name:?}");
+      continue;
+    };
+
+    if expires <= now {
+      info!("Removing expired key: {file_name:?}");
+      if let Err(err) = fs::remove_file(entry.path()).await {
+        warn!("Failed to remove expired key {file_name:?}: {err}");
+      }
+      continue;
+    }
+
+    keys.push((fs::read(entry.path()).await?, expires));
+  }
+
+  // Most recently retired keys first.
+  keys.sort_by(|a, b| b.1.cmp(&a.1));
+
+  return Ok(keys);
+}
+
 fn generate_new_key_pair() -> (SigningKey, VerifyingKey) {
   let mut csprng = argon2::password_hash::rand_core::OsRng;
   let signing_key = SigningKey::generate(&mut csprng);
@@ -310,13 +689,19 @@ fn generate_new_key_pair() -> (SigningKey, VerifyingKey) {
   return (signing_key, verifying_key);
 }
 
-async fn write_new_pem_keys(key_path: &Path) -> Result<(Vec<u8>, Vec<u8>), JwtHelperError> {
+fn new_pem_keys() -> Result<(Vec<u8>, Vec<u8>), JwtHelperError> {
   let (signing_key, verifying_key) = generate_new_key_pair();
 
   let le = LineEnding::default();
   let priv_key = signing_key.to_pkcs8_pem(le)?.as_bytes().to_vec();
   let pub_key = verifying_key.to_public_key_pem(le)?.into_bytes();
 
+  return Ok((priv_key, pub_key));
+}
+
+async fn write_new_pem_keys(key_path: &Path) -> Result<(Vec<u8>, Vec<u8>), JwtHelperError> {
+  let (priv_key, pub_key) = new_pem_keys()?;
+
   write_new_file(key_path.join(PRIVATE_KEY_FILE), &priv_key).await?;
   write_new_file(key_path.join(PUBLIC_KEY_FILE), &pub_key).await?;
 
@@ -390,7 +775,68 @@ mod tests {
     );
     assert!(AuthTokenClaims::from_auth_token(&jwt, &pending_auth_token).is_err())
   }
+
+  #[tokio::test]
+  async fn test_key_rotation() {
+    let temp_dir = temp_dir::TempDir::new().unwrap();
+    let data_dir = DataDir(temp_dir.path().to_path_buf());
+
+    let jwt = JwtHelper::init_from_path(&data_dir).await.unwrap();
+    let old_kid = jwt.kid().to_string();
+    assert_eq!(jwt.jwks().keys.len(), 1);
+
+    let claims = PendingAuthTokenClaims::new(
+      uuid::Uuid::now_v7(),
+      crate::constants::DEFAULT_MFA_TOKEN_TTL,
+    );
+    let old_token = jwt.encode(&claims).unwrap();
+    assert_eq!(
+      jsonwebtoken::decode_header(&old_token).unwrap().kid,
+      Some(old_kid.clone())
+    );
+
+    let new_kid = rotate_keys(&data_dir, chrono::Duration::hours(1))
+      .await
+      .unwrap();
+    assert_ne!(old_kid, new_kid);
+
+    let jwt = JwtHelper::init_from_path(&data_dir).await.unwrap();
+    assert_eq!(jwt.kid(), new_kid);
+    let keys = jwt.keys();
+    assert_eq!(keys.len(), 2);
+    assert!(keys[0].current);
+    assert_eq!(keys[1].kid, old_kid);
+    assert!(keys[1].expires.is_some());
+
+    let jwks = jwt.jwks();
+    assert_eq!(
+      jwks.keys.iter().map(|k| k.kid.as_str()).collect::<Vec<_>>(),
+      vec![new_kid.as_str(), old_kid.as_str()]
+    );
+
+    // Tokens minted with the retired key are still accepted during the grace period.
+    assert_eq!(
+      claims,
+      PendingAuthTokenClaims::from_pending_auth_token(&jwt, &old_token).unwrap()
+    );
+    let new_token = jwt.encode(&claims).unwrap();
+    assert_eq!(
+      claims,
+      PendingAuthTokenClaims::from_pending_auth_token(&jwt, &new_token).unwrap()
+    );
+
+    // W/o grace period the previous key is dropped immediately.
+    rotate_keys(&data_dir, chrono::Duration::zero())
+      .await
+      .unwrap();
+    let jwt = JwtHelper::init_from_path(&data_dir).await.unwrap();
+    assert_eq!(jwt.keys().len(), 2);
+    assert!(jwt.decode::<PendingAuthTokenClaims>(&old_token).is_ok());
+    assert!(jwt.decode::<PendingAuthTokenClaims>(&new_token).is_err());
+  }
 }
 
 const PRIVATE_KEY_FILE: &str = "private_key.pem";
 const PUBLIC_KEY_FILE: &str = "public_key.pem";
+/// Sub-directory of the key path holding retired public keys named `<expires>_<kid>.pem`.
+const RETIRED_KEYS_DIR: &str = "retired";
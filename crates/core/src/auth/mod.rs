@@ -1,20 +1,28 @@
 use axum::{
-  Router,
+  Router, middleware,
   routing::{delete, get, post},
 };
 use utoipa::OpenApi;
 
+pub mod api_key;
+pub mod claims;
 pub mod cli;
 pub mod jwt;
+pub mod roles;
+pub mod session;
 pub mod user;
 
 pub(crate) mod api;
 pub(crate) mod login_params;
 pub(crate) mod oauth;
+pub(crate) mod oidc;
 pub(crate) mod options;
 pub(crate) mod password;
+pub(crate) mod saml;
 pub(crate) mod tokens;
 pub(crate) mod util;
+pub(crate) mod webauthn;
+pub(crate) mod well_known;
 
 mod error;
 
@@ -51,6 +59,16 @@ use api::*;
     totp::register_totp_request_handler,
     totp::register_totp_confirm_handler,
     totp::unregister_totp_handler,
+    api::webauthn::register_webauthn_start_handler,
+    api::webauthn::register_webauthn_finish_handler,
+    api::webauthn::login_webauthn_start_handler,
+    api::webauthn::login_webauthn_finish_handler,
+    api::webauthn::mfa_webauthn_start_handler,
+    api::webauthn::mfa_webauthn_finish_handler,
+    api::webauthn::list_webauthn_credentials_handler,
+    api::webauthn::delete_webauthn_credential_handler,
+    sessions::list_sessions_handler,
+    sessions::revoke_session_handler,
     token::auth_code_to_token_handler,
     status::login_status_handler,
     logout::logout_handler,
@@ -62,6 +80,8 @@ use api::*;
   ),
   nest(
      (path = "/oauth", api = oauth::OAuthApi),
+     (path = "/oidc", api = oidc::OidcApi),
+     (path = "/saml", api = saml::SamlApi),
   ),
 )]
 pub(super) struct AuthApi;
@@ -150,6 +170,48 @@ pub(super) fn router(config: &Config) -> Router<crate::AppState> {
       &format!("/{AUTH_API_PATH}/totp/unregister"),
       post(api::totp::unregister_totp_handler),
     )
+    // WebAuthn flows: passkey registration, login and second factor.
+    .route(
+      &format!("/{AUTH_API_PATH}/webauthn/register/start"),
+      post(api::webauthn::register_webauthn_start_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/webauthn/register/finish"),
+      post(api::webauthn::register_webauthn_finish_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/webauthn/login/start"),
+      post(api::webauthn::login_webauthn_start_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/webauthn/login/finish"),
+      post(api::webauthn::login_webauthn_finish_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/webauthn/mfa/start"),
+      post(api::webauthn::mfa_webauthn_start_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/webauthn/mfa/finish"),
+      post(api::webauthn::mfa_webauthn_finish_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/webauthn/credentials"),
+      get(api::webauthn::list_webauthn_credentials_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/webauthn/credentials/{{credential_id}}"),
+      delete(api::webauthn::delete_webauthn_credential_handler),
+    )
+    // Sessions, i.e. signed-in devices.
+    .route(
+      &format!("/{AUTH_API_PATH}/sessions"),
+      get(api::sessions::list_sessions_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/sessions/{{session_id}}"),
+      delete(api::sessions::revoke_session_handler),
+    )
     // Converts auth code (+pkce code verifier) to auth tokens
     .route(
       &format!("/{AUTH_API_PATH}/token"),
@@ -189,10 +251,14 @@ pub(super) fn router(config: &Config) -> Router<crate::AppState> {
       delete(api::delete::delete_handler),
     )
     // OAuth flows: list providers, login+callback
-    .nest(&format!("/{AUTH_API_PATH}/oauth"), oauth::oauth_router());
+    .nest(&format!("/{AUTH_API_PATH}/oauth"), oauth::oauth_router())
+    // OAuth2/OpenID Connect provider flow for registered client applications.
+    .nest(&format!("/{AUTH_API_PATH}/oidc"), oidc::oidc_router())
+    // SAML 2.0 single sign-on: login, assertion consumer service and SP metadata.
+    .nest(&format!("/{AUTH_API_PATH}/saml"), saml::saml_router());
 
-  if config.auth.enable_otp_signin() {
-    return router
+  let router = if config.auth.enable_otp_signin() {
+    router
       // OTP flow
       .route(
         &format!("/{AUTH_API_PATH}/otp/request"),
@@ -201,10 +267,14 @@ pub(super) fn router(config: &Config) -> Router<crate::AppState> {
       .route(
         &format!("/{AUTH_API_PATH}/otp/login"),
         post(api::otp::login_otp_handler),
-      );
-  }
+      )
+  } else {
+    router
+  };
 
-  return router;
+  // API keys are meant for record access only and must not be used to manage the owning account,
+  // e.g. change its password.
+  return router.layer(middleware::from_fn(api_key::reject_api_key_auth));
 }
 
 /// Replicating minimal functionality of the above main router in case the admin dash is routed
@@ -10,24 +10,28 @@ use serde::Deserialize;
 use std::future::Future;
 use std::pin::Pin;
 use tower_cookies::Cookies;
-use trailbase_sqlite::{named_params, params};
+use trailbase_sqlite::params;
 use utoipa::IntoParams;
+use uuid::Uuid;
 
 use crate::AppState;
+use crate::audit::{Actor, AuditAction, AuditEvent, audit};
 use crate::auth::AuthError;
 use crate::auth::oauth::OAuthUser;
+use crate::auth::oauth::identity::{create_user_with_identity, link_identity, user_by_identity};
 use crate::auth::oauth::provider::TokenResponse;
 use crate::auth::oauth::providers::OAuthProviderType;
 use crate::auth::oauth::state::{OAuthStateClaims, ResponseType};
+use crate::auth::session::SessionMetadata;
 use crate::auth::tokens::{FreshTokens, mint_new_tokens};
 use crate::auth::user::DbUser;
-use crate::auth::util::{new_cookie, remove_cookie, validate_redirect};
-use crate::config::proto::OAuthProviderId;
+use crate::auth::util::{get_user_by_email, new_cookie, remove_cookie, validate_redirect};
 use crate::constants::{
   AUTHORIZATION_CODE_TABLE, COOKIE_AUTH_TOKEN, COOKIE_OAUTH_STATE, COOKIE_REFRESH_TOKEN,
-  DEFAULT_AUTHORIZATION_CODE_TTL, USER_TABLE, VERIFICATION_CODE_LENGTH,
+  DEFAULT_AUTHORIZATION_CODE_TTL, VERIFICATION_CODE_LENGTH,
 };
 use crate::rand::random_alphanumeric;
+use crate::util::b64_to_id;
 
 #[derive(Debug, Deserialize, IntoParams)]
 pub struct AuthQuery {
@@ -50,6 +54,7 @@ pub(crate) async fn callback_from_external_auth_provider(
   State(state): State<AppState>,
   Path(provider): Path<String>,
   Query(query): Query<AuthQuery>,
+  session: SessionMetadata,
   cookies: Cookies,
 ) -> Result<Response, AuthError> {
   let auth_options = state.auth_options();
@@ -58,14 +63,7 @@ pub(crate) async fn callback_from_external_auth_provider(
   };
 
   // Get round-tripped state from cookies, set by prior call to oauth::login.
-  let OAuthStateClaims {
-    csrf_secret,
-    pkce_code_verifier,
-    user_pkce_code_challenge,
-    response_type,
-    redirect_uri,
-    exp: _,
-  } = state
+  let oauth_state = state
     .jwt()
     .decode::<OAuthStateClaims>(
       cookies
@@ -78,51 +76,114 @@ pub(crate) async fn callback_from_external_auth_provider(
       return AuthError::BadRequest("invalid state");
     })?;
 
-  if csrf_secret != query.state {
+  if oauth_state.csrf_secret != query.state {
     remove_cookie(&cookies, COOKIE_OAUTH_STATE);
     return Err(AuthError::BadRequest("invalid state"));
   }
 
+  let oauth_user = fetch_oauth_user(
+    &state,
+    provider,
+    query.code,
+    oauth_state.pkce_code_verifier.clone(),
+  )
+  .await?;
+
+  return sign_in_external_user(&state, &session, &cookies, oauth_state, oauth_user).await;
+}
+
+/// Signs in, or links, the user with the given, already verified external identity according to
+/// the round-tripped state. This is shared between OAuth and other external identity providers,
+/// e.g. SAML.
+pub(crate) async fn sign_in_external_user(
+  state: &AppState,
+  session: &SessionMetadata,
+  cookies: &Cookies,
+  oauth_state: OAuthStateClaims,
+  oauth_user: OAuthUser,
+) -> Result<Response, AuthError> {
+  let OAuthStateClaims {
+    user_pkce_code_challenge,
+    response_type,
+    redirect_uri,
+    link_user,
+    ..
+  } = oauth_state;
+
   // NOTE: This was already validated in the login-handler, we're just pedantic.
-  let redirect_uri = validate_redirect(&state, redirect_uri)?;
+  let redirect_uri = validate_redirect(state, redirect_uri)?;
+
+  if let Some(link_user) = link_user {
+    return callback_from_oauth_provider_linking_identity(
+      state,
+      cookies,
+      redirect_uri,
+      link_user,
+      oauth_user,
+    )
+    .await;
+  }
 
   return match response_type {
     Some(ResponseType::Code) => {
       callback_from_oauth_provider_using_auth_code_flow(
-        &state,
-        &cookies,
-        provider,
+        state,
+        session,
+        cookies,
         redirect_uri,
-        query.code,
-        pkce_code_verifier,
+        oauth_user,
         user_pkce_code_challenge,
       )
       .await
     }
     _ => {
       callback_from_oauth_provider_setting_token_cookies(
-        &state,
-        &cookies,
-        provider,
+        state,
+        session,
+        cookies,
         redirect_uri,
-        query.code,
-        pkce_code_verifier,
+        oauth_user,
       )
       .await
     }
   };
 }
 
+/// Links the external identity to the already signed-in user who initiated the flow, see
+/// `crate::auth::oauth::link`.
+async fn callback_from_oauth_provider_linking_identity(
+  state: &AppState,
+  cookies: &Cookies,
+  redirect: Option<String>,
+  link_user: String,
+  oauth_user: OAuthUser,
+) -> Result<Response, AuthError> {
+  let user_id = b64_to_id(&link_user).map_err(|_err| {
+    remove_cookie(cookies, COOKIE_OAUTH_STATE);
+    return AuthError::BadRequest("invalid state");
+  })?;
+
+  link_identity(state.user_conn(), &Uuid::from_bytes(user_id), &oauth_user).await?;
+
+  remove_cookie(cookies, COOKIE_OAUTH_STATE);
+
+  return if let Some(ref redirect) = redirect {
+    Ok(Redirect::to(redirect).into_response())
+  } else {
+    Ok((StatusCode::OK, "linked").into_response())
+  };
+}
+
 /// Log users in using external OAuth setting token cookies on success.
 async fn callback_from_oauth_provider_setting_token_cookies(
   state: &AppState,
+  session: &SessionMetadata,
   cookies: &Cookies,
-  provider: &OAuthProviderType,
   redirect: Option<String>,
-  auth_code: String,
-  server_pkce_code_verifier: String,
+  oauth_user: OAuthUser,
 ) -> Result<Response, AuthError> {
-  let db_user = get_or_create_user(state, provider, auth_code, server_pkce_code_verifier).await?;
+  let provider = oauth_user.provider_id.as_str_name();
+  let db_user = get_or_create_user(state, oauth_user).await?;
 
   // Mint user token and start a session.
   let (auth_token_ttl, refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
@@ -132,8 +193,11 @@ async fn callback_from_oauth_provider_setting_token_cookies(
     refresh_token,
     ..
   } = mint_new_tokens(
+    state.user_conn(),
     state.session_conn(),
     &db_user,
+    session,
+    &state.custom_claims(),
     &auth_token_ttl,
     &refresh_token_ttl,
   )
@@ -157,6 +221,14 @@ async fn callback_from_oauth_provider_setting_token_cookies(
     refresh_token_ttl,
   ));
 
+  audit(
+    state,
+    AuditEvent::new(Actor::db_user(&db_user), AuditAction::Login)
+      .diff(serde_json::json!({ "provider": provider }))
+      .client_ip(session.client_ip.clone()),
+  )
+  .await;
+
   // NOTE: we're removing the OAUTH_STATE cookie deliberately late in case there are any
   // transient issues, letting users retry.
   remove_cookie(cookies, COOKIE_OAUTH_STATE);
@@ -181,11 +253,10 @@ async fn callback_from_oauth_provider_setting_token_cookies(
 /// Note further that TrailBase requires the use of PKCE when using "authentication code flow".
 async fn callback_from_oauth_provider_using_auth_code_flow(
   state: &AppState,
+  session: &SessionMetadata,
   cookies: &Cookies,
-  provider: &OAuthProviderType,
   redirect: Option<String>,
-  auth_code: String,
-  server_pkce_code_verifier: String,
+  oauth_user: OAuthUser,
   user_pkce_code_challenge: Option<String>,
 ) -> Result<Response, AuthError> {
   let (Some(redirect), Some(user_pkce_code_challenge)) = (redirect, user_pkce_code_challenge)
@@ -196,7 +267,8 @@ async fn callback_from_oauth_provider_using_auth_code_flow(
     return Err(AuthError::BadRequest("invalid state"));
   };
 
-  let db_user = get_or_create_user(state, provider, auth_code, server_pkce_code_verifier).await?;
+  let provider = oauth_user.provider_id.as_str_name();
+  let db_user = get_or_create_user(state, oauth_user).await?;
 
   // For the auth_code flow we generate a random code.
   let authorization_code = random_alphanumeric(VERIFICATION_CODE_LENGTH);
@@ -227,6 +299,16 @@ async fn callback_from_oauth_provider_using_auth_code_flow(
   // transient issues, letting users retry.
   remove_cookie(cookies, COOKIE_OAUTH_STATE);
 
+  if rows_affected == 1 {
+    audit(
+      state,
+      AuditEvent::new(Actor::db_user(&db_user), AuditAction::Login)
+        .diff(serde_json::json!({ "provider": provider }))
+        .client_ip(session.client_ip.clone()),
+    )
+    .await;
+  }
+
   return match rows_affected {
     0 => Err(AuthError::BadRequest("invalid user")),
     1 => Ok(Redirect::to(&format!("{redirect}?code={authorization_code}")).into_response()),
@@ -236,12 +318,13 @@ async fn callback_from_oauth_provider_using_auth_code_flow(
   };
 }
 
-async fn get_or_create_user(
+/// Exchanges the auth code for tokens and fetches the user's information from the provider.
+async fn fetch_oauth_user(
   state: &AppState,
   provider: &OAuthProviderType,
   auth_code: String,
   server_pkce_code_verifier: String,
-) -> Result<DbUser, AuthError> {
+) -> Result<OAuthUser, AuthError> {
   let http_client = reqwest::ClientBuilder::new()
     // Following redirects might set us up for server-side request forgery (SSRF).
     .redirect(reqwest::redirect::Policy::none())
@@ -276,8 +359,12 @@ async fn get_or_create_user(
     return Err(AuthError::BadRequest("External OAuth user unverified"));
   }
 
+  return Ok(oauth_user);
+}
+
+async fn get_or_create_user(state: &AppState, oauth_user: OAuthUser) -> Result<DbUser, AuthError> {
   // Look-up user in local DB to decide whether to create a new one.
-  if let Some(existing_user) = user_by_provider_id(
+  if let Some(existing_user) = user_by_identity(
     state.user_conn(),
     oauth_user.provider_id,
     oauth_user.provider_user_id.clone(),
@@ -291,8 +378,16 @@ async fn get_or_create_user(
     return Ok(existing_user);
   };
 
+  // Refuse to implicitly take over or merge with an existing account of the same e-mail address.
+  // Users need to sign in first and explicitly link the identity instead.
+  match get_user_by_email(state.user_conn(), &oauth_user.email).await {
+    Ok(_) => return Err(AuthError::Conflict),
+    Err(AuthError::NotFound) => {}
+    Err(err) => return Err(err),
+  };
+
   // Otherwise, create a new user and return that.
-  let db_user = create_user_for_external_provider(state.user_conn(), &oauth_user).await?;
+  let db_user = create_user_with_identity(state.user_conn(), &oauth_user).await?;
 
   // This should never happen. We only ever create a new local user here for verified users above.
   if !db_user.verified {
@@ -304,56 +399,6 @@ async fn get_or_create_user(
   return Ok(db_user);
 }
 
-async fn create_user_for_external_provider(
-  conn: &trailbase_sqlite::Connection,
-  user: &OAuthUser,
-) -> Result<DbUser, AuthError> {
-  if !user.verified {
-    return Err(AuthError::Unauthorized);
-  }
-
-  const QUERY: &str = formatcp!(
-    "\
-      INSERT INTO '{USER_TABLE}' ( \
-        provider_id, provider_user_id, verified, email, provider_avatar_url \
-      ) VALUES ( \
-        :provider_id, :provider_user_id, :verified, :email, :avatar \
-      ) RETURNING * \
-    "
-  );
-
-  let db_user: DbUser = conn
-    .write_query_value(
-      QUERY,
-      named_params! {
-          ":provider_id": user.provider_id as i64,
-          ":provider_user_id": user.provider_user_id.clone(),
-          ":verified": user.verified as i64,
-          ":email": user.email.clone(),
-          ":avatar": user.avatar.clone(),
-      },
-    )
-    .await?
-    .ok_or_else(|| AuthError::Internal("query should return".into()))?;
-
-  return Ok(db_user);
-}
-
-async fn user_by_provider_id(
-  conn: &trailbase_sqlite::Connection,
-  provider_id: OAuthProviderId,
-  provider_user_id: String,
-) -> Result<Option<DbUser>, AuthError> {
-  const QUERY: &str =
-    formatcp!("SELECT * FROM '{USER_TABLE}' WHERE provider_id = $1 AND provider_user_id = $2");
-
-  return Ok(
-    conn
-      .read_query_value::<DbUser>(QUERY, params!(provider_id as i64, provider_user_id))
-      .await?,
-  );
-}
-
 struct ReqwestClient(reqwest::Client);
 
 // Yanked from oauth2's `reqwest::Client` implementation.
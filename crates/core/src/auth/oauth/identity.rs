@@ -0,0 +1,260 @@
+use const_format::formatcp;
+use serde::{Deserialize, Serialize};
+use trailbase_sqlite::{SyncConnectionTrait, params};
+use ts_rs::TS;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::auth::AuthError;
+use crate::auth::api::webauthn::has_webauthn_credentials;
+use crate::auth::oauth::OAuthUser;
+use crate::auth::user::DbUser;
+use crate::auth::util::get_user_by_id;
+use crate::config::proto::OAuthProviderId;
+use crate::constants::{USER_IDENTITY_TABLE, USER_TABLE};
+
+/// External OAuth identity linked to a user.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct LinkedIdentity {
+  /// Name of the OAuth provider, e.g. "github".
+  pub provider: String,
+  /// The provider's id for the user.
+  pub provider_user_id: String,
+  /// E-mail address reported by the provider when the identity was linked.
+  pub email: Option<String>,
+  pub created: i64,
+}
+
+/// Looks up the local user linked to the given external identity.
+pub(crate) async fn user_by_identity(
+  conn: &trailbase_sqlite::Connection,
+  provider_id: OAuthProviderId,
+  provider_user_id: String,
+) -> Result<Option<DbUser>, AuthError> {
+  const QUERY: &str = formatcp!(
+    "\
+      SELECT u.* FROM '{USER_TABLE}' AS u \
+        JOIN '{USER_IDENTITY_TABLE}' AS i ON u.id = i.user \
+      WHERE i.provider_id = $1 AND i.provider_user_id = $2 \
+    "
+  );
+
+  return Ok(
+    conn
+      .read_query_value::<DbUser>(QUERY, params!(provider_id as i64, provider_user_id))
+      .await?,
+  );
+}
+
+/// Creates a new, verified local user together with its first linked identity.
+pub(crate) async fn create_user_with_identity(
+  conn: &trailbase_sqlite::Connection,
+  user: &OAuthUser,
+) -> Result<DbUser, AuthError> {
+  if !user.verified {
+    return Err(AuthError::Unauthorized);
+  }
+
+  // NOTE: The legacy `_user.provider_*` columns are still populated for the identity a user
+  // signed up with for backwards compatibility, e.g. for custom queries and the admin UI.
+  const USER_QUERY: &str = formatcp!(
+    "\
+      INSERT INTO '{USER_TABLE}' ( \
+        provider_id, provider_user_id, verified, email, provider_avatar_url \
+      ) VALUES ( \
+        $1, $2, $3, $4, $5 \
+      ) RETURNING id \
+    "
+  );
+  const IDENTITY_QUERY: &str = formatcp!(
+    "\
+      INSERT INTO '{USER_IDENTITY_TABLE}' ( \
+        user, provider_id, provider_user_id, email, avatar_url \
+      ) VALUES ( \
+        $1, $2, $3, $4, $5 \
+      ) \
+    "
+  );
+
+  let provider_id = user.provider_id as i64;
+  let provider_user_id = user.provider_user_id.clone();
+  let email = user.email.clone();
+  let avatar = user.avatar.clone();
+
+  let id: [u8; 16] = conn
+    .transaction(move |tx| -> Result<[u8; 16], trailbase_sqlite::Error> {
+      let Some(row) = tx.query_row(
+        USER_QUERY,
+        params!(
+          provider_id,
+          provider_user_id.clone(),
+          true,
+          email.clone(),
+          avatar.clone()
+        ),
+      )?
+      else {
+        return Err(trailbase_sqlite::Error::Other("query should return".into()));
+      };
+      let id: [u8; 16] = row.get(0)?;
+
+      tx.execute(
+        IDENTITY_QUERY,
+        params!(id, provider_id, provider_user_id, email, avatar),
+      )?;
+
+      tx.commit()?;
+
+      return Ok(id);
+    })
+    .await?;
+
+  return get_user_by_id(conn, &Uuid::from_bytes(id)).await;
+}
+
+/// Links an external identity to an existing user.
+///
+/// Fails with [AuthError::Conflict] if the identity is already linked to a different user.
+pub(crate) async fn link_identity(
+  conn: &trailbase_sqlite::Connection,
+  user_id: &Uuid,
+  user: &OAuthUser,
+) -> Result<(), AuthError> {
+  if !user.verified {
+    return Err(AuthError::Unauthorized);
+  }
+
+  const QUERY: &str = formatcp!(
+    "\
+      INSERT INTO '{USER_IDENTITY_TABLE}' ( \
+        user, provider_id, provider_user_id, email, avatar_url \
+      ) VALUES ( \
+        $1, $2, $3, $4, $5 \
+      ) \
+      ON CONFLICT DO UPDATE SET email = excluded.email, avatar_url = excluded.avatar_url \
+        WHERE user = excluded.user \
+    "
+  );
+
+  let rows_affected = conn
+    .execute(
+      QUERY,
+      params!(
+        user_id.into_bytes(),
+        user.provider_id as i64,
+        user.provider_user_id.clone(),
+        user.email.clone(),
+        user.avatar.clone(),
+      ),
+    )
+    .await?;
+
+  // No rows are affected, if the identity is linked to another user.
+  if rows_affected == 0 {
+    return Err(AuthError::Conflict);
+  }
+  return Ok(());
+}
+
+/// Unlinks the given provider's identity from the user.
+///
+/// Refuses to remove the last means of signing in, i.e. users w/o password or passkeys need to
+/// keep at least one linked identity.
+pub(crate) async fn unlink_identity(
+  state: &AppState,
+  user_id: &Uuid,
+  provider_id: OAuthProviderId,
+) -> Result<(), AuthError> {
+  const DELETE_QUERY: &str = formatcp!(
+    "\
+      DELETE FROM '{USER_IDENTITY_TABLE}' \
+      WHERE \
+        user = $1 AND provider_id = $2 \
+        AND ( \
+          $3 \
+          OR (SELECT password_hash FROM '{USER_TABLE}' WHERE id = $1) <> '' \
+          OR (SELECT COUNT(*) FROM '{USER_IDENTITY_TABLE}' WHERE user = $1) > 1 \
+        ) \
+    "
+  );
+  // Also clear the legacy columns, which are subject to a unique index, to allow the identity
+  // to subsequently sign up or be linked again.
+  const CLEAR_LEGACY_QUERY: &str = formatcp!(
+    "\
+      UPDATE '{USER_TABLE}' \
+      SET provider_id = 0, provider_user_id = NULL, provider_avatar_url = NULL \
+      WHERE id = $1 AND provider_id = $2 \
+    "
+  );
+
+  let has_passkeys = has_webauthn_credentials(state, &user_id.into_bytes()).await?;
+
+  let user_id = user_id.into_bytes();
+  let provider_id = provider_id as i64;
+  let rows_affected = state
+    .user_conn()
+    .transaction(move |tx| -> Result<usize, trailbase_sqlite::Error> {
+      let rows_affected = tx.execute(DELETE_QUERY, params!(user_id, provider_id, has_passkeys))?;
+      if rows_affected > 0 {
+        tx.execute(CLEAR_LEGACY_QUERY, params!(user_id, provider_id))?;
+      }
+      tx.commit()?;
+
+      return Ok(rows_affected);
+    })
+    .await?;
+
+  if rows_affected == 0 {
+    return Err(AuthError::BadRequest(
+      "not linked or last remaining sign-in method",
+    ));
+  }
+  return Ok(());
+}
+
+/// Lists the external identities linked to the given user.
+pub(crate) async fn list_identities(
+  state: &AppState,
+  user_id: &Uuid,
+) -> Result<Vec<LinkedIdentity>, AuthError> {
+  const QUERY: &str = formatcp!(
+    "\
+      SELECT provider_id, provider_user_id, email, created \
+      FROM '{USER_IDENTITY_TABLE}' \
+      WHERE user = $1 \
+      ORDER BY created \
+    "
+  );
+
+  let rows = state
+    .user_conn()
+    .read_query_rows(QUERY, params!(user_id.into_bytes()))
+    .await?;
+
+  let auth_options = state.auth_options();
+  return rows
+    .iter()
+    .map(|row| -> Result<LinkedIdentity, AuthError> {
+      let internal =
+        |err: trailbase_sqlite::from_sql::FromSqlError| AuthError::Internal(err.into());
+
+      let provider_id = OAuthProviderId::try_from(row.get::<i64>(0).map_err(internal)? as i32)
+        .map_err(|err| AuthError::Internal(err.into()))?;
+
+      return Ok(LinkedIdentity {
+        // Fall back to the canonical name for providers that are no longer configured.
+        provider: auth_options
+          .lookup_oauth_provider_by_id(provider_id)
+          .map_or_else(
+            || provider_id.as_str_name().to_lowercase(),
+            |p| p.name().to_string(),
+          ),
+        provider_user_id: row.get(1).map_err(internal)?,
+        email: row.get(2).map_err(internal)?,
+        created: row.get(3).map_err(internal)?,
+      });
+    })
+    .collect();
+}
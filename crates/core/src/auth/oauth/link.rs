@@ -0,0 +1,102 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Redirect, Response};
+use chrono::Duration;
+use serde::Deserialize;
+use tower_cookies::Cookies;
+use utoipa::IntoParams;
+
+use crate::AppState;
+use crate::auth::oauth::identity::unlink_identity;
+use crate::auth::oauth::login::{authorize_url, set_oauth_state_cookie};
+use crate::auth::oauth::state::OAuthStateClaims;
+use crate::auth::util::validate_redirect;
+use crate::auth::{AuthError, User};
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub(crate) struct LinkQuery {
+  /// Redirect target after the identity was (un)linked.
+  pub redirect_uri: Option<String>,
+}
+
+/// Link an external OAuth provider's identity to the signed-in user.
+///
+/// Redirects to the external provider, which will subsequently call back into
+/// `/{provider}/callback` to complete the linking.
+#[utoipa::path(
+  get,
+  path = "/{provider}/link",
+  tag = "oauth",
+  params(LinkQuery),
+  responses(
+    (status = 200, description = "Redirect.")
+  )
+)]
+pub(crate) async fn link_external_auth_provider(
+  State(state): State<AppState>,
+  Path(provider): Path<String>,
+  Query(query): Query<LinkQuery>,
+  user: User,
+  cookies: Cookies,
+) -> Result<Redirect, AuthError> {
+  let auth_options = state.auth_options();
+  let Some(provider) = auth_options.lookup_oauth_provider(&provider) else {
+    return Err(AuthError::OAuthProviderNotFound);
+  };
+  let redirect_uri = validate_redirect(&state, query.redirect_uri)?;
+
+  let (authorize_url, csrf_state, server_pkce_code_verifier) = authorize_url(&state, provider)?;
+
+  set_oauth_state_cookie(
+    &state,
+    &cookies,
+    &OAuthStateClaims {
+      exp: (chrono::Utc::now() + Duration::seconds(5 * 60)).timestamp(),
+      csrf_secret: csrf_state.secret().to_string(),
+      pkce_code_verifier: server_pkce_code_verifier.secret().to_string(),
+      user_pkce_code_challenge: None,
+      response_type: None,
+      redirect_uri,
+      link_user: Some(user.id),
+    },
+  )?;
+
+  return Ok(Redirect::to(authorize_url.as_str()));
+}
+
+/// Unlink an external OAuth provider's identity from the signed-in user.
+///
+/// Fails if the identity is the user's last remaining means to sign in.
+#[utoipa::path(
+  post,
+  path = "/{provider}/unlink",
+  tag = "oauth",
+  params(LinkQuery),
+  responses(
+    (status = 200, description = "Success, when redirect_uri not present."),
+    (status = 303, description = "Success, when redirect_uri present."),
+  )
+)]
+pub(crate) async fn unlink_external_auth_provider(
+  State(state): State<AppState>,
+  Path(provider): Path<String>,
+  Query(query): Query<LinkQuery>,
+  user: User,
+) -> Result<Response, AuthError> {
+  let provider_id = {
+    let auth_options = state.auth_options();
+    let Some(provider) = auth_options.lookup_oauth_provider(&provider) else {
+      return Err(AuthError::OAuthProviderNotFound);
+    };
+    provider.provider()
+  };
+  let redirect_uri = validate_redirect(&state, query.redirect_uri)?;
+
+  unlink_identity(&state, &user.uuid, provider_id).await?;
+
+  return if let Some(ref redirect) = redirect_uri {
+    Ok(Redirect::to(redirect).into_response())
+  } else {
+    Ok((StatusCode::OK, "unlinked").into_response())
+  };
+}
@@ -3,12 +3,14 @@ use axum::{
   response::Redirect,
 };
 use chrono::Duration;
-use oauth2::{CsrfToken, PkceCodeChallenge, Scope};
+use oauth2::{CsrfToken, PkceCodeChallenge, PkceCodeVerifier, Scope};
 use tower_cookies::Cookies;
+use url::Url;
 
 use crate::AppState;
 use crate::auth::AuthError;
 use crate::auth::login_params::{LoginInputParams, LoginParams, build_and_validate_input_params};
+use crate::auth::oauth::providers::OAuthProviderType;
 use crate::auth::oauth::state::{OAuthStateClaims, ResponseType};
 use crate::auth::util::{new_cookie_opts, secure_tls_only};
 use crate::constants::COOKIE_OAUTH_STATE;
@@ -35,22 +37,7 @@ pub(crate) async fn login_with_external_auth_provider(
   };
   let login_params = build_and_validate_input_params(&state, login_input_query)?;
 
-  // Also use PKCE between TrailBase and the external auth provider. Is is independent from PKCE
-  // between the client and TrailBase.
-  let (server_pkce_code_challenge, server_pkce_code_verifier) =
-    PkceCodeChallenge::new_random_sha256();
-
-  let (authorize_url, csrf_state) = provider
-    .oauth_client(&state)?
-    .authorize_url(CsrfToken::new_random)
-    .add_scopes(
-      provider
-        .oauth_scopes()
-        .into_iter()
-        .map(|s| Scope::new(s.to_string())),
-    )
-    .set_pkce_challenge(server_pkce_code_challenge)
-    .url();
+  let (authorize_url, csrf_state, server_pkce_code_verifier) = authorize_url(&state, provider)?;
 
   let oauth_state = match login_params {
     LoginParams::Password { redirect_uri } => OAuthStateClaims {
@@ -61,6 +48,7 @@ pub(crate) async fn login_with_external_auth_provider(
       redirect_uri,
       response_type: None,
       user_pkce_code_challenge: None,
+      link_user: None,
     },
     LoginParams::AuthorizationCodeFlowWithPkce {
       redirect_uri,
@@ -73,9 +61,46 @@ pub(crate) async fn login_with_external_auth_provider(
       user_pkce_code_challenge: Some(pkce_code_challenge),
       response_type: Some(ResponseType::Code),
       redirect_uri: Some(redirect_uri),
+      link_user: None,
     },
   };
 
+  set_oauth_state_cookie(&state, &cookies, &oauth_state)?;
+
+  Ok(Redirect::to(authorize_url.as_str()))
+}
+
+/// Builds the provider's authorize URL returning it together with the CSRF state and the
+/// server-side PKCE code verifier, which need to be round-tripped to the callback.
+pub(super) fn authorize_url(
+  state: &AppState,
+  provider: &OAuthProviderType,
+) -> Result<(Url, CsrfToken, PkceCodeVerifier), AuthError> {
+  // Also use PKCE between TrailBase and the external auth provider. Is is independent from PKCE
+  // between the client and TrailBase.
+  let (server_pkce_code_challenge, server_pkce_code_verifier) =
+    PkceCodeChallenge::new_random_sha256();
+
+  let (authorize_url, csrf_state) = provider
+    .oauth_client(state)?
+    .authorize_url(CsrfToken::new_random)
+    .add_scopes(
+      provider
+        .oauth_scopes()
+        .into_iter()
+        .map(|s| Scope::new(s.to_string())),
+    )
+    .set_pkce_challenge(server_pkce_code_challenge)
+    .url();
+
+  return Ok((authorize_url, csrf_state, server_pkce_code_verifier));
+}
+
+pub(super) fn set_oauth_state_cookie(
+  state: &AppState,
+  cookies: &Cookies,
+  oauth_state: &OAuthStateClaims,
+) -> Result<(), AuthError> {
   cookies.add(new_cookie_opts(
     COOKIE_OAUTH_STATE,
     // Encoding as JWT token for tamper proofing. This doesn't encrypt anything but merely adds a
@@ -85,12 +110,12 @@ pub(crate) async fn login_with_external_auth_provider(
     // `same_site=false`.
     state
       .jwt()
-      .encode(&oauth_state)
+      .encode(oauth_state)
       .map_err(|err| AuthError::Internal(err.into()))?,
     Duration::minutes(5),
-    /* secure/tls_only= */ secure_tls_only(&state),
+    /* secure/tls_only= */ secure_tls_only(state),
     /* same_site= */ false,
   ));
 
-  Ok(Redirect::to(authorize_url.as_str()))
+  return Ok(());
 }
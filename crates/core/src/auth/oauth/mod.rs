@@ -2,6 +2,8 @@ pub(crate) mod provider;
 pub(crate) mod providers;
 
 mod callback;
+pub(crate) mod identity;
+mod link;
 mod list_providers;
 mod login;
 mod state;
@@ -10,10 +12,12 @@ mod state;
 mod oauth_test;
 
 use axum::Router;
-use axum::routing::get;
+use axum::routing::{get, post};
 use utoipa::OpenApi;
 
+pub(crate) use callback::sign_in_external_user;
 pub(crate) use provider::{OAuthClientSettings, OAuthProvider, OAuthUser};
+pub(crate) use state::{OAuthStateClaims, ResponseType};
 
 use crate::AppState;
 
@@ -22,6 +26,8 @@ use crate::AppState;
   list_providers::list_configured_providers_handler,
   login::login_with_external_auth_provider,
   callback::callback_from_external_auth_provider,
+  link::link_external_auth_provider,
+  link::unlink_external_auth_provider,
 ))]
 pub(super) struct OAuthApi;
 
@@ -39,4 +45,9 @@ pub fn oauth_router() -> Router<AppState> {
       "/{provider}/callback",
       get(callback::callback_from_external_auth_provider),
     )
+    .route("/{provider}/link", get(link::link_external_auth_provider))
+    .route(
+      "/{provider}/unlink",
+      post(link::unlink_external_auth_provider),
+    )
 }
@@ -1,5 +1,5 @@
 use axum::extract::{Form, Json, Path, Query, State};
-use axum::response::{IntoResponse, Redirect};
+use axum::response::{IntoResponse, Redirect, Response};
 use axum::routing::{Router, get, post};
 use axum_test::{TestServer, TestServerConfig};
 use base64::prelude::*;
@@ -10,17 +10,21 @@ use std::collections::HashMap;
 use tower_cookies::Cookies;
 use uuid::Uuid;
 
+use crate::admin::user::create_user_for_test;
 use crate::api::AuthTokenClaims;
 use crate::app_state::{AppState, TestStateOptions, test_state};
 use crate::auth::api::token::{
   AuthCodeToTokenRequest, TokenResponse as TokenHandlerResponse, auth_code_to_token_handler,
 };
 use crate::auth::login_params::{LoginInputParams, ResponseType};
+use crate::auth::oauth::identity::list_identities;
 use crate::auth::oauth::providers::test::{TestOAuthProvider, TestUser};
 use crate::auth::oauth::state::OAuthStateClaims;
-use crate::auth::oauth::{callback, list_providers, login};
+use crate::auth::oauth::{callback, link, list_providers, login};
+use crate::auth::session::SessionMetadata;
 use crate::auth::user::DbUser;
-use crate::auth::util::derive_pkce_code_challenge;
+use crate::auth::util::{derive_pkce_code_challenge, login_with_password};
+use crate::auth::{AuthError, User};
 use crate::config::proto::{Config, OAuthProviderConfig, OAuthProviderId};
 use crate::constants::{
   AUTH_API_PATH, COOKIE_AUTH_TOKEN, COOKIE_OAUTH_STATE, COOKIE_REFRESH_TOKEN, SESSION_TABLE,
@@ -213,6 +217,7 @@ async fn test_oauth_login_flow_without_pkce() {
       state: auth_query.state.clone(),
       code: auth_query.code_challenge.clone(),
     }),
+    SessionMetadata::default(),
     cookies.clone(),
   )
   .await
@@ -318,6 +323,7 @@ async fn test_oauth_login_flow_with_pkce() {
       state: auth_query.state.clone(),
       code: auth_query.code_challenge.clone(),
     }),
+    SessionMetadata::default(),
     cookies.clone(),
   )
   .await
@@ -350,6 +356,7 @@ async fn test_oauth_login_flow_with_pkce() {
   // Upgrade to tokens, i.e. complete log-in.
   let Json(token_response): Json<TokenHandlerResponse> = auth_code_to_token_handler(
     State(state.clone()),
+    SessionMetadata::default(),
     Json(AuthCodeToTokenRequest {
       authorization_code: Some(auth_code.as_str().to_string()),
       pkce_code_verifier: Some(pkce_code_verifier.secret().to_string()),
@@ -375,6 +382,119 @@ async fn test_oauth_login_flow_with_pkce() {
   assert_eq!(EXTERNAL_USER_EMAIL, decoded_claims.email);
 }
 
+#[tokio::test]
+async fn test_oauth_link_identity_to_existing_user() {
+  let site_url = "https://bar.org";
+  let (_server, state) = setup_fake_oauth_server(site_url).await;
+
+  // An existing password user with the same e-mail address as the external user.
+  let password = "Secret!1!!";
+  let user_id = create_user_for_test(&state, EXTERNAL_USER_EMAIL, password)
+    .await
+    .unwrap();
+
+  // Signing in via OAuth must not implicitly take over the existing account.
+  let cookies = Cookies::default();
+  let external_redirect: Redirect = login::login_with_external_auth_provider(
+    State(state.clone()),
+    Path(TestOAuthProvider::NAME.to_string()),
+    Query(LoginInputParams::default()),
+    cookies.clone(),
+  )
+  .await
+  .unwrap();
+  assert!(matches!(
+    complete_external_auth(&state, &cookies, external_redirect).await,
+    Err(AuthError::Conflict)
+  ));
+
+  // Instead, the signed-in user explicitly links the external identity.
+  let tokens = login_with_password(&state, EXTERNAL_USER_EMAIL, password)
+    .await
+    .unwrap();
+  let user = User::from_auth_token(&state, &tokens.auth_token).unwrap();
+
+  let cookies = Cookies::default();
+  let external_redirect: Redirect = link::link_external_auth_provider(
+    State(state.clone()),
+    Path(TestOAuthProvider::NAME.to_string()),
+    Query(link::LinkQuery {
+      redirect_uri: Some("/profile".to_string()),
+    }),
+    user.clone(),
+    cookies.clone(),
+  )
+  .await
+  .unwrap();
+  let response = complete_external_auth(&state, &cookies, external_redirect)
+    .await
+    .unwrap();
+  assert_eq!(get_redirect_location(response).unwrap(), "/profile");
+
+  let identities = list_identities(&state, &user_id).await.unwrap();
+  assert_eq!(identities.len(), 1);
+  assert_eq!(identities[0].provider, TestOAuthProvider::NAME);
+  assert_eq!(identities[0].provider_user_id, EXTERNAL_USER_ID);
+
+  // Now signing in via OAuth yields the existing user.
+  let cookies = Cookies::default();
+  let external_redirect: Redirect = login::login_with_external_auth_provider(
+    State(state.clone()),
+    Path(TestOAuthProvider::NAME.to_string()),
+    Query(LoginInputParams::default()),
+    cookies.clone(),
+  )
+  .await
+  .unwrap();
+  complete_external_auth(&state, &cookies, external_redirect)
+    .await
+    .unwrap();
+  let auth_token = cookies.get(COOKIE_AUTH_TOKEN).unwrap().value().to_string();
+  let decoded_claims = state.jwt().decode::<AuthTokenClaims>(&auth_token).unwrap();
+  assert_eq!(
+    BASE64_URL_SAFE.decode(&decoded_claims.sub).unwrap(),
+    user_id.into_bytes()
+  );
+
+  // And finally unlink, which is fine since the user still has a password.
+  link::unlink_external_auth_provider(
+    State(state.clone()),
+    Path(TestOAuthProvider::NAME.to_string()),
+    Query(link::LinkQuery::default()),
+    user,
+  )
+  .await
+  .unwrap();
+  assert!(list_identities(&state, &user_id).await.unwrap().is_empty());
+}
+
+/// Pretend to be the browser following the redirect to the external provider and back to TB's
+/// callback handler.
+async fn complete_external_auth(
+  state: &AppState,
+  cookies: &Cookies,
+  external_redirect: Redirect,
+) -> Result<Response, AuthError> {
+  let auth_query: AuthQuery = reqwest::get(&get_redirect_location(external_redirect).unwrap())
+    .await
+    .unwrap()
+    .json()
+    .await
+    .unwrap();
+
+  return callback::callback_from_external_auth_provider(
+    State(state.clone()),
+    Path(TestOAuthProvider::NAME.to_string()),
+    Query(callback::AuthQuery {
+      state: auth_query.state.clone(),
+      code: auth_query.code_challenge.clone(),
+    }),
+    SessionMetadata::default(),
+    cookies.clone(),
+  )
+  .await;
+}
+
 fn get_redirect_location<T: IntoResponse>(response: T) -> Option<String> {
   return response
     .into_response()
@@ -61,7 +61,6 @@ pub struct OAuthClientSettings {
 
 #[async_trait]
 pub trait OAuthProvider {
-  #[allow(unused)]
   fn provider(&self) -> OAuthProviderId;
 
   fn name(&self) -> &str;
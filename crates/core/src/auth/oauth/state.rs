@@ -43,6 +43,11 @@ pub(crate) struct OAuthStateClaims {
 
   /// Redirect target.
   pub redirect_uri: Option<String>,
+
+  /// Base64 encoded id of the signed-in user, who initiated linking an external identity to
+  /// their account. If present, the callback will link rather than log in.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub link_user: Option<String>,
 }
 
 #[cfg(test)]
@@ -61,6 +66,7 @@ mod tests {
       user_pkce_code_challenge: Some("client challenge".to_string()),
       response_type: Some(ResponseType::Code),
       redirect_uri: Some("custom-sheme://test".to_string()),
+      link_user: None,
     };
 
     let encoded = state.jwt().encode(&oauth_state).unwrap();
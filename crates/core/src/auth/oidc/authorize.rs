@@ -0,0 +1,256 @@
+use axum::extract::{Form, OriginalUri, Query, State};
+use axum::response::Redirect;
+use chrono::Utc;
+use const_format::formatcp;
+use serde::Deserialize;
+use trailbase_sqlite::params;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::AppState;
+use crate::auth::jwt::OidcConsentClaims;
+use crate::auth::oidc::client::{OAuthClient, get_oauth_client};
+use crate::auth::oidc::{CONSENT_UI, LOGIN_UI, issuer, normalize_scope};
+use crate::auth::{AuthError, User};
+use crate::constants::{
+  AUTHORIZATION_CODE_TABLE, DEFAULT_AUTHORIZATION_CODE_TTL, DEFAULT_OIDC_CONSENT_TTL,
+  VERIFICATION_CODE_LENGTH,
+};
+use crate::rand::random_alphanumeric;
+use crate::util::urlencode;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub(crate) struct AuthorizeQuery {
+  /// Must be "code".
+  pub response_type: Option<String>,
+  pub client_id: Option<String>,
+  /// Must exactly match one of the client's registered redirect URIs.
+  pub redirect_uri: Option<String>,
+  /// Space-separated scopes, e.g. "openid email".
+  pub scope: Option<String>,
+  /// Opaque client state passed back to the client as is.
+  pub state: Option<String>,
+  /// Included in the ID token to mitigate replay attacks.
+  pub nonce: Option<String>,
+  pub code_challenge: Option<String>,
+  /// Must be "S256".
+  pub code_challenge_method: Option<String>,
+}
+
+/// Start signing into a registered OAuth client.
+///
+/// Redirects signed-in users to the consent screen and everyone else to the login screen first.
+#[utoipa::path(
+  get,
+  path = "/authorize",
+  tag = "oidc",
+  params(AuthorizeQuery),
+  responses(
+    (status = 303, description = "Redirect to consent or login screen, or error redirect to client.")
+  )
+)]
+pub(crate) async fn authorize_handler(
+  State(state): State<AppState>,
+  OriginalUri(original_uri): OriginalUri,
+  Query(query): Query<AuthorizeQuery>,
+  user: Option<User>,
+) -> Result<Redirect, AuthError> {
+  let issuer = issuer(&state)?;
+  let (client, redirect_uri) = validate_client(
+    &state,
+    query.client_id.as_deref(),
+    query.redirect_uri.as_deref(),
+  )
+  .await?;
+
+  // Once the redirect is validated, errors are reported back to the client.
+  let error = |error: &str, description: &str| {
+    return Redirect::to(&client_redirect(
+      &redirect_uri,
+      &[("error", error), ("error_description", description)],
+      query.state.as_deref(),
+      &issuer,
+    ));
+  };
+
+  if query.response_type.as_deref() != Some("code") {
+    return Ok(error(
+      "unsupported_response_type",
+      "only response_type=code is supported",
+    ));
+  }
+  let Some(code_challenge) = query.code_challenge.filter(|c| !c.is_empty()) else {
+    return Ok(error("invalid_request", "PKCE code_challenge required"));
+  };
+  if query.code_challenge_method.as_deref() != Some("S256") {
+    return Ok(error(
+      "invalid_request",
+      "code_challenge_method must be S256",
+    ));
+  }
+
+  let Some(user) = user else {
+    // Sign in first and come back here afterwards.
+    let path_and_query = original_uri
+      .path_and_query()
+      .map(|p| p.as_str())
+      .unwrap_or_default();
+    return Ok(Redirect::to(&format!(
+      "{LOGIN_UI}?redirect_uri={}",
+      urlencode(path_and_query)
+    )));
+  };
+
+  let scope = normalize_scope(query.scope.as_deref());
+  let consent_token = state
+    .jwt()
+    .encode(&OidcConsentClaims::new(
+      &user.uuid,
+      client.id,
+      redirect_uri,
+      scope.clone(),
+      query.state,
+      query.nonce,
+      code_challenge,
+      DEFAULT_OIDC_CONSENT_TTL,
+    ))
+    .map_err(|err| AuthError::Internal(err.into()))?;
+
+  return Ok(Redirect::to(&format!(
+    "{CONSENT_UI}?consent_token={consent_token}&client_name={name}&scope={scope}",
+    name = urlencode(&client.name),
+    scope = urlencode(&scope),
+  )));
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct ConsentRequest {
+  /// Token handed to the consent screen by `/oidc/authorize`.
+  pub consent_token: String,
+  /// Either "allow" or "deny".
+  pub decision: String,
+}
+
+/// Complete the consent screen, redirecting back to the client.
+///
+/// Passes an authorization code to the client, if the user allowed signing in.
+#[utoipa::path(
+  post,
+  path = "/authorize",
+  tag = "oidc",
+  request_body = ConsentRequest,
+  responses(
+    (status = 303, description = "Redirect to client with authorization code or error.")
+  )
+)]
+pub(crate) async fn consent_handler(
+  State(state): State<AppState>,
+  user: User,
+  Form(request): Form<ConsentRequest>,
+) -> Result<Redirect, AuthError> {
+  let issuer = issuer(&state)?;
+  let Ok(consent) = OidcConsentClaims::decode(state.jwt(), &request.consent_token) else {
+    return Err(AuthError::BadRequest("invalid consent token"));
+  };
+  if consent.sub != user.id {
+    return Err(AuthError::Forbidden);
+  }
+
+  // Re-validate in case the client was deleted or changed in the meantime.
+  let (client, redirect_uri) = validate_client(
+    &state,
+    Some(&consent.client_id),
+    Some(&consent.redirect_uri),
+  )
+  .await?;
+
+  if request.decision != "allow" {
+    return Ok(Redirect::to(&client_redirect(
+      &redirect_uri,
+      &[("error", "access_denied")],
+      consent.state.as_deref(),
+      &issuer,
+    )));
+  }
+
+  const QUERY: &str = formatcp!(
+    "\
+      INSERT INTO '{AUTHORIZATION_CODE_TABLE}' \
+        (user, authorization_code, pkce_code_challenge, expires, \
+         client_id, redirect_uri, scope, nonce) \
+      VALUES \
+        ($1, $2, $3, $4, $5, $6, $7, $8) \
+    "
+  );
+
+  let authorization_code = random_alphanumeric(VERIFICATION_CODE_LENGTH);
+  state
+    .session_conn()
+    .execute(
+      QUERY,
+      params!(
+        user.uuid.into_bytes(),
+        authorization_code.clone(),
+        consent.code_challenge,
+        (Utc::now() + DEFAULT_AUTHORIZATION_CODE_TTL).timestamp(),
+        client.id,
+        redirect_uri.clone(),
+        consent.scope,
+        consent.nonce,
+      ),
+    )
+    .await?;
+
+  return Ok(Redirect::to(&client_redirect(
+    &redirect_uri,
+    &[("code", &authorization_code)],
+    consent.state.as_deref(),
+    &issuer,
+  )));
+}
+
+/// Looks up the client and ensures the redirect URI was registered.
+///
+/// NOTE: Failures must not redirect back to the unvalidated redirect URI to avoid open redirects.
+async fn validate_client(
+  state: &AppState,
+  client_id: Option<&str>,
+  redirect_uri: Option<&str>,
+) -> Result<(OAuthClient, String), AuthError> {
+  let (Some(client_id), Some(redirect_uri)) = (client_id, redirect_uri) else {
+    return Err(AuthError::BadRequest("missing client_id or redirect_uri"));
+  };
+
+  let Some(client) = get_oauth_client(state.user_conn(), client_id).await? else {
+    return Err(AuthError::BadRequest("unknown client"));
+  };
+  if !client.allows_redirect(redirect_uri) {
+    return Err(AuthError::BadRequest("unregistered redirect_uri"));
+  }
+
+  return Ok((client, redirect_uri.to_string()));
+}
+
+/// Builds the redirect back to the client, including the client's state and the issuer as per
+/// RFC 9207.
+fn client_redirect(
+  redirect_uri: &str,
+  params: &[(&str, &str)],
+  state: Option<&str>,
+  issuer: &str,
+) -> String {
+  let Ok(mut url) = url::Url::parse(redirect_uri) else {
+    // Registered redirect URIs are validated upon registration.
+    return redirect_uri.to_string();
+  };
+
+  {
+    let mut pairs = url.query_pairs_mut();
+    pairs.extend_pairs(params);
+    if let Some(state) = state {
+      pairs.append_pair("state", state);
+    }
+    pairs.append_pair("iss", issuer);
+  }
+
+  return url.to_string();
+}
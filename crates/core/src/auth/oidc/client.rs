@@ -0,0 +1,165 @@
+use const_format::formatcp;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use trailbase_sqlite::{Connection, params};
+use ts_rs::TS;
+
+use crate::auth::AuthError;
+use crate::constants::{OAUTH_CLIENT_ID_LENGTH, OAUTH_CLIENT_SECRET_LENGTH, OAUTH_CLIENT_TABLE};
+use crate::rand::random_alphanumeric;
+
+/// Client application registered to sign in users via TrailBase.
+#[derive(Clone, Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct OAuthClient {
+  /// The public `client_id`.
+  pub id: String,
+  pub name: String,
+  /// Whether the client authenticates with a secret. Public clients, e.g. SPAs or mobile apps,
+  /// solely rely on PKCE.
+  pub confidential: bool,
+  pub redirect_uris: Vec<String>,
+  pub created: i64,
+}
+
+impl OAuthClient {
+  pub(crate) fn allows_redirect(&self, redirect_uri: &str) -> bool {
+    return self.redirect_uris.iter().any(|uri| uri == redirect_uri);
+  }
+}
+
+/// Registers a new client, returning its id and, for confidential clients, the secret. The
+/// secret itself is not stored and cannot be recovered later.
+pub async fn register_oauth_client(
+  user_conn: &Connection,
+  name: String,
+  redirect_uris: Vec<String>,
+  confidential: bool,
+) -> Result<(String, Option<String>), AuthError> {
+  if name.is_empty() {
+    return Err(AuthError::BadRequest("missing name"));
+  }
+  if redirect_uris.is_empty() {
+    return Err(AuthError::BadRequest("missing redirect uris"));
+  }
+  for uri in &redirect_uris {
+    // NOTE: Fragments are disallowed by RFC 6749 Section 3.1.2.
+    match url::Url::parse(uri) {
+      Ok(url) if url.fragment().is_none() => {}
+      _ => {
+        return Err(AuthError::BadRequest("invalid redirect uri"));
+      }
+    };
+  }
+
+  let id = random_alphanumeric(OAUTH_CLIENT_ID_LENGTH);
+  let secret = confidential.then(|| random_alphanumeric(OAUTH_CLIENT_SECRET_LENGTH));
+
+  const QUERY: &str = formatcp!(
+    "\
+      INSERT INTO '{OAUTH_CLIENT_TABLE}' (id, name, secret_hash, redirect_uris) \
+      VALUES ($1, $2, $3, $4) \
+    "
+  );
+
+  user_conn
+    .execute(
+      QUERY,
+      params!(
+        id.clone(),
+        name,
+        secret.as_deref().map(hash_client_secret),
+        serde_json::to_string(&redirect_uris).map_err(|err| AuthError::Internal(err.into()))?,
+      ),
+    )
+    .await?;
+
+  return Ok((id, secret));
+}
+
+/// Deletes the client with the given id.
+///
+/// NOTE: Tokens already issued to the client stay valid until they expire.
+pub async fn delete_oauth_client(user_conn: &Connection, id: &str) -> Result<(), AuthError> {
+  const QUERY: &str = formatcp!("DELETE FROM '{OAUTH_CLIENT_TABLE}' WHERE id = $1");
+
+  let rows_affected = user_conn.execute(QUERY, params!(id.to_string())).await?;
+  if rows_affected == 0 {
+    return Err(AuthError::NotFound);
+  }
+  return Ok(());
+}
+
+pub async fn list_oauth_clients(user_conn: &Connection) -> Result<Vec<OAuthClient>, AuthError> {
+  const QUERY: &str =
+    formatcp!("SELECT {CLIENT_COLUMNS} FROM '{OAUTH_CLIENT_TABLE}' ORDER BY created");
+
+  let rows = user_conn.read_query_rows(QUERY, ()).await?;
+  return rows.iter().map(client_from_row).collect();
+}
+
+pub(crate) async fn get_oauth_client(
+  user_conn: &Connection,
+  id: &str,
+) -> Result<Option<OAuthClient>, AuthError> {
+  const QUERY: &str =
+    formatcp!("SELECT {CLIENT_COLUMNS} FROM '{OAUTH_CLIENT_TABLE}' WHERE id = $1");
+
+  return user_conn
+    .read_query_row(QUERY, params!(id.to_string()))
+    .await?
+    .as_ref()
+    .map(client_from_row)
+    .transpose();
+}
+
+/// Authenticates a client presenting its id and optionally a secret.
+///
+/// Confidential clients have to present their secret, public ones must not.
+pub(crate) async fn authenticate_oauth_client(
+  user_conn: &Connection,
+  id: &str,
+  secret: Option<&str>,
+) -> Result<OAuthClient, AuthError> {
+  const QUERY: &str = formatcp!("SELECT secret_hash FROM '{OAUTH_CLIENT_TABLE}' WHERE id = $1");
+
+  let Some(secret_hash) = user_conn
+    .read_query_row_get::<Option<Vec<u8>>>(QUERY, params!(id.to_string()), 0)
+    .await?
+  else {
+    return Err(AuthError::Unauthorized);
+  };
+
+  match (secret_hash, secret) {
+    (None, None) => {}
+    (Some(expected), Some(secret)) if expected == hash_client_secret(secret) => {}
+    _ => {
+      return Err(AuthError::Unauthorized);
+    }
+  };
+
+  return get_oauth_client(user_conn, id)
+    .await?
+    .ok_or(AuthError::Unauthorized);
+}
+
+const CLIENT_COLUMNS: &str = "id, name, secret_hash IS NOT NULL, redirect_uris, created";
+
+fn client_from_row(row: &trailbase_sqlite::Row) -> Result<OAuthClient, AuthError> {
+  let internal = |err: trailbase_sqlite::from_sql::FromSqlError| AuthError::Internal(err.into());
+
+  let redirect_uris: String = row.get(3).map_err(internal)?;
+
+  return Ok(OAuthClient {
+    id: row.get(0).map_err(internal)?,
+    name: row.get(1).map_err(internal)?,
+    confidential: row.get(2).map_err(internal)?,
+    redirect_uris: serde_json::from_str(&redirect_uris)
+      .map_err(|err| AuthError::Internal(err.into()))?,
+    created: row.get(4).map_err(internal)?,
+  });
+}
+
+fn hash_client_secret(secret: &str) -> Vec<u8> {
+  return Sha256::digest(secret.as_bytes()).to_vec();
+}
@@ -0,0 +1,95 @@
+//! TrailBase acting as OAuth2/OpenID Connect provider, letting registered client applications
+//! "Sign in with TrailBase".
+//!
+//! Only the authorization code flow with (S256) PKCE is supported:
+//!
+//!  1. The client sends users to `/authorize`, which asks signed-in users for consent in the auth
+//!     UI or otherwise has them sign in first.
+//!  2. Upon approval, users get redirected back to the client with an authorization code.
+//!  3. The client exchanges the code for an access and ID token at `/token`.
+//!  4. The access token grants access to `/userinfo`, but not to any other TrailBase APIs.
+use axum::{
+  Router,
+  routing::{get, post},
+};
+use utoipa::OpenApi;
+
+use crate::AppState;
+use crate::auth::AuthError;
+use crate::constants::AUTH_API_PATH;
+
+pub mod client;
+
+pub(crate) mod authorize;
+pub(crate) mod token;
+pub(crate) mod userinfo;
+
+pub(crate) const AUTHORIZE_PATH: &str = "oidc/authorize";
+pub(crate) const TOKEN_PATH: &str = "oidc/token";
+pub(crate) const USERINFO_PATH: &str = "oidc/userinfo";
+
+/// Path of the auth UI's consent screen.
+const CONSENT_UI: &str = "/_/auth/oidc/consent";
+/// Path of the auth UI's login screen.
+const LOGIN_UI: &str = "/_/auth/login";
+
+pub(crate) const SCOPE_OPENID: &str = "openid";
+pub(crate) const SCOPE_EMAIL: &str = "email";
+pub(crate) const SUPPORTED_SCOPES: &[&str] = &[SCOPE_OPENID, SCOPE_EMAIL];
+
+#[derive(OpenApi)]
+#[openapi(
+  paths(
+    authorize::authorize_handler,
+    authorize::consent_handler,
+    token::token_handler,
+    userinfo::userinfo_handler,
+  ),
+  components(schemas(token::OidcTokenResponse, userinfo::UserInfo,))
+)]
+pub(super) struct OidcApi;
+
+pub(super) fn oidc_router() -> Router<AppState> {
+  return Router::new()
+    .route("/authorize", get(authorize::authorize_handler))
+    .route("/authorize", post(authorize::consent_handler))
+    .route("/token", post(token::token_handler))
+    .route(
+      "/userinfo",
+      get(userinfo::userinfo_handler).post(userinfo::userinfo_handler),
+    );
+}
+
+/// The issuer identifier, i.e. the `site_url` w/o trailing slash.
+pub(crate) fn issuer(state: &AppState) -> Result<String, AuthError> {
+  let Some(ref site_url) = *state.site_url() else {
+    return Err(AuthError::FailedDependency(
+      "OpenID Connect requires a site_url to derive the issuer".into(),
+    ));
+  };
+  return Ok(site_url.as_str().trim_end_matches('/').to_string());
+}
+
+/// Absolute URL of the given auth API endpoint, e.g. [TOKEN_PATH].
+pub(crate) fn endpoint_url(issuer: &str, path: &str) -> String {
+  return format!("{issuer}/{AUTH_API_PATH}/{path}");
+}
+
+/// Reduces the requested, space-separated scopes to the supported ones. Unknown scopes are
+/// ignored as permitted by RFC 6749 Section 3.3.
+pub(crate) fn normalize_scope(scope: Option<&str>) -> String {
+  let requested: Vec<&str> = scope.unwrap_or_default().split_whitespace().collect();
+  return SUPPORTED_SCOPES
+    .iter()
+    .filter(|s| requested.contains(s))
+    .copied()
+    .collect::<Vec<_>>()
+    .join(" ");
+}
+
+pub(crate) fn has_scope(scope: &str, name: &str) -> bool {
+  return scope.split_whitespace().any(|s| s == name);
+}
+
+#[cfg(test)]
+mod oidc_test;
@@ -0,0 +1,310 @@
+use axum::extract::{Form, Json, OriginalUri, Query, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, Uri, header};
+use axum::response::{IntoResponse, Redirect, Response};
+use base64::prelude::*;
+
+use crate::AppState;
+use crate::admin::user::create_user_for_test;
+use crate::app_state::test_state;
+use crate::auth::User;
+use crate::auth::oidc::authorize::{
+  AuthorizeQuery, ConsentRequest, authorize_handler, consent_handler,
+};
+use crate::auth::oidc::client::register_oauth_client;
+use crate::auth::oidc::token::{IdTokenClaims, OidcTokenRequest, OidcTokenResponse, token_handler};
+use crate::auth::oidc::userinfo::userinfo_handler;
+use crate::auth::oidc::{CONSENT_UI, LOGIN_UI};
+use crate::auth::util::derive_pkce_code_challenge;
+
+const REDIRECT_URI: &str = "https://app.test.org/callback";
+
+fn location(redirect: Redirect) -> url::Url {
+  let response = redirect.into_response();
+  let location = response
+    .headers()
+    .get(header::LOCATION)
+    .unwrap()
+    .to_str()
+    .unwrap();
+  return url::Url::parse("https://test.org")
+    .unwrap()
+    .join(location)
+    .unwrap();
+}
+
+fn query_param(url: &url::Url, name: &str) -> Option<String> {
+  return url
+    .query_pairs()
+    .find(|(key, _)| key == name)
+    .map(|(_, value)| value.to_string());
+}
+
+fn authorize_query(client_id: &str, code_challenge: &str) -> AuthorizeQuery {
+  return AuthorizeQuery {
+    response_type: Some("code".to_string()),
+    client_id: Some(client_id.to_string()),
+    redirect_uri: Some(REDIRECT_URI.to_string()),
+    scope: Some("openid email unknown".to_string()),
+    state: Some("client-state".to_string()),
+    nonce: Some("nonce".to_string()),
+    code_challenge: Some(code_challenge.to_string()),
+    code_challenge_method: Some("S256".to_string()),
+  };
+}
+
+async fn authorize_and_consent(
+  state: &AppState,
+  user: &User,
+  client_id: &str,
+  code_challenge: &str,
+) -> String {
+  let consent_redirect = location(
+    authorize_handler(
+      State(state.clone()),
+      OriginalUri(Uri::from_static("/api/auth/v1/oidc/authorize")),
+      Query(authorize_query(client_id, code_challenge)),
+      Some(user.clone()),
+    )
+    .await
+    .unwrap(),
+  );
+  assert_eq!(consent_redirect.path(), CONSENT_UI);
+  assert_eq!(
+    query_param(&consent_redirect, "scope").as_deref(),
+    Some("openid email")
+  );
+  assert_eq!(
+    query_param(&consent_redirect, "client_name").as_deref(),
+    Some("Test App")
+  );
+
+  let client_redirect = location(
+    consent_handler(
+      State(state.clone()),
+      user.clone(),
+      Form(ConsentRequest {
+        consent_token: query_param(&consent_redirect, "consent_token").unwrap(),
+        decision: "allow".to_string(),
+      }),
+    )
+    .await
+    .unwrap(),
+  );
+  assert!(client_redirect.as_str().starts_with(REDIRECT_URI));
+  assert_eq!(
+    query_param(&client_redirect, "state").as_deref(),
+    Some("client-state")
+  );
+  assert_eq!(
+    query_param(&client_redirect, "iss").as_deref(),
+    Some("https://test.org")
+  );
+
+  return query_param(&client_redirect, "code").unwrap();
+}
+
+async fn exchange(state: &AppState, headers: HeaderMap, request: OidcTokenRequest) -> Response {
+  return match token_handler(State(state.clone()), headers, Form(request)).await {
+    Ok(response) => response,
+    Err(err) => err.into_response(),
+  };
+}
+
+#[tokio::test]
+async fn test_oidc_authorization_code_flow() {
+  let state = test_state(None).await.unwrap();
+
+  let email = "user@test.org";
+  let user_id = create_user_for_test(&state, email, "Secret!1!!")
+    .await
+    .unwrap();
+  let user = User::from_unverified(user_id, email);
+
+  let (client_id, client_secret) = register_oauth_client(
+    state.user_conn(),
+    "Test App".to_string(),
+    vec![REDIRECT_URI.to_string()],
+    true,
+  )
+  .await
+  .unwrap();
+  let client_secret = client_secret.unwrap();
+
+  let code_verifier = "verifier_0123456789_0123456789_0123456789";
+  let code_challenge = derive_pkce_code_challenge(code_verifier);
+
+  // Unregistered redirect URIs must be rejected rather than redirected to.
+  assert!(
+    authorize_handler(
+      State(state.clone()),
+      OriginalUri(Uri::from_static("/api/auth/v1/oidc/authorize")),
+      Query(AuthorizeQuery {
+        redirect_uri: Some("https://evil.org/callback".to_string()),
+        ..authorize_query(&client_id, &code_challenge)
+      }),
+      Some(user.clone()),
+    )
+    .await
+    .is_err()
+  );
+
+  // Missing PKCE is reported back to the client.
+  let error_redirect = location(
+    authorize_handler(
+      State(state.clone()),
+      OriginalUri(Uri::from_static("/api/auth/v1/oidc/authorize")),
+      Query(AuthorizeQuery {
+        code_challenge: None,
+        ..authorize_query(&client_id, &code_challenge)
+      }),
+      Some(user.clone()),
+    )
+    .await
+    .unwrap(),
+  );
+  assert_eq!(
+    query_param(&error_redirect, "error").as_deref(),
+    Some("invalid_request")
+  );
+
+  // Users w/o a session are sent to the login screen first.
+  let login_redirect = location(
+    authorize_handler(
+      State(state.clone()),
+      OriginalUri(Uri::from_static(
+        "/api/auth/v1/oidc/authorize?client_id=foo",
+      )),
+      Query(authorize_query(&client_id, &code_challenge)),
+      None,
+    )
+    .await
+    .unwrap(),
+  );
+  assert_eq!(login_redirect.path(), LOGIN_UI);
+  assert_eq!(
+    query_param(&login_redirect, "redirect_uri").as_deref(),
+    Some("/api/auth/v1/oidc/authorize?client_id=foo")
+  );
+
+  let code = authorize_and_consent(&state, &user, &client_id, &code_challenge).await;
+
+  let token_request = |code: &str, code_verifier: &str| OidcTokenRequest {
+    grant_type: Some("authorization_code".to_string()),
+    code: Some(code.to_string()),
+    redirect_uri: Some(REDIRECT_URI.to_string()),
+    code_verifier: Some(code_verifier.to_string()),
+    client_id: Some(client_id.clone()),
+    client_secret: None,
+  };
+  let basic_auth = |secret: &str| {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+      header::AUTHORIZATION,
+      HeaderValue::from_str(&format!(
+        "Basic {}",
+        BASE64_STANDARD.encode(format!("{client_id}:{secret}"))
+      ))
+      .unwrap(),
+    );
+    return headers;
+  };
+
+  // Wrong client secret.
+  let response = exchange(
+    &state,
+    basic_auth("wrong"),
+    token_request(&code, code_verifier),
+  )
+  .await;
+  assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+  let response = exchange(
+    &state,
+    basic_auth(&client_secret),
+    token_request(&code, code_verifier),
+  )
+  .await;
+  assert_eq!(response.status(), StatusCode::OK);
+  let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+    .await
+    .unwrap();
+  let tokens: OidcTokenResponse = serde_json::from_slice(&body).unwrap();
+  assert_eq!(tokens.scope, "openid email");
+
+  // Validate the ID token like a client would, i.e. using the published JWKS.
+  let jwk = state.jwt().jwks().keys.remove(0);
+  let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::EdDSA);
+  validation.set_audience(&[&client_id]);
+  validation.set_issuer(&["https://test.org"]);
+  let id_token = jsonwebtoken::decode::<IdTokenClaims>(
+    tokens.id_token.as_ref().unwrap(),
+    &jsonwebtoken::DecodingKey::from_ed_components(&jwk.x).unwrap(),
+    &validation,
+  )
+  .unwrap()
+  .claims;
+  assert_eq!(id_token.sub, user.id);
+  assert_eq!(id_token.nonce.as_deref(), Some("nonce"));
+  assert_eq!(id_token.email.as_deref(), Some(email));
+
+  // Codes are single-use.
+  let response = exchange(
+    &state,
+    basic_auth(&client_secret),
+    token_request(&code, code_verifier),
+  )
+  .await;
+  assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+  // The access token grants access to the user info.
+  let mut headers = HeaderMap::new();
+  headers.insert(
+    header::AUTHORIZATION,
+    HeaderValue::from_str(&format!("Bearer {}", tokens.access_token)).unwrap(),
+  );
+  let Json(user_info) = userinfo_handler(State(state.clone()), headers)
+    .await
+    .unwrap();
+  assert_eq!(user_info.sub, user.id);
+  assert_eq!(user_info.email.as_deref(), Some(email));
+
+  // ...but is no auth token.
+  assert!(
+    crate::auth::jwt::AuthTokenClaims::from_auth_token(state.jwt(), &tokens.access_token).is_err()
+  );
+
+  // Wrong PKCE verifier.
+  let code = authorize_and_consent(&state, &user, &client_id, &code_challenge).await;
+  let response = exchange(
+    &state,
+    basic_auth(&client_secret),
+    token_request(&code, "wrong_verifier_0123456789_0123456789_0123"),
+  )
+  .await;
+  assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+  // Other users cannot complete someone else's consent.
+  let other_user = User::from_unverified(uuid::Uuid::now_v7(), "other@test.org");
+  let consent_redirect = location(
+    authorize_handler(
+      State(state.clone()),
+      OriginalUri(Uri::from_static("/api/auth/v1/oidc/authorize")),
+      Query(authorize_query(&client_id, &code_challenge)),
+      Some(user.clone()),
+    )
+    .await
+    .unwrap(),
+  );
+  assert!(
+    consent_handler(
+      State(state.clone()),
+      other_user,
+      Form(ConsentRequest {
+        consent_token: query_param(&consent_redirect, "consent_token").unwrap(),
+        decision: "allow".to_string(),
+      }),
+    )
+    .await
+    .is_err()
+  );
+}
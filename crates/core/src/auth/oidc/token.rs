@@ -0,0 +1,311 @@
+use axum::extract::{Form, Json, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use base64::prelude::*;
+use const_format::formatcp;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use trailbase_sqlite::params;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::auth::AuthError;
+use crate::auth::oidc::client::authenticate_oauth_client;
+use crate::auth::oidc::{SCOPE_EMAIL, SCOPE_OPENID, has_scope, issuer};
+use crate::auth::util::{derive_pkce_code_challenge, get_user_by_id};
+use crate::constants::AUTHORIZATION_CODE_TABLE;
+use crate::util::{get_header, id_to_b64};
+
+/// Access token issued to registered OAuth clients. It only grants access to `/oidc/userinfo`.
+///
+/// NOTE: The claims deliberately lack TrailBase's token `type` and the CSRF token, such that they
+/// cannot be mistaken for any other token, e.g. an auth token. They also lack an `aud` claim,
+/// since TrailBase itself is the only intended audience.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct OidcAccessTokenClaims {
+  pub iss: String,
+  /// Url-safe Base64 encoded id of the user.
+  pub sub: String,
+  pub client_id: String,
+  pub iat: i64,
+  pub exp: i64,
+  pub scope: String,
+}
+
+/// OpenID Connect ID token, see OpenID Connect Core 1.0 Section 2.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct IdTokenClaims {
+  pub iss: String,
+  /// Url-safe Base64 encoded id of the user.
+  pub sub: String,
+  pub aud: String,
+  pub iat: i64,
+  pub exp: i64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub nonce: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub email: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub email_verified: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub(crate) struct OidcTokenRequest {
+  /// Must be "authorization_code".
+  pub grant_type: Option<String>,
+  pub code: Option<String>,
+  pub redirect_uri: Option<String>,
+  pub code_verifier: Option<String>,
+  /// Alternatively to HTTP Basic auth.
+  pub client_id: Option<String>,
+  /// Alternatively to HTTP Basic auth. Only for confidential clients.
+  pub client_secret: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OidcTokenResponse {
+  pub access_token: String,
+  /// Always "Bearer".
+  pub token_type: String,
+  /// Seconds until the access token expires.
+  pub expires_in: i64,
+  /// Only present for the "openid" scope.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub id_token: Option<String>,
+  pub scope: String,
+}
+
+/// Token endpoint errors as defined by RFC 6749 Section 5.2.
+#[derive(Debug, Error)]
+pub(crate) enum TokenError {
+  #[error("invalid_request: {0}")]
+  InvalidRequest(&'static str),
+  #[error("invalid_client")]
+  InvalidClient,
+  #[error("invalid_grant")]
+  InvalidGrant,
+  #[error("unsupported_grant_type")]
+  UnsupportedGrantType,
+  #[error("Auth: {0}")]
+  Auth(#[from] AuthError),
+}
+
+impl From<trailbase_sqlite::Error> for TokenError {
+  fn from(err: trailbase_sqlite::Error) -> Self {
+    return Self::Auth(err.into());
+  }
+}
+
+impl IntoResponse for TokenError {
+  fn into_response(self) -> Response {
+    #[derive(Serialize)]
+    struct ErrorResponse {
+      error: &'static str,
+      #[serde(skip_serializing_if = "Option::is_none")]
+      error_description: Option<&'static str>,
+    }
+
+    let (status, error, error_description) = match self {
+      Self::InvalidRequest(description) => (
+        StatusCode::BAD_REQUEST,
+        "invalid_request",
+        Some(description),
+      ),
+      Self::InvalidClient => (StatusCode::UNAUTHORIZED, "invalid_client", None),
+      Self::InvalidGrant => (StatusCode::BAD_REQUEST, "invalid_grant", None),
+      Self::UnsupportedGrantType => (StatusCode::BAD_REQUEST, "unsupported_grant_type", None),
+      Self::Auth(err) => {
+        return err.into_response();
+      }
+    };
+
+    return (
+      status,
+      [(header::CACHE_CONTROL, "no-store")],
+      Json(ErrorResponse {
+        error,
+        error_description,
+      }),
+    )
+      .into_response();
+  }
+}
+
+/// Exchange an authorization code issued to a registered OAuth client for an access and ID token.
+#[utoipa::path(
+  post,
+  path = "/token",
+  tag = "oidc",
+  request_body(content = OidcTokenRequest, content_type = "application/x-www-form-urlencoded"),
+  responses(
+    (status = 200, description = "Access and ID token.", body = OidcTokenResponse),
+    (status = 400, description = "Invalid request or grant."),
+    (status = 401, description = "Invalid client credentials."),
+  )
+)]
+pub(crate) async fn token_handler(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+  Form(request): Form<OidcTokenRequest>,
+) -> Result<Response, TokenError> {
+  let issuer = issuer(&state)?;
+
+  let (client_id, client_secret) = client_credentials(&headers, &request)?;
+  let client = match authenticate_oauth_client(
+    state.user_conn(),
+    &client_id,
+    client_secret.as_deref(),
+  )
+  .await
+  {
+    Ok(client) => client,
+    Err(AuthError::Unauthorized) => {
+      return Err(TokenError::InvalidClient);
+    }
+    Err(err) => {
+      return Err(err.into());
+    }
+  };
+
+  if request.grant_type.as_deref() != Some("authorization_code") {
+    return Err(TokenError::UnsupportedGrantType);
+  }
+  let (Some(code), Some(redirect_uri), Some(code_verifier)) =
+    (request.code, request.redirect_uri, request.code_verifier)
+  else {
+    return Err(TokenError::InvalidRequest(
+      "missing code, redirect_uri or code_verifier",
+    ));
+  };
+
+  // Codes can only be used once, thus they're consumed regardless of whether the remaining
+  // checks succeed.
+  const QUERY: &str = formatcp!(
+    "\
+      DELETE FROM '{AUTHORIZATION_CODE_TABLE}' \
+      WHERE authorization_code = $1 AND client_id = $2 AND expires > UNIXEPOCH() \
+      RETURNING user, pkce_code_challenge, redirect_uri, scope, nonce \
+    "
+  );
+
+  let Some(row) = state
+    .session_conn()
+    .write_query_row(QUERY, params!(code, client.id.clone()))
+    .await?
+  else {
+    return Err(TokenError::InvalidGrant);
+  };
+
+  let internal = |err: trailbase_sqlite::from_sql::FromSqlError| {
+    return TokenError::Auth(AuthError::Internal(err.into()));
+  };
+  let user_id: [u8; 16] = row.get(0).map_err(internal)?;
+  let pkce_code_challenge: String = row.get(1).map_err(internal)?;
+  let expected_redirect_uri: Option<String> = row.get(2).map_err(internal)?;
+  let scope: String = row
+    .get::<Option<String>>(3)
+    .map_err(internal)?
+    .unwrap_or_default();
+  let nonce: Option<String> = row.get(4).map_err(internal)?;
+
+  if expected_redirect_uri.as_deref() != Some(redirect_uri.as_str())
+    || pkce_code_challenge != derive_pkce_code_challenge(&code_verifier)
+  {
+    return Err(TokenError::InvalidGrant);
+  }
+
+  let db_user = match get_user_by_id(state.user_conn(), &Uuid::from_bytes(user_id)).await {
+    Ok(db_user) if db_user.verified => db_user,
+    Ok(_) | Err(AuthError::NotFound) => {
+      return Err(TokenError::InvalidGrant);
+    }
+    Err(err) => {
+      return Err(err.into());
+    }
+  };
+
+  let (auth_token_ttl, _refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
+  let now = chrono::Utc::now();
+  let sub = id_to_b64(&db_user.id);
+
+  let access_token = state
+    .jwt()
+    .encode(&OidcAccessTokenClaims {
+      iss: issuer.clone(),
+      sub: sub.clone(),
+      client_id: client.id.clone(),
+      iat: now.timestamp(),
+      exp: (now + auth_token_ttl).timestamp(),
+      scope: scope.clone(),
+    })
+    .map_err(|err| AuthError::Internal(err.into()))?;
+
+  let id_token = if has_scope(&scope, SCOPE_OPENID) {
+    let with_email = has_scope(&scope, SCOPE_EMAIL);
+    Some(
+      state
+        .jwt()
+        .encode(&IdTokenClaims {
+          iss: issuer,
+          sub,
+          aud: client.id,
+          iat: now.timestamp(),
+          exp: (now + auth_token_ttl).timestamp(),
+          nonce,
+          email: with_email.then(|| db_user.email.clone()),
+          email_verified: with_email.then_some(db_user.verified),
+        })
+        .map_err(|err| AuthError::Internal(err.into()))?,
+    )
+  } else {
+    None
+  };
+
+  return Ok(
+    (
+      [(header::CACHE_CONTROL, "no-store")],
+      Json(OidcTokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: auth_token_ttl.num_seconds(),
+        id_token,
+        scope,
+      }),
+    )
+      .into_response(),
+  );
+}
+
+/// Extracts the client's credentials either from the HTTP Basic auth header or the request body,
+/// see RFC 6749 Section 2.3.1.
+fn client_credentials(
+  headers: &HeaderMap,
+  request: &OidcTokenRequest,
+) -> Result<(String, Option<String>), TokenError> {
+  if let Some(basic) =
+    get_header(headers, header::AUTHORIZATION).and_then(|v| v.strip_prefix("Basic "))
+  {
+    let decoded = BASE64_STANDARD
+      .decode(basic)
+      .ok()
+      .and_then(|bytes| String::from_utf8(bytes).ok())
+      .ok_or(TokenError::InvalidClient)?;
+    let Some((id, secret)) = decoded.split_once(':') else {
+      return Err(TokenError::InvalidClient);
+    };
+
+    // NOTE: Credentials are form-urlencoded prior to being Base64 encoded.
+    let decode = |s: &str| -> String {
+      return form_urlencoded::parse(s.as_bytes())
+        .map(|(key, _)| key)
+        .collect();
+    };
+    return Ok((decode(id), Some(decode(secret)).filter(|s| !s.is_empty())));
+  }
+
+  let Some(ref client_id) = request.client_id else {
+    return Err(TokenError::InvalidClient);
+  };
+  return Ok((client_id.clone(), request.client_secret.clone()));
+}
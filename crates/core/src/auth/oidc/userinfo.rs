@@ -0,0 +1,69 @@
+use axum::extract::{Json, State};
+use axum::http::{HeaderMap, header};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::AppState;
+use crate::auth::AuthError;
+use crate::auth::oidc::token::OidcAccessTokenClaims;
+use crate::auth::oidc::{SCOPE_EMAIL, has_scope};
+use crate::auth::util::get_user_by_id;
+use crate::util::{b64_to_uuid, get_header};
+
+/// Claims about the signed-in user, see OpenID Connect Core 1.0 Section 5.3.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserInfo {
+  /// Url-safe Base64 encoded id of the user.
+  pub sub: String,
+  /// Only present for the "email" scope.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub email: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub email_verified: Option<bool>,
+}
+
+/// Get claims about the user an access token was issued for to a registered OAuth client.
+#[utoipa::path(
+  get,
+  path = "/userinfo",
+  tag = "oidc",
+  responses(
+    (status = 200, description = "User info.", body = UserInfo),
+    (status = 401, description = "Missing or invalid access token."),
+  )
+)]
+pub(crate) async fn userinfo_handler(
+  State(state): State<AppState>,
+  headers: HeaderMap,
+) -> Result<Json<UserInfo>, AuthError> {
+  let Some(access_token) =
+    get_header(&headers, header::AUTHORIZATION).and_then(|v| v.strip_prefix("Bearer "))
+  else {
+    return Err(AuthError::Unauthorized);
+  };
+
+  let claims = state
+    .jwt()
+    .decode::<OidcAccessTokenClaims>(access_token)
+    .map_err(|_err| AuthError::Unauthorized)?;
+  let user_id = b64_to_uuid(&claims.sub).map_err(|_err| AuthError::Unauthorized)?;
+
+  // Look up the user to reflect changes, e.g. a changed e-mail address, or deletion since the
+  // access token was issued.
+  let db_user = match get_user_by_id(state.user_conn(), &user_id).await {
+    Ok(db_user) => db_user,
+    Err(AuthError::NotFound) => {
+      return Err(AuthError::Unauthorized);
+    }
+    Err(err) => {
+      return Err(err);
+    }
+  };
+
+  let with_email = has_scope(&claims.scope, SCOPE_EMAIL);
+  return Ok(Json(UserInfo {
+    sub: claims.sub,
+    email: with_email.then(|| db_user.email.clone()),
+    email_verified: with_email.then_some(db_user.verified),
+  }));
+}
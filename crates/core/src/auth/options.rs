@@ -6,12 +6,15 @@ use crate::auth::oauth::providers::{
   OAuthProviderError, OAuthProviderType, oauth_providers_static_registry,
 };
 use crate::auth::password::PasswordOptions;
-use crate::config::proto::AuthConfig;
+use crate::auth::saml::SamlProvider;
+use crate::config::proto::{AuthConfig, OAuthProviderId};
 
 #[derive(Default)]
 pub struct AuthOptions {
   password_options: PasswordOptions,
   oauth_providers: IndexMap<String, OAuthProviderType>,
+  saml_providers: IndexMap<String, SamlProvider>,
+  custom_claims_query: Option<String>,
 }
 
 #[derive(Default)]
@@ -22,6 +25,9 @@ pub struct OAuthProvider {
 
 impl AuthOptions {
   pub fn from_config(config: AuthConfig) -> Self {
+    let custom_claims_query = config.custom_claims_query.clone();
+    let saml_providers = build_saml_providers_from_config(&config);
+
     return Self {
       password_options: PasswordOptions {
         min_length: config.password_minimal_length.unwrap_or(8) as usize,
@@ -38,6 +44,8 @@ impl AuthOptions {
         error!("Failed to derive configured OAuth providers from config: {err}");
         return Default::default();
       }),
+      saml_providers,
+      custom_claims_query,
     };
   }
 
@@ -52,6 +60,18 @@ impl AuthOptions {
     return None;
   }
 
+  pub fn lookup_oauth_provider_by_id(&self, id: OAuthProviderId) -> Option<&OAuthProviderType> {
+    return self.oauth_providers.values().find(|p| p.provider() == id);
+  }
+
+  pub fn lookup_saml_provider(&self, name: &str) -> Option<&SamlProvider> {
+    return self.saml_providers.get(name);
+  }
+
+  pub fn custom_claims_query(&self) -> Option<&str> {
+    return self.custom_claims_query.as_deref();
+  }
+
   /// Returns list of tuples with (name, display_name);
   pub fn list_oauth_providers(&self) -> Vec<OAuthProvider> {
     return self
@@ -63,6 +83,18 @@ impl AuthOptions {
       })
       .collect();
   }
+
+  /// Returns list of configured SAML identity providers.
+  pub fn list_saml_providers(&self) -> Vec<OAuthProvider> {
+    return self
+      .saml_providers
+      .values()
+      .map(|p| OAuthProvider {
+        name: p.name().to_string(),
+        display_name: p.display_name().to_string(),
+      })
+      .collect();
+  }
 }
 
 fn build_oauth_providers_from_config(
@@ -94,3 +126,21 @@ fn build_oauth_providers_from_config(
       .map(|p| (p.name().to_string(), p)),
   ));
 }
+
+fn build_saml_providers_from_config(config: &AuthConfig) -> IndexMap<String, SamlProvider> {
+  // Unlike OAuth providers, a single misconfigured identity provider doesn't disable the others.
+  return config
+    .saml_providers
+    .iter()
+    .sorted_by(|a, b| Ord::cmp(a.0, b.0))
+    .filter_map(
+      |(name, config)| match SamlProvider::from_config(name, config) {
+        Ok(provider) => Some((name.clone(), provider)),
+        Err(err) => {
+          error!("Failed to build SAML provider '{name}': {err}");
+          None
+        }
+      },
+    )
+    .collect();
+}
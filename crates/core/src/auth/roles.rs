@@ -0,0 +1,210 @@
+//! Roles, i.e. named groups of users.
+//!
+//! A user's roles are carried in their auth token claims and exposed to record API ACLs as well
+//! as access rules via `_USER_.roles`, a JSON array of role names.
+use const_format::formatcp;
+use serde::{Deserialize, Serialize};
+use trailbase_sqlite::{Connection, params};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::auth::AuthError;
+use crate::constants::{ROLE_TABLE, USER_ROLE_TABLE};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct Role {
+  pub name: String,
+  pub description: Option<String>,
+  pub created: i64,
+}
+
+pub async fn create_role(
+  user_conn: &Connection,
+  name: &str,
+  description: Option<String>,
+) -> Result<(), AuthError> {
+  validate_role_name(name)?;
+
+  const QUERY: &str = formatcp!(
+    "INSERT INTO '{ROLE_TABLE}' (name, description) VALUES ($1, $2) ON CONFLICT DO NOTHING"
+  );
+
+  let rows_affected = user_conn
+    .execute(QUERY, params!(name.to_string(), description))
+    .await?;
+  if rows_affected == 0 {
+    return Err(AuthError::Conflict);
+  }
+  return Ok(());
+}
+
+/// Deletes the given role, which also removes it from all users.
+///
+/// NOTE: Already minted auth tokens will keep carrying the role until they expire.
+pub async fn delete_role(user_conn: &Connection, name: &str) -> Result<(), AuthError> {
+  const QUERY: &str = formatcp!("DELETE FROM '{ROLE_TABLE}' WHERE name = $1");
+
+  let rows_affected = user_conn.execute(QUERY, params!(name.to_string())).await?;
+  if rows_affected == 0 {
+    return Err(AuthError::NotFound);
+  }
+  return Ok(());
+}
+
+/// Lists roles, optionally only the ones assigned to the given user.
+pub async fn list_roles(
+  user_conn: &Connection,
+  user_id: Option<&Uuid>,
+) -> Result<Vec<Role>, AuthError> {
+  const QUERY: &str = formatcp!(
+    "\
+      SELECT name, description, created \
+      FROM '{ROLE_TABLE}' \
+      WHERE $1 IS NULL OR name IN (SELECT role FROM '{USER_ROLE_TABLE}' WHERE user = $1) \
+      ORDER BY name \
+    "
+  );
+
+  return Ok(
+    user_conn
+      .read_query_values::<Role>(QUERY, params!(user_id.map(|id| id.into_bytes().to_vec())))
+      .await?,
+  );
+}
+
+/// Assigns an existing role to the given user. Assigning a role twice is a no-op.
+pub async fn assign_role(
+  user_conn: &Connection,
+  user_id: &Uuid,
+  role: &str,
+) -> Result<(), AuthError> {
+  const EXISTS_QUERY: &str =
+    formatcp!("SELECT EXISTS(SELECT 1 FROM '{ROLE_TABLE}' WHERE name = $1)");
+
+  let exists = user_conn
+    .read_query_row_get::<bool>(EXISTS_QUERY, params!(role.to_string()), 0)
+    .await?
+    .unwrap_or(false);
+  if !exists {
+    return Err(AuthError::NotFound);
+  }
+
+  const QUERY: &str = formatcp!(
+    "INSERT INTO '{USER_ROLE_TABLE}' (user, role) VALUES ($1, $2) ON CONFLICT DO NOTHING"
+  );
+
+  user_conn
+    .execute(QUERY, params!(user_id.into_bytes(), role.to_string()))
+    .await?;
+  return Ok(());
+}
+
+pub async fn unassign_role(
+  user_conn: &Connection,
+  user_id: &Uuid,
+  role: &str,
+) -> Result<(), AuthError> {
+  const QUERY: &str = formatcp!("DELETE FROM '{USER_ROLE_TABLE}' WHERE user = $1 AND role = $2");
+
+  let rows_affected = user_conn
+    .execute(QUERY, params!(user_id.into_bytes(), role.to_string()))
+    .await?;
+  if rows_affected == 0 {
+    return Err(AuthError::NotFound);
+  }
+  return Ok(());
+}
+
+/// Names of the roles assigned to the given user, e.g. to be included in auth token claims.
+pub(crate) async fn get_user_roles(
+  user_conn: &Connection,
+  user_id: &[u8; 16],
+) -> Result<Vec<String>, AuthError> {
+  const QUERY: &str =
+    formatcp!("SELECT role FROM '{USER_ROLE_TABLE}' WHERE user = $1 ORDER BY role");
+
+  let rows = user_conn.read_query_rows(QUERY, params!(*user_id)).await?;
+
+  return rows
+    .iter()
+    .map(|row| {
+      row
+        .get::<String>(0)
+        .map_err(|err| AuthError::Internal(err.into()))
+    })
+    .collect();
+}
+
+fn validate_role_name(name: &str) -> Result<(), AuthError> {
+  if name.is_empty()
+    || !name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+  {
+    return Err(AuthError::BadRequest("invalid role name"));
+  }
+  return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::admin::user::create_user_for_test;
+  use crate::app_state::test_state;
+
+  #[tokio::test]
+  async fn test_role_lifecycle() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.user_conn();
+
+    let user_id = create_user_for_test(&state, "editor@test.org", "secret123")
+      .await
+      .unwrap();
+
+    assert!(matches!(
+      create_role(conn, "not a name", None).await,
+      Err(AuthError::BadRequest(_))
+    ));
+    create_role(conn, "editor", Some("Edits things".to_string()))
+      .await
+      .unwrap();
+    create_role(conn, "viewer", None).await.unwrap();
+    assert!(matches!(
+      create_role(conn, "editor", None).await,
+      Err(AuthError::Conflict)
+    ));
+
+    assert!(matches!(
+      assign_role(conn, &user_id, "unknown").await,
+      Err(AuthError::NotFound)
+    ));
+    assign_role(conn, &user_id, "editor").await.unwrap();
+    // Idempotent.
+    assign_role(conn, &user_id, "editor").await.unwrap();
+
+    assert_eq!(
+      get_user_roles(conn, user_id.as_bytes()).await.unwrap(),
+      vec!["editor".to_string()]
+    );
+    assert_eq!(list_roles(conn, None).await.unwrap().len(), 2);
+    let user_roles = list_roles(conn, Some(&user_id)).await.unwrap();
+    assert_eq!(user_roles.len(), 1);
+    assert_eq!(user_roles[0].name, "editor");
+
+    // Deleting a role removes it from users.
+    delete_role(conn, "editor").await.unwrap();
+    assert!(
+      get_user_roles(conn, user_id.as_bytes())
+        .await
+        .unwrap()
+        .is_empty()
+    );
+
+    assert!(matches!(
+      unassign_role(conn, &user_id, "editor").await,
+      Err(AuthError::NotFound)
+    ));
+  }
+}
@@ -0,0 +1,109 @@
+use axum::extract::{Form, Path, State};
+use axum::response::Response;
+use chrono::Utc;
+use log::*;
+use serde::Deserialize;
+use tower_cookies::Cookies;
+use utoipa::ToSchema;
+
+use crate::AppState;
+use crate::auth::AuthError;
+use crate::auth::oauth::{OAuthStateClaims, OAuthUser, sign_in_external_user};
+use crate::auth::saml::SamlError;
+use crate::auth::saml::response::{Expectations, validate_response};
+use crate::auth::saml::signature::decode_base64;
+use crate::auth::session::SessionMetadata;
+use crate::auth::util::remove_cookie;
+use crate::config::proto::OAuthProviderId;
+use crate::constants::COOKIE_OAUTH_STATE;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AcsRequest {
+  /// Base64-encoded SAML response.
+  #[serde(rename = "SAMLResponse")]
+  pub saml_response: String,
+  /// Opaque state round-tripped by the identity provider. Unused, since the state is kept in a
+  /// cookie.
+  #[serde(rename = "RelayState")]
+  pub relay_state: Option<String>,
+}
+
+/// Assertion consumer service receiving the identity provider's response via the HTTP-POST
+/// binding. Validates the response, creates a new local user if needed, and finally mints our
+/// own tokens.
+#[utoipa::path(
+  post,
+  path = "/{provider}/acs",
+  tag = "saml",
+  request_body(content = AcsRequest, content_type = "application/x-www-form-urlencoded"),
+  responses(
+    (status = 200, description = "Redirect.")
+  )
+)]
+pub(crate) async fn assertion_consumer_service_handler(
+  State(state): State<AppState>,
+  Path(provider): Path<String>,
+  session: SessionMetadata,
+  cookies: Cookies,
+  Form(request): Form<AcsRequest>,
+) -> Result<Response, AuthError> {
+  let auth_options = state.auth_options();
+  let Some(provider) = auth_options.lookup_saml_provider(&provider) else {
+    return Err(AuthError::OAuthProviderNotFound);
+  };
+  let Some(site_url) = state.site_url().as_ref().clone() else {
+    return Err(AuthError::Internal("SAML requires a site URL".into()));
+  };
+
+  // Get round-tripped state from cookies, set by prior call to saml::login.
+  let oauth_state = state
+    .jwt()
+    .decode::<OAuthStateClaims>(
+      cookies
+        .get(COOKIE_OAUTH_STATE)
+        .ok_or_else(|| AuthError::BadRequest("missing state"))?
+        .value(),
+    )
+    .map_err(|_err| {
+      remove_cookie(&cookies, COOKIE_OAUTH_STATE);
+      return AuthError::BadRequest("invalid state");
+    })?;
+
+  let identity = decode_base64(&request.saml_response)
+    .and_then(|xml| {
+      let xml = String::from_utf8(xml).map_err(|err| SamlError::Xml(err.to_string()))?;
+
+      return validate_response(
+        &xml,
+        provider,
+        &Expectations {
+          sp_entity_id: &provider.sp_entity_id(&site_url),
+          acs_url: &provider.acs_url(&site_url),
+          request_id: &oauth_state.csrf_secret,
+          now: Utc::now(),
+        },
+      );
+    })
+    .map_err(|err| {
+      debug!("Rejected SAML response from '{}': {err}", provider.name());
+      remove_cookie(&cookies, COOKIE_OAUTH_STATE);
+      return AuthError::BadRequest("invalid SAML response");
+    })?;
+
+  let Some(email) = identity.email else {
+    remove_cookie(&cookies, COOKIE_OAUTH_STATE);
+    return Err(AuthError::BadRequest("SAML response w/o email"));
+  };
+
+  let oauth_user = OAuthUser {
+    // NameIDs are only unique per identity provider.
+    provider_user_id: format!("{}#{}", provider.idp_entity_id, identity.name_id),
+    provider_id: OAuthProviderId::Saml,
+    email,
+    // Identity providers are the authority over their users' addresses.
+    verified: true,
+    avatar: None,
+  };
+
+  return sign_in_external_user(&state, &session, &cookies, oauth_state, oauth_user).await;
+}
@@ -0,0 +1,133 @@
+use axum::{
+  extract::{Path, Query, State},
+  response::Redirect,
+};
+use base64::prelude::*;
+use chrono::{Duration, SecondsFormat, Utc};
+use flate2::{Compression, write::DeflateEncoder};
+use std::io::Write;
+use tower_cookies::{
+  Cookie, Cookies,
+  cookie::{self, SameSite},
+};
+use url::Url;
+
+use crate::AppState;
+use crate::auth::AuthError;
+use crate::auth::login_params::{LoginInputParams, LoginParams, build_and_validate_input_params};
+use crate::auth::oauth::{OAuthStateClaims, ResponseType};
+use crate::auth::saml::xml::escape;
+use crate::auth::saml::{HTTP_POST_BINDING, NS_ASSERTION, NS_PROTOCOL, SamlProvider};
+use crate::auth::util::secure_tls_only;
+use crate::constants::COOKIE_OAUTH_STATE;
+use crate::rand::random_alphanumeric;
+
+/// Log in via SAML identity provider.
+#[utoipa::path(
+  get,
+  path = "/{provider}/login",
+  tag = "saml",
+  params(LoginInputParams),
+  responses(
+    (status = 200, description = "Redirect.")
+  )
+)]
+pub(crate) async fn login_with_saml_provider(
+  State(state): State<AppState>,
+  Path(provider): Path<String>,
+  Query(login_input_query): Query<LoginInputParams>,
+  cookies: Cookies,
+) -> Result<Redirect, AuthError> {
+  let auth_options = state.auth_options();
+  let Some(provider) = auth_options.lookup_saml_provider(&provider) else {
+    return Err(AuthError::OAuthProviderNotFound);
+  };
+  let Some(site_url) = state.site_url().as_ref().clone() else {
+    return Err(AuthError::Internal("SAML requires a site URL".into()));
+  };
+  let login_params = build_and_validate_input_params(&state, login_input_query)?;
+
+  // IDs must not start with a digit, see XML Schema's NCName.
+  let request_id = format!("_{}", random_alphanumeric(32));
+  let redirect_url = authn_request_url(provider, &site_url, &request_id)?;
+
+  // The authentication request's ID takes the place of OAuth's CSRF secret. Responses need to be
+  // in response to it, thus binding them to the browser that initiated the login.
+  let (redirect_uri, response_type, user_pkce_code_challenge) = match login_params {
+    LoginParams::Password { redirect_uri } => (redirect_uri, None, None),
+    LoginParams::AuthorizationCodeFlowWithPkce {
+      redirect_uri,
+      pkce_code_challenge,
+    } => (
+      Some(redirect_uri),
+      Some(ResponseType::Code),
+      Some(pkce_code_challenge),
+    ),
+  };
+  let oauth_state = OAuthStateClaims {
+    exp: (Utc::now() + Duration::seconds(5 * 60)).timestamp(),
+    csrf_secret: request_id,
+    pkce_code_verifier: String::new(),
+    user_pkce_code_challenge,
+    response_type,
+    redirect_uri,
+    link_user: None,
+  };
+
+  let tls_only = secure_tls_only(&state);
+  cookies.add(
+    Cookie::build((
+      COOKIE_OAUTH_STATE,
+      state
+        .jwt()
+        .encode(&oauth_state)
+        .map_err(|err| AuthError::Internal(err.into()))?,
+    ))
+    .path("/")
+    .http_only(true)
+    .secure(tls_only)
+    // NOTE: Unlike OAuth's redirect, the identity provider's response is a cross-site POST.
+    // Browsers only include cookies with such requests for `SameSite=None`, which in turn
+    // requires TLS.
+    .same_site(if tls_only {
+      SameSite::None
+    } else {
+      SameSite::Lax
+    })
+    .max_age(cookie::time::Duration::minutes(5))
+    .build(),
+  );
+
+  return Ok(Redirect::to(redirect_url.as_str()));
+}
+
+/// Builds the URL sending users to the identity provider with an authentication request using
+/// the HTTP-Redirect binding, see SAML Bindings 2.0 Section 3.4.
+fn authn_request_url(
+  provider: &SamlProvider,
+  site_url: &Url,
+  request_id: &str,
+) -> Result<Url, AuthError> {
+  let request = format!(
+    r#"<samlp:AuthnRequest xmlns:samlp="{NS_PROTOCOL}" xmlns:saml="{NS_ASSERTION}" ID="{id}" Version="2.0" IssueInstant="{now}" Destination="{destination}" ProtocolBinding="{HTTP_POST_BINDING}" AssertionConsumerServiceURL="{acs_url}"><saml:Issuer>{issuer}</saml:Issuer></samlp:AuthnRequest>"#,
+    id = escape(request_id),
+    now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+    destination = escape(provider.idp_sso_url.as_str()),
+    acs_url = escape(&provider.acs_url(site_url)),
+    issuer = escape(&provider.sp_entity_id(site_url)),
+  );
+
+  let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+  encoder
+    .write_all(request.as_bytes())
+    .map_err(|err| AuthError::Internal(err.into()))?;
+  let deflated = encoder
+    .finish()
+    .map_err(|err| AuthError::Internal(err.into()))?;
+
+  let mut url = provider.idp_sso_url.clone();
+  url
+    .query_pairs_mut()
+    .append_pair("SAMLRequest", &BASE64_STANDARD.encode(deflated));
+  return Ok(url);
+}
@@ -0,0 +1,38 @@
+use axum::extract::{Path, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+
+use crate::AppState;
+use crate::auth::AuthError;
+use crate::auth::saml::xml::escape;
+use crate::auth::saml::{HTTP_POST_BINDING, NS_METADATA, NS_PROTOCOL};
+
+/// Service provider metadata to be registered with the identity provider.
+#[utoipa::path(
+  get,
+  path = "/{provider}/metadata",
+  tag = "saml",
+  responses(
+    (status = 200, description = "SAML 2.0 service provider metadata.", content_type = "application/samlmetadata+xml")
+  )
+)]
+pub(crate) async fn service_provider_metadata_handler(
+  State(state): State<AppState>,
+  Path(provider): Path<String>,
+) -> Result<Response, AuthError> {
+  let auth_options = state.auth_options();
+  let Some(provider) = auth_options.lookup_saml_provider(&provider) else {
+    return Err(AuthError::OAuthProviderNotFound);
+  };
+  let Some(site_url) = state.site_url().as_ref().clone() else {
+    return Err(AuthError::Internal("SAML requires a site URL".into()));
+  };
+
+  let metadata = format!(
+    r#"<?xml version="1.0" encoding="UTF-8"?><md:EntityDescriptor xmlns:md="{NS_METADATA}" entityID="{entity_id}"><md:SPSSODescriptor AuthnRequestsSigned="false" WantAssertionsSigned="true" protocolSupportEnumeration="{NS_PROTOCOL}"><md:AssertionConsumerService Binding="{HTTP_POST_BINDING}" Location="{acs_url}" index="0" isDefault="true"/></md:SPSSODescriptor></md:EntityDescriptor>"#,
+    entity_id = escape(&provider.sp_entity_id(&site_url)),
+    acs_url = escape(&provider.acs_url(&site_url)),
+  );
+
+  return Ok(([(CONTENT_TYPE, "application/samlmetadata+xml")], metadata).into_response());
+}
@@ -0,0 +1,71 @@
+//! TrailBase acting as SAML 2.0 service provider, letting users sign in via their organization's
+//! identity provider (IdP), e.g. Okta or Microsoft Entra ID.
+//!
+//! Only the SP-initiated Web Browser SSO profile is supported:
+//!
+//!  1. `/{provider}/login` sends users to the IdP with an authentication request using the
+//!     HTTP-Redirect binding.
+//!  2. The IdP posts a signed response back to the assertion consumer service `/{provider}/acs`
+//!     using the HTTP-POST binding.
+//!  3. The asserted identity is mapped onto an [OAuthUser] and signed in exactly like an external
+//!     OAuth identity.
+//!
+//! [OAuthUser]: crate::auth::oauth::OAuthUser
+use axum::Router;
+use axum::routing::{get, post};
+use thiserror::Error;
+use utoipa::OpenApi;
+
+mod acs;
+mod login;
+mod metadata;
+mod provider;
+mod response;
+mod signature;
+mod xml;
+
+#[cfg(test)]
+mod saml_test;
+
+pub(crate) use provider::SamlProvider;
+
+use crate::AppState;
+
+pub(crate) const NS_PROTOCOL: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+pub(crate) const NS_ASSERTION: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+pub(crate) const NS_METADATA: &str = "urn:oasis:names:tc:SAML:2.0:metadata";
+
+pub(crate) const HTTP_POST_BINDING: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+
+#[derive(Debug, Error)]
+pub enum SamlError {
+  #[error("Config: {0}")]
+  Config(String),
+  #[error("XML: {0}")]
+  Xml(String),
+  #[error("Signature: {0}")]
+  Signature(&'static str),
+  #[error("Assertion: {0}")]
+  Assertion(&'static str),
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(
+  login::login_with_saml_provider,
+  acs::assertion_consumer_service_handler,
+  metadata::service_provider_metadata_handler,
+))]
+pub(super) struct SamlApi;
+
+pub(super) fn saml_router() -> Router<AppState> {
+  return Router::new()
+    .route("/{provider}/login", get(login::login_with_saml_provider))
+    .route(
+      "/{provider}/acs",
+      post(acs::assertion_consumer_service_handler),
+    )
+    .route(
+      "/{provider}/metadata",
+      get(metadata::service_provider_metadata_handler),
+    );
+}
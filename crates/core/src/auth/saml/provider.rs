@@ -0,0 +1,167 @@
+use base64::prelude::*;
+use jsonwebtoken::DecodingKey;
+use url::Url;
+
+use crate::auth::saml::signature::{NS_XMLDSIG, decode_base64};
+use crate::auth::saml::xml::{Element, parse};
+use crate::auth::saml::{NS_METADATA, SamlError};
+use crate::config::proto::SamlProviderConfig;
+use crate::constants::AUTH_API_PATH;
+
+const HTTP_REDIRECT_BINDING: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect";
+
+/// SAML 2.0 identity provider configured via `AuthConfig.saml_providers`.
+pub struct SamlProvider {
+  pub(crate) name: String,
+  pub(crate) display_name: String,
+
+  pub(crate) idp_entity_id: String,
+  /// Single sign-on endpoint accepting authentication requests via the HTTP-Redirect binding.
+  pub(crate) idp_sso_url: Url,
+  /// Public key of the identity provider's signing certificate.
+  pub(crate) idp_key: DecodingKey,
+
+  sp_entity_id: Option<String>,
+}
+
+impl SamlProvider {
+  pub fn from_config(name: &str, config: &SamlProviderConfig) -> Result<Self, SamlError> {
+    let Some(ref metadata) = config.idp_metadata else {
+      return Err(SamlError::Config("missing IdP metadata".into()));
+    };
+    let metadata = parse_idp_metadata(metadata)?;
+
+    let certificate = match config.idp_certificate {
+      Some(ref pem) => pem.clone(),
+      None => metadata
+        .certificate
+        .ok_or_else(|| SamlError::Config("missing IdP signing certificate".into()))?,
+    };
+
+    return Ok(Self {
+      name: name.to_string(),
+      display_name: config
+        .display_name
+        .clone()
+        .unwrap_or_else(|| name.to_string()),
+      idp_entity_id: metadata.entity_id,
+      idp_sso_url: metadata.sso_url,
+      idp_key: DecodingKey::from_rsa_pem(certificate.as_bytes())
+        .map_err(|err| SamlError::Config(format!("invalid IdP certificate: {err}")))?,
+      sp_entity_id: config.sp_entity_id.clone(),
+    });
+  }
+
+  pub fn name(&self) -> &str {
+    return &self.name;
+  }
+
+  pub fn display_name(&self) -> &str {
+    return &self.display_name;
+  }
+
+  /// URL of the assertion consumer service, the identity provider posts responses to.
+  pub(crate) fn acs_url(&self, site_url: &Url) -> String {
+    return self.endpoint_url(site_url, "acs");
+  }
+
+  /// URL of TrailBase's service provider metadata for this identity provider.
+  pub(crate) fn metadata_url(&self, site_url: &Url) -> String {
+    return self.endpoint_url(site_url, "metadata");
+  }
+
+  pub(crate) fn sp_entity_id(&self, site_url: &Url) -> String {
+    return self
+      .sp_entity_id
+      .clone()
+      .unwrap_or_else(|| self.metadata_url(site_url));
+  }
+
+  fn endpoint_url(&self, site_url: &Url, endpoint: &str) -> String {
+    return format!(
+      "{site}/{AUTH_API_PATH}/saml/{name}/{endpoint}",
+      site = site_url.as_str().trim_end_matches('/'),
+      name = self.name,
+    );
+  }
+}
+
+struct IdpMetadata {
+  entity_id: String,
+  sso_url: Url,
+  /// PEM-encoded signing certificate.
+  certificate: Option<String>,
+}
+
+fn parse_idp_metadata(xml: &str) -> Result<IdpMetadata, SamlError> {
+  let root = parse(xml)?;
+
+  // Metadata may either be a single entity or a list thereof.
+  let entity = if root.is(NS_METADATA, "EntityDescriptor") {
+    Some(&root)
+  } else if root.is(NS_METADATA, "EntitiesDescriptor") {
+    root
+      .children_named(NS_METADATA, "EntityDescriptor")
+      .find(|entity| entity.child(NS_METADATA, "IDPSSODescriptor").is_some())
+  } else {
+    None
+  };
+  let Some(entity) = entity else {
+    return Err(SamlError::Config("missing EntityDescriptor".into()));
+  };
+
+  let entity_id = entity
+    .attribute("entityID")
+    .filter(|id| !id.is_empty())
+    .ok_or_else(|| SamlError::Config("missing entityID".into()))?
+    .to_string();
+
+  let descriptor = entity
+    .child(NS_METADATA, "IDPSSODescriptor")
+    .ok_or_else(|| SamlError::Config("missing IDPSSODescriptor".into()))?;
+
+  let sso_url = descriptor
+    .children_named(NS_METADATA, "SingleSignOnService")
+    .find(|service| service.attribute("Binding") == Some(HTTP_REDIRECT_BINDING))
+    .and_then(|service| service.attribute("Location"))
+    .ok_or_else(|| SamlError::Config("missing HTTP-Redirect SingleSignOnService".into()))?;
+  let sso_url =
+    Url::parse(sso_url).map_err(|err| SamlError::Config(format!("invalid SSO url: {err}")))?;
+
+  let certificate = descriptor
+    .children_named(NS_METADATA, "KeyDescriptor")
+    .filter(|key| key.attribute("use").is_none_or(|u| u == "signing"))
+    .find_map(x509_certificate)
+    .map(|certificate| to_pem(&certificate))
+    .transpose()?;
+
+  return Ok(IdpMetadata {
+    entity_id,
+    sso_url,
+    certificate,
+  });
+}
+
+fn x509_certificate(key_descriptor: &Element) -> Option<String> {
+  return Some(
+    key_descriptor
+      .child(NS_XMLDSIG, "KeyInfo")?
+      .child(NS_XMLDSIG, "X509Data")?
+      .child(NS_XMLDSIG, "X509Certificate")?
+      .text(),
+  );
+}
+
+fn to_pem(certificate: &str) -> Result<String, SamlError> {
+  // Re-encode to validate and normalize line breaks.
+  let der = decode_base64(certificate)?;
+  let encoded = BASE64_STANDARD.encode(der);
+
+  let mut pem = "-----BEGIN CERTIFICATE-----\n".to_string();
+  for line in encoded.as_bytes().chunks(64) {
+    pem.push_str(&String::from_utf8_lossy(line));
+    pem.push('\n');
+  }
+  pem.push_str("-----END CERTIFICATE-----\n");
+  return Ok(pem);
+}
@@ -0,0 +1,202 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::auth::saml::provider::SamlProvider;
+use crate::auth::saml::signature::{NS_XMLDSIG, verify_enveloped_signature};
+use crate::auth::saml::xml::{Element, parse};
+use crate::auth::saml::{NS_ASSERTION, NS_PROTOCOL, SamlError};
+
+const STATUS_SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+const BEARER: &str = "urn:oasis:names:tc:SAML:2.0:cm:bearer";
+const NAME_ID_FORMAT_EMAIL: &str = "urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress";
+
+/// Attribute names commonly used by identity providers for the user's e-mail address.
+const EMAIL_ATTRIBUTES: &[&str] = &[
+  "email",
+  "mail",
+  "emailaddress",
+  "http://schemas.xmlsoap.org/ws/2005/05/identity/claims/emailaddress",
+  "urn:oid:0.9.2342.19200300.100.1.3",
+];
+
+/// Tolerated clock skew between TrailBase and the identity provider.
+const CLOCK_SKEW: Duration = Duration::minutes(2);
+
+/// What a response has to match to be accepted.
+pub(crate) struct Expectations<'a> {
+  pub sp_entity_id: &'a str,
+  pub acs_url: &'a str,
+  /// ID of the authentication request the response has to be in response to.
+  pub request_id: &'a str,
+  pub now: DateTime<Utc>,
+}
+
+/// Identity asserted by the identity provider.
+#[derive(Debug, PartialEq)]
+pub(crate) struct SamlIdentity {
+  pub name_id: String,
+  pub email: Option<String>,
+}
+
+/// Validates a SAML response received via the HTTP-POST binding and extracts the asserted
+/// identity.
+///
+/// Either the response or the assertion itself has to be signed by the identity provider.
+/// Encrypted assertions and unsolicited, i.e. IdP-initiated, responses are not supported.
+pub(crate) fn validate_response(
+  xml: &str,
+  provider: &SamlProvider,
+  expected: &Expectations,
+) -> Result<SamlIdentity, SamlError> {
+  let response = parse(xml)?;
+  if !response.is(NS_PROTOCOL, "Response") {
+    return Err(SamlError::Assertion("not a SAML response"));
+  }
+
+  let status = response
+    .child(NS_PROTOCOL, "Status")
+    .and_then(|status| status.child(NS_PROTOCOL, "StatusCode"))
+    .and_then(|code| code.attribute("Value"));
+  if status != Some(STATUS_SUCCESS) {
+    return Err(SamlError::Assertion("unsuccessful status"));
+  }
+
+  if response.child(NS_ASSERTION, "EncryptedAssertion").is_some() {
+    return Err(SamlError::Assertion("encrypted assertions not supported"));
+  }
+  let mut assertions = response.children_named(NS_ASSERTION, "Assertion");
+  let (Some(assertion), None) = (assertions.next(), assertions.next()) else {
+    return Err(SamlError::Assertion("expected exactly one assertion"));
+  };
+
+  // NOTE: All data is read from the very elements that have been verified, which protects
+  // against signature wrapping.
+  if assertion.child(NS_XMLDSIG, "Signature").is_some() {
+    verify_enveloped_signature(assertion, &provider.idp_key)?;
+  } else {
+    verify_enveloped_signature(&response, &provider.idp_key)?;
+  }
+
+  if let Some(destination) = response.attribute("Destination")
+    && destination != expected.acs_url
+  {
+    return Err(SamlError::Assertion("destination mismatch"));
+  }
+  if let Some(in_response_to) = response.attribute("InResponseTo")
+    && in_response_to != expected.request_id
+  {
+    return Err(SamlError::Assertion("request mismatch"));
+  }
+
+  let issuer = assertion
+    .child(NS_ASSERTION, "Issuer")
+    .map(|issuer| issuer.text());
+  if issuer.as_deref() != Some(provider.idp_entity_id.as_str()) {
+    return Err(SamlError::Assertion("issuer mismatch"));
+  }
+
+  validate_conditions(assertion, expected)?;
+
+  let subject = assertion
+    .child(NS_ASSERTION, "Subject")
+    .ok_or(SamlError::Assertion("missing subject"))?;
+  validate_subject_confirmation(subject, expected)?;
+
+  let name_id = subject
+    .child(NS_ASSERTION, "NameID")
+    .ok_or(SamlError::Assertion("missing NameID"))?;
+  let name_id_value = name_id.text();
+  if name_id_value.is_empty() {
+    return Err(SamlError::Assertion("missing NameID"));
+  }
+
+  let email = email_attribute(assertion).or_else(|| {
+    (name_id.attribute("Format") == Some(NAME_ID_FORMAT_EMAIL)).then(|| name_id_value.clone())
+  });
+
+  return Ok(SamlIdentity {
+    name_id: name_id_value,
+    email,
+  });
+}
+
+fn validate_conditions(assertion: &Element, expected: &Expectations) -> Result<(), SamlError> {
+  let conditions = assertion
+    .child(NS_ASSERTION, "Conditions")
+    .ok_or(SamlError::Assertion("missing conditions"))?;
+
+  if let Some(not_before) = conditions.attribute("NotBefore")
+    && expected.now + CLOCK_SKEW < parse_time(not_before)?
+  {
+    return Err(SamlError::Assertion("assertion not yet valid"));
+  }
+  if let Some(not_on_or_after) = conditions.attribute("NotOnOrAfter")
+    && expected.now - CLOCK_SKEW >= parse_time(not_on_or_after)?
+  {
+    return Err(SamlError::Assertion("assertion expired"));
+  }
+
+  // Every audience restriction has to include us, see SAML Core 2.0 Section 2.5.1.4.
+  let mut restrictions = conditions
+    .children_named(NS_ASSERTION, "AudienceRestriction")
+    .peekable();
+  if restrictions.peek().is_none() {
+    return Err(SamlError::Assertion("missing audience restriction"));
+  }
+  for restriction in restrictions {
+    if !restriction
+      .children_named(NS_ASSERTION, "Audience")
+      .any(|audience| audience.text() == expected.sp_entity_id)
+    {
+      return Err(SamlError::Assertion("audience mismatch"));
+    }
+  }
+
+  return Ok(());
+}
+
+/// Ensures the subject can be confirmed as the bearer of the assertion, see SAML Profiles 2.0
+/// Section 4.1.4.2.
+fn validate_subject_confirmation(
+  subject: &Element,
+  expected: &Expectations,
+) -> Result<(), SamlError> {
+  let confirmed = subject
+    .children_named(NS_ASSERTION, "SubjectConfirmation")
+    .filter(|confirmation| confirmation.attribute("Method") == Some(BEARER))
+    .filter_map(|confirmation| confirmation.child(NS_ASSERTION, "SubjectConfirmationData"))
+    .any(|data| {
+      let not_expired = data
+        .attribute("NotOnOrAfter")
+        .and_then(|t| parse_time(t).ok())
+        .is_some_and(|t| expected.now - CLOCK_SKEW < t);
+
+      return not_expired
+        && data.attribute("Recipient") == Some(expected.acs_url)
+        && data.attribute("InResponseTo") == Some(expected.request_id);
+    });
+
+  if !confirmed {
+    return Err(SamlError::Assertion("subject confirmation failed"));
+  }
+  return Ok(());
+}
+
+fn email_attribute(assertion: &Element) -> Option<String> {
+  return assertion
+    .children_named(NS_ASSERTION, "AttributeStatement")
+    .flat_map(|statement| statement.children_named(NS_ASSERTION, "Attribute"))
+    .filter(|attribute| {
+      attribute
+        .attribute("Name")
+        .is_some_and(|name| EMAIL_ATTRIBUTES.contains(&name))
+    })
+    .flat_map(|attribute| attribute.children_named(NS_ASSERTION, "AttributeValue"))
+    .map(|value| value.text())
+    .find(|value| !value.is_empty());
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>, SamlError> {
+  return DateTime::parse_from_rfc3339(value)
+    .map(|t| t.with_timezone(&Utc))
+    .map_err(|_err| SamlError::Assertion("invalid timestamp"));
+}
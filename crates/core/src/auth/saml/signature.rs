@@ -0,0 +1,144 @@
+use base64::prelude::*;
+use jsonwebtoken::{Algorithm, DecodingKey};
+use sha2::{Digest, Sha256};
+
+use crate::auth::saml::SamlError;
+use crate::auth::saml::xml::{Element, canonicalize};
+
+pub(crate) const NS_XMLDSIG: &str = "http://www.w3.org/2000/09/xmldsig#";
+
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const RSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256";
+const SHA256: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+
+/// Verifies the enveloped XML signature of `element`, i.e. a `ds:Signature` child referencing
+/// `element` itself by its `ID`.
+///
+/// Only the algorithms commonly used by SAML identity providers are supported: exclusive
+/// canonicalization, RSA-SHA256 signatures and SHA256 digests.
+///
+/// NOTE: The key is always the configured one. Keys embedded in the signature's `KeyInfo` are
+/// ignored, since anyone can sign a document with their own key.
+pub(crate) fn verify_enveloped_signature(
+  element: &Element,
+  key: &DecodingKey,
+) -> Result<(), SamlError> {
+  let mut signatures = element.children_named(NS_XMLDSIG, "Signature");
+  let (Some(signature), None) = (signatures.next(), signatures.next()) else {
+    return Err(SamlError::Signature("expected exactly one signature"));
+  };
+
+  let signed_info = signature
+    .child(NS_XMLDSIG, "SignedInfo")
+    .ok_or(SamlError::Signature("missing SignedInfo"))?;
+
+  let canonicalization_method = signed_info
+    .child(NS_XMLDSIG, "CanonicalizationMethod")
+    .ok_or(SamlError::Signature("missing CanonicalizationMethod"))?;
+  if algorithm(canonicalization_method) != Some(EXC_C14N) {
+    return Err(SamlError::Signature("unsupported canonicalization"));
+  }
+
+  let signature_method = signed_info.child(NS_XMLDSIG, "SignatureMethod");
+  if signature_method.and_then(algorithm) != Some(RSA_SHA256) {
+    return Err(SamlError::Signature("unsupported signature method"));
+  }
+
+  // Only the element the signature is enveloped in may be referenced. Otherwise, attackers could
+  // wrap a validly signed element and have us read unsigned data.
+  let mut references = signed_info.children_named(NS_XMLDSIG, "Reference");
+  let (Some(reference), None) = (references.next(), references.next()) else {
+    return Err(SamlError::Signature("expected exactly one reference"));
+  };
+  match (element.attribute("ID"), reference.attribute("URI")) {
+    (Some(id), Some(uri)) if !id.is_empty() && uri.strip_prefix('#') == Some(id) => {}
+    _ => {
+      return Err(SamlError::Signature("reference does not match element"));
+    }
+  };
+
+  let mut inclusive_prefixes: Vec<String> = vec![];
+  let mut canonicalized = false;
+  for transform in reference
+    .child(NS_XMLDSIG, "Transforms")
+    .into_iter()
+    .flat_map(|transforms| transforms.children_named(NS_XMLDSIG, "Transform"))
+  {
+    match algorithm(transform) {
+      Some(ENVELOPED_SIGNATURE) => {}
+      Some(EXC_C14N) => {
+        canonicalized = true;
+        inclusive_prefixes = prefix_list(transform);
+      }
+      _ => {
+        return Err(SamlError::Signature("unsupported transform"));
+      }
+    };
+  }
+  if !canonicalized {
+    return Err(SamlError::Signature("unsupported canonicalization"));
+  }
+
+  if reference
+    .child(NS_XMLDSIG, "DigestMethod")
+    .and_then(algorithm)
+    != Some(SHA256)
+  {
+    return Err(SamlError::Signature("unsupported digest method"));
+  }
+  let expected_digest = decode_base64(
+    &reference
+      .child(NS_XMLDSIG, "DigestValue")
+      .ok_or(SamlError::Signature("missing DigestValue"))?
+      .text(),
+  )?;
+
+  let digest = Sha256::digest(canonicalize(element, Some(signature), &inclusive_prefixes));
+  if digest.as_slice() != expected_digest.as_slice() {
+    return Err(SamlError::Signature("digest mismatch"));
+  }
+
+  let signature_value = decode_base64(
+    &signature
+      .child(NS_XMLDSIG, "SignatureValue")
+      .ok_or(SamlError::Signature("missing SignatureValue"))?
+      .text(),
+  )?;
+  let canonical_signed_info =
+    canonicalize(signed_info, None, &prefix_list(canonicalization_method));
+
+  let valid = jsonwebtoken::crypto::verify(
+    &BASE64_URL_SAFE_NO_PAD.encode(signature_value),
+    canonical_signed_info.as_bytes(),
+    key,
+    Algorithm::RS256,
+  )
+  .map_err(|_err| SamlError::Signature("invalid signature"))?;
+  if !valid {
+    return Err(SamlError::Signature("invalid signature"));
+  }
+
+  return Ok(());
+}
+
+fn algorithm(element: &Element) -> Option<&str> {
+  return element.attribute("Algorithm");
+}
+
+/// The `InclusiveNamespaces PrefixList` of a canonicalization method or transform.
+fn prefix_list(element: &Element) -> Vec<String> {
+  return element
+    .child(EXC_C14N, "InclusiveNamespaces")
+    .and_then(|inclusive| inclusive.attribute("PrefixList"))
+    .map(|list| list.split_whitespace().map(|p| p.to_string()).collect())
+    .unwrap_or_default();
+}
+
+/// Decodes Base64 content, which may be wrapped across multiple lines.
+pub(crate) fn decode_base64(value: &str) -> Result<Vec<u8>, SamlError> {
+  let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+  return BASE64_STANDARD
+    .decode(value)
+    .map_err(|err| SamlError::Xml(err.to_string()));
+}
@@ -0,0 +1,506 @@
+//! Minimal XML DOM and Exclusive XML Canonicalization (without comments) as needed for verifying
+//! SAML signatures, see https://www.w3.org/TR/xml-exc-c14n/.
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::auth::saml::SamlError;
+
+const NS_XML: &str = "http://www.w3.org/XML/1998/namespace";
+
+#[derive(Clone, Debug)]
+pub(crate) enum Node {
+  Element(Element),
+  Text(String),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Attribute {
+  /// Qualified name, e.g. "ID" or "xsi:type".
+  pub name: String,
+  /// Namespace URI of prefixed attributes. Empty otherwise.
+  pub namespace: String,
+  pub value: String,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Element {
+  /// Qualified name, e.g. "saml:Assertion".
+  pub name: String,
+  /// Namespace URI of the element. Empty if in no namespace.
+  pub namespace: String,
+  /// Attributes excluding namespace declarations.
+  pub attributes: Vec<Attribute>,
+  /// All namespaces in scope including inherited ones by prefix. The default namespace is keyed
+  /// by the empty prefix.
+  pub namespaces: BTreeMap<String, String>,
+  pub children: Vec<Node>,
+}
+
+impl Element {
+  pub fn local_name(&self) -> &str {
+    return local_name(&self.name);
+  }
+
+  pub fn is(&self, namespace: &str, local_name: &str) -> bool {
+    return self.namespace == namespace && self.local_name() == local_name;
+  }
+
+  /// Value of the given unprefixed attribute.
+  pub fn attribute(&self, name: &str) -> Option<&str> {
+    return self
+      .attributes
+      .iter()
+      .find(|a| a.name == name)
+      .map(|a| a.value.as_str());
+  }
+
+  pub fn elements(&self) -> impl Iterator<Item = &Element> {
+    return self.children.iter().filter_map(|child| match child {
+      Node::Element(element) => Some(element),
+      Node::Text(_) => None,
+    });
+  }
+
+  pub fn children_named<'a>(
+    &'a self,
+    namespace: &'a str,
+    local_name: &'a str,
+  ) -> impl Iterator<Item = &'a Element> {
+    return self
+      .elements()
+      .filter(move |element| element.is(namespace, local_name));
+  }
+
+  pub fn child(&self, namespace: &str, local_name: &str) -> Option<&Element> {
+    return self.children_named(namespace, local_name).next();
+  }
+
+  /// Concatenated, trimmed text content of the element's direct children.
+  pub fn text(&self) -> String {
+    let text: String = self
+      .children
+      .iter()
+      .filter_map(|child| match child {
+        Node::Text(text) => Some(text.as_str()),
+        Node::Element(_) => None,
+      })
+      .collect();
+    return text.trim().to_string();
+  }
+}
+
+/// Parses the given document into a DOM returning the root element.
+///
+/// NOTE: Document type declarations are rejected to avoid entity expansion attacks. Comments and
+/// processing instructions are dropped, since they're irrelevant for canonicalization without
+/// comments.
+pub(crate) fn parse(xml: &str) -> Result<Element, SamlError> {
+  let mut reader = Reader::from_str(xml);
+
+  let mut stack: Vec<Element> = vec![];
+  let mut root: Option<Element> = None;
+
+  let push_text = |stack: &mut Vec<Element>, text: &str| {
+    // Text outside the root element is irrelevant.
+    let Some(parent) = stack.last_mut() else {
+      return;
+    };
+
+    // XML processors normalize line endings, see XML 1.0 Section 2.11.
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    if let Some(Node::Text(previous)) = parent.children.last_mut() {
+      previous.push_str(&text);
+    } else {
+      parent.children.push(Node::Text(text));
+    }
+  };
+
+  loop {
+    match reader.read_event().map_err(xml_error)? {
+      Event::Start(start) => {
+        let element = new_element(&start, stack.last())?;
+        stack.push(element);
+      }
+      Event::Empty(start) => {
+        let element = new_element(&start, stack.last())?;
+        append_element(&mut stack, &mut root, element)?;
+      }
+      Event::End(_) => {
+        let Some(element) = stack.pop() else {
+          return Err(SamlError::Xml("unbalanced end tag".into()));
+        };
+        append_element(&mut stack, &mut root, element)?;
+      }
+      Event::Text(text) => {
+        push_text(&mut stack, utf8(&text)?);
+      }
+      Event::CData(data) => {
+        push_text(&mut stack, utf8(&data)?);
+      }
+      Event::GeneralRef(reference) => {
+        push_text(
+          &mut stack,
+          &resolve_reference(utf8(&reference)?)?.to_string(),
+        );
+      }
+      Event::DocType(_) => {
+        return Err(SamlError::Xml(
+          "document type declarations not allowed".into(),
+        ));
+      }
+      Event::Comment(_) | Event::Decl(_) | Event::PI(_) => {}
+      Event::Eof => break,
+    }
+  }
+
+  if !stack.is_empty() {
+    return Err(SamlError::Xml("unexpected end of document".into()));
+  }
+  return root.ok_or_else(|| SamlError::Xml("missing root element".into()));
+}
+
+/// Renders the given element in canonical form according to Exclusive XML Canonicalization.
+///
+/// The `excluded` element, if any, is omitted, which implements the enveloped signature
+/// transform. `inclusive_prefixes` are namespace prefixes treated as in inclusive
+/// canonicalization, i.e. the `InclusiveNamespaces PrefixList`.
+pub(crate) fn canonicalize(
+  element: &Element,
+  excluded: Option<&Element>,
+  inclusive_prefixes: &[String],
+) -> String {
+  let mut out = String::new();
+  write_canonical(
+    &mut out,
+    element,
+    excluded,
+    inclusive_prefixes,
+    &BTreeMap::new(),
+  );
+  return out;
+}
+
+fn write_canonical(
+  out: &mut String,
+  element: &Element,
+  excluded: Option<&Element>,
+  inclusive_prefixes: &[String],
+  rendered: &BTreeMap<String, String>,
+) {
+  // Only namespaces visibly utilized by the element or its attributes are rendered.
+  let mut prefixes: BTreeSet<&str> = BTreeSet::new();
+  prefixes.insert(prefix(&element.name));
+  for attribute in &element.attributes {
+    if attribute.name.contains(':') {
+      prefixes.insert(prefix(&attribute.name));
+    }
+  }
+  for prefix in inclusive_prefixes {
+    prefixes.insert(if prefix == "#default" { "" } else { prefix });
+  }
+
+  let mut rendered = rendered.clone();
+  let mut declarations: Vec<(&str, &str)> = vec![];
+  for prefix in prefixes {
+    if prefix == "xml" {
+      continue;
+    }
+    let uri = element
+      .namespaces
+      .get(prefix)
+      .map(|uri| uri.as_str())
+      .unwrap_or_default();
+
+    let render = if prefix.is_empty() {
+      // An empty default namespace only needs to be rendered to undeclare a rendered one.
+      rendered
+        .get(prefix)
+        .map(|uri| uri.as_str())
+        .unwrap_or_default()
+        != uri
+    } else {
+      !uri.is_empty() && rendered.get(prefix).map(|uri| uri.as_str()) != Some(uri)
+    };
+
+    if render {
+      declarations.push((prefix, uri));
+      rendered.insert(prefix.to_string(), uri.to_string());
+    }
+  }
+
+  out.push('<');
+  out.push_str(&element.name);
+  for (prefix, uri) in declarations {
+    if prefix.is_empty() {
+      out.push_str(" xmlns=\"");
+    } else {
+      out.push_str(" xmlns:");
+      out.push_str(prefix);
+      out.push_str("=\"");
+    }
+    escape_attribute_value(out, uri);
+    out.push('"');
+  }
+
+  let mut attributes: Vec<&Attribute> = element.attributes.iter().collect();
+  attributes.sort_by(|a, b| {
+    return (a.namespace.as_str(), local_name(&a.name))
+      .cmp(&(b.namespace.as_str(), local_name(&b.name)));
+  });
+  for attribute in attributes {
+    out.push(' ');
+    out.push_str(&attribute.name);
+    out.push_str("=\"");
+    escape_attribute_value(out, &attribute.value);
+    out.push('"');
+  }
+  out.push('>');
+
+  for child in &element.children {
+    match child {
+      Node::Text(text) => escape_text(out, text),
+      Node::Element(child) => {
+        if excluded.is_some_and(|excluded| std::ptr::eq(excluded, child)) {
+          continue;
+        }
+        write_canonical(out, child, excluded, &[], &rendered);
+      }
+    }
+  }
+
+  out.push_str("</");
+  out.push_str(&element.name);
+  out.push('>');
+}
+
+/// Escapes text for use in XML documents generated by TrailBase, e.g. SAML requests.
+pub(crate) fn escape(s: &str) -> String {
+  let mut out = String::with_capacity(s.len());
+  for c in s.chars() {
+    match c {
+      '&' => out.push_str("&amp;"),
+      '<' => out.push_str("&lt;"),
+      '>' => out.push_str("&gt;"),
+      '"' => out.push_str("&quot;"),
+      '\'' => out.push_str("&apos;"),
+      c => out.push(c),
+    }
+  }
+  return out;
+}
+
+fn escape_text(out: &mut String, s: &str) {
+  for c in s.chars() {
+    match c {
+      '&' => out.push_str("&amp;"),
+      '<' => out.push_str("&lt;"),
+      '>' => out.push_str("&gt;"),
+      '\r' => out.push_str("&#xD;"),
+      c => out.push(c),
+    }
+  }
+}
+
+fn escape_attribute_value(out: &mut String, s: &str) {
+  for c in s.chars() {
+    match c {
+      '&' => out.push_str("&amp;"),
+      '<' => out.push_str("&lt;"),
+      '"' => out.push_str("&quot;"),
+      '\t' => out.push_str("&#x9;"),
+      '\n' => out.push_str("&#xA;"),
+      '\r' => out.push_str("&#xD;"),
+      c => out.push(c),
+    }
+  }
+}
+
+fn new_element(start: &BytesStart, parent: Option<&Element>) -> Result<Element, SamlError> {
+  let name = utf8(start.name().as_ref())?.to_string();
+  let mut namespaces = parent
+    .map(|parent| parent.namespaces.clone())
+    .unwrap_or_default();
+
+  let mut attributes: Vec<(String, String)> = vec![];
+  for attribute in start.attributes() {
+    let attribute = attribute.map_err(|err| SamlError::Xml(err.to_string()))?;
+    let key = utf8(attribute.key.as_ref())?.to_string();
+    let value = unescape_attribute_value(utf8(&attribute.value)?)?;
+
+    if key == "xmlns" {
+      namespaces.insert(String::new(), value);
+    } else if let Some(prefix) = key.strip_prefix("xmlns:") {
+      namespaces.insert(prefix.to_string(), value);
+    } else {
+      attributes.push((key, value));
+    }
+  }
+
+  let namespace = resolve_namespace(&namespaces, prefix(&name))?;
+  let attributes = attributes
+    .into_iter()
+    .map(|(name, value)| {
+      // Unprefixed attributes are in no namespace rather than the default namespace.
+      let namespace = if name.contains(':') {
+        resolve_namespace(&namespaces, prefix(&name))?
+      } else {
+        String::new()
+      };
+      return Ok(Attribute {
+        name,
+        namespace,
+        value,
+      });
+    })
+    .collect::<Result<Vec<_>, SamlError>>()?;
+
+  return Ok(Element {
+    name,
+    namespace,
+    attributes,
+    namespaces,
+    children: vec![],
+  });
+}
+
+fn append_element(
+  stack: &mut [Element],
+  root: &mut Option<Element>,
+  element: Element,
+) -> Result<(), SamlError> {
+  if let Some(parent) = stack.last_mut() {
+    parent.children.push(Node::Element(element));
+    return Ok(());
+  }
+
+  if root.is_some() {
+    return Err(SamlError::Xml("multiple root elements".into()));
+  }
+  *root = Some(element);
+  return Ok(());
+}
+
+fn resolve_namespace(
+  namespaces: &BTreeMap<String, String>,
+  prefix: &str,
+) -> Result<String, SamlError> {
+  if prefix == "xml" {
+    return Ok(NS_XML.to_string());
+  }
+  return match namespaces.get(prefix) {
+    Some(uri) => Ok(uri.clone()),
+    None if prefix.is_empty() => Ok(String::new()),
+    None => Err(SamlError::Xml(format!("undeclared prefix: {prefix}"))),
+  };
+}
+
+fn unescape_attribute_value(raw: &str) -> Result<String, SamlError> {
+  let mut out = String::with_capacity(raw.len());
+  let mut rest = raw;
+  while let Some(index) = rest.find(['&', '\t', '\n', '\r']) {
+    out.push_str(&rest[..index]);
+    rest = &rest[index..];
+
+    if rest.starts_with("\r\n") {
+      // Literal whitespace is normalized to spaces, see XML 1.0 Section 3.3.3.
+      out.push(' ');
+      rest = &rest[2..];
+    } else if !rest.starts_with('&') {
+      out.push(' ');
+      rest = &rest[1..];
+    } else {
+      let Some(end) = rest.find(';') else {
+        return Err(SamlError::Xml("unterminated reference".into()));
+      };
+      out.push(resolve_reference(&rest[1..end])?);
+      rest = &rest[end + 1..];
+    }
+  }
+  out.push_str(rest);
+  return Ok(out);
+}
+
+/// Resolves predefined entities and character references, e.g. "amp" or "#x41".
+fn resolve_reference(name: &str) -> Result<char, SamlError> {
+  let c = match name {
+    "lt" => Some('<'),
+    "gt" => Some('>'),
+    "amp" => Some('&'),
+    "apos" => Some('\''),
+    "quot" => Some('"'),
+    name => {
+      let code = if let Some(hex) = name.strip_prefix("#x") {
+        u32::from_str_radix(hex, 16).ok()
+      } else if let Some(decimal) = name.strip_prefix('#') {
+        decimal.parse::<u32>().ok()
+      } else {
+        None
+      };
+      code.and_then(char::from_u32)
+    }
+  };
+
+  return c.ok_or_else(|| SamlError::Xml(format!("unknown reference: {name}")));
+}
+
+fn prefix(name: &str) -> &str {
+  return name.split_once(':').map_or("", |(prefix, _)| prefix);
+}
+
+fn local_name(name: &str) -> &str {
+  return name.split_once(':').map_or(name, |(_, local)| local);
+}
+
+fn utf8(bytes: &[u8]) -> Result<&str, SamlError> {
+  return std::str::from_utf8(bytes).map_err(|err| SamlError::Xml(err.to_string()));
+}
+
+fn xml_error(err: quick_xml::Error) -> SamlError {
+  return SamlError::Xml(err.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_exclusive_canonicalization() {
+    let xml = r#"<?xml version="1.0"?>
+<!-- comment -->
+<a:Root xmlns:a="urn:a" xmlns:b="urn:b" xmlns:unused="urn:unused"><a:Child z="1" b:y="2" a="&amp;&#x41;"/><b:Other>x &lt; y<!-- c --></b:Other></a:Root>"#;
+
+    let root = parse(xml).unwrap();
+    assert_eq!(
+      canonicalize(&root, None, &[]),
+      r#"<a:Root xmlns:a="urn:a"><a:Child xmlns:b="urn:b" a="&amp;A" z="1" b:y="2"></a:Child><b:Other xmlns:b="urn:b">x &lt; y</b:Other></a:Root>"#
+    );
+
+    // Canonicalizing a sub-tree only renders namespaces it utilizes.
+    let Node::Element(ref child) = root.children[0] else {
+      panic!("expected element");
+    };
+    assert_eq!(
+      canonicalize(child, None, &[]),
+      r#"<a:Child xmlns:a="urn:a" xmlns:b="urn:b" a="&amp;A" z="1" b:y="2"></a:Child>"#
+    );
+
+    // Inclusive prefixes are rendered even if unused.
+    assert_eq!(
+      canonicalize(child, None, &["unused".to_string()]),
+      r#"<a:Child xmlns:a="urn:a" xmlns:b="urn:b" xmlns:unused="urn:unused" a="&amp;A" z="1" b:y="2"></a:Child>"#
+    );
+
+    // Enveloped elements can be excluded.
+    assert_eq!(
+      canonicalize(&root, Some(child), &[]),
+      r#"<a:Root xmlns:a="urn:a"><b:Other xmlns:b="urn:b">x &lt; y</b:Other></a:Root>"#
+    );
+  }
+
+  #[test]
+  fn test_parse_rejects_doctype() {
+    let xml = r#"<!DOCTYPE x [<!ENTITY e "boom">]><x>&e;</x>"#;
+    assert!(parse(xml).is_err());
+  }
+}
@@ -0,0 +1,190 @@
+//! Sessions, i.e. refresh tokens handed out to a user's signed-in devices.
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use const_format::formatcp;
+use serde::{Deserialize, Serialize};
+use trailbase_sqlite::{Connection, params};
+use ts_rs::TS;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::auth::AuthError;
+use crate::constants::SESSION_TABLE;
+use crate::extract::ip::extract_ip_from_parts;
+use crate::util::get_header;
+
+/// Maximum length of user agent strings stored alongside sessions.
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// Request metadata recorded when starting a new session to help users tell their sessions apart.
+#[derive(Clone, Debug, Default)]
+pub struct SessionMetadata {
+  pub user_agent: Option<String>,
+  pub client_ip: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for SessionMetadata
+where
+  S: Send + Sync,
+{
+  type Rejection = std::convert::Infallible;
+
+  async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+    return Ok(SessionMetadata {
+      user_agent: get_header(&parts.headers, "user-agent")
+        .map(|ua| ua.chars().take(MAX_USER_AGENT_LENGTH).collect()),
+      client_ip: extract_ip_from_parts(parts).map(|ip| ip.to_string()),
+    });
+  }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct Session {
+  pub id: i64,
+  pub user_agent: Option<String>,
+  pub client_ip: Option<String>,
+  pub created: i64,
+  /// Last time the session was used to refresh an auth token.
+  pub last_used: i64,
+  pub expires: i64,
+  /// Whether this is the session of the listing request.
+  pub current: bool,
+}
+
+/// Lists the given user's unexpired sessions, most recently used first.
+///
+/// If provided, the session matching `current_refresh_token` is marked as current.
+pub async fn list_sessions(
+  session_conn: &Connection,
+  user_id: &Uuid,
+  current_refresh_token: Option<&str>,
+) -> Result<Vec<Session>, AuthError> {
+  const QUERY: &str = formatcp!(
+    "\
+      SELECT \
+        id, user_agent, client_ip, created, COALESCE(last_used, created), expires, \
+        refresh_token = COALESCE($2, '') \
+      FROM '{SESSION_TABLE}' \
+      WHERE user = $1 AND expires > UNIXEPOCH() \
+      ORDER BY COALESCE(last_used, created) DESC \
+    "
+  );
+
+  let rows = session_conn
+    .read_query_rows(
+      QUERY,
+      params!(
+        user_id.into_bytes(),
+        current_refresh_token.map(|t| t.to_string())
+      ),
+    )
+    .await?;
+
+  return rows
+    .into_iter()
+    .map(|row| -> Result<Session, AuthError> {
+      let internal =
+        |err: trailbase_sqlite::from_sql::FromSqlError| AuthError::Internal(err.into());
+
+      return Ok(Session {
+        id: row.get(0).map_err(internal)?,
+        user_agent: row.get(1).map_err(internal)?,
+        client_ip: row.get(2).map_err(internal)?,
+        created: row.get(3).map_err(internal)?,
+        last_used: row.get(4).map_err(internal)?,
+        expires: row.get(5).map_err(internal)?,
+        current: row.get(6).map_err(internal)?,
+      });
+    })
+    .collect();
+}
+
+/// Revokes, i.e. deletes, the given session of the given user.
+///
+/// NOTE: Auth tokens already minted from the session stay valid until they expire.
+pub async fn revoke_session(
+  session_conn: &Connection,
+  user_id: &Uuid,
+  session_id: i64,
+) -> Result<(), AuthError> {
+  const QUERY: &str = formatcp!("DELETE FROM '{SESSION_TABLE}' WHERE id = $1 AND user = $2");
+
+  let rows_affected = session_conn
+    .execute(QUERY, params!(session_id, user_id.into_bytes()))
+    .await?;
+  if rows_affected == 0 {
+    return Err(AuthError::NotFound);
+  }
+  return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::admin::user::create_user_for_test;
+  use crate::app_state::test_state;
+  use crate::auth::tokens::mint_new_tokens;
+  use crate::auth::util::get_user_by_id;
+
+  #[tokio::test]
+  async fn test_session_listing_and_revocation() {
+    let state = test_state(None).await.unwrap();
+
+    let user_id = create_user_for_test(&state, "user@test.org", "secret123")
+      .await
+      .unwrap();
+    let db_user = get_user_by_id(state.user_conn(), &user_id).await.unwrap();
+
+    let mut refresh_tokens = vec![];
+    for user_agent in ["laptop", "phone"] {
+      let tokens = mint_new_tokens(
+        state.user_conn(),
+        state.session_conn(),
+        &db_user,
+        &SessionMetadata {
+          user_agent: Some(user_agent.to_string()),
+          client_ip: Some("127.0.0.1".to_string()),
+        },
+        &state.custom_claims(),
+        &chrono::Duration::minutes(5),
+        &chrono::Duration::minutes(60),
+      )
+      .await
+      .unwrap();
+      refresh_tokens.push(tokens.refresh_token);
+    }
+
+    let sessions = list_sessions(state.session_conn(), &user_id, Some(&refresh_tokens[0]))
+      .await
+      .unwrap();
+    assert_eq!(sessions.len(), 2);
+    let laptop = sessions
+      .iter()
+      .find(|s| s.user_agent.as_deref() == Some("laptop"))
+      .unwrap();
+    assert!(laptop.current);
+    assert_eq!(laptop.client_ip.as_deref(), Some("127.0.0.1"));
+    assert_eq!(sessions.iter().filter(|s| s.current).count(), 1);
+
+    // Users cannot revoke other user's sessions.
+    let other_id = create_user_for_test(&state, "other@test.org", "secret123")
+      .await
+      .unwrap();
+    assert!(matches!(
+      revoke_session(state.session_conn(), &other_id, laptop.id).await,
+      Err(AuthError::NotFound)
+    ));
+
+    revoke_session(state.session_conn(), &user_id, laptop.id)
+      .await
+      .unwrap();
+    let sessions = list_sessions(state.session_conn(), &user_id, None)
+      .await
+      .unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].user_agent.as_deref(), Some("phone"));
+    assert!(!sessions[0].current);
+  }
+}
@@ -9,7 +9,11 @@ use trailbase_sqlite::{Connection, params};
 
 use crate::app_state::AppState;
 use crate::auth::AuthError;
+use crate::auth::api_key::{ApiKeyScope, authenticate_with_api_key, is_api_key};
+use crate::auth::claims::CustomClaims;
 use crate::auth::jwt::AuthTokenClaims;
+use crate::auth::roles::get_user_roles;
+use crate::auth::session::SessionMetadata;
 use crate::auth::user::DbUser;
 use crate::auth::util::new_cookie;
 use crate::constants::{
@@ -23,6 +27,8 @@ use crate::util::get_header;
 pub(crate) struct Tokens {
   pub auth_token_claims: AuthTokenClaims,
   pub refresh_token: Option<String>,
+  /// Scope restrictions if the request was authenticated using an API key.
+  pub api_key: Option<ApiKeyScope>,
 }
 
 impl<S> FromRequestParts<S> for Tokens
@@ -65,12 +71,28 @@ pub(crate) async fn extract_tokens_from_request_parts(
   // means to propagate the new token back (unlike for cookies). The responsibility sits with the
   // client to refresh tokens in time.
   if let Some(tokens) = extract_tokens_from_headers(&parts.headers) {
+    // Long-lived API keys are opaque and exchanged for ephemeral claims on every request.
+    if is_api_key(tokens.auth_token) {
+      let (claims, scope) = authenticate_with_api_key(state.user_conn(), tokens.auth_token).await?;
+      let claims = state
+        .custom_claims()
+        .apply(state.user_conn(), claims)
+        .await?;
+
+      return Ok(Tokens {
+        auth_token_claims: claims,
+        refresh_token: None,
+        api_key: Some(scope),
+      });
+    }
+
     let claims = AuthTokenClaims::from_auth_token(state.jwt(), tokens.auth_token)
       .map_err(|_| AuthError::Unauthorized)?;
 
     return Ok(Tokens {
       auth_token_claims: claims,
       refresh_token: tokens.refresh_token.map(|r| r.to_owned()),
+      api_key: None,
     });
   }
 
@@ -90,6 +112,7 @@ pub(crate) async fn extract_tokens_from_request_parts(
       return Ok(Tokens {
         auth_token_claims: claims,
         refresh_token: tokens.refresh_token.map(|r| r.to_owned()),
+        api_key: None,
       });
     }
 
@@ -110,6 +133,7 @@ pub(crate) async fn extract_tokens_from_request_parts(
       return Ok(Tokens {
         auth_token_claims: claims,
         refresh_token: Some(refresh_token),
+        api_key: None,
       });
     }
   }
@@ -166,8 +190,11 @@ pub struct FreshTokens {
 }
 
 pub(crate) async fn mint_new_tokens(
+  user_conn: &Connection,
   session_conn: &Connection,
   db_user: &DbUser,
+  metadata: &SessionMetadata,
+  custom_claims: &CustomClaims,
   auth_token_ttl: &Duration,
   refresh_token_ttl: &Duration,
 ) -> Result<FreshTokens, AuthError> {
@@ -178,12 +205,24 @@ pub(crate) async fn mint_new_tokens(
     ));
   }
 
-  let claims = AuthTokenClaims::new(db_user, auth_token_ttl);
+  let claims = custom_claims
+    .apply(
+      user_conn,
+      AuthTokenClaims::new(db_user, auth_token_ttl)
+        .with_roles(get_user_roles(user_conn, &db_user.id).await?),
+    )
+    .await?;
 
   // Unlike JWT auth tokens, refresh tokens are opaque.
   let refresh_token = random_alphanumeric(REFRESH_TOKEN_LENGTH);
-  const QUERY: &str =
-    formatcp!("INSERT INTO '{SESSION_TABLE}' (user, refresh_token, expires) VALUES ($1, $2, $3)");
+  const QUERY: &str = formatcp!(
+    "\
+      INSERT INTO '{SESSION_TABLE}' \
+        (user, refresh_token, expires, user_agent, client_ip) \
+      VALUES \
+        ($1, $2, $3, $4, $5) \
+    "
+  );
 
   session_conn
     .execute(
@@ -192,6 +231,8 @@ pub(crate) async fn mint_new_tokens(
         db_user.id,
         refresh_token.clone(),
         (chrono::Utc::now() + *refresh_token_ttl).timestamp(),
+        metadata.user_agent.clone(),
+        metadata.client_ip.clone(),
       ),
     )
     .await?;
@@ -208,18 +249,21 @@ pub(crate) async fn reauth_with_refresh_token(
 ) -> Result<(AuthTokenClaims, chrono::Duration), AuthError> {
   let (auth_token_ttl, _refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
 
+  // NOTE: Also keep track of when the session was last used, e.g. to let users identify stale
+  // sessions.
   const SESSION_QUERY: &str = formatcp!(
     "\
-      SELECT user \
-      FROM '{SESSION_TABLE}' \
+      UPDATE '{SESSION_TABLE}' \
+      SET last_used = UNIXEPOCH() \
       WHERE \
         refresh_token = $1 AND expires > UNIXEPOCH() \
+      RETURNING user \
     "
   );
 
   let Some(user_id) = state
     .session_conn()
-    .read_query_row_get::<[u8; 16]>(SESSION_QUERY, params!(refresh_token), 0)
+    .write_query_row_get::<[u8; 16]>(SESSION_QUERY, params!(refresh_token), 0)
     .await?
   else {
     // Row not found case, typically expected in one of 4 cases:
@@ -256,8 +300,14 @@ pub(crate) async fn reauth_with_refresh_token(
     "unverified user, should have been caught by above query"
   );
 
-  return Ok((
-    AuthTokenClaims::new(&db_user, &auth_token_ttl),
-    auth_token_ttl,
-  ));
+  let roles = get_user_roles(state.user_conn(), &db_user.id).await?;
+  let claims = state
+    .custom_claims()
+    .apply(
+      state.user_conn(),
+      AuthTokenClaims::new(&db_user, &auth_token_ttl).with_roles(roles),
+    )
+    .await?;
+
+  return Ok((claims, auth_token_ttl));
 }
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::auth::AuthError;
+use crate::auth::api_key::ApiKeyScope;
 use crate::auth::jwt::AuthTokenClaims;
 use crate::auth::tokens::extract_tokens_from_request_parts;
 use crate::{app_state::AppState, util::b64_to_uuid};
@@ -72,9 +73,18 @@ pub struct User {
   pub email: String,
   /// Convenience UUID representation of [id] above.
   pub uuid: Uuid,
+  /// Names of the roles assigned to the current user.
+  pub roles: Vec<String>,
+  /// Custom claims of the current user, see [crate::auth::claims::CustomClaims].
+  #[serde(default)]
+  pub claims: serde_json::Map<String, serde_json::Value>,
 
   /// The "expected" CSRF token as included in the auth token claims [User] was constructed from.
   pub csrf_token: String,
+
+  /// Scope restrictions if the user was authenticated using an API key rather than a session.
+  #[serde(skip)]
+  pub api_key: Option<ApiKeyScope>,
 }
 
 impl PartialEq for User {
@@ -93,7 +103,10 @@ impl User {
       id: claims.sub,
       email: claims.email,
       uuid,
+      roles: claims.roles,
+      claims: claims.custom,
       csrf_token: claims.csrf_token,
+      api_key: None,
     });
   }
 
@@ -111,7 +124,10 @@ impl User {
       id: crate::util::uuid_to_b64(&user_id),
       email: email.to_string(),
       uuid: user_id,
+      roles: vec![],
+      claims: Default::default(),
       csrf_token: crate::rand::random_alphanumeric(20),
+      api_key: None,
     };
   }
 }
@@ -124,11 +140,11 @@ where
   type Rejection = AuthError;
 
   async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-    let user = User::from_token_claims(
-      extract_tokens_from_request_parts(&AppState::from_ref(state), parts)
-        .await?
-        .auth_token_claims,
-    )?;
+    let tokens = extract_tokens_from_request_parts(&AppState::from_ref(state), parts).await?;
+    let user = User {
+      api_key: tokens.api_key,
+      ..User::from_token_claims(tokens.auth_token_claims)?
+    };
 
     tracing::Span::current().record("user_id", user.uuid.to_u128_le());
 
@@ -148,7 +164,10 @@ where
     state: &S,
   ) -> Result<Option<Self>, Self::Rejection> {
     if let Ok(tokens) = extract_tokens_from_request_parts(&AppState::from_ref(state), parts).await {
-      let user = User::from_token_claims(tokens.auth_token_claims)?;
+      let user = User {
+        api_key: tokens.api_key,
+        ..User::from_token_claims(tokens.auth_token_claims)?
+      };
 
       tracing::Span::current().record("user_id", user.uuid.to_u128_le());
 
@@ -152,8 +152,11 @@ pub async fn login_with_password_for_test(
 
   let (auth_token_ttl, refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
   let tokens = crate::auth::tokens::mint_new_tokens(
+    state.user_conn(),
     state.session_conn(),
     &db_user,
+    &crate::auth::session::SessionMetadata::default(),
+    &state.custom_claims(),
     &auth_token_ttl,
     &refresh_token_ttl,
   )
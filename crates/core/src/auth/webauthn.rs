@@ -0,0 +1,709 @@
+//! Minimal WebAuthn relying party supporting passkeys with ES256 or EdDSA keys.
+//!
+//! We only request "none" attestation, i.e. we trust the authenticator's public key on first use
+//! rather than validating attestation statements against vendor roots.
+use base64::prelude::*;
+use ciborium::Value as CborValue;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use ts_rs::TS;
+use utoipa::ToSchema;
+
+use crate::app_state::AppState;
+use crate::auth::AuthError;
+
+/// COSE algorithm identifiers: https://www.iana.org/assignments/cose/cose.xhtml#algorithms.
+const COSE_ALG_ES256: i64 = -7;
+const COSE_ALG_EDDSA: i64 = -8;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+const CEREMONY_TIMEOUT_MS: u32 = 5 * 60 * 1000;
+
+#[derive(Debug, Error)]
+pub enum WebAuthnError {
+  #[error("Invalid client data: {0}")]
+  ClientData(&'static str),
+  #[error("Invalid authenticator data: {0}")]
+  AuthenticatorData(&'static str),
+  #[error("Unsupported public key")]
+  UnsupportedKey,
+  #[error("Invalid signature")]
+  Signature,
+  #[error("Sign count regression")]
+  SignCount,
+  #[error("CBOR: {0}")]
+  Cbor(String),
+  #[error("Decoding: {0}")]
+  Decode(#[from] base64::DecodeError),
+  #[error("JSON: {0}")]
+  Json(#[from] serde_json::Error),
+}
+
+/// The relying party, i.e. this TrailBase instance, as identified by its public `site_url`.
+#[derive(Debug, Clone)]
+pub(crate) struct RelyingParty {
+  pub id: String,
+  pub name: String,
+  pub origin: String,
+}
+
+impl RelyingParty {
+  pub(crate) fn from_state(state: &AppState) -> Result<Self, AuthError> {
+    let Some(ref site_url) = *state.site_url() else {
+      return Err(AuthError::FailedDependency(
+        "Passkeys require a site_url to derive the relying party".into(),
+      ));
+    };
+
+    let Some(host) = site_url.host_str() else {
+      return Err(AuthError::FailedDependency(
+        "Passkeys require a site_url with host".into(),
+      ));
+    };
+
+    let name = state
+      .access_config(|c| c.server.application_name.clone())
+      .unwrap_or_else(|| "TrailBase".to_string());
+
+    return Ok(Self {
+      id: host.to_string(),
+      name,
+      origin: site_url.origin().ascii_serialization(),
+    });
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct RelyingPartyEntity {
+  pub id: String,
+  pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct UserEntity {
+  /// Url-safe Base64 encoded user handle.
+  pub id: String,
+  pub name: String,
+  pub display_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct CredentialParameters {
+  pub r#type: String,
+  pub alg: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct CredentialDescriptor {
+  pub r#type: String,
+  /// Url-safe Base64 encoded credential id.
+  pub id: String,
+}
+
+impl CredentialDescriptor {
+  pub(crate) fn new(credential_id: &[u8]) -> Self {
+    return Self {
+      r#type: "public-key".to_string(),
+      id: BASE64_URL_SAFE_NO_PAD.encode(credential_id),
+    };
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct AuthenticatorSelection {
+  pub resident_key: String,
+  pub user_verification: String,
+}
+
+/// JSON representation of `PublicKeyCredentialCreationOptions`, which can be passed to
+/// `PublicKeyCredential.parseCreationOptionsFromJSON()` in the browser.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct CreationOptions {
+  pub rp: RelyingPartyEntity,
+  pub user: UserEntity,
+  pub challenge: String,
+  pub pub_key_cred_params: Vec<CredentialParameters>,
+  pub timeout: u32,
+  pub exclude_credentials: Vec<CredentialDescriptor>,
+  pub authenticator_selection: AuthenticatorSelection,
+  pub attestation: String,
+}
+
+impl CreationOptions {
+  pub(crate) fn new(
+    rp: &RelyingParty,
+    user: UserEntity,
+    challenge: String,
+    exclude_credentials: Vec<CredentialDescriptor>,
+  ) -> Self {
+    return Self {
+      rp: RelyingPartyEntity {
+        id: rp.id.clone(),
+        name: rp.name.clone(),
+      },
+      user,
+      challenge,
+      pub_key_cred_params: [COSE_ALG_ES256, COSE_ALG_EDDSA]
+        .into_iter()
+        .map(|alg| CredentialParameters {
+          r#type: "public-key".to_string(),
+          alg: alg as i32,
+        })
+        .collect(),
+      timeout: CEREMONY_TIMEOUT_MS,
+      exclude_credentials,
+      authenticator_selection: AuthenticatorSelection {
+        resident_key: "preferred".to_string(),
+        user_verification: "preferred".to_string(),
+      },
+      attestation: "none".to_string(),
+    };
+  }
+}
+
+/// JSON representation of `PublicKeyCredentialRequestOptions`, which can be passed to
+/// `PublicKeyCredential.parseRequestOptionsFromJSON()` in the browser.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct RequestOptions {
+  pub challenge: String,
+  pub rp_id: String,
+  pub timeout: u32,
+  /// Empty for discoverable credentials, i.e. when the user isn't known upfront.
+  pub allow_credentials: Vec<CredentialDescriptor>,
+  pub user_verification: String,
+}
+
+impl RequestOptions {
+  pub(crate) fn new(
+    rp: &RelyingParty,
+    challenge: String,
+    allow_credentials: Vec<CredentialDescriptor>,
+  ) -> Self {
+    return Self {
+      challenge,
+      rp_id: rp.id.clone(),
+      timeout: CEREMONY_TIMEOUT_MS,
+      allow_credentials,
+      user_verification: "preferred".to_string(),
+    };
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct AttestationResponse {
+  #[serde(rename = "clientDataJSON")]
+  pub client_data_json: String,
+  pub attestation_object: String,
+}
+
+/// JSON representation of a newly created `PublicKeyCredential`, e.g. as returned by its
+/// `toJSON()`. Binary fields are url-safe Base64 encoded.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct RegistrationCredential {
+  pub id: String,
+  pub response: AttestationResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export)]
+pub struct AssertionResponse {
+  #[serde(rename = "clientDataJSON")]
+  pub client_data_json: String,
+  pub authenticator_data: String,
+  pub signature: String,
+  pub user_handle: Option<String>,
+}
+
+/// JSON representation of an asserted `PublicKeyCredential`, e.g. as returned by its `toJSON()`.
+/// Binary fields are url-safe Base64 encoded.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct AuthenticationCredential {
+  pub id: String,
+  pub response: AssertionResponse,
+}
+
+#[derive(Debug)]
+pub(crate) struct VerifiedRegistration {
+  pub credential_id: Vec<u8>,
+  /// COSE-encoded public key.
+  pub public_key: Vec<u8>,
+  pub sign_count: u32,
+}
+
+#[derive(Debug)]
+pub(crate) struct VerifiedAssertion {
+  pub sign_count: u32,
+  pub user_verified: bool,
+}
+
+/// Deserializes credentials either from nested JSON objects or from their JSON-encoded string
+/// representation. The latter is needed for HTML form submissions.
+pub(crate) fn deserialize_credential<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+  D: serde::Deserializer<'de>,
+  T: serde::de::DeserializeOwned,
+{
+  return match serde_json::Value::deserialize(deserializer)? {
+    serde_json::Value::String(json) => {
+      serde_json::from_str(&json).map_err(serde::de::Error::custom)
+    }
+    value => serde_json::from_value(value).map_err(serde::de::Error::custom),
+  };
+}
+
+pub(crate) fn new_challenge() -> String {
+  let bytes: [u8; 32] = rand::random();
+  return BASE64_URL_SAFE_NO_PAD.encode(bytes);
+}
+
+/// Decodes url-safe Base64 both with and without padding.
+pub(crate) fn decode_b64(input: &str) -> Result<Vec<u8>, WebAuthnError> {
+  return Ok(BASE64_URL_SAFE_NO_PAD.decode(input.trim_end_matches('='))?);
+}
+
+pub(crate) fn verify_registration(
+  rp: &RelyingParty,
+  challenge: &str,
+  credential: &RegistrationCredential,
+) -> Result<VerifiedRegistration, WebAuthnError> {
+  let client_data_json = decode_b64(&credential.response.client_data_json)?;
+  verify_client_data(rp, challenge, "webauthn.create", &client_data_json)?;
+
+  let attestation: CborValue =
+    ciborium::from_reader(decode_b64(&credential.response.attestation_object)?.as_slice())
+      .map_err(|err| WebAuthnError::Cbor(err.to_string()))?;
+  let Some(CborValue::Bytes(auth_data)) = cbor_map_get(&attestation, &CborValue::from("authData"))
+  else {
+    return Err(WebAuthnError::AuthenticatorData("missing authData"));
+  };
+
+  let auth_data = parse_authenticator_data(rp, auth_data)?;
+  let Some((credential_id, public_key)) = auth_data.attested else {
+    return Err(WebAuthnError::AuthenticatorData("missing credential"));
+  };
+
+  if credential_id != decode_b64(&credential.id)? {
+    return Err(WebAuthnError::AuthenticatorData("credential id mismatch"));
+  }
+
+  // Make sure we'll be able to verify future assertions.
+  let _ = CoseKey::parse(&public_key)?;
+
+  return Ok(VerifiedRegistration {
+    credential_id,
+    public_key,
+    sign_count: auth_data.sign_count,
+  });
+}
+
+pub(crate) fn verify_assertion(
+  rp: &RelyingParty,
+  challenge: &str,
+  credential: &AuthenticationCredential,
+  public_key: &[u8],
+  stored_sign_count: u32,
+) -> Result<VerifiedAssertion, WebAuthnError> {
+  let client_data_json = decode_b64(&credential.response.client_data_json)?;
+  verify_client_data(rp, challenge, "webauthn.get", &client_data_json)?;
+
+  let raw_auth_data = decode_b64(&credential.response.authenticator_data)?;
+  let auth_data = parse_authenticator_data(rp, &raw_auth_data)?;
+
+  // The signature is over the authenticator data followed by the hash of the client data.
+  let mut message = raw_auth_data.clone();
+  message.extend_from_slice(&Sha256::digest(&client_data_json));
+
+  let signature = decode_b64(&credential.response.signature)?;
+  CoseKey::parse(public_key)?.verify(&message, &signature)?;
+
+  // Authenticators w/o counter support always report zero. Otherwise, the counter must strictly
+  // increase or the authenticator may have been cloned.
+  if (auth_data.sign_count != 0 || stored_sign_count != 0)
+    && auth_data.sign_count <= stored_sign_count
+  {
+    return Err(WebAuthnError::SignCount);
+  }
+
+  return Ok(VerifiedAssertion {
+    sign_count: auth_data.sign_count,
+    user_verified: auth_data.flags & FLAG_USER_VERIFIED != 0,
+  });
+}
+
+#[derive(Debug, Deserialize)]
+struct ClientData {
+  r#type: String,
+  challenge: String,
+  origin: String,
+}
+
+fn verify_client_data(
+  rp: &RelyingParty,
+  challenge: &str,
+  expected_type: &str,
+  client_data_json: &[u8],
+) -> Result<(), WebAuthnError> {
+  let client_data: ClientData = serde_json::from_slice(client_data_json)?;
+
+  if client_data.r#type != expected_type {
+    return Err(WebAuthnError::ClientData("type mismatch"));
+  }
+  if decode_b64(&client_data.challenge)? != decode_b64(challenge)? {
+    return Err(WebAuthnError::ClientData("challenge mismatch"));
+  }
+  if client_data.origin != rp.origin {
+    return Err(WebAuthnError::ClientData("origin mismatch"));
+  }
+
+  return Ok(());
+}
+
+struct AuthenticatorData {
+  flags: u8,
+  sign_count: u32,
+  /// Credential id and COSE-encoded public key, only present during registration.
+  attested: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Parses the binary authenticator data: https://www.w3.org/TR/webauthn-2/#sctn-authenticator-data.
+fn parse_authenticator_data(
+  rp: &RelyingParty,
+  data: &[u8],
+) -> Result<AuthenticatorData, WebAuthnError> {
+  if data.len() < 37 {
+    return Err(WebAuthnError::AuthenticatorData("too short"));
+  }
+
+  if data[0..32] != Sha256::digest(rp.id.as_bytes())[..] {
+    return Err(WebAuthnError::AuthenticatorData("relying party mismatch"));
+  }
+
+  let flags = data[32];
+  if flags & FLAG_USER_PRESENT == 0 {
+    return Err(WebAuthnError::AuthenticatorData("user not present"));
+  }
+
+  let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+  let attested = if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+    // 16 bytes AAGUID followed by 2 bytes credential id length.
+    let rest = &data[37..];
+    if rest.len() < 18 {
+      return Err(WebAuthnError::AuthenticatorData("truncated credential"));
+    }
+
+    let id_len = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+    let Some(credential_id) = rest.get(18..18 + id_len) else {
+      return Err(WebAuthnError::AuthenticatorData("truncated credential id"));
+    };
+
+    // The public key is a CBOR item of unknown length, potentially followed by extensions.
+    let key_data = &rest[18 + id_len..];
+    let mut reader = key_data;
+    let _: CborValue =
+      ciborium::from_reader(&mut reader).map_err(|err| WebAuthnError::Cbor(err.to_string()))?;
+    let key_len = key_data.len() - reader.len();
+
+    Some((credential_id.to_vec(), key_data[..key_len].to_vec()))
+  } else {
+    None
+  };
+
+  return Ok(AuthenticatorData {
+    flags,
+    sign_count,
+    attested,
+  });
+}
+
+enum CoseKey {
+  Es256(p256::ecdsa::VerifyingKey),
+  Ed25519(ed25519_dalek::VerifyingKey),
+}
+
+impl CoseKey {
+  /// Parses a COSE_Key: https://www.rfc-editor.org/rfc/rfc9053.html#section-7.
+  fn parse(data: &[u8]) -> Result<Self, WebAuthnError> {
+    let key: CborValue =
+      ciborium::from_reader(data).map_err(|err| WebAuthnError::Cbor(err.to_string()))?;
+
+    let int = |label: i64| -> Option<i64> {
+      return cbor_map_get(&key, &CborValue::from(label))
+        .and_then(|v| v.as_integer())
+        .and_then(|i| i64::try_from(i).ok());
+    };
+    let bytes = |label: i64| -> Option<&[u8]> {
+      return cbor_map_get(&key, &CborValue::from(label))
+        .and_then(|v| v.as_bytes())
+        .map(|b| b.as_slice());
+    };
+
+    // Labels: 1 = kty, 3 = alg, -1 = crv, -2 = x, -3 = y.
+    return match (int(1), int(3), int(-1)) {
+      // EC2 key on P-256.
+      (Some(2), Some(COSE_ALG_ES256), Some(1)) => {
+        let (Some(x), Some(y)) = (bytes(-2), bytes(-3)) else {
+          return Err(WebAuthnError::UnsupportedKey);
+        };
+        if x.len() != 32 || y.len() != 32 {
+          return Err(WebAuthnError::UnsupportedKey);
+        }
+
+        let point = p256::EncodedPoint::from_affine_coordinates(
+          p256::FieldBytes::from_slice(x),
+          p256::FieldBytes::from_slice(y),
+          /* compress= */ false,
+        );
+        Ok(Self::Es256(
+          p256::ecdsa::VerifyingKey::from_encoded_point(&point)
+            .map_err(|_| WebAuthnError::UnsupportedKey)?,
+        ))
+      }
+      // OKP key on Ed25519.
+      (Some(1), Some(COSE_ALG_EDDSA), Some(6)) => {
+        let Some(x) = bytes(-2).and_then(|x| <[u8; 32]>::try_from(x).ok()) else {
+          return Err(WebAuthnError::UnsupportedKey);
+        };
+        Ok(Self::Ed25519(
+          ed25519_dalek::VerifyingKey::from_bytes(&x).map_err(|_| WebAuthnError::UnsupportedKey)?,
+        ))
+      }
+      _ => Err(WebAuthnError::UnsupportedKey),
+    };
+  }
+
+  fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), WebAuthnError> {
+    return match self {
+      Self::Es256(key) => {
+        use p256::ecdsa::signature::Verifier;
+
+        // NOTE: WebAuthn ES256 signatures are DER encoded.
+        let signature =
+          p256::ecdsa::Signature::from_der(signature).map_err(|_| WebAuthnError::Signature)?;
+        key
+          .verify(message, &signature)
+          .map_err(|_| WebAuthnError::Signature)
+      }
+      Self::Ed25519(key) => {
+        let signature =
+          ed25519_dalek::Signature::from_slice(signature).map_err(|_| WebAuthnError::Signature)?;
+        key
+          .verify_strict(message, &signature)
+          .map_err(|_| WebAuthnError::Signature)
+      }
+    };
+  }
+}
+
+fn cbor_map_get<'a>(value: &'a CborValue, key: &CborValue) -> Option<&'a CborValue> {
+  return value
+    .as_map()?
+    .iter()
+    .find_map(|(k, v)| (k == key).then_some(v));
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+
+  pub(crate) fn test_rp() -> RelyingParty {
+    return RelyingParty {
+      id: "example.org".to_string(),
+      name: "Test".to_string(),
+      origin: "https://example.org".to_string(),
+    };
+  }
+
+  /// Fake authenticator for tests.
+  pub(crate) struct TestAuthenticator {
+    pub credential_id: Vec<u8>,
+    key: p256::ecdsa::SigningKey,
+    pub sign_count: u32,
+  }
+
+  impl TestAuthenticator {
+    pub(crate) fn new() -> Self {
+      return Self {
+        credential_id: vec![7; 16],
+        key: p256::ecdsa::SigningKey::from_slice(&[42; 32]).unwrap(),
+        sign_count: 0,
+      };
+    }
+
+    fn cose_key(&self) -> Vec<u8> {
+      let point = self.key.verifying_key().to_encoded_point(false);
+      let key = CborValue::Map(vec![
+        (1.into(), 2.into()),
+        (3.into(), COSE_ALG_ES256.into()),
+        ((-1).into(), 1.into()),
+        ((-2).into(), CborValue::Bytes(point.x().unwrap().to_vec())),
+        ((-3).into(), CborValue::Bytes(point.y().unwrap().to_vec())),
+      ]);
+      let mut buffer = vec![];
+      ciborium::into_writer(&key, &mut buffer).unwrap();
+      return buffer;
+    }
+
+    fn auth_data(&self, rp: &RelyingParty, attested: bool) -> Vec<u8> {
+      let mut data = Sha256::digest(rp.id.as_bytes()).to_vec();
+      let flags = FLAG_USER_PRESENT
+        | FLAG_USER_VERIFIED
+        | if attested {
+          FLAG_ATTESTED_CREDENTIAL_DATA
+        } else {
+          0
+        };
+      data.push(flags);
+      data.extend_from_slice(&self.sign_count.to_be_bytes());
+      if attested {
+        data.extend_from_slice(&[0; 16]);
+        data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        data.extend_from_slice(&self.credential_id);
+        data.extend_from_slice(&self.cose_key());
+      }
+      return data;
+    }
+
+    fn client_data(rp: &RelyingParty, r#type: &str, challenge: &str) -> Vec<u8> {
+      return serde_json::to_vec(&serde_json::json!({
+        "type": r#type,
+        "challenge": challenge,
+        "origin": rp.origin,
+      }))
+      .unwrap();
+    }
+
+    pub(crate) fn register(&self, rp: &RelyingParty, challenge: &str) -> RegistrationCredential {
+      let attestation = CborValue::Map(vec![
+        ("fmt".into(), "none".into()),
+        ("attStmt".into(), CborValue::Map(vec![])),
+        (
+          "authData".into(),
+          CborValue::Bytes(self.auth_data(rp, true)),
+        ),
+      ]);
+      let mut attestation_object = vec![];
+      ciborium::into_writer(&attestation, &mut attestation_object).unwrap();
+
+      return RegistrationCredential {
+        id: BASE64_URL_SAFE_NO_PAD.encode(&self.credential_id),
+        response: AttestationResponse {
+          client_data_json: BASE64_URL_SAFE_NO_PAD.encode(Self::client_data(
+            rp,
+            "webauthn.create",
+            challenge,
+          )),
+          attestation_object: BASE64_URL_SAFE_NO_PAD.encode(attestation_object),
+        },
+      };
+    }
+
+    pub(crate) fn assert(
+      &mut self,
+      rp: &RelyingParty,
+      challenge: &str,
+    ) -> AuthenticationCredential {
+      use p256::ecdsa::signature::Signer;
+
+      self.sign_count += 1;
+
+      let auth_data = self.auth_data(rp, false);
+      let client_data = Self::client_data(rp, "webauthn.get", challenge);
+
+      let mut message = auth_data.clone();
+      message.extend_from_slice(&Sha256::digest(&client_data));
+      let signature: p256::ecdsa::Signature = self.key.sign(&message);
+
+      return AuthenticationCredential {
+        id: BASE64_URL_SAFE_NO_PAD.encode(&self.credential_id),
+        response: AssertionResponse {
+          client_data_json: BASE64_URL_SAFE_NO_PAD.encode(client_data),
+          authenticator_data: BASE64_URL_SAFE_NO_PAD.encode(auth_data),
+          signature: BASE64_URL_SAFE_NO_PAD.encode(signature.to_der()),
+          user_handle: None,
+        },
+      };
+    }
+  }
+
+  #[test]
+  fn test_registration_and_assertion() {
+    let rp = test_rp();
+    let mut authenticator = TestAuthenticator::new();
+
+    let challenge = new_challenge();
+    let registration =
+      verify_registration(&rp, &challenge, &authenticator.register(&rp, &challenge)).unwrap();
+    assert_eq!(authenticator.credential_id, registration.credential_id);
+
+    // Wrong challenge.
+    assert!(
+      verify_registration(
+        &rp,
+        &new_challenge(),
+        &authenticator.register(&rp, &challenge)
+      )
+      .is_err()
+    );
+
+    // Wrong relying party.
+    let other_rp = RelyingParty {
+      id: "other.org".to_string(),
+      name: "Other".to_string(),
+      origin: "https://other.org".to_string(),
+    };
+    assert!(
+      verify_registration(
+        &other_rp,
+        &challenge,
+        &authenticator.register(&rp, &challenge)
+      )
+      .is_err()
+    );
+
+    let challenge = new_challenge();
+    let assertion = authenticator.assert(&rp, &challenge);
+    let verified =
+      verify_assertion(&rp, &challenge, &assertion, &registration.public_key, 0).unwrap();
+    assert_eq!(1, verified.sign_count);
+    assert!(verified.user_verified);
+
+    // Replaying the same assertion fails the sign count check.
+    assert!(matches!(
+      verify_assertion(&rp, &challenge, &assertion, &registration.public_key, 1),
+      Err(WebAuthnError::SignCount)
+    ));
+
+    // Tampered signature.
+    let mut tampered = authenticator.assert(&rp, &challenge);
+    tampered.response.authenticator_data = BASE64_URL_SAFE_NO_PAD.encode({
+      let mut data = decode_b64(&tampered.response.authenticator_data).unwrap();
+      data[36] = data[36].wrapping_add(1);
+      data
+    });
+    assert!(matches!(
+      verify_assertion(&rp, &challenge, &tampered, &registration.public_key, 1),
+      Err(WebAuthnError::Signature)
+    ));
+  }
+}
@@ -0,0 +1,119 @@
+//! Well-known discovery endpoints letting external services validate TrailBase auth tokens.
+use axum::extract::{Json, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use axum::{Router, routing::get};
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+use crate::auth::AuthError;
+use crate::auth::oidc::{
+  AUTHORIZE_PATH, SUPPORTED_SCOPES, TOKEN_PATH, USERINFO_PATH, endpoint_url, issuer,
+};
+
+pub(crate) const JWKS_PATH: &str = ".well-known/jwks.json";
+pub(crate) const OPENID_CONFIGURATION_PATH: &str = ".well-known/openid-configuration";
+
+/// OpenID Connect discovery document, see OpenID Connect Discovery 1.0.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenIdConfiguration {
+  pub issuer: String,
+  pub jwks_uri: String,
+  pub authorization_endpoint: String,
+  pub token_endpoint: String,
+  pub userinfo_endpoint: String,
+  pub scopes_supported: Vec<String>,
+  pub response_types_supported: Vec<String>,
+  pub grant_types_supported: Vec<String>,
+  pub code_challenge_methods_supported: Vec<String>,
+  pub token_endpoint_auth_methods_supported: Vec<String>,
+  pub subject_types_supported: Vec<String>,
+  pub id_token_signing_alg_values_supported: Vec<String>,
+  pub claims_supported: Vec<String>,
+}
+
+pub(crate) fn well_known_router() -> Router<AppState> {
+  return Router::new()
+    .route(&format!("/{JWKS_PATH}"), get(jwks_handler))
+    .route(
+      &format!("/{OPENID_CONFIGURATION_PATH}"),
+      get(openid_configuration_handler),
+    );
+}
+
+/// Public keys for validating auth tokens, i.e. the current and retired keys within their grace
+/// period.
+async fn jwks_handler(State(state): State<AppState>) -> Response {
+  return (
+    // Allow verifiers to cache the keys but pick up rotations reasonably quickly.
+    [(header::CACHE_CONTROL, "public, max-age=300")],
+    Json(state.jwt().jwks()),
+  )
+    .into_response();
+}
+
+async fn openid_configuration_handler(
+  State(state): State<AppState>,
+) -> Result<Json<OpenIdConfiguration>, AuthError> {
+  let issuer = issuer(&state)?;
+  let strings = |values: &[&str]| -> Vec<String> {
+    return values.iter().map(|v| v.to_string()).collect();
+  };
+
+  return Ok(Json(OpenIdConfiguration {
+    jwks_uri: format!("{issuer}/{JWKS_PATH}"),
+    authorization_endpoint: endpoint_url(&issuer, AUTHORIZE_PATH),
+    token_endpoint: endpoint_url(&issuer, TOKEN_PATH),
+    userinfo_endpoint: endpoint_url(&issuer, USERINFO_PATH),
+    scopes_supported: strings(SUPPORTED_SCOPES),
+    response_types_supported: strings(&["code"]),
+    grant_types_supported: strings(&["authorization_code"]),
+    code_challenge_methods_supported: strings(&["S256"]),
+    token_endpoint_auth_methods_supported: strings(&[
+      "client_secret_basic",
+      "client_secret_post",
+      "none",
+    ]),
+    subject_types_supported: strings(&["public"]),
+    id_token_signing_alg_values_supported: strings(&["EdDSA"]),
+    claims_supported: strings(&[
+      "iss",
+      "sub",
+      "aud",
+      "iat",
+      "exp",
+      "nonce",
+      "email",
+      "email_verified",
+    ]),
+    issuer,
+  }));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::app_state::test_state;
+
+  #[tokio::test]
+  async fn test_well_known_endpoints() {
+    let state = test_state(None).await.unwrap();
+
+    let jwks = state.jwt().jwks();
+    assert_eq!(jwks.keys.len(), 1);
+    assert_eq!(jwks.keys[0].kid, state.jwt().kid());
+    assert_eq!(jwks.keys[0].kty, "OKP");
+    assert_eq!(jwks.keys[0].crv, "Ed25519");
+
+    let Json(config) = openid_configuration_handler(State(state.clone()))
+      .await
+      .unwrap();
+    assert_eq!(config.issuer, "https://test.org");
+    assert_eq!(config.jwks_uri, "https://test.org/.well-known/jwks.json");
+    assert_eq!(
+      config.token_endpoint,
+      "https://test.org/api/auth/v1/oidc/token"
+    );
+  }
+}
@@ -0,0 +1,367 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use log::*;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use trailbase_sqlite::Connection;
+
+use crate::DataDir;
+use crate::config::proto::{BackupConfig, DatabaseConfig};
+use crate::connection::ConnectionManager;
+use crate::constants::BACKUP_RETENTION_DEFAULT;
+
+#[derive(Debug, Error)]
+pub enum BackupError {
+  #[error("IO error: {0}")]
+  IO(#[from] std::io::Error),
+  #[error("SQLite error: {0}")]
+  Sqlite(#[from] trailbase_sqlite::Error),
+  #[error("Backup not found: {0}")]
+  NotFound(String),
+  #[error("Database in use, stop the server first: {0:?}")]
+  InUse(PathBuf),
+}
+
+/// Format of backup ids, which double as directory names within `<traildepot>/backups/`.
+const ID_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+const DB_EXTENSION: &str = ".db";
+const COMPRESSED_EXTENSION: &str = ".gz";
+
+/// Meta data of a single point-in-time backup.
+#[derive(Clone, Debug)]
+pub struct BackupInfo {
+  pub id: String,
+  pub created: DateTime<Utc>,
+  /// Backed up database files, e.g. "main.db" or "logs.db.gz".
+  pub files: Vec<String>,
+  pub size_bytes: u64,
+}
+
+/// A database to be backed up.
+pub(crate) struct BackupSource {
+  /// Name of the database file within `<traildepot>/data/` without extension, e.g. "main".
+  pub name: String,
+  /// Schema name of the database within `conn`, e.g. "main" or an attached database.
+  pub schema_name: String,
+  pub conn: Connection,
+}
+
+/// Collects all databases to be backed up: main, all configured databases, logs and session.
+pub(crate) fn backup_sources(
+  connection_manager: &ConnectionManager,
+  logs_conn: &Connection,
+  session_conn: &Connection,
+  databases: &[DatabaseConfig],
+) -> Vec<BackupSource> {
+  let mut sources = vec![BackupSource {
+    name: "main".to_string(),
+    schema_name: "main".to_string(),
+    conn: (*connection_manager.main_entry().connection).clone(),
+  }];
+
+  for name in databases.iter().flat_map(|d| d.name.as_ref()) {
+    match connection_manager.get_entry(false, Some([name.clone()].into())) {
+      Ok(entry) => sources.push(BackupSource {
+        name: name.clone(),
+        schema_name: name.clone(),
+        conn: (*entry.connection).clone(),
+      }),
+      Err(err) => {
+        warn!("Skipping backup of '{name}': {err}");
+      }
+    };
+  }
+
+  sources.extend([
+    BackupSource {
+      name: "logs".to_string(),
+      schema_name: "main".to_string(),
+      conn: logs_conn.clone(),
+    },
+    BackupSource {
+      name: "session".to_string(),
+      schema_name: "main".to_string(),
+      conn: session_conn.clone(),
+    },
+  ]);
+
+  return sources;
+}
+
+/// Creates a new timestamped backup of the given databases and prunes old backups according to
+/// the configured retention. Returns the new backup's id.
+pub(crate) async fn create_backup(
+  data_dir: &DataDir,
+  sources: Vec<BackupSource>,
+  config: &BackupConfig,
+) -> Result<String, BackupError> {
+  let id = Utc::now().format(ID_FORMAT).to_string();
+  let backup_path = data_dir.backup_path();
+
+  // Write into a temporary directory first to not end up with partial backups.
+  let tmp_dir = backup_path.join(format!(".{id}.tmp"));
+  tokio::fs::create_dir_all(&tmp_dir).await?;
+
+  if let Err(err) = write_backup(&tmp_dir, sources, config.compress.unwrap_or(false)).await {
+    let _ = tokio::fs::remove_dir_all(&tmp_dir).await;
+    return Err(err);
+  }
+
+  tokio::fs::rename(&tmp_dir, backup_path.join(&id)).await?;
+
+  let retention = config
+    .retention
+    .map_or(BACKUP_RETENTION_DEFAULT, |r| r as usize);
+  if retention > 0 {
+    let data_dir = data_dir.clone();
+    tokio::task::spawn_blocking(move || prune_backups(&data_dir, retention))
+      .await
+      .map_err(std::io::Error::other)??;
+  }
+
+  return Ok(id);
+}
+
+async fn write_backup(
+  dir: &Path,
+  sources: Vec<BackupSource>,
+  compress: bool,
+) -> Result<(), BackupError> {
+  for source in sources {
+    let path = dir.join(format!("{}{DB_EXTENSION}", source.name));
+    source
+      .conn
+      .backup_database(&source.schema_name, &path)
+      .await?;
+
+    if compress {
+      tokio::task::spawn_blocking(move || compress_file(&path))
+        .await
+        .map_err(std::io::Error::other)??;
+    }
+  }
+  return Ok(());
+}
+
+/// Lists all available backups, oldest first.
+pub fn list_backups(data_dir: &DataDir) -> Result<Vec<BackupInfo>, BackupError> {
+  let backup_path = data_dir.backup_path();
+  if !backup_path.exists() {
+    return Ok(vec![]);
+  }
+
+  let mut backups: Vec<BackupInfo> = vec![];
+  for entry in std::fs::read_dir(&backup_path)? {
+    let entry = entry?;
+    if !entry.file_type()?.is_dir() {
+      continue;
+    }
+
+    // Skips anything that doesn't look like a backup, e.g. in-progress temporary directories.
+    let id = entry.file_name().to_string_lossy().to_string();
+    let Ok(created) = NaiveDateTime::parse_from_str(&id, ID_FORMAT) else {
+      continue;
+    };
+
+    let mut files: Vec<String> = vec![];
+    let mut size_bytes: u64 = 0;
+    for file in std::fs::read_dir(entry.path())? {
+      let file = file?;
+      let name = file.file_name().to_string_lossy().to_string();
+      if name.ends_with(DB_EXTENSION)
+        || name.ends_with(&format!("{DB_EXTENSION}{COMPRESSED_EXTENSION}"))
+      {
+        size_bytes += file.metadata()?.len();
+        files.push(name);
+      }
+    }
+    files.sort();
+
+    backups.push(BackupInfo {
+      id,
+      created: created.and_utc(),
+      files,
+      size_bytes,
+    });
+  }
+
+  backups.sort_by(|a, b| a.created.cmp(&b.created));
+
+  return Ok(backups);
+}
+
+/// Restores the backup with the given id by replacing the corresponding databases in
+/// `<traildepot>/data/`. Must only be run while the server is stopped.
+///
+/// Returns the paths of the restored databases.
+pub fn restore_backup(
+  data_dir: &DataDir,
+  id: &str,
+  force: bool,
+) -> Result<Vec<PathBuf>, BackupError> {
+  // NOTE: Only accept ids of existing backups, thus avoiding any path shenanigans.
+  let Some(backup) = list_backups(data_dir)?.into_iter().find(|b| b.id == id) else {
+    return Err(BackupError::NotFound(id.to_string()));
+  };
+
+  let backup_dir = data_dir.backup_path().join(&backup.id);
+  let data_path = data_dir.data_path();
+
+  let targets: Vec<(PathBuf, PathBuf)> = backup
+    .files
+    .iter()
+    .map(|file| {
+      let db_file = file.strip_suffix(COMPRESSED_EXTENSION).unwrap_or(file);
+      return (backup_dir.join(file), data_path.join(db_file));
+    })
+    .collect();
+
+  // A present WAL indicates that the database is either in use or wasn't shut down cleanly.
+  // Either way, blindly replacing the database file would be unsafe.
+  if !force {
+    for (_src, dst) in &targets {
+      let wal = with_suffix(dst, "-wal");
+      if wal.exists() {
+        return Err(BackupError::InUse(dst.clone()));
+      }
+    }
+  }
+
+  std::fs::create_dir_all(&data_path)?;
+
+  let mut restored: Vec<PathBuf> = vec![];
+  for (src, dst) in targets {
+    let tmp = with_suffix(&dst, ".restore");
+    if src.to_string_lossy().ends_with(COMPRESSED_EXTENSION) {
+      decompress_file(&src, &tmp)?;
+    } else {
+      std::fs::copy(&src, &tmp)?;
+    }
+
+    // Remove any stale WAL and shared-memory files, which don't belong to the restored database.
+    for suffix in ["-wal", "-shm"] {
+      let path = with_suffix(&dst, suffix);
+      if path.exists() {
+        std::fs::remove_file(path)?;
+      }
+    }
+
+    std::fs::rename(&tmp, &dst)?;
+    restored.push(dst);
+  }
+
+  return Ok(restored);
+}
+
+fn prune_backups(data_dir: &DataDir, retention: usize) -> Result<(), BackupError> {
+  let backups = list_backups(data_dir)?;
+  let num_stale = backups.len().saturating_sub(retention);
+
+  for backup in backups.into_iter().take(num_stale) {
+    debug!("Removing stale backup: {}", backup.id);
+    std::fs::remove_dir_all(data_dir.backup_path().join(&backup.id))?;
+  }
+
+  return Ok(());
+}
+
+fn compress_file(path: &Path) -> std::io::Result<()> {
+  let dst = with_suffix(path, COMPRESSED_EXTENSION);
+  let mut encoder = flate2::write::GzEncoder::new(
+    std::fs::File::create_new(&dst)?,
+    flate2::Compression::default(),
+  );
+  std::io::copy(&mut std::fs::File::open(path)?, &mut encoder)?;
+  encoder.finish()?.sync_all()?;
+
+  return std::fs::remove_file(path);
+}
+
+fn decompress_file(src: &Path, dst: &Path) -> std::io::Result<()> {
+  let mut decoder = flate2::read::GzDecoder::new(std::fs::File::open(src)?);
+  let mut file = std::fs::File::create(dst)?;
+  std::io::copy(&mut decoder, &mut file)?;
+
+  return file.sync_all();
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+  let mut path = path.as_os_str().to_owned();
+  path.push(suffix);
+  return path.into();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app_state::test_state;
+
+  #[tokio::test]
+  async fn test_backup_and_restore() {
+    let state = test_state(None).await.unwrap();
+    let data_dir = state.data_dir().clone();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE backup_test (id INTEGER PRIMARY KEY) STRICT;
+          INSERT INTO backup_test (id) VALUES (1), (2);
+        "#,
+      )
+      .await
+      .unwrap();
+
+    let sources = || {
+      return vec![BackupSource {
+        name: "main".to_string(),
+        schema_name: "main".to_string(),
+        conn: state.conn().clone(),
+      }];
+    };
+
+    let mut ids: Vec<String> = vec![];
+    for compress in [false, true, true] {
+      let id = create_backup(
+        &data_dir,
+        sources(),
+        &BackupConfig {
+          retention: Some(2),
+          compress: Some(compress),
+        },
+      )
+      .await
+      .unwrap();
+      ids.push(id);
+
+      // Make sure ids are unique.
+      tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+    }
+
+    // The oldest, uncompressed backup got pruned.
+    let backups = list_backups(&data_dir).unwrap();
+    assert_eq!(
+      backups.iter().map(|b| b.id.clone()).collect::<Vec<_>>(),
+      ids[1..]
+    );
+    assert_eq!(backups[0].files, vec!["main.db.gz".to_string()]);
+    assert!(backups[0].size_bytes > 0);
+
+    assert!(matches!(
+      restore_backup(&data_dir, &ids[0], false),
+      Err(BackupError::NotFound(_))
+    ));
+    assert!(matches!(
+      restore_backup(&data_dir, "../data", false),
+      Err(BackupError::NotFound(_))
+    ));
+
+    let restored = restore_backup(&data_dir, &ids[2], true).unwrap();
+    assert_eq!(restored, vec![data_dir.data_path().join("main.db")]);
+
+    let conn = rusqlite::Connection::open(&restored[0]).unwrap();
+    let count: i64 = conn
+      .query_row("SELECT COUNT(*) FROM backup_test", (), |row| row.get(0))
+      .unwrap();
+    assert_eq!(count, 2);
+  }
+}
@@ -13,9 +13,10 @@ use validator::{ValidateEmail, ValidateUrl};
 
 use crate::DESCRIPTOR_POOL;
 use crate::auth::oauth::providers::oauth_providers_static_registry;
+use crate::auth::saml::SamlProvider;
 use crate::connection::ConnectionManager;
 use crate::data_dir::DataDir;
-use crate::records::validate_record_api_config;
+use crate::records::{validate_image_transforms, validate_record_api_config};
 
 #[derive(Debug, Error)]
 pub enum ConfigError {
@@ -405,6 +406,19 @@ pub(crate) fn maybe_load_config_textproto_unverified(
   };
 }
 
+/// Loads the config and merges in secrets from the vault and env. Unlike
+/// `load_or_init_config_textproto`, doesn't validate the config against the database schemas,
+/// e.g. for use in CLI commands while the server is stopped.
+pub(crate) fn maybe_load_merged_config_unverified(
+  data_dir: &DataDir,
+) -> Result<Option<proto::Config>, ConfigError> {
+  let Some(config) = maybe_load_config_textproto_unverified(data_dir)? else {
+    return Ok(None);
+  };
+  let vault = load_vault_textproto_or_default(data_dir)?;
+  return Ok(Some(merge_vault_and_env(config, vault)?));
+}
+
 // TODO: Initialization order is currently borked and worked-around by rebuilding
 // ConnectionMetadata. Specifically, building SchemaMatadataCache, which contains JSON metadata,
 // requires custom JSON schemas to be built from the config and globally registered. However,
@@ -550,6 +564,8 @@ pub fn validate_config(
     }
   }
 
+  validate_image_transforms("Avatar", &config.auth.avatar_image_transforms)?;
+
   // Check OAuth.
   if !config.auth.oauth_providers.is_empty() && site_url.is_none() {
     info!(
@@ -557,6 +573,19 @@ pub fn validate_config(
     );
   }
 
+  // Check SAML.
+  if !config.auth.saml_providers.is_empty() && site_url.is_none() {
+    info!(
+      "SAML requires a public URL for responses from identity providers but `config.server.site_url` not set. May have been provided via `--public-url` instead"
+    );
+  }
+
+  for (name, provider) in &config.auth.saml_providers {
+    if let Err(err) = SamlProvider::from_config(name, provider) {
+      return ierr(format!("Invalid SAML provider '{name}': {err}"));
+    }
+  }
+
   for (name, provider) in &config.auth.oauth_providers {
     let provider_id: OAuthProviderId = provider
       .provider_id
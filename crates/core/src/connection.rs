@@ -68,6 +68,7 @@ struct ConnectionManagerState {
   data_dir: DataDir,
   json_schema_registry: Arc<RwLock<trailbase_schema::registry::JsonSchemaRegistry>>,
   sqlite_function_runtimes: Vec<(SqliteStore, SqliteFunctions)>,
+  replicated: bool,
 
   // Properties for caching connections:
   main: RwLock<ConnectionEntry>,
@@ -88,6 +89,7 @@ impl ConnectionManager {
     data_dir: DataDir,
     json_schema_registry: Arc<RwLock<trailbase_schema::registry::JsonSchemaRegistry>>,
     sqlite_function_runtimes: Vec<(SqliteStore, SqliteFunctions)>,
+    replicated: bool,
   ) -> Result<(Self, bool), ConnectionError> {
     let (main_conn, main_metadata, new_db) = init_main_db_impl(
       Some(&data_dir),
@@ -95,6 +97,7 @@ impl ConnectionManager {
       vec![],
       sqlite_function_runtimes.clone(),
       true,
+      replicated,
     )?;
 
     return Ok((
@@ -103,6 +106,7 @@ impl ConnectionManager {
           data_dir,
           json_schema_registry,
           sqlite_function_runtimes,
+          replicated,
           main: RwLock::new(ConnectionEntry {
             connection: Arc::new(main_conn),
             metadata: Arc::new(main_metadata),
@@ -126,6 +130,7 @@ impl ConnectionManager {
       vec![],
       sqlite_function_runtimes.clone(),
       true,
+      false,
     )
     .unwrap();
     assert!(new_db);
@@ -135,6 +140,7 @@ impl ConnectionManager {
         data_dir,
         json_schema_registry,
         sqlite_function_runtimes,
+        replicated: false,
         main: RwLock::new(ConnectionEntry {
           connection: Arc::new(main_conn),
           metadata: Arc::new(main_metadata),
@@ -227,6 +233,7 @@ impl ConnectionManager {
       attach,
       self.state.sqlite_function_runtimes.clone(),
       main,
+      self.state.replicated,
     )?;
 
     return Ok(ConnectionEntry {
@@ -281,7 +288,14 @@ pub fn init_main_db(
     return Err(ConnectionError::Other("Too many databases".into()));
   }
 
-  return init_main_db_impl(data_dir, json_registry, attached_databases, runtimes, true);
+  return init_main_db_impl(
+    data_dir,
+    json_registry,
+    attached_databases,
+    runtimes,
+    true,
+    false,
+  );
 }
 
 fn init_main_db_impl(
@@ -290,6 +304,7 @@ fn init_main_db_impl(
   attach: Vec<AttachedDatabase>,
   runtimes: Vec<(SqliteStore, SqliteFunctions)>,
   main_migrations: bool,
+  replicated: bool,
 ) -> Result<(Connection, ConnectionMetadata, bool), ConnectionError> {
   let main_path = data_dir.map(|d| d.main_db_path());
   let migrations_path = data_dir.map(|d| d.migrations_path());
@@ -327,6 +342,11 @@ fn init_main_db_impl(
       // The default is just 16.
       conn.set_prepared_statement_cache_capacity(PREPARED_STATEMENT_CACHE_CAPACITY);
 
+      if replicated {
+        // Leave checkpointing to replication, which only checkpoints already replicated frames.
+        conn.pragma_update(None, "wal_autocheckpoint", 0)?;
+      }
+
       #[cfg(any(feature = "geos", feature = "geos-static"))]
       litegis::register(&conn)?;
 
@@ -402,9 +422,7 @@ fn init_main_db_impl(
   ));
 }
 
-pub(super) fn init_logs_db(
-  data_dir: Option<&DataDir>,
-) -> Result<Connection, trailbase_sqlite::Error> {
+pub fn init_logs_db(data_dir: Option<&DataDir>) -> Result<Connection, trailbase_sqlite::Error> {
   let path = data_dir.map(|d| d.logs_db_path());
 
   return trailbase_sqlite::Connection::with_opts(
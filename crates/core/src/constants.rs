@@ -4,13 +4,50 @@ pub const SQLITE_SCHEMA_TABLE: &str = "sqlite_schema";
 pub const USER_TABLE: &str = "_user";
 
 pub(crate) const LOGS_TABLE: &str = "_logs";
+pub(crate) const AUDIT_LOG_TABLE: &str = "_audit_log";
+pub(crate) const AUDIT_LOG_CHECKPOINT_TABLE: &str = "_audit_log_checkpoint";
 pub(crate) const SESSION_TABLE: &str = "_session";
 pub(crate) const AVATAR_TABLE: &str = "_user_avatar";
 pub(crate) const AUTHORIZATION_CODE_TABLE: &str = "_authorization_code";
 pub(crate) const OTP_CODE_TABLE: &str = "_otp_code";
+pub(crate) const WEBAUTHN_CREDENTIAL_TABLE: &str = "_webauthn_credential";
+pub(crate) const WEBAUTHN_CHALLENGE_TABLE: &str = "_webauthn_challenge";
+pub(crate) const API_KEY_TABLE: &str = "_api_key";
+pub(crate) const ROLE_TABLE: &str = "_role";
+pub(crate) const USER_ROLE_TABLE: &str = "_user_role";
+pub(crate) const USER_IDENTITY_TABLE: &str = "_user_identity";
+pub(crate) const OAUTH_CLIENT_TABLE: &str = "_oauth_client";
+pub(crate) const PENDING_UPLOAD_TABLE: &str = "_pending_upload";
+pub(crate) const TUS_UPLOAD_TABLE: &str = "_tus_upload";
 
 pub(crate) const LOGS_TABLE_ID_COLUMN: &str = "id";
 pub const LOGS_RETENTION_DEFAULT: Duration = Duration::days(7);
+pub const AUDIT_LOG_RETENTION_DEFAULT: Duration = Duration::days(90);
+pub(crate) const BACKUP_RETENTION_DEFAULT: usize = 7;
+pub(crate) const REPLICATION_INTERVAL_DEFAULT: std::time::Duration =
+  std::time::Duration::from_secs(1);
+pub(crate) const REPLICATION_SNAPSHOT_INTERVAL_DEFAULT: std::time::Duration =
+  std::time::Duration::from_secs(24 * 60 * 60);
+pub(crate) const REPLICATION_PREFIX_DEFAULT: &str = "replication";
+pub(crate) const REPLICATION_RETENTION_DEFAULT: usize = 7;
+/// Number of WAL frames after which replication checkpoints, same as SQLite's default
+/// `wal_autocheckpoint`.
+pub(crate) const REPLICATION_CHECKPOINT_THRESHOLD: u64 = 1000;
+pub(crate) const PRESIGNED_UPLOAD_URL_TTL: std::time::Duration =
+  std::time::Duration::from_secs(60 * 60);
+/// Time clients have to complete a presigned upload, i.e. how long pending uploads are kept around
+/// before they get cleaned up.
+pub(crate) const PENDING_UPLOAD_TTL: Duration = Duration::hours(24);
+/// Largest object S3 accepts with a single PUT.
+pub(crate) const PRESIGNED_UPLOAD_MAX_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+/// Time after the last received chunk before resumable uploads are considered abandoned.
+pub(crate) const TUS_UPLOAD_TTL: Duration = Duration::hours(24);
+pub(crate) const TUS_UPLOAD_MAX_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+/// Object store prefix under which derived image variants, e.g. thumbnails, are cached.
+pub(crate) const IMAGE_VARIANTS_PREFIX: &str = "variants";
+/// Largest source image to transform, since transformations happen in memory.
+pub(crate) const IMAGE_TRANSFORM_MAX_SOURCE_SIZE: u64 = 32 * 1024 * 1024;
+pub(crate) const IMAGE_TRANSFORM_MAX_DIMENSION: u32 = 4096;
 
 pub const COOKIE_AUTH_TOKEN: &str = "auth_token";
 pub const COOKIE_REFRESH_TOKEN: &str = "refresh_token";
@@ -27,11 +64,23 @@ pub const DEFAULT_AUTH_TOKEN_TTL: Duration =
 pub(crate) const DEFAULT_AUTHORIZATION_CODE_TTL: Duration = Duration::minutes(5);
 
 pub(crate) const DEFAULT_MFA_TOKEN_TTL: Duration = Duration::minutes(2);
+pub(crate) const DEFAULT_WEBAUTHN_CHALLENGE_TTL: Duration = Duration::minutes(5);
 pub(crate) const DEFAULT_REFRESH_TOKEN_TTL: Duration = Duration::days(30);
 
 pub(crate) const VERIFICATION_CODE_LENGTH: usize = 24;
 pub(crate) const REFRESH_TOKEN_LENGTH: usize = 32;
 
+/// Prefix distinguishing API keys from JWT auth tokens in "Authorization: Bearer" headers.
+pub const API_KEY_PREFIX: &str = "tbk_";
+pub(crate) const API_KEY_LENGTH: usize = 40;
+/// TTL of the ephemeral auth token claims an API key is exchanged for on each request.
+pub(crate) const API_KEY_AUTH_TOKEN_TTL: Duration = Duration::minutes(5);
+
+pub(crate) const OAUTH_CLIENT_ID_LENGTH: usize = 24;
+pub(crate) const OAUTH_CLIENT_SECRET_LENGTH: usize = 40;
+/// Time users have to approve or deny a registered OAuth client's sign-in request.
+pub(crate) const DEFAULT_OIDC_CONSENT_TTL: Duration = Duration::minutes(10);
+
 // Public APIs
 pub const RECORD_API_PATH: &str = "api/records/v1";
 pub const TRANSACTION_API_PATH: &str = "api/transaction/v1";
@@ -59,6 +59,11 @@ impl DataDir {
     return self.0.join("uploads/");
   }
 
+  /// Incomplete resumable uploads.
+  pub fn partial_uploads_path(&self) -> PathBuf {
+    return self.data_path().join("partial_uploads/");
+  }
+
   pub fn key_path(&self) -> PathBuf {
     return self.secrets_path().join("keys/");
   }
@@ -70,6 +75,7 @@ impl DataDir {
       self.backup_path(),
       self.migrations_path().join("main"),
       self.uploads_path(),
+      self.partial_uploads_path(),
       self.key_path(),
       self.root().join("wasm/"),
     ];
@@ -11,14 +11,18 @@ pub mod records;
 pub mod util;
 
 mod admin;
+mod audit;
 mod auth;
+mod backup;
 mod connection;
 mod data_dir;
 mod email;
 mod encryption;
 mod extract;
 mod listing;
+mod metrics;
 mod migrations;
+mod replication;
 mod scheduler;
 mod schema_metadata;
 mod server;
@@ -84,8 +88,15 @@ mod wasm {
     _components_path: PathBuf,
     _fs_root_path: Option<&std::path::Path>,
     _dev: bool,
-  ) -> Result<Vec<(SqliteStore, SqliteFunctions)>, AnyError> {
-    return Ok(vec![]);
+  ) -> Result<
+    (
+      Vec<(SqliteStore, SqliteFunctions)>,
+      crate::records::hooks::RecordHooks,
+      crate::auth::claims::CustomClaimsHooks,
+    ),
+    AnyError,
+  > {
+    return Ok((vec![], Default::default(), Default::default()));
   }
 
   #[cfg(not(feature = "wasm"))]
@@ -134,12 +145,17 @@ pub mod openapi {
 }
 
 pub mod api {
-  pub use crate::admin::user::{CreateUserRequest, create_user_handler};
-  pub use crate::auth::{AuthTokenClaims, JwtHelper, cli};
-  pub use crate::connection::{Connection, init_main_db, init_session_db};
+  pub use crate::admin::user::{CreateUserRequest, create_user};
+  pub use crate::audit::AuditLog;
+  pub use crate::auth::jwt::{KeyInfo, rotate_keys};
+  pub use crate::auth::oidc::client::OAuthClient;
+  pub use crate::auth::{AuthTokenClaims, JwtHelper, api_key, cli};
+  pub use crate::backup::{BackupError, BackupInfo, list_backups, restore_backup};
+  pub use crate::connection::{Connection, init_logs_db, init_main_db, init_session_db};
   pub use crate::email::{Email, EmailError};
   pub use crate::migrations::new_unique_migration_filename;
   pub use crate::records::json_schema::build_api_json_schema;
+  pub use crate::replication::{ReplicationError, restore_from_replica};
   pub use crate::schema_metadata::ConnectionMetadata;
   pub use crate::server::{InitArgs, init_app_state, serve};
 
@@ -44,6 +44,13 @@ pub(crate) fn build_filter_where_clause(
       )));
     }
 
+    // NOTE: Full-text search requires joining the FTS5 index and has to be handled by the caller.
+    if column_op_value.op == trailbase_qs::CompareOp::Match {
+      return Err(WhereClauseError::NotImplemented(format!(
+        "$match on: {column_name}"
+      )));
+    }
+
     return Ok(());
   })?;
 
@@ -0,0 +1,443 @@
+//! Prometheus metrics exposed via `/metrics` in the text-based exposition format.
+//!
+//! HTTP requests and WASM invocations are recorded as they happen. Everything else, e.g. SQLite
+//! queue depths or job results, is sampled on scrape.
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use crate::app_state::AppState;
+use crate::constants::RECORD_API_PATH;
+
+/// Request latency buckets in seconds, i.e. Prometheus' defaults.
+const LATENCY_BUCKETS: [f64; 11] = [
+  0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct RouteLabels {
+  method: String,
+  route: String,
+  record_api: Option<String>,
+}
+
+#[derive(Default)]
+struct Histogram {
+  buckets: [u64; LATENCY_BUCKETS.len()],
+  sum: f64,
+  count: u64,
+}
+
+impl Histogram {
+  fn observe(&mut self, value: f64) {
+    for (bucket, le) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+      if value <= le {
+        *bucket += 1;
+      }
+    }
+    self.sum += value;
+    self.count += 1;
+  }
+}
+
+#[derive(Default)]
+struct Metrics {
+  requests: Mutex<HashMap<(RouteLabels, u16), u64>>,
+  latencies: Mutex<HashMap<RouteLabels, Histogram>>,
+  wasm_invocations: Mutex<HashMap<(&'static str, &'static str), u64>>,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// Kind of WASM component invocation.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+pub(crate) enum WasmInvocation {
+  Http,
+  Job,
+  RecordHook,
+  CustomClaims,
+}
+
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+pub(crate) fn record_wasm_invocation(kind: WasmInvocation, success: bool) {
+  let kind = match kind {
+    WasmInvocation::Http => "http",
+    WasmInvocation::Job => "job",
+    WasmInvocation::RecordHook => "record_hook",
+    WasmInvocation::CustomClaims => "custom_claims",
+  };
+  let result = if success { "success" } else { "failure" };
+
+  *METRICS
+    .wasm_invocations
+    .lock()
+    .entry((kind, result))
+    .or_default() += 1;
+}
+
+fn record_request(labels: RouteLabels, status: StatusCode, latency: Duration) {
+  *METRICS
+    .requests
+    .lock()
+    .entry((labels.clone(), status.as_u16()))
+    .or_default() += 1;
+
+  METRICS
+    .latencies
+    .lock()
+    .entry(labels)
+    .or_default()
+    .observe(latency.as_secs_f64());
+}
+
+/// Middleware counting requests and their latencies per route and Record API.
+pub(crate) async fn track_request_metrics(
+  State(state): State<AppState>,
+  matched_path: Option<MatchedPath>,
+  req: Request,
+  next: Next,
+) -> Response {
+  // NOTE: Label by route rather than path to keep the cardinality bounded. For the same reason,
+  // only existing Record APIs are labeled.
+  let route = matched_path.map_or_else(|| "unmatched".to_string(), |p| p.as_str().to_string());
+  let record_api = req
+    .uri()
+    .path()
+    .strip_prefix(&format!("/{RECORD_API_PATH}/"))
+    .and_then(|rest| rest.split('/').next())
+    .filter(|name| state.lookup_record_api(name).is_some())
+    .map(|name| name.to_string());
+  let labels = RouteLabels {
+    method: req.method().to_string(),
+    route,
+    record_api,
+  };
+
+  let start = Instant::now();
+  let response = next.run(req).await;
+  record_request(labels, response.status(), start.elapsed());
+
+  return response;
+}
+
+pub(crate) async fn metrics_handler(State(state): State<AppState>) -> Response {
+  return (
+    StatusCode::OK,
+    [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+    render(&state),
+  )
+    .into_response();
+}
+
+fn render(state: &AppState) -> String {
+  let mut out = String::new();
+
+  // HTTP requests.
+  {
+    describe(
+      &mut out,
+      "trailbase_http_requests_total",
+      "counter",
+      "Number of HTTP requests.",
+    );
+    let requests = METRICS.requests.lock();
+    let mut entries: Vec<_> = requests.iter().collect();
+    entries.sort();
+    for ((labels, status), count) in entries {
+      let status = status.to_string();
+      sample(
+        &mut out,
+        "trailbase_http_requests_total",
+        &[route_labels(labels), vec![("status", status.as_str())]].concat(),
+        *count,
+      );
+    }
+  }
+
+  {
+    const NAME: &str = "trailbase_http_request_duration_seconds";
+    describe(
+      &mut out,
+      NAME,
+      "histogram",
+      "Latency of HTTP requests in seconds.",
+    );
+    let latencies = METRICS.latencies.lock();
+    let mut entries: Vec<_> = latencies.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    for (labels, histogram) in entries {
+      let labels = route_labels(labels);
+      for (le, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+        let le = le.to_string();
+        sample(
+          &mut out,
+          &format!("{NAME}_bucket"),
+          &[labels.clone(), vec![("le", le.as_str())]].concat(),
+          count,
+        );
+      }
+      sample(
+        &mut out,
+        &format!("{NAME}_bucket"),
+        &[labels.clone(), vec![("le", "+Inf")]].concat(),
+        histogram.count,
+      );
+      sample(&mut out, &format!("{NAME}_sum"), &labels, histogram.sum);
+      sample(&mut out, &format!("{NAME}_count"), &labels, histogram.count);
+    }
+  }
+
+  // SQLite.
+  {
+    let stats = [
+      ("main", state.conn().stats()),
+      ("logs", state.logs_conn().stats()),
+      ("session", state.session_conn().stats()),
+    ];
+
+    describe(
+      &mut out,
+      "trailbase_sqlite_write_queue_depth",
+      "gauge",
+      "Number of queued write operations.",
+    );
+    for (db, stats) in &stats {
+      sample(
+        &mut out,
+        "trailbase_sqlite_write_queue_depth",
+        &[("database", *db)],
+        stats.write_queue_depth,
+      );
+    }
+
+    describe(
+      &mut out,
+      "trailbase_sqlite_read_queue_depth",
+      "gauge",
+      "Number of queued read operations.",
+    );
+    for (db, stats) in &stats {
+      sample(
+        &mut out,
+        "trailbase_sqlite_read_queue_depth",
+        &[("database", *db)],
+        stats.read_queue_depth,
+      );
+    }
+
+    describe(
+      &mut out,
+      "trailbase_sqlite_write_lock_wait_seconds_total",
+      "counter",
+      "Time the writer spent waiting for the connection lock in seconds.",
+    );
+    for (db, stats) in &stats {
+      sample(
+        &mut out,
+        "trailbase_sqlite_write_lock_wait_seconds_total",
+        &[("database", *db)],
+        stats.write_lock_wait.as_secs_f64(),
+      );
+    }
+
+    describe(
+      &mut out,
+      "trailbase_sqlite_write_lock_acquisitions_total",
+      "counter",
+      "Number of times the writer acquired the connection lock.",
+    );
+    for (db, stats) in &stats {
+      sample(
+        &mut out,
+        "trailbase_sqlite_write_lock_acquisitions_total",
+        &[("database", *db)],
+        stats.write_lock_acquisitions,
+      );
+    }
+  }
+
+  // Subscriptions.
+  {
+    let manager = state.subscription_manager();
+    describe(
+      &mut out,
+      "trailbase_subscriptions_active",
+      "gauge",
+      "Number of active record and table subscriptions.",
+    );
+    sample(
+      &mut out,
+      "trailbase_subscriptions_active",
+      &[("kind", "record")],
+      manager.num_record_subscriptions(),
+    );
+    sample(
+      &mut out,
+      "trailbase_subscriptions_active",
+      &[("kind", "table")],
+      manager.num_table_subscriptions(),
+    );
+  }
+
+  // Jobs.
+  {
+    let registry = state.jobs();
+    let mut jobs: Vec<_> = registry.jobs.lock().values().cloned().collect();
+    jobs.sort_by_key(|job| job.id);
+
+    describe(
+      &mut out,
+      "trailbase_job_runs_total",
+      "counter",
+      "Number of job runs by result.",
+    );
+    for job in &jobs {
+      let name = job.name();
+      let (successes, failures) = job.run_counts();
+      for (result, count) in [("success", successes), ("failure", failures)] {
+        sample(
+          &mut out,
+          "trailbase_job_runs_total",
+          &[("job", name.as_str()), ("result", result)],
+          count,
+        );
+      }
+    }
+
+    describe(
+      &mut out,
+      "trailbase_job_last_run_duration_seconds",
+      "gauge",
+      "Duration of the latest job run in seconds.",
+    );
+    for job in &jobs {
+      if let Some((_start, duration, _error)) = job.latest() {
+        sample(
+          &mut out,
+          "trailbase_job_last_run_duration_seconds",
+          &[("job", job.name().as_str())],
+          duration.num_milliseconds() as f64 / 1000.0,
+        );
+      }
+    }
+  }
+
+  // WASM.
+  {
+    describe(
+      &mut out,
+      "trailbase_wasm_invocations_total",
+      "counter",
+      "Number of WASM component invocations by kind and result.",
+    );
+    let invocations = METRICS.wasm_invocations.lock();
+    let mut entries: Vec<_> = invocations.iter().collect();
+    entries.sort();
+    for ((kind, result), count) in entries {
+      sample(
+        &mut out,
+        "trailbase_wasm_invocations_total",
+        &[("kind", kind), ("result", result)],
+        *count,
+      );
+    }
+  }
+
+  return out;
+}
+
+fn route_labels(labels: &RouteLabels) -> Vec<(&'static str, &str)> {
+  return vec![
+    ("method", labels.method.as_str()),
+    ("route", labels.route.as_str()),
+    (
+      "record_api",
+      labels.record_api.as_deref().unwrap_or_default(),
+    ),
+  ];
+}
+
+fn describe(out: &mut String, name: &str, kind: &str, help: &str) {
+  let _ = writeln!(out, "# HELP {name} {help}");
+  let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: impl std::fmt::Display) {
+  out.push_str(name);
+  if !labels.is_empty() {
+    out.push('{');
+    for (i, (key, value)) in labels.iter().enumerate() {
+      if i > 0 {
+        out.push(',');
+      }
+      let _ = write!(out, "{key}=\"{}\"", escape_label_value(value));
+    }
+    out.push('}');
+  }
+  let _ = writeln!(out, " {value}");
+}
+
+fn escape_label_value(value: &str) -> String {
+  return value
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n");
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app_state::test_state;
+
+  #[test]
+  fn test_escape_label_value() {
+    assert_eq!(escape_label_value("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+  }
+
+  #[test]
+  fn test_histogram() {
+    let mut histogram = Histogram::default();
+    histogram.observe(0.007);
+    histogram.observe(3.0);
+
+    assert_eq!(histogram.count, 2);
+    assert_eq!(histogram.buckets[0], 0);
+    assert_eq!(histogram.buckets[1], 1);
+    assert_eq!(histogram.buckets[LATENCY_BUCKETS.len() - 2], 2);
+  }
+
+  #[tokio::test]
+  async fn test_render() {
+    let state = test_state(None).await.unwrap();
+
+    record_request(
+      RouteLabels {
+        method: "GET".to_string(),
+        route: "/api/test_render/{id}".to_string(),
+        record_api: None,
+      },
+      StatusCode::OK,
+      Duration::from_millis(20),
+    );
+    record_wasm_invocation(WasmInvocation::Job, false);
+
+    let text = render(&state);
+    assert!(text.contains(
+      r#"trailbase_http_requests_total{method="GET",route="/api/test_render/{id}",record_api="",status="200"} 1"#
+    ));
+    assert!(text.contains(
+      r#"trailbase_http_request_duration_seconds_bucket{method="GET",route="/api/test_render/{id}",record_api="",le="0.025"} 1"#
+    ));
+    assert!(text.contains(r#"trailbase_sqlite_write_queue_depth{database="main"} 0"#));
+    assert!(text.contains(r#"trailbase_subscriptions_active{kind="record"} 0"#));
+    assert!(text.contains("# TYPE trailbase_job_runs_total counter"));
+    assert!(text.contains(r#"trailbase_wasm_invocations_total{kind="job",result="failure"}"#));
+  }
+}
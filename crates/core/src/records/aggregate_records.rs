@@ -0,0 +1,429 @@
+use askama::Template;
+use axum::{
+  Json,
+  extract::{Path, RawQuery, State},
+};
+use itertools::Itertools;
+use serde::Serialize;
+use std::borrow::Cow;
+use trailbase_qs::{AggregateFunction, Aggregation, OrderPrecedent};
+use trailbase_schema::QualifiedNameEscaped;
+use trailbase_schema::json::value_to_flat_json;
+use trailbase_schema::metadata::ColumnMetadata;
+use trailbase_schema::sqlite::ColumnDataType;
+use trailbase_sqlite::Value;
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::listing::{WhereClause, build_filter_where_clause, limit_or_default};
+use crate::records::expand::row_to_json_expand;
+use crate::records::record_api::user_params;
+use crate::records::{Permission, RecordApi, RecordError};
+
+/// A single group of aggregated records.
+#[derive(Debug, Serialize)]
+pub struct AggregateGroup {
+  /// Values of the `group_by` columns identifying this group. Empty if no grouping was requested.
+  pub group: serde_json::Value,
+  /// Aggregated values keyed by their alias, e.g. `count` or `sum_price`.
+  pub aggregates: serde_json::Map<String, serde_json::Value>,
+}
+
+/// JSON response containing the aggregated groups.
+#[derive(Debug, Serialize)]
+pub struct AggregateResponse {
+  pub groups: Vec<AggregateGroup>,
+}
+
+/// Aggregates records matching the given filters, optionally grouped by columns.
+#[utoipa::path(
+  get,
+  path = "/{name}/aggregate",
+  tag = "records",
+  responses(
+    (status = 200, description = "Aggregated groups.")
+  )
+)]
+pub async fn aggregate_records_handler(
+  State(state): State<AppState>,
+  Path(api_name): Path<String>,
+  RawQuery(raw_url_query): RawQuery,
+  user: Option<User>,
+) -> Result<Json<AggregateResponse>, RecordError> {
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+
+  // NOTE: Same as for listing, the read access rule is applied as a filter, i.e. rows the user
+  // doesn't have access to simply don't contribute to the aggregates.
+  api.check_table_level_access(Permission::Read, user.as_ref())?;
+
+  let trailbase_qs::AggregateQuery {
+    limit,
+    offset,
+    aggregate,
+    group_by,
+    order,
+    filter: filter_params,
+  } = raw_url_query
+    .as_ref()
+    .map_or_else(
+      || Ok(Default::default()),
+      |query| trailbase_qs::AggregateQuery::parse(query),
+    )
+    .map_err(|_err| {
+      return RecordError::BadRequest("Invalid query");
+    })?;
+
+  // NOTE: Lookups are against the API's columns, i.e. excluded columns can neither be grouped by
+  // nor aggregated over. This also avoids SQL injections.
+  let group_by_columns: Vec<ColumnMetadata> = group_by
+    .map(|g| g.columns)
+    .unwrap_or_default()
+    .into_iter()
+    .map(|name| {
+      return lookup_column(&api, &name)
+        .cloned()
+        .ok_or(RecordError::BadRequest("Invalid group_by column"));
+    })
+    .collect::<Result<_, _>>()?;
+
+  let aggregations: Vec<Aggregation> = match aggregate {
+    Some(aggregate) if !aggregate.aggregations.is_empty() => aggregate.aggregations,
+    _ => vec![Aggregation {
+      function: AggregateFunction::Count,
+      column: None,
+    }],
+  };
+
+  let aggregation_exprs = aggregations
+    .iter()
+    .map(|aggregation| build_aggregation_expr(&api, aggregation))
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let aliases: Vec<String> = aggregations.iter().map(|a| a.alias()).collect();
+  if !aliases.iter().all_unique() {
+    return Err(RecordError::BadRequest("Duplicate aggregation"));
+  }
+
+  let group_by_exprs: Vec<String> = group_by_columns
+    .iter()
+    .map(|meta| format!(r#"_ROW_."{}""#, meta.column.name))
+    .collect();
+
+  let order_clause = match order {
+    Some(order) => Some(
+      order
+        .columns
+        .iter()
+        .map(|(name, precedent)| -> Result<String, RecordError> {
+          let expr = if let Some(index) = group_by_columns
+            .iter()
+            .position(|meta| meta.column.name == *name)
+          {
+            &group_by_exprs[index]
+          } else if let Some(index) = aliases.iter().position(|alias| alias == name) {
+            &aggregation_exprs[index]
+          } else {
+            return Err(RecordError::BadRequest("Invalid order"));
+          };
+
+          return Ok(format!(
+            "{expr} {}",
+            match precedent {
+              OrderPrecedent::Descending => "DESC",
+              OrderPrecedent::Ascending => "ASC",
+            }
+          ));
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .join(","),
+    ),
+    None if !group_by_exprs.is_empty() => Some(group_by_exprs.join(",")),
+    None => None,
+  };
+
+  // NOTE: This will also drop any filters for unknown columns, thus avoiding SQL injections.
+  let WhereClause {
+    clause: filter_clause,
+    mut params,
+  } = build_filter_where_clause("_ROW_", api.columns(), filter_params)
+    .map_err(|_err| RecordError::BadRequest("Invalid filter params"))?;
+
+  let limit: usize =
+    limit_or_default(limit, api.listing_hard_limit()).map_err(RecordError::BadRequest)?;
+
+  params.push((Cow::Borrowed(":__limit"), Value::Integer(limit as i64)));
+  params.extend(user_params(user.as_ref()));
+
+  if let Some(offset) = offset {
+    params.push((
+      Cow::Borrowed(":__offset"),
+      Value::Integer(
+        offset
+          .try_into()
+          .map_err(|_| RecordError::BadRequest("Invalid offset"))?,
+      ),
+    ));
+  }
+
+  let rows = api
+    .conn()
+    .read_query_rows(
+      AggregateRecordQueryTemplate {
+        table_name: api.table_name(),
+        select_exprs: &group_by_exprs
+          .iter()
+          .chain(aggregation_exprs.iter())
+          .map(|expr| expr.as_str())
+          .collect::<Vec<_>>(),
+        read_access_clause: api.read_access_rule().unwrap_or("TRUE"),
+        filter_clause: &filter_clause,
+        group_by_clause: (!group_by_exprs.is_empty()).then(|| group_by_exprs.join(",")),
+        order_clause,
+        offset: offset.is_some(),
+      }
+      .render()
+      .map_err(|err| RecordError::Internal(err.into()))?,
+      params,
+    )
+    .await?;
+
+  let num_groups = group_by_columns.len();
+  let groups = rows
+    .into_iter()
+    .map(|row| -> Result<AggregateGroup, RecordError> {
+      let group = row_to_json_expand(&group_by_columns, &row, |_| true, None)
+        .map_err(|err| RecordError::Internal(err.into()))?;
+
+      let aggregates = aliases
+        .iter()
+        .enumerate()
+        .map(|(i, alias)| {
+          let value = row
+            .get_value(num_groups + i)
+            .ok_or_else(|| RecordError::Internal("missing aggregate".into()))?;
+
+          return Ok((
+            alias.clone(),
+            value_to_flat_json(value).map_err(|err| RecordError::Internal(err.into()))?,
+          ));
+        })
+        .collect::<Result<_, RecordError>>()?;
+
+      return Ok(AggregateGroup { group, aggregates });
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+  return Ok(Json(AggregateResponse { groups }));
+}
+
+fn lookup_column<'a>(api: &'a RecordApi, name: &str) -> Option<&'a ColumnMetadata> {
+  // Hidden columns are not accessible through Record APIs, consistent with filters.
+  if name.starts_with("_") {
+    return None;
+  }
+  return api.column_metadata_by_name(name);
+}
+
+fn build_aggregation_expr(
+  api: &RecordApi,
+  aggregation: &Aggregation,
+) -> Result<String, RecordError> {
+  let function = match aggregation.function {
+    AggregateFunction::Count => "COUNT",
+    AggregateFunction::Sum => "SUM",
+    AggregateFunction::Avg => "AVG",
+    AggregateFunction::Min => "MIN",
+    AggregateFunction::Max => "MAX",
+  };
+
+  let Some(ref column_name) = aggregation.column else {
+    return match aggregation.function {
+      AggregateFunction::Count => Ok("COUNT(*)".to_string()),
+      _ => Err(RecordError::BadRequest("Missing aggregation column")),
+    };
+  };
+
+  let meta =
+    lookup_column(api, column_name).ok_or(RecordError::BadRequest("Invalid aggregation column"))?;
+
+  if matches!(
+    aggregation.function,
+    AggregateFunction::Sum | AggregateFunction::Avg
+  ) && (matches!(
+    meta.column.data_type,
+    ColumnDataType::Text | ColumnDataType::Blob
+  ) || meta.json.is_some()
+    || meta.is_geometry)
+  {
+    return Err(RecordError::BadRequest("Non-numeric aggregation column"));
+  }
+
+  return Ok(format!(r#"{function}(_ROW_."{}")"#, meta.column.name));
+}
+
+#[derive(Template)]
+#[template(escape = "none", path = "aggregate_record_query.sql")]
+struct AggregateRecordQueryTemplate<'a> {
+  table_name: &'a QualifiedNameEscaped,
+  select_exprs: &'a [&'a str],
+  read_access_clause: &'a str,
+  filter_clause: &'a str,
+  group_by_clause: Option<String>,
+  order_clause: Option<String>,
+  offset: bool,
+}
+
+#[cfg(test)]
+mod tests {
+  use trailbase_schema::parse::parse_into_statement;
+  use trailbase_schema::sqlite::QualifiedName;
+
+  use super::*;
+  use crate::app_state::*;
+  use crate::config::proto::PermissionFlag;
+  use crate::records::test_utils::*;
+
+  #[test]
+  fn test_aggregate_records_template() {
+    let query = AggregateRecordQueryTemplate {
+      table_name: &QualifiedName::parse("table").unwrap().into(),
+      select_exprs: &[r#"_ROW_."status""#, "COUNT(*)", r#"SUM(_ROW_."price")"#],
+      read_access_clause: "_USER_.id IS NOT NULL",
+      filter_clause: "TRUE",
+      group_by_clause: Some(r#"_ROW_."status""#.to_string()),
+      order_clause: Some("COUNT(*) DESC".to_string()),
+      offset: true,
+    }
+    .render()
+    .unwrap();
+
+    assert!(parse_into_statement(&query).is_ok(), "{query}");
+    assert!(!query.contains("\n\n"), "{query}");
+  }
+
+  async fn aggregate(state: &AppState, query: &str) -> Result<Vec<AggregateGroup>, RecordError> {
+    return Ok(
+      aggregate_records_handler(
+        State(state.clone()),
+        Path("api".to_string()),
+        RawQuery(Some(query.to_string())),
+        None,
+      )
+      .await?
+      .0
+      .groups,
+    );
+  }
+
+  #[tokio::test]
+  async fn test_record_api_aggregate() {
+    let state = test_state(None).await.unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+        CREATE TABLE 'table' (
+          id       INTEGER PRIMARY KEY,
+          status   TEXT NOT NULL,
+          price    REAL NOT NULL,
+          visible  INTEGER NOT NULL,
+          secret   INTEGER NOT NULL DEFAULT 0
+        ) STRICT;
+        INSERT INTO 'table' (status, price, visible) VALUES
+          ('open', 1.0, 1),
+          ('open', 2.5, 1),
+          ('closed', 4.0, 1),
+          ('closed', 100.0, 0);
+      "#,
+      )
+      .await
+      .unwrap();
+
+    state.rebuild_connection_metadata().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("api".to_string()),
+        table_name: Some("table".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        read_access_rule: Some("_ROW_.visible = 1".to_string()),
+        excluded_columns: vec!["secret".to_string()],
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    // Default is a plain count, subject to the read access rule.
+    let groups = aggregate(&state, "").await.unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].group, serde_json::json!({}));
+    assert_eq!(groups[0].aggregates["count"], serde_json::json!(3));
+
+    let groups = aggregate(
+      &state,
+      "aggregate=count,sum(price),avg(price),min(price),max(price)&group_by=status",
+    )
+    .await
+    .unwrap();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].group, serde_json::json!({"status": "closed"}));
+    assert_eq!(
+      serde_json::Value::Object(groups[0].aggregates.clone()),
+      serde_json::json!({
+        "count": 1,
+        "sum_price": 4.0,
+        "avg_price": 4.0,
+        "min_price": 4.0,
+        "max_price": 4.0,
+      })
+    );
+    assert_eq!(groups[1].group, serde_json::json!({"status": "open"}));
+    assert_eq!(
+      serde_json::Value::Object(groups[1].aggregates.clone()),
+      serde_json::json!({
+        "count": 2,
+        "sum_price": 3.5,
+        "avg_price": 1.75,
+        "min_price": 1.0,
+        "max_price": 2.5,
+      })
+    );
+
+    // Order by aggregate and filter.
+    let groups = aggregate(&state, "group_by=status&order=-count")
+      .await
+      .unwrap();
+    assert_eq!(groups[0].group, serde_json::json!({"status": "open"}));
+
+    let groups = aggregate(&state, "group_by=status&filter[price][$gt]=2")
+      .await
+      .unwrap();
+    assert_eq!(
+      groups
+        .iter()
+        .map(|g| (g.group["status"].clone(), g.aggregates["count"].clone()))
+        .collect::<Vec<_>>(),
+      vec![
+        (serde_json::json!("closed"), serde_json::json!(1)),
+        (serde_json::json!("open"), serde_json::json!(1)),
+      ]
+    );
+
+    // Excluded, hidden and unknown columns are rejected.
+    assert!(aggregate(&state, "group_by=secret").await.is_err());
+    assert!(aggregate(&state, "aggregate=sum(secret)").await.is_err());
+    assert!(aggregate(&state, "aggregate=max(_rowid_)").await.is_err());
+    assert!(aggregate(&state, "aggregate=sum(missing)").await.is_err());
+    assert!(aggregate(&state, "order=count").await.is_ok());
+    assert!(aggregate(&state, "order=price").await.is_err());
+
+    // Non-numeric sums are rejected.
+    assert!(aggregate(&state, "aggregate=sum(status)").await.is_err());
+    assert!(aggregate(&state, "aggregate=count,count(*)").await.is_err());
+  }
+}
@@ -8,6 +8,7 @@ use utoipa::{IntoParams, ToSchema};
 use crate::app_state::AppState;
 use crate::auth::user::User;
 use crate::extract::Either;
+use crate::records::hooks::{HookedWrite, run_writes_with_hooks};
 use crate::records::params::{JsonRow, LazyParams, Params};
 use crate::records::write_queries::{WriteQuery, run_insert_query, run_queries};
 use crate::records::{Permission, RecordError};
@@ -99,6 +100,10 @@ pub async fn create_record_handler(
     Either::Form(value) => vec![(extract_record(value)?, None)],
   };
 
+  // Record hooks need the original payload, i.e. we only keep a copy if hooks are registered.
+  let has_hooks = state.record_hooks().has_hooks(&api_name);
+  let mut hook_records: Vec<JsonRow> = vec![];
+
   let mut params_list: Vec<Params> = Vec::with_capacity(records_and_files.len());
   for (mut record, files) in records_and_files {
     if api.insert_autofill_missing_user_id_columns()
@@ -124,6 +129,10 @@ pub async fn create_record_handler(
     )
     .map_err(|_err| RecordError::BadRequest("Invalid Parameters"))?;
 
+    if has_hooks {
+      hook_records.push(record.clone());
+    }
+
     let mut lazy_params =
       LazyParams::for_insert(&api, state.json_schema_registry().clone(), record, files);
 
@@ -150,6 +159,22 @@ pub async fn create_record_handler(
     0 => {
       return Err(RecordError::BadRequest("no values provided"));
     }
+    _ if has_hooks => {
+      let writes = std::iter::zip(hook_records, params_list)
+        .map(|(record, params)| HookedWrite::Insert { record, params })
+        .collect();
+
+      run_writes_with_hooks(&state, &api, user.as_ref(), writes)
+        .await?
+        .into_iter()
+        .map(|result| -> Result<String, RecordError> {
+          let Some(pk_value) = result.pk_value else {
+            return Err(RecordError::Internal("missing pk".into()));
+          };
+          return Ok(extract_record_id(pk_value)?);
+        })
+        .collect::<Result<Vec<_>, _>>()?
+    }
     1 => {
       let record_id = run_insert_query(
         api.conn(),
@@ -197,7 +222,9 @@ pub async fn create_record_handler(
 }
 
 #[inline]
-fn extract_record_id(value: trailbase_sqlite::Value) -> Result<String, trailbase_sqlite::Error> {
+pub(crate) fn extract_record_id(
+  value: trailbase_sqlite::Value,
+) -> Result<String, trailbase_sqlite::Error> {
   return match value {
     trailbase_sqlite::Value::Blob(blob) => Ok(BASE64_URL_SAFE.encode(blob)),
     trailbase_sqlite::Value::Text(text) => Ok(text),
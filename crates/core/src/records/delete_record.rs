@@ -6,6 +6,7 @@ use axum::{
 
 use crate::app_state::AppState;
 use crate::auth::user::User;
+use crate::records::hooks::{HookedWrite, run_writes_with_hooks};
 use crate::records::write_queries::run_delete_query;
 use crate::records::{Permission, RecordError};
 
@@ -30,12 +31,27 @@ pub async fn delete_record_handler(
     return Err(RecordError::ApiRequiresTable);
   }
 
-  let record_id = api.primary_key_to_value(record)?;
+  let record_id = api.primary_key_to_value(record.clone())?;
 
   api
     .check_record_level_access(Permission::Delete, Some(&record_id), None, user.as_ref())
     .await?;
 
+  if state.record_hooks().has_hooks(&api_name) {
+    run_writes_with_hooks(
+      &state,
+      &api,
+      user.as_ref(),
+      vec![HookedWrite::Delete {
+        record_id: record,
+        pk_value: record_id,
+      }],
+    )
+    .await?;
+
+    return Ok((StatusCode::OK, "deleted").into_response());
+  }
+
   let pk_meta = api.record_pk_column();
 
   run_delete_query(
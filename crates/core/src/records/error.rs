@@ -20,6 +20,9 @@ pub enum RecordError {
   Forbidden,
   #[error("Bad request: {0}")]
   BadRequest(&'static str),
+  /// Write aborted by a record hook with a custom status and message.
+  #[error("Aborted ({0}): {1}")]
+  Aborted(StatusCode, String),
   #[error("Internal: {0}")]
   Internal(Box<dyn std::error::Error + Send + Sync>),
 }
@@ -86,6 +89,7 @@ impl IntoResponse for RecordError {
       Self::RecordNotFound => (StatusCode::NOT_FOUND, None),
       Self::Forbidden => (StatusCode::FORBIDDEN, None),
       Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, Some(msg.to_string())),
+      Self::Aborted(status, msg) => (status, Some(msg)),
       Self::Internal(err) if cfg!(debug_assertions) => {
         (StatusCode::INTERNAL_SERVER_ERROR, Some(err.to_string()))
       }
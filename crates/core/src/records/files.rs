@@ -1,16 +1,20 @@
 use axum::body::Body;
-use axum::http::header;
-use axum::response::{IntoResponse, Response};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{AppendHeaders, IntoResponse, Response};
+use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use log::*;
-use object_store::{ObjectStore, ObjectStoreExt};
+use object_store::{GetOptions, GetRange, ObjectStore, ObjectStoreExt};
 use serde::{Deserialize, Serialize};
+use std::ops::Range;
 use std::sync::Arc;
 use thiserror::Error;
+use tokio::io::AsyncReadExt;
 use trailbase_schema::{FileUpload, FileUploads, QualifiedNameEscaped};
 use trailbase_sqlite::params;
 
 use crate::app_state::AppState;
+use crate::records::image_transform::delete_image_variants;
 use crate::records::params::FileMetadataContents;
 
 #[derive(Debug, Error)]
@@ -25,38 +29,239 @@ pub enum FileError {
   Sql(#[from] trailbase_sqlite::Error),
 }
 
+/// Serves an uploaded file, streaming its contents from the object store.
+///
+/// Supports conditional requests via `If-None-Match`/`If-Modified-Since` and single byte-range
+/// requests via `Range` (and `If-Range`). Files are served as attachments unless `inline` is
+/// requested and the content type is safe to be rendered by browsers.
 pub(crate) async fn read_file_into_response(
   state: &AppState,
   file_upload: FileUpload,
+  request_headers: &HeaderMap,
+  inline: bool,
 ) -> Result<Response, FileError> {
-  let result = state
-    .objectstore()
-    .get(&object_store::path::Path::from(
-      file_upload.objectstore_id(),
-    ))
-    .await?;
+  // NOTE: Uploaded files are immutable, i.e. changing a file column's contents results in a new
+  // file with a new id. The id is therefore a sufficient strong validator.
+  let etag = format!("\"{}\"", file_upload.objectstore_id());
+  let content_type = file_upload.content_type().map_or_else(
+    || "text/plain; charset=utf-8".to_string(),
+    |c| c.to_string(),
+  );
+
+  return read_object_into_response(
+    state,
+    &object_store::path::Path::from(file_upload.objectstore_id()),
+    etag,
+    content_type,
+    request_headers,
+    inline,
+  )
+  .await;
+}
+
+/// Files are subject to access control, thus must not be stored by shared caches, e.g. CDNs.
+const CACHE_CONTROL: &str = "private";
 
-  let headers = || {
-    return [
+/// Serves an immutable object from the object store given its strong `etag`.
+pub(crate) async fn read_object_into_response(
+  state: &AppState,
+  path: &object_store::path::Path,
+  etag: String,
+  content_type: String,
+  request_headers: &HeaderMap,
+  inline: bool,
+) -> Result<Response, FileError> {
+  let store = state.objectstore();
+  let meta = store.head(path).await?;
+  let last_modified = format_http_date(&meta.last_modified);
+
+  if is_not_modified(request_headers, &etag, &meta.last_modified) {
+    return Ok(
       (
-        header::CONTENT_TYPE,
-        file_upload.content_type().map_or_else(
-          || "text/plain; charset=utf-8".to_string(),
-          |c| c.to_string(),
-        ),
-      ),
-      (header::CONTENT_DISPOSITION, "attachment".to_string()),
-    ];
+        StatusCode::NOT_MODIFIED,
+        [
+          (header::ETAG, etag),
+          (header::LAST_MODIFIED, last_modified),
+          (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+        ],
+      )
+        .into_response(),
+    );
+  }
+
+  let disposition = if inline && is_safe_to_inline(&content_type) {
+    "inline"
+  } else {
+    "attachment"
   };
 
-  return match result.payload {
-    object_store::GetResultPayload::File(_file, path) => {
-      let contents = tokio::fs::read(path).await?;
-      Ok((headers(), Body::from(contents)).into_response())
+  let mut headers = vec![
+    (header::CONTENT_TYPE, content_type),
+    (header::CONTENT_DISPOSITION, disposition.to_string()),
+    (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+    (header::ACCEPT_RANGES, "bytes".to_string()),
+    (header::ETAG, etag.clone()),
+    (header::LAST_MODIFIED, last_modified.clone()),
+    (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+  ];
+
+  let range = request_headers
+    .get(header::RANGE)
+    .and_then(|v| v.to_str().ok())
+    .filter(|_| if_range_matches(request_headers, &etag, &last_modified))
+    .map_or(ByteRange::Full, |v| parse_byte_range(v, meta.size));
+
+  let (status, range) = match range {
+    ByteRange::Full => (StatusCode::OK, None),
+    ByteRange::Partial(range) => {
+      headers.push((
+        header::CONTENT_RANGE,
+        format!("bytes {}-{}/{}", range.start, range.end - 1, meta.size),
+      ));
+      (StatusCode::PARTIAL_CONTENT, Some(range))
+    }
+    ByteRange::Unsatisfiable => {
+      return Ok(
+        (
+          StatusCode::RANGE_NOT_SATISFIABLE,
+          [(header::CONTENT_RANGE, format!("bytes */{}", meta.size))],
+        )
+          .into_response(),
+      );
     }
-    object_store::GetResultPayload::Stream(stream) => {
-      Ok((headers(), Body::from_stream(stream)).into_response())
+  };
+
+  let content_length = range.as_ref().map_or(meta.size, |r| r.end - r.start);
+  headers.push((header::CONTENT_LENGTH, content_length.to_string()));
+
+  let result = store
+    .get_opts(
+      path,
+      GetOptions {
+        range: range.map(GetRange::Bounded),
+        ..Default::default()
+      },
+    )
+    .await?;
+
+  // NOTE: For local files, the stream reads the file in chunks rather than loading it into memory
+  // at once.
+  return Ok(
+    (
+      status,
+      AppendHeaders(headers),
+      Body::from_stream(result.into_stream()),
+    )
+      .into_response(),
+  );
+}
+
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+  Full,
+  Partial(Range<u64>),
+  Unsatisfiable,
+}
+
+/// Parses a `Range` header value for a resource of the given `size`.
+///
+/// Only single ranges are supported. Unsupported or malformed ranges are ignored, i.e. the full
+/// contents are served, as permitted by RFC 9110.
+fn parse_byte_range(value: &str, size: u64) -> ByteRange {
+  let Some(spec) = value.trim().strip_prefix("bytes=") else {
+    return ByteRange::Full;
+  };
+  if spec.contains(',') {
+    return ByteRange::Full;
+  }
+  let Some((start, end)) = spec.trim().split_once('-') else {
+    return ByteRange::Full;
+  };
+
+  if start.is_empty() {
+    // Suffix range, i.e. the last N bytes.
+    let Ok(suffix) = end.parse::<u64>() else {
+      return ByteRange::Full;
+    };
+    if suffix == 0 || size == 0 {
+      return ByteRange::Unsatisfiable;
+    }
+    return ByteRange::Partial(size.saturating_sub(suffix)..size);
+  }
+
+  let Ok(start) = start.parse::<u64>() else {
+    return ByteRange::Full;
+  };
+  let end = if end.is_empty() {
+    u64::MAX
+  } else {
+    let Ok(end) = end.parse::<u64>() else {
+      return ByteRange::Full;
+    };
+    if end < start {
+      return ByteRange::Full;
+    }
+    end
+  };
+
+  if start >= size {
+    return ByteRange::Unsatisfiable;
+  }
+  return ByteRange::Partial(start..end.min(size - 1) + 1);
+}
+
+fn is_not_modified(headers: &HeaderMap, etag: &str, last_modified: &DateTime<Utc>) -> bool {
+  // If-None-Match takes precedence over If-Modified-Since, see RFC 9110 Section 13.1.3.
+  if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+    let Ok(if_none_match) = if_none_match.to_str() else {
+      return false;
+    };
+    return if_none_match.split(',').any(|tag| {
+      let tag = tag.trim();
+      // Weak comparison.
+      return tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag;
+    });
+  }
+
+  return headers
+    .get(header::IF_MODIFIED_SINCE)
+    .and_then(|v| v.to_str().ok())
+    .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+    .is_some_and(|since| last_modified.timestamp() <= since.timestamp());
+}
+
+/// A range request is only honored if `If-Range` is absent or still matches the current
+/// representation. Otherwise, the full contents are served.
+fn if_range_matches(headers: &HeaderMap, etag: &str, last_modified: &str) -> bool {
+  return match headers.get(header::IF_RANGE).map(|v| v.to_str()) {
+    None => true,
+    Some(Ok(if_range)) => {
+      let if_range = if_range.trim();
+      // Strong comparison.
+      if_range == etag || if_range == last_modified
     }
+    Some(Err(_)) => false,
+  };
+}
+
+pub(crate) fn format_http_date(date: &DateTime<Utc>) -> String {
+  return date.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+}
+
+/// Content types browsers can render w/o executing scripts in the server's origin. Anything else,
+/// e.g. HTML or SVG, is always served as an attachment.
+fn is_safe_to_inline(content_type: &str) -> bool {
+  let mime = content_type
+    .split(';')
+    .next()
+    .unwrap_or_default()
+    .trim()
+    .to_ascii_lowercase();
+
+  return match mime.split_once('/') {
+    Some(("image", subtype)) => subtype != "svg+xml",
+    Some(("audio" | "video", _)) => true,
+    _ => matches!(mime.as_str(), "application/pdf" | "text/plain"),
   };
 }
 
@@ -154,7 +359,11 @@ pub(crate) async fn delete_pending_files_impl(
           warn!("Abandoning deletion of {file:?} after {ATTEMPTS_LIMIT} attempts: {err}");
         }
       }
-      Ok(_) => {}
+      Ok(_) => {
+        if let Err(err) = delete_image_variants(store, file.objectstore_id()).await {
+          warn!("Failed to delete image variants of {file:?}: {err}");
+        }
+      }
     };
   };
 
@@ -222,6 +431,45 @@ impl FileManager {
       written_files.push(metadata);
     }
 
+    return Ok(Self::new(store, written_files));
+  }
+
+  /// Writes a single file from the local file system in parts, i.e. w/o loading it into memory at
+  /// once.
+  pub(crate) async fn write_from_path(
+    store: &Arc<dyn ObjectStore>,
+    metadata: FileUpload,
+    path: &std::path::Path,
+  ) -> Result<Self, FileError> {
+    // NOTE: S3 requires all but the last part to be at least 5MiB.
+    const PART_SIZE: usize = 8 * 1024 * 1024;
+
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut writer = store
+      .put_multipart(&object_store::path::Path::from(metadata.objectstore_id()))
+      .await?;
+
+    loop {
+      let mut part = Vec::with_capacity(PART_SIZE);
+      (&mut file)
+        .take(PART_SIZE as u64)
+        .read_to_end(&mut part)
+        .await?;
+
+      let last = part.len() < PART_SIZE;
+      if !part.is_empty() {
+        writer.put_part(part.into()).await?;
+      }
+      if last {
+        break;
+      }
+    }
+    writer.complete().await?;
+
+    return Ok(Self::new(store, vec![metadata]));
+  }
+
+  fn new(store: &Arc<dyn ObjectStore>, written_files: Vec<FileUpload>) -> Self {
     let cleanup: Option<Box<dyn FnOnce() + Send + Sync>> = if written_files.is_empty() {
       None
     } else {
@@ -238,7 +486,7 @@ impl FileManager {
       }))
     };
 
-    return Ok(Self { cleanup });
+    return Self { cleanup };
   }
 
   pub(crate) fn release(&mut self) {
@@ -253,3 +501,94 @@ impl Drop for FileManager {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_byte_range() {
+    assert_eq!(parse_byte_range("bytes=0-3", 10), ByteRange::Partial(0..4));
+    assert_eq!(parse_byte_range("bytes=5-", 10), ByteRange::Partial(5..10));
+    assert_eq!(
+      parse_byte_range("bytes=5-100", 10),
+      ByteRange::Partial(5..10)
+    );
+    assert_eq!(parse_byte_range("bytes=-3", 10), ByteRange::Partial(7..10));
+    assert_eq!(parse_byte_range("bytes=-30", 10), ByteRange::Partial(0..10));
+
+    assert_eq!(parse_byte_range("bytes=10-", 10), ByteRange::Unsatisfiable);
+    assert_eq!(parse_byte_range("bytes=-0", 10), ByteRange::Unsatisfiable);
+
+    // Unsupported or malformed ranges are ignored.
+    assert_eq!(parse_byte_range("bytes=0-1,4-5", 10), ByteRange::Full);
+    assert_eq!(parse_byte_range("bytes=3-1", 10), ByteRange::Full);
+    assert_eq!(parse_byte_range("bytes=a-b", 10), ByteRange::Full);
+    assert_eq!(parse_byte_range("items=0-1", 10), ByteRange::Full);
+  }
+
+  #[test]
+  fn test_conditional_headers() {
+    let etag = "\"id\"";
+    let last_modified = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+      .unwrap()
+      .to_utc();
+    assert_eq!(
+      format_http_date(&last_modified),
+      "Wed, 21 Oct 2015 07:28:00 GMT"
+    );
+
+    let headers = |name, value: &str| {
+      let mut headers = HeaderMap::new();
+      headers.insert(name, value.parse().unwrap());
+      return headers;
+    };
+
+    assert!(!is_not_modified(&HeaderMap::new(), etag, &last_modified));
+    assert!(is_not_modified(
+      &headers(header::IF_NONE_MATCH, "\"other\", W/\"id\""),
+      etag,
+      &last_modified
+    ));
+    assert!(!is_not_modified(
+      &headers(header::IF_NONE_MATCH, "\"other\""),
+      etag,
+      &last_modified
+    ));
+    assert!(is_not_modified(
+      &headers(header::IF_MODIFIED_SINCE, "Wed, 21 Oct 2015 07:28:00 GMT"),
+      etag,
+      &last_modified
+    ));
+    assert!(!is_not_modified(
+      &headers(header::IF_MODIFIED_SINCE, "Wed, 21 Oct 2015 07:27:59 GMT"),
+      etag,
+      &last_modified
+    ));
+
+    let last_modified = format_http_date(&last_modified);
+    assert!(if_range_matches(&HeaderMap::new(), etag, &last_modified));
+    assert!(if_range_matches(
+      &headers(header::IF_RANGE, etag),
+      etag,
+      &last_modified
+    ));
+    assert!(!if_range_matches(
+      &headers(header::IF_RANGE, "\"other\""),
+      etag,
+      &last_modified
+    ));
+  }
+
+  #[test]
+  fn test_is_safe_to_inline() {
+    assert!(is_safe_to_inline("video/mp4"));
+    assert!(is_safe_to_inline("image/PNG"));
+    assert!(is_safe_to_inline("application/pdf"));
+    assert!(is_safe_to_inline("text/plain; charset=utf-8"));
+
+    assert!(!is_safe_to_inline("image/svg+xml"));
+    assert!(!is_safe_to_inline("text/html"));
+    assert!(!is_safe_to_inline("application/javascript"));
+  }
+}
@@ -26,7 +26,7 @@ pub enum Filter {
   Record(ValueOrComposite),
 }
 
-fn any_qs_value_to_sql(value: trailbase_qs::Value) -> trailbase_sqlite::Value {
+fn any_qs_value_to_sql(value: trailbase_qs::Value) -> Result<trailbase_sqlite::Value, RecordError> {
   use base64::prelude::*;
   use trailbase_qs::Value as QsValue;
   use trailbase_sqlite::Value;
@@ -34,13 +34,14 @@ fn any_qs_value_to_sql(value: trailbase_qs::Value) -> trailbase_sqlite::Value {
   return match value {
     QsValue::String(s) => {
       if let Ok(b) = BASE64_URL_SAFE.decode(&s) {
-        Value::Blob(b)
+        Ok(Value::Blob(b))
       } else {
-        Value::Text(s.clone())
+        Ok(Value::Text(s.clone()))
       }
     }
-    QsValue::Integer(i) => Value::Integer(i),
-    QsValue::Double(d) => Value::Real(d),
+    QsValue::Integer(i) => Ok(Value::Integer(i)),
+    QsValue::Double(d) => Ok(Value::Real(d)),
+    QsValue::List(_) => Err(RecordError::BadRequest("Invalid query")),
   };
 }
 
@@ -53,7 +54,7 @@ pub(crate) fn qs_value_to_sql_with_constraints(
   use trailbase_sqlite::Value;
 
   return match column.data_type {
-    ColumnDataType::Any => Ok(any_qs_value_to_sql(value)),
+    ColumnDataType::Any => any_qs_value_to_sql(value),
     ColumnDataType::Blob => match value {
       QsValue::String(s) => Ok(Value::Blob(
         BASE64_URL_SAFE
@@ -66,6 +67,7 @@ pub(crate) fn qs_value_to_sql_with_constraints(
       QsValue::String(s) => Ok(Value::Text(s)),
       QsValue::Integer(i) => Ok(Value::Text(i.to_string())),
       QsValue::Double(d) => Ok(Value::Text(d.to_string())),
+      QsValue::List(_) => Err(RecordError::BadRequest("Invalid query")),
     },
     ColumnDataType::Integer => match value {
       QsValue::Integer(i) => Ok(Value::Integer(i)),
@@ -90,11 +92,56 @@ pub(crate) fn qs_filter_to_record_filter(
         .find(|meta| meta.column.name == col_op_value.column)
         .ok_or_else(|| RecordError::BadRequest("Invalid query"))?;
 
-      Ok(ValueOrComposite::Value(ColumnOpValue {
-        column: col_op_value.column,
-        op: col_op_value.op,
-        value: qs_value_to_sql_with_constraints(&meta.column, col_op_value.value)?,
-      }))
+      if col_op_value.op == CompareOp::Match {
+        return Err(RecordError::BadRequest(
+          "$match not supported for subscriptions",
+        ));
+      }
+
+      let column = col_op_value.column;
+      let scalar = |op: CompareOp, value: trailbase_qs::Value| -> Result<_, RecordError> {
+        return Ok(ValueOrComposite::Value(ColumnOpValue {
+          column: column.clone(),
+          op,
+          value: qs_value_to_sql_with_constraints(&meta.column, value)?,
+        }));
+      };
+      let scalars = |op: CompareOp, values: Vec<trailbase_qs::Value>| {
+        return values
+          .into_iter()
+          .map(|value| scalar(op, value))
+          .collect::<Result<Vec<_>, RecordError>>();
+      };
+
+      // List-valued operators are expanded into composites of scalar comparisons, each of which
+      // is checked against the column like any other filter value.
+      match (col_op_value.op, col_op_value.value) {
+        (CompareOp::In, trailbase_qs::Value::List(values)) => Ok(ValueOrComposite::Composite(
+          Combiner::Or,
+          scalars(CompareOp::Equal, values)?,
+        )),
+        (CompareOp::NotIn, trailbase_qs::Value::List(values)) => Ok(ValueOrComposite::Composite(
+          Combiner::And,
+          scalars(CompareOp::NotEqual, values)?,
+        )),
+        (CompareOp::Between, trailbase_qs::Value::List(values)) => {
+          let [lower, upper]: [trailbase_qs::Value; 2] = values
+            .try_into()
+            .map_err(|_| RecordError::BadRequest("Invalid query"))?;
+
+          Ok(ValueOrComposite::Composite(
+            Combiner::And,
+            vec![
+              scalar(CompareOp::GreaterThanEqual, lower)?,
+              scalar(CompareOp::LessThanEqual, upper)?,
+            ],
+          ))
+        }
+        (CompareOp::In | CompareOp::NotIn | CompareOp::Between, _) => {
+          Err(RecordError::BadRequest("Invalid query"))
+        }
+        (op, value) => scalar(op, value),
+      }
     }
     trailbase_qs::ValueOrComposite::Composite(combiner, expressions) => {
       Ok(ValueOrComposite::Composite(
@@ -209,6 +256,11 @@ fn compare_values(
       }
       _ => false,
     },
+    // Full-text search requires an FTS5 index, which isn't available for subscriptions. Filters
+    // are rejected early in `qs_filter_to_record_filter`.
+    CompareOp::Match => false,
+    // List-valued operators are expanded into composites in `qs_filter_to_record_filter`.
+    CompareOp::In | CompareOp::NotIn | CompareOp::Between => false,
   };
 }
 
@@ -357,6 +409,53 @@ mod tests {
     ));
   }
 
+  #[test]
+  fn test_list_operators() {
+    use trailbase_schema::sqlite::ColumnAffinityType;
+
+    let column_metadata = [ColumnMetadata {
+      index: 0,
+      column: Column {
+        name: "id".to_string(),
+        type_name: "INTEGER".to_string(),
+        data_type: ColumnDataType::Integer,
+        affinity_type: ColumnAffinityType::Integer,
+        options: vec![],
+      },
+      json: None,
+      is_file: false,
+      is_geometry: false,
+    }];
+
+    let filter = |query: &str| {
+      let filter = trailbase_qs::Query::parse(query).unwrap().filter.unwrap();
+      return qs_filter_to_record_filter(&column_metadata, filter);
+    };
+    let matches = |filter: &ValueOrComposite, id: i64| {
+      let record: IndexMap<String, Value> =
+        IndexMap::from([("id".to_string(), Value::Integer(id))]);
+      return apply_filter_recursively_to_record(filter, &record);
+    };
+
+    let is_in = filter("filter[id][$in]=3,7").unwrap();
+    assert!(matches(&is_in, 3));
+    assert!(matches(&is_in, 7));
+    assert!(!matches(&is_in, 5));
+
+    let not_in = filter("filter[id][$nin]=3,7").unwrap();
+    assert!(!matches(&not_in, 3));
+    assert!(matches(&not_in, 5));
+
+    let between = filter("filter[id][$between]=2,5").unwrap();
+    assert!(matches(&between, 2));
+    assert!(matches(&between, 5));
+    assert!(!matches(&between, 1));
+    assert!(!matches(&between, 6));
+
+    // Every list element is checked against the column's type.
+    assert!(filter("filter[id][$in]=3,foo").is_err());
+  }
+
   #[test]
   fn test_basic_composite_filter() {
     let record: IndexMap<String, Value> = IndexMap::from([
@@ -0,0 +1,261 @@
+use regex::Regex;
+use std::sync::LazyLock;
+use trailbase_qs::{ColumnOpValue, Combiner, CompareOp, ValueOrComposite};
+use trailbase_schema::QualifiedName;
+
+use crate::constants::SQLITE_SCHEMA_TABLE;
+use crate::records::RecordError;
+
+/// An FTS5 virtual table indexing the contents of a Record API's table, i.e. an FTS5 table
+/// declared with `content='<table>'`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct FtsTable {
+  pub name: QualifiedName,
+  /// Column of the content table corresponding to the FTS table's rowid, None for `_rowid_`.
+  pub content_rowid: Option<String>,
+  /// Indexed columns in declaration order.
+  pub columns: Vec<String>,
+}
+
+impl FtsTable {
+  pub fn column_index(&self, name: &str) -> Option<usize> {
+    return self.columns.iter().position(|c| c == name);
+  }
+
+  /// Reference to the FTS table's hidden column, e.g. the first argument to auxiliary functions
+  /// such as `snippet()` and `highlight()`.
+  pub fn table_column(&self) -> String {
+    return format!(r#"_FTS_."{}""#, self.name.name);
+  }
+
+  /// JOIN clause associating `_FTS_` rows with the content table's `_ROW_`s.
+  pub fn join_clause(&self) -> String {
+    let row_id = match self.content_rowid {
+      Some(ref column) => format!(r#"_ROW_."{column}""#),
+      None => "_ROW_._rowid_".to_string(),
+    };
+    return format!(
+      "INNER JOIN {fts} AS _FTS_ ON _FTS_.rowid = {row_id}",
+      fts = self.name.escaped_string()
+    );
+  }
+}
+
+/// Looks up the FTS5 virtual table associated with the given table, if any.
+pub(crate) async fn lookup_fts_table(
+  conn: &trailbase_sqlite::Connection,
+  table_name: &QualifiedName,
+) -> Result<Option<FtsTable>, RecordError> {
+  let db = table_name.database_schema.as_deref().unwrap_or("main");
+  let rows = conn
+    .read_query_rows(
+      format!("SELECT name, sql FROM '{db}'.{SQLITE_SCHEMA_TABLE} WHERE type = 'table'"),
+      (),
+    )
+    .await?;
+
+  for row in rows.iter() {
+    let name: String = row
+      .get(0)
+      .map_err(|err| RecordError::Internal(err.into()))?;
+    let sql: String = row
+      .get(1)
+      .map_err(|err| RecordError::Internal(err.into()))?;
+
+    let Some((content, content_rowid, columns)) = parse_fts5_definition(&sql) else {
+      continue;
+    };
+    if content.as_deref() != Some(table_name.name.as_str()) {
+      continue;
+    }
+
+    return Ok(Some(FtsTable {
+      name: QualifiedName {
+        name,
+        database_schema: table_name.database_schema.clone(),
+      },
+      content_rowid: content_rowid.filter(|c| !c.eq_ignore_ascii_case("rowid")),
+      columns,
+    }));
+  }
+
+  return Ok(None);
+}
+
+/// Splits a `$match` full-text search expression off the rest of the filter.
+///
+/// NOTE: FTS5 can only evaluate MATCH as a top-level constraint. Thus `$match` is only supported
+/// at the top-level or as part of a top-level `$and` composite.
+pub(crate) fn split_off_fts_match(
+  filter: Option<ValueOrComposite>,
+) -> Result<(Option<ValueOrComposite>, Option<ColumnOpValue>), RecordError> {
+  let (rest, fts_match) = match filter {
+    None => {
+      return Ok((None, None));
+    }
+    Some(ValueOrComposite::Value(value)) if value.op == CompareOp::Match => {
+      return Ok((None, Some(value)));
+    }
+    Some(ValueOrComposite::Composite(Combiner::And, values)) => {
+      let mut fts_match: Option<ColumnOpValue> = None;
+      let mut rest: Vec<ValueOrComposite> = vec![];
+      for value in values {
+        match value {
+          ValueOrComposite::Value(value) if value.op == CompareOp::Match => {
+            if fts_match.is_some() {
+              return Err(RecordError::BadRequest("Only a single $match is supported"));
+            }
+            fts_match = Some(value);
+          }
+          value => rest.push(value),
+        };
+      }
+
+      let rest = (!rest.is_empty()).then(|| ValueOrComposite::Composite(Combiner::And, rest));
+      (rest, fts_match)
+    }
+    Some(filter) => (Some(filter), None),
+  };
+
+  if let Some(ref rest) = rest {
+    rest.visit_values(|value| {
+      if value.op == CompareOp::Match {
+        return Err(RecordError::BadRequest(
+          "$match is only supported in top-level $and",
+        ));
+      }
+      return Ok(());
+    })?;
+  }
+
+  return Ok((rest, fts_match));
+}
+
+/// Extracts content table, content rowid column and indexed columns from a
+/// `CREATE VIRTUAL TABLE <name> USING fts5(<args>)` statement.
+fn parse_fts5_definition(sql: &str) -> Option<(Option<String>, Option<String>, Vec<String>)> {
+  static FTS5_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)\bUSING\s+fts5\s*\((?<args>.*)\)\s*;?\s*$").expect("valid"));
+
+  let args = FTS5_RE.captures(sql)?.name("args")?.as_str();
+
+  let mut content: Option<String> = None;
+  let mut content_rowid: Option<String> = None;
+  let mut columns: Vec<String> = vec![];
+  for arg in split_args(args) {
+    if let Some((key, value)) = arg.split_once('=') {
+      match unquote(key.trim()).to_ascii_lowercase().as_str() {
+        "content" => content = Some(unquote(value.trim())),
+        "content_rowid" => content_rowid = Some(unquote(value.trim())),
+        _ => {}
+      };
+      continue;
+    }
+
+    // Column declarations may be followed by "UNINDEXED".
+    let column = arg.trim();
+    let column = match column.rsplit_once(char::is_whitespace) {
+      Some((name, option)) if option.eq_ignore_ascii_case("unindexed") => name.trim(),
+      _ => column,
+    };
+    columns.push(unquote(column));
+  }
+
+  return Some((content.filter(|c| !c.is_empty()), content_rowid, columns));
+}
+
+/// Splits comma-separated arguments while respecting quotes.
+fn split_args(args: &str) -> Vec<&str> {
+  let mut result: Vec<&str> = vec![];
+  let mut quote: Option<char> = None;
+  let mut start = 0;
+  for (i, c) in args.char_indices() {
+    match (quote, c) {
+      (None, '\'' | '"' | '`') => quote = Some(c),
+      (None, '[') => quote = Some(']'),
+      (None, ',') => {
+        result.push(&args[start..i]);
+        start = i + 1;
+      }
+      (Some(q), c) if q == c => quote = None,
+      _ => {}
+    }
+  }
+  result.push(&args[start..]);
+
+  return result
+    .into_iter()
+    .filter(|a| !a.trim().is_empty())
+    .collect();
+}
+
+fn unquote(s: &str) -> String {
+  for (open, close) in [('\'', '\''), ('"', '"'), ('`', '`'), ('[', ']')] {
+    if s.len() >= 2 && s.starts_with(open) && s.ends_with(close) {
+      let inner = &s[1..s.len() - 1];
+      return if open == close {
+        inner.replace(&format!("{close}{close}"), &close.to_string())
+      } else {
+        inner.to_string()
+      };
+    }
+  }
+  return s.to_string();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_fts5_definition() {
+    assert_eq!(
+      parse_fts5_definition(
+        r#"CREATE VIRTUAL TABLE "post_fts" USING fts5(title, "body" UNINDEXED, content='post', content_rowid = 'id', tokenize = 'porter unicode61')"#
+      ),
+      Some((
+        Some("post".to_string()),
+        Some("id".to_string()),
+        vec!["title".to_string(), "body".to_string()]
+      ))
+    );
+
+    assert_eq!(
+      parse_fts5_definition("CREATE VIRTUAL TABLE x USING fts5(a, content='')"),
+      Some((None, None, vec!["a".to_string()]))
+    );
+
+    assert_eq!(
+      parse_fts5_definition("CREATE VIRTUAL TABLE x USING rtree(id, minX, maxX)"),
+      None
+    );
+  }
+
+  #[test]
+  fn test_split_off_fts_match() {
+    let qs = |q: &str| trailbase_qs::Query::parse(q).unwrap().filter;
+
+    let (rest, fts_match) = split_off_fts_match(qs("filter[body][$match]=foo")).unwrap();
+    assert_eq!(rest, None);
+    assert_eq!(fts_match.unwrap().column, "body");
+
+    let (rest, fts_match) =
+      split_off_fts_match(qs("filter[body][$match]=foo&filter[id][$gt]=5")).unwrap();
+    assert_eq!(
+      rest,
+      Some(ValueOrComposite::Composite(
+        Combiner::And,
+        vec![ValueOrComposite::Value(ColumnOpValue {
+          column: "id".to_string(),
+          op: CompareOp::GreaterThan,
+          value: trailbase_qs::Value::Integer(5),
+        })]
+      ))
+    );
+    assert!(fts_match.is_some());
+
+    assert!(
+      split_off_fts_match(qs("filter[$or][0][body][$match]=foo&filter[$or][1][id]=5")).is_err()
+    );
+  }
+}
@@ -0,0 +1,720 @@
+use axum::http::StatusCode;
+use std::collections::HashMap;
+use std::sync::Arc;
+use trailbase_schema::registry::JsonSchemaRegistry;
+use trailbase_sqlite::{SyncConnectionTrait, Value};
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::records::create_record::extract_record_id;
+use crate::records::error::RecordError;
+use crate::records::expand::row_to_json_expand;
+use crate::records::files::{FileManager, delete_files_marked_for_deletion};
+use crate::records::params::{FileMetadataContents, JsonRow, LazyParams, Params};
+use crate::records::write_queries::{WriteQuery, WriteQueryResult};
+use crate::records::{Permission, RecordApi};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Record lifecycle events hooks can be registered for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordHookEvent {
+  BeforeCreate,
+  AfterCreate,
+  BeforeUpdate,
+  AfterUpdate,
+  BeforeDelete,
+  AfterDelete,
+}
+
+pub struct RecordHookArgs<'a> {
+  pub api_name: &'a str,
+  pub event: RecordHookEvent,
+  pub record_id: Option<&'a str>,
+  pub record: Option<&'a JsonRow>,
+  pub user: Option<&'a User>,
+}
+
+pub enum RecordHookOutcome {
+  /// Continue with the write as is.
+  Proceed,
+  /// Merge the given fields into the record before writing. Ignored for all but
+  /// `RecordHookEvent::{BeforeCreate, BeforeUpdate}`.
+  Modify(JsonRow),
+  /// Abort the write and roll back the transaction.
+  Abort { status: StatusCode, message: String },
+}
+
+/// Abstraction over hook implementations, e.g. WASM components.
+///
+/// NOTE: Hooks are dispatched synchronously from SQLite's writer thread while the write
+/// transaction is pending.
+pub trait RecordHookDispatcher: Send + Sync {
+  fn dispatch(&self, args: RecordHookArgs<'_>) -> Result<RecordHookOutcome, BoxError>;
+}
+
+struct RegisteredHook {
+  events: Vec<RecordHookEvent>,
+  dispatcher: Arc<dyn RecordHookDispatcher>,
+}
+
+/// Registry of record hooks by Record API name.
+#[derive(Clone, Default)]
+pub struct RecordHooks {
+  hooks: Arc<HashMap<String, Vec<RegisteredHook>>>,
+}
+
+impl RecordHooks {
+  pub fn new(
+    hooks: impl IntoIterator<Item = (String, Vec<RecordHookEvent>, Arc<dyn RecordHookDispatcher>)>,
+  ) -> Self {
+    let mut map = HashMap::<String, Vec<RegisteredHook>>::new();
+    for (api_name, events, dispatcher) in hooks {
+      map
+        .entry(api_name)
+        .or_default()
+        .push(RegisteredHook { events, dispatcher });
+    }
+
+    return Self {
+      hooks: Arc::new(map),
+    };
+  }
+
+  pub fn has_hooks(&self, api_name: &str) -> bool {
+    return self.hooks.contains_key(api_name);
+  }
+
+  fn has_event(&self, api_name: &str, event: RecordHookEvent) -> bool {
+    return self
+      .hooks
+      .get(api_name)
+      .is_some_and(|hooks| hooks.iter().any(|h| h.events.contains(&event)));
+  }
+
+  /// Dispatches `event` to all hooks registered for `api_name` in registration order and returns
+  /// the accumulated modifications, if any.
+  fn dispatch(
+    &self,
+    api_name: &str,
+    event: RecordHookEvent,
+    record_id: Option<&str>,
+    record: Option<&JsonRow>,
+    user: Option<&User>,
+  ) -> Result<Option<JsonRow>, RecordError> {
+    let Some(hooks) = self.hooks.get(api_name) else {
+      return Ok(None);
+    };
+
+    // Subsequent hooks see the modifications of prior ones.
+    let mut current: Option<JsonRow> = None;
+    let mut patch: Option<JsonRow> = None;
+
+    for hook in hooks.iter().filter(|h| h.events.contains(&event)) {
+      let outcome = hook
+        .dispatcher
+        .dispatch(RecordHookArgs {
+          api_name,
+          event,
+          record_id,
+          record: current.as_ref().or(record),
+          user,
+        })
+        .map_err(RecordError::Internal)?;
+
+      match outcome {
+        RecordHookOutcome::Proceed => {}
+        RecordHookOutcome::Modify(fields) => {
+          let current = current.get_or_insert_with(|| record.cloned().unwrap_or_default());
+          let patch = patch.get_or_insert_with(JsonRow::new);
+          for (key, value) in fields {
+            current.insert(key.clone(), value.clone());
+            patch.insert(key, value);
+          }
+        }
+        RecordHookOutcome::Abort { status, message } => {
+          return Err(RecordError::Aborted(status, message));
+        }
+      }
+    }
+
+    return Ok(patch);
+  }
+}
+
+/// A record write to be applied together with the registered hooks.
+pub(crate) enum HookedWrite {
+  Insert {
+    /// The request's payload.
+    record: JsonRow,
+    params: Params,
+  },
+  Update {
+    record_id: String,
+    /// The request's payload.
+    record: JsonRow,
+    params: Params,
+  },
+  Delete {
+    record_id: String,
+    pk_value: Value,
+  },
+}
+
+impl HookedWrite {
+  fn take_files(&mut self) -> FileMetadataContents {
+    return match self {
+      Self::Insert { params, .. } | Self::Update { params, .. } => params.take_files(),
+      Self::Delete { .. } => vec![],
+    };
+  }
+}
+
+/// Applies a single write and dispatches the respective before- and after-hooks.
+///
+/// Expects to be called within a transaction, so that an aborting hook rolls back the write.
+pub(crate) fn apply_write_with_hooks<T: SyncConnectionTrait>(
+  conn: &T,
+  hooks: &RecordHooks,
+  api: &RecordApi,
+  json_schema_registry: &parking_lot::RwLock<JsonSchemaRegistry>,
+  user: Option<&User>,
+  write: HookedWrite,
+) -> Result<WriteQueryResult, RecordError> {
+  let api_name = api.api_name();
+  let pk_column_name = &api.record_pk_column().column.name;
+
+  return match write {
+    HookedWrite::Insert { record, mut params } => {
+      if let Some(patch) = hooks.dispatch(
+        api_name,
+        RecordHookEvent::BeforeCreate,
+        None,
+        Some(&record),
+        user,
+      )? {
+        params
+          .merge(api, &json_schema_registry.read(), patch)
+          .map_err(|_| RecordError::BadRequest("Invalid Parameters"))?;
+
+        // Access was checked against the request's payload, re-check the patched record.
+        params = recheck_access(conn, api, Permission::Create, None, params, user)?;
+      }
+
+      let (query, files) = WriteQuery::new_insert(
+        api.table_name(),
+        pk_column_name,
+        api.insert_conflict_resolution_strategy(),
+        params,
+      )?;
+      debug_assert!(files.is_empty(), "files should have been taken");
+
+      let result = query.apply_sync(conn)?;
+
+      if hooks.has_event(api_name, RecordHookEvent::AfterCreate) {
+        let record_id = result.pk_value.clone().map(extract_record_id).transpose()?;
+        let record = read_row(conn, api, "_rowid_", Value::Integer(result.rowid))?;
+
+        hooks.dispatch(
+          api_name,
+          RecordHookEvent::AfterCreate,
+          record_id.as_deref(),
+          record.as_ref(),
+          user,
+        )?;
+      }
+
+      Ok(result)
+    }
+    HookedWrite::Update {
+      record_id,
+      record,
+      mut params,
+    } => {
+      if let Some(patch) = hooks.dispatch(
+        api_name,
+        RecordHookEvent::BeforeUpdate,
+        Some(&record_id),
+        Some(&record),
+        user,
+      )? {
+        params
+          .merge(api, &json_schema_registry.read(), patch)
+          .map_err(|_| RecordError::BadRequest("Invalid Parameters"))?;
+
+        // Access was checked against the request's payload, re-check the patched record.
+        let pk_value = api.primary_key_to_value(record_id.clone())?;
+        params = recheck_access(conn, api, Permission::Update, Some(&pk_value), params, user)?;
+      }
+
+      let (query, files) = WriteQuery::new_update(api.table_name(), params)?;
+      debug_assert!(files.is_empty(), "files should have been taken");
+
+      let result = query.apply_sync(conn)?;
+
+      if hooks.has_event(api_name, RecordHookEvent::AfterUpdate) {
+        let record = read_row(conn, api, "_rowid_", Value::Integer(result.rowid))?;
+
+        hooks.dispatch(
+          api_name,
+          RecordHookEvent::AfterUpdate,
+          Some(&record_id),
+          record.as_ref(),
+          user,
+        )?;
+      }
+
+      Ok(result)
+    }
+    HookedWrite::Delete {
+      record_id,
+      pk_value,
+    } => {
+      let record = if hooks.has_event(api_name, RecordHookEvent::BeforeDelete)
+        || hooks.has_event(api_name, RecordHookEvent::AfterDelete)
+      {
+        read_row(
+          conn,
+          api,
+          &format!(r#""{pk_column_name}""#),
+          pk_value.clone(),
+        )?
+      } else {
+        None
+      };
+
+      hooks.dispatch(
+        api_name,
+        RecordHookEvent::BeforeDelete,
+        Some(&record_id),
+        record.as_ref(),
+        user,
+      )?;
+
+      let result =
+        WriteQuery::new_delete(api.table_name(), pk_column_name, pk_value)?.apply_sync(conn)?;
+
+      hooks.dispatch(
+        api_name,
+        RecordHookEvent::AfterDelete,
+        Some(&record_id),
+        record.as_ref(),
+        user,
+      )?;
+
+      Ok(result)
+    }
+  };
+}
+
+/// Runs the given writes in a single transaction, dispatching all registered hooks.
+///
+/// This is the slow-path equivalent of `run_queries` & co for APIs with registered hooks.
+pub(crate) async fn run_writes_with_hooks(
+  state: &AppState,
+  api: &RecordApi,
+  user: Option<&User>,
+  mut writes: Vec<HookedWrite>,
+) -> Result<Vec<WriteQueryResult>, RecordError> {
+  let files: FileMetadataContents = writes.iter_mut().flat_map(|w| w.take_files()).collect();
+
+  // We're storing any files to the object store first to make sure the DB entry is valid right
+  // after commit and not racily pointing to soon-to-be-written files.
+  let file_manager = if files.is_empty() {
+    None
+  } else {
+    Some(FileManager::write(state.objectstore(), files).await?)
+  };
+
+  let hooks = state.record_hooks().clone();
+  let json_schema_registry = state.json_schema_registry().clone();
+  let user = user.cloned();
+  let tx_api = api.clone();
+
+  let results: Vec<WriteQueryResult> = api
+    .conn()
+    .transaction(
+      move |tx| -> Result<Result<Vec<WriteQueryResult>, RecordError>, trailbase_sqlite::Error> {
+        let mut results = Vec::with_capacity(writes.len());
+        for write in writes {
+          match apply_write_with_hooks(
+            &tx,
+            &hooks,
+            &tx_api,
+            &json_schema_registry,
+            user.as_ref(),
+            write,
+          ) {
+            Ok(result) => results.push(result),
+            // Dropping the transaction without committing rolls back.
+            Err(err) => return Ok(Err(err)),
+          };
+        }
+
+        tx.commit()?;
+
+        return Ok(Ok(results));
+      },
+    )
+    .await??;
+
+  // Successful write, do not cleanup written files.
+  if let Some(mut file_manager) = file_manager {
+    file_manager.release();
+  }
+
+  if api.has_file_columns() {
+    let rowids: Vec<i64> = results.iter().map(|r| r.rowid).collect();
+    delete_files_marked_for_deletion(api.conn(), state.objectstore(), api.table_name(), &rowids)
+      .await
+      .map_err(|err| RecordError::Internal(err.into()))?;
+  }
+
+  return Ok(results);
+}
+
+/// Evaluates the API's access rule for `p` against params patched by a hook.
+fn recheck_access<T: SyncConnectionTrait>(
+  conn: &T,
+  api: &RecordApi,
+  p: Permission,
+  record_id: Option<&Value>,
+  params: Params,
+  user: Option<&User>,
+) -> Result<Params, RecordError> {
+  let mut lazy_params = LazyParams::Params(Ok(params));
+  api.record_level_access_check(conn, p, record_id, Some(&mut lazy_params), user)?;
+
+  return lazy_params
+    .consume()
+    .map_err(|_| RecordError::BadRequest("Invalid Parameters"));
+}
+
+/// Reads the row matching `value` in the (already escaped) column `column` as JSON.
+fn read_row<T: SyncConnectionTrait>(
+  conn: &T,
+  api: &RecordApi,
+  column: &str,
+  value: Value,
+) -> Result<Option<JsonRow>, RecordError> {
+  let column_names = api
+    .columns()
+    .iter()
+    .map(|c| format!(r#""{}""#, c.column.name))
+    .collect::<Vec<_>>()
+    .join(", ");
+
+  let Some(row) = conn.query_row(
+    format!(
+      "SELECT {column_names} FROM {table_name} WHERE {column} = $1",
+      table_name = api.table_name()
+    ),
+    [value],
+  )?
+  else {
+    return Ok(None);
+  };
+
+  // NOTE: Unlike for reads, hooks get to see all columns.
+  return match row_to_json_expand(api.columns(), &row, |_| true, None)
+    .map_err(|err| RecordError::Internal(err.into()))?
+  {
+    serde_json::Value::Object(record) => Ok(Some(record)),
+    _ => Err(RecordError::Internal("not an object".into())),
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use parking_lot::Mutex;
+  use serde_json::json;
+
+  use super::*;
+  use crate::app_state::*;
+  use crate::config::proto::{PermissionFlag, RecordApiConfig};
+  use crate::records::test_utils::*;
+
+  struct TestDispatcher {
+    events: Mutex<Vec<(RecordHookEvent, Option<String>)>>,
+  }
+
+  impl RecordHookDispatcher for TestDispatcher {
+    fn dispatch(&self, args: RecordHookArgs<'_>) -> Result<RecordHookOutcome, BoxError> {
+      self
+        .events
+        .lock()
+        .push((args.event, args.record_id.map(|id| id.to_string())));
+
+      let value = args.record.and_then(|r| r.get("value")).cloned();
+      return Ok(match (args.event, value) {
+        (RecordHookEvent::BeforeCreate, _) => {
+          RecordHookOutcome::Modify(json_row_from_value(json!({"value": 42})).unwrap())
+        }
+        (RecordHookEvent::BeforeUpdate, Some(v)) if v == json!(7) => {
+          RecordHookOutcome::Modify(json_row_from_value(json!({"value": 1000})).unwrap())
+        }
+        (RecordHookEvent::BeforeUpdate, Some(v)) if v == json!(-1) => RecordHookOutcome::Abort {
+          status: StatusCode::UNPROCESSABLE_ENTITY,
+          message: "negative".to_string(),
+        },
+        _ => RecordHookOutcome::Proceed,
+      });
+    }
+  }
+
+  #[tokio::test]
+  async fn test_record_hooks() {
+    let dispatcher = Arc::new(TestDispatcher {
+      events: Mutex::new(vec![]),
+    });
+
+    let state = test_state(Some(TestStateOptions {
+      record_hooks: Some(RecordHooks::new([(
+        "test_api".to_string(),
+        vec![
+          RecordHookEvent::BeforeCreate,
+          RecordHookEvent::AfterCreate,
+          RecordHookEvent::BeforeUpdate,
+          RecordHookEvent::AfterDelete,
+        ],
+        dispatcher.clone() as Arc<dyn RecordHookDispatcher>,
+      )])),
+      ..Default::default()
+    }))
+    .await
+    .unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE test (
+            id      INTEGER PRIMARY KEY,
+            value   INTEGER
+          ) STRICT;
+        "#,
+      )
+      .await
+      .unwrap();
+
+    state.rebuild_connection_metadata().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("test_api".to_string()),
+        table_name: Some("test".to_string()),
+        acl_world: [
+          PermissionFlag::Create as i32,
+          PermissionFlag::Update as i32,
+          PermissionFlag::Delete as i32,
+        ]
+        .into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let api = state.lookup_record_api("test_api").unwrap();
+    let registry = state.json_schema_registry().clone();
+
+    // Create: the value gets overridden by the before-hook.
+    let record = json_row_from_value(json!({"value": 5})).unwrap();
+    let params = Params::for_insert(&api, &registry.read(), record.clone(), None).unwrap();
+    let results = run_writes_with_hooks(
+      &state,
+      &api,
+      None,
+      vec![HookedWrite::Insert { record, params }],
+    )
+    .await
+    .unwrap();
+    assert_eq!(results.len(), 1);
+    let id: i64 = results[0].rowid;
+
+    let value: i64 = state
+      .conn()
+      .read_query_row_get(
+        "SELECT value FROM test WHERE id = $1",
+        [Value::Integer(id)],
+        0,
+      )
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(value, 42);
+
+    // Update: aborted by the before-hook and rolled back.
+    let record = json_row_from_value(json!({"value": -1})).unwrap();
+    let params = Params::for_update(
+      &api,
+      &registry.read(),
+      record.clone(),
+      None,
+      "id".to_string(),
+      Value::Integer(id),
+    )
+    .unwrap();
+    let err = run_writes_with_hooks(
+      &state,
+      &api,
+      None,
+      vec![HookedWrite::Update {
+        record_id: id.to_string(),
+        record,
+        params,
+      }],
+    )
+    .await
+    .err()
+    .unwrap();
+    assert!(
+      matches!(err, RecordError::Aborted(StatusCode::UNPROCESSABLE_ENTITY, ref msg) if msg == "negative"),
+      "{err}"
+    );
+
+    let value: i64 = state
+      .conn()
+      .read_query_row_get(
+        "SELECT value FROM test WHERE id = $1",
+        [Value::Integer(id)],
+        0,
+      )
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(value, 42);
+
+    // Delete.
+    run_writes_with_hooks(
+      &state,
+      &api,
+      None,
+      vec![HookedWrite::Delete {
+        record_id: id.to_string(),
+        pk_value: Value::Integer(id),
+      }],
+    )
+    .await
+    .unwrap();
+
+    let id = Some(id.to_string());
+    assert_eq!(
+      *dispatcher.events.lock(),
+      vec![
+        (RecordHookEvent::BeforeCreate, None),
+        (RecordHookEvent::AfterCreate, id.clone()),
+        (RecordHookEvent::BeforeUpdate, id.clone()),
+        (RecordHookEvent::AfterDelete, id.clone()),
+      ]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_record_hook_patches_are_access_checked() {
+    let dispatcher = Arc::new(TestDispatcher {
+      events: Mutex::new(vec![]),
+    });
+
+    let state = test_state(Some(TestStateOptions {
+      record_hooks: Some(RecordHooks::new([(
+        "test_api".to_string(),
+        vec![RecordHookEvent::BeforeCreate, RecordHookEvent::BeforeUpdate],
+        dispatcher.clone() as Arc<dyn RecordHookDispatcher>,
+      )])),
+      ..Default::default()
+    }))
+    .await
+    .unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE test (
+            id      INTEGER PRIMARY KEY,
+            value   INTEGER
+          ) STRICT;
+
+          INSERT INTO test (id, value) VALUES (1, 0);
+        "#,
+      )
+      .await
+      .unwrap();
+
+    state.rebuild_connection_metadata().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("test_api".to_string()),
+        table_name: Some("test".to_string()),
+        acl_world: [PermissionFlag::Create as i32, PermissionFlag::Update as i32].into(),
+        create_access_rule: Some("_REQ_.value < 10".to_string()),
+        update_access_rule: Some("_REQ_.value < 100".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let api = state.lookup_record_api("test_api").unwrap();
+    let registry = state.json_schema_registry().clone();
+
+    // The request's payload passes the rule but the hook patches in a forbidden value.
+    let record = json_row_from_value(json!({"value": 5})).unwrap();
+    let params = Params::for_insert(&api, &registry.read(), record.clone(), None).unwrap();
+    let err = run_writes_with_hooks(
+      &state,
+      &api,
+      None,
+      vec![HookedWrite::Insert { record, params }],
+    )
+    .await
+    .err()
+    .unwrap();
+    assert!(matches!(err, RecordError::Forbidden), "{err}");
+
+    let record = json_row_from_value(json!({"value": 7})).unwrap();
+    let params = Params::for_update(
+      &api,
+      &registry.read(),
+      record.clone(),
+      None,
+      "id".to_string(),
+      Value::Integer(1),
+    )
+    .unwrap();
+    let err = run_writes_with_hooks(
+      &state,
+      &api,
+      None,
+      vec![HookedWrite::Update {
+        record_id: "1".to_string(),
+        record,
+        params,
+      }],
+    )
+    .await
+    .err()
+    .unwrap();
+    assert!(matches!(err, RecordError::Forbidden), "{err}");
+
+    let (count, value): (i64, i64) = (
+      state
+        .conn()
+        .read_query_row_get("SELECT COUNT(*) FROM test", (), 0)
+        .await
+        .unwrap()
+        .unwrap(),
+      state
+        .conn()
+        .read_query_row_get("SELECT value FROM test WHERE id = 1", (), 0)
+        .await
+        .unwrap()
+        .unwrap(),
+    );
+    assert_eq!((count, value), (1, 0));
+  }
+}
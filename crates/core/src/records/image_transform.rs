@@ -0,0 +1,404 @@
+//! On-the-fly image transformations, e.g. thumbnails, for files stored in record columns.
+//!
+//! Derived variants are generated once and cached in the object store next to the originals.
+use axum::http::HeaderMap;
+use axum::response::Response;
+use futures_util::StreamExt;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageReader, Limits};
+use log::*;
+use object_store::{ObjectStore, ObjectStoreExt, PutPayload};
+use serde::Deserialize;
+use std::io::Cursor;
+use std::sync::Arc;
+use trailbase_schema::FileUpload;
+use utoipa::ToSchema;
+
+use crate::app_state::AppState;
+use crate::config::proto;
+use crate::constants::{
+  IMAGE_TRANSFORM_MAX_DIMENSION, IMAGE_TRANSFORM_MAX_SOURCE_SIZE, IMAGE_VARIANTS_PREFIX,
+};
+use crate::records::RecordError;
+use crate::records::files::read_object_into_response;
+use crate::records::read_record::ReadFileQuery;
+
+/// Guards against decompression bombs, i.e. small files decoding into huge images.
+const MAX_SOURCE_DIMENSION: u32 = 16384;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFit {
+  #[default]
+  Contain,
+  Cover,
+  Fill,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+  Png,
+  Jpeg,
+  Webp,
+}
+
+impl ImageFormat {
+  fn from_source(format: image::ImageFormat) -> Self {
+    return match format {
+      image::ImageFormat::Jpeg => Self::Jpeg,
+      image::ImageFormat::WebP => Self::Webp,
+      _ => Self::Png,
+    };
+  }
+
+  fn extension(&self) -> &'static str {
+    return match self {
+      Self::Png => "png",
+      Self::Jpeg => "jpg",
+      Self::Webp => "webp",
+    };
+  }
+
+  fn content_type(&self) -> &'static str {
+    return match self {
+      Self::Png => "image/png",
+      Self::Jpeg => "image/jpeg",
+      Self::Webp => "image/webp",
+    };
+  }
+}
+
+impl From<ImageFormat> for image::ImageFormat {
+  fn from(format: ImageFormat) -> Self {
+    return match format {
+      ImageFormat::Png => Self::Png,
+      ImageFormat::Jpeg => Self::Jpeg,
+      ImageFormat::Webp => Self::WebP,
+    };
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ImageTransform {
+  width: Option<u32>,
+  height: Option<u32>,
+  fit: ImageFit,
+  format: Option<ImageFormat>,
+}
+
+impl ImageTransform {
+  /// Returns `None` if no transformation was requested.
+  pub(crate) fn from_query(query: &ReadFileQuery) -> Option<Self> {
+    if query.w.is_none() && query.h.is_none() && query.fit.is_none() && query.format.is_none() {
+      return None;
+    }
+
+    return Some(Self {
+      width: query.w,
+      height: query.h,
+      fit: query.fit.unwrap_or_default(),
+      format: query.format,
+    });
+  }
+
+  fn from_config(config: &proto::ImageTransform) -> Self {
+    return Self {
+      width: config.width,
+      height: config.height,
+      fit: match config.fit() {
+        proto::ImageFit::Cover => ImageFit::Cover,
+        proto::ImageFit::Fill => ImageFit::Fill,
+        proto::ImageFit::Contain | proto::ImageFit::Undefined => ImageFit::Contain,
+      },
+      format: match config.format() {
+        proto::ImageFormat::Png => Some(ImageFormat::Png),
+        proto::ImageFormat::Jpeg => Some(ImageFormat::Jpeg),
+        proto::ImageFormat::Webp => Some(ImageFormat::Webp),
+        proto::ImageFormat::Undefined => None,
+      },
+    };
+  }
+
+  /// Name uniquely identifying the variant of a given file.
+  fn variant_name(&self, format: ImageFormat) -> String {
+    let dimension = |d: Option<u32>| d.map_or_else(|| "auto".to_string(), |d| d.to_string());
+    let fit = match self.fit {
+      ImageFit::Contain => "contain",
+      ImageFit::Cover => "cover",
+      ImageFit::Fill => "fill",
+    };
+
+    return format!(
+      "{}x{}-{fit}.{}",
+      dimension(self.width),
+      dimension(self.height),
+      format.extension()
+    );
+  }
+}
+
+/// Serves the requested variant of an image, generating and caching it on first access.
+pub(crate) async fn read_image_variant_into_response(
+  state: &AppState,
+  allowed: &[proto::ImageTransform],
+  file_upload: FileUpload,
+  transform: ImageTransform,
+  request_headers: &HeaderMap,
+  inline: bool,
+) -> Result<Response, RecordError> {
+  // Generating variants is costly. Only allow what has been explicitly configured.
+  if !allowed
+    .iter()
+    .any(|config| ImageTransform::from_config(config) == transform)
+  {
+    return Err(RecordError::BadRequest("Image transform not allowed"));
+  }
+
+  // NOTE: Only trust the inferred mime type, the user-provided content type may lie.
+  let Some(source_format) = file_upload
+    .mime_type()
+    .and_then(image::ImageFormat::from_mime_type)
+    .filter(|f| f.reading_enabled())
+  else {
+    return Err(RecordError::BadRequest("Not a supported image"));
+  };
+  let format = transform
+    .format
+    .unwrap_or_else(|| ImageFormat::from_source(source_format));
+
+  let variant_name = transform.variant_name(format);
+  let path = variant_path(file_upload.objectstore_id(), &variant_name);
+
+  let store = state.objectstore();
+  match store.head(&path).await {
+    Ok(_) => {}
+    Err(object_store::Error::NotFound { .. }) => {
+      let source = object_store::path::Path::from(file_upload.objectstore_id());
+      let result = store
+        .get(&source)
+        .await
+        .map_err(|err| RecordError::Internal(err.into()))?;
+      if result.meta.size > IMAGE_TRANSFORM_MAX_SOURCE_SIZE {
+        return Err(RecordError::BadRequest("Image too large"));
+      }
+      let data = result
+        .bytes()
+        .await
+        .map_err(|err| RecordError::Internal(err.into()))?;
+
+      let variant = tokio::task::spawn_blocking(move || transform_image(&data, &transform, format))
+        .await
+        .map_err(|err| RecordError::Internal(err.into()))?
+        .map_err(|err| {
+          debug!("Failed to transform image: {err}");
+          return RecordError::BadRequest("Invalid image");
+        })?;
+
+      store
+        .put(&path, PutPayload::from(variant))
+        .await
+        .map_err(|err| RecordError::Internal(err.into()))?;
+    }
+    Err(err) => {
+      return Err(RecordError::Internal(err.into()));
+    }
+  };
+
+  // NOTE: Variants are as immutable as the files they are derived from.
+  let etag = format!("\"{}/{variant_name}\"", file_upload.objectstore_id());
+  return read_object_into_response(
+    state,
+    &path,
+    etag,
+    format.content_type().to_string(),
+    request_headers,
+    inline,
+  )
+  .await
+  .map_err(|err| RecordError::Internal(err.into()));
+}
+
+/// Deletes all cached variants derived from the given file.
+pub(crate) async fn delete_image_variants(
+  store: &Arc<dyn ObjectStore>,
+  objectstore_id: &str,
+) -> Result<(), object_store::Error> {
+  let prefix = object_store::path::Path::from_iter([IMAGE_VARIANTS_PREFIX, objectstore_id]);
+
+  let mut variants = store.list(Some(&prefix));
+  while let Some(meta) = variants.next().await {
+    match store.delete(&meta?.location).await {
+      Ok(_) | Err(object_store::Error::NotFound { .. }) => {}
+      Err(err) => return Err(err),
+    };
+  }
+
+  return Ok(());
+}
+
+fn variant_path(objectstore_id: &str, variant_name: &str) -> object_store::path::Path {
+  return object_store::path::Path::from_iter([
+    IMAGE_VARIANTS_PREFIX,
+    objectstore_id,
+    variant_name,
+  ]);
+}
+
+fn transform_image(
+  data: &[u8],
+  transform: &ImageTransform,
+  format: ImageFormat,
+) -> Result<Vec<u8>, image::ImageError> {
+  let mut limits = Limits::default();
+  limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+  limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+
+  let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+  reader.limits(limits);
+  let image = reader.decode()?;
+
+  const FILTER: FilterType = FilterType::Lanczos3;
+  let resized = match (transform.fit, transform.width.zip(transform.height)) {
+    (ImageFit::Cover, Some((width, height))) => image.resize_to_fill(width, height, FILTER),
+    (ImageFit::Fill, Some((width, height))) => image.resize_exact(width, height, FILTER),
+    // With only one dimension given, all fits preserve the aspect ratio. The other dimension is
+    // still bounded, since extreme aspect ratios would otherwise produce huge outputs.
+    _ => image.resize(
+      transform.width.unwrap_or(IMAGE_TRANSFORM_MAX_DIMENSION),
+      transform.height.unwrap_or(IMAGE_TRANSFORM_MAX_DIMENSION),
+      FILTER,
+    ),
+  };
+
+  // Not all encoders support all color types, e.g. JPEG has no alpha channel.
+  let resized = match format {
+    ImageFormat::Jpeg => DynamicImage::ImageRgb8(resized.to_rgb8()),
+    ImageFormat::Webp => DynamicImage::ImageRgba8(resized.to_rgba8()),
+    ImageFormat::Png => resized,
+  };
+
+  let mut buffer = Cursor::new(Vec::new());
+  resized.write_to(&mut buffer, format.into())?;
+  return Ok(buffer.into_inner());
+}
+
+#[cfg(test)]
+mod tests {
+  use image::{GenericImageView, ImageBuffer, Rgba};
+
+  use super::*;
+
+  fn test_png(width: u32, height: u32) -> Vec<u8> {
+    let image = ImageBuffer::from_pixel(width, height, Rgba([255u8, 0, 0, 255]));
+    let mut buffer = Cursor::new(Vec::new());
+    image
+      .write_to(&mut buffer, image::ImageFormat::Png)
+      .unwrap();
+    return buffer.into_inner();
+  }
+
+  fn transform(width: Option<u32>, height: Option<u32>, fit: ImageFit) -> ImageTransform {
+    return ImageTransform {
+      width,
+      height,
+      fit,
+      format: None,
+    };
+  }
+
+  #[test]
+  fn test_transform_image() {
+    let png = test_png(40, 20);
+
+    let dimensions = |t: ImageTransform, format: ImageFormat| {
+      let data = transform_image(&png, &t, format).unwrap();
+      let image = image::load_from_memory_with_format(&data, format.into()).unwrap();
+      return image.dimensions();
+    };
+
+    assert_eq!(
+      dimensions(
+        transform(Some(10), Some(10), ImageFit::Contain),
+        ImageFormat::Png
+      ),
+      (10, 5)
+    );
+    assert_eq!(
+      dimensions(
+        transform(Some(10), Some(10), ImageFit::Cover),
+        ImageFormat::Webp
+      ),
+      (10, 10)
+    );
+    assert_eq!(
+      dimensions(
+        transform(Some(10), Some(30), ImageFit::Fill),
+        ImageFormat::Jpeg
+      ),
+      (10, 30)
+    );
+    assert_eq!(
+      dimensions(transform(None, Some(10), ImageFit::Cover), ImageFormat::Png),
+      (20, 10)
+    );
+
+    // Extreme aspect ratios must not blow up the unconstrained dimension.
+    let png = test_png(1, MAX_SOURCE_DIMENSION);
+    let data = transform_image(
+      &png,
+      &transform(Some(64), None, ImageFit::Contain),
+      ImageFormat::Png,
+    )
+    .unwrap();
+    let (width, height) = image::load_from_memory(&data).unwrap().dimensions();
+    assert!(width <= IMAGE_TRANSFORM_MAX_DIMENSION);
+    assert_eq!(height, IMAGE_TRANSFORM_MAX_DIMENSION);
+
+    assert!(
+      transform_image(
+        b"not an image",
+        &transform(Some(10), None, ImageFit::Contain),
+        ImageFormat::Png
+      )
+      .is_err()
+    );
+  }
+
+  #[test]
+  fn test_transform_from_query_and_config() {
+    assert_eq!(ImageTransform::from_query(&ReadFileQuery::default()), None);
+
+    let from_query = ImageTransform::from_query(&ReadFileQuery {
+      w: Some(200),
+      h: Some(200),
+      fit: Some(ImageFit::Cover),
+      format: Some(ImageFormat::Webp),
+      ..Default::default()
+    })
+    .unwrap();
+
+    let config = proto::ImageTransform {
+      width: Some(200),
+      height: Some(200),
+      fit: Some(proto::ImageFit::Cover as i32),
+      format: Some(proto::ImageFormat::Webp as i32),
+    };
+    assert_eq!(ImageTransform::from_config(&config), from_query);
+
+    let config = proto::ImageTransform {
+      format: None,
+      ..config
+    };
+    assert_ne!(ImageTransform::from_config(&config), from_query);
+
+    assert_eq!(
+      from_query.variant_name(ImageFormat::Webp),
+      "200x200-cover.webp"
+    );
+    assert_eq!(
+      transform(Some(64), None, ImageFit::Contain).variant_name(ImageFormat::Png),
+      "64xauto-contain.png"
+    );
+  }
+}
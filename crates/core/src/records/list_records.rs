@@ -18,6 +18,9 @@ use crate::auth::user::User;
 use crate::encryption::{KeyType, decrypt, encrypt, generate_random_key};
 use crate::listing::{WhereClause, build_filter_where_clause, limit_or_default};
 use crate::records::expand::{ExpandedTable, JsonError, expand_tables, row_to_json_expand};
+use crate::records::fts::{FtsTable, lookup_fts_table, split_off_fts_match};
+use crate::records::projection::build_projection;
+use crate::records::record_api::user_params;
 use crate::records::{Permission, RecordError};
 
 /// JSON response containing the listed records.
@@ -57,6 +60,10 @@ impl Serialize for ListOrGeoJSONResponse {
 #[derive(Debug, Default, Deserialize)]
 pub struct ListRecordsQuery {
   pub geojson: Option<String>,
+  /// Column of the full-text index for which to return a `_snippet` of the `$match`.
+  pub snippet: Option<String>,
+  /// Column of the full-text index for which to return a `_highlight`ed `$match`.
+  pub highlight: Option<String>,
 }
 
 /// Lists records matching the given filters
@@ -106,6 +113,7 @@ pub async fn list_records_handler(
     cursor,
     count,
     expand: query_expand,
+    fields,
     order,
     filter: filter_params,
     offset,
@@ -119,30 +127,94 @@ pub async fn list_records_handler(
       return RecordError::BadRequest("Invalid query");
     })?;
 
+  // Full-text search `$match`es are evaluated against the FTS5 index associated with the table
+  // rather than the table itself.
+  let (filter_params, fts_match) = split_off_fts_match(filter_params)?;
+  let fts_table = match fts_match {
+    Some(_) => Some(
+      lookup_fts_table(api.conn(), api.qualified_name())
+        .await?
+        .ok_or(RecordError::BadRequest("Missing full-text index"))?,
+    ),
+    None => None,
+  };
+
+  // NOTE: Only accept columns that are both indexed, thus avoiding SQL injections, and exposed by
+  // the API, thus not leaking excluded columns.
+  let fts_column_index = |fts_table: &FtsTable, column: &str| {
+    if api.column_index_by_name(column).is_none() {
+      return Err(RecordError::BadRequest("Invalid full-text column"));
+    }
+    return fts_table
+      .column_index(column)
+      .ok_or(RecordError::BadRequest("Invalid full-text column"));
+  };
+
+  let fts_select_exprs: Vec<String> = match fts_table {
+    Some(ref fts_table) => {
+      let column_index = |column: &str| fts_column_index(fts_table, column);
+
+      let mut exprs: Vec<String> = vec![];
+      if let Some(ref column) = query.snippet {
+        exprs.push(format!(
+          "snippet({}, {}, '<b>', '</b>', '…', 16) AS _snippet_",
+          fts_table.table_column(),
+          column_index(column)?
+        ));
+      }
+      if let Some(ref column) = query.highlight {
+        exprs.push(format!(
+          "highlight({}, {}, '<b>', '</b>') AS _highlight_",
+          fts_table.table_column(),
+          column_index(column)?
+        ));
+      }
+      exprs
+    }
+    None => {
+      if query.snippet.is_some() || query.highlight.is_some() {
+        return Err(RecordError::BadRequest(
+          "snippet and highlight require $match",
+        ));
+      }
+      vec![]
+    }
+  };
+
   // Where clause contains column filters and cursor depending on what's present.
   // NOTE: This will also drop any filters for unknown columns, thus avoiding SQL injections.
   let WhereClause {
-    clause: filter_clause,
+    clause: mut filter_clause,
     mut params,
   } = build_filter_where_clause("_ROW_", api.columns(), filter_params)
     .map_err(|_err| RecordError::BadRequest("Invalid filter params"))?;
 
+  if let Some(ref fts_table) = fts_table
+    && let Some(fts_match) = fts_match
+  {
+    fts_column_index(fts_table, &fts_match.column)?;
+
+    filter_clause = format!(
+      r#"({filter_clause}) AND (_FTS_."{}" MATCH :__match)"#,
+      fts_match.column
+    );
+    params.push((
+      Cow::Borrowed(":__match"),
+      Value::Text(fts_match.value.to_string()),
+    ));
+  }
+
   let limit: usize =
     limit_or_default(limit, api.listing_hard_limit()).map_err(RecordError::BadRequest)?;
 
+  params.push((
+    Cow::Borrowed(":__limit"),
+    // NOTE: We want to query at least one record, otherwise limit=0 isn't very meaningful. It
+    // may be used to query the total count.
+    Value::Integer(limit.max(1) as i64),
+  ));
   // User properties
-  params.extend_from_slice(&[
-    (
-      Cow::Borrowed(":__limit"),
-      // NOTE: We want to query at least one record, otherwise limit=0 isn't very meaningful. It
-      // may be used to query the total count.
-      Value::Integer(limit.max(1) as i64),
-    ),
-    (
-      Cow::Borrowed(":__user_id"),
-      user.map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
-    ),
-  ]);
+  params.extend(user_params(user.as_ref()));
 
   if let Some(offset) = offset {
     params.push((
@@ -202,7 +274,11 @@ pub async fn list_records_handler(
     |o| {
       o.columns
         .iter()
-        .map(|(col, ord)| fmt_order(col, ord.clone()))
+        .map(|(col, ord)| match fts_table {
+          // Order by relevance, i.e. the FTS5 `rank`, if requested.
+          Some(_) if col == "rank" => fmt_rank_order(ord.clone()),
+          _ => fmt_order(col, ord.clone()),
+        })
         .join(",")
     },
   );
@@ -226,6 +302,22 @@ pub async fn list_records_handler(
     None => vec![],
   };
 
+  // Sparse fieldset. GeoJSON features always require the geometry and id.
+  let projection = match fields {
+    Some(ref fields) => {
+      let implied: Vec<&str> = geojson_geometry_column
+        .map(|meta| vec![meta.column.name.as_str(), pk_column.name.as_str()])
+        .unwrap_or_default();
+      Some(build_projection(&api, fields, &expanded_tables, &implied)?)
+    }
+    None => None,
+  };
+  let columns = projection
+    .as_ref()
+    .map_or(api.columns(), |p| p.columns.as_slice());
+
+  let fts_join_clause = fts_table.as_ref().map(|t| t.join_clause());
+
   // Execute the query.
   let rows = api
     .conn()
@@ -234,8 +326,7 @@ pub async fn list_records_handler(
       // have them be stripped later on by `rows_to_json`.
       ListRecordQueryTemplate {
         table_name,
-        column_names: &api
-          .columns()
+        column_names: &columns
           .iter()
           .map(|meta| meta.column.name.as_str())
           .collect::<Vec<_>>(),
@@ -246,6 +337,8 @@ pub async fn list_records_handler(
         cursor_clause: cursor_clause.as_deref(),
         order_clause: &order_clause,
         expanded_tables: &expanded_tables,
+        fts_join_clause: fts_join_clause.as_deref(),
+        fts_select_exprs: &fts_select_exprs,
         count: count.unwrap_or(false),
         offset: offset.is_some(),
         is_table,
@@ -304,10 +397,41 @@ pub async fn list_records_handler(
     None
   };
 
-  let records = if expanded_tables.is_empty() {
+  // Full-text search auxiliary outputs, e.g. `_snippet_`, which are otherwise filtered out.
+  let fts_outputs: Vec<Vec<(&'static str, serde_json::Value)>> = if fts_select_exprs.is_empty() {
+    vec![]
+  } else {
+    let indexes: Vec<(&'static str, usize)> =
+      [("_snippet", "_snippet_"), ("_highlight", "_highlight_")]
+        .into_iter()
+        .filter_map(|(key, column)| {
+          return (0..rows.column_count())
+            .find(|i| rows.column_name(*i) == Some(column))
+            .map(|i| (key, i));
+        })
+        .collect();
+
+    rows
+      .iter()
+      .map(|row| {
+        return indexes
+          .iter()
+          .map(|(key, i)| {
+            let value = match row.get_value(*i) {
+              Some(Value::Text(text)) => serde_json::Value::String(text.clone()),
+              _ => serde_json::Value::Null,
+            };
+            return (*key, value);
+          })
+          .collect();
+      })
+      .collect()
+  };
+
+  let mut records = if expanded_tables.is_empty() {
     rows
       .into_iter()
-      .map(|row| row_to_json_expand(api.columns(), &row, column_filter, api.expand()))
+      .map(|row| row_to_json_expand(columns, &row, column_filter, api.expand()))
       .collect::<Result<Vec<_>, JsonError>>()
       .map_err(|err| RecordError::Internal(err.into()))?
   } else {
@@ -321,12 +445,12 @@ pub async fn list_records_handler(
           ));
         };
 
-        let mut curr = row.split_off(api.columns().len());
+        let mut curr = row.split_off(columns.len());
 
         for expanded in &expanded_tables {
           let next = curr.split_off(expanded.num_columns);
 
-          let foreign_value = row_to_json_expand(
+          let mut foreign_value = row_to_json_expand(
             &expanded.metadata.column_metadata,
             &curr,
             column_filter,
@@ -334,13 +458,17 @@ pub async fn list_records_handler(
           )
           .map_err(|err| RecordError::Internal(err.into()))?;
 
+          if let Some(ref projection) = projection {
+            projection.project_foreign(&expanded.local_column_name, &mut foreign_value);
+          }
+
           let result = expand.insert(expanded.local_column_name.clone(), foreign_value);
           assert!(result.is_some());
 
           curr = next;
         }
 
-        return row_to_json_expand(api.columns(), &row, column_filter, Some(&expand))
+        return row_to_json_expand(columns, &row, column_filter, Some(&expand))
           .map_err(|err| RecordError::Internal(err.into()));
       })
       .collect::<Result<Vec<_>, RecordError>>()?
@@ -353,8 +481,9 @@ pub async fn list_records_handler(
     )));
   }
 
+  // NOTE: Sparse records are expected to lack required properties.
   #[cfg(debug_assertions)]
-  for record in &records {
+  for record in records.iter().filter(|_| projection.is_none()) {
     crate::records::json_schema::validate_api_json_schema(
       &state,
       &api,
@@ -363,6 +492,16 @@ pub async fn list_records_handler(
     )?;
   }
 
+  for (record, outputs) in records.iter_mut().zip(fts_outputs) {
+    if let serde_json::Value::Object(obj) = record {
+      obj.extend(
+        outputs
+          .into_iter()
+          .map(|(key, value)| (key.to_string(), value)),
+      );
+    }
+  }
+
   return Ok(Json(ListOrGeoJSONResponse::List(ListResponse {
     cursor,
     total_count,
@@ -432,6 +571,18 @@ fn fmt_order(col: &str, order: OrderPrecedent) -> String {
   );
 }
 
+fn fmt_rank_order(order: OrderPrecedent) -> String {
+  // NOTE: FTS5's rank is smaller for better matches, i.e. ascending order yields the most relevant
+  // matches first.
+  return format!(
+    "_FTS_.rank {}",
+    match order {
+      OrderPrecedent::Descending => "DESC",
+      OrderPrecedent::Ascending => "ASC",
+    }
+  );
+}
+
 #[inline]
 fn column_filter(col_name: &str) -> bool {
   return !col_name.starts_with("_");
@@ -472,6 +623,8 @@ struct ListRecordQueryTemplate<'a> {
   cursor_clause: Option<&'a str>,
   order_clause: &'a str,
   expanded_tables: &'a [ExpandedTable<'a>],
+  fts_join_clause: Option<&'a str>,
+  fts_select_exprs: &'a [String],
   count: bool,
   offset: bool,
   is_table: bool,
@@ -515,6 +668,8 @@ mod tests {
         cursor_clause: Some("TRUE"),
         order_clause: "NULL",
         expanded_tables: &[],
+        fts_join_clause: None,
+        fts_select_exprs: &[],
         count: false,
         offset: false,
         is_table: true,
@@ -536,6 +691,10 @@ mod tests {
         cursor_clause: None,
         order_clause: "'index' ASC",
         expanded_tables: &[],
+        fts_join_clause: Some(r#"INNER JOIN "table_fts" AS _FTS_ ON _FTS_.rowid = _ROW_._rowid_"#),
+        fts_select_exprs: &[
+          r#"snippet(_FTS_."table_fts", 0, '<b>', '</b>', '…', 16) AS _snippet_"#.to_string(),
+        ],
         count: true,
         offset: true,
         is_table: false,
@@ -630,6 +789,8 @@ mod tests {
       cursor_clause: None,
       order_clause: "tid",
       expanded_tables: &expanded_tables,
+      fts_join_clause: None,
+      fts_select_exprs: &[],
       count: true,
       offset: false,
       is_table: true,
@@ -768,6 +929,40 @@ mod tests {
       panic!("not a list");
     };
     assert_eq!(1, not_null_response.records.len());
+
+    let ListOrGeoJSONResponse::List(sparse_response) = list_records_handler(
+      State(state.clone()),
+      Path("api".to_string()),
+      Query(ListRecordsQuery::default()),
+      RawQuery(Some("fields=id,nullable&order=id&limit=2".to_string())),
+      None,
+    )
+    .await
+    .unwrap()
+    .0
+    else {
+      panic!("not a list");
+    };
+    assert_eq!(
+      sparse_response.records,
+      vec![
+        serde_json::json!({"id": 1, "nullable": 1}),
+        serde_json::json!({"id": 2, "nullable": null}),
+      ]
+    );
+    assert!(sparse_response.cursor.is_some());
+
+    assert!(
+      list_records_handler(
+        State(state.clone()),
+        Path("api".to_string()),
+        Query(ListRecordsQuery::default()),
+        RawQuery(Some("fields=id,UNKNOWN".to_string())),
+        None,
+      )
+      .await
+      .is_err()
+    );
   }
 
   #[tokio::test]
@@ -1275,6 +1470,197 @@ mod tests {
     assert_eq!(1, resp_filtered1.total_count.unwrap());
   }
 
+  #[tokio::test]
+  async fn test_record_api_list_full_text_search() {
+    let state = test_state(None).await.unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE post (
+            id       INTEGER PRIMARY KEY,
+            title    TEXT NOT NULL,
+            body     TEXT NOT NULL
+          ) STRICT;
+
+          CREATE VIRTUAL TABLE post_fts USING fts5(title, body, content='post', content_rowid='id');
+
+          INSERT INTO post (id, title, body) VALUES
+            (1, 'Rust', 'Rust is a systems programming language.'),
+            (2, 'SQLite', 'SQLite has a full-text search extension written in C.'),
+            (3, 'Rust & SQLite', 'Using SQLite from Rust, rust, rust.');
+
+          INSERT INTO post_fts (rowid, title, body) SELECT id, title, body FROM post;
+        "#,
+      )
+      .await
+      .unwrap();
+
+    state.rebuild_connection_metadata().await.unwrap();
+
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("post_api".to_string()),
+        table_name: Some("post".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let list = async |query: ListRecordsQuery, raw: &str| {
+      return list_records_handler(
+        State(state.clone()),
+        Path("post_api".to_string()),
+        Query(query),
+        RawQuery(Some(raw.to_string())),
+        None,
+      )
+      .await
+      .map(|resp| {
+        let ListOrGeoJSONResponse::List(resp) = resp.0 else {
+          panic!("not a list");
+        };
+        return resp;
+      });
+    };
+
+    let ids = |resp: &ListResponse| {
+      return resp
+        .records
+        .iter()
+        .map(|r| r["id"].as_i64().unwrap())
+        .collect::<Vec<_>>();
+    };
+
+    let resp = list(
+      ListRecordsQuery::default(),
+      "filter[body][$match]=rust&order=rank&count=true",
+    )
+    .await
+    .unwrap();
+    assert_eq!(vec![3, 1], ids(&resp));
+    assert_eq!(Some(2), resp.total_count);
+
+    // Combined with regular filters.
+    let resp = list(
+      ListRecordsQuery::default(),
+      "filter[body][$match]=sqlite&filter[id][$gt]=2",
+    )
+    .await
+    .unwrap();
+    assert_eq!(vec![3], ids(&resp));
+
+    let resp = list(
+      ListRecordsQuery {
+        snippet: Some("body".to_string()),
+        highlight: Some("title".to_string()),
+        ..Default::default()
+      },
+      "filter[body][$match]=extension",
+    )
+    .await
+    .unwrap();
+    assert_eq!(vec![2], ids(&resp));
+    assert_eq!(
+      resp.records[0]["_highlight"],
+      serde_json::Value::String("SQLite".to_string())
+    );
+    assert!(
+      resp.records[0]["_snippet"]
+        .as_str()
+        .unwrap()
+        .contains("<b>extension</b>")
+    );
+
+    // Only indexed columns can be matched and `$match` can only be used at the top-level.
+    assert!(
+      list(ListRecordsQuery::default(), "filter[id][$match]=rust")
+        .await
+        .is_err()
+    );
+    assert!(
+      list(
+        ListRecordsQuery::default(),
+        "filter[$or][0][body][$match]=rust&filter[$or][1][id]=1"
+      )
+      .await
+      .is_err()
+    );
+    assert!(
+      list(
+        ListRecordsQuery {
+          snippet: Some("body".to_string()),
+          ..Default::default()
+        },
+        "filter[id]=1"
+      )
+      .await
+      .is_err()
+    );
+
+    // Indexed columns excluded from the API can neither be matched nor be used for snippets.
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("post_title_api".to_string()),
+        table_name: Some("post".to_string()),
+        acl_world: [PermissionFlag::Read as i32].into(),
+        excluded_columns: vec!["body".to_string()],
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let list_titles = async |query: ListRecordsQuery, raw: &str| {
+      return list_records_handler(
+        State(state.clone()),
+        Path("post_title_api".to_string()),
+        Query(query),
+        RawQuery(Some(raw.to_string())),
+        None,
+      )
+      .await;
+    };
+
+    assert!(
+      list_titles(ListRecordsQuery::default(), "filter[title][$match]=rust")
+        .await
+        .is_ok()
+    );
+    assert!(
+      list_titles(ListRecordsQuery::default(), "filter[body][$match]=rust")
+        .await
+        .is_err()
+    );
+    assert!(
+      list_titles(
+        ListRecordsQuery {
+          snippet: Some("body".to_string()),
+          ..Default::default()
+        },
+        "filter[title][$match]=rust"
+      )
+      .await
+      .is_err()
+    );
+    assert!(
+      list_titles(
+        ListRecordsQuery {
+          highlight: Some("body".to_string()),
+          ..Default::default()
+        },
+        "filter[title][$match]=rust"
+      )
+      .await
+      .is_err()
+    );
+  }
+
   #[cfg(any(feature = "geos", feature = "geos-static"))]
   #[tokio::test]
   async fn test_record_api_geojson_list() {
@@ -1321,6 +1707,7 @@ mod tests {
         Path(name.to_string()),
         Query(ListRecordsQuery {
           geojson: Some("geom".to_string()),
+          ..Default::default()
         }),
         RawQuery(None),
         None,
@@ -1342,6 +1729,7 @@ mod tests {
         Path(name.to_string()),
         Query(ListRecordsQuery {
           geojson: Some("geom".to_string()),
+          ..Default::default()
         }),
         RawQuery(Some(format!(
           "filter[geom][@within]={polygon}",
@@ -1371,6 +1759,7 @@ mod tests {
         Path(name.to_string()),
         Query(ListRecordsQuery {
           geojson: Some("geom".to_string()),
+          ..Default::default()
         }),
         RawQuery(Some(format!(
           "filter[geom][@contains]={point}",
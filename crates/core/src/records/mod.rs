@@ -1,13 +1,16 @@
 use axum::{
   Router,
-  routing::{delete, get, patch, post},
+  routing::{delete, get, head, patch, post},
 };
 use utoipa::OpenApi;
 
+pub(crate) mod aggregate_records;
 pub(crate) mod create_record;
 pub(crate) mod delete_record;
 pub(crate) mod files;
 pub(crate) mod filter;
+pub(crate) mod hooks;
+pub(crate) mod image_transform;
 pub(crate) mod json_schema;
 pub(crate) mod list_records;
 pub(crate) mod params;
@@ -20,14 +23,18 @@ pub(crate) mod write_queries;
 
 mod error;
 mod expand;
+mod fts;
+mod presigned_upload;
+mod projection;
 mod record_api;
 mod transaction;
+mod tus;
 mod update_record;
 mod validate;
 
 pub(crate) use error::RecordError;
 pub use record_api::RecordApi;
-pub(crate) use validate::validate_record_api_config;
+pub(crate) use validate::{validate_image_transforms, validate_record_api_config};
 
 use crate::AppState;
 use crate::config::proto::PermissionFlag;
@@ -40,9 +47,17 @@ use crate::constants::{RECORD_API_PATH, TRANSACTION_API_PATH};
   read_record::get_uploaded_file_from_record_handler,
   read_record::get_uploaded_files_from_record_handler,
   list_records::list_records_handler,
+  aggregate_records::aggregate_records_handler,
   create_record::create_record_handler,
   update_record::update_record_handler,
   delete_record::delete_record_handler,
+  presigned_upload::create_presigned_upload_handler,
+  presigned_upload::complete_presigned_upload_handler,
+  tus::tus_options_handler,
+  tus::tus_create_upload_handler,
+  tus::tus_upload_offset_handler,
+  tus::tus_upload_chunk_handler,
+  tus::tus_terminate_upload_handler,
   json_schema::json_schema_handler,
   subscribe::handler::add_subscription_sse_and_ws_handler,
 ))]
@@ -78,6 +93,29 @@ pub(crate) fn router(enable_transactions: bool) -> Router<AppState> {
       &format!("/{RECORD_API_PATH}/{{name}}/{{record}}/files/{{column_name}}/{{file_name}}"),
       get(read_record::get_uploaded_files_from_record_handler),
     )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/{{record}}/upload/{{column_name}}"),
+      post(presigned_upload::create_presigned_upload_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/{{record}}/upload/{{column_name}}/{{upload_id}}"),
+      post(presigned_upload::complete_presigned_upload_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/{{record}}/tus/{{column_name}}"),
+      post(tus::tus_create_upload_handler).options(tus::tus_options_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/{{record}}/tus/{{column_name}}/{{upload_id}}"),
+      head(tus::tus_upload_offset_handler)
+        .patch(tus::tus_upload_chunk_handler)
+        .delete(tus::tus_terminate_upload_handler)
+        .options(tus::tus_options_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/aggregate"),
+      get(aggregate_records::aggregate_records_handler),
+    )
     .route(
       &format!("/{RECORD_API_PATH}/{{name}}/schema"),
       get(json_schema::json_schema_handler),
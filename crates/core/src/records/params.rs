@@ -366,6 +366,142 @@ impl Params {
       pk_column_name,
     });
   }
+
+  /// Points a `std.FileUpload` column at a file that is already present in the object store, e.g.
+  /// uploaded directly by the client using a presigned URL.
+  pub fn for_file_update<S: ColumnAccessor>(
+    accessor: &S,
+    json_schema_registry: &JsonSchemaRegistry,
+    column_name: &str,
+    file_upload: &FileUpload,
+    pk_column_name: String,
+    pk_column_value: Value,
+  ) -> Result<Self, ParamsError> {
+    let Some(ColumnMetadata {
+      index,
+      column,
+      json,
+      is_file: _,
+      is_geometry: _,
+    }) = accessor.column_by_name(column_name)
+    else {
+      return Err(ParamsError::Column("Unknown column"));
+    };
+
+    let Some(json_metadata @ JsonColumnMetadata::SchemaName(schema_name)) = json else {
+      return Err(ParamsError::Column("Expected json file column"));
+    };
+    if schema_name != "std.FileUpload" {
+      return Err(ParamsError::Column("Mismatching JSON schema"));
+    }
+
+    let value = serde_json::to_value(file_upload)?;
+    json_metadata.validate(json_schema_registry, &value)?;
+
+    return Ok(Params::Update {
+      named_params: vec![
+        (
+          named_placeholder(&column.name).into(),
+          Value::Text(value.to_string()),
+        ),
+        (":__pk_value".into(), pk_column_value),
+      ],
+      files: vec![],
+      column_names: vec![column.name.clone()],
+      column_indexes: vec![*index],
+      pk_column_name,
+    });
+  }
+
+  /// Merges the fields of `patch`, e.g. returned by a record hook, into already constructed
+  /// params. Values of columns already present are overridden.
+  ///
+  /// Patches may neither introduce new files nor change the primary key of an update.
+  pub fn merge<S: ColumnAccessor>(
+    &mut self,
+    accessor: &S,
+    json_schema_registry: &JsonSchemaRegistry,
+    patch: JsonRow,
+  ) -> Result<(), ParamsError> {
+    let (named_params, column_names, column_indexes, pk_column_name) = match self {
+      Params::Insert {
+        named_params,
+        column_names,
+        column_indexes,
+        ..
+      } => (named_params, column_names, column_indexes, None),
+      Params::Update {
+        named_params,
+        column_names,
+        column_indexes,
+        pk_column_name,
+        ..
+      } => (
+        named_params,
+        column_names,
+        column_indexes,
+        Some(pk_column_name.as_str()),
+      ),
+    };
+
+    for (key, value) in patch {
+      // Same as above, skip unknown columns.
+      let Some(ColumnMetadata {
+        index,
+        column,
+        json,
+        is_file: _,
+        is_geometry,
+      }) = accessor.column_by_name(&key)
+      else {
+        continue;
+      };
+
+      let (param, json_files) = extract_params_and_files_from_json(
+        json_schema_registry,
+        column,
+        json.as_ref(),
+        *is_geometry,
+        value,
+      )?;
+      if json_files.is_some_and(|files| !files.is_empty()) {
+        return Err(ParamsError::Column("Patch must not contain files"));
+      }
+
+      if pk_column_name == Some(key.as_str()) {
+        let pk_value = named_params
+          .iter()
+          .find_map(|(name, value)| (name == ":__pk_value").then_some(value));
+        if pk_value != Some(&param) {
+          return Err(ParamsError::Column(
+            "Primary key mismatch in update request",
+          ));
+        }
+      }
+
+      let placeholder = named_placeholder(&key);
+      if let Some(existing) = named_params
+        .iter_mut()
+        .find(|(name, _value)| *name == placeholder)
+      {
+        existing.1 = param;
+        continue;
+      }
+
+      named_params.push((placeholder.into(), param));
+      column_names.push(key);
+      column_indexes.push(*index);
+    }
+
+    return Ok(());
+  }
+
+  /// Takes the files out of the params, e.g. to write them to the object store ahead of time.
+  pub fn take_files(&mut self) -> FileMetadataContents {
+    return match self {
+      Params::Insert { files, .. } | Params::Update { files, .. } => std::mem::take(files),
+    };
+  }
 }
 
 /// A lazy representation of SQL query parameters derived from the request json to share between
@@ -892,4 +1028,101 @@ mod tests {
       );
     }
   }
+  #[tokio::test]
+  async fn test_params_merge() {
+    let registry = trailbase_schema::registry::build_json_schema_registry(vec![]).unwrap();
+
+    let table: Table = parse_into_statement(
+      r#"
+        CREATE TABLE test (
+          id INTEGER PRIMARY KEY,
+          text TEXT NOT NULL,
+          num INTEGER NOT NULL DEFAULT 42
+        )
+      "#,
+    )
+    .unwrap()
+    .unwrap()
+    .try_into()
+    .unwrap();
+
+    let metadata = TableMetadata::new(&registry, table.clone(), &[table]).unwrap();
+
+    let find_param = |params: &Params, name: &str| -> Option<Value> {
+      let (Params::Insert { named_params, .. } | Params::Update { named_params, .. }) = params;
+      return named_params
+        .iter()
+        .find_map(|(n, v)| (n == name).then(|| v.clone()));
+    };
+
+    {
+      let mut params = Params::for_insert(
+        &metadata,
+        &registry,
+        json_row_from_value(json!({"text": "original"})).unwrap(),
+        None,
+      )
+      .unwrap();
+
+      params
+        .merge(
+          &metadata,
+          &registry,
+          json_row_from_value(json!({"text": "patched", "num": 5, "unknown": 1})).unwrap(),
+        )
+        .unwrap();
+
+      assert_eq!(
+        find_param(&params, ":text"),
+        Some(Value::Text("patched".to_string()))
+      );
+      assert_eq!(find_param(&params, ":num"), Some(Value::Integer(5)));
+      assert_eq!(find_param(&params, ":unknown"), None);
+
+      let Params::Insert {
+        named_params,
+        column_names,
+        column_indexes,
+        ..
+      } = params
+      else {
+        panic!("Not an insert");
+      };
+      assert_eq!(named_params.len(), 2);
+      assert_eq!(column_names, vec!["text".to_string(), "num".to_string()]);
+      assert_eq!(column_indexes, vec![1, 2]);
+    }
+
+    {
+      let mut params = Params::for_update(
+        &metadata,
+        &registry,
+        json_row_from_value(json!({"text": "original"})).unwrap(),
+        None,
+        "id".to_string(),
+        Value::Integer(1),
+      )
+      .unwrap();
+
+      // Same pk is fine.
+      params
+        .merge(
+          &metadata,
+          &registry,
+          json_row_from_value(json!({"id": 1})).unwrap(),
+        )
+        .unwrap();
+
+      // Changing the pk is not.
+      assert!(
+        params
+          .merge(
+            &metadata,
+            &registry,
+            json_row_from_value(json!({"id": 2})).unwrap(),
+          )
+          .is_err()
+      );
+    }
+  }
 }
@@ -0,0 +1,598 @@
+use axum::{
+  Json,
+  extract::{Path, State},
+  http::Method,
+};
+use chrono::Utc;
+use log::*;
+use object_store::ObjectStoreExt;
+use object_store::signer::Signer;
+use serde::{Deserialize, Serialize};
+use trailbase_schema::FileUpload;
+use trailbase_schema::file::infer_mime_type;
+use trailbase_sqlite::params;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::constants::{
+  PENDING_UPLOAD_TABLE, PENDING_UPLOAD_TTL, PRESIGNED_UPLOAD_MAX_SIZE, PRESIGNED_UPLOAD_URL_TTL,
+};
+use crate::records::update_record::{assign_file_to_record, check_file_update_access};
+use crate::records::{RecordApi, RecordError};
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct CreatePresignedUploadRequest {
+  /// The file's original file name.
+  pub filename: Option<String>,
+  /// The file's content type.
+  pub content_type: Option<String>,
+  /// The file's exact size in bytes.
+  pub size: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreatePresignedUploadResponse {
+  /// Id of the pending upload, needed to complete the upload.
+  pub upload_id: String,
+  /// Presigned URL to PUT the file's contents to.
+  pub url: String,
+  /// Expiration of the presigned URL in seconds since epoch.
+  pub expires: i64,
+}
+
+/// Create a presigned URL for uploading a file directly to the object store.
+///
+/// Only available when using S3 storage. Once the file's contents have been PUT to the returned
+/// URL, the upload needs to be completed for the file to be assigned to the record.
+#[utoipa::path(
+  post,
+  path = "/{name}/{record}/upload/{column_name}",
+  tag = "records",
+  request_body = CreatePresignedUploadRequest,
+  responses(
+    (status = 200, description = "Presigned upload URL.", body = CreatePresignedUploadResponse)
+  )
+)]
+pub async fn create_presigned_upload_handler(
+  State(state): State<AppState>,
+  Path((api_name, record, column_name)): Path<(String, String, String)>,
+  user: Option<User>,
+  Json(request): Json<CreatePresignedUploadRequest>,
+) -> Result<Json<CreatePresignedUploadResponse>, RecordError> {
+  let Some(signer) = state.objectstore_signer() else {
+    return Err(RecordError::BadRequest(
+      "Presigned uploads require S3 storage",
+    ));
+  };
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+  if !api.is_table() {
+    return Err(RecordError::ApiRequiresTable);
+  }
+
+  if request.size > PRESIGNED_UPLOAD_MAX_SIZE {
+    return Err(RecordError::BadRequest("File too large"));
+  }
+
+  let record_id = api.primary_key_to_value(record.clone())?;
+  let file_id = Uuid::new_v4();
+
+  // Check access ahead of time with the prospective file. Access is checked again upon
+  // completion, when the file's actual mime type is known.
+  check_file_update_access(
+    &state,
+    &api,
+    record_id,
+    &column_name,
+    &FileUpload::new(
+      file_id,
+      request.filename.clone(),
+      request.content_type.clone(),
+      None,
+    ),
+    user.as_ref(),
+  )
+  .await?;
+
+  let url = signer
+    .signed_url(
+      Method::PUT,
+      &object_store::path::Path::from(file_id.to_string()),
+      PRESIGNED_UPLOAD_URL_TTL,
+    )
+    .await
+    .map_err(|err| RecordError::Internal(err.into()))?;
+
+  let url_expires = insert_pending_upload(
+    &state,
+    file_id,
+    &api_name,
+    &record,
+    &column_name,
+    user.as_ref(),
+    &request,
+  )
+  .await?;
+
+  return Ok(Json(CreatePresignedUploadResponse {
+    upload_id: file_id.to_string(),
+    url: url.to_string(),
+    expires: url_expires,
+  }));
+}
+
+/// Records a pending upload to be staged under `file_id`. Returns the expiration of its URL.
+async fn insert_pending_upload(
+  state: &AppState,
+  file_id: Uuid,
+  api_name: &str,
+  record: &str,
+  column_name: &str,
+  user: Option<&User>,
+  request: &CreatePresignedUploadRequest,
+) -> Result<i64, RecordError> {
+  let now = Utc::now();
+  let url_expires = now.timestamp() + PRESIGNED_UPLOAD_URL_TTL.as_secs() as i64;
+
+  state
+    .session_conn()
+    .execute(
+      format!(
+        "INSERT INTO '{PENDING_UPLOAD_TABLE}' \
+           (file_id, record_api, record_id, column_name, user, original_filename, content_type, size, expires, url_expires) \
+         VALUES \
+           (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"
+      ),
+      params!(
+        file_id.to_string(),
+        api_name.to_string(),
+        record.to_string(),
+        column_name.to_string(),
+        user.map(|u| u.uuid.into_bytes().to_vec()),
+        request.filename.clone(),
+        request.content_type.clone(),
+        request.size as i64,
+        (now + PENDING_UPLOAD_TTL).timestamp(),
+        url_expires,
+      ),
+    )
+    .await?;
+
+  return Ok(url_expires);
+}
+
+#[derive(Debug, Deserialize)]
+struct PendingUploadDb {
+  id: i64,
+  user: Option<Vec<u8>>,
+  original_filename: Option<String>,
+  content_type: Option<String>,
+  size: i64,
+  expires: i64,
+}
+
+/// Complete a presigned upload.
+///
+/// Verifies the uploaded file's size, mime type and the column's JSON schema before assigning the
+/// file to the record. The file is assigned under a new id, since the presigned URL of the staged
+/// upload remains valid for a while. Failed completions, e.g. before the file's contents have been
+/// uploaded, may be retried.
+#[utoipa::path(
+  post,
+  path = "/{name}/{record}/upload/{column_name}/{upload_id}",
+  tag = "records",
+  responses(
+    (status = 200, description = "Metadata of the assigned file.")
+  )
+)]
+pub async fn complete_presigned_upload_handler(
+  State(state): State<AppState>,
+  Path((api_name, record, column_name, upload_id)): Path<(String, String, String, String)>,
+  user: Option<User>,
+) -> Result<Json<FileUpload>, RecordError> {
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+  let Ok(file_id) = Uuid::parse_str(&upload_id) else {
+    return Err(RecordError::BadRequest("Invalid upload id"));
+  };
+
+  let Some(pending) = state
+    .session_conn()
+    .read_query_value::<PendingUploadDb>(
+      format!(
+        "SELECT id, user, original_filename, content_type, size, expires \
+         FROM '{PENDING_UPLOAD_TABLE}' \
+         WHERE file_id = ?1 AND record_api = ?2 AND record_id = ?3 AND column_name = ?4 \
+           AND consumed = FALSE"
+      ),
+      params!(
+        file_id.to_string(),
+        api_name.clone(),
+        record.clone(),
+        column_name.clone()
+      ),
+    )
+    .await?
+  else {
+    return Err(RecordError::RecordNotFound);
+  };
+
+  if pending.expires < Utc::now().timestamp() {
+    return Err(RecordError::BadRequest("Upload expired"));
+  }
+  if pending.user.as_deref() != user.as_ref().map(|u| u.uuid.as_bytes().as_slice()) {
+    return Err(RecordError::Forbidden);
+  }
+
+  // Check for the staged upload before consuming the pending upload, s.t. early completions can be
+  // retried.
+  let staged_path = object_store::path::Path::from(file_id.to_string());
+  match state.objectstore().head(&staged_path).await {
+    Ok(_) => {}
+    Err(object_store::Error::NotFound { .. }) => {
+      return Err(RecordError::BadRequest("Missing upload"));
+    }
+    Err(err) => {
+      return Err(RecordError::Internal(err.into()));
+    }
+  };
+
+  // Pending uploads can only be completed once. Consumed uploads are kept until their presigned
+  // URL expires, s.t. the cleanup job can delete anything PUT to the staged path in the meantime.
+  let pending_id = pending.id;
+  let claimed = state
+    .session_conn()
+    .execute(
+      format!(
+        "UPDATE '{PENDING_UPLOAD_TABLE}' SET consumed = TRUE WHERE id = ?1 AND consumed = FALSE"
+      ),
+      params!(pending_id),
+    )
+    .await?;
+  if claimed == 0 {
+    return Err(RecordError::RecordNotFound);
+  }
+
+  let result = complete_upload(
+    &state,
+    &api,
+    &record,
+    &column_name,
+    &staged_path,
+    pending,
+    user,
+  )
+  .await;
+
+  match result {
+    Ok(_) => {
+      // The staged upload has been copied. Anything PUT to it later is deleted by the cleanup job.
+      if let Err(err) = state.objectstore().delete(&staged_path).await {
+        debug!("Failed to delete staged upload {staged_path}: {err}");
+      }
+    }
+    Err(_) => {
+      // Release the pending upload for clients to retry, e.g. after re-uploading its contents.
+      if let Err(err) = state
+        .session_conn()
+        .execute(
+          format!("UPDATE '{PENDING_UPLOAD_TABLE}' SET consumed = FALSE WHERE id = ?1"),
+          params!(pending_id),
+        )
+        .await
+      {
+        warn!("Failed to release pending upload {file_id}: {err}");
+      }
+    }
+  };
+
+  return result.map(Json);
+}
+
+async fn complete_upload(
+  state: &AppState,
+  api: &RecordApi,
+  record: &str,
+  column_name: &str,
+  staged_path: &object_store::path::Path,
+  pending: PendingUploadDb,
+  user: Option<User>,
+) -> Result<FileUpload, RecordError> {
+  // Copy the staged upload first and only validate the copy. Otherwise, the contents could still
+  // be replaced using the presigned URL after validation.
+  let store = state.objectstore();
+  let file_id = Uuid::new_v4();
+  let path = object_store::path::Path::from(file_id.to_string());
+
+  match store.copy(staged_path, &path).await {
+    Ok(_) => {}
+    Err(object_store::Error::NotFound { .. }) => {
+      return Err(RecordError::BadRequest("Missing upload"));
+    }
+    Err(err) => {
+      return Err(RecordError::Internal(err.into()));
+    }
+  };
+
+  let result = validate_and_assign_upload(
+    state,
+    api,
+    record,
+    column_name,
+    file_id,
+    pending,
+    user.as_ref(),
+  )
+  .await;
+  if result.is_err() {
+    // Don't keep contents of rejected uploads around.
+    if let Err(err) = store.delete(&path).await {
+      debug!("Failed to delete rejected upload {path}: {err}");
+    }
+  }
+
+  return result;
+}
+
+async fn validate_and_assign_upload(
+  state: &AppState,
+  api: &RecordApi,
+  record: &str,
+  column_name: &str,
+  file_id: Uuid,
+  pending: PendingUploadDb,
+  user: Option<&User>,
+) -> Result<FileUpload, RecordError> {
+  let store = state.objectstore();
+  let path = object_store::path::Path::from(file_id.to_string());
+
+  let meta = store
+    .head(&path)
+    .await
+    .map_err(|err| RecordError::Internal(err.into()))?;
+  if meta.size != pending.size as u64 {
+    return Err(RecordError::BadRequest("Size mismatch"));
+  }
+
+  // Like for regular uploads, we don't trust the user provided content type and check ourselves.
+  const MAGIC_BYTES_LEN: u64 = 8192;
+  let mime_type = if meta.size > 0 {
+    let head = store
+      .get_range(&path, 0..meta.size.min(MAGIC_BYTES_LEN))
+      .await
+      .map_err(|err| RecordError::Internal(err.into()))?;
+    infer_mime_type(&head)
+  } else {
+    None
+  };
+
+  if let (Some(content_type), Some(mime_type)) = (&pending.content_type, &mime_type)
+    && !content_type_matches(content_type, mime_type)
+  {
+    return Err(RecordError::BadRequest("Content type mismatch"));
+  }
+
+  let file_upload = FileUpload::new(
+    file_id,
+    pending.original_filename,
+    pending.content_type,
+    mime_type,
+  );
+
+  assign_file_to_record(state, api, record, column_name, &file_upload, user).await?;
+
+  return Ok(file_upload);
+}
+
+/// Compares mime types w/o parameters, e.g. "text/plain; charset=utf-8" matches "text/plain".
+fn content_type_matches(content_type: &str, mime_type: &str) -> bool {
+  let essence = |s: &str| {
+    return s
+      .split(';')
+      .next()
+      .unwrap_or_default()
+      .trim()
+      .to_ascii_lowercase();
+  };
+  return essence(content_type) == essence(mime_type);
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::extract::Query;
+
+  use super::*;
+  use crate::app_state::test_state;
+  use crate::config::proto::{PermissionFlag, RecordApiConfig};
+  use crate::extract::Either;
+  use crate::records::create_record::{
+    CreateRecordQuery, CreateRecordResponse, create_record_handler,
+  };
+  use crate::records::params::JsonRow;
+  use crate::records::test_utils::*;
+  use crate::scheduler::delete_expired_uploads_job;
+  use crate::test::unpack_json_response;
+
+  #[test]
+  fn test_content_type_matches() {
+    assert!(content_type_matches("image/png", "image/png"));
+    assert!(content_type_matches(
+      "Text/Plain; charset=utf-8",
+      "text/plain"
+    ));
+    assert!(!content_type_matches("image/png", "application/pdf"));
+  }
+
+  #[tokio::test]
+  async fn test_presigned_upload() {
+    let state = test_state(None).await.unwrap();
+    state
+      .conn()
+      .execute(
+        r#"CREATE TABLE test_table (
+          id           BLOB PRIMARY KEY NOT NULL CHECK(is_uuid_v7(id)) DEFAULT(uuid_v7()),
+          file         TEXT CHECK(jsonschema('std.FileUpload', file))
+        ) STRICT"#,
+        (),
+      )
+      .await
+      .unwrap();
+    state.rebuild_connection_metadata().await.unwrap();
+
+    const API_NAME: &str = "test_api";
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some(API_NAME.to_string()),
+        table_name: Some("test_table".to_string()),
+        acl_world: [
+          PermissionFlag::Create as i32,
+          PermissionFlag::Read as i32,
+          PermissionFlag::Update as i32,
+        ]
+        .into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let create_response: CreateRecordResponse = unpack_json_response(
+      create_record_handler(
+        State(state.clone()),
+        Path(API_NAME.to_string()),
+        Query(CreateRecordQuery::default()),
+        None,
+        Either::Json(JsonRow::new().into()),
+      )
+      .await
+      .unwrap(),
+    )
+    .await
+    .unwrap();
+    let record_id = create_response.ids[0].clone();
+
+    let bytes = b"%PDF-1.7 some pdf".to_vec();
+    let request = CreatePresignedUploadRequest {
+      filename: Some("doc.pdf".to_string()),
+      content_type: Some("application/pdf".to_string()),
+      size: bytes.len() as u64,
+    };
+    let presign = create_presigned_upload_handler(
+      State(state.clone()),
+      Path((API_NAME.to_string(), record_id.clone(), "file".to_string())),
+      None,
+      Json(request.clone()),
+    )
+    .await;
+
+    // Presigned URLs are only available for S3 storage, e.g. using `TEST_S3_OBJECT_STORE=TRUE`
+    // with a local S3-compatible server. Otherwise, stage the upload directly.
+    let (upload_id, url) = match state.objectstore_signer() {
+      Some(_) => {
+        let Json(presign) = presign.unwrap();
+        (presign.upload_id, Some(presign.url))
+      }
+      None => {
+        assert!(matches!(presign, Err(RecordError::BadRequest(_))));
+
+        let file_id = Uuid::new_v4();
+        insert_pending_upload(
+          &state, file_id, API_NAME, &record_id, "file", None, &request,
+        )
+        .await
+        .unwrap();
+        (file_id.to_string(), None)
+      }
+    };
+
+    let store = state.objectstore();
+    let staged_path = object_store::path::Path::from(upload_id.clone());
+    let complete = async || {
+      return complete_presigned_upload_handler(
+        State(state.clone()),
+        Path((
+          API_NAME.to_string(),
+          record_id.clone(),
+          "file".to_string(),
+          upload_id.clone(),
+        )),
+        None,
+      )
+      .await;
+    };
+
+    // Completing before uploading is rejected but doesn't consume the pending upload.
+    assert!(matches!(
+      complete().await,
+      Err(RecordError::BadRequest("Missing upload"))
+    ));
+
+    match url {
+      Some(url) => {
+        let response = reqwest::Client::new()
+          .put(&url)
+          .body(bytes.clone())
+          .send()
+          .await
+          .unwrap();
+        assert!(response.status().is_success(), "{response:?}");
+      }
+      None => {
+        store.put(&staged_path, bytes.clone().into()).await.unwrap();
+      }
+    };
+
+    let Json(file_upload) = complete().await.unwrap();
+    assert_ne!(file_upload.objectstore_id(), upload_id);
+    assert_eq!(file_upload.content_type(), Some("application/pdf"));
+
+    // The staged upload is replaced by a copy, thus the presigned URL cannot be used to alter the
+    // assigned file.
+    assert!(matches!(
+      store.head(&staged_path).await,
+      Err(object_store::Error::NotFound { .. })
+    ));
+    let assigned_path = object_store::path::Path::from(file_upload.objectstore_id());
+    let contents = store
+      .get(&assigned_path)
+      .await
+      .unwrap()
+      .bytes()
+      .await
+      .unwrap();
+    assert_eq!(contents.to_vec(), bytes);
+
+    // Pending uploads can only be completed once.
+    assert!(matches!(complete().await, Err(RecordError::RecordNotFound)));
+
+    // Contents PUT to the staged path after completion are deleted, once the URL has expired.
+    store.put(&staged_path, bytes.clone().into()).await.unwrap();
+    let partial_uploads_path = state.data_dir().partial_uploads_path();
+    delete_expired_uploads_job(state.session_conn(), store, &partial_uploads_path)
+      .await
+      .unwrap();
+    assert!(store.head(&staged_path).await.is_ok());
+
+    state
+      .session_conn()
+      .execute(
+        format!("UPDATE '{PENDING_UPLOAD_TABLE}' SET url_expires = UNIXEPOCH() - 1"),
+        (),
+      )
+      .await
+      .unwrap();
+    delete_expired_uploads_job(state.session_conn(), store, &partial_uploads_path)
+      .await
+      .unwrap();
+    assert!(matches!(
+      store.head(&staged_path).await,
+      Err(object_store::Error::NotFound { .. })
+    ));
+    assert!(store.head(&assigned_path).await.is_ok());
+  }
+}
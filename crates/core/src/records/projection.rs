@@ -0,0 +1,84 @@
+use std::collections::{HashMap, HashSet};
+use trailbase_qs::Fields;
+use trailbase_schema::metadata::ColumnMetadata;
+
+use crate::records::RecordError;
+use crate::records::expand::ExpandedTable;
+use crate::records::record_api::RecordApi;
+
+/// Sparse fieldset of a read or list request, validated against the API's schema metadata.
+#[derive(Debug)]
+pub(crate) struct Projection {
+  /// Metadata of the selected columns in table order, i.e. the SELECT list.
+  pub columns: Vec<ColumnMetadata>,
+  /// Selected columns of expanded foreign records keyed by foreign-key column name. Foreign
+  /// records w/o entry are returned in full.
+  foreign: HashMap<String, HashSet<String>>,
+}
+
+impl Projection {
+  /// Strips all unselected columns from an expanded foreign record.
+  pub fn project_foreign(&self, fk_column: &str, value: &mut serde_json::Value) {
+    if let Some(columns) = self.foreign.get(fk_column)
+      && let serde_json::Value::Object(obj) = value
+    {
+      obj.retain(|key, _| columns.contains(key));
+    }
+  }
+}
+
+/// Builds a projection from the requested `fields`.
+///
+/// `implied` columns are always selected, e.g. the geometry column for GeoJSON responses.
+/// Foreign columns, i.e. `<fk>.<column>`, are only valid for expanded foreign keys and imply the
+/// foreign-key column itself.
+pub(crate) fn build_projection(
+  api: &RecordApi,
+  fields: &Fields,
+  expanded_tables: &[ExpandedTable<'_>],
+  implied: &[&str],
+) -> Result<Projection, RecordError> {
+  let mut local: HashSet<&str> = implied.iter().copied().collect();
+  let mut foreign: HashMap<String, HashSet<String>> = HashMap::new();
+
+  for column_name in fields.local() {
+    if api.column_index_by_name(column_name).is_none() {
+      return Err(RecordError::BadRequest("Invalid field"));
+    }
+    local.insert(column_name);
+  }
+
+  for (fk_column, column_name) in fields.foreign() {
+    let Some(expanded) = expanded_tables
+      .iter()
+      .find(|e| e.local_column_name == fk_column)
+    else {
+      return Err(RecordError::BadRequest("Invalid field, not expanded"));
+    };
+
+    if !expanded
+      .metadata
+      .column_metadata
+      .iter()
+      .any(|meta| meta.column.name == column_name)
+    {
+      return Err(RecordError::BadRequest("Invalid field"));
+    }
+
+    local.insert(fk_column);
+    foreign
+      .entry(fk_column.to_string())
+      .or_default()
+      .insert(column_name.to_string());
+  }
+
+  return Ok(Projection {
+    columns: api
+      .columns()
+      .iter()
+      .filter(|meta| local.contains(meta.column.name.as_str()))
+      .cloned()
+      .collect(),
+    foreign,
+  });
+}
@@ -1,16 +1,24 @@
 use axum::{
   Json,
   extract::{Path, Query, State},
+  http::HeaderMap,
   response::Response,
 };
 use serde::Deserialize;
+use trailbase_qs::Fields;
 use trailbase_schema::FileUploads;
+use trailbase_schema::metadata::ColumnMetadata;
+use utoipa::IntoParams;
 
 use crate::app_state::AppState;
 use crate::auth::user::User;
 use crate::records::expand::expand_tables;
 use crate::records::expand::row_to_json_expand;
 use crate::records::files::read_file_into_response;
+use crate::records::image_transform::{
+  ImageFit, ImageFormat, ImageTransform, read_image_variant_into_response,
+};
+use crate::records::projection::build_projection;
 use crate::records::read_queries::{
   ExpandedSelectQueryResult, run_expanded_select_query, run_get_file_query, run_get_files_query,
   run_select_query,
@@ -23,6 +31,11 @@ pub struct ReadRecordQuery {
   ///
   /// Requires the API's configuration to explicitly allow expanding said columns.
   pub expand: Option<String>,
+
+  /// Comma separated list of columns to return, e.g. `id,name,author.name`. All by default.
+  ///
+  /// Columns of expanded foreign records are referenced as `<fk>.<column>`.
+  pub fields: Option<Fields>,
 }
 
 /// Read record.
@@ -51,13 +64,6 @@ pub async fn read_record_handler(
     .await?;
 
   let pk_meta = api.record_pk_column();
-  let column_names = || {
-    api
-      .columns()
-      .iter()
-      .map(|meta| meta.column.name.as_str())
-      .collect::<Vec<_>>()
-  };
 
   if let Some(query_expand) = query.expand
     && !query_expand.is_empty()
@@ -77,10 +83,19 @@ pub async fn read_record_handler(
     let metadata = api.connection_metadata();
     let expanded_tables = expand_tables(&api, &metadata, &query_expand)?;
 
+    let projection = query
+      .fields
+      .as_ref()
+      .map(|fields| build_projection(&api, fields, &expanded_tables, &[]))
+      .transpose()?;
+    let columns = projection
+      .as_ref()
+      .map_or(api.columns(), |p| p.columns.as_slice());
+
     let Some(ExpandedSelectQueryResult { root, foreign_rows }) = run_expanded_select_query(
       api.conn(),
       api.table_name(),
-      &column_names(),
+      &column_names(columns),
       &pk_meta.column.name,
       record_id,
       &expanded_tables,
@@ -95,23 +110,36 @@ pub async fn read_record_handler(
     let mut expand = expand.clone();
 
     for (col_name, (metadata, row)) in std::iter::zip(query_expand, foreign_rows) {
-      let foreign_value = row_to_json_expand(&metadata.column_metadata, &row, prefix_filter, None)
-        .map_err(|err| RecordError::Internal(err.into()))?;
+      let mut foreign_value =
+        row_to_json_expand(&metadata.column_metadata, &row, prefix_filter, None)
+          .map_err(|err| RecordError::Internal(err.into()))?;
+      if let Some(ref projection) = projection {
+        projection.project_foreign(col_name, &mut foreign_value);
+      }
 
       let result = expand.insert(col_name.to_string(), foreign_value);
       debug_assert!(result.is_some(), "{col_name} duplicate");
     }
 
     return Ok(Json(
-      row_to_json_expand(api.columns(), &root, prefix_filter, Some(&expand))
+      row_to_json_expand(columns, &root, prefix_filter, Some(&expand))
         .map_err(|err| RecordError::Internal(err.into()))?,
     ));
   }
 
+  let projection = query
+    .fields
+    .as_ref()
+    .map(|fields| build_projection(&api, fields, &[], &[]))
+    .transpose()?;
+  let columns = projection
+    .as_ref()
+    .map_or(api.columns(), |p| p.columns.as_slice());
+
   let Some(row) = run_select_query(
     api.conn(),
     api.table_name(),
-    &column_names(),
+    &column_names(columns),
     &pk_meta.column.name,
     record_id,
   )
@@ -120,20 +148,40 @@ pub async fn read_record_handler(
     return Err(RecordError::RecordNotFound);
   };
 
-  let json_response = row_to_json_expand(api.columns(), &row, prefix_filter, api.expand())
+  let json_response = row_to_json_expand(columns, &row, prefix_filter, api.expand())
     .map_err(|err| RecordError::Internal(err.into()))?;
 
+  // NOTE: Sparse records are expected to lack required properties.
   #[cfg(debug_assertions)]
-  crate::records::json_schema::validate_api_json_schema(
-    &state,
-    &api,
-    trailbase_schema::json_schema::JsonSchemaMode::Select,
-    &json_response,
-  )?;
+  if projection.is_none() {
+    crate::records::json_schema::validate_api_json_schema(
+      &state,
+      &api,
+      trailbase_schema::json_schema::JsonSchemaMode::Select,
+      &json_response,
+    )?;
+  }
 
   return Ok(Json(json_response));
 }
 
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct ReadFileQuery {
+  /// Serve the file with `Content-Disposition: inline` rather than as an attachment, e.g. to let
+  /// browsers play media directly. Only honored for content types that are safe to render.
+  pub inline: Option<bool>,
+
+  /// Width in pixels to resize images to. Requires a matching image transform to be configured
+  /// for the Record API.
+  pub w: Option<u32>,
+  /// Height in pixels to resize images to.
+  pub h: Option<u32>,
+  /// How images are fit into the requested dimensions. Default: contain.
+  pub fit: Option<ImageFit>,
+  /// Format to convert images to. Default: the original's format.
+  pub format: Option<ImageFormat>,
+}
+
 type GetUploadedFileFromRecordPath = Path<(
   String, // RecordApi name
   String, // Record id
@@ -148,6 +196,7 @@ type GetUploadedFileFromRecordPath = Path<(
   get,
   path = "/{name}/{record}/file/{column_name}",
   tag = "records",
+  params(ReadFileQuery),
   responses(
     (status = 200, description = "File contents.")
   )
@@ -155,7 +204,9 @@ type GetUploadedFileFromRecordPath = Path<(
 pub async fn get_uploaded_file_from_record_handler(
   state: State<AppState>,
   Path((api_name, record, column_name)): GetUploadedFileFromRecordPath,
+  Query(query): Query<ReadFileQuery>,
   user: Option<User>,
+  headers: HeaderMap,
 ) -> Result<Response, RecordError> {
   let Some(api) = state.lookup_record_api(&api_name) else {
     return Err(RecordError::ApiNotFound);
@@ -185,7 +236,20 @@ pub async fn get_uploaded_file_from_record_handler(
   )
   .await?;
 
-  return read_file_into_response(&state, file_upload)
+  let inline = query.inline.unwrap_or(false);
+  if let Some(transform) = ImageTransform::from_query(&query) {
+    return read_image_variant_into_response(
+      &state,
+      api.image_transforms(),
+      file_upload,
+      transform,
+      &headers,
+      inline,
+    )
+    .await;
+  }
+
+  return read_file_into_response(&state, file_upload, &headers, inline)
     .await
     .map_err(|err| RecordError::Internal(err.into()));
 }
@@ -204,6 +268,7 @@ type GetUploadedFilesFromRecordPath = Path<(
   get,
   path = "/{name}/{record}/files/{column_name}/{file_name}",
   tag = "records",
+  params(ReadFileQuery),
   responses(
     (status = 200, description = "File contents.")
   )
@@ -211,7 +276,9 @@ type GetUploadedFilesFromRecordPath = Path<(
 pub async fn get_uploaded_files_from_record_handler(
   State(state): State<AppState>,
   Path((api_name, record, column_name, file_name)): GetUploadedFilesFromRecordPath,
+  Query(query): Query<ReadFileQuery>,
   user: Option<User>,
+  headers: HeaderMap,
 ) -> Result<Response, RecordError> {
   let Some(api) = state.lookup_record_api(&api_name) else {
     return Err(RecordError::ApiNotFound);
@@ -239,11 +306,31 @@ pub async fn get_uploaded_files_from_record_handler(
     .find(|f| f.filename() == file_name)
     .ok_or_else(|| RecordError::RecordNotFound)?;
 
-  return read_file_into_response(&state, file_upload)
+  let inline = query.inline.unwrap_or(false);
+  if let Some(transform) = ImageTransform::from_query(&query) {
+    return read_image_variant_into_response(
+      &state,
+      api.image_transforms(),
+      file_upload,
+      transform,
+      &headers,
+      inline,
+    )
+    .await;
+  }
+
+  return read_file_into_response(&state, file_upload, &headers, inline)
     .await
     .map_err(|err| RecordError::Internal(err.into()));
 }
 
+fn column_names(columns: &[ColumnMetadata]) -> Vec<&str> {
+  return columns
+    .iter()
+    .map(|meta| meta.column.name.as_str())
+    .collect();
+}
+
 #[inline]
 fn prefix_filter(col_name: &str) -> bool {
   return !col_name.starts_with("_");
@@ -257,6 +344,7 @@ mod test {
 
   use axum::Json;
   use axum::extract::{Path, Query, State};
+  use axum::http::{StatusCode, header};
   use serde::Serialize;
   use serde_json::json;
   use trailbase_schema::{FileUpload, FileUploadInput};
@@ -264,9 +352,10 @@ mod test {
   use super::*;
   use crate::admin::user::*;
   use crate::app_state::*;
+  use crate::auth::roles::{assign_role, create_role};
   use crate::auth::user::User;
   use crate::auth::util::login_with_password;
-  use crate::config::proto::{JsonSchemaConfig, PermissionFlag, RecordApiConfig};
+  use crate::config::proto::{JsonSchemaConfig, PermissionFlag, RecordApiConfig, RoleAcl};
   use crate::constants::USER_TABLE;
   use crate::extract::Either;
   use crate::records::create_record::{
@@ -423,6 +512,95 @@ mod test {
     }
   }
 
+  #[tokio::test]
+  async fn test_record_api_read_with_roles() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    create_chat_message_app_tables(&state).await.unwrap();
+    let room = add_room(conn, "room0").await.unwrap();
+    let password = "Secret!1!!";
+
+    // Only editors have table-level read access.
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("editor_messages_api".to_string()),
+        table_name: Some("message".to_string()),
+        acl_roles: vec![RoleAcl {
+          role: Some("editor".to_string()),
+          acl: [PermissionFlag::Read as i32].into(),
+        }],
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    // All authenticated users have table-level read access, but the access rule requires the
+    // moderator role.
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some("moderator_messages_api".to_string()),
+        table_name: Some("message".to_string()),
+        acl_authenticated: [PermissionFlag::Read as i32].into(),
+        read_access_rule: Some(
+          "'moderator' IN (SELECT value FROM json_each(_USER_.roles))".to_string(),
+        ),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let user_conn = state.user_conn();
+    create_role(user_conn, "editor", None).await.unwrap();
+    create_role(user_conn, "moderator", None).await.unwrap();
+
+    let editor_email = "editor@test.com";
+    let editor = create_user_for_test(&state, editor_email, password)
+      .await
+      .unwrap();
+    assign_role(user_conn, &editor, "editor").await.unwrap();
+
+    let moderator_email = "moderator@test.com";
+    let moderator = create_user_for_test(&state, moderator_email, password)
+      .await
+      .unwrap();
+    assign_role(user_conn, &moderator, "moderator")
+      .await
+      .unwrap();
+
+    let message_id = send_message(conn, editor.into_bytes(), room, "msg")
+      .await
+      .unwrap();
+
+    let read = async |api_name: &str, email: &str| {
+      let tokens = login_with_password(&state, email, password).await.unwrap();
+      let user = User::from_auth_token(&state, &tokens.auth_token).unwrap();
+      assert!(!user.roles.is_empty());
+
+      return read_record_handler(
+        State(state.clone()),
+        Path((api_name.to_string(), id_to_b64(&message_id))),
+        Query(ReadRecordQuery::default()),
+        Some(user),
+      )
+      .await;
+    };
+
+    assert!(read("editor_messages_api", editor_email).await.is_ok());
+    assert!(read("editor_messages_api", moderator_email).await.is_err());
+
+    assert!(
+      read("moderator_messages_api", moderator_email)
+        .await
+        .is_ok()
+    );
+    assert!(read("moderator_messages_api", editor_email).await.is_err());
+  }
+
   async fn create_test_record_api(state: &AppState, api_name: &str) {
     let conn = state.conn();
 
@@ -610,7 +788,9 @@ mod test {
     let read_response = get_uploaded_file_from_record_handler(
       State(state.clone()),
       Path(record_file_path.clone()),
+      Query(ReadFileQuery::default()),
       None,
+      HeaderMap::new(),
     )
     .await
     .unwrap();
@@ -620,6 +800,59 @@ mod test {
       .unwrap();
     assert_eq!(body.to_vec(), bytes);
 
+    // Range requests.
+    let mut range_headers = HeaderMap::new();
+    range_headers.insert(header::RANGE, "bytes=1-2".parse().unwrap());
+    let range_response = get_uploaded_file_from_record_handler(
+      State(state.clone()),
+      Path(record_file_path.clone()),
+      Query(ReadFileQuery::default()),
+      None,
+      range_headers,
+    )
+    .await
+    .unwrap();
+    assert_eq!(range_response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+      range_response.headers().get(header::CACHE_CONTROL).unwrap(),
+      "private"
+    );
+    assert_eq!(
+      range_response.headers().get(header::CONTENT_RANGE).unwrap(),
+      "bytes 1-2/4"
+    );
+    let etag = range_response.headers().get(header::ETAG).unwrap().clone();
+    assert_eq!(
+      etag,
+      format!("\"{}\"", file_upload.objectstore_id()).as_str()
+    );
+
+    let body = axum::body::to_bytes(range_response.into_body(), usize::MAX)
+      .await
+      .unwrap();
+    assert_eq!(body.to_vec(), bytes[1..3]);
+
+    // Conditional requests.
+    let mut conditional_headers = HeaderMap::new();
+    conditional_headers.insert(header::IF_NONE_MATCH, etag);
+    let not_modified_response = get_uploaded_file_from_record_handler(
+      State(state.clone()),
+      Path(record_file_path.clone()),
+      Query(ReadFileQuery::default()),
+      None,
+      conditional_headers,
+    )
+    .await
+    .unwrap();
+    assert_eq!(not_modified_response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(
+      not_modified_response
+        .headers()
+        .get(header::CACHE_CONTROL)
+        .unwrap(),
+      "private"
+    );
+
     let _ = delete_record_handler(State(state.clone()), Path(record_path.clone()), None)
       .await
       .unwrap();
@@ -636,7 +869,9 @@ mod test {
       get_uploaded_file_from_record_handler(
         State(state.clone()),
         Path(record_file_path.clone()),
+        Query(ReadFileQuery::default()),
         None,
+        HeaderMap::new(),
       )
       .await
       .is_err()
@@ -754,7 +989,9 @@ mod test {
           return get_uploaded_file_from_record_handler(
             State(state.clone()),
             Path((API_NAME.to_string(), record_id.clone(), "file".to_string())),
+            Query(ReadFileQuery::default()),
             None,
+            HeaderMap::new(),
           )
           .await
           .unwrap();
@@ -769,7 +1006,9 @@ mod test {
               "files".to_string(),
               files[0].filename().to_string(),
             )),
+            Query(ReadFileQuery::default()),
             None,
+            HeaderMap::new(),
           )
           .await
           .unwrap();
@@ -784,7 +1023,9 @@ mod test {
               "files".to_string(),
               files[1].filename().to_string(),
             )),
+            Query(ReadFileQuery::default()),
             None,
+            HeaderMap::new(),
           )
           .await
           .unwrap();
@@ -1151,6 +1392,7 @@ mod test {
       Path(("child_api".to_string(), "1".to_string())),
       Query(ReadRecordQuery {
         expand: Some("parent".to_string()),
+        ..Default::default()
       }),
       None,
     )
@@ -1178,6 +1420,7 @@ mod test {
       Path(("child_view_api".to_string(), "1".to_string())),
       Query(ReadRecordQuery {
         expand: Some("parent".to_string()),
+        ..Default::default()
       }),
       None,
     )
@@ -1185,6 +1428,73 @@ mod test {
     .unwrap();
 
     assert_eq!(value, expected);
+
+    // Sparse fieldsets.
+    let fields = |columns: &[&str]| {
+      return Some(trailbase_qs::Fields {
+        columns: columns.iter().map(|c| c.to_string()).collect(),
+      });
+    };
+
+    let Json(value) = read_record_handler(
+      State(state.clone()),
+      Path(("child_api".to_string(), "1".to_string())),
+      Query(ReadRecordQuery {
+        fields: fields(&["id"]),
+        ..Default::default()
+      }),
+      None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(value, json!({"id": 1}));
+
+    let Json(value) = read_record_handler(
+      State(state.clone()),
+      Path(("child_api".to_string(), "1".to_string())),
+      Query(ReadRecordQuery {
+        expand: Some("parent".to_string()),
+        fields: fields(&["parent.value"]),
+      }),
+      None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+      value,
+      json!({
+        "parent": {
+          "id": 1,
+          "data": {
+            "value":"first",
+          },
+        },
+      })
+    );
+
+    for (expand, invalid) in [
+      (Some("parent"), &["UNKNOWN"][..]),
+      (Some("parent"), &["parent.UNKNOWN"]),
+      // Foreign fields require expansion.
+      (None, &["parent.value"]),
+    ] {
+      assert!(
+        read_record_handler(
+          State(state.clone()),
+          Path(("child_api".to_string(), "1".to_string())),
+          Query(ReadRecordQuery {
+            expand: expand.map(|e| e.to_string()),
+            fields: fields(invalid),
+          }),
+          None,
+        )
+        .await
+        .is_err(),
+        "{invalid:?}"
+      );
+    }
   }
 
   #[tokio::test]
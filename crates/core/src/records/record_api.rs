@@ -11,7 +11,7 @@ use trailbase_schema::{QualifiedName, QualifiedNameEscaped};
 use trailbase_sqlite::{Connection, NamedParams, SyncConnectionTrait, Value};
 
 use crate::auth::user::User;
-use crate::config::proto::{ConflictResolutionStrategy, RecordApiConfig};
+use crate::config::proto::{ConflictResolutionStrategy, ImageTransform, RecordApiConfig};
 use crate::constants::USER_TABLE;
 use crate::records::params::{LazyParams, Params};
 use crate::records::util::named_placeholder;
@@ -141,6 +141,7 @@ struct RecordApiState {
   // Below properties are filled from `proto::RecordApiConfig`.
   api_name: String,
   acl: [u8; 2],
+  acl_roles: Vec<(String, u8)>,
   insert_conflict_resolution_strategy: Option<ConflictResolutionStrategy>,
   insert_autofill_missing_user_id_columns: bool,
   enable_subscriptions: bool,
@@ -150,6 +151,9 @@ struct RecordApiState {
 
   listing_hard_limit: Option<usize>,
 
+  // Allowed image transformations for file columns.
+  image_transforms: Vec<ImageTransform>,
+
   // Open question: right now the read_access rule is also used for listing. It might be nice to
   // allow different permissions, however there's a risk of listing records w/o read access.
   // Arguably, this could always be modeled as two APIs with different permissions on the same
@@ -326,11 +330,18 @@ impl RecordApi {
 
         listing_hard_limit: config.listing_hard_limit.map(|l| l as usize),
 
+        image_transforms: config.image_transforms.clone(),
+
         // Access control lists.
         acl: [
           convert_acl(&config.acl_world),
           convert_acl(&config.acl_authenticated),
         ],
+        acl_roles: config
+          .acl_roles
+          .iter()
+          .filter_map(|role_acl| Some((role_acl.role.clone()?, convert_acl(&role_acl.acl))))
+          .collect(),
         // Access rules.
         //
         // Create:
@@ -444,6 +455,11 @@ impl RecordApi {
     return self.state.listing_hard_limit;
   }
 
+  #[inline]
+  pub fn image_transforms(&self) -> &[ImageTransform] {
+    return &self.state.image_transforms;
+  }
+
   #[inline]
   pub fn insert_autofill_missing_user_id_columns(&self) -> bool {
     return self.state.insert_autofill_missing_user_id_columns;
@@ -574,12 +590,29 @@ impl RecordApi {
     p: Permission,
     user: Option<&User>,
   ) -> Result<(), RecordError> {
+    // API keys are additionally restricted to their scope.
+    if let Some(scope) = user.and_then(|u| u.api_key.as_ref())
+      && !scope.allows(self.api_name(), p)
+    {
+      return Err(RecordError::Forbidden);
+    }
+
     if (user.is_some() && self.has_access(Entity::Authenticated, p))
       || self.has_access(Entity::World, p)
     {
       return Ok(());
     }
 
+    if let Some(user) = user
+      && self
+        .state
+        .acl_roles
+        .iter()
+        .any(|(role, acl)| (acl & (p as u8)) > 0 && user.roles.contains(role))
+    {
+      return Ok(());
+    }
+
     return Err(RecordError::Forbidden);
   }
 
@@ -656,10 +689,7 @@ impl RecordApi {
       Permission::Read | Permission::Delete | Permission::Schema => NamedParams::with_capacity(2),
     };
 
-    params.push((
-      Cow::Borrowed(":__user_id"),
-      user.map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
-    ));
+    params.extend(user_params(user));
     params.push((
       Cow::Borrowed(":__record_id"),
       record_id.map_or(Value::Null, |id| id.clone()),
@@ -669,6 +699,29 @@ impl RecordApi {
   }
 }
 
+/// Builds the named params backing the `_USER_` table available to access rules: the user's id,
+/// roles as JSON array and custom claims as JSON object.
+pub(crate) fn user_params(user: Option<&User>) -> [(Cow<'static, str>, Value); 3] {
+  return [
+    (
+      Cow::Borrowed(":__user_id"),
+      user.map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
+    ),
+    (
+      Cow::Borrowed(":__user_roles"),
+      user.map_or(Value::Null, |u| {
+        Value::Text(serde_json::to_string(&u.roles).expect("json array"))
+      }),
+    ),
+    (
+      Cow::Borrowed(":__user_claims"),
+      user.map_or(Value::Null, |u| {
+        Value::Text(serde_json::to_string(&u.claims).expect("json object"))
+      }),
+    ),
+  ];
+}
+
 struct SubscriptionAclParams {
   params: Arc<indexmap::IndexMap<String, trailbase_sqlite::Value>>,
   user: Option<User>,
@@ -685,10 +738,10 @@ impl trailbase_sqlite::Params for SubscriptionAclParams {
       };
     }
 
-    if let Some(user) = self.user
-      && let Some(idx) = stmt.parameter_index(":__user_id")?
-    {
-      stmt.bind_parameter(idx, trailbase_sqlite::Value::Blob(user.uuid.into()).into())?;
+    for (name, v) in user_params(self.user.as_ref()) {
+      if let Some(idx) = stmt.parameter_index(&name)? {
+        stmt.bind_parameter(idx, (&v).into())?;
+      }
     }
 
     return Ok(());
@@ -719,7 +772,7 @@ fn build_read_delete_schema_query(
       SELECT \
         CAST(({access_rule}) AS INTEGER) \
       FROM \
-        (SELECT :__user_id AS id) AS _USER_, \
+        (SELECT :__user_id AS id, :__user_roles AS roles, :__user_claims AS claims) AS _USER_, \
         (SELECT * FROM {qualified_table_name} WHERE \"{pk_column_name}\" = :__record_id) AS _ROW_ \
     ",
   )
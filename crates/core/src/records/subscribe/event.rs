@@ -18,6 +18,9 @@ pub enum EventErrorStatus {
   /// additional losses that may happen between the TrailBase server and the client. This
   /// needs to be determined client-side based on event `seq` numbers.
   Loss = 2,
+  /// Events missed while disconnected could not be replayed, e.g. because the resume token is too
+  /// old or unknown. Clients need to refetch to get back in sync.
+  Resync = 3,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -26,6 +29,37 @@ pub struct EventError {
   pub message: Option<String>,
 }
 
+/// Identifies an event's position within a table's replay buffer, allowing reconnecting clients to
+/// resume where they left off, e.g. via SSE's `Last-Event-ID`.
+///
+/// The epoch identifies a continuous stream of events. It changes whenever events may have been
+/// missed, e.g. after a server restart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResumeToken {
+  pub epoch: u64,
+  pub seq: i64,
+}
+
+impl std::fmt::Display for ResumeToken {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return write!(f, "{:x}-{}", self.epoch, self.seq);
+  }
+}
+
+impl std::str::FromStr for ResumeToken {
+  type Err = RecordError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = || RecordError::BadRequest("Invalid resume token");
+
+    let (epoch, seq) = s.split_once('-').ok_or_else(invalid)?;
+    return Ok(Self {
+      epoch: u64::from_str_radix(epoch, 16).map_err(|_| invalid())?,
+      seq: seq.parse().map_err(|_| invalid())?,
+    });
+  }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum JsonEventPayload {
   Update { value: JsonObject },
@@ -89,31 +123,54 @@ impl EventPayload {
     };
   }
 
+  /// Converts into an SSE event. The resume token, if any, is sent as the SSE event id, which
+  /// clients will send back as `Last-Event-ID` when reconnecting.
   #[inline]
   pub fn into_sse_event(
     self: Arc<EventPayload>,
     seq: Option<i64>,
+    resume: Option<ResumeToken>,
   ) -> Result<SseEvent, RecordError> {
     return match *self {
       Self::Ping => Ok(SseEvent::default().comment("ping")),
       _ => {
-        let ev = ChangeEvent { event: self, seq };
+        let ev = ChangeEvent {
+          event: self,
+          seq,
+          resume: None,
+        };
         let s = serde_json::to_string(&ev).map_err(|err| RecordError::Internal(err.into()))?;
-        Ok(SseEvent::default().data(&s))
+        let event = SseEvent::default().data(&s);
+        Ok(match resume {
+          Some(resume) => event.id(resume.to_string()),
+          None => event,
+        })
       }
     };
   }
 
+  /// Converts into a WebSocket message. Since there's no equivalent to SSE event ids, the resume
+  /// token, if any, is sent as part of the message.
   #[cfg(feature = "ws")]
   #[inline]
-  pub fn into_ws_event(self: Arc<EventPayload>) -> Result<axum::extract::ws::Message, RecordError> {
+  pub fn into_ws_event(
+    self: Arc<EventPayload>,
+    resume: Option<ResumeToken>,
+  ) -> Result<axum::extract::ws::Message, RecordError> {
     return match *self {
       Self::Ping => Err(RecordError::Internal("not implemented".into())),
-      _ => Ok(axum::extract::ws::Message::Text(
-        serde_json::to_string(&*self)
-          .map_err(|err| RecordError::Internal(err.into()))?
-          .into(),
-      )),
+      _ => {
+        let ev = ChangeEvent {
+          event: self,
+          seq: None,
+          resume: resume.map(|r| r.to_string()),
+        };
+        Ok(axum::extract::ws::Message::Text(
+          serde_json::to_string(&ev)
+            .map_err(|err| RecordError::Internal(err.into()))?
+            .into(),
+        ))
+      }
     };
   }
 }
@@ -125,6 +182,9 @@ pub struct ChangeEvent {
   // NOTE: Because unsigned isn't supported by Avro.
   #[serde(skip_serializing_if = "Option::is_none")]
   seq: Option<i64>,
+  /// Resume token for transports w/o native event ids, i.e. WebSockets.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  resume: Option<String>,
 }
 
 #[cfg(test)]
@@ -146,6 +206,9 @@ pub struct TestChangeEvent {
   #[serde(flatten)]
   pub event: TestJsonEventPayload,
   pub seq: Option<i64>,
+  /// Populated from the SSE event id.
+  #[serde(skip)]
+  pub resume: Option<String>,
 }
 
 #[cfg(test)]
@@ -161,6 +224,7 @@ mod tests {
           value: JsonObject::from_iter([("foo".to_string(), json!(4))]),
         })),
         seq: Some(4),
+        resume: None,
       };
 
       let value = serde_json::to_value(&event).unwrap();
@@ -184,6 +248,7 @@ mod tests {
           },
         })),
         seq: Some(4),
+        resume: None,
       };
 
       let expected = serde_json::json!({
@@ -197,6 +262,12 @@ mod tests {
       assert_eq!(expected, serde_json::to_value(&event).unwrap());
     }
 
+    assert_eq!(
+      ResumeToken { epoch: 255, seq: 7 },
+      "ff-7".parse::<ResumeToken>().unwrap()
+    );
+    assert!("ff".parse::<ResumeToken>().is_err());
+
     {
       let json = r#"
             {
@@ -14,7 +14,7 @@ use crate::auth::User;
 use crate::records::RecordApi;
 use crate::records::filter::{Filter, apply_filter_recursively_to_record};
 use crate::records::subscribe::event::{
-  EventError, EventErrorStatus, EventPayload, JsonEventPayload,
+  EventError, EventErrorStatus, EventPayload, JsonEventPayload, ResumeToken,
 };
 use crate::records::subscribe::state::{EventCandidate, Subscription};
 use crate::records::{Permission, RecordError};
@@ -27,6 +27,10 @@ pub struct SubscriptionQuery {
 
   /// Whether to use WebSocket instead of default SSE.
   pub ws: Option<bool>,
+
+  /// Resume token of the last received event to replay missed events after reconnecting.
+  /// Alternatively, SSE clients may provide the `Last-Event-ID` header.
+  pub resume: Option<String>,
 }
 
 impl SubscriptionQuery {
@@ -56,7 +60,7 @@ pub async fn add_subscription_sse_and_ws_handler(
   Path((api_name, record)): Path<(String, String)>,
   user: Option<User>,
   RawQuery(raw_url_query): RawQuery,
-  _request: Request,
+  request: Request,
 ) -> Result<Response, RecordError> {
   let Some(api) = state.lookup_record_api(&api_name) else {
     return Err(RecordError::ApiNotFound);
@@ -66,7 +70,7 @@ pub async fn add_subscription_sse_and_ws_handler(
     return Err(RecordError::Forbidden);
   }
 
-  let SubscriptionQuery { filter, ws } = raw_url_query
+  let SubscriptionQuery { filter, ws, resume } = raw_url_query
     .as_ref()
     .map_or_else(
       || Ok(SubscriptionQuery::default()),
@@ -76,10 +80,23 @@ pub async fn add_subscription_sse_and_ws_handler(
       return RecordError::BadRequest("Invalid query");
     })?;
 
+  // NOTE: Browsers' EventSource automatically sends the id of the last received event when
+  // reconnecting.
+  let resume: Option<ResumeToken> = resume
+    .as_deref()
+    .or_else(|| {
+      request
+        .headers()
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+    })
+    .map(|token| token.parse())
+    .transpose()?;
+
   return if ws.unwrap_or(false) {
     #[cfg(feature = "ws")]
     {
-      subscribe_ws(state, api, record, filter, user, _request).await
+      subscribe_ws(state, api, record, filter, user, resume, request).await
     }
 
     #[cfg(not(feature = "ws"))]
@@ -87,7 +104,7 @@ pub async fn add_subscription_sse_and_ws_handler(
       Err(RecordError::BadRequest("ws unsupported"))
     }
   } else {
-    subscribe_sse(state, api, record, filter, user).await
+    subscribe_sse(state, api, record, filter, user, resume).await
   };
 }
 
@@ -99,18 +116,21 @@ struct ValidateEventArgs {
   expected_candidate_seq: AtomicI64,
 }
 
+/// Validated event ready to be sent alongside its resume token.
+type ValidatedEvent = (Arc<EventPayload>, Option<ResumeToken>);
+
 async fn validate_event(
   args: Arc<ValidateEventArgs>,
   ev: EventCandidate,
-) -> Result<Option<Arc<EventPayload>>, RecordError> {
+) -> Result<Option<ValidatedEvent>, RecordError> {
   if ev.seq != args.expected_candidate_seq.fetch_add(1, Ordering::SeqCst) {
     args.expected_candidate_seq.store(ev.seq, Ordering::SeqCst);
-    return Ok(Some(EVENT_LOSS_EVENT.clone()));
+    return Ok(Some((EVENT_LOSS_EVENT.clone(), None)));
   }
 
   let Some(ref record) = ev.record else {
-    // Established events.
-    return Ok(Some(ev.payload));
+    // Events not associated with a record, e.g. resync requests.
+    return Ok(Some((ev.payload, ev.resume)));
   };
 
   let sub: &Subscription = &args.subscription;
@@ -130,7 +150,7 @@ async fn validate_event(
     .check_record_level_read_access_for_subscriptions(record, sub.user.as_ref())
     .await?;
 
-  return Ok(Some(ev.payload));
+  return Ok(Some((ev.payload, ev.resume)));
 }
 
 pub async fn subscribe_sse(
@@ -139,6 +159,7 @@ pub async fn subscribe_sse(
   record: String,
   filter: Option<ValueOrComposite>,
   user: Option<User>,
+  resume: Option<ResumeToken>,
 ) -> Result<Response, RecordError> {
   let seq = Arc::new(AtomicI64::default());
 
@@ -148,7 +169,7 @@ pub async fn subscribe_sse(
 
       let (receiver, subscription) = state
         .subscription_manager()
-        .add_sse_table_subscription(api, user, filter)
+        .add_sse_table_subscription(api, user, filter, resume)
         .await?;
 
       let args = Arc::new(ValidateEventArgs {
@@ -157,18 +178,29 @@ pub async fn subscribe_sse(
         expected_candidate_seq: AtomicI64::default(),
       });
 
+      // Send an immediate comment to flush SSE headers and establish the connection.
+      let established = ESTABLISHED_EVENT
+        .clone()
+        .into_sse_event(Some(seq.fetch_add(1, Ordering::SeqCst)), None);
+
       Ok(
-        Sse::new(receiver.filter_map(move |ev: EventCandidate| {
-          let seq = seq.clone();
-          let args = args.clone();
-
-          return async move {
-            validate_event(args.clone(), ev)
-              .await
-              .unwrap_or_default()
-              .map(|ev| ev.into_sse_event(Some(seq.fetch_add(1, Ordering::SeqCst))))
-          };
-        }))
+        Sse::new(
+          stream::once(std::future::ready(established)).chain(receiver.filter_map(
+            move |ev: EventCandidate| {
+              let seq = seq.clone();
+              let args = args.clone();
+
+              return async move {
+                validate_event(args.clone(), ev)
+                  .await
+                  .unwrap_or_default()
+                  .map(|(ev, resume)| {
+                    ev.into_sse_event(Some(seq.fetch_add(1, Ordering::SeqCst)), resume)
+                  })
+              };
+            },
+          )),
+        )
         .keep_alive(KeepAlive::default())
         .into_response(),
       )
@@ -181,7 +213,7 @@ pub async fn subscribe_sse(
 
       let (receiver, subscription) = state
         .subscription_manager()
-        .add_sse_record_subscription(api, record_id, user)
+        .add_sse_record_subscription(api, record_id, user, resume)
         .await?;
 
       let args = Arc::new(ValidateEventArgs {
@@ -190,36 +222,44 @@ pub async fn subscribe_sse(
         expected_candidate_seq: AtomicI64::default(),
       });
 
+      // Send an immediate comment to flush SSE headers and establish the connection.
+      let established = ESTABLISHED_EVENT
+        .clone()
+        .into_sse_event(Some(seq.fetch_add(1, Ordering::SeqCst)), None);
+
       Ok(
         Sse::new(
-          receiver
-            .then(move |ev: EventCandidate| {
-              let seq = seq.clone();
-              let args = args.clone();
-
-              return async move {
-                match validate_event(args.clone(), ev).await {
-                  Ok(None) => stream::empty().boxed(),
-                  Ok(Some(ev)) => stream::once(std::future::ready(
-                    ev.into_sse_event(Some(seq.fetch_add(1, Ordering::SeqCst))),
-                  ))
-                  .boxed(),
-                  Err(_) => {
-                    // Death sentence for record subscriptions to not have access
-                    stream::iter(vec![
-                      // First send an error event to the user.
-                      ACCESS_DENIED_EVENT
-                        .clone()
-                        .into_sse_event(Some(seq.fetch_add(1, Ordering::SeqCst))),
-                      // Then terminate the stream via the `take_while` below.
-                      Err(RecordError::Forbidden),
-                    ])
-                    .boxed()
-                  }
-                }
-              };
-            })
-            .flatten()
+          stream::once(std::future::ready(established))
+            .chain(
+              receiver
+                .then(move |ev: EventCandidate| {
+                  let seq = seq.clone();
+                  let args = args.clone();
+
+                  return async move {
+                    match validate_event(args.clone(), ev).await {
+                      Ok(None) => stream::empty().boxed(),
+                      Ok(Some((ev, resume))) => stream::once(std::future::ready(
+                        ev.into_sse_event(Some(seq.fetch_add(1, Ordering::SeqCst)), resume),
+                      ))
+                      .boxed(),
+                      Err(_) => {
+                        // Death sentence for record subscriptions to not have access
+                        stream::iter(vec![
+                          // First send an error event to the user.
+                          ACCESS_DENIED_EVENT
+                            .clone()
+                            .into_sse_event(Some(seq.fetch_add(1, Ordering::SeqCst)), None),
+                          // Then terminate the stream via the `take_while` below.
+                          Err(RecordError::Forbidden),
+                        ])
+                        .boxed()
+                      }
+                    }
+                  };
+                })
+                .flatten(),
+            )
             .take_while(|event: &Result<SseEvent, RecordError>| std::future::ready(event.is_ok())),
         )
         .keep_alive(KeepAlive::default())
@@ -243,6 +283,7 @@ pub async fn subscribe_ws(
   record: String,
   filter: Option<ValueOrComposite>,
   mut user: Option<User>,
+  resume: Option<ResumeToken>,
   request: Request,
 ) -> Result<Response, RecordError> {
   use axum::extract::FromRequestParts;
@@ -250,7 +291,7 @@ pub async fn subscribe_ws(
   use futures_util::SinkExt;
   use std::sync::Arc;
 
-  use crate::records::subscribe::state::AutoCleanupEventStream;
+  use crate::records::subscribe::state::{AutoCleanupEventStream, event_channel};
 
   let (mut parts, _body) = request.into_parts();
   let ws = match WebSocketUpgrade::from_request_parts(&mut parts, &state).await {
@@ -307,8 +348,8 @@ pub async fn subscribe_ws(
 
     let mut pinned_receiver = std::pin::pin!(receiver);
     while let Some(ev) = pinned_receiver.next().await {
-      let payload = match validate_event(args.clone(), ev).await {
-        Ok(Some(payload)) => payload,
+      let (payload, resume) = match validate_event(args.clone(), ev).await {
+        Ok(Some(validated)) => validated,
         Ok(None) => {
           continue;
         }
@@ -317,7 +358,7 @@ pub async fn subscribe_ws(
             // Death sentence for record subscriptions to not have access
             let _ = ACCESS_DENIED_EVENT
               .clone()
-              .into_ws_event()
+              .into_ws_event(None)
               .map(|ev| sender.send(ev));
             return;
           } else {
@@ -326,7 +367,7 @@ pub async fn subscribe_ws(
         }
       };
 
-      match payload.into_ws_event() {
+      match payload.into_ws_event(resume) {
         Ok(msg) => {
           if let Err(_value) = sender.send(msg).await {
             log::debug!("Sending WS event to client failed");
@@ -393,8 +434,6 @@ pub async fn subscribe_ws(
   return match record.as_str() {
     "*" => {
       Ok(ws.on_upgrade(async move |socket: WebSocket| {
-        use crate::records::subscribe::state::EventCandidate;
-
         let Some(mut ws_sender) = init(&state, socket, &mut user).await else {
           return;
         };
@@ -407,12 +446,12 @@ pub async fn subscribe_ws(
           return;
         }
 
-        let (sender, receiver) = async_channel::bounded::<EventCandidate>(64);
+        let (sender, receiver) = event_channel(resume.as_ref());
         let conn_state = state.subscription_manager().get_per_connection_state(&api);
 
         let Ok(subscription) = conn_state
           .clone()
-          .add_table_subscription(api, user, filter, sender)
+          .add_table_subscription(api, user, filter, resume, sender)
           .await
         else {
           abort(&mut ws_sender, Code::Unexpected, "subscription failed").await;
@@ -428,8 +467,6 @@ pub async fn subscribe_ws(
       let record_id = api.primary_key_to_value(record)?;
 
       Ok(ws.on_upgrade(async move |socket: WebSocket| {
-        use crate::records::subscribe::state::EventCandidate;
-
         let Some(mut ws_sender) = init(&state, socket, &mut user).await else {
           return;
         };
@@ -445,12 +482,12 @@ pub async fn subscribe_ws(
           return;
         }
 
-        let (sender, receiver) = async_channel::bounded::<EventCandidate>(64);
+        let (sender, receiver) = event_channel(resume.as_ref());
         let conn_state = state.subscription_manager().get_per_connection_state(&api);
 
         let Ok(subscription) = conn_state
           .clone()
-          .add_record_subscription(api, record_id, user, sender)
+          .add_record_subscription(api, record_id, user, resume, sender)
           .await
         else {
           abort(&mut ws_sender, Code::Unexpected, "subscription failed").await;
@@ -465,6 +502,10 @@ pub async fn subscribe_ws(
   };
 }
 
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+static ESTABLISHED_EVENT: LazyLock<Arc<EventPayload>> =
+  LazyLock::new(|| Arc::new(EventPayload::from(&JsonEventPayload::Ping)));
 static ACCESS_DENIED_EVENT: LazyLock<Arc<EventPayload>> = LazyLock::new(|| {
   Arc::new(EventPayload::from(&JsonEventPayload::Error {
     value: EventError {
@@ -489,5 +530,6 @@ mod tests {
   #[test]
   fn static_sse_event_test() {
     let _x: Arc<EventPayload> = (*ACCESS_DENIED_EVENT).clone();
+    let _y: Arc<EventPayload> = (*ESTABLISHED_EVENT).clone();
   }
 }
@@ -1,17 +1,16 @@
 use parking_lot::{Mutex, RwLock};
 use std::collections::{HashMap, hash_map::Entry};
-use std::sync::atomic::Ordering;
-use std::sync::{Arc, LazyLock};
+use std::sync::Arc;
 use trailbase_qs::ValueOrComposite;
 use trailbase_reactive::Reactive;
 
 use crate::auth::User;
 use crate::records::RecordApi;
 use crate::records::RecordError;
-use crate::records::subscribe::event::{EventPayload, JsonEventPayload};
+use crate::records::subscribe::event::ResumeToken;
 use crate::records::subscribe::state::{
-  AutoCleanupEventStream, EventCandidate, PerConnectionState, PerConnectionStateInternal,
-  Subscription,
+  AutoCleanupEventStream, PerConnectionState, PerConnectionStateInternal, Subscription,
+  event_channel,
 };
 
 /// Internal, shareable state of the cloneable SubscriptionManager.
@@ -79,28 +78,16 @@ impl SubscriptionManager {
     api: RecordApi,
     user: Option<User>,
     filter: Option<ValueOrComposite>,
+    resume: Option<ResumeToken>,
   ) -> Result<(AutoCleanupEventStream, Arc<Subscription>), RecordError> {
-    let (sender, receiver) = async_channel::bounded::<EventCandidate>(64);
+    let (sender, receiver) = event_channel(resume.as_ref());
     let state = self.get_per_connection_state(&api);
 
     let subscription = state
       .clone()
-      .add_table_subscription(api, user, filter, sender.clone())
+      .add_table_subscription(api, user, filter, resume, sender)
       .await?;
 
-    // Send an immediate comment to flush SSE headers and establish the connection
-    if sender
-      .send(EventCandidate {
-        record: None,
-        payload: ESTABLISHED_EVENT.clone(),
-        seq: subscription.candidate_seq.fetch_add(1, Ordering::SeqCst),
-      })
-      .await
-      .is_err()
-    {
-      return Err(RecordError::BadRequest("channel already closed"));
-    }
-
     return Ok((
       AutoCleanupEventStream::new(receiver, state, subscription.id.clone()),
       subscription,
@@ -112,28 +99,16 @@ impl SubscriptionManager {
     api: RecordApi,
     record: trailbase_sqlite::Value,
     user: Option<User>,
+    resume: Option<ResumeToken>,
   ) -> Result<(AutoCleanupEventStream, Arc<Subscription>), RecordError> {
-    let (sender, receiver) = async_channel::bounded::<EventCandidate>(64);
+    let (sender, receiver) = event_channel(resume.as_ref());
     let state = self.get_per_connection_state(&api);
 
     let subscription = state
       .clone()
-      .add_record_subscription(api, record, user, sender.clone())
+      .add_record_subscription(api, record, user, resume, sender)
       .await?;
 
-    // Send an immediate comment to flush SSE headers and establish the connection
-    if sender
-      .send(EventCandidate {
-        record: None,
-        payload: ESTABLISHED_EVENT.clone(),
-        seq: subscription.candidate_seq.fetch_add(1, Ordering::SeqCst),
-      })
-      .await
-      .is_err()
-    {
-      return Err(RecordError::BadRequest("channel already closed"));
-    }
-
     return Ok((
       AutoCleanupEventStream::new(receiver, state, subscription.id.clone()),
       subscription,
@@ -157,6 +132,9 @@ impl SubscriptionManager {
               record_apis: filter_record_apis(id, &self.state.record_apis.value()),
               conn: api.conn().clone(),
               subscriptions: Default::default(),
+              replay_buffers: Default::default(),
+              hook_installed: false,
+              idle_since: None,
             }),
           });
           v.insert(state).clone()
@@ -165,7 +143,6 @@ impl SubscriptionManager {
     });
   }
 
-  #[cfg(test)]
   pub fn num_record_subscriptions(&self) -> usize {
     let mut count: usize = 0;
     for state in self.state.connections.read().values() {
@@ -178,7 +155,6 @@ impl SubscriptionManager {
     return count;
   }
 
-  #[cfg(test)]
   pub fn num_table_subscriptions(&self) -> usize {
     let mut count: usize = 0;
     for state in self.state.connections.read().values() {
@@ -205,16 +181,3 @@ fn filter_record_apis(conn_id: usize, record_apis: &[RecordApi]) -> HashMap<Stri
     })
     .collect();
 }
-
-static ESTABLISHED_EVENT: LazyLock<Arc<EventPayload>> =
-  LazyLock::new(|| Arc::new(EventPayload::from(&JsonEventPayload::Ping)));
-
-#[cfg(test)]
-mod tests {
-  use super::*;
-
-  #[test]
-  fn static_establish_event_test() {
-    let _y: Arc<EventPayload> = (*ESTABLISHED_EVENT).clone();
-  }
-}
@@ -3,11 +3,12 @@ use futures_util::Stream;
 use log::*;
 use parking_lot::Mutex;
 use pin_project_lite::pin_project;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::pin::Pin;
 use std::sync::atomic::{AtomicI64, Ordering};
-use std::sync::{Arc, Weak};
+use std::sync::{Arc, LazyLock, Weak};
 use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 use trailbase_qs::ValueOrComposite;
 use trailbase_schema::QualifiedName;
 use trailbase_schema::json::value_to_flat_json;
@@ -16,7 +17,9 @@ use crate::auth::User;
 use crate::records::RecordApi;
 use crate::records::RecordError;
 use crate::records::filter::{Filter, qs_filter_to_record_filter};
-use crate::records::subscribe::event::{EventPayload, JsonEventPayload};
+use crate::records::subscribe::event::{
+  EventError, EventErrorStatus, EventPayload, JsonEventPayload, ResumeToken,
+};
 use crate::records::subscribe::hook::{
   PreupdateHookEvent, RecordAction, install_hook, uninstall_hook,
 };
@@ -123,6 +126,97 @@ pub struct EventCandidate {
   pub record: Option<Arc<indexmap::IndexMap<String, trailbase_sqlite::Value>>>,
   pub payload: Arc<EventPayload>,
   pub seq: i64,
+  /// Position in the table's replay buffer, if any.
+  pub resume: Option<ResumeToken>,
+}
+
+/// Creates the channel for sending events to a subscriber. Channels for resuming subscribers have
+/// extra capacity to fit the replayed events.
+pub fn event_channel(
+  resume: Option<&ResumeToken>,
+) -> (
+  async_channel::Sender<EventCandidate>,
+  async_channel::Receiver<EventCandidate>,
+) {
+  return async_channel::bounded::<EventCandidate>(if resume.is_some() {
+    CHANNEL_CAPACITY + REPLAY_CAPACITY
+  } else {
+    CHANNEL_CAPACITY
+  });
+}
+
+#[derive(Debug)]
+struct ReplayEvent {
+  seq: i64,
+  row_id: i64,
+  record: Arc<indexmap::IndexMap<String, trailbase_sqlite::Value>>,
+  payload: Arc<EventPayload>,
+}
+
+/// Bounded buffer of a table's most recent change events, which allows subscribers to resume
+/// after reconnecting w/o missing events.
+#[derive(Debug)]
+pub struct ReplayBuffer {
+  epoch: u64,
+  next_seq: i64,
+  events: VecDeque<ReplayEvent>,
+}
+
+impl ReplayBuffer {
+  fn new() -> Self {
+    return Self {
+      epoch: rand::random(),
+      next_seq: 0,
+      events: VecDeque::with_capacity(REPLAY_CAPACITY),
+    };
+  }
+
+  fn push(
+    &mut self,
+    row_id: i64,
+    record: Arc<indexmap::IndexMap<String, trailbase_sqlite::Value>>,
+    payload: Arc<EventPayload>,
+  ) -> ResumeToken {
+    let seq = self.next_seq;
+    self.next_seq += 1;
+
+    if self.events.len() >= REPLAY_CAPACITY {
+      self.events.pop_front();
+    }
+    self.events.push_back(ReplayEvent {
+      seq,
+      row_id,
+      record,
+      payload,
+    });
+
+    return ResumeToken {
+      epoch: self.epoch,
+      seq,
+    };
+  }
+
+  /// Returns all events after the given token or None, if the gap cannot be filled, e.g. because
+  /// events have already been evicted or the token stems from a different epoch.
+  fn events_since(&self, token: &ResumeToken) -> Option<impl Iterator<Item = &ReplayEvent>> {
+    if token.epoch != self.epoch || token.seq >= self.next_seq {
+      return None;
+    }
+
+    let first_seq = self.events.front().map_or(self.next_seq, |ev| ev.seq);
+    if token.seq + 1 < first_seq {
+      return None;
+    }
+
+    return Some(self.events.iter().filter(move |ev| ev.seq > token.seq));
+  }
+
+  fn token(&self, seq: i64) -> ResumeToken {
+    return ResumeToken {
+      epoch: self.epoch,
+      seq,
+    };
+  }
 }
 
 #[derive(Default)]
@@ -156,9 +250,101 @@ pub struct PerConnectionStateInternal {
   /// NOTE: Use layered locking to allow cleaning up per-table subscriptions w/o having to
   /// exclusively lock the entire map.
   pub subscriptions: HashMap</* table_name= */ QualifiedName, Subscriptions>,
+
+  /// Map from table name to recent events for subscribers to resume from.
+  pub replay_buffers: HashMap</* table_name= */ QualifiedName, ReplayBuffer>,
+
+  /// Whether the preupdate hook and corresponding broker are currently installed.
+  pub hook_installed: bool,
+
+  /// Since when there are no more subscriptions. The hook is kept around for a while longer to
+  /// continue recording events for subscribers to resume.
+  pub idle_since: Option<Instant>,
 }
 
 impl PerConnectionStateInternal {
+  fn mark_idle_if_empty(&mut self) {
+    if self.subscriptions.is_empty() && self.idle_since.is_none() {
+      self.idle_since = Some(Instant::now());
+    }
+  }
+
+  fn uninstall(&mut self) {
+    uninstall_hook(&self.conn);
+    self.hook_installed = false;
+    self.idle_since = None;
+    self.replay_buffers.clear();
+  }
+
+  /// Registers the subscription and, when resuming, first replays missed events or asks the
+  /// subscriber to resync, if the gap cannot be filled.
+  ///
+  /// NOTE: This happens under the same lock the broker uses, thus ensuring that no live events
+  /// get lost or duplicated in between.
+  fn add_subscription(
+    &mut self,
+    subscription: Arc<Subscription>,
+    resume: Option<ResumeToken>,
+  ) -> Result<(), RecordError> {
+    let table_name = &subscription.id.table_name;
+    let buffer = self
+      .replay_buffers
+      .entry(table_name.clone())
+      .or_insert_with(ReplayBuffer::new);
+
+    if let Some(ref resume) = resume {
+      let send = |candidate: EventCandidate| {
+        return subscription
+          .sender
+          .try_send(candidate)
+          .map_err(|_err| RecordError::Internal("Failed to replay events".into()));
+      };
+
+      match buffer.events_since(resume) {
+        Some(events) => {
+          for ev in events {
+            if subscription
+              .id
+              .row_id
+              .is_some_and(|row_id| row_id != ev.row_id)
+            {
+              continue;
+            }
+
+            send(EventCandidate {
+              record: Some(ev.record.clone()),
+              payload: ev.payload.clone(),
+              seq: subscription.candidate_seq.fetch_add(1, Ordering::SeqCst),
+              resume: Some(buffer.token(ev.seq)),
+            })?;
+          }
+        }
+        None => {
+          send(EventCandidate {
+            record: None,
+            payload: RESYNC_EVENT.clone(),
+            seq: subscription.candidate_seq.fetch_add(1, Ordering::SeqCst),
+            resume: None,
+          })?;
+        }
+      }
+    }
+
+    let subscriptions = self.subscriptions.entry(table_name.clone()).or_default();
+    match subscription.id.row_id {
+      Some(row_id) => subscriptions
+        .record
+        .entry(row_id)
+        .or_default()
+        .push(subscription),
+      None => subscriptions.table.push(subscription),
+    };
+
+    self.idle_since = None;
+
+    return Ok(());
+  }
+
   pub fn remove_subscription2(&mut self, id: SubscriptionId) {
     let Some(subscriptions) = self.subscriptions.get_mut(&id.table_name) else {
       return;
@@ -182,9 +368,7 @@ impl PerConnectionStateInternal {
 
     if subscriptions.is_empty() {
       self.subscriptions.remove(&id.table_name);
-      if self.subscriptions.is_empty() {
-        uninstall_hook(&self.conn);
-      }
+      self.mark_idle_if_empty();
     }
   }
 }
@@ -210,7 +394,7 @@ impl PerConnectionState {
             break;
           }
 
-          let event = match receiver.recv() {
+          let event = match receiver.recv_timeout(IDLE_CHECK_INTERVAL) {
             Ok((cnt, event)) => {
               if cnt != expected {
                 // QUESTION: There's several ways we could deal with failure. We
@@ -218,19 +402,34 @@ impl PerConnectionState {
                 // SQLite access. We could try to deliver event loss messages to all receivers but
                 // that may just make the problem worse. We're probably at limit already
                 // if we don't manage to catch up. Should we just disconnect all subscriptions?
-                state.state.lock().subscriptions.clear();
+                let mut lock = state.state.lock();
+                lock.subscriptions.clear();
+                // Replay buffers have a gap now. Removing them, will ask resuming subscribers to
+                // resync.
+                lock.uninstall();
                 break;
               }
               expected += 1;
 
               event
             }
-            Err(flume::RecvError::Disconnected) => {
+            Err(flume::RecvTimeoutError::Timeout) => {
+              let mut lock = state.state.lock();
+              if lock
+                .idle_since
+                .is_some_and(|since| since.elapsed() > REPLAY_RETENTION)
+              {
+                lock.uninstall();
+                break;
+              }
+              continue;
+            }
+            Err(flume::RecvTimeoutError::Disconnected) => {
               break;
             }
           };
 
-          broker(&conn, &state, event);
+          broker(&state, event);
         }
 
         debug!("Channel closed: terminating subscription broker task.");
@@ -240,11 +439,32 @@ impl PerConnectionState {
     }
   }
 
+  fn register(
+    self: &Arc<Self>,
+    api: &RecordApi,
+    subscription: Arc<Subscription>,
+    resume: Option<ResumeToken>,
+  ) -> Result<(), RecordError> {
+    let install_hook: bool = {
+      let mut lock = self.state.lock();
+      lock.add_subscription(subscription, resume)?;
+
+      !std::mem::replace(&mut lock.hook_installed, true)
+    };
+
+    if install_hook {
+      self.add_hook(api.clone());
+    }
+
+    return Ok(());
+  }
+
   pub async fn add_record_subscription(
     self: Arc<Self>,
     api: RecordApi,
     record: trailbase_sqlite::Value,
     user: Option<User>,
+    resume: Option<ResumeToken>,
     sender: async_channel::Sender<EventCandidate>,
   ) -> Result<Arc<Subscription>, RecordError> {
     let table_name = api.table_name();
@@ -276,26 +496,7 @@ impl PerConnectionState {
       candidate_seq: AtomicI64::default(),
     });
 
-    let install_hook: bool = {
-      let mut lock = self.state.lock();
-      let empty = lock.subscriptions.is_empty();
-
-      let subscriptions = lock
-        .subscriptions
-        .entry(qualified_name.clone())
-        .or_default();
-      subscriptions
-        .record
-        .entry(row_id)
-        .or_default()
-        .push(subscription_entry.clone());
-
-      empty
-    };
-
-    if install_hook {
-      self.add_hook(api.clone());
-    }
+    self.register(&api, subscription_entry.clone(), resume)?;
 
     return Ok(subscription_entry);
   }
@@ -305,6 +506,7 @@ impl PerConnectionState {
     api: RecordApi,
     user: Option<User>,
     filter: Option<ValueOrComposite>,
+    resume: Option<ResumeToken>,
     sender: async_channel::Sender<EventCandidate>,
   ) -> Result<Arc<Subscription>, RecordError> {
     let filter = if let Some(filter) = filter {
@@ -327,23 +529,7 @@ impl PerConnectionState {
       candidate_seq: AtomicI64::default(),
     });
 
-    let install_hook: bool = {
-      let mut lock = self.state.lock();
-      let empty = lock.subscriptions.is_empty();
-
-      let subscriptions = lock
-        .subscriptions
-        .entry(qualified_name.clone())
-        .or_default();
-
-      subscriptions.table.push(subscription_entry.clone());
-
-      empty
-    };
-
-    if install_hook {
-      self.add_hook(api.clone());
-    }
+    self.register(&api, subscription_entry.clone(), resume)?;
 
     return Ok(subscription_entry);
   }
@@ -359,6 +545,7 @@ fn broker_subscriptions(
   subs: &[Arc<Subscription>],
   record: &Arc<indexmap::IndexMap<String, trailbase_sqlite::Value>>,
   event: &Arc<EventPayload>,
+  resume: Option<ResumeToken>,
 ) -> Vec<usize> {
   return subs
     .iter()
@@ -370,6 +557,7 @@ fn broker_subscriptions(
         record: Some(record.clone()),
         payload: event.clone(),
         seq: sub.candidate_seq.fetch_add(1, Ordering::SeqCst),
+        resume,
       }) {
         match err {
           async_channel::TrySendError::Full(ev) => {
@@ -387,11 +575,7 @@ fn broker_subscriptions(
 }
 
 /// Broker event to various subscriptions.
-fn broker(
-  conn: &trailbase_sqlite::Connection,
-  state: &Arc<PerConnectionState>,
-  event: PreupdateHookEvent,
-) {
+fn broker(state: &Arc<PerConnectionState>, event: PreupdateHookEvent) {
   let PreupdateHookEvent {
     action,
     table_name,
@@ -408,17 +592,18 @@ fn broker(
     warn!("Table {table_name:?} not found. Removing subscriptions");
 
     state.subscriptions.remove(&table_name);
-    if state.subscriptions.is_empty() {
-      uninstall_hook(conn);
-    }
+    state.replay_buffers.remove(&table_name);
+    state.mark_idle_if_empty();
     return;
   };
 
-  // Check if there are any matching subscriptions and otherwise go back to listening.
-  let Some(subscriptions) = state.subscriptions.get_mut(&table_name) else {
-    return;
-  };
-  if subscriptions.table.is_empty() && !subscriptions.record.contains_key(&row_id) {
+  // Check if there are any matching subscriptions or a replay buffer and otherwise go back to
+  // listening.
+  let has_subscribers = state
+    .subscriptions
+    .get(&table_name)
+    .is_some_and(|subs| !subs.table.is_empty() || subs.record.contains_key(&row_id));
+  if !has_subscribers && !state.replay_buffers.contains_key(&table_name) {
     return;
   }
 
@@ -450,24 +635,97 @@ fn broker(
     }))
   };
 
+  // Record the event for subscribers to resume from.
+  let resume = state
+    .replay_buffers
+    .get_mut(&table_name)
+    .map(|buffer| buffer.push(row_id, record.clone(), event.clone()));
+
+  let Some(subscriptions) = state.subscriptions.get_mut(&table_name) else {
+    return;
+  };
+
   // First broker record subscriptions.
   if let Some(record_subscriptions) = subscriptions.record.get_mut(&row_id) {
-    let dead = broker_subscriptions(record_subscriptions, &record, &event);
+    let dead = broker_subscriptions(record_subscriptions, &record, &event, resume);
 
     for idx in dead.iter().rev() {
       record_subscriptions.remove(*idx);
     }
+
+    if record_subscriptions.is_empty() {
+      subscriptions.record.remove(&row_id);
+    }
   }
 
   // Then broker table subscriptions.
-  let dead = broker_subscriptions(&subscriptions.table, &record, &event);
+  let dead = broker_subscriptions(&subscriptions.table, &record, &event, resume);
   for idx in dead.iter().rev() {
     subscriptions.table.remove(*idx);
   }
 
   if subscriptions.is_empty() {
-    uninstall_hook(conn);
+    state.subscriptions.remove(&table_name);
+    state.mark_idle_if_empty();
   }
 }
 
 static SUBSCRIPTION_COUNTER: AtomicI64 = AtomicI64::new(0);
+
+static RESYNC_EVENT: LazyLock<Arc<EventPayload>> = LazyLock::new(|| {
+  Arc::new(EventPayload::from(&JsonEventPayload::Error {
+    value: EventError {
+      status: EventErrorStatus::Resync,
+      message: Some("Missed events cannot be replayed".into()),
+    },
+  }))
+});
+
+/// Capacity of per-subscriber event channels.
+const CHANNEL_CAPACITY: usize = 64;
+/// Max number of recent events per table kept around for subscribers to resume.
+const REPLAY_CAPACITY: usize = 1024;
+/// How long to continue recording events after the last subscriber left.
+const REPLAY_RETENTION: Duration = Duration::from_secs(120);
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_replay_buffer() {
+    let record = Arc::new(indexmap::IndexMap::new());
+    let payload = Arc::new(EventPayload::Ping);
+
+    let mut buffer = ReplayBuffer::new();
+    let tokens: Vec<ResumeToken> = (0..REPLAY_CAPACITY + 2)
+      .map(|i| buffer.push(i as i64, record.clone(), payload.clone()))
+      .collect();
+
+    let since = |token: &ResumeToken| {
+      return buffer
+        .events_since(token)
+        .map(|events| events.map(|ev| ev.row_id).collect::<Vec<_>>());
+    };
+
+    // Up to date.
+    assert_eq!(Some(vec![]), since(tokens.last().unwrap()));
+    // Gap can be filled.
+    assert_eq!(
+      Some(vec![REPLAY_CAPACITY as i64, REPLAY_CAPACITY as i64 + 1]),
+      since(&tokens[REPLAY_CAPACITY - 1])
+    );
+    assert!(since(&tokens[1]).is_some());
+    // Events have been evicted.
+    assert_eq!(None, since(&tokens[0]));
+    // Different epoch.
+    assert_eq!(
+      None,
+      since(&ResumeToken {
+        epoch: tokens[0].epoch.wrapping_add(1),
+        seq: 5,
+      })
+    );
+  }
+}
@@ -146,9 +146,21 @@ async fn subscribe_to_records(
   user: Option<User>,
   filter: Option<&str>,
   // ) -> kanal::AsyncReceiver<TestChangeEvent> {
+) -> std::pin::Pin<Box<dyn futures_util::Stream<Item = TestChangeEvent>>> {
+  return resume_subscription(state, api, record, user, filter, None).await;
+}
+
+async fn resume_subscription(
+  state: AppState,
+  api: RecordApi,
+  record: &str,
+  user: Option<User>,
+  filter: Option<&str>,
+  resume: Option<&str>,
 ) -> std::pin::Pin<Box<dyn futures_util::Stream<Item = TestChangeEvent>>> {
   let filter = filter.map(|f| SubscriptionQuery::parse(f).unwrap().filter.unwrap());
-  let response = subscribe_sse(state, api, record.to_string(), filter, user)
+  let resume = resume.map(|r| r.parse().unwrap());
+  let response = subscribe_sse(state, api, record.to_string(), filter, user, resume)
     .await
     .unwrap();
 
@@ -180,10 +192,16 @@ async fn subscribe_to_records(
         }
 
         // Ignore heartbeats.
-        if let Some((_a, b)) = payload.split_once("data: ") {
-          return Some(serde_json::from_str(b).unwrap());
+        let mut event: Option<TestChangeEvent> = None;
+        let mut resume: Option<String> = None;
+        for line in payload.lines() {
+          if let Some(data) = line.strip_prefix("data: ") {
+            event = Some(serde_json::from_str(data).unwrap());
+          } else if let Some(id) = line.strip_prefix("id: ") {
+            resume = Some(id.to_string());
+          }
         }
-        return None;
+        return event.map(|ev| TestChangeEvent { resume, ..ev });
       };
     })
     .boxed();
@@ -712,3 +730,106 @@ async fn subscription_filter_test() {
 
   assert_eq!(0, manager.num_table_subscriptions());
 }
+
+#[tokio::test]
+async fn subscription_membership_filter_test() {
+  let state = setup_world_readable().await;
+  let conn = state.conn().clone();
+
+  let api = state.lookup_record_api("api_name").unwrap();
+
+  let stream = subscribe_to_records(
+    state.clone(),
+    api.clone(),
+    "*",
+    /* user= */ None,
+    Some("filter[id][$in]=3,7,11&filter[text][$nin]=bar"),
+  )
+  .await;
+
+  for (id, text) in [(1, "foo"), (3, "bar"), (7, "foo"), (8, "foo")] {
+    conn
+      .execute(
+        "INSERT INTO test (id, text) VALUES ($1, $2)",
+        params!(id, text.to_string()),
+      )
+      .await
+      .unwrap();
+  }
+
+  let events = take_test_events(stream, 2).await;
+
+  assert!(matches!(events[0].event, TestJsonEventPayload::Ping));
+
+  match &events[1].event {
+    TestJsonEventPayload::Insert(obj) => {
+      let expected = serde_json::json!({
+        "id": 7,
+        "text": "foo",
+      });
+      assert_eq!(Value::Object(obj.clone()), expected);
+    }
+    x => {
+      panic!("Expected insert, got: {x:?}");
+    }
+  };
+}
+
+#[tokio::test]
+async fn subscription_resume_test() {
+  let state = setup_world_readable().await;
+  let conn = state.conn().clone();
+
+  let api = state.lookup_record_api("api_name").unwrap();
+
+  let insert = async |id: i64| {
+    conn
+      .execute(
+        "INSERT INTO test (id, text) VALUES ($1, 'foo')",
+        params!(id),
+      )
+      .await
+      .unwrap();
+  };
+
+  let id = |ev: &TestChangeEvent| match &ev.event {
+    TestJsonEventPayload::Insert(obj) => obj["id"].as_i64().unwrap(),
+    x => panic!("Expected insert, got: {x:?}"),
+  };
+
+  let stream = subscribe_to_records(state.clone(), api.clone(), "*", None, None).await;
+  insert(1).await;
+  insert(2).await;
+
+  let events = take_test_events(stream, 3).await;
+  assert!(matches!(events[0].event, TestJsonEventPayload::Ping));
+  assert_eq!(1, id(&events[1]));
+  assert_eq!(2, id(&events[2]));
+  let token = events[1].resume.clone().unwrap();
+
+  // Insert while "disconnected".
+  insert(3).await;
+
+  // Resuming replays everything after the last received event w/o duplicates.
+  let stream = resume_subscription(state.clone(), api.clone(), "*", None, None, Some(&token)).await;
+  insert(4).await;
+
+  let events = take_test_events(stream, 4).await;
+  assert!(matches!(events[0].event, TestJsonEventPayload::Ping));
+  assert_eq!(
+    vec![2, 3, 4],
+    events[1..].iter().map(id).collect::<Vec<_>>()
+  );
+
+  // Unknown tokens require a resync.
+  let stream = resume_subscription(state.clone(), api, "*", None, None, Some("abc-5")).await;
+  let events = take_test_events(stream, 2).await;
+  match &events[1].event {
+    TestJsonEventPayload::Error { status, .. } => {
+      assert_eq!(EventErrorStatus::Resync, *status);
+    }
+    x => {
+      panic!("Expected error, got: {x:?}");
+    }
+  };
+}
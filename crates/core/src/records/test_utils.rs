@@ -26,6 +26,7 @@ mod tests {
 
       acl_world: acls.world.into_iter().map(|f| f as i32).collect(),
       acl_authenticated: acls.authenticated.into_iter().map(|f| f as i32).collect(),
+      acl_roles: vec![],
       conflict_resolution: None,
       autofill_missing_user_id_columns: None,
       enable_subscriptions: None,
@@ -37,6 +38,7 @@ mod tests {
       schema_access_rule: access_rules.schema,
       expand: vec![],
       listing_hard_limit: None,
+      image_transforms: vec![],
     });
 
     return state.validate_and_update_config(config, None).await;
@@ -2,17 +2,23 @@ use axum::extract::{Json, State};
 use base64::prelude::*;
 use serde::{Deserialize, Serialize};
 use trailbase_schema::QualifiedName;
+use trailbase_schema::json::value_to_flat_json;
 use trailbase_sqlite::SyncConnectionTrait;
 use utoipa::ToSchema;
 
 use crate::app_state::AppState;
 use crate::auth::user::User;
+use crate::records::hooks::{HookedWrite, apply_write_with_hooks};
 use crate::records::params::LazyParams;
 use crate::records::record_api::RecordApi;
 use crate::records::write_queries::WriteQuery;
 use crate::records::{Permission, RecordError};
 use crate::util::uuid_to_b64;
 
+/// Operations can reference the id of a record created by an earlier operation in the same batch
+/// using `"$ref:<index>"` placeholders, both as `record_id` and as top-level values of a record,
+/// e.g. to create a parent and children pointing to it. Literal strings starting with `$ref:` need
+/// to be escaped with an additional leading `$`, e.g. `"$$ref:0"` for `"$ref:0"`.
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub enum Operation {
   Create {
@@ -36,12 +42,43 @@ pub struct TransactionRequest {
   transaction: Option<bool>,
 }
 
+/// Outcome of a single operation.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+pub enum OperationResult {
+  Create { id: String },
+  Update { id: String },
+  Delete { id: String },
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct TransactionResponse {
   /// Url-Safe base64 encoded ids of the newly created record.
   pub ids: Vec<String>,
+  /// Per-operation results in the same order as the requested operations.
+  pub results: Vec<OperationResult>,
+}
+
+impl TransactionResponse {
+  fn new(results: Vec<OperationResult>) -> Self {
+    return Self {
+      ids: results
+        .iter()
+        .filter_map(|r| match r {
+          OperationResult::Create { id } => Some(id.clone()),
+          _ => None,
+        })
+        .collect(),
+      results,
+    };
+  }
 }
 
+/// Prefix of placeholders referencing records created earlier in the same batch, e.g. `$ref:0`.
+const REF_PREFIX: &str = "$ref:";
+
+/// Id of a record created earlier in the batch, as a string and as a JSON value.
+type CreatedId = (String, serde_json::Value);
+
 /// Execute a batch of transactions.
 #[utoipa::path(
   post,
@@ -62,7 +99,7 @@ pub async fn record_transactions_handler(
   // `request.transaction == true`.
   match request.operations.len() {
     0 => {
-      return Ok(Json(TransactionResponse { ids: vec![] }));
+      return Ok(Json(TransactionResponse::new(vec![])));
     }
     n if n > 128 => {
       return Err(RecordError::BadRequest("Batch size exceeds limit: 128"));
@@ -82,36 +119,43 @@ pub async fn record_transactions_handler(
     return Err(RecordError::BadRequest("empty ops?"));
   };
 
+  // NOTE: RecordErrors are passed through as is, to preserve their status codes, e.g. when a
+  // record hook aborts.
   let conn = first_api.conn().clone();
-  let ids = if request.transaction.unwrap_or(false) {
+  let results = if request.transaction.unwrap_or(false) {
     conn
       .transaction({
-        move |tx| -> Result<Vec<String>, trailbase_sqlite::Error> {
-          let ids: Vec<String> =
-            apply_ops(&state, &tx, user.as_ref(), &first_api, request.operations)
-              .map_err(|err| trailbase_sqlite::Error::Other(err.into()))?;
+        move |tx| -> Result<Result<Vec<OperationResult>, RecordError>, trailbase_sqlite::Error> {
+          let results: Vec<OperationResult> =
+            match apply_ops(&state, &tx, user.as_ref(), &first_api, request.operations) {
+              Ok(results) => results,
+              // Dropping the transaction without committing rolls back.
+              Err(err) => return Ok(Err(err)),
+            };
 
           tx.commit()?;
 
-          return Ok(ids);
+          return Ok(Ok(results));
         }
       })
-      .await?
+      .await??
   } else {
     conn
       .call_writer(
-        move |conn| -> Result<Vec<String>, trailbase_sqlite::Error> {
-          let ids: Vec<String> =
-            apply_ops(&state, &conn, user.as_ref(), &first_api, request.operations)
-              .map_err(|err| trailbase_sqlite::Error::Other(err.into()))?;
-
-          return Ok(ids);
+        move |conn| -> Result<Result<Vec<OperationResult>, RecordError>, trailbase_sqlite::Error> {
+          return Ok(apply_ops(
+            &state,
+            &conn,
+            user.as_ref(),
+            &first_api,
+            request.operations,
+          ));
         },
       )
-      .await?
+      .await??
   };
 
-  return Ok(Json(TransactionResponse { ids }));
+  return Ok(Json(TransactionResponse::new(results)));
 }
 
 #[inline]
@@ -126,6 +170,67 @@ fn extract_record_id(value: trailbase_sqlite::Value) -> Result<String, trailbase
   };
 }
 
+enum Resolved<'a> {
+  /// Id of the referenced record.
+  Ref(&'a CreatedId),
+  /// Literal string with its escape removed.
+  Unescaped(String),
+}
+
+/// Resolves `$ref:<index>` placeholders and escaped literals, e.g. `$$ref:0`. Returns `None` for
+/// any other string.
+fn resolve_ref<'a>(
+  value: &str,
+  created: &'a [Option<CreatedId>],
+) -> Result<Option<Resolved<'a>>, RecordError> {
+  if let Some(index) = value.strip_prefix(REF_PREFIX) {
+    let index: usize = index
+      .parse()
+      .map_err(|_| RecordError::BadRequest("Invalid reference"))?;
+
+    // Only earlier create operations can be referenced.
+    return match created.get(index) {
+      Some(Some(id)) => Ok(Some(Resolved::Ref(id))),
+      _ => Err(RecordError::BadRequest("Invalid reference")),
+    };
+  }
+
+  if value.starts_with("$$") && value.trim_start_matches('$').starts_with("ref:") {
+    return Ok(Some(Resolved::Unescaped(value[1..].to_string())));
+  }
+
+  return Ok(None);
+}
+
+fn resolve_record_id(
+  record_id: String,
+  created: &[Option<CreatedId>],
+) -> Result<String, RecordError> {
+  return Ok(match resolve_ref(&record_id, created)? {
+    Some(Resolved::Ref((id, _))) => id.clone(),
+    Some(Resolved::Unescaped(literal)) => literal,
+    None => record_id,
+  });
+}
+
+fn resolve_record_refs(
+  record: &mut serde_json::Map<String, serde_json::Value>,
+  created: &[Option<CreatedId>],
+) -> Result<(), RecordError> {
+  for value in record.values_mut() {
+    let serde_json::Value::String(str) = value else {
+      continue;
+    };
+
+    match resolve_ref(str, created)? {
+      Some(Resolved::Ref((_, id))) => *value = id.clone(),
+      Some(Resolved::Unescaped(literal)) => *value = serde_json::Value::String(literal),
+      None => {}
+    };
+  }
+  return Ok(());
+}
+
 #[inline]
 fn get_db_name(name: &QualifiedName) -> &str {
   return name.database_schema.as_deref().unwrap_or("main");
@@ -159,143 +264,208 @@ fn apply_ops<T: SyncConnectionTrait>(
   user: Option<&User>,
   api: &RecordApi,
   ops: Vec<Operation>,
-) -> Result<Vec<String>, RecordError> {
+) -> Result<Vec<OperationResult>, RecordError> {
   let expected_db_name = get_db_name(api.qualified_name());
 
-  let ids: Vec<String> = ops
-    .into_iter()
-    .map(|op| -> Result<Option<String>, RecordError> {
-      return match op {
-        Operation::Create { api_name, value } => {
-          let api = get_api(state, &api_name)?;
-          if get_db_name(api.qualified_name()) != expected_db_name {
-            return Err(RecordError::BadRequest("DB mismatch"));
-          }
+  // Ids of created records by operation index for resolving `$ref:<index>` placeholders.
+  let mut created: Vec<Option<CreatedId>> = Vec::with_capacity(ops.len());
+  let mut results: Vec<OperationResult> = Vec::with_capacity(ops.len());
+
+  for op in ops {
+    match op {
+      Operation::Create { api_name, value } => {
+        let api = get_api(state, &api_name)?;
+        if get_db_name(api.qualified_name()) != expected_db_name {
+          return Err(RecordError::BadRequest("DB mismatch"));
+        }
 
-          let mut record = extract_record(value)?;
-
-          if api.insert_autofill_missing_user_id_columns()
-            && let Some(user) = user
-          {
-            for column_index in api.user_id_columns() {
-              let col_name = &api.columns()[*column_index].column.name;
-              if !record.contains_key(col_name) {
-                record.insert(
-                  col_name.to_owned(),
-                  serde_json::Value::String(uuid_to_b64(&user.uuid)),
-                );
-              }
+        let mut record = extract_record(value)?;
+        resolve_record_refs(&mut record, &created)?;
+
+        if api.insert_autofill_missing_user_id_columns()
+          && let Some(user) = user
+        {
+          for column_index in api.user_id_columns() {
+            let col_name = &api.columns()[*column_index].column.name;
+            if !record.contains_key(col_name) {
+              record.insert(
+                col_name.to_owned(),
+                serde_json::Value::String(uuid_to_b64(&user.uuid)),
+              );
             }
           }
+        }
 
-          let mut lazy_params =
-            LazyParams::for_insert(&api, state.json_schema_registry().clone(), record, None);
-          api.record_level_access_check(
+        // Record hooks need the original payload, i.e. only copy if hooks are registered.
+        let hook_record = state
+          .record_hooks()
+          .has_hooks(&api_name)
+          .then(|| record.clone());
+
+        let mut lazy_params =
+          LazyParams::for_insert(&api, state.json_schema_registry().clone(), record, None);
+        api.record_level_access_check(
+          conn,
+          Permission::Create,
+          None,
+          Some(&mut lazy_params),
+          user,
+        )?;
+
+        let params = lazy_params
+          .consume()
+          .map_err(|_| RecordError::BadRequest("Invalid Parameters"))?;
+
+        let result = if let Some(record) = hook_record {
+          // NOTE: Without `transaction: true`, an aborting after-hook cannot undo the write.
+          apply_write_with_hooks(
             conn,
-            Permission::Create,
-            None,
-            Some(&mut lazy_params),
+            state.record_hooks(),
+            &api,
+            state.json_schema_registry(),
             user,
-          )?;
-
+            HookedWrite::Insert { record, params },
+          )?
+        } else {
           let (query, _files) = WriteQuery::new_insert(
             api.table_name(),
             &api.record_pk_column().column.name,
             api.insert_conflict_resolution_strategy(),
-            lazy_params
-              .consume()
-              .map_err(|_| RecordError::BadRequest("Invalid Parameters"))?,
+            params,
           )
           .map_err(|err| RecordError::Internal(err.into()))?;
 
-          let result = query
+          query
             .apply_sync(conn)
-            .map_err(|err| RecordError::Internal(err.into()))?;
-
-          Ok(Some(
-            extract_record_id(result.pk_value.expect("insert"))
-              .map_err(|err| RecordError::Internal(err.into()))?,
-          ))
+            .map_err(|err| RecordError::Internal(err.into()))?
+        };
+
+        let pk_value = result.pk_value.expect("insert");
+        let json_id =
+          value_to_flat_json(&pk_value).map_err(|err| RecordError::Internal(err.into()))?;
+        let id = extract_record_id(pk_value).map_err(|err| RecordError::Internal(err.into()))?;
+
+        created.push(Some((id.clone(), json_id)));
+        results.push(OperationResult::Create { id });
+      }
+      Operation::Update {
+        api_name,
+        record_id,
+        value,
+      } => {
+        let api = get_api(state, &api_name)?;
+        if get_db_name(api.qualified_name()) != expected_db_name {
+          return Err(RecordError::BadRequest("DB mismatch"));
         }
-        Operation::Update {
-          api_name,
-          record_id,
-          value,
-        } => {
-          let api = get_api(state, &api_name)?;
-          if get_db_name(api.qualified_name()) != expected_db_name {
-            return Err(RecordError::BadRequest("DB mismatch"));
-          }
 
-          let record = extract_record(value)?;
-          let record_id = api.primary_key_to_value(record_id)?;
-          let pk_meta = api.record_pk_column();
-
-          let mut lazy_params = LazyParams::for_update(
-            &api,
-            state.json_schema_registry().clone(),
-            record,
-            None,
-            pk_meta.column.name.clone(),
-            record_id.clone(),
-          );
-
-          api.record_level_access_check(
+        let record_id = resolve_record_id(record_id, &created)?;
+        let mut record = extract_record(value)?;
+        resolve_record_refs(&mut record, &created)?;
+
+        let hook_record = state
+          .record_hooks()
+          .has_hooks(&api_name)
+          .then(|| (record_id.clone(), record.clone()));
+
+        let pk_value = api.primary_key_to_value(record_id.clone())?;
+        let pk_meta = api.record_pk_column();
+
+        let mut lazy_params = LazyParams::for_update(
+          &api,
+          state.json_schema_registry().clone(),
+          record,
+          None,
+          pk_meta.column.name.clone(),
+          pk_value.clone(),
+        );
+
+        api.record_level_access_check(
+          conn,
+          Permission::Update,
+          Some(&pk_value),
+          Some(&mut lazy_params),
+          user,
+        )?;
+
+        let params = lazy_params
+          .consume()
+          .map_err(|_| RecordError::BadRequest("Invalid Parameters"))?;
+
+        if let Some((record_id, record)) = hook_record {
+          apply_write_with_hooks(
             conn,
-            Permission::Update,
-            Some(&record_id),
-            Some(&mut lazy_params),
+            state.record_hooks(),
+            &api,
+            state.json_schema_registry(),
             user,
+            HookedWrite::Update {
+              record_id,
+              record,
+              params,
+            },
           )?;
-
-          let (query, _files) = WriteQuery::new_update(
-            api.table_name(),
-            lazy_params
-              .consume()
-              .map_err(|_| RecordError::BadRequest("Invalid Parameters"))?,
-          )
-          .map_err(|err| RecordError::Internal(err.into()))?;
+        } else {
+          let (query, _files) = WriteQuery::new_update(api.table_name(), params)
+            .map_err(|err| RecordError::Internal(err.into()))?;
 
           let _ = query
             .apply_sync(conn)
             .map_err(|err| RecordError::Internal(err.into()))?;
+        }
 
-          Ok(None)
+        created.push(None);
+        results.push(OperationResult::Update { id: record_id });
+      }
+      Operation::Delete {
+        api_name,
+        record_id,
+      } => {
+        let api = get_api(state, &api_name)?;
+        if get_db_name(api.qualified_name()) != expected_db_name {
+          return Err(RecordError::BadRequest("DB mismatch"));
         }
-        Operation::Delete {
-          api_name,
-          record_id,
-        } => {
-          let api = get_api(state, &api_name)?;
-          if get_db_name(api.qualified_name()) != expected_db_name {
-            return Err(RecordError::BadRequest("DB mismatch"));
-          }
 
-          let record_id = api.primary_key_to_value(record_id)?;
+        let record_id = resolve_record_id(record_id, &created)?;
+        let hook_record_id = state
+          .record_hooks()
+          .has_hooks(&api_name)
+          .then(|| record_id.clone());
+        let pk_value = api.primary_key_to_value(record_id.clone())?;
 
-          api.record_level_access_check(conn, Permission::Delete, Some(&record_id), None, user)?;
+        api.record_level_access_check(conn, Permission::Delete, Some(&pk_value), None, user)?;
 
+        if let Some(hook_record_id) = hook_record_id {
+          apply_write_with_hooks(
+            conn,
+            state.record_hooks(),
+            &api,
+            state.json_schema_registry(),
+            user,
+            HookedWrite::Delete {
+              record_id: hook_record_id,
+              pk_value,
+            },
+          )?;
+        } else {
           let query = WriteQuery::new_delete(
             api.table_name(),
             &api.record_pk_column().column.name,
-            record_id,
+            pk_value,
           )
           .map_err(|err| RecordError::Internal(err.into()))?;
 
           let _ = query
             .apply_sync(conn)
             .map_err(|err| RecordError::Internal(err.into()))?;
-
-          Ok(None)
         }
-      };
-    })
-    .collect::<Result<Vec<_>, _>>()?
-    .into_iter()
-    .flatten()
-    .collect();
-
-  return Ok(ids);
+
+        created.push(None);
+        results.push(OperationResult::Delete { id: record_id });
+      }
+    };
+  }
+
+  return Ok(results);
 }
 
 #[cfg(test)]
@@ -399,4 +569,207 @@ mod tests {
         .unwrap()
     );
   }
+
+  #[tokio::test]
+  async fn test_transaction_references() {
+    let state = test_state(None).await.unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE orders (
+            id      INTEGER PRIMARY KEY,
+            name    TEXT NOT NULL
+          ) STRICT;
+          CREATE TABLE order_items (
+            id      INTEGER PRIMARY KEY,
+            order_id INTEGER NOT NULL REFERENCES orders(id),
+            item    TEXT NOT NULL
+          ) STRICT;
+        "#,
+      )
+      .await
+      .unwrap();
+
+    state.rebuild_connection_metadata().await.unwrap();
+
+    for (name, table) in [("orders_api", "orders"), ("items_api", "order_items")] {
+      add_record_api_config(
+        &state,
+        RecordApiConfig {
+          name: Some(name.to_string()),
+          table_name: Some(table.to_string()),
+          acl_world: [
+            PermissionFlag::Create as i32,
+            PermissionFlag::Read as i32,
+            PermissionFlag::Update as i32,
+            PermissionFlag::Delete as i32,
+          ]
+          .into(),
+          ..Default::default()
+        },
+      )
+      .await
+      .unwrap();
+    }
+
+    let response = record_transactions_handler(
+      State(state.clone()),
+      None,
+      Json(TransactionRequest {
+        operations: vec![
+          Operation::Create {
+            api_name: "orders_api".to_string(),
+            value: json!({"name": "order"}),
+          },
+          Operation::Create {
+            api_name: "items_api".to_string(),
+            value: json!({"order_id": "$ref:0", "item": "first"}),
+          },
+          Operation::Create {
+            api_name: "items_api".to_string(),
+            value: json!({"order_id": "$ref:0", "item": "second"}),
+          },
+          Operation::Update {
+            api_name: "orders_api".to_string(),
+            record_id: "$ref:0".to_string(),
+            value: json!({"name": "updated"}),
+          },
+          Operation::Delete {
+            api_name: "items_api".to_string(),
+            record_id: "$ref:2".to_string(),
+          },
+        ],
+        transaction: Some(true),
+      }),
+    )
+    .await
+    .unwrap();
+
+    let order_id = response.ids[0].clone();
+    assert_eq!(3, response.ids.len());
+    assert_eq!(
+      response.results,
+      vec![
+        OperationResult::Create {
+          id: order_id.clone()
+        },
+        OperationResult::Create {
+          id: response.ids[1].clone()
+        },
+        OperationResult::Create {
+          id: response.ids[2].clone()
+        },
+        OperationResult::Update {
+          id: order_id.clone()
+        },
+        OperationResult::Delete {
+          id: response.ids[2].clone()
+        },
+      ]
+    );
+
+    let rows = state
+      .conn()
+      .read_query_rows(
+        "SELECT o.name, i.item FROM orders AS o JOIN order_items AS i ON i.order_id = o.id",
+        (),
+      )
+      .await
+      .unwrap();
+    assert_eq!(1, rows.len());
+    assert_eq!("updated", rows[0].get::<String>(0).unwrap());
+    assert_eq!("first", rows[0].get::<String>(1).unwrap());
+
+    // Referencing non-create or later operations is invalid and rolls back.
+    for reference in ["$ref:1", "$ref:2"] {
+      let result = record_transactions_handler(
+        State(state.clone()),
+        None,
+        Json(TransactionRequest {
+          operations: vec![
+            Operation::Create {
+              api_name: "orders_api".to_string(),
+              value: json!({"name": "other"}),
+            },
+            Operation::Update {
+              api_name: "orders_api".to_string(),
+              record_id: order_id.clone(),
+              value: json!({"name": "other"}),
+            },
+            Operation::Create {
+              api_name: "items_api".to_string(),
+              value: json!({"order_id": reference, "item": "third"}),
+            },
+          ],
+          transaction: Some(true),
+        }),
+      )
+      .await;
+      assert!(
+        matches!(result, Err(RecordError::BadRequest(_))),
+        "{reference}"
+      );
+    }
+
+    assert_eq!(
+      1,
+      state
+        .conn()
+        .read_query_value::<i64>("SELECT COUNT(*) FROM orders;", ())
+        .await
+        .unwrap()
+        .unwrap()
+    );
+
+    // Malformed references are rejected rather than being taken literally.
+    let result = record_transactions_handler(
+      State(state.clone()),
+      None,
+      Json(TransactionRequest {
+        operations: vec![Operation::Create {
+          api_name: "orders_api".to_string(),
+          value: json!({"name": "$ref:first"}),
+        }],
+        transaction: Some(true),
+      }),
+    )
+    .await;
+    assert!(matches!(result, Err(RecordError::BadRequest(_))));
+
+    // Literal strings starting with `$ref:` can be escaped.
+    let response = record_transactions_handler(
+      State(state.clone()),
+      None,
+      Json(TransactionRequest {
+        operations: vec![
+          Operation::Create {
+            api_name: "orders_api".to_string(),
+            value: json!({"name": "$$ref:0"}),
+          },
+          Operation::Create {
+            api_name: "orders_api".to_string(),
+            value: json!({"name": "$$$ref:0"}),
+          },
+        ],
+        transaction: Some(true),
+      }),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(2, response.ids.len());
+
+    let rows = state
+      .conn()
+      .read_query_rows("SELECT name FROM orders ORDER BY id DESC LIMIT 2", ())
+      .await
+      .unwrap();
+    let names: Vec<String> = rows
+      .iter()
+      .map(|row| row.get::<String>(0).unwrap())
+      .collect();
+    assert_eq!(names, vec!["$$ref:0", "$ref:0"]);
+  }
 }
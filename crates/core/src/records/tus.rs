@@ -0,0 +1,850 @@
+//! Resumable uploads for `std.FileUpload` columns implementing the tus protocol 1.0.0 with the
+//! creation, expiration and termination extensions, see https://tus.io/protocols/resumable-upload.
+use axum::{
+  body::Body,
+  extract::{OriginalUri, Path, State},
+  http::{HeaderMap, HeaderName, StatusCode, header},
+  response::{IntoResponse, Response},
+};
+use base64::prelude::*;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use log::*;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use trailbase_schema::FileUpload;
+use trailbase_schema::file::infer_mime_type;
+use trailbase_sqlite::params;
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::constants::{TUS_UPLOAD_MAX_SIZE, TUS_UPLOAD_TABLE, TUS_UPLOAD_TTL};
+use crate::records::RecordError;
+use crate::records::files::{FileManager, format_http_date};
+use crate::records::update_record::{assign_file_to_record, check_file_update_access};
+
+const TUS_VERSION: &str = "1.0.0";
+const TUS_EXTENSIONS: &str = "creation,expiration,termination";
+const OFFSET_OCTET_STREAM: &str = "application/offset+octet-stream";
+
+const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+const TUS_VERSION_HEADER: HeaderName = HeaderName::from_static("tus-version");
+const TUS_EXTENSION: HeaderName = HeaderName::from_static("tus-extension");
+const TUS_MAX_SIZE: HeaderName = HeaderName::from_static("tus-max-size");
+const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+const UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+const UPLOAD_EXPIRES: HeaderName = HeaderName::from_static("upload-expires");
+
+/// Ids of uploads currently being written to, see [UploadLock].
+///
+/// NOTE: The set is local to this process. Deployments with multiple instances sharing the same
+/// data directory aren't protected against concurrent writes from different instances.
+static LOCKED_UPLOADS: LazyLock<parking_lot::Mutex<HashSet<String>>> =
+  LazyLock::new(Default::default);
+
+type TusUploadPath = Path<(
+  String, // RecordApi name
+  String, // Record id
+  String, // Column name
+  String, // Upload id
+)>;
+
+/// Advertise the server's tus capabilities.
+#[utoipa::path(
+  options,
+  path = "/{name}/{record}/tus/{column_name}",
+  tag = "records",
+  responses(
+    (status = 204, description = "Supported tus version and extensions.")
+  )
+)]
+pub async fn tus_options_handler() -> Response {
+  return (
+    StatusCode::NO_CONTENT,
+    [
+      (TUS_RESUMABLE, TUS_VERSION.to_string()),
+      (TUS_VERSION_HEADER, TUS_VERSION.to_string()),
+      (TUS_EXTENSION, TUS_EXTENSIONS.to_string()),
+      (TUS_MAX_SIZE, TUS_UPLOAD_MAX_SIZE.to_string()),
+    ],
+  )
+    .into_response();
+}
+
+/// Create a resumable upload for the record's file column.
+///
+/// Expects the file's size as `Upload-Length` and optionally its `filename` and `filetype` as
+/// `Upload-Metadata`. Responds with the new upload's URL as `Location`.
+#[utoipa::path(
+  post,
+  path = "/{name}/{record}/tus/{column_name}",
+  tag = "records",
+  responses(
+    (status = 201, description = "Upload created.")
+  )
+)]
+pub async fn tus_create_upload_handler(
+  State(state): State<AppState>,
+  Path((api_name, record, column_name)): Path<(String, String, String)>,
+  OriginalUri(uri): OriginalUri,
+  user: Option<User>,
+  headers: HeaderMap,
+) -> Result<Response, RecordError> {
+  if let Some(response) = check_tus_resumable(&headers) {
+    return Ok(response);
+  }
+
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+  if !api.is_table() {
+    return Err(RecordError::ApiRequiresTable);
+  }
+
+  let Some(upload_length) = parse_header::<u64>(&headers, &UPLOAD_LENGTH) else {
+    return Err(RecordError::BadRequest("Missing Upload-Length"));
+  };
+  if upload_length > TUS_UPLOAD_MAX_SIZE {
+    return Ok(tus_response(StatusCode::PAYLOAD_TOO_LARGE, []));
+  }
+
+  let mut metadata = headers
+    .get(&UPLOAD_METADATA)
+    .and_then(|v| v.to_str().ok())
+    .map(parse_upload_metadata)
+    .unwrap_or_default();
+  let filename = metadata.remove("filename");
+  let content_type = metadata.remove("filetype");
+
+  let upload_id = Uuid::new_v4();
+  let record_id = api.primary_key_to_value(record.clone())?;
+  check_file_update_access(
+    &state,
+    &api,
+    record_id,
+    &column_name,
+    &FileUpload::new(upload_id, filename.clone(), content_type.clone(), None),
+    user.as_ref(),
+  )
+  .await?;
+
+  let partial_uploads_path = state.data_dir().partial_uploads_path();
+  tokio::fs::create_dir_all(&partial_uploads_path)
+    .await
+    .map_err(|err| RecordError::Internal(err.into()))?;
+  tokio::fs::File::create_new(partial_uploads_path.join(upload_id.to_string()))
+    .await
+    .map_err(|err| RecordError::Internal(err.into()))?;
+
+  let expires = Utc::now() + TUS_UPLOAD_TTL;
+  state
+    .session_conn()
+    .execute(
+      format!(
+        "INSERT INTO '{TUS_UPLOAD_TABLE}' \
+           (upload_id, record_api, record_id, column_name, user, original_filename, content_type, upload_length, expires) \
+         VALUES \
+           (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"
+      ),
+      params!(
+        upload_id.to_string(),
+        api_name.clone(),
+        record.clone(),
+        column_name.clone(),
+        user.as_ref().map(|u| u.uuid.into_bytes().to_vec()),
+        filename.clone(),
+        content_type.clone(),
+        upload_length as i64,
+        expires.timestamp(),
+      ),
+    )
+    .await?;
+
+  // Empty uploads won't receive any chunks and are therefore completed right away.
+  if upload_length == 0 {
+    let upload = TusUploadDb {
+      user: user.as_ref().map(|u| u.uuid.into_bytes().to_vec()),
+      original_filename: filename,
+      content_type,
+      upload_length: 0,
+      upload_offset: 0,
+      expires: expires.timestamp(),
+    };
+    let upload_id = upload_id.to_string();
+    let result = complete_upload(
+      &state,
+      &api_name,
+      &record,
+      &column_name,
+      &upload_id,
+      upload,
+      user.as_ref(),
+    )
+    .await;
+    delete_upload(&state, &upload_id).await;
+    result?;
+  }
+
+  return Ok(tus_response(
+    StatusCode::CREATED,
+    [
+      (
+        header::LOCATION,
+        format!("{}/{upload_id}", uri.path().trim_end_matches('/')),
+      ),
+      (UPLOAD_EXPIRES, format_http_date(&expires)),
+    ],
+  ));
+}
+
+/// Look up the offset of a resumable upload, e.g. to resume after a failure.
+#[utoipa::path(
+  head,
+  path = "/{name}/{record}/tus/{column_name}/{upload_id}",
+  tag = "records",
+  responses(
+    (status = 200, description = "Current offset as Upload-Offset.")
+  )
+)]
+pub async fn tus_upload_offset_handler(
+  State(state): State<AppState>,
+  Path((api_name, record, column_name, upload_id)): TusUploadPath,
+  user: Option<User>,
+  headers: HeaderMap,
+) -> Result<Response, RecordError> {
+  if let Some(response) = check_tus_resumable(&headers) {
+    return Ok(response);
+  }
+
+  let upload = lookup_upload(
+    &state,
+    &api_name,
+    &record,
+    &column_name,
+    &upload_id,
+    user.as_ref(),
+  )
+  .await?;
+
+  return Ok(tus_response(
+    StatusCode::OK,
+    [
+      (UPLOAD_OFFSET, upload.upload_offset.to_string()),
+      (UPLOAD_LENGTH, upload.upload_length.to_string()),
+      (UPLOAD_EXPIRES, format_http_date(&upload.expires()?)),
+      (header::CACHE_CONTROL, "no-store".to_string()),
+    ],
+  ));
+}
+
+/// Append a chunk to a resumable upload at the current `Upload-Offset`.
+///
+/// Once all bytes have been received, the file is assigned to the record's column.
+#[utoipa::path(
+  patch,
+  path = "/{name}/{record}/tus/{column_name}/{upload_id}",
+  tag = "records",
+  request_body(content = Vec<u8>, content_type = "application/offset+octet-stream"),
+  responses(
+    (status = 204, description = "Chunk received, new offset as Upload-Offset.")
+  )
+)]
+pub async fn tus_upload_chunk_handler(
+  State(state): State<AppState>,
+  Path((api_name, record, column_name, upload_id)): TusUploadPath,
+  user: Option<User>,
+  headers: HeaderMap,
+  body: Body,
+) -> Result<Response, RecordError> {
+  if let Some(response) = check_tus_resumable(&headers) {
+    return Ok(response);
+  }
+
+  if headers
+    .get(header::CONTENT_TYPE)
+    .is_none_or(|v| v != OFFSET_OCTET_STREAM)
+  {
+    return Ok(tus_response(StatusCode::UNSUPPORTED_MEDIA_TYPE, []));
+  }
+  let Some(offset) = parse_header::<i64>(&headers, &UPLOAD_OFFSET) else {
+    return Err(RecordError::BadRequest("Missing Upload-Offset"));
+  };
+
+  // Concurrent requests would otherwise interleave their writes to the same partial upload. The
+  // lock is held until the chunk has been appended and the upload possibly been completed.
+  let Some(_lock) = UploadLock::try_acquire(&upload_id) else {
+    return Ok(tus_response(StatusCode::LOCKED, []));
+  };
+
+  let upload = lookup_upload(
+    &state,
+    &api_name,
+    &record,
+    &column_name,
+    &upload_id,
+    user.as_ref(),
+  )
+  .await?;
+  if offset != upload.upload_offset {
+    return Ok(tus_response(StatusCode::CONFLICT, []));
+  }
+
+  let path = state.data_dir().partial_uploads_path().join(&upload_id);
+  let (new_offset, exceeded) = append_chunk(&path, &upload, body)
+    .await
+    .map_err(|err| RecordError::Internal(err.into()))?;
+  if exceeded {
+    delete_upload(&state, &upload_id).await;
+    return Err(RecordError::BadRequest("Chunk exceeds Upload-Length"));
+  }
+
+  // NOTE: Only advance the offset if no concurrent request did so in the meantime.
+  let expires = Utc::now() + TUS_UPLOAD_TTL;
+  let updated = state
+    .session_conn()
+    .execute(
+      format!(
+        "UPDATE '{TUS_UPLOAD_TABLE}' SET upload_offset = ?1, expires = ?2 \
+         WHERE upload_id = ?3 AND upload_offset = ?4"
+      ),
+      params!(new_offset, expires.timestamp(), upload_id.clone(), offset),
+    )
+    .await?;
+  if updated == 0 {
+    return Ok(tus_response(StatusCode::CONFLICT, []));
+  }
+
+  if new_offset == upload.upload_length {
+    let result = complete_upload(
+      &state,
+      &api_name,
+      &record,
+      &column_name,
+      &upload_id,
+      upload,
+      user.as_ref(),
+    )
+    .await;
+    delete_upload(&state, &upload_id).await;
+    result?;
+  }
+
+  return Ok(tus_response(
+    StatusCode::NO_CONTENT,
+    [
+      (UPLOAD_OFFSET, new_offset.to_string()),
+      (UPLOAD_EXPIRES, format_http_date(&expires)),
+    ],
+  ));
+}
+
+/// Abort a resumable upload.
+#[utoipa::path(
+  delete,
+  path = "/{name}/{record}/tus/{column_name}/{upload_id}",
+  tag = "records",
+  responses(
+    (status = 204, description = "Upload terminated.")
+  )
+)]
+pub async fn tus_terminate_upload_handler(
+  State(state): State<AppState>,
+  Path((api_name, record, column_name, upload_id)): TusUploadPath,
+  user: Option<User>,
+  headers: HeaderMap,
+) -> Result<Response, RecordError> {
+  if let Some(response) = check_tus_resumable(&headers) {
+    return Ok(response);
+  }
+
+  let Some(_lock) = UploadLock::try_acquire(&upload_id) else {
+    return Ok(tus_response(StatusCode::LOCKED, []));
+  };
+
+  lookup_upload(
+    &state,
+    &api_name,
+    &record,
+    &column_name,
+    &upload_id,
+    user.as_ref(),
+  )
+  .await?;
+  delete_upload(&state, &upload_id).await;
+
+  return Ok(tus_response(StatusCode::NO_CONTENT, []));
+}
+
+#[derive(Debug, Deserialize)]
+struct TusUploadDb {
+  user: Option<Vec<u8>>,
+  original_filename: Option<String>,
+  content_type: Option<String>,
+  upload_length: i64,
+  upload_offset: i64,
+  expires: i64,
+}
+
+impl TusUploadDb {
+  fn expires(&self) -> Result<DateTime<Utc>, RecordError> {
+    return DateTime::from_timestamp(self.expires, 0)
+      .ok_or_else(|| RecordError::Internal("invalid timestamp".into()));
+  }
+}
+
+/// Exclusive access to a partial upload, released on drop, e.g. also when the client disconnects.
+///
+/// NOTE: Only exclusive within this process, see [LOCKED_UPLOADS].
+struct UploadLock {
+  upload_id: String,
+}
+
+impl UploadLock {
+  /// Returns `None` if the upload is already locked.
+  fn try_acquire(upload_id: &str) -> Option<Self> {
+    if !LOCKED_UPLOADS.lock().insert(upload_id.to_string()) {
+      return None;
+    }
+    return Some(Self {
+      upload_id: upload_id.to_string(),
+    });
+  }
+}
+
+impl Drop for UploadLock {
+  fn drop(&mut self) {
+    LOCKED_UPLOADS.lock().remove(&self.upload_id);
+  }
+}
+
+async fn lookup_upload(
+  state: &AppState,
+  api_name: &str,
+  record: &str,
+  column_name: &str,
+  upload_id: &str,
+  user: Option<&User>,
+) -> Result<TusUploadDb, RecordError> {
+  let Some(upload) = state
+    .session_conn()
+    .read_query_value::<TusUploadDb>(
+      format!(
+        "SELECT user, original_filename, content_type, upload_length, upload_offset, expires \
+         FROM '{TUS_UPLOAD_TABLE}' \
+         WHERE upload_id = ?1 AND record_api = ?2 AND record_id = ?3 AND column_name = ?4 \
+           AND expires > UNIXEPOCH()"
+      ),
+      params!(
+        upload_id.to_string(),
+        api_name.to_string(),
+        record.to_string(),
+        column_name.to_string(),
+      ),
+    )
+    .await?
+  else {
+    return Err(RecordError::RecordNotFound);
+  };
+
+  // Uploads can only be continued by whoever created them.
+  if upload.user.as_deref() != user.map(|u| u.uuid.as_bytes().as_slice()) {
+    return Err(RecordError::Forbidden);
+  }
+
+  return Ok(upload);
+}
+
+/// Appends the request body to the partial upload at its current offset. Returns the new offset
+/// and whether the body exceeded the upload's length.
+///
+/// NOTE: If the connection breaks, the bytes received so far are kept. This is what allows clients
+/// to resume.
+async fn append_chunk(
+  path: &std::path::Path,
+  upload: &TusUploadDb,
+  body: Body,
+) -> Result<(i64, bool), std::io::Error> {
+  let mut file = tokio::fs::OpenOptions::new().write(true).open(path).await?;
+  file
+    .seek(std::io::SeekFrom::Start(upload.upload_offset as u64))
+    .await?;
+
+  let mut offset = upload.upload_offset;
+  let mut exceeded = false;
+  let mut stream = body.into_data_stream();
+  while let Some(chunk) = stream.next().await {
+    let Ok(chunk) = chunk else {
+      debug!("Resumable upload interrupted at offset: {offset}");
+      break;
+    };
+
+    let remaining = (upload.upload_length - offset) as usize;
+    if chunk.len() > remaining {
+      file.write_all(&chunk[..remaining]).await?;
+      offset += remaining as i64;
+      exceeded = true;
+      break;
+    }
+
+    file.write_all(&chunk).await?;
+    offset += chunk.len() as i64;
+  }
+  file.sync_data().await?;
+
+  return Ok((offset, exceeded));
+}
+
+async fn complete_upload(
+  state: &AppState,
+  api_name: &str,
+  record: &str,
+  column_name: &str,
+  upload_id: &str,
+  upload: TusUploadDb,
+  user: Option<&User>,
+) -> Result<(), RecordError> {
+  let Some(api) = state.lookup_record_api(api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+  let Ok(file_id) = Uuid::parse_str(upload_id) else {
+    return Err(RecordError::BadRequest("Invalid upload id"));
+  };
+  let path = state.data_dir().partial_uploads_path().join(upload_id);
+
+  // Like for regular uploads, we don't trust the user provided content type and check ourselves.
+  const MAGIC_BYTES_LEN: u64 = 8192;
+  let mut head = Vec::new();
+  tokio::fs::File::open(&path)
+    .await
+    .map_err(|err| RecordError::Internal(err.into()))?
+    .take(MAGIC_BYTES_LEN)
+    .read_to_end(&mut head)
+    .await
+    .map_err(|err| RecordError::Internal(err.into()))?;
+
+  let file_upload = FileUpload::new(
+    file_id,
+    upload.original_filename,
+    upload.content_type,
+    infer_mime_type(&head),
+  );
+
+  let mut file_manager =
+    FileManager::write_from_path(state.objectstore(), file_upload.clone(), &path)
+      .await
+      .map_err(|err| RecordError::Internal(err.into()))?;
+
+  // Dropping the file manager on error removes the file from the object store again.
+  assign_file_to_record(state, &api, record, column_name, &file_upload, user).await?;
+  file_manager.release();
+
+  return Ok(());
+}
+
+async fn delete_upload(state: &AppState, upload_id: &str) {
+  if let Err(err) = state
+    .session_conn()
+    .execute(
+      format!("DELETE FROM '{TUS_UPLOAD_TABLE}' WHERE upload_id = ?1"),
+      params!(upload_id.to_string()),
+    )
+    .await
+  {
+    warn!("Failed to delete resumable upload {upload_id}: {err}");
+  }
+
+  let path = state.data_dir().partial_uploads_path().join(upload_id);
+  if let Err(err) = tokio::fs::remove_file(&path).await {
+    debug!("Failed to remove partial upload {path:?}: {err}");
+  }
+}
+
+/// All requests but OPTIONS must specify the protocol version.
+fn check_tus_resumable(headers: &HeaderMap) -> Option<Response> {
+  if headers
+    .get(&TUS_RESUMABLE)
+    .is_some_and(|v| v == TUS_VERSION)
+  {
+    return None;
+  }
+
+  return Some(
+    (
+      StatusCode::PRECONDITION_FAILED,
+      [(TUS_VERSION_HEADER, TUS_VERSION)],
+    )
+      .into_response(),
+  );
+}
+
+fn tus_response<const N: usize>(
+  status: StatusCode,
+  headers: [(HeaderName, String); N],
+) -> Response {
+  return (status, [(TUS_RESUMABLE, TUS_VERSION.to_string())], headers).into_response();
+}
+
+fn parse_header<T: std::str::FromStr>(headers: &HeaderMap, name: &HeaderName) -> Option<T> {
+  return headers.get(name)?.to_str().ok()?.trim().parse().ok();
+}
+
+/// Parses `Upload-Metadata`, i.e. comma-separated pairs of keys and base64-encoded values.
+fn parse_upload_metadata(value: &str) -> HashMap<String, String> {
+  return value
+    .split(',')
+    .filter_map(|pair| {
+      let mut parts = pair.split_whitespace();
+      let key = parts.next()?;
+      let value = match parts.next() {
+        Some(encoded) => String::from_utf8(BASE64_STANDARD.decode(encoded).ok()?).ok()?,
+        None => String::new(),
+      };
+      return Some((key.to_string(), value));
+    })
+    .collect();
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::http::HeaderValue;
+  use object_store::ObjectStoreExt;
+
+  use super::*;
+  use crate::app_state::test_state;
+  use crate::config::proto::{PermissionFlag, RecordApiConfig};
+  use crate::records::test_utils::*;
+
+  #[test]
+  fn test_parse_upload_metadata() {
+    let metadata =
+      parse_upload_metadata("filename d29ybGRfZG9taW5hdGlvbl9wbGFuLnBkZg==,is_confidential");
+    assert_eq!(
+      metadata.get("filename").map(String::as_str),
+      Some("world_domination_plan.pdf")
+    );
+    assert_eq!(
+      metadata.get("is_confidential").map(String::as_str),
+      Some("")
+    );
+  }
+
+  #[tokio::test]
+  async fn test_tus_upload() {
+    let state = test_state(None).await.unwrap();
+    state
+      .conn()
+      .execute(
+        r#"CREATE TABLE test_table (
+          id           INTEGER PRIMARY KEY,
+          file         TEXT CHECK(jsonschema('std.FileUpload', file))
+        ) STRICT"#,
+        (),
+      )
+      .await
+      .unwrap();
+    state
+      .conn()
+      .execute("INSERT INTO test_table (id) VALUES (1)", ())
+      .await
+      .unwrap();
+    state.rebuild_connection_metadata().await.unwrap();
+
+    const API_NAME: &str = "test_api";
+    add_record_api_config(
+      &state,
+      RecordApiConfig {
+        name: Some(API_NAME.to_string()),
+        table_name: Some("test_table".to_string()),
+        acl_world: [PermissionFlag::Read as i32, PermissionFlag::Update as i32].into(),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let tus_headers = |pairs: &[(HeaderName, &str)]| {
+      let mut headers = HeaderMap::new();
+      headers.insert(TUS_RESUMABLE, HeaderValue::from_static(TUS_VERSION));
+      for (name, value) in pairs {
+        headers.insert(name, value.parse().unwrap());
+      }
+      return headers;
+    };
+
+    // Missing protocol version.
+    let response = tus_create_upload_handler(
+      State(state.clone()),
+      Path((API_NAME.to_string(), "1".to_string(), "file".to_string())),
+      OriginalUri("/tus".parse().unwrap()),
+      None,
+      HeaderMap::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+    let contents = b"0123456789".to_vec();
+    let response = tus_create_upload_handler(
+      State(state.clone()),
+      Path((API_NAME.to_string(), "1".to_string(), "file".to_string())),
+      OriginalUri("/api/records/v1/test_api/1/tus/file".parse().unwrap()),
+      None,
+      tus_headers(&[
+        (UPLOAD_LENGTH, &contents.len().to_string()),
+        (
+          UPLOAD_METADATA,
+          "filename Zm9vLnR4dA==,filetype dGV4dC9wbGFpbg==",
+        ),
+      ]),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let location = response
+      .headers()
+      .get(header::LOCATION)
+      .unwrap()
+      .to_str()
+      .unwrap();
+    let upload_id = location
+      .strip_prefix("/api/records/v1/test_api/1/tus/file/")
+      .unwrap()
+      .to_string();
+    let upload_path = || {
+      return Path((
+        API_NAME.to_string(),
+        "1".to_string(),
+        "file".to_string(),
+        upload_id.clone(),
+      ));
+    };
+
+    let offset = async || {
+      let response =
+        tus_upload_offset_handler(State(state.clone()), upload_path(), None, tus_headers(&[]))
+          .await
+          .unwrap();
+      assert_eq!(response.status(), StatusCode::OK);
+      return response
+        .headers()
+        .get(&UPLOAD_OFFSET)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    };
+    assert_eq!(offset().await, "0");
+
+    let chunk = async |offset: usize, bytes: &[u8]| {
+      return tus_upload_chunk_handler(
+        State(state.clone()),
+        upload_path(),
+        None,
+        tus_headers(&[
+          (header::CONTENT_TYPE, OFFSET_OCTET_STREAM),
+          (UPLOAD_OFFSET, &offset.to_string()),
+        ]),
+        Body::from(bytes.to_vec()),
+      )
+      .await
+      .unwrap();
+    };
+
+    assert_eq!(
+      chunk(0, &contents[..4]).await.status(),
+      StatusCode::NO_CONTENT
+    );
+    assert_eq!(offset().await, "4");
+
+    // Mismatching offset.
+    assert_eq!(
+      chunk(2, &contents[2..]).await.status(),
+      StatusCode::CONFLICT
+    );
+
+    // Concurrent requests are rejected while another one is being processed.
+    {
+      let _lock = UploadLock::try_acquire(&upload_id).unwrap();
+      assert!(UploadLock::try_acquire(&upload_id).is_none());
+      assert_eq!(chunk(4, &contents[4..]).await.status(), StatusCode::LOCKED);
+    }
+    assert_eq!(offset().await, "4");
+
+    let response = chunk(4, &contents[4..]).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(response.headers().get(&UPLOAD_OFFSET).unwrap(), "10");
+
+    // Completed uploads are gone.
+    assert!(
+      tus_upload_offset_handler(State(state.clone()), upload_path(), None, tus_headers(&[]))
+        .await
+        .is_err()
+    );
+
+    let file_upload: FileUpload = serde_json::from_str(
+      &state
+        .conn()
+        .read_query_row_get::<String>("SELECT file FROM test_table WHERE id = 1", (), 0)
+        .await
+        .unwrap()
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(file_upload.objectstore_id(), upload_id);
+    assert_eq!(file_upload.original_filename(), Some("foo.txt"));
+    assert_eq!(file_upload.content_type(), Some("text/plain"));
+
+    let stored = state
+      .objectstore()
+      .get(&object_store::path::Path::from(upload_id.as_str()))
+      .await
+      .unwrap()
+      .bytes()
+      .await
+      .unwrap();
+    assert_eq!(stored.to_vec(), contents);
+
+    // Empty uploads are completed on creation.
+    let response = tus_create_upload_handler(
+      State(state.clone()),
+      Path((API_NAME.to_string(), "1".to_string(), "file".to_string())),
+      OriginalUri("/api/records/v1/test_api/1/tus/file".parse().unwrap()),
+      None,
+      tus_headers(&[
+        (UPLOAD_LENGTH, "0"),
+        (UPLOAD_METADATA, "filename ZW1wdHkudHh0"),
+      ]),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let file_upload: FileUpload = serde_json::from_str(
+      &state
+        .conn()
+        .read_query_row_get::<String>("SELECT file FROM test_table WHERE id = 1", (), 0)
+        .await
+        .unwrap()
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(file_upload.original_filename(), Some("empty.txt"));
+
+    let stored = state
+      .objectstore()
+      .get(&object_store::path::Path::from(
+        file_upload.objectstore_id(),
+      ))
+      .await
+      .unwrap()
+      .bytes()
+      .await
+      .unwrap();
+    assert!(stored.is_empty());
+  }
+}
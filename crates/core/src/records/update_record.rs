@@ -1,11 +1,14 @@
 use axum::extract::{Path, State};
+use trailbase_schema::FileUpload;
+use trailbase_sqlite::Value;
 
 use crate::app_state::AppState;
 use crate::auth::user::User;
 use crate::extract::Either;
-use crate::records::params::{JsonRow, LazyParams};
+use crate::records::hooks::{HookedWrite, run_writes_with_hooks};
+use crate::records::params::{JsonRow, LazyParams, Params};
 use crate::records::write_queries::run_update_query;
-use crate::records::{Permission, RecordError};
+use crate::records::{Permission, RecordApi, RecordError};
 
 /// Update existing record.
 #[utoipa::path(
@@ -37,7 +40,7 @@ pub async fn update_record_handler(
     Either::Form(value) => (value, None),
   };
 
-  let record_id = api.primary_key_to_value(record)?;
+  let record_id = api.primary_key_to_value(record.clone())?;
 
   #[cfg(debug_assertions)]
   crate::records::json_schema::validate_api_json_schema(
@@ -48,6 +51,12 @@ pub async fn update_record_handler(
   )
   .map_err(|_err| RecordError::BadRequest("Invalid Parameters"))?;
 
+  // Record hooks need the original payload, i.e. we only keep a copy if hooks are registered.
+  let hook_record = state
+    .record_hooks()
+    .has_hooks(&api_name)
+    .then(|| request.clone());
+
   let mut lazy_params = LazyParams::for_update(
     &api,
     state.json_schema_registry().clone(),
@@ -66,20 +75,107 @@ pub async fn update_record_handler(
     )
     .await?;
 
-  run_update_query(
-    api.conn(),
-    state.objectstore(),
-    api.table_name(),
-    lazy_params
-      .consume()
-      .map_err(|_err| RecordError::BadRequest("Invalid Parameters"))?,
-  )
-  .await
-  .map_err(|err| RecordError::Internal(err.into()))?;
+  let params = lazy_params
+    .consume()
+    .map_err(|_err| RecordError::BadRequest("Invalid Parameters"))?;
+
+  if let Some(hook_record) = hook_record {
+    run_writes_with_hooks(
+      &state,
+      &api,
+      user.as_ref(),
+      vec![HookedWrite::Update {
+        record_id: record,
+        record: hook_record,
+        params,
+      }],
+    )
+    .await?;
+
+    return Ok(());
+  }
+
+  run_update_query(api.conn(), state.objectstore(), api.table_name(), params)
+    .await
+    .map_err(|err| RecordError::Internal(err.into()))?;
 
   return Ok(());
 }
 
+/// Checks the user's update access for pointing the record's `std.FileUpload` column at
+/// `file_upload`, e.g. ahead of an upload.
+pub(crate) async fn check_file_update_access(
+  state: &AppState,
+  api: &RecordApi,
+  record_id: Value,
+  column_name: &str,
+  file_upload: &FileUpload,
+  user: Option<&User>,
+) -> Result<Params, RecordError> {
+  let params = Params::for_file_update(
+    api,
+    &state.json_schema_registry().read(),
+    column_name,
+    file_upload,
+    api.record_pk_column().column.name.clone(),
+    record_id.clone(),
+  )
+  .map_err(|_err| RecordError::BadRequest("Invalid file column"))?;
+
+  let mut lazy_params = LazyParams::Params(Ok(params));
+  api
+    .check_record_level_access(
+      Permission::Update,
+      Some(&record_id),
+      Some(&mut lazy_params),
+      user,
+    )
+    .await?;
+
+  return lazy_params
+    .consume()
+    .map_err(|err| RecordError::Internal(err.into()));
+}
+
+/// Points the record's `std.FileUpload` column at a file already present in the object store, e.g.
+/// uploaded directly by the client or resumably in chunks.
+pub(crate) async fn assign_file_to_record(
+  state: &AppState,
+  api: &RecordApi,
+  record: &str,
+  column_name: &str,
+  file_upload: &FileUpload,
+  user: Option<&User>,
+) -> Result<(), RecordError> {
+  let record_id = api.primary_key_to_value(record.to_string())?;
+  let params =
+    check_file_update_access(state, api, record_id, column_name, file_upload, user).await?;
+
+  if state.record_hooks().has_hooks(api.api_name()) {
+    let mut hook_record = JsonRow::new();
+    hook_record.insert(
+      column_name.to_string(),
+      serde_json::to_value(file_upload).map_err(|err| RecordError::Internal(err.into()))?,
+    );
+
+    run_writes_with_hooks(
+      state,
+      api,
+      user,
+      vec![HookedWrite::Update {
+        record_id: record.to_string(),
+        record: hook_record,
+        params,
+      }],
+    )
+    .await?;
+
+    return Ok(());
+  }
+
+  return run_update_query(api.conn(), state.objectstore(), api.table_name(), params).await;
+}
+
 #[cfg(test)]
 mod test {
   use axum::extract::Query;
@@ -6,6 +6,7 @@ use trailbase_schema::sqlite::ColumnOption;
 
 use crate::config::{ConfigError, proto};
 use crate::connection::{ConnectionEntry, ConnectionManager};
+use crate::constants::IMAGE_TRANSFORM_MAX_DIMENSION;
 
 fn validate_record_api_name(name: &str) -> Result<(), ConfigError> {
   if name.is_empty() {
@@ -21,6 +22,32 @@ fn validate_record_api_name(name: &str) -> Result<(), ConfigError> {
   Ok(())
 }
 
+/// Validates the image transforms allowed for `owner`, e.g. a Record API.
+pub(crate) fn validate_image_transforms(
+  owner: &str,
+  transforms: &[proto::ImageTransform],
+) -> Result<(), ConfigError> {
+  for transform in transforms {
+    let dimensions = [transform.width, transform.height];
+    if dimensions.iter().all(Option::is_none) {
+      return Err(invalid(format!(
+        "{owner} has an image transform w/o width or height."
+      )));
+    }
+    if dimensions
+      .iter()
+      .flatten()
+      .any(|d| *d == 0 || *d > IMAGE_TRANSFORM_MAX_DIMENSION)
+    {
+      return Err(invalid(format!(
+        "{owner} has an image transform with dimensions outside (0, {IMAGE_TRANSFORM_MAX_DIMENSION}]."
+      )));
+    }
+  }
+
+  return Ok(());
+}
+
 pub(crate) fn validate_record_api_config(
   connection_manager: &ConnectionManager,
   api_config: &proto::RecordApiConfig,
@@ -51,6 +78,18 @@ pub(crate) fn validate_record_api_config(
     )));
   }
 
+  if api_config
+    .acl_roles
+    .iter()
+    .any(|role_acl| role_acl.role.as_deref().is_none_or(str::is_empty))
+  {
+    return Err(invalid(format!(
+      "API '{api_name}' has a role ACL w/o role."
+    )));
+  }
+
+  validate_image_transforms(&format!("API '{api_name}'"), &api_config.image_transforms)?;
+
   for (kind, rule) in [
     (AccessKind::Create, api_config.create_access_rule.as_ref()),
     (AccessKind::Read, api_config.read_access_rule.as_ref()),
@@ -0,0 +1,969 @@
+//! Continuous, Litestream-style replication of SQLite databases to S3-compatible object storage.
+//!
+//! Each replicated database is stored as a sequence of "generations" under
+//! `<prefix>/<db>/<generation>/`. A generation starts with a full snapshot of the database and is
+//! followed by WAL segments, i.e. batches of committed WAL frames, which can be replayed on top of
+//! the snapshot to recover the database's state at any point in time.
+//!
+//! The server's connections don't checkpoint automatically while replication is enabled. Instead,
+//! the replicator checkpoints frames once they have been shipped, which ensures that the WAL is
+//! never restarted before all its frames have been replicated.
+use chrono::{DateTime, NaiveDateTime, Utc};
+use futures_util::TryStreamExt;
+use log::*;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectMeta, ObjectStore, ObjectStoreExt};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::runtime::Handle;
+
+use crate::DataDir;
+use crate::app_state::build_objectstore;
+use crate::config::proto::{DatabaseConfig, ReplicationConfig, S3StorageConfig};
+use crate::config::{ConfigError, maybe_load_merged_config_unverified};
+use crate::constants::{
+  REPLICATION_CHECKPOINT_THRESHOLD, REPLICATION_INTERVAL_DEFAULT, REPLICATION_PREFIX_DEFAULT,
+  REPLICATION_RETENTION_DEFAULT, REPLICATION_SNAPSHOT_INTERVAL_DEFAULT,
+};
+
+#[derive(Debug, Error)]
+pub enum ReplicationError {
+  #[error("IO error: {0}")]
+  IO(#[from] std::io::Error),
+  #[error("SQLite error: {0}")]
+  Sqlite(#[from] rusqlite::Error),
+  #[error("Object store error: {0}")]
+  ObjectStore(#[from] object_store::Error),
+  #[error("Config error: {0}")]
+  Config(#[from] ConfigError),
+  #[error("Replication not configured: {0}")]
+  NotConfigured(&'static str),
+  #[error("Database not in WAL mode: {0:?}")]
+  NotWal(PathBuf),
+  #[error("Database in use, stop the server first: {0:?}")]
+  InUse(PathBuf),
+  #[error("Corrupt replica: {0}")]
+  Corrupt(String),
+}
+
+/// Format of generation ids and segment timestamps. Sorts lexicographically.
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+const SNAPSHOT_NAME: &str = "snapshot.db.gz";
+const SEGMENTS_DIR: &str = "wal";
+const COMPRESSED_EXTENSION: &str = ".gz";
+
+const WAL_HEADER_SIZE: usize = 32;
+const WAL_FRAME_HEADER_SIZE: usize = 24;
+const WAL_MAGIC_LE: u32 = 0x377f0682;
+const WAL_MAGIC_BE: u32 = 0x377f0683;
+
+/// Handle to the running replication threads. Dropping it stops replication.
+pub(crate) struct Replication {
+  _stop: Vec<flume::Sender<()>>,
+}
+
+/// Starts replicating the main and all configured databases, if enabled.
+pub(crate) fn start_replication(
+  data_dir: &DataDir,
+  config: Option<&ReplicationConfig>,
+  s3_config: Option<&S3StorageConfig>,
+  databases: &[DatabaseConfig],
+) -> Result<Option<Replication>, ReplicationError> {
+  let Some(config) = config.filter(|c| c.enabled.unwrap_or(false)) else {
+    return Ok(None);
+  };
+  let store = build_replica_store(data_dir, s3_config)?;
+  let handle = Handle::try_current().map_err(std::io::Error::other)?;
+
+  let prefix = ObjectPath::from(
+    config
+      .prefix
+      .as_deref()
+      .unwrap_or(REPLICATION_PREFIX_DEFAULT),
+  );
+  let interval = config
+    .interval_ms
+    .map_or(REPLICATION_INTERVAL_DEFAULT, |ms| {
+      Duration::from_millis(ms as u64)
+    });
+  let snapshot_interval = config
+    .snapshot_interval_sec
+    .map_or(REPLICATION_SNAPSHOT_INTERVAL_DEFAULT, |s| {
+      Duration::from_secs(s as u64)
+    });
+  let retention = config
+    .retention
+    .map_or(REPLICATION_RETENTION_DEFAULT, |r| r as usize);
+
+  let mut stop: Vec<flume::Sender<()>> = vec![];
+  for name in database_names(databases) {
+    let mut replicator = Replicator {
+      db_path: database_path(data_dir, &name),
+      store: store.clone(),
+      prefix: prefix.child(name.as_str()),
+      snapshot_interval,
+      retention,
+      checkpoint_threshold: REPLICATION_CHECKPOINT_THRESHOLD,
+      generation: None,
+      name: name.clone(),
+    };
+
+    let (sender, receiver) = flume::bounded::<()>(1);
+    let handle = handle.clone();
+    std::thread::Builder::new()
+      .name(format!("replication-{name}"))
+      .spawn(move || {
+        loop {
+          if let Err(err) = replicator.tick(&handle) {
+            warn!("Replication of '{}' failed: {err}", replicator.name);
+          }
+
+          // Runs until the sender gets dropped.
+          if let Err(flume::RecvTimeoutError::Disconnected) = receiver.recv_timeout(interval) {
+            return;
+          }
+        }
+      })?;
+    stop.push(sender);
+  }
+
+  info!("Started replication to: {prefix}");
+
+  return Ok(Some(Replication { _stop: stop }));
+}
+
+/// Restores the main and all configured databases from the replicas in the configured S3 bucket.
+/// Restores the latest replicated state or, if `at` is given, the state at the given point in
+/// time. Must only be run while the server is stopped.
+///
+/// Returns the paths of the restored databases.
+pub async fn restore_from_replica(
+  data_dir: &DataDir,
+  at: Option<DateTime<Utc>>,
+  force: bool,
+) -> Result<Vec<PathBuf>, ReplicationError> {
+  let Some(config) = maybe_load_merged_config_unverified(data_dir)? else {
+    return Err(ReplicationError::NotConfigured("missing config"));
+  };
+  let store = build_replica_store(data_dir, config.server.s3_storage_config.as_ref())?;
+  let prefix = ObjectPath::from(
+    config
+      .server
+      .replication_config
+      .as_ref()
+      .and_then(|c| c.prefix.as_deref())
+      .unwrap_or(REPLICATION_PREFIX_DEFAULT),
+  );
+
+  return restore_databases(
+    data_dir,
+    &*store,
+    &prefix,
+    &database_names(&config.databases),
+    at,
+    force,
+  )
+  .await;
+}
+
+fn build_replica_store(
+  data_dir: &DataDir,
+  s3_config: Option<&S3StorageConfig>,
+) -> Result<Arc<dyn ObjectStore>, ReplicationError> {
+  let Some(s3_config) = s3_config else {
+    return Err(ReplicationError::NotConfigured(
+      "missing 'server.s3_storage_config'",
+    ));
+  };
+  if s3_config.bucket_name.is_none() {
+    return Err(ReplicationError::NotConfigured(
+      "missing 'server.s3_storage_config.bucket_name'",
+    ));
+  }
+  return Ok(build_objectstore(data_dir, Some(s3_config))?.into());
+}
+
+fn database_names(databases: &[DatabaseConfig]) -> Vec<String> {
+  return std::iter::once("main".to_string())
+    .chain(databases.iter().flat_map(|d| d.name.clone()))
+    .collect();
+}
+
+fn database_path(data_dir: &DataDir, name: &str) -> PathBuf {
+  return data_dir.data_path().join(format!("{name}.db"));
+}
+
+/// Replicates a single database.
+struct Replicator {
+  name: String,
+  db_path: PathBuf,
+  store: Arc<dyn ObjectStore>,
+  /// Object path of this database's replica, i.e. `<prefix>/<db>`.
+  prefix: ObjectPath,
+  snapshot_interval: Duration,
+  retention: usize,
+  /// Number of WAL frames after which replicated frames get checkpointed.
+  checkpoint_threshold: u64,
+  generation: Option<Generation>,
+}
+
+struct Generation {
+  id: String,
+  started: Instant,
+  /// Position in the WAL up to which frames have been replicated. None if there was no valid WAL
+  /// at the time of the snapshot.
+  cursor: Option<WalCursor>,
+  next_segment: u64,
+  /// Whether all frames up to `cursor` have been checkpointed, i.e. the next writer may restart
+  /// the WAL without any frames being lost.
+  checkpointed: bool,
+  conn: rusqlite::Connection,
+}
+
+impl Replicator {
+  /// Ships all newly committed WAL frames or starts a new generation if needed.
+  fn tick(&mut self, handle: &Handle) -> Result<(), ReplicationError> {
+    let Some(generation) = self
+      .generation
+      .as_mut()
+      .filter(|g| g.started.elapsed() < self.snapshot_interval)
+    else {
+      return self.start_generation(handle);
+    };
+
+    let wal_path = wal_path(&self.db_path);
+    let wal = with_read_lock(&mut generation.conn, || {
+      return Ok(match read_wal(&wal_path, generation.cursor.as_ref())? {
+        Some(wal) => Some(wal),
+        // Restarted after a complete checkpoint, i.e. all prior frames had been replicated.
+        None if generation.checkpointed => read_wal(&wal_path, None)?,
+        None => None,
+      });
+    })?;
+    let Some((frames, cursor)) = wal else {
+      info!("WAL of '{}' was reset, starting new generation", self.name);
+      return self.start_generation(handle);
+    };
+
+    if let Some(ref cursor) = cursor
+      && !frames.is_empty()
+    {
+      let mut segment = cursor.page_size.to_be_bytes().to_vec();
+      segment.extend(frames);
+
+      let path = self
+        .prefix
+        .child(generation.id.as_str())
+        .child(SEGMENTS_DIR)
+        .child(format!(
+          "{:010}-{}{COMPRESSED_EXTENSION}",
+          generation.next_segment,
+          Utc::now().format(TIMESTAMP_FORMAT)
+        ));
+      handle.block_on(self.store.put(&path, compress(&segment)?.into()))?;
+
+      generation.next_segment += 1;
+      generation.checkpointed = false;
+    }
+
+    // Only advance after a successful upload, otherwise frames will be retried next time.
+    generation.cursor = cursor;
+
+    if let Some(ref cursor) = generation.cursor
+      && !generation.checkpointed
+      && cursor.num_frames() >= self.checkpoint_threshold
+    {
+      generation.checkpointed = checkpoint(&self.db_path, &generation.conn, cursor)?;
+    }
+
+    return Ok(());
+  }
+
+  /// Starts a new generation by uploading a full snapshot of the database.
+  fn start_generation(&mut self, handle: &Handle) -> Result<(), ReplicationError> {
+    self.generation = None;
+
+    let id = Utc::now().format(TIMESTAMP_FORMAT).to_string();
+    let snapshot_path = with_suffix(&self.db_path, "-snapshot");
+
+    let (cursor, conn) = {
+      let conn = open_connection(&self.db_path)?;
+
+      let journal_mode: String = conn.query_row("PRAGMA journal_mode", (), |row| row.get(0))?;
+      if !journal_mode.eq_ignore_ascii_case("wal") {
+        return Err(ReplicationError::NotWal(self.db_path.clone()));
+      }
+
+      // Block writers to make sure that the snapshot and the WAL position are consistent.
+      conn.execute_batch("BEGIN IMMEDIATE")?;
+
+      let cursor = read_wal(&wal_path(&self.db_path), None)?.and_then(|(_, cursor)| cursor);
+
+      if snapshot_path.exists() {
+        std::fs::remove_file(&snapshot_path)?;
+      }
+      conn.backup("main", &snapshot_path, None)?;
+      conn.execute_batch("COMMIT")?;
+
+      (cursor, conn)
+    };
+
+    let snapshot = compress(&std::fs::read(&snapshot_path)?)?;
+    std::fs::remove_file(&snapshot_path)?;
+
+    handle.block_on(self.store.put(
+      &self.prefix.child(id.as_str()).child(SNAPSHOT_NAME),
+      snapshot.into(),
+    ))?;
+    debug!("Started replication generation {}/{id}", self.prefix);
+
+    self.generation = Some(Generation {
+      id,
+      started: Instant::now(),
+      cursor,
+      next_segment: 0,
+      checkpointed: false,
+      conn,
+    });
+
+    if self.retention > 0 {
+      handle.block_on(prune_generations(
+        &*self.store,
+        &self.prefix,
+        self.retention,
+      ))?;
+    }
+
+    return Ok(());
+  }
+}
+
+fn open_connection(db_path: &Path) -> Result<rusqlite::Connection, rusqlite::Error> {
+  let conn = rusqlite::Connection::open(db_path)?;
+  conn.busy_timeout(Duration::from_secs(5))?;
+  return Ok(conn);
+}
+
+/// Runs `f` while holding a read transaction, which prevents SQLite from restarting the WAL.
+fn with_read_lock<T>(
+  conn: &mut rusqlite::Connection,
+  f: impl FnOnce() -> Result<T, ReplicationError>,
+) -> Result<T, ReplicationError> {
+  let tx = conn.transaction()?;
+  // Read transactions only start with the first read.
+  tx.query_row("SELECT COUNT(*) FROM sqlite_schema", (), |_row| Ok(()))?;
+
+  let result = f();
+  tx.rollback()?;
+  return result;
+}
+
+/// Checkpoints the already replicated frames up to `cursor` into the database.
+///
+/// Returns true if the WAL was checkpointed completely, i.e. may be restarted by the next writer.
+fn checkpoint(
+  db_path: &Path,
+  conn: &rusqlite::Connection,
+  cursor: &WalCursor,
+) -> Result<bool, ReplicationError> {
+  // Block writers while checkpointing. Otherwise, frames committed after `cursor` could get
+  // checkpointed and subsequently lost when the WAL is restarted before they were replicated.
+  let lock = open_connection(db_path)?;
+  lock.execute_batch("BEGIN IMMEDIATE")?;
+
+  let replicated =
+    read_wal(&wal_path(db_path), Some(cursor))?.is_some_and(|(frames, _cursor)| frames.is_empty());
+  if !replicated {
+    // Retry after the new frames have been replicated on the next tick.
+    return Ok(false);
+  }
+
+  let (busy, log, checkpointed): (i64, i64, i64) =
+    conn.query_row("PRAGMA wal_checkpoint(PASSIVE)", (), |row| {
+      return Ok((row.get(0)?, row.get(1)?, row.get(2)?));
+    })?;
+  lock.execute_batch("ROLLBACK")?;
+
+  return Ok(busy == 0 && log == checkpointed);
+}
+
+/// Lists a database's generations, oldest first.
+async fn list_generations(
+  store: &dyn ObjectStore,
+  prefix: &ObjectPath,
+) -> Result<Vec<(String, DateTime<Utc>)>, object_store::Error> {
+  let listing = store.list_with_delimiter(Some(prefix)).await?;
+
+  let mut generations: Vec<(String, DateTime<Utc>)> = listing
+    .common_prefixes
+    .iter()
+    .filter_map(|p| {
+      let id = p.filename()?;
+      let created = NaiveDateTime::parse_from_str(id, TIMESTAMP_FORMAT).ok()?;
+      return Some((id.to_string(), created.and_utc()));
+    })
+    .collect();
+  generations.sort_by(|a, b| a.1.cmp(&b.1));
+
+  return Ok(generations);
+}
+
+async fn prune_generations(
+  store: &dyn ObjectStore,
+  prefix: &ObjectPath,
+  retention: usize,
+) -> Result<(), object_store::Error> {
+  let generations = list_generations(store, prefix).await?;
+  let num_stale = generations.len().saturating_sub(retention);
+
+  for (id, _created) in generations.into_iter().take(num_stale) {
+    debug!("Removing stale replication generation: {prefix}/{id}");
+
+    let objects: Vec<ObjectMeta> = store
+      .list(Some(&prefix.child(id.as_str())))
+      .try_collect()
+      .await?;
+    for object in objects {
+      store.delete(&object.location).await?;
+    }
+  }
+
+  return Ok(());
+}
+
+async fn restore_databases(
+  data_dir: &DataDir,
+  store: &dyn ObjectStore,
+  prefix: &ObjectPath,
+  names: &[String],
+  at: Option<DateTime<Utc>>,
+  force: bool,
+) -> Result<Vec<PathBuf>, ReplicationError> {
+  // A present WAL indicates that the database is either in use or wasn't shut down cleanly.
+  // Either way, blindly replacing the database file would be unsafe.
+  if !force {
+    for name in names {
+      let dst = database_path(data_dir, name);
+      if wal_path(&dst).exists() {
+        return Err(ReplicationError::InUse(dst));
+      }
+    }
+  }
+
+  std::fs::create_dir_all(data_dir.data_path())?;
+
+  let mut restored: Vec<PathBuf> = vec![];
+  for name in names {
+    let db_prefix = prefix.child(name.as_str());
+    let Some((generation, _created)) = list_generations(store, &db_prefix)
+      .await?
+      .into_iter()
+      .rev()
+      .find(|(_id, created)| at.is_none_or(|at| *created <= at))
+    else {
+      warn!("No replica of '{name}' found, skipping");
+      continue;
+    };
+
+    let dst = database_path(data_dir, name);
+    let tmp = with_suffix(&dst, ".restore");
+    restore_generation(store, &db_prefix.child(generation.as_str()), &tmp, at).await?;
+
+    // Remove any stale WAL and shared-memory files, which don't belong to the restored database.
+    for suffix in ["-wal", "-shm"] {
+      let path = with_suffix(&dst, suffix);
+      if path.exists() {
+        std::fs::remove_file(path)?;
+      }
+    }
+
+    std::fs::rename(&tmp, &dst)?;
+    restored.push(dst);
+  }
+
+  return Ok(restored);
+}
+
+async fn restore_generation(
+  store: &dyn ObjectStore,
+  generation: &ObjectPath,
+  dst: &Path,
+  at: Option<DateTime<Utc>>,
+) -> Result<(), ReplicationError> {
+  let objects: Vec<ObjectMeta> = store
+    .list(Some(&generation.child(SEGMENTS_DIR)))
+    .try_collect()
+    .await?;
+
+  let mut segments: Vec<(u64, ObjectPath)> = vec![];
+  for object in objects {
+    let Some((index, created)) = object.location.filename().and_then(parse_segment_name) else {
+      warn!("Skipping unexpected object: {}", object.location);
+      continue;
+    };
+    if at.is_some_and(|at| created > at) {
+      continue;
+    }
+    segments.push((index, object.location));
+  }
+  segments.sort_by_key(|(index, _)| *index);
+
+  for (expected, (index, _)) in segments.iter().enumerate() {
+    if *index != expected as u64 {
+      return Err(ReplicationError::Corrupt(format!(
+        "missing WAL segment {expected} in {generation}"
+      )));
+    }
+  }
+
+  let snapshot = store
+    .get(&generation.child(SNAPSHOT_NAME))
+    .await?
+    .bytes()
+    .await?;
+
+  let mut file = std::fs::File::create(dst)?;
+  file.write_all(&decompress(&snapshot)?)?;
+
+  for (_index, path) in segments {
+    let segment = store.get(&path).await?.bytes().await?;
+    apply_segment(&mut file, &decompress(&segment)?)?;
+  }
+  file.sync_all()?;
+  drop(file);
+
+  return check_integrity(dst);
+}
+
+fn parse_segment_name(name: &str) -> Option<(u64, DateTime<Utc>)> {
+  let (index, created) = name.strip_suffix(COMPRESSED_EXTENSION)?.split_once('-')?;
+  return Some((
+    index.parse().ok()?,
+    NaiveDateTime::parse_from_str(created, TIMESTAMP_FORMAT)
+      .ok()?
+      .and_utc(),
+  ));
+}
+
+/// Writes the pages of a segment's WAL frames into the database file.
+fn apply_segment(file: &mut std::fs::File, segment: &[u8]) -> Result<(), ReplicationError> {
+  let corrupt = || ReplicationError::Corrupt("invalid WAL segment".to_string());
+
+  if segment.len() < 4 {
+    return Err(corrupt());
+  }
+  let page_size = be_u32(segment, 0) as usize;
+  let frame_size = WAL_FRAME_HEADER_SIZE + page_size;
+  let frames = &segment[4..];
+  if page_size == 0 || frames.len() % frame_size != 0 {
+    return Err(corrupt());
+  }
+
+  // NOTE: Segments only contain frames of committed transactions, thus they can be applied
+  // frame-by-frame.
+  for frame in frames.chunks_exact(frame_size) {
+    let page_number = be_u32(frame, 0) as u64;
+    if page_number == 0 {
+      return Err(corrupt());
+    }
+
+    file.seek(SeekFrom::Start((page_number - 1) * page_size as u64))?;
+    file.write_all(&frame[WAL_FRAME_HEADER_SIZE..])?;
+
+    // Commit frames carry the size of the database in pages after the commit.
+    let db_size = be_u32(frame, 4) as u64;
+    if db_size > 0 {
+      file.set_len(db_size * page_size as u64)?;
+    }
+  }
+
+  return Ok(());
+}
+
+fn check_integrity(path: &Path) -> Result<(), ReplicationError> {
+  let conn = rusqlite::Connection::open(path)?;
+  // CHECK constraints may depend on TrailBase's extension functions, which aren't available here.
+  conn.pragma_update(None, "ignore_check_constraints", true)?;
+
+  let result: String = conn.query_row("PRAGMA integrity_check", (), |row| row.get(0))?;
+  if result != "ok" {
+    return Err(ReplicationError::Corrupt(format!("{path:?}: {result}")));
+  }
+  return Ok(());
+}
+
+/// Position within a WAL up to which all committed frames have been read.
+#[derive(Clone, Debug, PartialEq)]
+struct WalCursor {
+  salt: [u32; 2],
+  page_size: u32,
+  big_endian: bool,
+  /// Offset of the next frame.
+  offset: u64,
+  /// Cumulative checksum up to `offset`.
+  checksum: [u32; 2],
+}
+
+impl WalCursor {
+  /// Number of frames preceding the cursor.
+  fn num_frames(&self) -> u64 {
+    let frame_size = (WAL_FRAME_HEADER_SIZE + self.page_size as usize) as u64;
+    return (self.offset - WAL_HEADER_SIZE as u64) / frame_size;
+  }
+}
+
+/// Reads all committed frames following `cursor`, or following the WAL header if no cursor is
+/// given, and returns them alongside the advanced cursor.
+///
+/// Returns None if the WAL has been reset or truncated since `cursor`.
+fn read_wal(
+  wal_path: &Path,
+  cursor: Option<&WalCursor>,
+) -> Result<Option<(Vec<u8>, Option<WalCursor>)>, std::io::Error> {
+  let mut file = match std::fs::File::open(wal_path) {
+    Ok(file) => file,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+      return Ok(cursor.is_none().then(|| (vec![], None)));
+    }
+    Err(err) => {
+      return Err(err);
+    }
+  };
+
+  let mut header = [0u8; WAL_HEADER_SIZE];
+  let header = match file.read_exact(&mut header) {
+    Ok(()) => parse_wal_header(&header),
+    Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => None,
+    Err(err) => {
+      return Err(err);
+    }
+  };
+
+  let start = match (cursor, header) {
+    (None, None) => {
+      return Ok(Some((vec![], None)));
+    }
+    (None, Some(header)) => header,
+    (Some(cursor), Some(header))
+      if cursor.salt == header.salt && cursor.page_size == header.page_size =>
+    {
+      cursor.clone()
+    }
+    (Some(_), _) => {
+      return Ok(None);
+    }
+  };
+
+  file.seek(SeekFrom::Start(start.offset))?;
+  let mut data: Vec<u8> = vec![];
+  file.read_to_end(&mut data)?;
+
+  let frame_size = WAL_FRAME_HEADER_SIZE + start.page_size as usize;
+  let mut offset: usize = 0;
+  let mut checksum = start.checksum;
+  let mut committed = (0, checksum);
+
+  // Frames are valid as long as their salts match the header and their checksums continue the
+  // cumulative checksum. Anything after the last valid commit frame is ignored.
+  while offset + frame_size <= data.len() {
+    let frame = &data[offset..offset + frame_size];
+    if [be_u32(frame, 8), be_u32(frame, 12)] != start.salt {
+      break;
+    }
+
+    checksum = wal_checksum(start.big_endian, &frame[0..8], checksum);
+    checksum = wal_checksum(start.big_endian, &frame[WAL_FRAME_HEADER_SIZE..], checksum);
+    if checksum != [be_u32(frame, 16), be_u32(frame, 20)] {
+      break;
+    }
+
+    offset += frame_size;
+    if be_u32(frame, 4) != 0 {
+      committed = (offset, checksum);
+    }
+  }
+
+  let (len, checksum) = committed;
+  data.truncate(len);
+
+  return Ok(Some((
+    data,
+    Some(WalCursor {
+      offset: start.offset + len as u64,
+      checksum,
+      ..start
+    }),
+  )));
+}
+
+/// Parses a WAL header, returning a cursor pointing to the first frame. Returns None for invalid
+/// headers.
+fn parse_wal_header(header: &[u8; WAL_HEADER_SIZE]) -> Option<WalCursor> {
+  let big_endian = match be_u32(header, 0) {
+    WAL_MAGIC_LE => false,
+    WAL_MAGIC_BE => true,
+    _ => {
+      return None;
+    }
+  };
+
+  let checksum = wal_checksum(big_endian, &header[0..24], [0, 0]);
+  if checksum != [be_u32(header, 24), be_u32(header, 28)] {
+    return None;
+  }
+
+  return Some(WalCursor {
+    salt: [be_u32(header, 16), be_u32(header, 20)],
+    page_size: be_u32(header, 8),
+    big_endian,
+    offset: WAL_HEADER_SIZE as u64,
+    checksum,
+  });
+}
+
+/// SQLite's cumulative WAL checksum, see https://www.sqlite.org/fileformat.html#checksum_algorithm.
+fn wal_checksum(big_endian: bool, data: &[u8], mut checksum: [u32; 2]) -> [u32; 2] {
+  let word = |bytes: &[u8]| {
+    let bytes: [u8; 4] = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    return if big_endian {
+      u32::from_be_bytes(bytes)
+    } else {
+      u32::from_le_bytes(bytes)
+    };
+  };
+
+  for chunk in data.chunks_exact(8) {
+    checksum[0] = checksum[0]
+      .wrapping_add(word(&chunk[0..4]))
+      .wrapping_add(checksum[1]);
+    checksum[1] = checksum[1]
+      .wrapping_add(word(&chunk[4..8]))
+      .wrapping_add(checksum[0]);
+  }
+  return checksum;
+}
+
+fn be_u32(data: &[u8], offset: usize) -> u32 {
+  return u32::from_be_bytes([
+    data[offset],
+    data[offset + 1],
+    data[offset + 2],
+    data[offset + 3],
+  ]);
+}
+
+fn compress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+  let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+  encoder.write_all(data)?;
+  return encoder.finish();
+}
+
+fn decompress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+  let mut buffer: Vec<u8> = vec![];
+  flate2::read::GzDecoder::new(data).read_to_end(&mut buffer)?;
+  return Ok(buffer);
+}
+
+fn wal_path(db_path: &Path) -> PathBuf {
+  return with_suffix(db_path, "-wal");
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+  let mut path = path.as_os_str().to_owned();
+  path.push(suffix);
+  return path.into();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use object_store::memory::InMemory;
+
+  #[test]
+  fn test_replicate_and_restore() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let temp_dir = temp_dir::TempDir::new().unwrap();
+    let data_dir = DataDir(temp_dir.child("source"));
+    std::fs::create_dir_all(data_dir.data_path()).unwrap();
+
+    let conn = rusqlite::Connection::open(database_path(&data_dir, "main")).unwrap();
+    conn
+      .query_row("PRAGMA journal_mode = WAL", (), |_row| Ok(()))
+      .unwrap();
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE replication_test (id INTEGER PRIMARY KEY) STRICT;
+          INSERT INTO replication_test (id) VALUES (1);
+        "#,
+      )
+      .unwrap();
+
+    let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+    let prefix = ObjectPath::from("replication");
+    let mut replicator = Replicator {
+      name: "main".to_string(),
+      db_path: database_path(&data_dir, "main"),
+      store: store.clone(),
+      prefix: prefix.child("main"),
+      snapshot_interval: Duration::from_secs(3600),
+      retention: 1,
+      checkpoint_threshold: REPLICATION_CHECKPOINT_THRESHOLD,
+      generation: None,
+    };
+
+    let insert = |id: i64| {
+      conn
+        .execute("INSERT INTO replication_test (id) VALUES (?1)", [id])
+        .unwrap();
+    };
+    let sleep = || std::thread::sleep(Duration::from_millis(5));
+
+    // First tick takes the snapshot.
+    replicator.tick(runtime.handle()).unwrap();
+    insert(2);
+    replicator.tick(runtime.handle()).unwrap();
+
+    sleep();
+    let at = Utc::now();
+    sleep();
+
+    insert(3);
+    replicator.tick(runtime.handle()).unwrap();
+    // No new frames.
+    replicator.tick(runtime.handle()).unwrap();
+    assert_eq!(replicator.generation.as_ref().unwrap().next_segment, 2);
+
+    let target = DataDir(temp_dir.child("target"));
+    let count = |path: &Path| -> i64 {
+      let conn = rusqlite::Connection::open(path).unwrap();
+      return conn
+        .query_row("SELECT COUNT(*) FROM replication_test", (), |row| {
+          row.get(0)
+        })
+        .unwrap();
+    };
+
+    let restored = runtime
+      .block_on(restore_databases(
+        &target,
+        &*store,
+        &prefix,
+        &["main".to_string(), "other".to_string()],
+        Some(at),
+        false,
+      ))
+      .unwrap();
+    assert_eq!(restored, vec![database_path(&target, "main")]);
+    assert_eq!(count(&restored[0]), 2);
+
+    let restored = runtime
+      .block_on(restore_databases(
+        &target,
+        &*store,
+        &prefix,
+        &["main".to_string()],
+        None,
+        false,
+      ))
+      .unwrap();
+    assert_eq!(count(&restored[0]), 3);
+
+    // Starting a new generation prunes the old one given a retention of 1.
+    let first = replicator.generation.as_ref().unwrap().id.clone();
+    sleep();
+    replicator.start_generation(runtime.handle()).unwrap();
+    let generations = runtime
+      .block_on(list_generations(&*store, &prefix.child("main")))
+      .unwrap();
+    assert_eq!(generations.len(), 1);
+    assert_ne!(generations[0].0, first);
+
+    // Nothing to restore from before the first generation.
+    let restored = runtime
+      .block_on(restore_databases(
+        &target,
+        &*store,
+        &prefix,
+        &["main".to_string()],
+        Some(at),
+        false,
+      ))
+      .unwrap();
+    assert!(restored.is_empty());
+  }
+
+  #[test]
+  fn test_replicate_across_checkpoints() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let temp_dir = temp_dir::TempDir::new().unwrap();
+    let data_dir = DataDir(temp_dir.child("source"));
+    std::fs::create_dir_all(data_dir.data_path()).unwrap();
+
+    let conn = rusqlite::Connection::open(database_path(&data_dir, "main")).unwrap();
+    conn
+      .query_row("PRAGMA journal_mode = WAL", (), |_row| Ok(()))
+      .unwrap();
+    conn.pragma_update(None, "wal_autocheckpoint", 0).unwrap();
+    conn
+      .execute_batch("CREATE TABLE replication_test (id INTEGER PRIMARY KEY) STRICT")
+      .unwrap();
+
+    let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+    let prefix = ObjectPath::from("replication");
+    let mut replicator = Replicator {
+      name: "main".to_string(),
+      db_path: database_path(&data_dir, "main"),
+      store: store.clone(),
+      prefix: prefix.child("main"),
+      snapshot_interval: Duration::from_secs(3600),
+      retention: 1,
+      checkpoint_threshold: 1,
+      generation: None,
+    };
+
+    let insert = |id: i64| {
+      conn
+        .execute("INSERT INTO replication_test (id) VALUES (?1)", [id])
+        .unwrap();
+    };
+
+    replicator.tick(runtime.handle()).unwrap();
+    let id = replicator.generation.as_ref().unwrap().id.clone();
+
+    for i in 0..10 {
+      insert(i);
+      // Frames get checkpointed after they have been shipped.
+      replicator.tick(runtime.handle()).unwrap();
+      assert!(replicator.generation.as_ref().unwrap().checkpointed);
+    }
+
+    // Restarting the WAL after a checkpoint continues the current generation.
+    let generation = replicator.generation.as_ref().unwrap();
+    assert_eq!(generation.id, id);
+    assert_eq!(generation.next_segment, 10);
+
+    let restored = runtime
+      .block_on(restore_databases(
+        &DataDir(temp_dir.child("target")),
+        &*store,
+        &prefix,
+        &["main".to_string()],
+        None,
+        false,
+      ))
+      .unwrap();
+    let count: i64 = rusqlite::Connection::open(&restored[0])
+      .unwrap()
+      .query_row("SELECT COUNT(*) FROM replication_test", (), |row| {
+        row.get(0)
+      })
+      .unwrap();
+    assert_eq!(count, 10);
+  }
+}
@@ -3,7 +3,7 @@ use const_format::formatcp;
 use cron::Schedule;
 use futures_util::future::BoxFuture;
 use log::*;
-use object_store::ObjectStore;
+use object_store::{ObjectStore, ObjectStoreExt};
 use parking_lot::Mutex;
 use std::collections::{HashMap, hash_map::Entry};
 use std::future::Future;
@@ -15,10 +15,13 @@ use std::sync::{
 use trailbase_sqlite::{Connection, params};
 
 use crate::DataDir;
+use crate::audit::{AuditLog, apply_audit_log_retention};
+use crate::backup::{BackupError, backup_sources, create_backup};
 use crate::config::proto::{Config, SystemJob, SystemJobId};
 use crate::connection::ConnectionManager;
 use crate::constants::{
-  AUTHORIZATION_CODE_TABLE, LOGS_RETENTION_DEFAULT, OTP_CODE_TABLE, SESSION_TABLE,
+  AUDIT_LOG_RETENTION_DEFAULT, AUTHORIZATION_CODE_TABLE, LOGS_RETENTION_DEFAULT, OTP_CODE_TABLE,
+  PENDING_UPLOAD_TABLE, SESSION_TABLE, TUS_UPLOAD_TABLE, WEBAUTHN_CHALLENGE_TABLE,
 };
 use crate::records::files::{FileDeletionsDb, FileError, delete_pending_files_impl};
 
@@ -57,6 +60,10 @@ struct JobState {
 
   handle: Option<tokio::task::AbortHandle>,
   latest: Option<ExecutionResult>,
+
+  // Number of successful and failed runs.
+  successes: u64,
+  failures: u64,
 }
 
 #[derive(Clone)]
@@ -75,6 +82,8 @@ impl Job {
         callback: callback.into(),
         handle: None,
         latest: None,
+        successes: 0,
+        failures: 0,
       })),
     };
   }
@@ -118,7 +127,13 @@ impl Job {
     let end_time = Utc::now();
 
     let result_str = result.as_ref().map_err(|err| err.to_string()).copied();
-    self.state.lock().latest = Some(ExecutionResult {
+    let mut lock = self.state.lock();
+    if result.is_ok() {
+      lock.successes += 1;
+    } else {
+      lock.failures += 1;
+    }
+    lock.latest = Some(ExecutionResult {
       start_time,
       end_time,
       error: result.err(),
@@ -157,6 +172,12 @@ impl Job {
     return None;
   }
 
+  /// Number of successful and failed runs.
+  pub fn run_counts(&self) -> (u64, u64) {
+    let lock = self.state.lock();
+    return (lock.successes, lock.failures);
+  }
+
   pub fn name(&self) -> String {
     return self.state.lock().name.clone();
   }
@@ -244,6 +265,7 @@ fn build_job(
   config: &Config,
   connection_manager: &ConnectionManager,
   logs_conn: &Connection,
+  audit_log: &AuditLog,
   session_conn: &Connection,
   object_store: Arc<dyn ObjectStore>,
 ) -> DefaultSystemJob {
@@ -258,8 +280,12 @@ fn build_job(
       }),
     },
     SystemJobId::Backup => {
-      let backup_file = data_dir.backup_path().join("backup.db");
-      let main_conn = connection_manager.main_entry().connection.clone();
+      let data_dir = data_dir.clone();
+      let connection_manager = connection_manager.clone();
+      let logs_conn = logs_conn.clone();
+      let session_conn = session_conn.clone();
+      let databases = config.databases.clone();
+      let backup_config = config.server.backup_config.clone().unwrap_or_default();
 
       DefaultSystemJob {
         name: "Backup",
@@ -269,11 +295,20 @@ fn build_job(
           disabled: Some(true),
         },
         callback: build_callback(move || {
-          let conn = main_conn.clone();
-          let path = backup_file.clone();
+          let data_dir = data_dir.clone();
+          let backup_config = backup_config.clone();
+          let sources = backup_sources(&connection_manager, &logs_conn, &session_conn, &databases);
+
           return async move {
-            conn.backup(path).await?;
-            return Ok::<(), trailbase_sqlite::Error>(());
+            let id = create_backup(&data_dir, sources, &backup_config)
+              .await
+              .map_err(|err| {
+                warn!("Periodic backup failed: {err}");
+                return err;
+              })?;
+
+            info!("Created backup: {id}");
+            return Ok::<(), BackupError>(());
           };
         }),
       }
@@ -292,10 +327,15 @@ fn build_job(
     },
     SystemJobId::LogCleaner => {
       let logs_conn = logs_conn.clone();
+      let audit_log = audit_log.clone();
       let retention = config
         .server
         .logs_retention_sec
         .map_or(LOGS_RETENTION_DEFAULT, Duration::seconds);
+      let audit_log_retention = config
+        .server
+        .audit_log_retention_sec
+        .map_or(AUDIT_LOG_RETENTION_DEFAULT, Duration::seconds);
 
       DefaultSystemJob {
         name: "Logs Cleanup",
@@ -306,6 +346,7 @@ fn build_job(
         },
         callback: build_callback(move || {
           let logs_conn = logs_conn.clone();
+          let audit_log = audit_log.clone();
 
           return async move {
             let timestamp = (Utc::now() - retention).timestamp();
@@ -317,7 +358,17 @@ fn build_job(
                 err
               })?;
 
-            Ok::<(), trailbase_sqlite::Error>(())
+            if audit_log_retention > Duration::zero() {
+              let timestamp = (Utc::now() - audit_log_retention).timestamp();
+              apply_audit_log_retention(&audit_log, timestamp)
+                .await
+                .map_err(|err| {
+                  warn!("Periodic audit log cleanup failed: {err}");
+                  err
+                })?;
+            }
+
+            Ok::<(), CallbackError>(())
           };
         }),
       }
@@ -340,6 +391,7 @@ fn build_job(
               DELETE FROM '{SESSION_TABLE}' WHERE expires < (UNIXEPOCH() - 60); \
               DELETE FROM '{AUTHORIZATION_CODE_TABLE}' WHERE expires < (UNIXEPOCH() - 60); \
               DELETE FROM '{OTP_CODE_TABLE}' WHERE expires < (UNIXEPOCH() - 60); \
+              DELETE FROM '{WEBAUTHN_CHALLENGE_TABLE}' WHERE expires < (UNIXEPOCH() - 60); \
             "
           );
 
@@ -423,6 +475,35 @@ fn build_job(
         }),
       }
     }
+    SystemJobId::ExpiredUploads => {
+      let session_conn = session_conn.clone();
+      let partial_uploads_path = data_dir.partial_uploads_path();
+
+      DefaultSystemJob {
+        name: "Expired Uploads",
+        default: SystemJob {
+          id: Some(id as i32),
+          schedule: Some("@hourly".into()),
+          disabled: Some(false),
+        },
+        callback: build_callback(move || {
+          let session_conn = session_conn.clone();
+          let object_store = object_store.clone();
+          let partial_uploads_path = partial_uploads_path.clone();
+
+          return async move {
+            delete_expired_uploads_job(&session_conn, &object_store, &partial_uploads_path)
+              .await
+              .map_err(|err| {
+                warn!("Failed to delete expired uploads: {err}");
+                return err;
+              })?;
+
+            return Ok::<(), FileError>(());
+          };
+        }),
+      }
+    }
   };
 }
 
@@ -451,11 +532,70 @@ async fn delete_pending_files_job(
   return Ok(());
 }
 
+/// Deletes presigned and resumable uploads, which haven't been completed in time.
+///
+/// Staged objects of presigned uploads are deleted, whether completed or not, only once their
+/// presigned URL has expired. Until then, their contents may still change.
+pub(crate) async fn delete_expired_uploads_job(
+  session_conn: &Connection,
+  object_store: &Arc<dyn ObjectStore>,
+  partial_uploads_path: &std::path::Path,
+) -> Result<(), FileError> {
+  let file_ids: Vec<String> = session_conn
+    .write_query_rows(
+      format!(
+        "DELETE FROM '{PENDING_UPLOAD_TABLE}' \
+         WHERE url_expires < UNIXEPOCH() AND (consumed OR expires < UNIXEPOCH()) \
+         RETURNING file_id"
+      ),
+      (),
+    )
+    .await?
+    .iter()
+    .map(|row| row.get::<String>(0).map_err(trailbase_sqlite::Error::from))
+    .collect::<Result<_, _>>()?;
+
+  for file_id in file_ids {
+    match object_store
+      .delete(&object_store::path::Path::from(file_id))
+      .await
+    {
+      Ok(_) | Err(object_store::Error::NotFound { .. }) => {}
+      Err(err) => {
+        warn!("Failed to delete expired upload: {err}");
+      }
+    }
+  }
+
+  let upload_ids: Vec<String> = session_conn
+    .write_query_rows(
+      format!("DELETE FROM '{TUS_UPLOAD_TABLE}' WHERE expires < UNIXEPOCH() RETURNING upload_id"),
+      (),
+    )
+    .await?
+    .iter()
+    .map(|row| row.get::<String>(0).map_err(trailbase_sqlite::Error::from))
+    .collect::<Result<_, _>>()?;
+
+  for upload_id in upload_ids {
+    match tokio::fs::remove_file(partial_uploads_path.join(upload_id)).await {
+      Ok(_) => {}
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+      Err(err) => {
+        warn!("Failed to delete expired partial upload: {err}");
+      }
+    }
+  }
+
+  return Ok(());
+}
+
 pub fn build_job_registry_from_config(
   config: &Config,
   data_dir: &DataDir,
   connection_manager: &ConnectionManager,
   logs_conn: &Connection,
+  audit_log: &AuditLog,
   session_conn: &Connection,
   object_store: Arc<dyn ObjectStore>,
 ) -> Result<JobRegistry, CallbackError> {
@@ -466,6 +606,7 @@ pub fn build_job_registry_from_config(
     SystemJobId::AuthCleaner,
     SystemJobId::QueryOptimizer,
     SystemJobId::FileDeletions,
+    SystemJobId::ExpiredUploads,
   ];
 
   let jobs = JobRegistry::new();
@@ -480,6 +621,7 @@ pub fn build_job_registry_from_config(
       config,
       connection_manager,
       logs_conn,
+      audit_log,
       session_conn,
       object_store.clone(),
     );
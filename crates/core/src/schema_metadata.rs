@@ -453,6 +453,7 @@ mod tests {
         Path(("test_table_api".to_string(), "1".to_string())),
         Query(ReadRecordQuery {
           expand: Some("UNKNOWN".to_string()),
+          ..Default::default()
         }),
         None,
       )
@@ -483,7 +484,7 @@ mod tests {
       let Json(value) = read_record_handler(
         State(state.clone()),
         Path(("test_table_api".to_string(), "1".to_string())),
-        Query(ReadRecordQuery { expand: None }),
+        Query(ReadRecordQuery::default()),
         None,
       )
       .await
@@ -528,6 +529,7 @@ mod tests {
         Path(("test_table_api".to_string(), "1".to_string())),
         Query(ReadRecordQuery {
           expand: Some("fk".to_string()),
+          ..Default::default()
         }),
         None,
       )
@@ -630,7 +632,7 @@ mod tests {
       let Json(value) = read_record_handler(
         State(state.clone()),
         Path(("test_table_api".to_string(), "1".to_string())),
-        Query(ReadRecordQuery { expand: None }),
+        Query(ReadRecordQuery::default()),
         None,
       )
       .await
@@ -681,6 +683,7 @@ mod tests {
         Path(("test_table_api".to_string(), "1".to_string())),
         Query(ReadRecordQuery {
           expand: Some("fk1".to_string()),
+          ..Default::default()
         }),
         None,
       )
@@ -730,6 +733,7 @@ mod tests {
         Path(("test_table_api".to_string(), "1".to_string())),
         Query(ReadRecordQuery {
           expand: Some("fk0,fk1".to_string()),
+          ..Default::default()
         }),
         None,
       )
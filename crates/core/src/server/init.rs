@@ -4,7 +4,10 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use thiserror::Error;
 
-use crate::app_state::{AppState, AppStateArgs, build_objectstore, update_json_schema_registry};
+use crate::app_state::{
+  AppState, AppStateArgs, build_objectstore, build_objectstore_signer, update_json_schema_registry,
+};
+use crate::audit::{AuditError, AuditLog};
 use crate::auth::jwt::{JwtHelper, JwtHelperError};
 use crate::config::load_or_init_config_textproto;
 use crate::connection::ConnectionManager;
@@ -25,6 +28,8 @@ pub enum InitError {
   Config(#[from] crate::config::ConfigError),
   #[error("JwtHelper error: {0}")]
   JwtHelper(#[from] JwtHelperError),
+  #[error("Audit error: {0}")]
+  Audit(#[from] AuditError),
   #[error("CreateAdmin error: {0}")]
   CreateAdmin(String),
   #[error("Custom initializer error: {0}")]
@@ -67,22 +72,31 @@ pub async fn init_app_state(args: InitArgs) -> Result<(bool, AppState), InitErro
     trailbase_schema::registry::build_json_schema_registry(vec![])?,
   ));
 
+  let mut replicated = false;
   if let Some(config) = crate::config::maybe_load_config_textproto_unverified(&args.data_dir)? {
     update_json_schema_registry(&config.schemas, &json_schema_registry)?;
+
+    replicated = config
+      .server
+      .replication_config
+      .as_ref()
+      .is_some_and(|c| c.enabled.unwrap_or(false));
   }
 
-  let sync_wasm_runtimes = crate::wasm::build_sync_wasm_runtimes_for_components(
-    args.data_dir.root().join("wasm"),
-    args.runtime_root_fs.as_deref(),
-    args.dev,
-  )
-  .await
-  .map_err(|err| InitError::ScriptError(err.to_string()))?;
+  let (sync_wasm_runtimes, record_hooks, custom_claims_hooks) =
+    crate::wasm::build_sync_wasm_runtimes_for_components(
+      args.data_dir.root().join("wasm"),
+      args.runtime_root_fs.as_deref(),
+      args.dev,
+    )
+    .await
+    .map_err(|err| InitError::ScriptError(err.to_string()))?;
 
   let (connection_manager, new_db) = ConnectionManager::new(
     args.data_dir.clone(),
     json_schema_registry.clone(),
     sync_wasm_runtimes,
+    replicated,
   )?;
 
   // Read config or write default one. Ensures config is validated.
@@ -92,6 +106,7 @@ pub async fn init_app_state(args: InitArgs) -> Result<(bool, AppState), InitErro
   let _metadata = load_or_init_metadata_textproto(&args.data_dir).await?;
 
   let jwt = JwtHelper::init_from_path(&args.data_dir).await?;
+  let audit_log = AuditLog::init_from_path(&args.data_dir, logs_conn.clone()).await?;
 
   // Init geoip if present.
   let geoip_db_path = args
@@ -102,6 +117,7 @@ pub async fn init_app_state(args: InitArgs) -> Result<(bool, AppState), InitErro
   }
 
   let object_store = build_objectstore(&args.data_dir, config.server.s3_storage_config.as_ref())?;
+  let object_store_signer = build_objectstore_signer(config.server.s3_storage_config.as_ref())?;
 
   let app_state = AppState::new(AppStateArgs {
     data_dir: args.data_dir.clone(),
@@ -114,9 +130,13 @@ pub async fn init_app_state(args: InitArgs) -> Result<(bool, AppState), InitErro
     json_schema_registry,
     session_conn,
     logs_conn,
+    audit_log,
     connection_manager,
     jwt,
     object_store,
+    object_store_signer,
+    record_hooks,
+    custom_claims_hooks,
     wasm_tokio_runtime: args.wasm_tokio_runtime,
   });
 
@@ -4,7 +4,7 @@ mod serve;
 use axum::body::Body;
 use axum::extract::{DefaultBodyLimit, Request, State};
 use axum::handler::HandlerWithoutStateExt;
-use axum::http::{HeaderValue, StatusCode};
+use axum::http::{HeaderName, HeaderValue, StatusCode, header};
 use axum::middleware::{self, Next};
 use axum::response::{IntoResponse, Response};
 use axum::routing::get;
@@ -39,6 +39,7 @@ use crate::constants::{ADMIN_API_PATH, HEADER_CSRF_TOKEN};
 use crate::data_dir::DataDir;
 use crate::extract::ip::RealIpKeyExtractor;
 use crate::logging;
+use crate::metrics;
 use crate::records;
 
 pub use init::{InitArgs, InitError, init_app_state};
@@ -86,6 +87,9 @@ pub struct ServerOptions {
   /// Limit the set of allowed origins the HTTP server will answer to.
   pub cors_allowed_origins: Vec<String>,
 
+  /// Expose Prometheus metrics at `/metrics`. Served on the admin address, if separate.
+  pub enable_metrics: bool,
+
   /// Optional dedicated Tokio runtime to execute async WASM code on.
   pub wasm_tokio_runtime: Option<tokio::runtime::Handle>,
 
@@ -424,6 +428,13 @@ impl Server {
       );
   }
 
+  fn build_metrics_router(opts: &ServerOptions) -> Router<AppState> {
+    if !opts.enable_metrics {
+      return Router::new();
+    }
+    return Router::new().route("/metrics", get(metrics::metrics_handler));
+  }
+
   fn build_independent_admin_router(
     state: &AppState,
     opts: &ServerOptions,
@@ -440,7 +451,8 @@ impl Server {
           inst(auth::admin_auth_router())
         }),
       )
-      .merge(Self::build_admin_router(state));
+      .merge(Self::build_admin_router(state))
+      .merge(Self::build_metrics_router(opts));
 
     return Some((
       address.clone(),
@@ -464,10 +476,13 @@ impl Server {
         || auth::router(&state.get_config()),
         |inst| inst(auth::router(&state.get_config())),
       ))
+      .merge(auth::well_known::well_known_router())
       .route("/api/healthcheck", get(healthcheck_handler));
 
     if !has_indepenedent_admin_router(opts) {
-      router = router.merge(Self::build_admin_router(state));
+      router = router
+        .merge(Self::build_admin_router(state))
+        .merge(Self::build_metrics_router(opts));
     }
 
     for custom_router in custom_routers {
@@ -535,6 +550,15 @@ impl Server {
       .layer(axum_tracing_opentelemetry::middleware::OtelInResponseLayer)
       .layer(axum_tracing_opentelemetry::middleware::OtelAxumLayer::default());
 
+    let router = if opts.enable_metrics {
+      router.layer(middleware::from_fn_with_state(
+        state.clone(),
+        metrics::track_request_metrics,
+      ))
+    } else {
+      router
+    };
+
     return router
       .layer(CookieManagerLayer::new())
       .layer(build_cors(opts))
@@ -582,6 +606,11 @@ async fn assert_admin_api_access(
 ) -> Result<Response, AuthError> {
   let user = req.extract_parts_with_state::<User, _>(&state).await?;
 
+  // API keys never grant admin access.
+  if user.api_key.is_some() {
+    return Err(AuthError::Forbidden);
+  }
+
   if !is_admin(&state, &user.uuid).await {
     return Err(AuthError::Forbidden);
   }
@@ -631,6 +660,17 @@ fn build_cors(opts: &ServerOptions) -> cors::CorsLayer {
   return cors::CorsLayer::new()
     .allow_methods(cors::Any)
     .allow_headers(cors::Any)
+    // Resumable upload clients need to read the upload's location and progress.
+    .expose_headers([
+      header::LOCATION,
+      HeaderName::from_static("tus-resumable"),
+      HeaderName::from_static("tus-version"),
+      HeaderName::from_static("tus-extension"),
+      HeaderName::from_static("tus-max-size"),
+      HeaderName::from_static("upload-offset"),
+      HeaderName::from_static("upload-length"),
+      HeaderName::from_static("upload-expires"),
+    ])
     .allow_origin(origins);
 }
 
@@ -12,10 +12,17 @@ use trailbase_wasm_common::{HttpContext, HttpContextKind, HttpContextUser};
 use trailbase_wasm_runtime_host::{InitArgs, RuntimeOptions, find_wasm_components};
 
 use crate::User;
+use crate::auth::claims::{CustomClaimsHook, CustomClaimsHooks, JsonObject};
+use crate::auth::jwt::AuthTokenClaims;
+use crate::metrics::{WasmInvocation, record_wasm_invocation};
+use crate::records::hooks::{
+  RecordHookArgs, RecordHookDispatcher, RecordHookEvent, RecordHookOutcome, RecordHooks,
+};
 use crate::util::urlencode;
 use crate::{AppState, DataDir};
 
 pub(crate) use trailbase_wasm_runtime_host::functions::{SqliteFunctions, SqliteStore};
+pub(crate) use trailbase_wasm_runtime_host::hooks::{AuthHookStore, RecordHookStore};
 pub(crate) use trailbase_wasm_runtime_host::{HttpStore, KvStore, Runtime, SharedState};
 
 pub(crate) type AnyError = Box<dyn std::error::Error + Send + Sync>;
@@ -24,7 +31,14 @@ pub(crate) async fn build_sync_wasm_runtimes_for_components(
   components_path: PathBuf,
   fs_root_path: Option<&Path>,
   use_winch: bool,
-) -> Result<Vec<(SqliteStore, SqliteFunctions)>, AnyError> {
+) -> Result<
+  (
+    Vec<(SqliteStore, SqliteFunctions)>,
+    RecordHooks,
+    CustomClaimsHooks,
+  ),
+  AnyError,
+> {
   let components = find_wasm_components(&components_path);
   let shared_state = Arc::new(SharedState {
     conn: None,
@@ -33,6 +47,8 @@ pub(crate) async fn build_sync_wasm_runtimes_for_components(
   });
 
   let mut sync_runtimes: Vec<(SqliteStore, SqliteFunctions)> = vec![];
+  let mut record_hooks: Vec<(String, Vec<RecordHookEvent>, Arc<dyn RecordHookDispatcher>)> = vec![];
+  let mut custom_claims_hooks: Vec<Arc<dyn CustomClaimsHook>> = vec![];
 
   for path in components {
     let rt = Runtime::init(
@@ -57,12 +73,125 @@ pub(crate) async fn build_sync_wasm_runtimes_for_components(
       .initialize_sqlite_functions(trailbase_wasm_runtime_host::InitArgs { version: None })
       .await?;
 
-    if !functions.scalar_functions.is_empty() {
+    if !functions.is_empty() {
       sync_runtimes.push((store, functions));
     }
+
+    // Record hooks are dispatched from the same "raw" SQLite writer thread and thus also need a
+    // synchronous store. Note that hooks, like custom SQLite functions, have no DB access.
+    let store = RecordHookStore::new(&rt).await?;
+    let hooks = store
+      .initialize_record_hooks(trailbase_wasm_runtime_host::InitArgs { version: None })
+      .await?;
+
+    if !hooks.is_empty() {
+      let dispatcher: Arc<dyn RecordHookDispatcher> = Arc::new(WasmRecordHookDispatcher(store));
+      for hook in hooks {
+        record_hooks.push((
+          hook.api_name,
+          hook.events.into_iter().map(from_wasm_event).collect(),
+          dispatcher.clone(),
+        ));
+      }
+    }
+
+    // Unlike record hooks, auth hooks are called asynchronously, e.g. when minting auth tokens.
+    let store = AuthHookStore::new(&rt).await?;
+    let auth_hooks = store
+      .initialize_auth_hooks(trailbase_wasm_runtime_host::InitArgs { version: None })
+      .await?;
+
+    if auth_hooks.custom_claims {
+      custom_claims_hooks.push(Arc::new(WasmCustomClaimsHook(store)));
+    }
   }
 
-  return Ok(sync_runtimes);
+  return Ok((
+    sync_runtimes,
+    RecordHooks::new(record_hooks),
+    CustomClaimsHooks::new(custom_claims_hooks),
+  ));
+}
+
+struct WasmCustomClaimsHook(AuthHookStore);
+
+#[async_trait::async_trait]
+impl CustomClaimsHook for WasmCustomClaimsHook {
+  async fn custom_claims(&self, claims: &AuthTokenClaims) -> Result<JsonObject, AnyError> {
+    use trailbase_wasm_runtime_host::hooks::AuthHookUser;
+
+    let result = self
+      .0
+      .custom_claims(AuthHookUser {
+        id: claims.sub.clone(),
+        email: claims.email.clone(),
+        admin: claims.admin,
+        roles: claims.roles.clone(),
+      })
+      .await;
+    record_wasm_invocation(WasmInvocation::CustomClaims, result.is_ok());
+    let json = result?;
+
+    return Ok(serde_json::from_str(&json)?);
+  }
+}
+
+struct WasmRecordHookDispatcher(RecordHookStore);
+
+impl RecordHookDispatcher for WasmRecordHookDispatcher {
+  fn dispatch(&self, args: RecordHookArgs<'_>) -> Result<RecordHookOutcome, AnyError> {
+    use trailbase_wasm_runtime_host::hooks::{
+      RecordHookArguments, RecordHookOutcome as WasmOutcome, RecordHookUser,
+    };
+
+    let result = self.0.dispatch_record_hook_sync(RecordHookArguments {
+      api_name: args.api_name.to_string(),
+      event: to_wasm_event(args.event),
+      record_id: args.record_id.map(|id| id.to_string()),
+      record: args.record.map(serde_json::to_string).transpose()?,
+      user: args.user.map(|user| RecordHookUser {
+        id: user.id.clone(),
+        email: user.email.clone(),
+      }),
+    });
+    record_wasm_invocation(WasmInvocation::RecordHook, result.is_ok());
+    let outcome = result?;
+
+    return Ok(match outcome {
+      WasmOutcome::Proceed => RecordHookOutcome::Proceed,
+      WasmOutcome::Modify(json) => RecordHookOutcome::Modify(serde_json::from_str(&json)?),
+      WasmOutcome::Abort(abort) => RecordHookOutcome::Abort {
+        status: StatusCode::from_u16(abort.status)?,
+        message: abort.message,
+      },
+    });
+  }
+}
+
+fn from_wasm_event(event: trailbase_wasm_runtime_host::hooks::RecordHookEvent) -> RecordHookEvent {
+  use trailbase_wasm_runtime_host::hooks::RecordHookEvent as E;
+
+  return match event {
+    E::BeforeCreate => RecordHookEvent::BeforeCreate,
+    E::AfterCreate => RecordHookEvent::AfterCreate,
+    E::BeforeUpdate => RecordHookEvent::BeforeUpdate,
+    E::AfterUpdate => RecordHookEvent::AfterUpdate,
+    E::BeforeDelete => RecordHookEvent::BeforeDelete,
+    E::AfterDelete => RecordHookEvent::AfterDelete,
+  };
+}
+
+fn to_wasm_event(event: RecordHookEvent) -> trailbase_wasm_runtime_host::hooks::RecordHookEvent {
+  use trailbase_wasm_runtime_host::hooks::RecordHookEvent as E;
+
+  return match event {
+    RecordHookEvent::BeforeCreate => E::BeforeCreate,
+    RecordHookEvent::AfterCreate => E::AfterCreate,
+    RecordHookEvent::BeforeUpdate => E::BeforeUpdate,
+    RecordHookEvent::AfterUpdate => E::AfterUpdate,
+    RecordHookEvent::BeforeDelete => E::BeforeDelete,
+    RecordHookEvent::AfterDelete => E::AfterDelete,
+  };
 }
 
 pub(crate) type WasmRuntimeBuilder =
@@ -162,7 +291,9 @@ pub(crate) async fn install_routes_and_jobs(
             .body(empty())
             .map_err(|err| WasmError::Other(err.to_string()))?;
 
-          store.call_incoming_http_handler(request).await?;
+          let result = store.call_incoming_http_handler(request).await;
+          record_wasm_invocation(WasmInvocation::Job, result.is_ok());
+          result?;
 
           Ok::<_, AnyError>(())
         };
@@ -230,7 +361,9 @@ pub(crate) async fn install_routes_and_jobs(
         );
 
         // Call WASM.
-        return match store.call_incoming_http_handler(request).await {
+        let result = store.call_incoming_http_handler(request).await;
+        record_wasm_invocation(WasmInvocation::Http, result.is_ok());
+        return match result {
           Ok(response) => {
             // Construct hyper/axum response from WASI response.
             let (parts, body) = response.into_parts();
@@ -0,0 +1,21 @@
+SELECT
+{% for expr in select_exprs -%}
+  {%- if !loop.first %},{% endif %}{{ expr }}
+{%- endfor %}
+FROM
+  (SELECT :__user_id AS id, :__user_roles AS roles, :__user_claims AS claims) AS _USER_,
+  {{ table_name }} AS _ROW_
+WHERE
+  ({{ read_access_clause }}) AND ({{ filter_clause }})
+{%- if let Some(group_by_clause) = group_by_clause %}
+GROUP BY
+  {{ group_by_clause }}
+{%- endif %}
+{%- if let Some(order_clause) = order_clause %}
+ORDER BY
+  {{ order_clause }}
+{%- endif %}
+LIMIT :__limit
+{%- if offset %}
+OFFSET :__offset
+{%- endif -%}
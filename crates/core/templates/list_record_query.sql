@@ -3,8 +3,11 @@ WITH
   total_count AS (
     SELECT COUNT(*) AS _value_
     FROM
-      (SELECT :__user_id AS id) AS _USER_,
+      (SELECT :__user_id AS id, :__user_roles AS roles, :__user_claims AS claims) AS _USER_,
       {{ table_name }} as _ROW_
+{%- if let Some(fts_join_clause) = fts_join_clause %}
+      {{ fts_join_clause }}
+{%- endif %}
     WHERE
       ({{ read_access_clause }}) AND ({{ filter_clause }})
   )
@@ -17,14 +20,20 @@ SELECT
 {%- for expanded in expanded_tables -%}
   , F{{ loop.index0 }}.*
 {%- endfor %}
+{%- for expr in fts_select_exprs -%}
+  , {{ expr }}
+{%- endfor %}
 {%- if count -%}, total_count._value_ AS _total_count_{%- endif %}
 {%- if is_table -%}, _ROW_._rowid_ AS _rowid_{%- endif %}
 FROM
-  (SELECT :__user_id AS id) AS _USER_,
+  (SELECT :__user_id AS id, :__user_roles AS roles, :__user_claims AS claims) AS _USER_,
 {%- if count %}
   total_count,
 {%- endif %}
   {{ table_name }} AS _ROW_
+{%- if let Some(fts_join_clause) = fts_join_clause %}
+    {{ fts_join_clause }}
+{%- endif %}
 {%- for expanded in expanded_tables %}
     LEFT JOIN "{{ expanded.foreign_table_name }}" AS F{{ loop.index0 }} ON _ROW_."{{ expanded.local_column_name }}" = F{{ loop.index0 }}."{{ expanded.foreign_column_name }}"
 {%- endfor %}
@@ -1,7 +1,7 @@
 SELECT
   CAST(({{ read_access_rule }}) AS INTEGER)
 FROM
-  (SELECT :__user_id AS id) AS _USER_
+  (SELECT :__user_id AS id, :__user_roles AS roles, :__user_claims AS claims) AS _USER_
   {% if !column_names.is_empty() -%}
   , (SELECT
     {%- for name in column_names -%}
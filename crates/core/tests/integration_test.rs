@@ -1,4 +1,3 @@
-use axum::extract::{Json, State};
 use axum::http::StatusCode;
 use axum_test::TestServer;
 use axum_test::multipart::MultipartForm;
@@ -8,7 +7,7 @@ use tower_cookies::Cookie;
 use trailbase_sqlite::params;
 
 use trailbase::AppState;
-use trailbase::api::{CreateUserRequest, create_user_handler, login_with_password_for_test};
+use trailbase::api::{CreateUserRequest, create_user, login_with_password_for_test};
 use trailbase::config::proto::{PermissionFlag, RecordApiConfig};
 use trailbase::constants::{COOKIE_AUTH_TOKEN, RECORD_API_PATH};
 use trailbase::util::id_to_b64;
@@ -343,14 +342,14 @@ async fn create_user_for_test(
   password: &str,
 ) -> Result<uuid::Uuid, anyhow::Error> {
   return Ok(
-    create_user_handler(
-      State(state.clone()),
-      Json(CreateUserRequest {
+    create_user(
+      state,
+      CreateUserRequest {
         email: email.to_string(),
         password: password.to_string(),
         verified: true,
         admin: false,
-      }),
+      },
     )
     .await?
     .id,
@@ -16,6 +16,14 @@ pub enum CompareOp {
   Like,
   Regexp,
 
+  // Full-text search, i.e. FTS5 MATCH:
+  Match,
+
+  // List-valued membership and range:
+  In,
+  NotIn,
+  Between,
+
   // Spatial Types:
   StWithin,
   StIntersects,
@@ -34,6 +42,10 @@ impl CompareOp {
       "$is" => Some(Self::Is),
       "$like" => Some(Self::Like),
       "$re" => Some(Self::Regexp),
+      "$match" => Some(Self::Match),
+      "$in" => Some(Self::In),
+      "$nin" => Some(Self::NotIn),
+      "$between" => Some(Self::Between),
       // Spatial Types:
       "@within" => Some(Self::StWithin),
       "@intersects" => Some(Self::StIntersects),
@@ -42,6 +54,10 @@ impl CompareOp {
     };
   }
 
+  /// Renders the SQL expression for the given column and parameter.
+  ///
+  /// NOTE: For list-valued operators, `param` is expected to be the comma-separated list of
+  /// parameters for `In` and `NotIn`, and `<lower> AND <upper>` for `Between`.
   #[inline]
   pub fn as_sql(&self, column: &str, param: &str) -> String {
     return match self {
@@ -54,6 +70,10 @@ impl CompareOp {
       Self::Like => format!("{column} LIKE {param}"),
       Self::Regexp => format!("{column} REGEXP {param}"),
       Self::Equal => format!("{column} = {param}"),
+      Self::Match => format!("{column} MATCH {param}"),
+      Self::In => format!("{column} IN ({param})"),
+      Self::NotIn => format!("{column} NOT IN ({param})"),
+      Self::Between => format!("{column} BETWEEN {param}"),
       // Spatial Types:
       Self::StWithin => format!("ST_Within({column}, {param})"),
       Self::StIntersects => format!("ST_Intersects({column}, {param})"),
@@ -73,6 +93,10 @@ impl CompareOp {
       Self::Is => "$is",
       Self::Like => "$like",
       Self::Regexp => "$re",
+      Self::Match => "$match",
+      Self::In => "$in",
+      Self::NotIn => "$nin",
+      Self::Between => "$between",
       // Spatial Types:
       Self::StWithin => "@within",
       Self::StIntersects => "@intersects",
@@ -103,6 +127,30 @@ where
       }
       _ => Err(Error::invalid_type(unexpected(&value), &"NULL or !NULL")),
     },
+    CompareOp::Match => match value {
+      // NOTE: Don't try to parse numbers, FTS5 queries are always strings.
+      serde_value::Value::String(v) => Ok(Value::String(v)),
+      _ => Err(Error::invalid_type(
+        unexpected(&value),
+        &"FTS5 query string",
+      )),
+    },
+    CompareOp::In | CompareOp::NotIn | CompareOp::Between => {
+      let values = parse_list::<D>(value)?;
+      if values.is_empty() || values.len() > MAX_LIST_LEN {
+        return Err(Error::invalid_length(
+          values.len(),
+          &"between one and 256 values",
+        ));
+      }
+      if op == CompareOp::Between && values.len() != 2 {
+        return Err(Error::invalid_length(
+          values.len(),
+          &"lower and upper bound",
+        ));
+      }
+      Ok(Value::List(values))
+    }
     CompareOp::StWithin | CompareOp::StIntersects | CompareOp::StContains => {
       // WARN: The assumption here is that valid WKTs cannot be used for SQL injection.
       match value {
@@ -130,6 +178,47 @@ where
   };
 }
 
+/// Parses list values, which can be provided either comma-separated, i.e. `[$in]=a,b`, or
+/// indexed, i.e. `[$in][0]=a&[$in][1]=b`. The latter allows for values containing commas.
+fn parse_list<'de, D>(value: serde_value::Value) -> Result<Vec<Value>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  use crate::util::unexpected;
+
+  return match value {
+    serde_value::Value::String(s) => Ok(
+      s.split(',')
+        .map(|v| Value::unparse(v.to_string()))
+        .collect(),
+    ),
+    serde_value::Value::Seq(values) => values
+      .into_iter()
+      .map(|v| parse_value::<D>(CompareOp::Equal, v))
+      .collect(),
+    serde_value::Value::Map(m) => {
+      let mut indexed = m
+        .into_iter()
+        .map(|(k, v)| {
+          let index = match k {
+            serde_value::Value::String(ref s) => s.parse::<usize>().ok(),
+            _ => None,
+          }
+          .ok_or_else(|| Error::invalid_type(unexpected(&k), &"list index"))?;
+          return Ok((index, v));
+        })
+        .collect::<Result<Vec<_>, D::Error>>()?;
+      indexed.sort_by_key(|(index, _v)| *index);
+
+      indexed
+        .into_iter()
+        .map(|(_index, v)| parse_value::<D>(CompareOp::Equal, v))
+        .collect()
+    }
+    v => Err(Error::invalid_type(unexpected(&v), &"list of values")),
+  };
+}
+
 #[inline]
 fn validate_wkt(s: &str) -> bool {
   if s.chars().all(|c| c != ';' && c != '\'') {
@@ -187,3 +276,6 @@ where
 }
 
 const OP_ERR: &str = "one of [$eq, $ne, $lt, ...]";
+
+/// Max number of values for list-valued operators such as `$in`.
+const MAX_LIST_LEN: usize = 256;
@@ -69,10 +69,7 @@ impl ValueOrComposite {
     ) -> Result<(String, Vec<(String, V)>), E> {
       match v {
         ValueOrComposite::Value(v) => {
-          return Ok(match render_sql_fragment(v, column_prefix, map, index)? {
-            (sql, Some(param)) => (sql, vec![param]),
-            (sql, None) => (sql, vec![]),
-          });
+          return render_sql_fragment(v, column_prefix, map, index);
         }
         ValueOrComposite::Composite(combiner, vec) => {
           let mut params: Vec<(String, V)> = vec![];
@@ -103,15 +100,29 @@ impl ValueOrComposite {
   pub fn to_query(&self) -> String {
     /// Return a (key, value) pair suitable for query-string serialization (not percent-encoded).
     fn render_param(prefix: &str, v: &ColumnOpValue) -> String {
+      let column = &v.column;
       let value: std::borrow::Cow<str> = match (&v.op, &v.value) {
         (CompareOp::Is, Value::String(s)) if s == "NOT NULL" => "!NULL".into(),
         (CompareOp::Is, Value::String(s)) if s == "NULL" => "NULL".into(),
         (_, Value::String(s)) => s.into(),
         (_, Value::Integer(i)) => i.to_string().into(),
         (_, Value::Double(d)) => d.to_string().into(),
+        (op, Value::List(values)) => {
+          // Fall back to indexed lists if values contain commas.
+          if values
+            .iter()
+            .any(|v| matches!(v, Value::String(s) if s.contains(',')))
+          {
+            return values
+              .iter()
+              .enumerate()
+              .map(|(i, value)| format!("{prefix}[{column}][{}][{i}]={value}", op.as_query()))
+              .join("&");
+          }
+          v.value.to_string().into()
+        }
       };
 
-      let column = &v.column;
       return if matches!(v.op, CompareOp::Equal) {
         format!("{prefix}[{column}]={value}")
       } else {
@@ -261,18 +272,49 @@ fn render_sql_fragment<V, E>(
   column_prefix: Option<&str>,
   map: &dyn Fn(ColumnOpValue) -> Result<V, E>,
   index: &mut usize,
-) -> Result<(String, Option<(String, V)>), E> {
+) -> Result<(String, Vec<(String, V)>), E> {
   let c = &column_op_value.column;
   let column_name = match column_prefix {
     Some(p) => format!(r#"{p}."{c}""#),
     None => format!(r#""{c}""#),
   };
 
+  if let ColumnOpValue {
+    column,
+    op: op @ (CompareOp::In | CompareOp::NotIn | CompareOp::Between),
+    value: Value::List(values),
+  } = column_op_value
+  {
+    // Bind every list element separately, mapping them individually as if they were scalar
+    // values on the same column.
+    let params = values
+      .into_iter()
+      .map(|value| {
+        let param = param_name(*index);
+        *index += 1;
+
+        let value = map(ColumnOpValue {
+          column: column.clone(),
+          op,
+          value,
+        })?;
+        return Ok((param, value));
+      })
+      .collect::<Result<Vec<_>, E>>()?;
+
+    let joined = params.iter().map(|(name, _)| name.as_str()).join(match op {
+      CompareOp::Between => " AND ",
+      _ => ", ",
+    });
+
+    return Ok((op.as_sql(&column_name, &joined), params));
+  }
+
   return match (column_op_value.op, &column_op_value.value) {
     (CompareOp::Is, Value::String(s)) if s == "NULL" || s == "NOT NULL" => {
       // We need to inline NULL/NOT NULL, since `IS [NOT ]NULL` is an operator and not a `TEXT`
       // literal.
-      Ok((column_op_value.op.as_sql(&column_name, s), None))
+      Ok((column_op_value.op.as_sql(&column_name, s), vec![]))
     }
     (CompareOp::StWithin | CompareOp::StIntersects | CompareOp::StContains, Value::String(s)) => {
       // QUESTION: should we pass the string as a parameter instead? Right now we can't because
@@ -283,7 +325,7 @@ fn render_sql_fragment<V, E>(
         column_op_value
           .op
           .as_sql(&column_name, &format!("ST_GeomFromText('{s}')")),
-        None,
+        vec![],
       ))
     }
     (op, _) => {
@@ -292,7 +334,7 @@ fn render_sql_fragment<V, E>(
 
       Ok((
         op.as_sql(&column_name, &param),
-        Some((param, map(column_op_value)?)),
+        vec![(param, map(column_op_value)?)],
       ))
     }
   };
@@ -397,6 +439,55 @@ mod tests {
 
     assert_eq!(f2_explicit, f2);
     assert_eq!(q2_explicit, f2.to_query());
+
+    let q4 = "filter[col0][$in]=1,2,three";
+    let f4 = qs.deserialize_str::<Query>(q4).unwrap().filter.unwrap();
+    assert_eq!(
+      f4,
+      ValueOrComposite::Value(ColumnOpValue {
+        column: "col0".to_string(),
+        op: CompareOp::In,
+        value: Value::List(vec![
+          Value::Integer(1),
+          Value::Integer(2),
+          Value::String("three".to_string())
+        ]),
+      })
+    );
+    assert_eq!(q4, f4.to_query());
+
+    // Indexed lists allow for values containing commas.
+    let q5 = "filter[col0][$nin][0]=a,b&filter[col0][$nin][1]=c";
+    let f5 = qs.deserialize_str::<Query>(q5).unwrap().filter.unwrap();
+    assert_eq!(
+      f5,
+      ValueOrComposite::Value(ColumnOpValue {
+        column: "col0".to_string(),
+        op: CompareOp::NotIn,
+        value: Value::List(vec![
+          Value::String("a,b".to_string()),
+          Value::String("c".to_string())
+        ]),
+      })
+    );
+    assert_eq!(q5, f5.to_query());
+
+    let q6 = "filter[col0][$between]=1,10";
+    let f6 = qs.deserialize_str::<Query>(q6).unwrap().filter.unwrap();
+    assert_eq!(
+      f6,
+      ValueOrComposite::Value(ColumnOpValue {
+        column: "col0".to_string(),
+        op: CompareOp::Between,
+        value: Value::List(vec![Value::Integer(1), Value::Integer(10)]),
+      })
+    );
+    assert_eq!(q6, f6.to_query());
+
+    assert!(
+      qs.deserialize_str::<Query>("filter[col0][$between]=1,2,3")
+        .is_err()
+    );
   }
 
   #[test]
@@ -412,6 +503,9 @@ mod tests {
         Value::String(s) => SqlValue::Text(s),
         Value::Integer(i) => SqlValue::Integer(i),
         Value::Double(d) => SqlValue::Real(d),
+        Value::List(_) => {
+          return Err("unexpected list".to_string());
+        }
       });
     }
 
@@ -427,5 +521,41 @@ mod tests {
     });
     let sql1 = v1.into_sql(None, map).unwrap();
     assert_eq!(sql1.0, r#""col0" IS NULL"#, "{sql1:?}",);
+
+    let v2 = ValueOrComposite::Composite(
+      Combiner::And,
+      vec![
+        ValueOrComposite::Value(ColumnOpValue {
+          column: "col0".to_string(),
+          op: CompareOp::In,
+          value: Value::List(vec![Value::Integer(1), Value::Integer(2)]),
+        }),
+        ValueOrComposite::Value(ColumnOpValue {
+          column: "col1".to_string(),
+          op: CompareOp::Between,
+          value: Value::List(vec![Value::Integer(3), Value::Double(4.5)]),
+        }),
+        ValueOrComposite::Value(ColumnOpValue {
+          column: "col2".to_string(),
+          op: CompareOp::NotIn,
+          value: Value::List(vec![Value::String("a".to_string())]),
+        }),
+      ],
+    );
+    let (sql2, params2) = v2.into_sql(Some("p"), map).unwrap();
+    assert_eq!(
+      sql2,
+      r#"(p."col0" IN (:__p0, :__p1) AND p."col1" BETWEEN :__p2 AND :__p3 AND p."col2" NOT IN (:__p4))"#
+    );
+    assert_eq!(
+      params2,
+      vec![
+        (":__p0".to_string(), SqlValue::Integer(1)),
+        (":__p1".to_string(), SqlValue::Integer(2)),
+        (":__p2".to_string(), SqlValue::Integer(3)),
+        (":__p3".to_string(), SqlValue::Real(4.5)),
+        (":__p4".to_string(), SqlValue::Text("a".to_string())),
+      ]
+    );
   }
 }
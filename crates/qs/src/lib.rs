@@ -10,5 +10,8 @@ mod value;
 
 pub use column_rel_value::{ColumnOpValue, CompareOp};
 pub use filter::{Combiner, ValueOrComposite};
-pub use query::{Cursor, CursorType, Expand, FilterQuery, Order, OrderPrecedent, Query};
+pub use query::{
+  Aggregate, AggregateFunction, AggregateQuery, Aggregation, Cursor, CursorType, Expand,
+  Fields, FilterQuery, GroupBy, Order, OrderPrecedent, Query,
+};
 pub use value::Value;
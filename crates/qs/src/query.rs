@@ -151,6 +151,78 @@ impl<'de> serde::de::Deserialize<'de> for Expand {
   }
 }
 
+/// Sparse fieldset, e.g. `fields=id,name,author.name`.
+///
+/// Plain names select columns of the record itself, while `<fk>.<column>` select columns of the
+/// foreign record referenced by an expanded foreign-key column `<fk>`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fields {
+  pub columns: Vec<String>,
+}
+
+impl Fields {
+  /// Columns of the record itself, i.e. columns without a `<fk>.` prefix.
+  pub fn local(&self) -> impl Iterator<Item = &str> {
+    return self
+      .columns
+      .iter()
+      .filter(|c| !c.contains('.'))
+      .map(|c| c.as_str());
+  }
+
+  /// Pairs of `(<fk>, <column>)` for columns of expanded foreign records.
+  pub fn foreign(&self) -> impl Iterator<Item = (&str, &str)> {
+    return self.columns.iter().filter_map(|c| c.split_once('.'));
+  }
+}
+
+impl<'de> serde::de::Deserialize<'de> for Fields {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: serde::de::Deserializer<'de>,
+  {
+    use serde::de::Error;
+    use serde_value::Value;
+
+    let value = Value::deserialize(deserializer)?;
+    let Value::String(str) = value else {
+      return Err(Error::invalid_type(
+        crate::util::unexpected(&value),
+        &"comma separated column names to return",
+      ));
+    };
+
+    let is_valid = |name: &str| {
+      return !name.is_empty() && !name.contains('.') && crate::util::sanitize_column_name(name);
+    };
+
+    let columns = str
+      .split(",")
+      .map(|field| {
+        let field = field.trim();
+        let valid = match field.split_once('.') {
+          Some((fk, column)) => is_valid(fk) && is_valid(column),
+          None => is_valid(field),
+        };
+
+        if !valid {
+          return Err(Error::custom(format!(
+            "invalid column name for fields: {field}"
+          )));
+        }
+
+        return Ok(field.to_string());
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    if columns.len() > 64 {
+      return Err(Error::invalid_length(64, &"no more than 64 fields"));
+    }
+
+    return Ok(Fields { columns });
+  }
+}
+
 #[derive(Clone, Default, Debug, PartialEq, Deserialize)]
 pub struct Query {
   /// Pagination parameters:
@@ -169,6 +241,9 @@ pub struct Query {
   /// Which foreign key columns to expand (only when allowed by configuration).
   pub expand: Option<Expand>,
 
+  /// Sparse fieldset, i.e. which columns to return. All by default.
+  pub fields: Option<Fields>,
+
   /// Ordering. It's a vector for &order=-col0,+col1,col2
   pub order: Option<Order>,
 
@@ -211,6 +286,13 @@ impl Query {
       pairs.push(format!("expand={s}"));
     }
 
+    if let Some(ref fields) = self.fields
+      && !fields.columns.is_empty()
+    {
+      let s = fields.columns.join(",");
+      pairs.push(format!("fields={s}"));
+    }
+
     if let Some(ref order) = self.order {
       let s = order
         .columns
@@ -255,6 +337,258 @@ impl FilterQuery {
   }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum AggregateFunction {
+  Count,
+  Sum,
+  Avg,
+  Min,
+  Max,
+}
+
+impl AggregateFunction {
+  fn parse(name: &str) -> Option<Self> {
+    return match name {
+      "count" => Some(Self::Count),
+      "sum" => Some(Self::Sum),
+      "avg" => Some(Self::Avg),
+      "min" => Some(Self::Min),
+      "max" => Some(Self::Max),
+      _ => None,
+    };
+  }
+
+  pub fn name(&self) -> &'static str {
+    return match self {
+      Self::Count => "count",
+      Self::Sum => "sum",
+      Self::Avg => "avg",
+      Self::Min => "min",
+      Self::Max => "max",
+    };
+  }
+}
+
+/// A single aggregation, e.g. `count`, `count(col)` or `sum(col)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Aggregation {
+  pub function: AggregateFunction,
+  /// Column to aggregate over. Only `count` may omit it, i.e. `COUNT(*)`.
+  pub column: Option<String>,
+}
+
+impl Aggregation {
+  /// Name under which the aggregated value is returned, e.g. `count` or `sum_col`.
+  pub fn alias(&self) -> String {
+    return match self.column {
+      Some(ref column) => format!("{}_{column}", self.function.name()),
+      None => self.function.name().to_string(),
+    };
+  }
+
+  fn to_query(&self) -> String {
+    return match self.column {
+      Some(ref column) => format!("{}({column})", self.function.name()),
+      None => self.function.name().to_string(),
+    };
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Aggregate {
+  pub aggregations: Vec<Aggregation>,
+}
+
+impl<'de> serde::de::Deserialize<'de> for Aggregate {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: serde::de::Deserializer<'de>,
+  {
+    use serde::de::Error;
+    use serde_value::Value;
+
+    let value = Value::deserialize(deserializer)?;
+    let Value::String(str) = value else {
+      return Err(Error::invalid_type(
+        crate::util::unexpected(&value),
+        &"comma separated aggregations, e.g. count,sum(col)",
+      ));
+    };
+
+    let aggregations = str
+      .split(",")
+      .map(|v| {
+        let v = v.trim();
+        let (name, column) = match v.split_once("(") {
+          Some((name, rest)) => {
+            let Some(column) = rest.strip_suffix(")") else {
+              return Err(Error::custom(format!("invalid aggregation: {v}")));
+            };
+            (name, Some(column))
+          }
+          None => (v, None),
+        };
+
+        let Some(function) = AggregateFunction::parse(name) else {
+          return Err(Error::custom(format!("unknown aggregate function: {name}")));
+        };
+
+        let column = match column {
+          // Allow `count(*)` as an alias for `count`.
+          Some("*") if function == AggregateFunction::Count => None,
+          Some(column) => {
+            if !crate::util::sanitize_column_name(column) {
+              return Err(Error::custom(format!(
+                "invalid column name for aggregation: {column}"
+              )));
+            }
+            Some(column.to_string())
+          }
+          None if function == AggregateFunction::Count => None,
+          None => {
+            return Err(Error::custom(format!(
+              "missing column for aggregation: {v}"
+            )));
+          }
+        };
+
+        return Ok(Aggregation { function, column });
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    if aggregations.len() > 10 {
+      return Err(Error::invalid_length(10, &"no more than 10 aggregations"));
+    }
+
+    return Ok(Aggregate { aggregations });
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupBy {
+  pub columns: Vec<String>,
+}
+
+impl<'de> serde::de::Deserialize<'de> for GroupBy {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: serde::de::Deserializer<'de>,
+  {
+    use serde::de::Error;
+    use serde_value::Value;
+
+    let value = Value::deserialize(deserializer)?;
+    let Value::String(str) = value else {
+      return Err(Error::invalid_type(
+        crate::util::unexpected(&value),
+        &"comma separated column names to group by",
+      ));
+    };
+
+    let columns = str
+      .split(",")
+      .map(|column_name| {
+        let column_name = column_name.trim();
+        if !crate::util::sanitize_column_name(column_name) {
+          return Err(Error::custom(format!(
+            "invalid column name for group_by: {column_name}",
+          )));
+        }
+
+        return Ok(column_name.to_string());
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    if columns.len() > 5 {
+      return Err(Error::invalid_length(
+        5,
+        &"more more than 5 group_by dimension",
+      ));
+    }
+
+    return Ok(GroupBy { columns });
+  }
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Deserialize)]
+pub struct AggregateQuery {
+  /// Max number of groups returned.
+  pub limit: Option<usize>,
+  /// Offset to page through groups.
+  pub offset: Option<usize>,
+
+  /// Aggregations to compute, e.g. `&aggregate=count,sum(col0),max(col1)`.
+  pub aggregate: Option<Aggregate>,
+
+  /// Columns to group by, e.g. `&group_by=col0,col1`.
+  pub group_by: Option<GroupBy>,
+
+  /// Ordering of the groups by either group-by columns or aggregation aliases, e.g.
+  /// `&order=-count`.
+  pub order: Option<Order>,
+
+  /// Map from filter params to filter value. Same as for `Query`.
+  pub filter: Option<ValueOrComposite>,
+}
+
+impl AggregateQuery {
+  pub fn parse(query: &str) -> Result<AggregateQuery, Error> {
+    // NOTE: We rely on non-strict mode to parse `filter[col0]=a&b%filter[col1]=c`.
+    let qs = serde_qs::Config::new().max_depth(9).use_form_encoding(true);
+    return qs.deserialize_bytes::<AggregateQuery>(query.as_bytes());
+  }
+
+  /// Produce a query-string representation of this `AggregateQuery`.
+  pub fn to_query(&self) -> String {
+    let mut pairs: Vec<String> = vec![];
+
+    if let Some(limit) = self.limit {
+      pairs.push(format!("limit={limit}"));
+    }
+
+    if let Some(offset) = self.offset {
+      pairs.push(format!("offset={offset}"));
+    }
+
+    if let Some(ref aggregate) = self.aggregate
+      && !aggregate.aggregations.is_empty()
+    {
+      let s = aggregate
+        .aggregations
+        .iter()
+        .map(|a| a.to_query())
+        .join(",");
+      pairs.push(format!("aggregate={s}"));
+    }
+
+    if let Some(ref group_by) = self.group_by
+      && !group_by.columns.is_empty()
+    {
+      let s = group_by.columns.join(",");
+      pairs.push(format!("group_by={s}"));
+    }
+
+    if let Some(ref order) = self.order {
+      let s = order
+        .columns
+        .iter()
+        .map(|(c, p)| match p {
+          OrderPrecedent::Descending => format!("-{}", c),
+          OrderPrecedent::Ascending => c.to_string(),
+        })
+        .join(",");
+
+      pairs.push(format!("order={s}"));
+    }
+
+    if let Some(ref filter) = self.filter {
+      pairs.push(filter.to_query());
+    }
+
+    return pairs.into_iter().join("&");
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -359,6 +693,9 @@ mod tests {
       expand: Some(Expand {
         columns: vec!["a".to_string(), "b".to_string()],
       }),
+      fields: Some(Fields {
+        columns: vec!["id".to_string(), "a.name".to_string()],
+      }),
       order: Some(Order {
         columns: vec![
           ("a".to_string(), OrderPrecedent::Ascending),
@@ -375,6 +712,7 @@ mod tests {
     assert!(s.contains("offset=2"));
     assert!(s.contains("count=true"));
     assert!(s.contains("expand=a,b"));
+    assert!(s.contains("fields=id,a.name"));
     assert!(s.contains("order=a,-b"));
   }
 
@@ -412,6 +750,27 @@ mod tests {
     assert!(qs.deserialize_str::<Query>("expand=a,b,c,d,e,f").is_err());
   }
 
+  #[test]
+  fn test_query_fields_parsing() {
+    let qs = Config::new().max_depth(5);
+
+    assert!(qs.deserialize_str::<Query>("fields=").is_err());
+    assert!(qs.deserialize_str::<Query>("fields=$").is_err());
+    assert!(qs.deserialize_str::<Query>("fields=a.b.c").is_err());
+    assert!(qs.deserialize_str::<Query>("fields=a.").is_err());
+
+    let fields = qs
+      .deserialize_str::<Query>("fields=id,name,author.name")
+      .unwrap()
+      .fields
+      .unwrap();
+    assert_eq!(fields.local().collect::<Vec<_>>(), vec!["id", "name"]);
+    assert_eq!(
+      fields.foreign().collect::<Vec<_>>(),
+      vec![("author", "name")]
+    );
+  }
+
   #[test]
   fn test_query_filter_parsing() {
     let qs = Config::new();
@@ -478,6 +837,9 @@ mod tests {
         Value::String(s) => SqlValue::Text(s),
         Value::Integer(i) => SqlValue::Integer(i),
         Value::Double(d) => SqlValue::Real(d),
+        Value::List(_) => {
+          return Err("unexpected list".to_string());
+        }
       });
     }
 
@@ -633,4 +995,106 @@ mod tests {
       })
     );
   }
+
+  #[test]
+  fn test_aggregate_query_parsing() {
+    assert_eq!(
+      AggregateQuery::parse("").unwrap(),
+      AggregateQuery::default()
+    );
+
+    let q = AggregateQuery::parse(
+      "aggregate=count,sum(price),avg(price),min(created),max(created)&group_by=status,owner&order=-count&limit=10&filter[price][$gt]=5",
+    )
+    .unwrap();
+
+    assert_eq!(
+      q.aggregate.as_ref().unwrap().aggregations,
+      vec![
+        Aggregation {
+          function: AggregateFunction::Count,
+          column: None,
+        },
+        Aggregation {
+          function: AggregateFunction::Sum,
+          column: Some("price".to_string()),
+        },
+        Aggregation {
+          function: AggregateFunction::Avg,
+          column: Some("price".to_string()),
+        },
+        Aggregation {
+          function: AggregateFunction::Min,
+          column: Some("created".to_string()),
+        },
+        Aggregation {
+          function: AggregateFunction::Max,
+          column: Some("created".to_string()),
+        },
+      ]
+    );
+    assert_eq!(
+      q.aggregate
+        .as_ref()
+        .unwrap()
+        .aggregations
+        .iter()
+        .map(|a| a.alias())
+        .collect::<Vec<_>>(),
+      vec![
+        "count",
+        "sum_price",
+        "avg_price",
+        "min_created",
+        "max_created"
+      ]
+    );
+    assert_eq!(
+      q.group_by.as_ref().unwrap().columns,
+      vec!["status".to_string(), "owner".to_string()]
+    );
+    assert_eq!(
+      q.order.as_ref().unwrap().columns,
+      vec![("count".to_string(), OrderPrecedent::Descending)]
+    );
+    assert_eq!(q.limit, Some(10));
+    assert_eq!(
+      q.filter.as_ref().unwrap(),
+      &ValueOrComposite::Value(ColumnOpValue {
+        column: "price".to_string(),
+        op: CompareOp::GreaterThan,
+        value: Value::Integer(5),
+      })
+    );
+
+    // Round trip.
+    assert_eq!(AggregateQuery::parse(&q.to_query()).unwrap(), q);
+
+    assert_eq!(
+      AggregateQuery::parse("aggregate=count(*),count(col)")
+        .unwrap()
+        .aggregate
+        .unwrap()
+        .aggregations,
+      vec![
+        Aggregation {
+          function: AggregateFunction::Count,
+          column: None,
+        },
+        Aggregation {
+          function: AggregateFunction::Count,
+          column: Some("col".to_string()),
+        },
+      ]
+    );
+
+    assert!(AggregateQuery::parse("aggregate=").is_err());
+    assert!(AggregateQuery::parse("aggregate=sum").is_err());
+    assert!(AggregateQuery::parse("aggregate=sum(*)").is_err());
+    assert!(AggregateQuery::parse("aggregate=median(col)").is_err());
+    assert!(AggregateQuery::parse("aggregate=sum(col").is_err());
+    assert!(AggregateQuery::parse("aggregate=sum(a\"b)").is_err());
+    assert!(AggregateQuery::parse("group_by=$").is_err());
+    assert!(AggregateQuery::parse("group_by=a,b,c,d,e,f").is_err());
+  }
 }
@@ -7,6 +7,8 @@ pub enum Value {
   String(String),
   Integer(i64),
   Double(f64),
+  // Values of list-valued operators, e.g. `$in` or `$between`.
+  List(Vec<Value>),
 }
 
 impl Value {
@@ -27,6 +29,15 @@ impl std::fmt::Display for Value {
       Self::String(s) => s.fmt(f),
       Self::Integer(i) => i.fmt(f),
       Self::Double(d) => d.fmt(f),
+      Self::List(values) => {
+        for (i, value) in values.iter().enumerate() {
+          if i > 0 {
+            f.write_str(",")?;
+          }
+          value.fmt(f)?;
+        }
+        Ok(())
+      }
     };
   }
 }
@@ -109,5 +120,17 @@ mod tests {
         value: Value::String("NULL".to_string()),
       })
     );
+
+    assert_eq!(
+      qs.deserialize_str::<Query>("filter[col0][$match]=2024")
+        .unwrap()
+        .filter
+        .unwrap(),
+      ValueOrComposite::Value(ColumnOpValue {
+        column: "col0".to_string(),
+        op: CompareOp::Match,
+        value: Value::String("2024".to_string()),
+      })
+    );
   }
 }
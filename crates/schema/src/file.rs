@@ -59,7 +59,7 @@ mod bytes_or_base64 {
 impl FileUploadInput {
   pub fn consume(self) -> Result<(Option<String>, FileUpload, Vec<u8>), Error> {
     // We don't trust user provided type, we check ourselves.
-    let mime_type = infer::get(&self.data).map(|t| t.mime_type().to_string());
+    let mime_type = infer_mime_type(&self.data);
 
     return Ok((
       self.name,
@@ -74,6 +74,11 @@ impl FileUploadInput {
   }
 }
 
+/// Infers a file's mime type from its leading magic bytes, if known.
+pub fn infer_mime_type(data: &[u8]) -> Option<String> {
+  return infer::get(data).map(|t| t.mime_type().to_string());
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 // NOTE: Currently we must allow unknown fields since we re-use the same json schema for API inputs
 // and storage. There's an additional "name" field referencing form input's name.
@@ -128,6 +133,11 @@ impl FileUpload {
     return self.mime_type.as_deref().or(self.content_type.as_deref());
   }
 
+  /// The file's inferred mime type, unlike `content_type` never user-provided.
+  pub fn mime_type(&self) -> Option<&str> {
+    return self.mime_type.as_deref();
+  }
+
   pub fn original_filename(&self) -> Option<&str> {
     return self.original_filename.as_deref();
   }
@@ -1,2 +1,4 @@
-pub use crate::sqlite::connection::{ArcLockGuard, Connection, LockGuard, Options};
+pub use crate::sqlite::connection::{
+  ArcLockGuard, Connection, ConnectionStats, LockGuard, Options,
+};
 pub use crate::sqlite::sync::{SyncConnection, SyncConnectionTrait};
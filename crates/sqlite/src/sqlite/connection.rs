@@ -14,7 +14,7 @@ use crate::sqlite::transaction::Transaction;
 use crate::sqlite::util::{columns, from_row, from_rows, get_value, map_first};
 
 // NOTE: We should probably decouple from the impl.
-pub use crate::sqlite::executor::{ArcLockGuard, LockGuard, Options};
+pub use crate::sqlite::executor::{ArcLockGuard, ConnectionStats, LockGuard, Options};
 
 /// A handle to call functions in background thread.
 #[derive(Clone)]
@@ -71,6 +71,11 @@ impl Connection {
     return self.exec.threads();
   }
 
+  /// Snapshot of the connection's queue depths and lock contention.
+  pub fn stats(&self) -> ConnectionStats {
+    return self.exec.stats();
+  }
+
   /// Acquire write lock on the connections.
   ///
   /// NOTE: This should not be used for installing extension methods, since only the writer
@@ -332,13 +337,24 @@ impl Connection {
   }
 
   pub async fn backup(&self, path: impl AsRef<std::path::Path>) -> Result<(), Error> {
+    return self.backup_database("main", path).await;
+  }
+
+  /// Backs up the database with the given schema name, e.g. "main" or an attached database, to
+  /// a new database file at `path`.
+  pub async fn backup_database(
+    &self,
+    schema_name: &str,
+    path: impl AsRef<std::path::Path>,
+  ) -> Result<(), Error> {
+    let schema_name = schema_name.to_string();
     let mut dst = rusqlite::Connection::open(path)?;
     return self
       .exec
       .call_reader(move |src_conn| -> Result<(), Error> {
         use rusqlite::backup::{Backup, StepResult};
 
-        let backup = Backup::new(src_conn, &mut dst)?;
+        let backup = Backup::new_with_names(src_conn, schema_name.as_str(), &mut dst, "main")?;
         let mut retries = 0;
 
         loop {
@@ -3,6 +3,8 @@ use log::*;
 use parking_lot::RwLock;
 use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use tokio::sync::oneshot;
 
 use crate::error::Error;
@@ -31,6 +33,35 @@ pub struct Options {
   pub num_threads: Option<usize>,
 }
 
+/// Snapshot of runtime statistics, e.g. for monitoring.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionStats {
+  /// Number of queued write operations.
+  pub write_queue_depth: usize,
+  /// Number of queued read operations.
+  pub read_queue_depth: usize,
+  /// Total time the writer thread spent waiting to acquire the lock on the connections.
+  pub write_lock_wait: Duration,
+  /// Number of times the writer thread acquired the lock on the connections.
+  pub write_lock_acquisitions: u64,
+}
+
+#[derive(Default)]
+struct LockStats {
+  wait_nanos: AtomicU64,
+  acquisitions: AtomicU64,
+}
+
+impl LockStats {
+  #[inline]
+  fn record(&self, wait: Duration) {
+    self
+      .wait_nanos
+      .fetch_add(wait.as_nanos() as u64, Ordering::Relaxed);
+    self.acquisitions.fetch_add(1, Ordering::Relaxed);
+  }
+}
+
 /// A handle to call functions in background thread.
 #[derive(Clone)]
 pub(crate) struct Executor {
@@ -38,6 +69,7 @@ pub(crate) struct Executor {
   writer: Sender<WriterMessage>,
   // NOTE: Is shared across reader and writer worker threads.
   conns: Arc<RwLock<ConnectionVec>>,
+  lock_stats: Arc<LockStats>,
 }
 
 impl Executor {
@@ -110,6 +142,7 @@ impl Executor {
 
     let (shared_write_sender, shared_write_receiver) = flume::unbounded::<WriterMessage>();
     let (shared_read_sender, shared_read_receiver) = flume::unbounded::<ReaderMessage>();
+    let lock_stats = Arc::new(LockStats::default());
 
     // Spawn writer thread.
     std::thread::Builder::new()
@@ -117,8 +150,16 @@ impl Executor {
       .spawn({
         let shared_read_receiver = shared_read_receiver.clone();
         let conns = conns.clone();
-
-        move || writer_event_loop(conns, shared_read_receiver, shared_write_receiver)
+        let lock_stats = lock_stats.clone();
+
+        move || {
+          writer_event_loop(
+            conns,
+            lock_stats,
+            shared_read_receiver,
+            shared_write_receiver,
+          )
+        }
       })
       .map_err(|err| Error::Other(format!("spawning rw thread failed: {err}").into()))?;
 
@@ -144,6 +185,7 @@ impl Executor {
       reader: shared_read_sender,
       writer: shared_write_sender,
       conns,
+      lock_stats,
     };
 
     assert_eq!(num_threads, conn.threads());
@@ -155,6 +197,15 @@ impl Executor {
     return self.conns.read().0.len();
   }
 
+  pub fn stats(&self) -> ConnectionStats {
+    return ConnectionStats {
+      write_queue_depth: self.writer.len(),
+      read_queue_depth: self.reader.len(),
+      write_lock_wait: Duration::from_nanos(self.lock_stats.wait_nanos.load(Ordering::Relaxed)),
+      write_lock_acquisitions: self.lock_stats.acquisitions.load(Ordering::Relaxed),
+    };
+  }
+
   #[inline]
   pub fn write_lock(&self) -> LockGuard<'_> {
     return LockGuard {
@@ -317,6 +368,7 @@ fn reader_event_loop(
 
 fn writer_event_loop(
   conns: Arc<RwLock<ConnectionVec>>,
+  lock_stats: Arc<LockStats>,
   reader_receiver: Receiver<ReaderMessage>,
   writer_receiver: Receiver<WriterMessage>,
 ) {
@@ -328,7 +380,9 @@ fn writer_event_loop(
 
       return match m {
         WriterMessage::RunMut(f) => {
+          let start = Instant::now();
           let mut lock = conns.write();
+          lock_stats.record(start.elapsed());
           f(&mut lock.0[0]);
 
           // Continue
@@ -31,6 +31,22 @@ async fn call_success_test() {
   assert_eq!(0, result.unwrap());
 }
 
+#[tokio::test]
+async fn stats_test() {
+  let conn = Connection::open_in_memory().unwrap();
+  assert_eq!(0, conn.stats().write_lock_acquisitions);
+
+  conn
+    .execute("CREATE TABLE person(id INTEGER PRIMARY KEY)", ())
+    .await
+    .unwrap();
+
+  let stats = conn.stats();
+  assert_eq!(1, stats.write_lock_acquisitions);
+  assert_eq!(0, stats.write_queue_depth);
+  assert_eq!(0, stats.read_queue_depth);
+}
+
 #[tokio::test]
 async fn call_failure_test() {
   let conn = Connection::open_in_memory().unwrap();
@@ -0,0 +1,98 @@
+use http::StatusCode;
+
+pub use crate::wit::exports::trailbase::component::record_hook_endpoint::RecordHookEvent as Event;
+
+pub type JsonObject = serde_json::Map<String, serde_json::Value>;
+
+#[derive(Clone, Debug)]
+pub struct User {
+  /// Url-safe Base64 encoded id of the current user.
+  pub id: String,
+  /// E-mail of the current user.
+  pub email: String,
+}
+
+/// Context passed to record hooks.
+#[derive(Clone, Debug)]
+pub struct RecordHookContext {
+  /// Name of the Record API.
+  pub api_name: String,
+  pub event: Event,
+  /// Id of the record. Absent for `Event::BeforeCreate`.
+  pub record_id: Option<String>,
+  /// For `before` create and update events the request payload, for `after` events the written
+  /// row and for deletes the row being removed.
+  pub record: Option<JsonObject>,
+  /// The authenticated user, if any.
+  pub user: Option<User>,
+}
+
+/// Result of a record hook.
+#[derive(Clone, Debug)]
+pub enum RecordHookResult {
+  /// Continue with the write as is.
+  Continue,
+  /// Continue with the write after merging the given fields into the record's payload. Only valid
+  /// for `Event::BeforeCreate` and `Event::BeforeUpdate`.
+  Modify(JsonObject),
+  /// Abort the write and roll back the transaction.
+  Abort { status: StatusCode, message: String },
+}
+
+impl RecordHookResult {
+  pub fn abort(status: StatusCode, message: impl std::string::ToString) -> Self {
+    return Self::Abort {
+      status,
+      message: message.to_string(),
+    };
+  }
+}
+
+type RecordHookHandler = Box<dyn Fn(RecordHookContext) -> RecordHookResult + Send + Sync>;
+
+pub struct RecordHook {
+  pub api_name: String,
+  pub events: Vec<Event>,
+  pub handler: RecordHookHandler,
+}
+
+impl RecordHook {
+  pub fn new(
+    api_name: impl std::string::ToString,
+    events: &[Event],
+    f: impl Fn(RecordHookContext) -> RecordHookResult + Send + Sync + 'static,
+  ) -> Self {
+    return Self {
+      api_name: api_name.to_string(),
+      events: events.into(),
+      handler: Box::new(f),
+    };
+  }
+}
+
+/// User auth tokens are minted for.
+#[derive(Clone, Debug)]
+pub struct AuthUser {
+  /// Url-safe Base64 encoded id of the user.
+  pub id: String,
+  /// E-mail of the user.
+  pub email: String,
+  pub admin: bool,
+  /// Names of the roles assigned to the user.
+  pub roles: Vec<String>,
+}
+
+type CustomClaimsHandler = Box<dyn Fn(AuthUser) -> Result<JsonObject, String> + Send + Sync>;
+
+/// Hook providing custom claims, e.g. a tenant id, to be added to a user's auth tokens.
+pub struct CustomClaimsHook {
+  pub handler: CustomClaimsHandler,
+}
+
+impl CustomClaimsHook {
+  pub fn new(f: impl Fn(AuthUser) -> Result<JsonObject, String> + Send + Sync + 'static) -> Self {
+    return Self {
+      handler: Box::new(f),
+    };
+  }
+}
@@ -29,19 +29,25 @@ pub mod wit {
 pub mod db;
 pub mod fetch;
 pub mod fs;
+pub mod hook;
 pub mod http;
 pub mod job;
 pub mod kv;
 pub mod time;
 
-use std::sync::OnceLock;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, OnceLock};
 use trailbase_wasm_common::{HttpContext, HttpContextKind};
 use wstd::http::Request;
 use wstd::http::body::IncomingBody;
 use wstd::http::server::{Finished, Responder};
 
+use crate::hook::{CustomClaimsHook, RecordHook, RecordHookContext, RecordHookResult};
 use crate::http::{HttpRoute, Method, StatusCode, empty_error_response};
 use crate::job::Job;
+use crate::wit::exports::trailbase::component::sqlite_aggregate_function_endpoint::{
+  AggregateArguments, AggregateContext, SqliteAggregateFunction as WitSqliteAggregateFunction,
+};
 
 // Needed for export macro
 pub use static_assertions::assert_impl_all;
@@ -104,6 +110,65 @@ impl SqliteFunction {
   }
 }
 
+/// State of a single aggregation, e.g. one group of a `GROUP BY` query.
+pub trait SqliteAggregate: Send {
+  /// Adds a row to the aggregation.
+  fn step(&mut self, args: Vec<sqlite::Value>) -> Result<(), sqlite::Error>;
+
+  /// Returns the final result.
+  fn finalize(self: Box<Self>) -> Result<sqlite::Value, sqlite::Error>;
+
+  /// Returns the current result. Only required for window functions.
+  fn value(&self) -> Result<sqlite::Value, sqlite::Error> {
+    return Err(sqlite::Error::Other("not a window function".to_string()));
+  }
+
+  /// Removes the oldest row from the aggregation. Only required for window functions.
+  fn inverse(&mut self, _args: Vec<sqlite::Value>) -> Result<(), sqlite::Error> {
+    return Err(sqlite::Error::Other("not a window function".to_string()));
+  }
+}
+
+type SqliteAggregateBuilder = Box<dyn Fn() -> Box<dyn SqliteAggregate> + Send + Sync>;
+
+pub struct SqliteAggregateFunction {
+  name: String,
+  num_args: u32,
+  flags: Vec<sqlite::SqliteFunctionFlags>,
+  window: bool,
+  builder: SqliteAggregateBuilder,
+}
+
+impl SqliteAggregateFunction {
+  /// Aggregate function with a fresh `A::default()` state per aggregation.
+  pub fn new<A: SqliteAggregate + Default + 'static>(
+    name: impl std::string::ToString,
+    num_args: u32,
+    flags: &[sqlite::SqliteFunctionFlags],
+  ) -> Self {
+    return Self {
+      name: name.to_string(),
+      num_args,
+      flags: flags.into(),
+      window: false,
+      builder: Box::new(|| Box::new(A::default())),
+    };
+  }
+
+  /// Same as `new` but also registers `A`'s `value` and `inverse` to be usable as a window
+  /// function.
+  pub fn new_window<A: SqliteAggregate + Default + 'static>(
+    name: impl std::string::ToString,
+    num_args: u32,
+    flags: &[sqlite::SqliteFunctionFlags],
+  ) -> Self {
+    return Self {
+      window: true,
+      ..Self::new::<A>(name, num_args, flags)
+    };
+  }
+}
+
 pub trait Guest {
   fn init(_: Args) {}
 
@@ -118,6 +183,18 @@ pub trait Guest {
   fn sqlite_scalar_functions() -> Vec<SqliteFunction> {
     return vec![];
   }
+
+  fn sqlite_aggregate_functions() -> Vec<SqliteAggregateFunction> {
+    return vec![];
+  }
+
+  fn record_hooks() -> Vec<RecordHook> {
+    return vec![];
+  }
+
+  fn custom_claims_hook() -> Option<CustomClaimsHook> {
+    return None;
+  }
 }
 
 pub struct TrailbaseHandler<T: Guest> {
@@ -135,6 +212,24 @@ impl<T: Guest> TrailbaseHandler<T> {
     static FUNCS: OnceLock<Vec<SqliteFunction>> = OnceLock::new();
     return FUNCS.get_or_init(T::sqlite_scalar_functions);
   }
+
+  fn get_sqlite_aggregate_functions() -> &'static [SqliteAggregateFunction] {
+    // NOTE: This assumes that there's only one `T`, since static are shared across generics.
+    static FUNCS: OnceLock<Vec<SqliteAggregateFunction>> = OnceLock::new();
+    return FUNCS.get_or_init(T::sqlite_aggregate_functions);
+  }
+
+  fn get_record_hooks() -> &'static [RecordHook] {
+    // NOTE: This assumes that there's only one `T`, since static are shared across generics.
+    static HOOKS: OnceLock<Vec<RecordHook>> = OnceLock::new();
+    return HOOKS.get_or_init(T::record_hooks);
+  }
+
+  fn get_custom_claims_hook() -> Option<&'static CustomClaimsHook> {
+    // NOTE: This assumes that there's only one `T`, since static are shared across generics.
+    static HOOK: OnceLock<Option<CustomClaimsHook>> = OnceLock::new();
+    return HOOK.get_or_init(T::custom_claims_hook).as_ref();
+  }
 }
 
 impl<T: Guest> crate::wit::exports::trailbase::component::init_endpoint::Guest
@@ -214,6 +309,225 @@ impl<T: Guest> crate::wit::exports::trailbase::component::sqlite_function_endpoi
   }
 }
 
+impl<T: Guest> crate::wit::exports::trailbase::component::sqlite_aggregate_function_endpoint::Guest
+  for TrailbaseHandler<T>
+{
+  fn init_sqlite_aggregate_functions(args: Arguments) -> Vec<WitSqliteAggregateFunction> {
+    Self::call_init_once(Args {
+      version: args.version,
+    });
+
+    return Self::get_sqlite_aggregate_functions()
+      .iter()
+      .map(|f| WitSqliteAggregateFunction {
+        name: f.name.clone(),
+        num_args: f.num_args,
+        function_flags: f.flags.clone(),
+        window: f.window,
+      })
+      .collect();
+  }
+
+  fn dispatch_aggregate_step(
+    args: AggregateArguments,
+  ) -> Result<(), crate::wit::exports::trailbase::component::sqlite_function_endpoint::Error> {
+    return Self::with_aggregate(&args.context, |aggregate| {
+      return aggregate.step(args.arguments);
+    });
+  }
+
+  fn dispatch_aggregate_final(
+    context: AggregateContext,
+  ) -> Result<
+    crate::wit::exports::trailbase::component::sqlite_function_endpoint::Value,
+    crate::wit::exports::trailbase::component::sqlite_function_endpoint::Error,
+  > {
+    let aggregate = match lock_aggregates().remove(&context.context_id) {
+      Some(aggregate) => aggregate,
+      // No rows, i.e. no prior step.
+      None => (Self::find_aggregate_function(&context.function_name)?.builder)(),
+    };
+
+    return aggregate.finalize();
+  }
+
+  fn dispatch_aggregate_value(
+    context: AggregateContext,
+  ) -> Result<
+    crate::wit::exports::trailbase::component::sqlite_function_endpoint::Value,
+    crate::wit::exports::trailbase::component::sqlite_function_endpoint::Error,
+  > {
+    return Self::with_aggregate(&context, |aggregate| {
+      return aggregate.value();
+    });
+  }
+
+  fn dispatch_aggregate_inverse(
+    args: AggregateArguments,
+  ) -> Result<(), crate::wit::exports::trailbase::component::sqlite_function_endpoint::Error> {
+    return Self::with_aggregate(&args.context, |aggregate| {
+      return aggregate.inverse(args.arguments);
+    });
+  }
+}
+
+type AggregateStates = HashMap<u64, Box<dyn SqliteAggregate>>;
+
+/// In-flight aggregations by context id.
+static AGGREGATES: LazyLock<Mutex<AggregateStates>> = LazyLock::new(Default::default);
+
+fn lock_aggregates() -> std::sync::MutexGuard<'static, AggregateStates> {
+  // Guests are single-threaded, i.e. the lock cannot be poisoned by a concurrent panic.
+  return AGGREGATES
+    .lock()
+    .unwrap_or_else(|poisoned| poisoned.into_inner());
+}
+
+impl<T: Guest> TrailbaseHandler<T> {
+  fn find_aggregate_function(
+    name: &str,
+  ) -> Result<&'static SqliteAggregateFunction, sqlite::Error> {
+    return Self::get_sqlite_aggregate_functions()
+      .iter()
+      .find(|f| f.name == name)
+      .ok_or_else(|| sqlite::Error::Other("Missing function".to_string()));
+  }
+
+  /// Runs `f` on the aggregation's state, creating it on first use.
+  fn with_aggregate<R>(
+    context: &AggregateContext,
+    f: impl FnOnce(&mut dyn SqliteAggregate) -> Result<R, sqlite::Error>,
+  ) -> Result<R, sqlite::Error> {
+    let mut aggregates = lock_aggregates();
+    let aggregate = match aggregates.entry(context.context_id) {
+      std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+      std::collections::hash_map::Entry::Vacant(entry) => {
+        entry.insert((Self::find_aggregate_function(&context.function_name)?.builder)())
+      }
+    };
+
+    return f(aggregate.as_mut());
+  }
+}
+
+impl<T: Guest> crate::wit::exports::trailbase::component::record_hook_endpoint::Guest
+  for TrailbaseHandler<T>
+{
+  fn init_record_hooks(
+    args: Arguments,
+  ) -> Vec<crate::wit::exports::trailbase::component::record_hook_endpoint::RecordHook> {
+    use crate::wit::exports::trailbase::component::record_hook_endpoint::RecordHook as WitRecordHook;
+
+    Self::call_init_once(Args {
+      version: args.version,
+    });
+
+    return Self::get_record_hooks()
+      .iter()
+      .map(|h| WitRecordHook {
+        api_name: h.api_name.clone(),
+        events: h.events.clone(),
+      })
+      .collect();
+  }
+
+  fn dispatch_record_hook(
+    args: crate::wit::exports::trailbase::component::record_hook_endpoint::Arguments,
+  ) -> Result<
+    crate::wit::exports::trailbase::component::record_hook_endpoint::Outcome,
+    crate::wit::exports::trailbase::component::record_hook_endpoint::Error,
+  > {
+    use crate::wit::exports::trailbase::component::record_hook_endpoint::{Abort, Error, Outcome};
+
+    let record = match args.record {
+      Some(record) => Some(
+        serde_json::from_str::<hook::JsonObject>(&record)
+          .map_err(|err| Error::Other(format!("Invalid record: {err}")))?,
+      ),
+      None => None,
+    };
+
+    let context = RecordHookContext {
+      api_name: args.api_name,
+      event: args.event,
+      record_id: args.record_id,
+      record,
+      user: args.user.map(|u| hook::User {
+        id: u.id,
+        email: u.email,
+      }),
+    };
+
+    // Hooks are called in registration order, modifications are visible to subsequent hooks.
+    let mut modified: Option<hook::JsonObject> = None;
+    for h in Self::get_record_hooks()
+      .iter()
+      .filter(|h| h.api_name == context.api_name && h.events.contains(&context.event))
+    {
+      let mut context = context.clone();
+      if let (Some(record), Some(modified)) = (&mut context.record, &modified) {
+        record.extend(modified.clone());
+      }
+
+      match (h.handler)(context) {
+        RecordHookResult::Continue => {}
+        RecordHookResult::Modify(fields) => {
+          modified.get_or_insert_default().extend(fields);
+        }
+        RecordHookResult::Abort { status, message } => {
+          return Ok(Outcome::Abort(Abort {
+            status: status.as_u16(),
+            message,
+          }));
+        }
+      }
+    }
+
+    return match modified {
+      Some(fields) => Ok(Outcome::Modify(
+        serde_json::to_string(&fields).map_err(|err| Error::Other(err.to_string()))?,
+      )),
+      None => Ok(Outcome::Proceed),
+    };
+  }
+}
+
+impl<T: Guest> crate::wit::exports::trailbase::component::auth_hook_endpoint::Guest
+  for TrailbaseHandler<T>
+{
+  fn init_auth_hooks(
+    args: Arguments,
+  ) -> crate::wit::exports::trailbase::component::auth_hook_endpoint::AuthHooks {
+    Self::call_init_once(Args {
+      version: args.version,
+    });
+
+    return crate::wit::exports::trailbase::component::auth_hook_endpoint::AuthHooks {
+      custom_claims: Self::get_custom_claims_hook().is_some(),
+    };
+  }
+
+  fn custom_claims(
+    user: crate::wit::exports::trailbase::component::auth_hook_endpoint::User,
+  ) -> Result<String, crate::wit::exports::trailbase::component::auth_hook_endpoint::Error> {
+    use crate::wit::exports::trailbase::component::auth_hook_endpoint::Error;
+
+    let Some(hook) = Self::get_custom_claims_hook() else {
+      return Err(Error::Other("no custom claims hook registered".to_string()));
+    };
+
+    let claims = (hook.handler)(hook::AuthUser {
+      id: user.id,
+      email: user.email,
+      admin: user.admin,
+      roles: user.roles,
+    })
+    .map_err(Error::Other)?;
+
+    return serde_json::to_string(&claims).map_err(|err| Error::Other(err.to_string()));
+  }
+}
+
 pub struct HttpIncomingHandler<T: Guest> {
   phantom: std::marker::PhantomData<T>,
 }
@@ -1,8 +1,15 @@
+use rusqlite::functions::{Aggregate, Context, FunctionFlags, WindowAggregate};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::Mutex;
 use wasmtime::{Result, Store};
 
 use crate::Error;
+use crate::host::aggregates::exports::trailbase::component::sqlite_aggregate_function_endpoint::{
+  AggregateArguments, AggregateContext,
+};
+use crate::host::aggregates::exports::trailbase::component::sqlite_function_endpoint::Value as AggregateValue;
+use crate::host::exports::trailbase::component::sqlite_function_endpoint::Value;
 
 #[derive(Clone)]
 pub struct SqliteScalarFunction {
@@ -11,14 +18,33 @@ pub struct SqliteScalarFunction {
   pub flags: Vec<rusqlite::functions::FunctionFlags>,
 }
 
+#[derive(Clone)]
+pub struct SqliteAggregateFunction {
+  pub name: String,
+  pub num_args: u32,
+  pub flags: Vec<rusqlite::functions::FunctionFlags>,
+  /// Whether the function also implements `value` and `inverse`.
+  pub window: bool,
+}
+
 #[derive(Clone)]
 pub struct SqliteFunctions {
   pub scalar_functions: Vec<SqliteScalarFunction>,
+  pub aggregate_functions: Vec<SqliteAggregateFunction>,
+}
+
+impl SqliteFunctions {
+  pub fn is_empty(&self) -> bool {
+    return self.scalar_functions.is_empty() && self.aggregate_functions.is_empty();
+  }
 }
 
 struct SqliteStoreInternal {
   store: Mutex<Store<crate::host::State>>,
-  bindings: crate::host::Interfaces,
+  bindings: crate::host::Init,
+  /// Absent for components that don't export the optional
+  /// `sqlite-aggregate-function-endpoint`.
+  aggregate_bindings: Option<crate::host::aggregates::SqliteAggregateFunctions>,
 }
 
 #[derive(Clone)]
@@ -28,11 +54,19 @@ pub struct SqliteStore {
 
 impl SqliteStore {
   pub async fn new(runtime: &crate::Runtime) -> Result<Self, Error> {
-    let (store, bindings) = runtime.new_bindings().await?;
+    let (mut store, instance, bindings) = runtime.new_instance().await?;
+    let aggregate_bindings =
+      crate::host::aggregates::SqliteAggregateFunctions::new(&mut store, &instance)
+        .map_err(|err| {
+          log::debug!("Component doesn't provide SQLite aggregate functions: {err}");
+        })
+        .ok();
+
     return Ok(Self {
       state: Arc::new(SqliteStoreInternal {
         store: Mutex::new(store),
         bindings,
+        aggregate_bindings,
       }),
     });
   }
@@ -51,11 +85,33 @@ impl SqliteStore {
     let mut store = self.state.store.lock().await;
     let functions = store
       .run_concurrent(async |accessor| -> Result<_, Error> {
-        let functions = api.call_init_sqlite_functions(accessor, args).await?;
+        let functions = api
+          .call_init_sqlite_functions(accessor, args.clone())
+          .await?;
         return Ok(functions);
       })
       .await??;
 
+    let aggregate_functions = match self.state.aggregate_bindings {
+      Some(ref bindings) => {
+        let api = bindings.trailbase_component_sqlite_aggregate_function_endpoint();
+        let args =
+          crate::host::aggregates::exports::trailbase::component::init_endpoint::Arguments {
+            version: args.version,
+          };
+
+        store
+          .run_concurrent(async |accessor| -> Result<_, Error> {
+            let functions = api
+              .call_init_sqlite_aggregate_functions(accessor, args)
+              .await?;
+            return Ok(functions);
+          })
+          .await??
+      }
+      None => vec![],
+    };
+
     return Ok(SqliteFunctions {
       scalar_functions: functions
         .scalar_functions
@@ -74,6 +130,23 @@ impl SqliteStore {
           };
         })
         .collect(),
+      aggregate_functions: aggregate_functions
+        .into_iter()
+        .map(|f| {
+          return SqliteAggregateFunction {
+            name: f.name,
+            num_args: f.num_args,
+            flags: f
+              .function_flags
+              .into_iter()
+              .map(|f| -> rusqlite::functions::FunctionFlags {
+                return rusqlite::functions::FunctionFlags::from_bits_truncate(f as i32);
+              })
+              .collect(),
+            window: f.window,
+          };
+        })
+        .collect(),
     });
   }
 
@@ -107,6 +180,160 @@ impl SqliteStore {
       return Error::Other(err.to_string());
     });
   }
+
+  fn aggregate_api(
+    &self,
+  ) -> Result<
+    &crate::host::aggregates::exports::trailbase::component::sqlite_aggregate_function_endpoint::Guest,
+    Error,
+  >{
+    return match self.state.aggregate_bindings {
+      Some(ref bindings) => Ok(bindings.trailbase_component_sqlite_aggregate_function_endpoint()),
+      None => Err(Error::Other(
+        "missing sqlite-aggregate-function-endpoint".to_string(),
+      )),
+    };
+  }
+
+  pub async fn dispatch_aggregate_step(&self, args: AggregateArguments) -> Result<(), Error> {
+    let api = self.aggregate_api()?;
+
+    let mut store = self.state.store.lock().await;
+    let result = store
+      .run_concurrent(async |accessor| -> Result<_, Error> {
+        return Ok(api.call_dispatch_aggregate_step(accessor, args).await?);
+      })
+      .await??;
+
+    return result.map_err(|err| Error::Other(err.to_string()));
+  }
+
+  pub async fn dispatch_aggregate_inverse(&self, args: AggregateArguments) -> Result<(), Error> {
+    let api = self.aggregate_api()?;
+
+    let mut store = self.state.store.lock().await;
+    let result = store
+      .run_concurrent(async |accessor| -> Result<_, Error> {
+        return Ok(api.call_dispatch_aggregate_inverse(accessor, args).await?);
+      })
+      .await??;
+
+    return result.map_err(|err| Error::Other(err.to_string()));
+  }
+
+  pub async fn dispatch_aggregate_value(
+    &self,
+    context: AggregateContext,
+  ) -> Result<AggregateValue, Error> {
+    let api = self.aggregate_api()?;
+
+    let mut store = self.state.store.lock().await;
+    let result = store
+      .run_concurrent(async |accessor| -> Result<_, Error> {
+        return Ok(api.call_dispatch_aggregate_value(accessor, context).await?);
+      })
+      .await??;
+
+    return result.map_err(|err| Error::Other(err.to_string()));
+  }
+
+  pub async fn dispatch_aggregate_final(
+    &self,
+    context: AggregateContext,
+  ) -> Result<AggregateValue, Error> {
+    let api = self.aggregate_api()?;
+
+    let mut store = self.state.store.lock().await;
+    let result = store
+      .run_concurrent(async |accessor| -> Result<_, Error> {
+        return Ok(api.call_dispatch_aggregate_final(accessor, context).await?);
+      })
+      .await??;
+
+    return result.map_err(|err| Error::Other(err.to_string()));
+  }
+}
+
+/// Ids identifying individual aggregations across all connections and functions.
+static NEXT_AGGREGATE_CONTEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Forwards SQLite's aggregate and window callbacks to the WASM guest, which keeps the actual
+/// aggregation state keyed by context id.
+struct WasmAggregate {
+  store: SqliteStore,
+  function_name: String,
+}
+
+impl WasmAggregate {
+  fn context(&self, context_id: u64) -> AggregateContext {
+    return AggregateContext {
+      function_name: self.function_name.clone(),
+      context_id,
+    };
+  }
+}
+
+impl Aggregate<u64, rusqlite::types::Value> for WasmAggregate {
+  fn init(&self, _ctx: &mut Context<'_>) -> Result<u64, rusqlite::Error> {
+    return Ok(NEXT_AGGREGATE_CONTEXT_ID.fetch_add(1, Ordering::Relaxed));
+  }
+
+  fn step(&self, ctx: &mut Context<'_>, context_id: &mut u64) -> Result<(), rusqlite::Error> {
+    let args = AggregateArguments {
+      context: self.context(*context_id),
+      arguments: context_args(ctx)?,
+    };
+    return block_on(self.store.dispatch_aggregate_step(args));
+  }
+
+  fn finalize(
+    &self,
+    _ctx: &mut Context<'_>,
+    context_id: Option<u64>,
+  ) -> Result<rusqlite::types::Value, rusqlite::Error> {
+    // No rows were aggregated, i.e. `init` was never called. Still ask the guest for a result,
+    // e.g. `COUNT` would return 0.
+    let context_id =
+      context_id.unwrap_or_else(|| NEXT_AGGREGATE_CONTEXT_ID.fetch_add(1, Ordering::Relaxed));
+
+    return block_on(
+      self
+        .store
+        .dispatch_aggregate_final(self.context(context_id)),
+    )
+    .map(rusqlite::types::Value::from);
+  }
+}
+
+impl WindowAggregate<u64, rusqlite::types::Value> for WasmAggregate {
+  fn value(&self, context_id: Option<&mut u64>) -> Result<rusqlite::types::Value, rusqlite::Error> {
+    let Some(context_id) = context_id else {
+      // Empty window, i.e. no state. Rather than having the guest create state under a fresh id,
+      // which would never be released, ask for the final result of an empty aggregation.
+      let context_id = NEXT_AGGREGATE_CONTEXT_ID.fetch_add(1, Ordering::Relaxed);
+      return block_on(
+        self
+          .store
+          .dispatch_aggregate_final(self.context(context_id)),
+      )
+      .map(rusqlite::types::Value::from);
+    };
+
+    return block_on(
+      self
+        .store
+        .dispatch_aggregate_value(self.context(*context_id)),
+    )
+    .map(rusqlite::types::Value::from);
+  }
+
+  fn inverse(&self, ctx: &mut Context<'_>, context_id: &mut u64) -> Result<(), rusqlite::Error> {
+    let args = AggregateArguments {
+      context: self.context(*context_id),
+      arguments: context_args(ctx)?,
+    };
+    return block_on(self.store.dispatch_aggregate_inverse(args));
+  }
 }
 
 pub fn setup_connection(
@@ -114,65 +341,116 @@ pub fn setup_connection(
   store: SqliteStore,
   functions: &SqliteFunctions,
 ) -> Result<(), rusqlite::Error> {
-  use crate::host::exports::trailbase::component::sqlite_function_endpoint::Value;
-
   for function in &functions.scalar_functions {
     let store = store.clone();
     let function_name = function.name.clone();
 
-    let flags = {
-      if function.flags.is_empty() {
-        rusqlite::functions::FunctionFlags::default()
-      } else {
-        let mut flags = rusqlite::functions::FunctionFlags::from_bits_truncate(0);
-        for flag in &function.flags {
-          flags |= *flag;
-        }
-        flags
-      }
-    };
-
     // Registers the WASM function with the SQLite connection.
     conn.create_scalar_function(
       function.name.as_str(),
       function.num_args as i32,
-      flags,
+      to_function_flags(&function.flags),
       move |context| -> Result<rusqlite::types::Value, rusqlite::Error> {
-        let args = (0..context.len())
-          .map(|idx| -> Result<Value, rusqlite::Error> {
-            return Ok(match context.get::<rusqlite::types::Value>(idx)? {
-              rusqlite::types::Value::Null => Value::Null,
-              rusqlite::types::Value::Integer(i) => Value::Integer(i),
-              rusqlite::types::Value::Real(r) => Value::Real(r),
-              rusqlite::types::Value::Text(s) => Value::Text(s),
-              rusqlite::types::Value::Blob(b) => Value::Blob(b),
-            });
-          })
-          .collect::<Result<Vec<_>, _>>()?;
+        let args = context_args(context)?;
 
         // This is where the actual dispatch happens in a stateless manner, i.e. subsequent
         // executions don't share state.
-        let tokio = tokio::runtime::Builder::new_current_thread()
-          .enable_time()
-          .build()
-          .expect("running on a 'raw' thread of trailbase-sqlite");
-
-        let value = tokio
-          .block_on(store.dispatch_scalar_function(function_name.clone(), args))
-          .map_err(|err| {
-            return rusqlite::Error::UserFunctionError(err.into());
-          })?;
-
-        return Ok(match value {
-          Value::Null => rusqlite::types::Value::Null,
-          Value::Integer(i) => rusqlite::types::Value::Integer(i),
-          Value::Real(r) => rusqlite::types::Value::Real(r),
-          Value::Text(s) => rusqlite::types::Value::Text(s),
-          Value::Blob(b) => rusqlite::types::Value::Blob(b),
-        });
+        let value = block_on(store.dispatch_scalar_function(function_name.clone(), args))?;
+
+        return Ok(value.into());
       },
     )?;
   }
 
+  for function in &functions.aggregate_functions {
+    let aggregate = WasmAggregate {
+      store: store.clone(),
+      function_name: function.name.clone(),
+    };
+
+    if function.window {
+      conn.create_window_function(
+        function.name.as_str(),
+        function.num_args as i32,
+        to_function_flags(&function.flags),
+        aggregate,
+      )?;
+    } else {
+      conn.create_aggregate_function(
+        function.name.as_str(),
+        function.num_args as i32,
+        to_function_flags(&function.flags),
+        aggregate,
+      )?;
+    }
+  }
+
   return Ok(());
 }
+
+fn to_function_flags(flags: &[FunctionFlags]) -> FunctionFlags {
+  if flags.is_empty() {
+    return FunctionFlags::default();
+  }
+
+  let mut result = FunctionFlags::from_bits_truncate(0);
+  for flag in flags {
+    result |= *flag;
+  }
+  return result;
+}
+
+fn context_args<V: From<rusqlite::types::Value>>(
+  context: &Context<'_>,
+) -> Result<Vec<V>, rusqlite::Error> {
+  return (0..context.len())
+    .map(|idx| -> Result<V, rusqlite::Error> {
+      return Ok(context.get::<rusqlite::types::Value>(idx)?.into());
+    })
+    .collect();
+}
+
+/// Scalar and aggregate functions are bound separately and thus have distinct but identical
+/// `Value` types.
+macro_rules! impl_value_conversions {
+  ($value:ty) => {
+    impl From<rusqlite::types::Value> for $value {
+      fn from(value: rusqlite::types::Value) -> Self {
+        return match value {
+          rusqlite::types::Value::Null => Self::Null,
+          rusqlite::types::Value::Integer(i) => Self::Integer(i),
+          rusqlite::types::Value::Real(r) => Self::Real(r),
+          rusqlite::types::Value::Text(s) => Self::Text(s),
+          rusqlite::types::Value::Blob(b) => Self::Blob(b),
+        };
+      }
+    }
+
+    impl From<$value> for rusqlite::types::Value {
+      fn from(value: $value) -> Self {
+        return match value {
+          <$value>::Null => Self::Null,
+          <$value>::Integer(i) => Self::Integer(i),
+          <$value>::Real(r) => Self::Real(r),
+          <$value>::Text(s) => Self::Text(s),
+          <$value>::Blob(b) => Self::Blob(b),
+        };
+      }
+    }
+  };
+}
+
+impl_value_conversions!(Value);
+impl_value_conversions!(AggregateValue);
+
+/// Blocks on `f` from SQLite's "raw", i.e. non-tokio, threads.
+fn block_on<T>(f: impl Future<Output = Result<T, Error>>) -> Result<T, rusqlite::Error> {
+  let tokio = tokio::runtime::Builder::new_current_thread()
+    .enable_time()
+    .build()
+    .expect("running on a 'raw' thread of trailbase-sqlite");
+
+  return tokio
+    .block_on(f)
+    .map_err(|err| rusqlite::Error::UserFunctionError(err.into()));
+}
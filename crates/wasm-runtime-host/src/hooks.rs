@@ -0,0 +1,205 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use wasmtime::{Result, Store};
+
+use crate::Error;
+
+pub use crate::host::record_hooks::exports::trailbase::component::record_hook_endpoint::{
+  Abort, Arguments as RecordHookArguments, Outcome as RecordHookOutcome, RecordHookEvent,
+  User as RecordHookUser,
+};
+
+#[derive(Clone, Debug)]
+pub struct RecordHook {
+  /// Name of the Record API the hook is registered for.
+  pub api_name: String,
+  pub events: Vec<RecordHookEvent>,
+}
+
+struct RecordHookStoreInternal {
+  store: Mutex<Store<crate::host::State>>,
+  /// Absent for components that don't export the optional `record-hook-endpoint`.
+  bindings: Option<crate::host::record_hooks::RecordHooks>,
+}
+
+/// A long-lived store for dispatching record hooks.
+///
+/// Similar to `SqliteStore`, hooks are dispatched synchronously from within SQLite's writer
+/// thread, i.e. while the write transaction is pending.
+#[derive(Clone)]
+pub struct RecordHookStore {
+  state: Arc<RecordHookStoreInternal>,
+}
+
+impl RecordHookStore {
+  pub async fn new(runtime: &crate::Runtime) -> Result<Self, Error> {
+    let (mut store, instance, _) = runtime.new_instance().await?;
+    let bindings = crate::host::record_hooks::RecordHooks::new(&mut store, &instance)
+      .map_err(|err| {
+        log::debug!("Component doesn't provide record hooks: {err}");
+      })
+      .ok();
+
+    return Ok(Self {
+      state: Arc::new(RecordHookStoreInternal {
+        store: Mutex::new(store),
+        bindings,
+      }),
+    });
+  }
+
+  // Call WASM components `init` implementation.
+  pub async fn initialize_record_hooks(
+    &self,
+    args: crate::InitArgs,
+  ) -> Result<Vec<RecordHook>, Error> {
+    let Some(ref bindings) = self.state.bindings else {
+      return Ok(vec![]);
+    };
+    let api = bindings.trailbase_component_record_hook_endpoint();
+
+    let args = crate::host::record_hooks::exports::trailbase::component::init_endpoint::Arguments {
+      version: args.version,
+    };
+
+    let mut store = self.state.store.lock().await;
+    let hooks = store
+      .run_concurrent(async |accessor| -> Result<_, Error> {
+        let hooks = api.call_init_record_hooks(accessor, args).await?;
+        return Ok(hooks);
+      })
+      .await??;
+
+    return Ok(
+      hooks
+        .into_iter()
+        .map(|h| RecordHook {
+          api_name: h.api_name,
+          events: h.events,
+        })
+        .collect(),
+    );
+  }
+
+  pub async fn dispatch_record_hook(
+    &self,
+    args: RecordHookArguments,
+  ) -> Result<RecordHookOutcome, Error> {
+    let Some(ref bindings) = self.state.bindings else {
+      return Err(Error::Other("missing record-hook-endpoint".to_string()));
+    };
+    let api = bindings.trailbase_component_record_hook_endpoint();
+
+    let mut store = self.state.store.lock().await;
+    let result = store
+      .run_concurrent(async |accessor| -> Result<_, Error> {
+        let result = api.call_dispatch_record_hook(accessor, args).await?;
+        return Ok(result);
+      })
+      .await??;
+
+    return result.map_err(|err| {
+      return Error::Other(err.to_string());
+    });
+  }
+
+  /// Blocking version of `dispatch_record_hook` to be called from SQLite's "raw" threads.
+  pub fn dispatch_record_hook_sync(
+    &self,
+    args: RecordHookArguments,
+  ) -> Result<RecordHookOutcome, Error> {
+    // Same as for custom SQLite functions: dispatch on a throw-away runtime, since we're
+    // executing on a non-tokio thread.
+    let tokio = tokio::runtime::Builder::new_current_thread()
+      .enable_time()
+      .build()
+      .map_err(|err| Error::Other(err.to_string()))?;
+
+    return tokio.block_on(self.dispatch_record_hook(args));
+  }
+}
+
+pub use crate::host::auth_hooks::exports::trailbase::component::auth_hook_endpoint::User as AuthHookUser;
+
+/// Auth hooks implemented by a component.
+#[derive(Clone, Debug, Default)]
+pub struct AuthHooks {
+  /// Whether the component provides custom auth token claims.
+  pub custom_claims: bool,
+}
+
+struct AuthHookStoreInternal {
+  store: Mutex<Store<crate::host::State>>,
+  /// Absent for components that don't export the optional `auth-hook-endpoint`.
+  bindings: Option<crate::host::auth_hooks::AuthHooks>,
+}
+
+/// A long-lived store for dispatching auth hooks, e.g. when minting new auth tokens.
+///
+/// Unlike record hooks, auth hooks are dispatched asynchronously.
+#[derive(Clone)]
+pub struct AuthHookStore {
+  state: Arc<AuthHookStoreInternal>,
+}
+
+impl AuthHookStore {
+  pub async fn new(runtime: &crate::Runtime) -> Result<Self, Error> {
+    let (mut store, instance, _) = runtime.new_instance().await?;
+    let bindings = crate::host::auth_hooks::AuthHooks::new(&mut store, &instance)
+      .map_err(|err| {
+        log::debug!("Component doesn't provide auth hooks: {err}");
+      })
+      .ok();
+
+    return Ok(Self {
+      state: Arc::new(AuthHookStoreInternal {
+        store: Mutex::new(store),
+        bindings,
+      }),
+    });
+  }
+
+  // Call WASM components `init` implementation.
+  pub async fn initialize_auth_hooks(&self, args: crate::InitArgs) -> Result<AuthHooks, Error> {
+    let Some(ref bindings) = self.state.bindings else {
+      return Ok(AuthHooks::default());
+    };
+    let api = bindings.trailbase_component_auth_hook_endpoint();
+
+    let args = crate::host::auth_hooks::exports::trailbase::component::init_endpoint::Arguments {
+      version: args.version,
+    };
+
+    let mut store = self.state.store.lock().await;
+    let hooks = store
+      .run_concurrent(async |accessor| -> Result<_, Error> {
+        let hooks = api.call_init_auth_hooks(accessor, args).await?;
+        return Ok(hooks);
+      })
+      .await??;
+
+    return Ok(AuthHooks {
+      custom_claims: hooks.custom_claims,
+    });
+  }
+
+  /// Returns the JSON-encoded custom claims for the given user.
+  pub async fn custom_claims(&self, user: AuthHookUser) -> Result<String, Error> {
+    let Some(ref bindings) = self.state.bindings else {
+      return Err(Error::Other("missing auth-hook-endpoint".to_string()));
+    };
+    let api = bindings.trailbase_component_auth_hook_endpoint();
+
+    let mut store = self.state.store.lock().await;
+    let result = store
+      .run_concurrent(async |accessor| -> Result<_, Error> {
+        let result = api.call_custom_claims(accessor, user).await?;
+        return Ok(result);
+      })
+      .await??;
+
+    return result.map_err(|err| {
+      return Error::Other(err.to_string());
+    });
+  }
+}
@@ -14,8 +14,11 @@ use wasmtime_wasi_io::IoView;
 use crate::sqlite::acquire_transaction_lock_with_timeout;
 
 // Documentation: https://docs.wasmtime.dev/api/wasmtime/component/macro.bindgen.html
+//
+// NOTE: Only binds the exports required from every component. Optional exports are bound
+// separately below.
 wasmtime::component::bindgen!({
-    world: "trailbase:component/interfaces",
+    world: "trailbase:component/init",
     path: [
         // Order-sensitive: will import *.wit from the folder.
         "wit/deps-0.2.6/random",
@@ -60,6 +63,75 @@ wasmtime::component::bindgen!({
 
 pub use self::trailbase::database::sqlite::{Transaction, TxError, Value};
 
+/// Optional custom SQLite aggregate and window functions.
+pub(crate) mod aggregates {
+  wasmtime::component::bindgen!({
+      world: "trailbase:component/sqlite-aggregate-functions",
+      path: [
+          "wit/deps-0.2.6/random",
+          "wit/deps-0.2.6/io",
+          "wit/deps-0.2.6/clocks",
+          "wit/deps-0.2.6/filesystem",
+          "wit/deps-0.2.6/sockets",
+          "wit/deps-0.2.6/cli",
+          "wit/deps-0.2.6/http",
+          "wit/keyvalue-0.2.0-draft",
+          "wit/trailbase/database",
+          "wit/trailbase/component",
+      ],
+      require_store_data_send: false,
+      exports: {
+          default: async | store,
+      },
+  });
+}
+
+/// Optional record lifecycle hooks.
+pub(crate) mod record_hooks {
+  wasmtime::component::bindgen!({
+      world: "trailbase:component/record-hooks",
+      path: [
+          "wit/deps-0.2.6/random",
+          "wit/deps-0.2.6/io",
+          "wit/deps-0.2.6/clocks",
+          "wit/deps-0.2.6/filesystem",
+          "wit/deps-0.2.6/sockets",
+          "wit/deps-0.2.6/cli",
+          "wit/deps-0.2.6/http",
+          "wit/keyvalue-0.2.0-draft",
+          "wit/trailbase/database",
+          "wit/trailbase/component",
+      ],
+      require_store_data_send: false,
+      exports: {
+          default: async | store,
+      },
+  });
+}
+
+/// Optional auth hooks, e.g. custom auth token claims.
+pub(crate) mod auth_hooks {
+  wasmtime::component::bindgen!({
+      world: "trailbase:component/auth-hooks",
+      path: [
+          "wit/deps-0.2.6/random",
+          "wit/deps-0.2.6/io",
+          "wit/deps-0.2.6/clocks",
+          "wit/deps-0.2.6/filesystem",
+          "wit/deps-0.2.6/sockets",
+          "wit/deps-0.2.6/cli",
+          "wit/deps-0.2.6/http",
+          "wit/keyvalue-0.2.0-draft",
+          "wit/trailbase/database",
+          "wit/trailbase/component",
+      ],
+      require_store_data_send: false,
+      exports: {
+          default: async | store,
+      },
+  });
+}
+
 /// NOTE: This is needed due to State needing to be Send.
 unsafe impl Send for crate::sqlite::OwnedTx {}
 
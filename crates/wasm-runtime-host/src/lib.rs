@@ -3,6 +3,7 @@
 #![warn(clippy::await_holding_lock, clippy::inefficient_to_string)]
 
 pub mod functions;
+pub mod hooks;
 mod host;
 mod sqlite;
 
@@ -15,7 +16,7 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::SystemTime;
 use tokio::task::JoinError;
 use trailbase_wasi_keyvalue::WasiKeyValueCtx;
-use wasmtime::component::{Component, Linker, ResourceTable};
+use wasmtime::component::{Component, Instance, Linker, ResourceTable};
 use wasmtime::{AsContextMut, Config, Engine, Result, Store};
 use wasmtime_wasi::{DirPerms, FilePerms, WasiCtxBuilder};
 use wasmtime_wasi_http::WasiHttpCtx;
@@ -160,16 +161,21 @@ impl<T: StoreBuilder<State>> RuntimeT<T> {
     return &self.state.component_path;
   }
 
-  async fn new_bindings(&self) -> Result<(Store<State>, crate::host::Interfaces), Error> {
+  /// Instantiates the component and binds the exports required from every component.
+  async fn new_instance(&self) -> Result<(Store<State>, Instance, crate::host::Init), Error> {
     let mut store = self.state.store_builder.new_store(&self.state.engine)?;
 
-    let bindings = crate::host::Interfaces::instantiate_async(
-      &mut store,
-      &self.state.component,
-      &self.state.linker,
-    )
-    .await
-    .map_err(|err| {
+    let instance = self
+      .state
+      .linker
+      .instantiate_async(&mut store, &self.state.component)
+      .await
+      .and_then(|instance| {
+        let bindings = crate::host::Init::new(&mut store, &instance)?;
+        return Ok((instance, bindings));
+      });
+
+    let (instance, bindings) = instance.map_err(|err| {
       log::error!(
         "Failed to instantiate WIT component {path:?}: '{err}'.\n{ABI_MISMATCH_WARNING}",
         path = self.state.component_path
@@ -177,6 +183,11 @@ impl<T: StoreBuilder<State>> RuntimeT<T> {
       return err;
     })?;
 
+    return Ok((store, instance, bindings));
+  }
+
+  async fn new_bindings(&self) -> Result<(Store<State>, crate::host::Init), Error> {
+    let (store, _instance, bindings) = self.new_instance().await?;
     return Ok((store, bindings));
   }
 }
@@ -232,7 +243,7 @@ impl StoreBuilder<State> for Arc<SharedState> {
 
 struct HttpStoreInternal {
   // store: Mutex<Store<State>>,
-  // bindings: crate::host::Interfaces,
+  // bindings: crate::host::Init,
   // proxy_bindings: wasmtime_wasi_http::bindings::Proxy,
   runtime_state: Arc<RuntimeInternal<Arc<SharedState>>>,
   rt: Runtime,
@@ -633,6 +644,112 @@ mod tests {
     }
   }
 
+  #[tokio::test]
+  async fn test_custom_sqlite_aggregate_function() {
+    let conn = parking_lot::Mutex::new(rusqlite::Connection::open_in_memory().ok());
+
+    let _sqlite_function_runtime = {
+      let lock = conn.lock();
+      init_sqlite_function_runtime(lock.as_ref().unwrap()).await
+    };
+
+    let conn = trailbase_sqlite::Connection::with_opts(
+      move || -> Result<_, rusqlite::Error> {
+        let mut lock = conn.lock();
+        return Ok(lock.take().unwrap());
+      },
+      trailbase_sqlite::Options {
+        num_threads: Some(1),
+        ..Default::default()
+      },
+    )
+    .unwrap();
+
+    conn
+      .execute_batch(
+        "
+          CREATE TABLE numbers (id INTEGER PRIMARY KEY, x INTEGER NOT NULL);
+          INSERT INTO numbers (x) VALUES (1), (2), (3), (4);
+        ",
+      )
+      .await
+      .unwrap();
+
+    assert_eq!(
+      10,
+      conn
+        .read_query_row_get::<i64>("SELECT custom_sum(x) FROM numbers", (), 0)
+        .await
+        .unwrap()
+        .unwrap()
+    );
+
+    // No rows, i.e. no prior step.
+    assert_eq!(
+      0,
+      conn
+        .read_query_row_get::<i64>("SELECT custom_sum(x) FROM numbers WHERE x > 100", (), 0)
+        .await
+        .unwrap()
+        .unwrap()
+    );
+
+    // Sliding window exercising `value` and `inverse`.
+    let rows = conn
+      .read_query_rows(
+        "SELECT custom_sum(x) OVER (ORDER BY id ROWS BETWEEN 1 PRECEDING AND CURRENT ROW) FROM numbers",
+        (),
+      )
+      .await
+      .unwrap();
+    let sums: Vec<i64> = rows.iter().map(|row| row.get(0).unwrap()).collect();
+    assert_eq!(sums, vec![1, 3, 5, 7]);
+  }
+
+  #[tokio::test]
+  async fn test_record_hooks() {
+    let runtime = init_runtime(None);
+    let store = hooks::RecordHookStore::new(&runtime).await.unwrap();
+
+    let registered = store
+      .initialize_record_hooks(InitArgs { version: None })
+      .await
+      .unwrap();
+    assert_eq!(registered.len(), 1);
+    assert_eq!(registered[0].api_name, "wasm_hook_api");
+    assert_eq!(
+      registered[0].events,
+      vec![hooks::RecordHookEvent::BeforeCreate]
+    );
+
+    let dispatch = |text: &str| {
+      return store.dispatch_record_hook(hooks::RecordHookArguments {
+        api_name: "wasm_hook_api".to_string(),
+        event: hooks::RecordHookEvent::BeforeCreate,
+        record_id: None,
+        record: Some(serde_json::json!({"text": text}).to_string()),
+        user: None,
+      });
+    };
+
+    match dispatch("foo").await.unwrap() {
+      hooks::RecordHookOutcome::Modify(patch) => {
+        assert_eq!(
+          serde_json::from_str::<serde_json::Value>(&patch).unwrap(),
+          serde_json::json!({"source": "wasm"})
+        );
+      }
+      outcome => panic!("unexpected outcome: {outcome:?}"),
+    };
+
+    match dispatch("forbidden").await.unwrap() {
+      hooks::RecordHookOutcome::Abort(abort) => {
+        assert_eq!(abort.status, 403);
+      }
+      outcome => panic!("unexpected outcome: {outcome:?}"),
+    };
+  }
+
   async fn send_http_request(
     runtime: &Runtime,
     uri: &str,
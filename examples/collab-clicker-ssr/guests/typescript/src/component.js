@@ -1,3 +1,10 @@
 import e from "./index";
 
-export const { initEndpoint, incomingHandler, sqliteFunctionEndpoint } = e;
+export const {
+  initEndpoint,
+  incomingHandler,
+  sqliteFunctionEndpoint,
+  sqliteAggregateFunctionEndpoint,
+  recordHookEndpoint,
+  authHookEndpoint,
+} = e;
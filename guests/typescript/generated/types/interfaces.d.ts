@@ -1,4 +1,7 @@
+/// <reference path="./interfaces/trailbase-component-auth-hook-endpoint.d.ts" />
 /// <reference path="./interfaces/trailbase-component-init-endpoint.d.ts" />
+/// <reference path="./interfaces/trailbase-component-record-hook-endpoint.d.ts" />
+/// <reference path="./interfaces/trailbase-component-sqlite-aggregate-function-endpoint.d.ts" />
 /// <reference path="./interfaces/trailbase-component-sqlite-function-endpoint.d.ts" />
 /// <reference path="./interfaces/trailbase-database-sqlite.d.ts" />
 /// <reference path="./interfaces/wasi-clocks-monotonic-clock.d.ts" />
@@ -17,7 +20,7 @@
 /// <reference path="./interfaces/wasi-random-insecure-seed.d.ts" />
 /// <reference path="./interfaces/wasi-random-insecure.d.ts" />
 /// <reference path="./interfaces/wasi-random-random.d.ts" />
-declare module "trailbase:component/interfaces@0.1.1" {
+declare module "trailbase:component/interfaces@0.1.2" {
   export type * as TrailbaseDatabaseSqlite011 from "trailbase:database/sqlite@0.1.1"; // import trailbase:database/sqlite@0.1.1
   export type * as WasiClocksMonotonicClock023 from "wasi:clocks/monotonic-clock@0.2.3"; // import wasi:clocks/monotonic-clock@0.2.3
   export type * as WasiClocksWallClock023 from "wasi:clocks/wall-clock@0.2.3"; // import wasi:clocks/wall-clock@0.2.3
@@ -34,7 +37,10 @@ declare module "trailbase:component/interfaces@0.1.1" {
   export type * as WasiRandomInsecureSeed023 from "wasi:random/insecure-seed@0.2.3"; // import wasi:random/insecure-seed@0.2.3
   export type * as WasiRandomInsecure023 from "wasi:random/insecure@0.2.3"; // import wasi:random/insecure@0.2.3
   export type * as WasiRandomRandom023 from "wasi:random/random@0.2.3"; // import wasi:random/random@0.2.3
+  export * as authHookEndpoint from "trailbase:component/auth-hook-endpoint@0.1.2"; // export trailbase:component/auth-hook-endpoint@0.1.2
   export * as incomingHandler from "wasi:http/incoming-handler@0.2.3"; // export wasi:http/incoming-handler@0.2.3
-  export * as initEndpoint from "trailbase:component/init-endpoint@0.1.1"; // export trailbase:component/init-endpoint@0.1.1
-  export * as sqliteFunctionEndpoint from "trailbase:component/sqlite-function-endpoint@0.1.1"; // export trailbase:component/sqlite-function-endpoint@0.1.1
+  export * as initEndpoint from "trailbase:component/init-endpoint@0.1.2"; // export trailbase:component/init-endpoint@0.1.2
+  export * as recordHookEndpoint from "trailbase:component/record-hook-endpoint@0.1.2"; // export trailbase:component/record-hook-endpoint@0.1.2
+  export * as sqliteAggregateFunctionEndpoint from "trailbase:component/sqlite-aggregate-function-endpoint@0.1.2"; // export trailbase:component/sqlite-aggregate-function-endpoint@0.1.2
+  export * as sqliteFunctionEndpoint from "trailbase:component/sqlite-function-endpoint@0.1.2"; // export trailbase:component/sqlite-function-endpoint@0.1.2
 }
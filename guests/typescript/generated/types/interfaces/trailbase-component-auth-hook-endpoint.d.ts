@@ -0,0 +1,42 @@
+declare module "trailbase:component/auth-hook-endpoint@0.1.2" {
+  export function initAuthHooks(args: InitArguments): AuthHooks;
+  /**
+   * Returns a JSON-encoded object of custom claims to be added to the user's auth tokens.
+   */
+  export function customClaims(user: User): string;
+  export type InitArguments =
+    import("trailbase:component/init-endpoint@0.1.2").Arguments;
+  /**
+   * WARNING: Evolving a variant currently breaks the ABI:
+   *   https://github.com/WebAssembly/component-model/issues/454
+   */
+  export type Error = ErrorOther;
+  export interface ErrorOther {
+    tag: "other";
+    val: string;
+  }
+  export interface User {
+    /**
+     * Url-safe Base64 encoded id of the user.
+     */
+    id: string;
+    /**
+     * E-mail of the user.
+     */
+    email: string;
+    /**
+     * Whether the user is an admin.
+     */
+    admin: boolean;
+    /**
+     * Names of the roles assigned to the user.
+     */
+    roles: Array<string>;
+  }
+  export interface AuthHooks {
+    /**
+     * Whether the component implements `custom-claims`.
+     */
+    customClaims: boolean;
+  }
+}
@@ -0,0 +1,114 @@
+declare module "trailbase:component/record-hook-endpoint@0.1.2" {
+  export function initRecordHooks(args: InitArguments): Array<RecordHook>;
+  export function dispatchRecordHook(args: Arguments): Outcome;
+  export type InitArguments =
+    import("trailbase:component/init-endpoint@0.1.2").Arguments;
+  /**
+   * WARNING: Evolving a variant currently breaks the ABI:
+   *   https://github.com/WebAssembly/component-model/issues/454
+   */
+  export type Error = ErrorOther;
+  export interface ErrorOther {
+    tag: "other";
+    val: string;
+  }
+  /**
+   * # Variants
+   *
+   * ## `"before-create"`
+   *
+   * Before a new record is inserted. May modify the record.
+   * ## `"after-create"`
+   *
+   * After a new record was inserted but before the transaction commits.
+   * ## `"before-update"`
+   *
+   * Before an existing record is updated. May modify the record.
+   * ## `"after-update"`
+   *
+   * After an existing record was updated but before the transaction commits.
+   * ## `"before-delete"`
+   *
+   * Before an existing record is deleted.
+   * ## `"after-delete"`
+   *
+   * After an existing record was deleted but before the transaction commits.
+   */
+  export type RecordHookEvent =
+    | "before-create"
+    | "after-create"
+    | "before-update"
+    | "after-update"
+    | "before-delete"
+    | "after-delete";
+  export interface User {
+    /**
+     * Url-safe Base64 encoded id of the current user.
+     */
+    id: string;
+    /**
+     * E-mail of the current user.
+     */
+    email: string;
+  }
+  export interface Arguments {
+    /**
+     * Name of the Record API, i.e. `RecordApiConfig.name`.
+     */
+    apiName: string;
+    event: RecordHookEvent;
+    /**
+     * Id of the record. Absent for `before-create`.
+     */
+    recordId?: string;
+    /**
+     * JSON-encoded record object. For `before-create` and `before-update` this is the request's
+     * payload, for `after-create` and `after-update` the written row, and for deletes the row
+     * that is being removed.
+     */
+    record?: string;
+    /**
+     * The authenticated user, if any.
+     */
+    user?: User;
+  }
+  export interface Abort {
+    /**
+     * HTTP status code returned to the client, e.g. 400.
+     */
+    status: number;
+    message: string;
+  }
+  export type Outcome = OutcomeProceed | OutcomeModify | OutcomeAbort;
+  /**
+   * Continue with the write as is.
+   */
+  export interface OutcomeProceed {
+    tag: "proceed";
+  }
+  /**
+   * Continue with the write after merging the given JSON-encoded object into the record's
+   * payload. Only valid for `before-create` and `before-update`.
+   */
+  export interface OutcomeModify {
+    tag: "modify";
+    val: string;
+  }
+  /**
+   * Abort the write and roll back the transaction.
+   */
+  export interface OutcomeAbort {
+    tag: "abort";
+    val: Abort;
+  }
+  export interface RecordHook {
+    /**
+     * Name of the Record API, i.e. `RecordApiConfig.name`, the hook is registered for.
+     */
+    apiName: string;
+    /**
+     * Events the hook should be called for.
+     */
+    events: Array<RecordHookEvent>;
+  }
+}
@@ -0,0 +1,52 @@
+declare module "trailbase:component/sqlite-aggregate-function-endpoint@0.1.2" {
+  export function initSqliteAggregateFunctions(
+    args: Arguments,
+  ): Array<SqliteAggregateFunction>;
+  /**
+   * Adds a row to the aggregation.
+   */
+  export function dispatchAggregateStep(args: AggregateArguments): void;
+  /**
+   * Returns the final result and releases the aggregation's state. May be called without any
+   * prior step, e.g. for empty tables.
+   */
+  export function dispatchAggregateFinal(context: AggregateContext): Value;
+  /**
+   * Returns the current value of a window aggregation.
+   */
+  export function dispatchAggregateValue(context: AggregateContext): Value;
+  /**
+   * Removes the oldest row from a window aggregation.
+   */
+  export function dispatchAggregateInverse(args: AggregateArguments): void;
+  export type Arguments =
+    import("trailbase:component/init-endpoint@0.1.2").Arguments;
+  export type SqliteFunctionFlags =
+    import("trailbase:component/init-endpoint@0.1.2").SqliteFunctionFlags;
+  export type Error =
+    import("trailbase:component/sqlite-function-endpoint@0.1.2").Error;
+  export type Value =
+    import("trailbase:component/sqlite-function-endpoint@0.1.2").Value;
+  export interface SqliteAggregateFunction {
+    name: string;
+    numArgs: number;
+    functionFlags: Array<SqliteFunctionFlags>;
+    /**
+     * Whether the aggregate also implements `value` and `inverse`, i.e. can be used as a window
+     * function.
+     */
+    window: boolean;
+  }
+  export interface AggregateContext {
+    functionName: string;
+    /**
+     * Identifies a single aggregation, e.g. one group of a `GROUP BY` query. Unique for the
+     * lifetime of the host. The guest is expected to track the aggregation's state by this id.
+     */
+    contextId: bigint;
+  }
+  export interface AggregateArguments {
+    context: AggregateContext;
+    arguments: Array<Value>;
+  }
+}
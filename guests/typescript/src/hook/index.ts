@@ -0,0 +1,155 @@
+import type {
+  RecordHookEvent,
+  Arguments,
+  Outcome,
+} from "trailbase:component/record-hook-endpoint@0.1.2";
+import type {
+  Error as AuthHookError,
+  User as AuthHookUser,
+} from "trailbase:component/auth-hook-endpoint@0.1.2";
+
+export type { RecordHookEvent } from "trailbase:component/record-hook-endpoint@0.1.2";
+
+export type JsonObject = { [key: string]: unknown };
+
+export type User = {
+  // Url-safe Base64 encoded id of the current user.
+  id: string;
+  // E-mail of the current user.
+  email: string;
+};
+
+export type RecordHookContext = {
+  // Name of the Record API.
+  apiName: string;
+  event: RecordHookEvent;
+  // Id of the record. Absent for "before-create".
+  recordId: string | undefined;
+  // For "before" create and update events the request payload, for "after" events the written
+  // row and for deletes the row being removed.
+  record: JsonObject | undefined;
+  // The authenticated user, if any.
+  user: User | undefined;
+};
+
+export type RecordHookResult =
+  // Continue with the write as is.
+  | { kind: "continue" }
+  // Continue after merging the given fields into the record's payload. Only valid for
+  // "before-create" and "before-update".
+  | { kind: "modify"; fields: JsonObject }
+  // Abort the write and roll back the transaction.
+  | { kind: "abort"; status: number; message: string };
+
+export type RecordHookType = (
+  ctx: RecordHookContext,
+) => RecordHookResult | void;
+
+export type RecordHookInterface = {
+  apiName: string;
+  events: RecordHookEvent[];
+  handler: RecordHookType;
+};
+
+export class RecordHook implements RecordHookInterface {
+  constructor(
+    public readonly apiName: string,
+    public readonly events: RecordHookEvent[],
+    public readonly handler: RecordHookType,
+  ) {}
+
+  static beforeCreate(apiName: string, handler: RecordHookType): RecordHook {
+    return new RecordHook(apiName, ["before-create"], handler);
+  }
+
+  static beforeUpdate(apiName: string, handler: RecordHookType): RecordHook {
+    return new RecordHook(apiName, ["before-update"], handler);
+  }
+
+  static beforeDelete(apiName: string, handler: RecordHookType): RecordHook {
+    return new RecordHook(apiName, ["before-delete"], handler);
+  }
+}
+
+export function continueWrite(): RecordHookResult {
+  return { kind: "continue" };
+}
+
+export function modifyRecord(fields: JsonObject): RecordHookResult {
+  return { kind: "modify", fields };
+}
+
+export function abortWrite(status: number, message: string): RecordHookResult {
+  return { kind: "abort", status, message };
+}
+
+export function buildRecordHookDispatcher(
+  hooks: RecordHookInterface[],
+): (args: Arguments) => Outcome {
+  return function (args: Arguments): Outcome {
+    const ctx: RecordHookContext = {
+      apiName: args.apiName,
+      event: args.event,
+      recordId: args.recordId,
+      record:
+        args.record !== undefined
+          ? (JSON.parse(args.record) as JsonObject)
+          : undefined,
+      user: args.user,
+    };
+
+    let modified: JsonObject | undefined;
+    for (const hook of hooks) {
+      if (hook.apiName !== args.apiName || !hook.events.includes(args.event)) {
+        continue;
+      }
+
+      const result = hook.handler(ctx) ?? { kind: "continue" };
+      switch (result.kind) {
+        case "continue":
+          break;
+        case "modify":
+          modified = { ...(modified ?? {}), ...result.fields };
+          ctx.record = { ...(ctx.record ?? {}), ...result.fields };
+          break;
+        case "abort":
+          return {
+            tag: "abort",
+            val: { status: result.status, message: result.message },
+          };
+      }
+    }
+
+    if (modified !== undefined) {
+      return { tag: "modify", val: JSON.stringify(modified) };
+    }
+    return { tag: "proceed" };
+  };
+}
+
+export type AuthUser = {
+  // Url-safe Base64 encoded id of the user.
+  id: string;
+  // E-mail of the user.
+  email: string;
+  admin: boolean;
+  // Names of the roles assigned to the user.
+  roles: string[];
+};
+
+// Returns custom claims, e.g. a tenant id, to be added to the user's auth tokens.
+export type CustomClaimsHook = (user: AuthUser) => JsonObject;
+
+export function buildCustomClaimsDispatcher(
+  hook: CustomClaimsHook | undefined,
+): (user: AuthHookUser) => string {
+  return function (user: AuthHookUser): string {
+    if (hook === undefined) {
+      throw {
+        tag: "other",
+        val: "no custom claims hook registered",
+      } as AuthHookError;
+    }
+    return JSON.stringify(hook(user));
+  };
+}
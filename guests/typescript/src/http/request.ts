@@ -3,7 +3,7 @@ import {
   IncomingBody,
   Scheme as WasiScheme,
 } from "wasi:http/types@0.2.3";
-import type { HttpMethodType } from "trailbase:component/init-endpoint@0.1.1";
+import type { HttpMethodType } from "trailbase:component/init-endpoint@0.1.2";
 import type { HttpContextUser } from "@common/HttpContextUser";
 
 export type Scheme = "HTTP" | "HTTPS" | "other";
@@ -4,15 +4,34 @@ import type {
   HttpHandlers,
   JobHandlers,
   SqliteFunctions,
-} from "trailbase:component/init-endpoint@0.1.1";
+} from "trailbase:component/init-endpoint@0.1.2";
+import type {
+  RecordHook,
+  dispatchRecordHook,
+} from "trailbase:component/record-hook-endpoint@0.1.2";
+import type {
+  AuthHooks,
+  customClaims,
+} from "trailbase:component/auth-hook-endpoint@0.1.2";
 import type {
   Arguments as SqliteArguments,
   Error as SqliteError,
   dispatchScalarFunction,
-} from "trailbase:component/sqlite-function-endpoint@0.1.1";
+} from "trailbase:component/sqlite-function-endpoint@0.1.2";
+import type {
+  SqliteAggregateFunction,
+  dispatchAggregateFinal,
+  dispatchAggregateInverse,
+  dispatchAggregateStep,
+  dispatchAggregateValue,
+} from "trailbase:component/sqlite-aggregate-function-endpoint@0.1.2";
 import type { HttpHandlerInterface } from "./http";
 import type { JobHandlerInterface } from "./job";
+import type { CustomClaimsHook, RecordHookInterface } from "./hook";
+import { buildCustomClaimsDispatcher, buildRecordHookDispatcher } from "./hook";
 import { buildIncomingHttpHandler } from "./http/incoming";
+import type { SqliteAggregateFunctionInterface } from "./sqlite";
+import { buildSqliteAggregateDispatcher } from "./sqlite";
 
 export { addPeriodicCallback } from "./timer";
 
@@ -33,6 +52,23 @@ export interface Config {
   sqliteFunctionEndpoint: {
     dispatchScalarFunction: typeof dispatchScalarFunction;
   };
+  sqliteAggregateFunctionEndpoint: {
+    initSqliteAggregateFunctions: (
+      args: Arguments,
+    ) => Array<SqliteAggregateFunction>;
+    dispatchAggregateStep: typeof dispatchAggregateStep;
+    dispatchAggregateFinal: typeof dispatchAggregateFinal;
+    dispatchAggregateValue: typeof dispatchAggregateValue;
+    dispatchAggregateInverse: typeof dispatchAggregateInverse;
+  };
+  recordHookEndpoint: {
+    initRecordHooks: (args: Arguments) => Array<RecordHook>;
+    dispatchRecordHook: typeof dispatchRecordHook;
+  };
+  authHookEndpoint: {
+    initAuthHooks: (args: Arguments) => AuthHooks;
+    customClaims: typeof customClaims;
+  };
 }
 
 export interface InitArgs {
@@ -43,6 +79,9 @@ export function defineConfig(opts: {
   init?: (args: InitArgs) => void;
   httpHandlers?: HttpHandlerInterface[];
   jobHandlers?: JobHandlerInterface[];
+  sqliteAggregateFunctions?: SqliteAggregateFunctionInterface[];
+  recordHooks?: RecordHookInterface[];
+  customClaims?: CustomClaimsHook;
 }): Config {
   return {
     incomingHandler: {
@@ -85,5 +124,47 @@ export function defineConfig(opts: {
         } as SqliteError;
       },
     },
+    sqliteAggregateFunctionEndpoint: {
+      initSqliteAggregateFunctions: function (
+        args: Arguments,
+      ): Array<SqliteAggregateFunction> {
+        opts.init?.({
+          version: args.version,
+        });
+
+        return (opts.sqliteAggregateFunctions ?? []).map((f) => ({
+          name: f.name,
+          numArgs: f.numArgs,
+          functionFlags: f.flags,
+          window: f.window,
+        }));
+      },
+      ...buildSqliteAggregateDispatcher(opts.sqliteAggregateFunctions ?? []),
+    },
+    recordHookEndpoint: {
+      initRecordHooks: function (args: Arguments): Array<RecordHook> {
+        opts.init?.({
+          version: args.version,
+        });
+
+        return (opts.recordHooks ?? []).map((h) => ({
+          apiName: h.apiName,
+          events: h.events,
+        }));
+      },
+      dispatchRecordHook: buildRecordHookDispatcher(opts.recordHooks ?? []),
+    },
+    authHookEndpoint: {
+      initAuthHooks: function (args: Arguments): AuthHooks {
+        opts.init?.({
+          version: args.version,
+        });
+
+        return {
+          customClaims: opts.customClaims !== undefined,
+        };
+      },
+      customClaims: buildCustomClaimsDispatcher(opts.customClaims),
+    },
   };
 }
@@ -0,0 +1,141 @@
+import type {
+  SqliteFunctionFlags,
+} from "trailbase:component/init-endpoint@0.1.2";
+import type {
+  Error as SqliteError,
+  Value as WitValue,
+} from "trailbase:component/sqlite-function-endpoint@0.1.2";
+import type {
+  AggregateArguments,
+  AggregateContext,
+} from "trailbase:component/sqlite-aggregate-function-endpoint@0.1.2";
+
+import type { Value } from "../db/value";
+import { fromWitValue, toWitValue } from "../db/value";
+
+export type {
+  SqliteFunctionFlags,
+} from "trailbase:component/init-endpoint@0.1.2";
+export type { Value } from "../db/value";
+
+/// State of a single aggregation, e.g. one group of a `GROUP BY` query.
+export interface SqliteAggregate {
+  /// Adds a row to the aggregation.
+  step(args: Value[]): void;
+  /// Returns the final result.
+  finalize(): Value;
+  /// Returns the current result. Only required for window functions.
+  value?(): Value;
+  /// Removes the oldest row from the aggregation. Only required for window functions.
+  inverse?(args: Value[]): void;
+}
+
+export type SqliteAggregateFunctionInterface = {
+  name: string;
+  numArgs: number;
+  flags: SqliteFunctionFlags[];
+  window: boolean;
+  // Creates a fresh state per aggregation.
+  builder: () => SqliteAggregate;
+};
+
+export class SqliteAggregateFunction
+  implements SqliteAggregateFunctionInterface
+{
+  constructor(
+    public readonly name: string,
+    public readonly numArgs: number,
+    public readonly builder: () => SqliteAggregate,
+    public readonly flags: SqliteFunctionFlags[] = [],
+    public readonly window: boolean = false,
+  ) {}
+
+  /// Same as the constructor but also registers the aggregate's `value` and `inverse` to be
+  /// usable as a window function.
+  static window(
+    name: string,
+    numArgs: number,
+    builder: () => SqliteAggregate,
+    flags: SqliteFunctionFlags[] = [],
+  ): SqliteAggregateFunction {
+    return new SqliteAggregateFunction(name, numArgs, builder, flags, true);
+  }
+}
+
+export type SqliteAggregateDispatcher = {
+  dispatchAggregateStep: (args: AggregateArguments) => void;
+  dispatchAggregateFinal: (context: AggregateContext) => WitValue;
+  dispatchAggregateValue: (context: AggregateContext) => WitValue;
+  dispatchAggregateInverse: (args: AggregateArguments) => void;
+};
+
+export function buildSqliteAggregateDispatcher(
+  functions: SqliteAggregateFunctionInterface[],
+): SqliteAggregateDispatcher {
+  // In-flight aggregations by context id.
+  const aggregates = new Map<bigint, SqliteAggregate>();
+
+  const build = (context: AggregateContext): SqliteAggregate => {
+    const f = functions.find((f) => f.name === context.functionName);
+    if (f === undefined) {
+      throw sqliteError("Missing function");
+    }
+    return f.builder();
+  };
+
+  // Returns the aggregation's state, creating it on first use.
+  const get = (context: AggregateContext): SqliteAggregate => {
+    let aggregate = aggregates.get(context.contextId);
+    if (aggregate === undefined) {
+      aggregate = build(context);
+      aggregates.set(context.contextId, aggregate);
+    }
+    return aggregate;
+  };
+
+  return {
+    dispatchAggregateStep: function (args: AggregateArguments) {
+      const aggregate = get(args.context);
+      call(() => aggregate.step(args.arguments.map(fromWitValue)));
+    },
+    dispatchAggregateFinal: function (context: AggregateContext): WitValue {
+      // No prior step, e.g. for empty tables, if absent.
+      const aggregate = aggregates.get(context.contextId) ?? build(context);
+      aggregates.delete(context.contextId);
+
+      return call(() => toWitValue(aggregate.finalize()));
+    },
+    dispatchAggregateValue: function (context: AggregateContext): WitValue {
+      const aggregate = get(context);
+      const value = aggregate.value?.bind(aggregate);
+      if (value === undefined) {
+        throw sqliteError("not a window function");
+      }
+      return call(() => toWitValue(value()));
+    },
+    dispatchAggregateInverse: function (args: AggregateArguments) {
+      const aggregate = get(args.context);
+      const inverse = aggregate.inverse?.bind(aggregate);
+      if (inverse === undefined) {
+        throw sqliteError("not a window function");
+      }
+      call(() => inverse(args.arguments.map(fromWitValue)));
+    },
+  };
+}
+
+/// Maps exceptions thrown by user code onto the WIT error.
+function call<T>(f: () => T): T {
+  try {
+    return f();
+  } catch (err) {
+    throw sqliteError(`${err}`);
+  }
+}
+
+function sqliteError(message: string): SqliteError {
+  return {
+    tag: "other",
+    val: message,
+  };
+}
@@ -6,6 +6,11 @@ import {
   toJsonSqlValue,
   toWitValue,
 } from "../src/db/value";
+import type { Value } from "../src/db/value";
+import {
+  SqliteAggregateFunction,
+  buildSqliteAggregateDispatcher,
+} from "../src/sqlite";
 import { urlSafeBase64Encode, urlSafeBase64Decode } from "../src/util";
 
 test("base64", ({ expect }) => {
@@ -60,3 +65,59 @@ test("SQL escape", ({ expect }) => {
   expect(escape("foo'")).toEqual("'foo'''");
   expect(escape("foo\0")).toEqual("'foo\\0'");
 });
+
+test("SQLite aggregate dispatch", ({ expect }) => {
+  class Sum {
+    sum = 0n;
+    step(args: Value[]) {
+      this.sum += args[0] as bigint;
+    }
+    finalize(): Value {
+      return this.sum;
+    }
+    value(): Value {
+      return this.sum;
+    }
+    inverse(args: Value[]) {
+      this.sum -= args[0] as bigint;
+    }
+  }
+
+  const dispatcher = buildSqliteAggregateDispatcher([
+    SqliteAggregateFunction.window("sum", 1, () => new Sum()),
+  ]);
+  const context = (contextId: bigint) => ({ functionName: "sum", contextId });
+  const integer = (val: bigint) => ({ tag: "integer" as const, val });
+
+  // Interleaved aggregations are independent.
+  dispatcher.dispatchAggregateStep({
+    context: context(1n),
+    arguments: [integer(2n)],
+  });
+  dispatcher.dispatchAggregateStep({
+    context: context(2n),
+    arguments: [integer(5n)],
+  });
+  dispatcher.dispatchAggregateStep({
+    context: context(1n),
+    arguments: [integer(3n)],
+  });
+  expect(dispatcher.dispatchAggregateValue(context(1n))).toEqual(integer(5n));
+
+  dispatcher.dispatchAggregateInverse({
+    context: context(1n),
+    arguments: [integer(2n)],
+  });
+  expect(dispatcher.dispatchAggregateFinal(context(1n))).toEqual(integer(3n));
+  expect(dispatcher.dispatchAggregateFinal(context(2n))).toEqual(integer(5n));
+
+  // State is released on final, i.e. a reused id starts fresh.
+  expect(dispatcher.dispatchAggregateFinal(context(1n))).toEqual(integer(0n));
+
+  expect(() =>
+    dispatcher.dispatchAggregateFinal({
+      functionName: "missing",
+      contextId: 3n,
+    }),
+  ).toThrow();
+});
@@ -6,9 +6,11 @@ const entryPoints = {
   "index": resolve(__dirname, 'src/index.ts'),
   "db": resolve(__dirname, 'src/db/index.ts'),
   "fs": resolve(__dirname, 'src/fs/index.ts'),
+  "hook": resolve(__dirname, 'src/hook/index.ts'),
   "http": resolve(__dirname, 'src/http/index.ts'),
   "job": resolve(__dirname, 'src/job/index.ts'),
   "kv": resolve(__dirname, 'src/kv/index.ts'),
+  "sqlite": resolve(__dirname, 'src/sqlite/index.ts'),
 };
 
 export default defineConfig({